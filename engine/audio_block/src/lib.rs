@@ -0,0 +1,146 @@
+//! The processing contract shared by all of the synth and effect modules.  Audio is processed in
+//! fixed-size blocks of planar stereo samples, which is the render quantum of Web Audio's
+//! `AudioWorkletProcessor`; the graph executor, the offline renderer, the meters, and the worklets
+//! themselves all drive modules through the `BlockProcessor` trait.
+//!
+//! This crate has no dependencies so that the DSP crates that are loaded directly into
+//! AudioWorklets without any JS glue can use it.
+
+use std::ops::{Deref, DerefMut};
+
+/// Number of frames in each block, matching `FRAME_SIZE` in the AudioWorklet processors
+pub const BLOCK_SIZE: usize = 128;
+
+/// A block of stereo audio with the left channel's samples followed by the right channel's.  This
+/// layout is relied on by the AudioWorklet processors, which read and write blocks directly out of
+/// Wasm memory.
+#[derive(Clone, PartialEq)]
+#[repr(C)]
+pub struct Block {
+    pub left: [f32; BLOCK_SIZE],
+    pub right: [f32; BLOCK_SIZE],
+}
+
+impl Default for Block {
+    fn default() -> Self {
+        Block {
+            left: [0.; BLOCK_SIZE],
+            right: [0.; BLOCK_SIZE],
+        }
+    }
+}
+
+impl std::fmt::Debug for Block {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Block")
+            .field("left", &&self.left[..])
+            .field("right", &&self.right[..])
+            .finish()
+    }
+}
+
+impl Block {
+    /// Builds a block with both channels set to `val`
+    pub fn filled(val: f32) -> Self {
+        Block {
+            left: [val; BLOCK_SIZE],
+            right: [val; BLOCK_SIZE],
+        }
+    }
+
+    pub fn channels_mut(&mut self) -> (&mut [f32; BLOCK_SIZE], &mut [f32; BLOCK_SIZE]) {
+        (&mut self.left, &mut self.right)
+    }
+
+    pub fn clear(&mut self) { self.clear_range(0, BLOCK_SIZE); }
+
+    /// Silences frames `[start, end)` of both channels
+    pub fn clear_range(&mut self, start: usize, end: usize) {
+        let end = end.min(BLOCK_SIZE);
+        if start >= end {
+            return;
+        }
+        for sample in self.left[start..end].iter_mut() {
+            *sample = 0.;
+        }
+        for sample in self.right[start..end].iter_mut() {
+            *sample = 0.;
+        }
+    }
+
+    /// Sums `other` into this block
+    pub fn mix(&mut self, other: &Block) {
+        for (dst, src) in self.left.iter_mut().zip(other.left.iter()) {
+            *dst += src;
+        }
+        for (dst, src) in self.right.iter_mut().zip(other.right.iter()) {
+            *dst += src;
+        }
+    }
+
+    pub fn apply_gain(&mut self, gain: f32) {
+        for sample in self.left.iter_mut().chain(self.right.iter_mut()) {
+            *sample *= gain;
+        }
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut f32 { self.left.as_mut_ptr() }
+}
+
+/// Anything that processes audio a block at a time.  Instruments overwrite the block with their
+/// output, effects transform it in place, and analyzers read it and leave it untouched.
+pub trait BlockProcessor {
+    fn process(&mut self, block: &mut Block);
+}
+
+impl<P: BlockProcessor + ?Sized> BlockProcessor for Box<P> {
+    fn process(&mut self, block: &mut Block) { (**self).process(block) }
+}
+
+/// A processor that plays notes on the voices handed out by the polysynth voice manager.  Notes
+/// can start and stop partway through a block, so instruments render ranges of frames between
+/// them; `process` renders the whole block.
+pub trait Instrument: BlockProcessor {
+    fn trigger_attack(&mut self, voice_ix: usize, note_id: usize, velocity: u8);
+
+    /// Releases the voice if it's still playing `note_id`
+    fn trigger_release(&mut self, voice_ix: usize, note_id: usize);
+
+    /// Overwrites frames `[start, end)` of the block with the instrument's output
+    fn render(&mut self, block: &mut Block, start: usize, end: usize);
+}
+
+/// A processor paired with the block that an AudioWorklet writes its input into and reads its
+/// output out of.  Pointers to these are what the DSP crates hand out to their worklets, and it
+/// dereferences to the processor so that exports can call into it directly.
+pub struct WorkletHandle<P> {
+    pub processor: P,
+    pub io_buffer: Block,
+}
+
+impl<P> WorkletHandle<P> {
+    pub fn new(processor: P) -> Self {
+        WorkletHandle {
+            processor,
+            io_buffer: Block::default(),
+        }
+    }
+
+    /// Returns a pointer to the IO buffer, which holds `BLOCK_SIZE * 2` samples
+    pub fn io_buffer_ptr(&mut self) -> *mut f32 { self.io_buffer.as_mut_ptr() }
+}
+
+impl<P: BlockProcessor> WorkletHandle<P> {
+    /// Runs the processor over the IO buffer in place
+    pub fn process(&mut self) { self.processor.process(&mut self.io_buffer) }
+}
+
+impl<P> Deref for WorkletHandle<P> {
+    type Target = P;
+
+    fn deref(&self) -> &P { &self.processor }
+}
+
+impl<P> DerefMut for WorkletHandle<P> {
+    fn deref_mut(&mut self) -> &mut P { &mut self.processor }
+}
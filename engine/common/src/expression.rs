@@ -0,0 +1,116 @@
+//! Per-note expression recorded from MPE (MIDI Polyphonic Expression) controllers.  Along with its
+//! pitch bend curve, each note can carry curves of the pressure and timbre that it was played with,
+//! which are replayed on the voice playing the note.
+
+/// A dimension of per-note expression.  Pitch bends are in semitones while pressure and timbre
+/// are in the range [0, 1].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExpressionDimension {
+    PitchBend = 0,
+    Pressure = 1,
+    Timbre = 2,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpressionPoint {
+    /// Offset in beats from the start of the note
+    pub beat_offset: f32,
+    pub value: f32,
+}
+
+/// Each point of a curve holds its value until the next one.  Curves are sorted by offset.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct NoteExpression {
+    #[serde(default)]
+    pub pressure: Vec<ExpressionPoint>,
+    #[serde(default)]
+    pub timbre: Vec<ExpressionPoint>,
+}
+
+/// Splits `curve` at `offset`, returning the points after it with offsets relative to it.  The
+/// value held at `offset` is carried over to the start of the returned curve.
+fn split_curve(curve: &mut Vec<ExpressionPoint>, offset: f32) -> Vec<ExpressionPoint> {
+    let split_ix = curve
+        .iter()
+        .position(|point| point.beat_offset > offset)
+        .unwrap_or(curve.len());
+    let mut second_half: Vec<ExpressionPoint> = Vec::new();
+    if split_ix > 0 {
+        second_half.push(ExpressionPoint {
+            beat_offset: 0.,
+            value: curve[split_ix - 1].value,
+        });
+    }
+    second_half.extend(curve.drain(split_ix..).map(|point| ExpressionPoint {
+        beat_offset: point.beat_offset - offset,
+        ..point
+    }));
+    second_half
+}
+
+/// Appends the points of `other` to `curve`, offsetting them by `offset`
+fn join_curve(curve: &mut Vec<ExpressionPoint>, other: &[ExpressionPoint], offset: f32) {
+    curve.extend(other.iter().map(|point| ExpressionPoint {
+        beat_offset: point.beat_offset + offset,
+        ..*point
+    }));
+}
+
+impl NoteExpression {
+    pub fn is_empty(&self) -> bool { self.pressure.is_empty() && self.timbre.is_empty() }
+
+    /// Returns the curve of `dimension`, or `None` for pitch bends since they're stored separately
+    pub fn curve_mut(
+        &mut self,
+        dimension: ExpressionDimension,
+    ) -> Option<&mut Vec<ExpressionPoint>> {
+        match dimension {
+            ExpressionDimension::PitchBend => None,
+            ExpressionDimension::Pressure => Some(&mut self.pressure),
+            ExpressionDimension::Timbre => Some(&mut self.timbre),
+        }
+    }
+
+    /// Sorts the points of the curves by offset and drops any that are outside of a note `width`
+    /// beats long, since they would never be played.
+    pub fn normalize(&mut self, width: f32) {
+        for curve in &mut [&mut self.pressure, &mut self.timbre] {
+            curve.retain(|point| point.beat_offset >= 0. && point.beat_offset <= width);
+            curve.sort_by(|a, b| a.beat_offset.partial_cmp(&b.beat_offset).unwrap());
+        }
+    }
+
+    /// Splits the curves at `offset` beats into the note, returning the expression of the part
+    /// after it with offsets relative to the split
+    pub fn split_off(&mut self, offset: f32) -> NoteExpression {
+        NoteExpression {
+            pressure: split_curve(&mut self.pressure, offset),
+            timbre: split_curve(&mut self.timbre, offset),
+        }
+    }
+
+    /// Appends the curves of `other`, a note starting `offset` beats into this one.  This is the
+    /// inverse of `split_off`.
+    pub fn join(&mut self, other: &NoteExpression, offset: f32) {
+        join_curve(&mut self.pressure, &other.pressure, offset);
+        join_curve(&mut self.timbre, &other.timbre, offset);
+    }
+
+    /// Returns `(beat_offset, dimension, value)` for every point of the curves, sorted by offset
+    pub fn events(&self) -> Vec<(f32, ExpressionDimension, f32)> {
+        let mut events: Vec<(f32, ExpressionDimension, f32)> = self
+            .pressure
+            .iter()
+            .map(|point| (point.beat_offset, ExpressionDimension::Pressure, point.value))
+            .chain(
+                self.timbre
+                    .iter()
+                    .map(|point| (point.beat_offset, ExpressionDimension::Timbre, point.value)),
+            )
+            .collect();
+        events.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        events
+    }
+}
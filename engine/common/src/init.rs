@@ -4,7 +4,7 @@ use rand::prelude::*;
 use rand_pcg::Pcg32;
 use wasm_bindgen::prelude::*;
 
-use super::{rng, RNG};
+use super::{DEFAULT_RNG_SEED, RNG, RNG_STREAM};
 
 static ONCE: Once = Once::new();
 
@@ -16,23 +16,21 @@ extern "C" {
 
 /// Initialize our PRNG with real(er) RNG from the browser
 pub fn init_rng() {
-    // Initialize the global PRNG
-    unsafe {
-        // slightly customized versions of the default seeds for the PCG32 PRNG, but seeded with
-        // some actual RNG from JS so that things aren't deterministic.
-        RNG = Box::into_raw(box Pcg32::new(
-            if cfg!(target_os = "wasm32") {
-                mem::transmute(random())
-            } else {
-                0xcafef00dd15ea5e5
-            },
-            721_347_520_420_481_703,
-        ))
-    }
-
-    // Pump it a few times because it seems to generate a fully null output the first time
-    let _: usize = rng().gen();
-    let _: usize = rng().gen();
+    // slightly customized versions of the default seeds for the PCG32 PRNG, but seeded with
+    // some actual RNG from JS so that things aren't deterministic.
+    let seed = if cfg!(target_os = "wasm32") {
+        unsafe { mem::transmute(random()) }
+    } else {
+        DEFAULT_RNG_SEED
+    };
+    RNG.with(|rng| {
+        let mut rng = rng.borrow_mut();
+        *rng = Pcg32::new(seed, RNG_STREAM);
+
+        // Pump it a few times because it seems to generate a fully null output the first time
+        let _: usize = rng.gen();
+        let _: usize = rng.gen();
+    });
 }
 
 #[cfg(debug_assertions)]
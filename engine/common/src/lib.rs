@@ -1,6 +1,4 @@
-#![feature(box_syntax, thread_local)]
-
-use std::{mem, ptr};
+use std::{cell::RefCell, mem};
 
 #[macro_use]
 extern crate serde_derive;
@@ -9,23 +7,130 @@ use rand::prelude::*;
 use rand_pcg::Pcg32;
 use uuid::Uuid;
 
+pub mod expression;
 mod init;
+pub mod pitch_bend;
+pub mod tempo_map;
+pub mod theory;
+pub mod tuning;
 
 pub use crate::init::*;
+use crate::{expression::NoteExpression, pitch_bend::PitchBendPoint};
+
+/// Velocity given to notes that are created without an explicit one
+pub const DEFAULT_NOTE_VELOCITY: u8 = 100;
+pub const MIN_NOTE_VELOCITY: u8 = 1;
+pub const MAX_NOTE_VELOCITY: u8 = 127;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct RawNoteData {
     pub line_ix: usize,
     pub start_beat: f32,
     pub width: f32,
+    pub velocity: u8,
+    #[serde(default)]
+    pub pitch_bend: Vec<PitchBendPoint>,
+    #[serde(default)]
+    pub expression: NoteExpression,
 }
 
-#[thread_local]
-pub static mut RNG: *mut Pcg32 = ptr::null_mut();
+/// The format that `RawNoteData` was serialized in before notes had expression curves
+#[derive(Deserialize)]
+struct PitchBendRawNoteData {
+    pub line_ix: usize,
+    pub start_beat: f32,
+    pub width: f32,
+    pub velocity: u8,
+    pub pitch_bend: Vec<PitchBendPoint>,
+}
 
-pub fn rng() -> &'static mut Pcg32 { unsafe { &mut *RNG } }
+/// The format that `RawNoteData` was serialized in before notes had pitch bend curves
+#[derive(Deserialize)]
+struct VelocityRawNoteData {
+    pub line_ix: usize,
+    pub start_beat: f32,
+    pub width: f32,
+    pub velocity: u8,
+}
+
+/// The format that `RawNoteData` was serialized in before notes had velocities
+#[derive(Deserialize)]
+struct LegacyRawNoteData {
+    pub line_ix: usize,
+    pub start_beat: f32,
+    pub width: f32,
+}
+
+/// Deserializes a bincode-encoded `Vec<RawNoteData>`, falling back to the formats used before
+/// velocities, pitch bend curves, and expression curves were added so that previously saved
+/// compositions can still be loaded.
+pub fn deserialize_raw_note_data(bytes: &[u8]) -> Result<Vec<RawNoteData>, bincode::Error> {
+    let err = match bincode::deserialize::<Vec<RawNoteData>>(bytes) {
+        Ok(notes) => return Ok(notes),
+        Err(err) => err,
+    };
+
+    if let Ok(pitch_bend_notes) = bincode::deserialize::<Vec<PitchBendRawNoteData>>(bytes) {
+        return Ok(pitch_bend_notes
+            .into_iter()
+            .map(|note| RawNoteData {
+                line_ix: note.line_ix,
+                start_beat: note.start_beat,
+                width: note.width,
+                velocity: note.velocity,
+                pitch_bend: note.pitch_bend,
+                expression: NoteExpression::default(),
+            })
+            .collect());
+    }
+
+    if let Ok(velocity_notes) = bincode::deserialize::<Vec<VelocityRawNoteData>>(bytes) {
+        return Ok(velocity_notes
+            .into_iter()
+            .map(|note| RawNoteData {
+                line_ix: note.line_ix,
+                start_beat: note.start_beat,
+                width: note.width,
+                velocity: note.velocity,
+                pitch_bend: Vec::new(),
+                expression: NoteExpression::default(),
+            })
+            .collect());
+    }
+
+    match bincode::deserialize::<Vec<LegacyRawNoteData>>(bytes) {
+        Ok(legacy_notes) => Ok(legacy_notes
+            .into_iter()
+            .map(|note| RawNoteData {
+                line_ix: note.line_ix,
+                start_beat: note.start_beat,
+                width: note.width,
+                velocity: DEFAULT_NOTE_VELOCITY,
+                pitch_bend: Vec::new(),
+                expression: NoteExpression::default(),
+            })
+            .collect()),
+        Err(_) => Err(err),
+    }
+}
+
+/// Seed used for the PRNG until it's reseeded with entropy from the browser
+pub const DEFAULT_RNG_SEED: u64 = 0xcafe_f00d_d15e_a5e5;
+pub const RNG_STREAM: u64 = 721_347_520_420_481_703;
+
+thread_local! {
+    /// The PRNG used for everything that needs randomness.  It's seeded with the defaults until
+    /// `init_rng` reseeds it.
+    static RNG: RefCell<Pcg32> = RefCell::new(Pcg32::new(DEFAULT_RNG_SEED, RNG_STREAM));
+}
+
+/// Calls `f` with the global PRNG.  It's only borrowed for the duration of `f`, so `f` must not
+/// use the PRNG through `with_rng` again.
+pub fn with_rng<T>(f: impl FnOnce(&mut Pcg32) -> T) -> T {
+    RNG.with(|rng| f(&mut rng.borrow_mut()))
+}
 
 pub fn uuid_v4() -> Uuid {
-    let entropy: (u64, i64) = rng().gen();
+    let entropy: (u64, i64) = with_rng(|rng| rng.gen());
     unsafe { mem::transmute(entropy) }
 }
@@ -0,0 +1,88 @@
+//! Per-note pitch bend curves.  Each note can carry a curve of points describing how far its pitch
+//! is bent over its length, which lets individual notes glide or vibrate without affecting the
+//! other notes that are playing at the same time.
+
+/// The number of semitones represented by a fully extended MIDI pitch bend.  This is the default
+/// range used by most synthesizers.
+pub const MIDI_PITCH_BEND_RANGE_SEMITONES: f32 = 2.;
+/// The MIDI pitch bend value that corresponds to no bend
+pub const MIDI_PITCH_BEND_CENTER: u16 = 8192;
+pub const MAX_MIDI_PITCH_BEND: u16 = 16383;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PitchBendPoint {
+    /// Offset in beats from the start of the note
+    pub beat_offset: f32,
+    pub semitones: f32,
+}
+
+/// Returns the bend in semitones at `beat_offset` beats from the start of a note.  The bend starts
+/// at zero at the start of the note, is linearly interpolated between points, and holds the value
+/// of the last point after it.  `curve` must be sorted by `beat_offset`.
+pub fn pitch_bend_at(curve: &[PitchBendPoint], beat_offset: f32) -> f32 {
+    let mut prev = PitchBendPoint {
+        beat_offset: 0.,
+        semitones: 0.,
+    };
+    for point in curve {
+        if point.beat_offset >= beat_offset {
+            let span = point.beat_offset - prev.beat_offset;
+            if span <= 0. {
+                return point.semitones;
+            }
+            let progress = (beat_offset - prev.beat_offset).max(0.) / span;
+            return prev.semitones + (point.semitones - prev.semitones) * progress;
+        }
+        prev = *point;
+    }
+    prev.semitones
+}
+
+/// Sorts the points of `curve` by offset and drops any that are outside of a note `width` beats
+/// long, since they would never be played.
+pub fn normalize_curve(curve: &mut Vec<PitchBendPoint>, width: f32) {
+    curve.retain(|point| point.beat_offset >= 0. && point.beat_offset <= width);
+    curve.sort_by(|a, b| a.beat_offset.partial_cmp(&b.beat_offset).unwrap());
+}
+
+/// Converts a bend in semitones into a 14-bit MIDI pitch bend value, clamping it to the range
+/// that MIDI pitch bends can represent.
+pub fn semitones_to_midi_pitch_bend(semitones: f32) -> u16 {
+    let normalized = (semitones / MIDI_PITCH_BEND_RANGE_SEMITONES).clamp(-1., 1.);
+    let value = MIDI_PITCH_BEND_CENTER as f32 + normalized * MIDI_PITCH_BEND_CENTER as f32;
+    (value.round() as u16).min(MAX_MIDI_PITCH_BEND)
+}
+
+/// Samples `curve` every `interval` beats through the end of a note `width` beats long, returning
+/// `(beat_offset, semitones)` pairs.  The points of the curve itself are always included so that
+/// peaks aren't smoothed over.  Used to turn curves into discrete events for MIDI, which has no
+/// concept of ramping between values.
+pub fn sample_curve(curve: &[PitchBendPoint], width: f32, interval: f32) -> Vec<(f32, f32)> {
+    let last_offset = match curve.last() {
+        Some(point) => point.beat_offset.min(width),
+        None => return Vec::new(),
+    };
+
+    let mut offsets: Vec<f32> = Vec::new();
+    if interval > 0. {
+        let mut offset = 0.;
+        while offset < last_offset {
+            offsets.push(offset);
+            offset += interval;
+        }
+    }
+    offsets.extend(
+        curve
+            .iter()
+            .map(|point| point.beat_offset)
+            .filter(|&offset| offset <= width),
+    );
+    offsets.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    offsets.dedup();
+
+    offsets
+        .into_iter()
+        .map(|offset| (offset, pitch_bend_at(curve, offset)))
+        .collect()
+}
@@ -0,0 +1,317 @@
+//! Describes how the tempo and time signature of a composition change over its length.  Beats are
+//! always quarter notes, and all conversions between beats and seconds go through the tempo map so
+//! that playback, recording, MIDI export, and the grid all agree on where things are.
+
+pub const DEFAULT_BPM: f64 = 120.;
+pub const MIN_BPM: f64 = 1.;
+pub const MAX_BPM: f64 = 999.;
+
+/// Change points closer together than this are considered to be at the same beat
+const BEAT_EPSILON: f64 = 0.0001;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TimeSignature {
+    pub numerator: u8,
+    /// Must be a power of two
+    pub denominator: u8,
+}
+
+impl Default for TimeSignature {
+    fn default() -> Self {
+        TimeSignature {
+            numerator: 4,
+            denominator: 4,
+        }
+    }
+}
+
+impl TimeSignature {
+    pub fn new(numerator: u8, denominator: u8) -> Option<Self> {
+        if numerator == 0 || denominator == 0 || !denominator.is_power_of_two() || denominator > 64
+        {
+            return None;
+        }
+
+        Some(TimeSignature {
+            numerator,
+            denominator,
+        })
+    }
+
+    /// The length of one of the time signature's beats in quarter notes
+    pub fn beat_unit(&self) -> f64 { 4. / self.denominator as f64 }
+
+    /// The length of a measure in quarter notes
+    pub fn beats_per_measure(&self) -> f64 { self.numerator as f64 * self.beat_unit() }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TempoChange {
+    pub beat: f64,
+    pub bpm: f64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeSignatureChange {
+    pub beat: f64,
+    pub time_signature: TimeSignature,
+}
+
+/// A line to draw on a grid at the start of one of the time signature's beats
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GridLine {
+    pub beat: f64,
+    pub is_measure_start: bool,
+}
+
+/// Returns lines for every beat of every measure before `end_beat`.  A time signature change
+/// always starts a new measure, cutting short the measure before it if it doesn't line up.
+pub fn compute_grid_lines(
+    time_signature_changes: &[TimeSignatureChange],
+    end_beat: f64,
+) -> Vec<GridLine> {
+    let mut lines = Vec::new();
+    for (i, change) in time_signature_changes.iter().enumerate() {
+        let segment_end_beat = time_signature_changes
+            .get(i + 1)
+            .map(|next_change| next_change.beat)
+            .unwrap_or(end_beat)
+            .min(end_beat);
+        let beat_unit = change.time_signature.beat_unit();
+        let numerator = change.time_signature.numerator as usize;
+
+        for beat_ix in 0.. {
+            let beat = change.beat + beat_ix as f64 * beat_unit;
+            if beat >= segment_end_beat - BEAT_EPSILON {
+                break;
+            }
+            lines.push(GridLine {
+                beat,
+                is_measure_start: beat_ix % numerator == 0,
+            });
+        }
+    }
+    lines
+}
+
+/// Tempo and time signature change points, each sorted by beat.  There is always a change point
+/// of each kind at beat 0.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", from = "SerializedTempoMap")]
+pub struct TempoMap {
+    tempo_changes: Vec<TempoChange>,
+    time_signature_changes: Vec<TimeSignatureChange>,
+}
+
+/// Used to restore the invariants of `TempoMap` when deserializing it
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedTempoMap {
+    tempo_changes: Vec<TempoChange>,
+    time_signature_changes: Vec<TimeSignatureChange>,
+}
+
+impl From<SerializedTempoMap> for TempoMap {
+    fn from(serialized: SerializedTempoMap) -> Self {
+        let base_bpm = serialized
+            .tempo_changes
+            .first()
+            .map(|change| change.bpm)
+            .unwrap_or(DEFAULT_BPM);
+        let mut tempo_map = TempoMap::new(base_bpm);
+        for change in serialized.tempo_changes {
+            tempo_map.set_tempo(change.beat, change.bpm);
+        }
+        for TimeSignatureChange {
+            beat,
+            time_signature,
+        } in serialized.time_signature_changes
+        {
+            let time_signature =
+                TimeSignature::new(time_signature.numerator, time_signature.denominator)
+                    .unwrap_or_default();
+            tempo_map.set_time_signature(beat, time_signature);
+        }
+        tempo_map
+    }
+}
+
+impl Default for TempoMap {
+    fn default() -> Self { TempoMap::new(DEFAULT_BPM) }
+}
+
+/// Inserts `change` into the sorted `changes`, replacing any existing change at the same beat
+fn upsert_change<T>(changes: &mut Vec<T>, change: T, get_beat: impl Fn(&T) -> f64) {
+    let beat = get_beat(&change);
+    match changes
+        .iter()
+        .position(|existing| get_beat(existing) > beat - BEAT_EPSILON)
+    {
+        Some(ix) if (get_beat(&changes[ix]) - beat).abs() < BEAT_EPSILON => changes[ix] = change,
+        Some(ix) => changes.insert(ix, change),
+        None => changes.push(change),
+    }
+}
+
+/// Removes the change at `beat`, returning `true` if one was removed.  The change at beat 0 can't
+/// be removed.
+fn remove_change<T>(changes: &mut Vec<T>, beat: f64, get_beat: impl Fn(&T) -> f64) -> bool {
+    match changes
+        .iter()
+        .position(|change| (get_beat(change) - beat).abs() < BEAT_EPSILON)
+    {
+        Some(ix) if ix > 0 => {
+            changes.remove(ix);
+            true
+        },
+        _ => false,
+    }
+}
+
+impl TempoMap {
+    pub fn new(bpm: f64) -> Self {
+        TempoMap {
+            tempo_changes: vec![TempoChange {
+                beat: 0.,
+                bpm: bpm.clamp(MIN_BPM, MAX_BPM),
+            }],
+            time_signature_changes: vec![TimeSignatureChange {
+                beat: 0.,
+                time_signature: TimeSignature::default(),
+            }],
+        }
+    }
+
+    pub fn tempo_changes(&self) -> &[TempoChange] { &self.tempo_changes }
+
+    pub fn time_signature_changes(&self) -> &[TimeSignatureChange] {
+        &self.time_signature_changes
+    }
+
+    /// The tempo at the start of the composition
+    pub fn base_bpm(&self) -> f64 { self.tempo_changes[0].bpm }
+
+    pub fn set_base_bpm(&mut self, bpm: f64) { self.set_tempo(0., bpm) }
+
+    /// Sets the tempo from `beat` until the next tempo change, replacing any existing change at
+    /// that beat.
+    pub fn set_tempo(&mut self, beat: f64, bpm: f64) {
+        let change = TempoChange {
+            beat: beat.max(0.),
+            bpm: bpm.clamp(MIN_BPM, MAX_BPM),
+        };
+        upsert_change(&mut self.tempo_changes, change, |change| change.beat);
+    }
+
+    pub fn remove_tempo_change(&mut self, beat: f64) -> bool {
+        remove_change(&mut self.tempo_changes, beat, |change| change.beat)
+    }
+
+    pub fn set_time_signature(&mut self, beat: f64, time_signature: TimeSignature) {
+        let change = TimeSignatureChange {
+            beat: beat.max(0.),
+            time_signature,
+        };
+        upsert_change(&mut self.time_signature_changes, change, |change| change.beat);
+    }
+
+    pub fn remove_time_signature_change(&mut self, beat: f64) -> bool {
+        remove_change(&mut self.time_signature_changes, beat, |change| change.beat)
+    }
+
+    pub fn bpm_at(&self, beat: f64) -> f64 {
+        self.tempo_changes
+            .iter()
+            .take_while(|change| change.beat <= beat)
+            .last()
+            .unwrap_or(&self.tempo_changes[0])
+            .bpm
+    }
+
+    pub fn time_signature_at(&self, beat: f64) -> TimeSignature {
+        self.time_signature_changes
+            .iter()
+            .take_while(|change| change.beat <= beat)
+            .last()
+            .unwrap_or(&self.time_signature_changes[0])
+            .time_signature
+    }
+
+    /// Returns the time in seconds at which `beat` plays, relative to beat 0.  Negative beats are
+    /// converted using the starting tempo.
+    pub fn beat_to_seconds(&self, beat: f64) -> f64 {
+        if beat <= 0. {
+            return beat * 60. / self.base_bpm();
+        }
+
+        let mut seconds = 0.;
+        for (i, change) in self.tempo_changes.iter().enumerate() {
+            if beat <= change.beat {
+                break;
+            }
+            let segment_end_beat = self
+                .tempo_changes
+                .get(i + 1)
+                .map(|next_change| next_change.beat.min(beat))
+                .unwrap_or(beat);
+            seconds += (segment_end_beat - change.beat) * 60. / change.bpm;
+        }
+        seconds
+    }
+
+    /// The inverse of `beat_to_seconds`
+    pub fn seconds_to_beat(&self, seconds: f64) -> f64 {
+        if seconds <= 0. {
+            return seconds * self.base_bpm() / 60.;
+        }
+
+        let mut segment_start_seconds = 0.;
+        for (i, change) in self.tempo_changes.iter().enumerate() {
+            let next_change = match self.tempo_changes.get(i + 1) {
+                Some(next_change) => next_change,
+                None => break,
+            };
+            let segment_length_seconds = (next_change.beat - change.beat) * 60. / change.bpm;
+            if seconds < segment_start_seconds + segment_length_seconds {
+                return change.beat + (seconds - segment_start_seconds) * change.bpm / 60.;
+            }
+            segment_start_seconds += segment_length_seconds;
+        }
+
+        let last_change = self.tempo_changes.last().unwrap();
+        last_change.beat + (seconds - segment_start_seconds) * last_change.bpm / 60.
+    }
+
+    /// Returns how many seconds it takes to play `beats` beats starting at `start_beat`
+    pub fn beats_to_seconds_from(&self, start_beat: f64, beats: f64) -> f64 {
+        self.beat_to_seconds(start_beat + beats) - self.beat_to_seconds(start_beat)
+    }
+
+    /// Returns how many beats are played in `seconds` seconds starting at `start_beat`
+    pub fn seconds_to_beats_from(&self, start_beat: f64, seconds: f64) -> f64 {
+        self.seconds_to_beat(self.beat_to_seconds(start_beat) + seconds) - start_beat
+    }
+
+    /// Returns the beat at which the first measure starting at or after `beat` begins
+    pub fn next_measure_start(&self, beat: f64) -> f64 {
+        let ix = self
+            .time_signature_changes
+            .iter()
+            .rposition(|change| change.beat <= beat)
+            .unwrap_or(0);
+        let change = &self.time_signature_changes[ix];
+        let beats_per_measure = change.time_signature.beats_per_measure();
+        let measures = ((beat - change.beat) / beats_per_measure - BEAT_EPSILON).ceil();
+        let measure_start = change.beat + measures.max(0.) * beats_per_measure;
+
+        match self.time_signature_changes.get(ix + 1) {
+            Some(next_change) => measure_start.min(next_change.beat),
+            None => measure_start,
+        }
+    }
+
+    pub fn grid_lines(&self, end_beat: f64) -> Vec<GridLine> {
+        compute_grid_lines(&self.time_signature_changes, end_beat)
+    }
+}
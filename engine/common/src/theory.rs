@@ -0,0 +1,194 @@
+//! Scales, keys, and chords.  Notes are identified by their MIDI note number, so pitch classes are
+//! counted in semitones up from C.
+
+pub const NOTES_PER_OCTAVE: usize = 12;
+
+pub const PITCH_CLASS_NAMES: [&str; NOTES_PER_OCTAVE] = [
+    "C", "C#", "D", "Eb", "E", "F", "F#", "G", "Ab", "A", "Bb", "B",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Scale {
+    Chromatic,
+    Major,
+    NaturalMinor,
+    HarmonicMinor,
+    MelodicMinor,
+    Dorian,
+    Phrygian,
+    Lydian,
+    Mixolydian,
+    Locrian,
+    MajorPentatonic,
+    MinorPentatonic,
+    Blues,
+}
+
+impl Scale {
+    /// The semitones above the tonic of each of the scale's degrees, in ascending order
+    pub fn intervals(self) -> &'static [u8] {
+        match self {
+            Scale::Chromatic => &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+            Scale::Major => &[0, 2, 4, 5, 7, 9, 11],
+            Scale::NaturalMinor => &[0, 2, 3, 5, 7, 8, 10],
+            Scale::HarmonicMinor => &[0, 2, 3, 5, 7, 8, 11],
+            Scale::MelodicMinor => &[0, 2, 3, 5, 7, 9, 11],
+            Scale::Dorian => &[0, 2, 3, 5, 7, 9, 10],
+            Scale::Phrygian => &[0, 1, 3, 5, 7, 8, 10],
+            Scale::Lydian => &[0, 2, 4, 6, 7, 9, 11],
+            Scale::Mixolydian => &[0, 2, 4, 5, 7, 9, 10],
+            Scale::Locrian => &[0, 1, 3, 5, 6, 8, 10],
+            Scale::MajorPentatonic => &[0, 2, 4, 7, 9],
+            Scale::MinorPentatonic => &[0, 3, 5, 7, 10],
+            Scale::Blues => &[0, 3, 5, 6, 7, 10],
+        }
+    }
+}
+
+/// A scale rooted at a tonic pitch class
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Key {
+    /// Pitch class of the tonic, from 0 (C) to 11 (B)
+    pub tonic: u8,
+    pub scale: Scale,
+}
+
+impl Default for Key {
+    fn default() -> Self {
+        Key {
+            tonic: 0,
+            scale: Scale::Chromatic,
+        }
+    }
+}
+
+impl Key {
+    /// Returns the number of semitones `note` is above the closest tonic below it
+    fn offset_from_tonic(&self, note: usize) -> u8 {
+        ((note + NOTES_PER_OCTAVE - self.tonic as usize % NOTES_PER_OCTAVE) % NOTES_PER_OCTAVE)
+            as u8
+    }
+
+    /// Returns the index of the scale degree of `note`, or `None` if it's not in the key
+    pub fn degree(&self, note: usize) -> Option<usize> {
+        let offset = self.offset_from_tonic(note);
+        self.scale
+            .intervals()
+            .iter()
+            .position(|&interval| interval == offset)
+    }
+
+    pub fn contains(&self, note: usize) -> bool { self.degree(note).is_some() }
+
+    /// Returns the note in the key that's closest to `note`.  Ties are broken towards the lower
+    /// note.
+    pub fn nearest_in_key(&self, note: usize) -> usize {
+        for distance in 0..NOTES_PER_OCTAVE {
+            if distance <= note && self.contains(note - distance) {
+                return note - distance;
+            }
+            if self.contains(note + distance) {
+                return note + distance;
+            }
+        }
+        note
+    }
+
+    /// Returns `count` notes starting at `root` that are each two scale degrees above the last,
+    /// building a chord out of stacked thirds.  `root` is moved to the nearest note in the key if
+    /// it's not in it.
+    pub fn stack_thirds(&self, root: usize, count: usize) -> Vec<usize> {
+        let root = self.nearest_in_key(root);
+        let intervals = self.scale.intervals();
+        let root_degree = self.degree(root).unwrap();
+        // This is negative if the root is below the lowest tonic
+        let octave_start = root as isize - intervals[root_degree] as isize;
+
+        (0..count)
+            .map(|i| {
+                let degree = root_degree + i * 2;
+                let semitones = (degree / intervals.len()) * NOTES_PER_OCTAVE
+                    + intervals[degree % intervals.len()] as usize;
+                (octave_start + semitones as isize) as usize
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Chord {
+    Major,
+    Minor,
+    Diminished,
+    Augmented,
+    Sus2,
+    Sus4,
+    Major7,
+    Minor7,
+    Dominant7,
+    HalfDiminished7,
+    Diminished7,
+}
+
+impl Chord {
+    /// The semitones above the root of each of the chord's notes, including the root itself
+    pub fn intervals(self) -> &'static [u8] {
+        match self {
+            Chord::Major => &[0, 4, 7],
+            Chord::Minor => &[0, 3, 7],
+            Chord::Diminished => &[0, 3, 6],
+            Chord::Augmented => &[0, 4, 8],
+            Chord::Sus2 => &[0, 2, 7],
+            Chord::Sus4 => &[0, 5, 7],
+            Chord::Major7 => &[0, 4, 7, 11],
+            Chord::Minor7 => &[0, 3, 7, 10],
+            Chord::Dominant7 => &[0, 4, 7, 10],
+            Chord::HalfDiminished7 => &[0, 3, 6, 10],
+            Chord::Diminished7 => &[0, 3, 6, 9],
+        }
+    }
+}
+
+/// Describes the notes of a chord relative to the note it's rooted at
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ChordShape {
+    /// A chord with the same intervals regardless of the key
+    #[serde(rename_all = "camelCase")]
+    Fixed { chord: Chord },
+    /// Stacks `note_count` thirds from the scale of the key, giving triads for 3 notes and
+    /// seventh chords for 4.  The root is moved to the nearest note in the key if it's not in it.
+    #[serde(rename_all = "camelCase")]
+    Diatonic { note_count: usize },
+    /// Arbitrary offsets in semitones from the root
+    #[serde(rename_all = "camelCase")]
+    Custom { intervals: Vec<i8> },
+}
+
+impl Default for ChordShape {
+    fn default() -> Self { ChordShape::Diatonic { note_count: 3 } }
+}
+
+impl ChordShape {
+    /// Returns the notes of the chord rooted at `root` in `key`.  Notes that would be below
+    /// note 0 are dropped.
+    pub fn notes(&self, key: &Key, root: usize) -> Vec<usize> {
+        match self {
+            ChordShape::Fixed { chord } => chord
+                .intervals()
+                .iter()
+                .map(|&interval| root + interval as usize)
+                .collect(),
+            ChordShape::Diatonic { note_count } => key.stack_thirds(root, *note_count),
+            ChordShape::Custom { intervals } => intervals
+                .iter()
+                .map(|&interval| root as isize + interval as isize)
+                .filter(|&note| note >= 0)
+                .map(|note| note as usize)
+                .collect(),
+        }
+    }
+}
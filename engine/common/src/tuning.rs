@@ -0,0 +1,310 @@
+//! Microtuning with Scala scale (`.scl`) and keyboard mapping (`.kbm`) files.  A scale defines the
+//! pitches of its degrees in cents or frequency ratios above its root, repeating at its last degree
+//! (the period, usually an octave).  A keyboard mapping assigns scale degrees to MIDI notes and
+//! pins one note to a reference frequency.  Together they're turned into a table of the frequency
+//! of every MIDI note, which takes the place of 12-TET when triggering notes.
+//!
+//! The formats are described at http://www.huygens-fokker.org/scala/scl_format.html and
+//! http://www.huygens-fokker.org/scala/help.htm#mappings
+
+pub const MIDI_NOTE_COUNT: usize = 128;
+
+const CENTS_PER_OCTAVE: f64 = 1200.;
+
+#[derive(Debug, PartialEq)]
+pub enum TuningParseError {
+    /// The file ended before all of its required values were read
+    UnexpectedEnd,
+    /// A value couldn't be parsed or was out of range.  `line` starts at 1.
+    InvalidValue { line: usize, value: String },
+    EmptyScale,
+    /// The keyboard mapping leaves its reference note unmapped, so it can't be tuned
+    UnmappedReferenceNote,
+}
+
+impl std::fmt::Display for TuningParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TuningParseError::UnexpectedEnd => write!(f, "The file ended unexpectedly"),
+            TuningParseError::InvalidValue { line, value } =>
+                write!(f, "Invalid value \"{}\" on line {}", value, line),
+            TuningParseError::EmptyScale => write!(f, "The scale has no degrees"),
+            TuningParseError::UnmappedReferenceNote =>
+                write!(f, "The reference note isn't mapped to a scale degree"),
+        }
+    }
+}
+
+/// Returns the lines of a Scala file that aren't comments, trimmed and paired with their numbers
+fn content_lines(src: &str) -> impl Iterator<Item = (usize, &str)> {
+    src.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.starts_with('!'))
+        .map(|(i, line)| (i + 1, line.trim()))
+}
+
+/// Returns the first whitespace-separated value of each non-empty line.  Anything after it is
+/// ignored, which is where files put labels for values.
+fn values<'a>(
+    lines: impl Iterator<Item = (usize, &'a str)>,
+) -> impl Iterator<Item = (usize, &'a str)> {
+    lines.filter_map(|(line, text)| text.split_whitespace().next().map(|value| (line, value)))
+}
+
+fn parse_value<T: std::str::FromStr>((line, value): (usize, &str)) -> Result<T, TuningParseError> {
+    value.parse().map_err(|_| TuningParseError::InvalidValue {
+        line,
+        value: value.into(),
+    })
+}
+
+/// Parses a pitch as cents if it contains a period and as a ratio (`3/2` or `2`) otherwise
+fn parse_pitch((line, value): (usize, &str)) -> Result<f64, TuningParseError> {
+    if value.contains('.') {
+        return parse_value((line, value));
+    }
+
+    let mut parts = value.splitn(2, '/');
+    let numerator: u64 = parse_value((line, parts.next().unwrap_or_default()))?;
+    let denominator: u64 = match parts.next() {
+        Some(denominator) => parse_value((line, denominator))?,
+        None => 1,
+    };
+    if numerator == 0 || denominator == 0 {
+        return Err(TuningParseError::InvalidValue {
+            line,
+            value: value.into(),
+        });
+    }
+    Ok(CENTS_PER_OCTAVE * (numerator as f64 / denominator as f64).log2())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScalaScale {
+    pub description: String,
+    /// Cents above the root of each degree after the root, ending with the period of the scale
+    pub degrees: Vec<f64>,
+}
+
+impl ScalaScale {
+    pub fn parse(src: &str) -> Result<Self, TuningParseError> {
+        let mut lines = content_lines(src);
+        // The description can be empty, so it's read before empty lines are skipped
+        let (_, description) = lines.next().ok_or(TuningParseError::UnexpectedEnd)?;
+        let mut values = values(lines);
+        let degree_count: usize =
+            parse_value(values.next().ok_or(TuningParseError::UnexpectedEnd)?)?;
+        if degree_count == 0 {
+            return Err(TuningParseError::EmptyScale);
+        }
+
+        let degrees = values
+            .take(degree_count)
+            .map(parse_pitch)
+            .collect::<Result<Vec<_>, _>>()?;
+        if degrees.len() < degree_count {
+            return Err(TuningParseError::UnexpectedEnd);
+        }
+
+        Ok(ScalaScale {
+            description: description.into(),
+            degrees,
+        })
+    }
+
+    pub fn equal_temperament(notes_per_octave: usize) -> Self {
+        let step = CENTS_PER_OCTAVE / notes_per_octave as f64;
+        ScalaScale {
+            description: format!("{}-TET", notes_per_octave),
+            degrees: (1..=notes_per_octave).map(|i| i as f64 * step).collect(),
+        }
+    }
+
+    pub fn period(&self) -> f64 { self.degrees.last().copied().unwrap_or(CENTS_PER_OCTAVE) }
+
+    /// Returns the cents above the root of a degree, which can be outside of the first period
+    pub fn degree_cents(&self, degree: i64) -> f64 {
+        let degree_count = self.degrees.len() as i64;
+        let period_ix = degree.div_euclid(degree_count);
+        let cents_in_period = match degree.rem_euclid(degree_count) {
+            0 => 0.,
+            ix => self.degrees[ix as usize - 1],
+        };
+        period_ix as f64 * self.period() + cents_in_period
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyboardMapping {
+    /// Notes outside of `first_note..=last_note` are unmapped
+    pub first_note: u8,
+    pub last_note: u8,
+    /// The note that plays the root of the scale
+    pub middle_note: u8,
+    pub reference_note: u8,
+    pub reference_frequency: f64,
+    /// The scale degree at which the mapping repeats.  0 means the period of the scale.
+    pub octave_degree: usize,
+    /// The scale degree played by each key of a repetition of the mapping starting at the middle
+    /// note.  Keys that are `None` are unmapped and don't play.  If the mapping is empty, keys
+    /// play consecutive scale degrees.
+    pub mapping: Vec<Option<usize>>,
+}
+
+impl Default for KeyboardMapping {
+    fn default() -> Self {
+        KeyboardMapping {
+            first_note: 0,
+            last_note: (MIDI_NOTE_COUNT - 1) as u8,
+            middle_note: 60,
+            reference_note: 69,
+            reference_frequency: 440.,
+            octave_degree: 0,
+            mapping: Vec::new(),
+        }
+    }
+}
+
+impl KeyboardMapping {
+    pub fn parse(src: &str) -> Result<Self, TuningParseError> {
+        let mut values = values(content_lines(src));
+        let mut next_value = || values.next().ok_or(TuningParseError::UnexpectedEnd);
+        let parse_note = |(line, value): (usize, &str)| match parse_value((line, value))? {
+            note if note < MIDI_NOTE_COUNT as u8 => Ok(note),
+            _ => Err(TuningParseError::InvalidValue {
+                line,
+                value: value.into(),
+            }),
+        };
+
+        let map_size: usize = parse_value(next_value()?)?;
+        let first_note = parse_note(next_value()?)?;
+        let last_note = parse_note(next_value()?)?;
+        let middle_note = parse_note(next_value()?)?;
+        let reference_note = parse_note(next_value()?)?;
+        let reference_frequency = parse_value(next_value()?)?;
+        let octave_degree = parse_value(next_value()?)?;
+        let mut mapping = values
+            .take(map_size)
+            .map(|(line, value)| match value {
+                "x" | "X" => Ok(None),
+                _ => parse_value((line, value)).map(Some),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        // Keys that the file doesn't list an entry for are unmapped
+        mapping.resize(map_size, None);
+
+        Ok(KeyboardMapping {
+            first_note,
+            last_note,
+            middle_note,
+            reference_note,
+            reference_frequency,
+            octave_degree,
+            mapping,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tuning {
+    scale: ScalaScale,
+    keyboard_mapping: KeyboardMapping,
+    /// Cents above the root of the scale of the reference note
+    reference_cents: f64,
+}
+
+impl Tuning {
+    pub fn new(
+        scale: ScalaScale,
+        keyboard_mapping: KeyboardMapping,
+    ) -> Result<Self, TuningParseError> {
+        if scale.degrees.is_empty() {
+            return Err(TuningParseError::EmptyScale);
+        }
+
+        let mut tuning = Tuning {
+            scale,
+            keyboard_mapping,
+            reference_cents: 0.,
+        };
+        tuning.reference_cents = tuning
+            .note_cents(tuning.keyboard_mapping.reference_note)
+            .ok_or(TuningParseError::UnmappedReferenceNote)?;
+        Ok(tuning)
+    }
+
+    pub fn twelve_tet() -> Self {
+        let scale = ScalaScale::equal_temperament(12);
+        Tuning::new(scale, KeyboardMapping::default())
+            .expect("The default keyboard mapping maps its reference note")
+    }
+
+    /// Returns the cents above the root of the scale of a note regardless of whether it's in the
+    /// range of notes that the keyboard mapping retunes
+    fn note_cents(&self, note: u8) -> Option<f64> {
+        let KeyboardMapping {
+            middle_note,
+            octave_degree,
+            mapping,
+            ..
+        } = &self.keyboard_mapping;
+        let offset = note as i64 - *middle_note as i64;
+        if mapping.is_empty() {
+            return Some(self.scale.degree_cents(offset));
+        }
+
+        let map_size = mapping.len() as i64;
+        let octave_cents = match *octave_degree {
+            0 => self.scale.period(),
+            degree => self.scale.degree_cents(degree as i64),
+        };
+        let degree = mapping[offset.rem_euclid(map_size) as usize]?;
+        let repetition_ix = offset.div_euclid(map_size);
+        Some(repetition_ix as f64 * octave_cents + self.scale.degree_cents(degree as i64))
+    }
+
+    /// Returns `None` if the note is unmapped
+    pub fn frequency(&self, note: u8) -> Option<f64> {
+        let KeyboardMapping {
+            first_note,
+            last_note,
+            reference_frequency,
+            ..
+        } = &self.keyboard_mapping;
+        if note < *first_note || note > *last_note {
+            return None;
+        }
+
+        let cents = self.note_cents(note)?;
+        Some(reference_frequency * 2f64.powf((cents - self.reference_cents) / CENTS_PER_OCTAVE))
+    }
+
+    /// Builds a table of the frequency of every MIDI note, with 0 for unmapped notes
+    pub fn build_table(&self) -> Vec<f32> {
+        (0..MIDI_NOTE_COUNT as u8)
+            .map(|note| self.frequency(note).unwrap_or(0.) as f32)
+            .collect()
+    }
+}
+
+/// The contents of the Scala files that a tuning was loaded from.  Tunings are saved like this
+/// rather than as frequency tables since the files are small and can be shown to the user again.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TuningConf {
+    pub scl: String,
+    /// The default mapping tunes A4 (MIDI note 69) to 440Hz with the root of the scale on middle C
+    #[serde(default)]
+    pub kbm: Option<String>,
+}
+
+impl TuningConf {
+    pub fn parse(&self) -> Result<Tuning, TuningParseError> {
+        let scale = ScalaScale::parse(&self.scl)?;
+        let keyboard_mapping = match &self.kbm {
+            Some(kbm) => KeyboardMapping::parse(kbm)?,
+            None => KeyboardMapping::default(),
+        };
+        Tuning::new(scale, keyboard_mapping)
+    }
+}
@@ -0,0 +1,64 @@
+#[macro_use]
+extern crate criterion;
+extern crate engine;
+extern crate rand_pcg;
+
+use criterion::{BatchSize, Criterion};
+use engine::helpers::grid::{
+    note_box::{NoteBox, NoteBoxBounds, NoteId},
+    skip_list::*,
+};
+use rand_pcg::Pcg32;
+
+fn mknotes(count: usize) -> Vec<NoteBox<usize>> {
+    (0..count)
+        .map(|i| NoteBox {
+            id: NoteId::next(),
+            bounds: NoteBoxBounds {
+                start_beat: i as f32,
+                end_beat: i as f32 + 0.5,
+            },
+            data: i,
+            velocity: 100,
+            pitch_bend: Vec::new(),
+            expression: Default::default(),
+        })
+        .collect()
+}
+
+fn skiplist_level_generation(c: &mut Criterion) {
+    let mut rng = Pcg32::new(0, common::RNG_STREAM);
+    c.bench_function("skiplist_level_generation", |b| {
+        b.iter(|| get_skip_list_level(&mut rng))
+    });
+}
+
+fn skiplist_insertion(c: &mut Criterion) {
+    c.bench_function("skiplist_insert_1000", |b| {
+        b.iter_batched(
+            || mknotes(1000),
+            |notes| {
+                let mut line = NoteSkipList::with_seed(0);
+                for note in notes {
+                    line.insert(note);
+                }
+                line
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    c.bench_function("skiplist_insert_sorted_batch_1000", |b| {
+        b.iter_batched(
+            || mknotes(1000),
+            |notes| {
+                let mut line = NoteSkipList::with_seed(0);
+                line.insert_sorted_batch(notes);
+                line
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+criterion_group!(benches, skiplist_level_generation, skiplist_insertion);
+criterion_main!(benches);
@@ -0,0 +1,407 @@
+//! A patching graph of audio nodes such as instruments, effects, and mixers along with the
+//! connections between their ports.  The graph is validated here as it's built (port types must
+//! match and cycles aren't allowed) so that it can always be processed in topological order.
+//!
+//! The graph itself only models structure; the WebAudio nodes that implement it live on the JS
+//! side.  `GraphExecutor` can run a graph in Rust as well by attaching a `BlockProcessor` to each
+//! node and processing the nodes a block at a time in topological order.
+
+use std::{collections::VecDeque, fmt};
+
+use audio_block::{Block, BlockProcessor};
+
+pub type NodeId = u32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PortType {
+    /// Audio-rate signals; maps to the `customAudio` connectable type on the JS side
+    Audio,
+    /// Control voltage, used to modulate params; maps to the `number` connectable type
+    Cv,
+    Midi,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeKind {
+    Instrument,
+    Effect,
+    Mixer,
+    Output,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Port {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub port_type: PortType,
+}
+
+impl Port {
+    pub fn new(name: &str, port_type: PortType) -> Self {
+        Port {
+            name: name.into(),
+            port_type,
+        }
+    }
+}
+
+/// The parts of a node provided when adding it to the graph
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NodeDefinition {
+    pub kind: NodeKind,
+    pub name: String,
+    pub inputs: Vec<Port>,
+    pub outputs: Vec<Port>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AudioGraphNode {
+    pub id: NodeId,
+    #[serde(flatten)]
+    pub definition: NodeDefinition,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortRef {
+    pub node: NodeId,
+    pub port: String,
+}
+
+impl PortRef {
+    pub fn new(node: NodeId, port: &str) -> Self {
+        PortRef {
+            node,
+            port: port.into(),
+        }
+    }
+}
+
+/// A connection from an output port of one node to an input port of another
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Connection {
+    pub from: PortRef,
+    pub to: PortRef,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConnectError {
+    UnknownNode(NodeId),
+    UnknownPort(PortRef),
+    PortTypeMismatch {
+        from: PortType,
+        to: PortType,
+    },
+    AlreadyConnected,
+    /// The connection would create a cycle in the graph, including connecting a node to itself
+    WouldCreateCycle,
+}
+
+impl fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConnectError::UnknownNode(id) => write!(f, "No node with id {} exists", id),
+            ConnectError::UnknownPort(port_ref) => write!(
+                f,
+                "Node {} has no port named \"{}\"",
+                port_ref.node, port_ref.port
+            ),
+            ConnectError::PortTypeMismatch { from, to } => write!(
+                f,
+                "Can't connect a port of type {:?} to a port of type {:?}",
+                from, to
+            ),
+            ConnectError::AlreadyConnected => write!(f, "Those ports are already connected"),
+            ConnectError::WouldCreateCycle => write!(f, "The connection would create a cycle"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AudioGraph {
+    nodes: Vec<AudioGraphNode>,
+    connections: Vec<Connection>,
+    next_node_id: NodeId,
+}
+
+impl AudioGraph {
+    pub fn nodes(&self) -> &[AudioGraphNode] { &self.nodes }
+
+    pub fn connections(&self) -> &[Connection] { &self.connections }
+
+    pub fn get_node(&self, id: NodeId) -> Option<&AudioGraphNode> {
+        self.nodes.iter().find(|node| node.id == id)
+    }
+
+    /// Adds a new node with no connections, returning its ID
+    pub fn add_node(&mut self, definition: NodeDefinition) -> NodeId {
+        let id = self.next_node_id;
+        self.next_node_id += 1;
+        self.nodes.push(AudioGraphNode { id, definition });
+        id
+    }
+
+    /// Removes the node along with all connections to or from it
+    pub fn remove_node(&mut self, id: NodeId) -> Option<AudioGraphNode> {
+        let ix = self.nodes.iter().position(|node| node.id == id)?;
+        self.connections
+            .retain(|conn| conn.from.node != id && conn.to.node != id);
+        Some(self.nodes.remove(ix))
+    }
+
+    fn get_port_type(&self, port_ref: &PortRef, is_input: bool) -> Result<PortType, ConnectError> {
+        let node = self
+            .get_node(port_ref.node)
+            .ok_or(ConnectError::UnknownNode(port_ref.node))?;
+        let ports = if is_input {
+            &node.definition.inputs
+        } else {
+            &node.definition.outputs
+        };
+
+        ports
+            .iter()
+            .find(|port| port.name == port_ref.port)
+            .map(|port| port.port_type)
+            .ok_or_else(|| ConnectError::UnknownPort(port_ref.clone()))
+    }
+
+    /// Returns `true` if `to` can be reached by following connections starting at `from`
+    fn is_reachable(&self, from: NodeId, to: NodeId) -> bool {
+        let mut visited = vec![from];
+        let mut queue: VecDeque<NodeId> = VecDeque::new();
+        queue.push_back(from);
+
+        while let Some(id) = queue.pop_front() {
+            if id == to {
+                return true;
+            }
+
+            for conn in self.connections.iter().filter(|conn| conn.from.node == id) {
+                if !visited.contains(&conn.to.node) {
+                    visited.push(conn.to.node);
+                    queue.push_back(conn.to.node);
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Connects an output port to an input port.  The graph is left unchanged if an error is
+    /// returned.
+    pub fn connect(&mut self, from: PortRef, to: PortRef) -> Result<(), ConnectError> {
+        let from_type = self.get_port_type(&from, false)?;
+        let to_type = self.get_port_type(&to, true)?;
+        if from_type != to_type {
+            return Err(ConnectError::PortTypeMismatch {
+                from: from_type,
+                to: to_type,
+            });
+        }
+
+        let connection = Connection { from, to };
+        if self.connections.contains(&connection) {
+            return Err(ConnectError::AlreadyConnected);
+        }
+        if self.is_reachable(connection.to.node, connection.from.node) {
+            return Err(ConnectError::WouldCreateCycle);
+        }
+
+        self.connections.push(connection);
+        Ok(())
+    }
+
+    /// Returns `false` if the ports weren't connected
+    pub fn disconnect(&mut self, from: &PortRef, to: &PortRef) -> bool {
+        match self
+            .connections
+            .iter()
+            .position(|conn| &conn.from == from && &conn.to == to)
+        {
+            Some(ix) => {
+                self.connections.remove(ix);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Returns the IDs of all nodes ordered such that every node comes after all of the nodes that
+    /// feed into it, which is the order in which they must be processed.  Nodes that don't depend
+    /// on each other keep the order in which they were added.
+    pub fn topological_order(&self) -> Vec<NodeId> {
+        let mut in_degrees: Vec<usize> = self
+            .nodes
+            .iter()
+            .map(|node| {
+                self.connections
+                    .iter()
+                    .filter(|conn| conn.to.node == node.id)
+                    .count()
+            })
+            .collect();
+        let mut order = Vec::with_capacity(self.nodes.len());
+        let mut ready: VecDeque<usize> = (0..self.nodes.len())
+            .filter(|&i| in_degrees[i] == 0)
+            .collect();
+
+        while let Some(ix) = ready.pop_front() {
+            let id = self.nodes[ix].id;
+            order.push(id);
+
+            for conn in self.connections.iter().filter(|conn| conn.from.node == id) {
+                let dst_ix = self
+                    .nodes
+                    .iter()
+                    .position(|node| node.id == conn.to.node)
+                    .expect("Connection references a node that isn't in the graph");
+                in_degrees[dst_ix] -= 1;
+                if in_degrees[dst_ix] == 0 {
+                    ready.push_back(dst_ix);
+                }
+            }
+        }
+
+        debug_assert_eq!(
+            order.len(),
+            self.nodes.len(),
+            "Cycle found in audio graph; `connect` should have prevented this"
+        );
+        order
+    }
+
+    pub fn serialize(&self) -> String {
+        serde_json::to_string(self).expect("Error serializing `AudioGraph` to String")
+    }
+
+    /// Deserializes a graph, making sure that it contains no cycles or invalid connections.
+    pub fn deserialize(serialized: &str) -> Result<Self, String> {
+        let raw: AudioGraph = serde_json::from_str(serialized).map_err(|err| err.to_string())?;
+
+        let mut graph = AudioGraph {
+            nodes: raw.nodes,
+            connections: Vec::new(),
+            next_node_id: raw.next_node_id,
+        };
+        if let Some(max_id) = graph.nodes.iter().map(|node| node.id).max() {
+            graph.next_node_id = graph.next_node_id.max(max_id + 1);
+        }
+        for Connection { from, to } in raw.connections {
+            graph.connect(from, to).map_err(|err| err.to_string())?;
+        }
+
+        Ok(graph)
+    }
+}
+
+struct ExecutorNode {
+    id: NodeId,
+    kind: NodeKind,
+    /// Indices in the executor's `nodes` of the nodes with audio connections into this one, which
+    /// always come before it
+    sources: Vec<usize>,
+    processor: Option<Box<dyn BlockProcessor>>,
+    /// The node's output for the block that was most recently processed
+    output: Block,
+}
+
+/// Processes the audio of a graph a block at a time.  Each node is handed the sum of the outputs
+/// of the nodes connected to its audio inputs, and nodes are run in topological order so that
+/// those outputs are always ready.  Nodes without a processor pass their input through unchanged.
+///
+/// The executor is itself a `BlockProcessor` which overwrites the block with the sum of the
+/// outputs of all `Output` nodes.  It's built from a snapshot of the graph, so it must be rebuilt
+/// when the graph changes.
+pub struct GraphExecutor {
+    nodes: Vec<ExecutorNode>,
+}
+
+impl GraphExecutor {
+    pub fn new(graph: &AudioGraph) -> Self {
+        let order = graph.topological_order();
+        let position = |id: NodeId| order.iter().position(|&ordered_id| ordered_id == id);
+        let is_audio = |conn: &Connection| {
+            graph
+                .get_node(conn.from.node)
+                .and_then(|node| {
+                    node.definition
+                        .outputs
+                        .iter()
+                        .find(|port| port.name == conn.from.port)
+                })
+                .map(|port| port.port_type == PortType::Audio)
+                .unwrap_or(false)
+        };
+
+        let nodes = order
+            .iter()
+            .filter_map(|&id| graph.get_node(id))
+            .map(|node| {
+                let mut sources: Vec<usize> = graph
+                    .connections
+                    .iter()
+                    .filter(|conn| conn.to.node == node.id && is_audio(conn))
+                    .filter_map(|conn| position(conn.from.node))
+                    .collect();
+                // A node connected to multiple inputs of another is only summed in once
+                sources.sort_unstable();
+                sources.dedup();
+
+                ExecutorNode {
+                    id: node.id,
+                    kind: node.definition.kind,
+                    sources,
+                    processor: None,
+                    output: Block::default(),
+                }
+            })
+            .collect();
+
+        GraphExecutor { nodes }
+    }
+
+    /// Sets the processor that implements the node, returning `false` if the node isn't in the
+    /// graph that the executor was built from.
+    pub fn set_processor(&mut self, id: NodeId, processor: Box<dyn BlockProcessor>) -> bool {
+        match self.nodes.iter_mut().find(|node| node.id == id) {
+            Some(node) => {
+                node.processor = Some(processor);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Returns the output of the node for the block that was most recently processed
+    pub fn node_output(&self, id: NodeId) -> Option<&Block> {
+        self.nodes
+            .iter()
+            .find(|node| node.id == id)
+            .map(|node| &node.output)
+    }
+}
+
+impl BlockProcessor for GraphExecutor {
+    fn process(&mut self, block: &mut Block) {
+        block.clear();
+
+        for ix in 0..self.nodes.len() {
+            let (processed, rest) = self.nodes.split_at_mut(ix);
+            let node = &mut rest[0];
+
+            node.output.clear();
+            for &source_ix in &node.sources {
+                node.output.mix(&processed[source_ix].output);
+            }
+            if let Some(processor) = &mut node.processor {
+                processor.process(&mut node.output);
+            }
+
+            if node.kind == NodeKind::Output {
+                block.mix(&node.output);
+            }
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! Records the master output of the application into memory while it plays.  Blocks of processed
+//! audio are copied out of the audio thread and appended here, after which the recording can be
+//! exported as a WAV file or handed over to the sample library.
+
+// The exports are only called by `src/audioRecorder.ts` with the pointer that it got from
+// `create_audio_recorder`
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use wasm_bindgen::prelude::*;
+
+use crate::offline_render::{encode_wav, parse_bit_depth, WavBitDepth};
+
+/// Recordings are stopped once they reach this length to keep memory usage bounded.  At 44.1kHz,
+/// that's about 100MB of stereo `f32` samples.
+pub const DEFAULT_MAX_RECORDING_SECONDS: f32 = 60. * 5.;
+
+pub struct AudioRecorder {
+    pub sample_rate: u32,
+    pub max_frames: usize,
+    left: Vec<f32>,
+    right: Vec<f32>,
+}
+
+impl AudioRecorder {
+    pub fn new(sample_rate: u32, max_seconds: f32) -> Self {
+        AudioRecorder {
+            sample_rate,
+            max_frames: (sample_rate as f32 * max_seconds.max(0.)) as usize,
+            left: Vec::new(),
+            right: Vec::new(),
+        }
+    }
+
+    pub fn len_frames(&self) -> usize { self.left.len() }
+
+    pub fn len_seconds(&self) -> f32 { self.len_frames() as f32 / self.sample_rate as f32 }
+
+    pub fn is_full(&self) -> bool { self.len_frames() >= self.max_frames }
+
+    /// Appends a block of stereo audio to the recording.  If the block would take the recording
+    /// past its maximum length, it's truncated and `false` is returned.  Mono blocks can be
+    /// recorded by passing the same channel twice.
+    pub fn append_block(&mut self, left: &[f32], right: &[f32]) -> bool {
+        debug_assert_eq!(left.len(), right.len());
+        let remaining_frames = self.max_frames - self.len_frames();
+        let frame_count = left.len().min(right.len());
+        let appended_frames = frame_count.min(remaining_frames);
+
+        self.left.extend_from_slice(&left[..appended_frames]);
+        self.right.extend_from_slice(&right[..appended_frames]);
+        appended_frames == frame_count
+    }
+
+    pub fn clear(&mut self) {
+        self.left = Vec::new();
+        self.right = Vec::new();
+    }
+
+    /// Returns all of the left channel's samples followed by all of the right channel's
+    pub fn get_planar_samples(&self) -> Vec<f32> {
+        let mut samples = Vec::with_capacity(self.len_frames() * 2);
+        samples.extend_from_slice(&self.left);
+        samples.extend_from_slice(&self.right);
+        samples
+    }
+
+    pub fn encode_wav(&self, bit_depth: WavBitDepth) -> Vec<u8> {
+        encode_wav(&[&self.left, &self.right], self.sample_rate, bit_depth)
+    }
+}
+
+fn with_recorder<T>(
+    recorder_ptr: *mut AudioRecorder,
+    f: impl FnOnce(&mut AudioRecorder) -> T,
+) -> T {
+    f(unsafe { &mut *recorder_ptr })
+}
+
+#[wasm_bindgen]
+pub fn create_audio_recorder(sample_rate: u32, max_seconds: Option<f32>) -> *mut AudioRecorder {
+    let max_seconds = max_seconds.unwrap_or(DEFAULT_MAX_RECORDING_SECONDS);
+    Box::into_raw(Box::new(AudioRecorder::new(sample_rate, max_seconds)))
+}
+
+#[wasm_bindgen]
+pub fn drop_audio_recorder(recorder_ptr: *mut AudioRecorder) {
+    drop(unsafe { Box::from_raw(recorder_ptr) })
+}
+
+/// Returns `false` if the recording has reached its maximum length and recording should stop.
+#[wasm_bindgen]
+pub fn audio_recorder_append_block(
+    recorder_ptr: *mut AudioRecorder,
+    left: &[f32],
+    right: &[f32],
+) -> bool {
+    with_recorder(recorder_ptr, |recorder| recorder.append_block(left, right))
+}
+
+#[wasm_bindgen]
+pub fn audio_recorder_get_length_seconds(recorder_ptr: *mut AudioRecorder) -> f32 {
+    with_recorder(recorder_ptr, |recorder| recorder.len_seconds())
+}
+
+#[wasm_bindgen]
+pub fn audio_recorder_clear(recorder_ptr: *mut AudioRecorder) {
+    with_recorder(recorder_ptr, AudioRecorder::clear)
+}
+
+#[wasm_bindgen]
+pub fn audio_recorder_get_planar_samples(recorder_ptr: *mut AudioRecorder) -> Vec<f32> {
+    with_recorder(recorder_ptr, |recorder| recorder.get_planar_samples())
+}
+
+/// `bit_depth` must be 16 or 24.
+#[wasm_bindgen]
+pub fn audio_recorder_encode_wav(recorder_ptr: *mut AudioRecorder, bit_depth: u8) -> Vec<u8> {
+    let bit_depth = parse_bit_depth(bit_depth);
+    with_recorder(recorder_ptr, |recorder| recorder.encode_wav(bit_depth))
+}
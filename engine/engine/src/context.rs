@@ -0,0 +1,49 @@
+//! The state that's shared by the whole engine.  Everything that used to live in its own global,
+//! such as the VCM, the storage backend, and the registries of loaded samples and SoundFonts, is
+//! owned by the `EngineContext` so that there's a single place where global state is kept.
+
+use std::cell::UnsafeCell;
+
+use crate::{
+    sample_import::SamplePool,
+    soundfont::SoundFontRegistry,
+    storage::{LocalStorage, Storage},
+    view_context::manager::ViewContextManager,
+};
+
+pub struct EngineContext {
+    /// Holds all of the view contexts for the application.  It's replaced with a fresh one every
+    /// time that the engine is initialized.
+    pub vcm: ViewContextManager,
+    /// The storage used for state owned by the engine.  It's `LocalStorage` unless it's replaced
+    /// with `storage::set_storage`.
+    pub storage: Box<dyn Storage>,
+    /// The sample pool shared by all samplers in the application
+    pub sample_pool: SamplePool,
+    /// The SoundFont registry shared by all SoundFont players in the application
+    pub soundfont_registry: SoundFontRegistry,
+}
+
+impl Default for EngineContext {
+    fn default() -> Self {
+        EngineContext {
+            vcm: ViewContextManager::default(),
+            storage: Box::new(LocalStorage),
+            sample_pool: SamplePool::default(),
+            soundfont_registry: SoundFontRegistry::default(),
+        }
+    }
+}
+
+thread_local! {
+    static ENGINE_CONTEXT: UnsafeCell<EngineContext> = UnsafeCell::new(EngineContext::default());
+}
+
+/// Retrieves the engine's context, creating it the first time that it's accessed.
+///
+/// The engine runs on the main thread and JS calls back into it while it's handling calls from
+/// JS, so the context is handed out directly rather than being borrowed through a `RefCell`,
+/// which would panic when those calls re-enter the engine.
+pub fn ctx() -> &'static mut EngineContext {
+    ENGINE_CONTEXT.with(|ctx| unsafe { &mut *ctx.get() })
+}
@@ -0,0 +1,67 @@
+//! A basic radix-2 FFT along with the windows that are applied to signals before transforming them.
+
+use std::f64::consts::PI;
+
+/// Computes the FFT of the complex signal with real parts `re` and imaginary parts `im` in place.
+/// Both slices must have the same length, which must be a power of 2.
+pub fn fft_in_place(re: &mut [f32], im: &mut [f32]) {
+    let len = re.len();
+    assert!(
+        len.is_power_of_two(),
+        "FFT length must be a power of 2; got {}",
+        len
+    );
+    assert_eq!(
+        len,
+        im.len(),
+        "FFT real and imaginary parts must be the same length"
+    );
+
+    // Re-order the input into bit-reversed order so that the butterflies can be computed in place
+    let mut j = 0;
+    for i in 1..len {
+        let mut bit = len >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut span = 2;
+    while span <= len {
+        let half_span = span / 2;
+        let (step_im, step_re) = (-2. * PI / span as f64).sin_cos();
+        for start in (0..len).step_by(span) {
+            // Twiddle factors are accumulated in `f64` so that rounding errors don't build up
+            let (mut twiddle_re, mut twiddle_im) = (1.0f64, 0.0f64);
+            for a in start..start + half_span {
+                let b = a + half_span;
+                let (tw_re, tw_im) = (twiddle_re as f32, twiddle_im as f32);
+                let t_re = re[b] * tw_re - im[b] * tw_im;
+                let t_im = re[b] * tw_im + im[b] * tw_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+
+                let next_re = twiddle_re * step_re - twiddle_im * step_im;
+                twiddle_im = twiddle_re * step_im + twiddle_im * step_re;
+                twiddle_re = next_re;
+            }
+        }
+        span <<= 1;
+    }
+}
+
+/// Builds a periodic Hann window of length `len`, which is what should be used for windowing
+/// frames before analysis or overlap-add processing.
+pub fn hann_window(len: usize) -> Vec<f32> {
+    (0..len)
+        .map(|i| (0.5 - 0.5 * (2. * PI * i as f64 / len as f64).cos()) as f32)
+        .collect()
+}
@@ -0,0 +1,153 @@
+//! Level metering with the ballistics of a typical DAW meter.  Peaks rise instantly and fall back
+//! at a fixed rate in dB, RMS levels are averaged over a sliding time window, and the highest
+//! recent peak is held for a while so that it can be read.
+//!
+//! Meters are fed a block at a time.  Blocks are usually summarized by the audio thread before
+//! being sent over so that only their peak and mean square need to be transferred.
+
+use audio_block::{Block, BlockProcessor, BLOCK_SIZE};
+
+/// Summary of a block of audio, which is all that meters need to see
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BlockLevels {
+    pub peak: f32,
+    pub mean_square: f32,
+    pub frame_count: usize,
+}
+
+impl BlockLevels {
+    pub fn from_samples(samples: &[f32]) -> Self {
+        let (peak, sum_of_squares) = samples.iter().fold((0.0f32, 0.0f32), |(peak, sum), &s| {
+            (peak.max(s.abs()), sum + s * s)
+        });
+        BlockLevels {
+            peak,
+            mean_square: if samples.is_empty() {
+                0.
+            } else {
+                sum_of_squares / samples.len() as f32
+            },
+            frame_count: samples.len(),
+        }
+    }
+
+    /// Summarizes both channels of the block together, taking the louder channel's peak and the
+    /// mean square across both channels
+    pub fn from_block(block: &Block) -> Self {
+        let left = Self::from_samples(&block.left);
+        let right = Self::from_samples(&block.right);
+        BlockLevels {
+            peak: left.peak.max(right.peak),
+            mean_square: (left.mean_square + right.mean_square) / 2.,
+            frame_count: BLOCK_SIZE,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeterBallistics {
+    /// How fast the peak level falls once the signal gets quieter
+    pub peak_release_db_per_second: f32,
+    /// Time constant of the exponential average used for the RMS level
+    pub rms_window_seconds: f32,
+    /// How long the highest peak is held before it starts falling
+    pub peak_hold_seconds: f32,
+}
+
+impl Default for MeterBallistics {
+    fn default() -> Self {
+        MeterBallistics {
+            peak_release_db_per_second: 24.,
+            rms_window_seconds: 0.3,
+            peak_hold_seconds: 1.5,
+        }
+    }
+}
+
+/// The current levels of a meter, all linear amplitudes
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MeterReading {
+    pub peak: f32,
+    pub rms: f32,
+    pub held_peak: f32,
+    /// Set when any sample reaches full scale, and stays set until it's explicitly reset
+    pub clipped: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Meter {
+    peak: f32,
+    mean_square: f32,
+    held_peak: f32,
+    hold_remaining_seconds: f32,
+    clipped: bool,
+}
+
+impl Meter {
+    pub fn process_block(
+        &mut self,
+        ballistics: &MeterBallistics,
+        block: BlockLevels,
+        sample_rate: f32,
+    ) {
+        let block_seconds = block.frame_count as f32 / sample_rate;
+
+        let released_peak =
+            self.peak * 10f32.powf(-ballistics.peak_release_db_per_second * block_seconds / 20.);
+        self.peak = block.peak.max(released_peak);
+
+        let rms_coefficient = 1. - (-block_seconds / ballistics.rms_window_seconds).exp();
+        self.mean_square += (block.mean_square - self.mean_square) * rms_coefficient;
+
+        if self.peak >= self.held_peak {
+            self.held_peak = self.peak;
+            self.hold_remaining_seconds = ballistics.peak_hold_seconds;
+        } else if self.hold_remaining_seconds > 0. {
+            self.hold_remaining_seconds -= block_seconds;
+        } else {
+            // Once the hold time is up, the held peak falls back to the current peak
+            self.held_peak = self.peak;
+        }
+
+        self.clipped |= block.peak >= 1.;
+    }
+
+    pub fn reading(&self) -> MeterReading {
+        MeterReading {
+            peak: self.peak,
+            rms: self.mean_square.sqrt(),
+            held_peak: self.held_peak,
+            clipped: self.clipped,
+        }
+    }
+
+    pub fn reset_clipped(&mut self) { self.clipped = false; }
+}
+
+/// A meter along with the ballistics and sample rate that it's run with.  It can be fed summaries
+/// of blocks that were taken elsewhere, or it can be placed anywhere in a chain of processors and
+/// meter the audio passing through it without changing it.
+#[derive(Clone, Debug)]
+pub struct MeterTap {
+    pub meter: Meter,
+    pub ballistics: MeterBallistics,
+    pub sample_rate: f32,
+}
+
+impl MeterTap {
+    pub fn new(ballistics: MeterBallistics, sample_rate: f32) -> Self {
+        MeterTap {
+            meter: Meter::default(),
+            ballistics,
+            sample_rate,
+        }
+    }
+
+    pub fn process_levels(&mut self, levels: BlockLevels) {
+        self.meter.process_block(&self.ballistics, levels, self.sample_rate);
+    }
+}
+
+impl BlockProcessor for MeterTap {
+    fn process(&mut self, block: &mut Block) { self.process_levels(BlockLevels::from_block(block)) }
+}
@@ -0,0 +1,8 @@
+//! Signal processing routines that run in the engine itself rather than in the audio thread, such
+//! as analysis of audio that is tapped from the graph and sent over from JS or offline processing
+//! of samples.
+
+pub mod fft;
+pub mod meter;
+pub mod time_stretch;
+pub mod transients;
@@ -0,0 +1,130 @@
+//! Offline time-stretching and pitch-shifting of sampled audio using WSOLA (waveform similarity
+//! overlap-add).  The input is cut into overlapping windowed frames which are laid back down with
+//! a different spacing than they were taken with.  Each frame is taken from wherever near its ideal
+//! position it lines up best with the audio before it, which keeps the waveform continuous without
+//! smearing transients the way that frequency-domain methods do on material like drum loops.
+
+use crate::{dsp::fft::hann_window, sample_import::resample_by_ratio};
+
+/// Length of the frames that are overlapped, in seconds.  Shorter frames follow transients more
+/// closely but can't hold on to low frequencies as well.
+const FRAME_SECONDS: f64 = 0.04;
+/// How far from its ideal position each frame can be taken from, as a fraction of the frame length
+const SEARCH_FRACTION: f64 = 0.25;
+
+/// Returns how long audio that's `len` frames long is after being stretched by `stretch`
+pub fn stretched_len(len: usize, stretch: f64) -> usize { (len as f64 * stretch).round() as usize }
+
+/// Returns the position in `[min_pos, max_pos]` at which `signal` is most similar to `target`,
+/// measured by cross-correlation normalized by the energy of the candidate
+fn best_match(signal: &[f32], target: &[f32], min_pos: usize, max_pos: usize) -> usize {
+    let mut best_pos = min_pos;
+    let mut best_score = f32::NEG_INFINITY;
+    for pos in min_pos..=max_pos {
+        let (dot, energy) = signal[pos..pos + target.len()].iter().zip(target).fold(
+            (0.0f32, 0.0f32),
+            |(dot, energy), (&candidate, &target)| {
+                (dot + candidate * target, energy + candidate * candidate)
+            },
+        );
+        let score = dot / (energy + 1e-9).sqrt();
+        if score > best_score {
+            best_score = score;
+            best_pos = pos;
+        }
+    }
+    best_pos
+}
+
+/// Changes the length of `channels` by a factor of `stretch` without changing their pitch.  All
+/// channels are cut at the same places so that the stereo image is preserved.
+pub fn time_stretch(channels: &[Vec<f32>], sample_rate: u32, stretch: f64) -> Vec<Vec<f32>> {
+    if stretch == 1. {
+        return channels.to_vec();
+    }
+
+    let in_len = channels.first().map(Vec::len).unwrap_or(0);
+    let out_len = stretched_len(in_len, stretch);
+    // Kept even so that frames overlap by exactly half
+    let frame_len = ((sample_rate as f64 * FRAME_SECONDS) as usize / 2 * 2).max(2);
+    // There isn't enough audio to cut into frames, so it's just resampled.  It's too short for the
+    // change in pitch to be heard anyway.
+    if in_len < frame_len * 2 {
+        return channels
+            .iter()
+            .map(|channel| {
+                let mut resampled = resample_by_ratio(channel, stretch);
+                resampled.resize(out_len, 0.);
+                resampled
+            })
+            .collect();
+    }
+
+    let hop = frame_len / 2;
+    let tolerance = (frame_len as f64 * SEARCH_FRACTION) as usize;
+    let max_pos = in_len - frame_len;
+    // The window is periodic, so windows overlapping by half sum to exactly 1
+    let window = hann_window(frame_len);
+    let mono: Vec<f32> = (0..in_len)
+        .map(|i| channels.iter().map(|channel| channel[i]).sum())
+        .collect();
+
+    let mut out = vec![vec![0.0f32; out_len + frame_len]; channels.len()];
+    let mut window_sum = vec![0.0f32; out_len + frame_len];
+    let mut prev_pos = 0;
+    let mut out_pos = 0;
+    while out_pos < out_len {
+        let pos = if out_pos == 0 {
+            0
+        } else {
+            // The audio that followed the previous frame in the input is what would continue it
+            // seamlessly, so the frame is taken from near its ideal position wherever the input
+            // looks most like that.
+            let natural_pos = (prev_pos + hop).min(max_pos);
+            let ideal_pos = ((out_pos as f64 / stretch).round() as usize).min(max_pos);
+            best_match(
+                &mono,
+                &mono[natural_pos..natural_pos + hop],
+                ideal_pos.saturating_sub(tolerance),
+                (ideal_pos + tolerance).min(max_pos),
+            )
+        };
+
+        for (channel, out_channel) in channels.iter().zip(out.iter_mut()) {
+            for (i, &gain) in window.iter().enumerate() {
+                out_channel[out_pos + i] += channel[pos + i] * gain;
+            }
+        }
+        for (i, &gain) in window.iter().enumerate() {
+            window_sum[out_pos + i] += gain;
+        }
+        prev_pos = pos;
+        out_pos += hop;
+    }
+
+    // Only the start of the first frame isn't covered by overlapping windows that sum to 1
+    for out_channel in &mut out {
+        for (sample, &sum) in out_channel.iter_mut().zip(window_sum.iter()) {
+            if sum > 1e-3 {
+                *sample /= sum;
+            }
+        }
+        out_channel.truncate(out_len);
+    }
+    out
+}
+
+/// Shifts the pitch of `channels` by `semitones` without changing their length by stretching them
+/// and then resampling them back down to their original length.
+pub fn pitch_shift(channels: &[Vec<f32>], sample_rate: u32, semitones: f32) -> Vec<Vec<f32>> {
+    let ratio = 2f64.powf(semitones as f64 / 12.);
+    time_stretch(channels, sample_rate, ratio)
+        .iter()
+        .zip(channels)
+        .map(|(stretched, channel)| {
+            let mut shifted = resample_by_ratio(stretched, 1. / ratio);
+            shifted.resize(channel.len(), 0.);
+            shifted
+        })
+        .collect()
+}
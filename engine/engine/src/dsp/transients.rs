@@ -0,0 +1,182 @@
+//! Detection of transients, the sudden onsets of drum hits and notes, in sampled audio.  This is
+//! what beat-slicing is built on: a loop is cut at each transient so that its hits can be played
+//! back individually.
+//!
+//! Transients are found with spectral flux, which is how much the magnitude spectrum grows from one
+//! frame to the next.  Peaks in the flux that stand out from its local average are taken as onsets,
+//! and each one is then refined to the exact sample where the attack starts since the frames are
+//! much too long to place slices by.
+
+use crate::{
+    dsp::fft::{fft_in_place, hann_window},
+    util::clamp,
+};
+
+const FRAME_LEN: usize = 1024;
+const HOP_LEN: usize = 256;
+/// Number of frames on either side of each frame that the flux is averaged over to compute the
+/// threshold that peaks have to reach
+const THRESHOLD_RADIUS: usize = 8;
+/// Length in samples of the blocks of the amplitude envelope used to refine the onset positions
+const ENVELOPE_BLOCK_LEN: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TransientDetectionConf {
+    /// In `[0, 1]`; higher values detect quieter and less sudden transients
+    pub sensitivity: f32,
+    /// Transients closer than this to the previous one are ignored
+    pub min_interval_seconds: f32,
+}
+
+impl Default for TransientDetectionConf {
+    fn default() -> Self {
+        TransientDetectionConf {
+            sensitivity: 0.5,
+            min_interval_seconds: 0.05,
+        }
+    }
+}
+
+/// Returns the magnitude spectrum of each frame, with frames centered `HOP_LEN` samples apart.
+/// Magnitudes are log-compressed so that quiet hits register alongside loud ones.
+fn magnitude_spectra(mono: &[f32]) -> Vec<Vec<f32>> {
+    let window = hann_window(FRAME_LEN);
+    let frame_count = mono.len() / HOP_LEN + 1;
+    let (mut re, mut im) = (vec![0.; FRAME_LEN], vec![0.; FRAME_LEN]);
+    (0..frame_count)
+        .map(|frame_ix| {
+            let center = (frame_ix * HOP_LEN) as isize;
+            for (i, (re, im)) in re.iter_mut().zip(im.iter_mut()).enumerate() {
+                let sample_ix = center - (FRAME_LEN / 2) as isize + i as isize;
+                let sample = if sample_ix >= 0 && (sample_ix as usize) < mono.len() {
+                    mono[sample_ix as usize]
+                } else {
+                    0.
+                };
+                *re = sample * window[i];
+                *im = 0.;
+            }
+            fft_in_place(&mut re, &mut im);
+            (0..FRAME_LEN / 2)
+                .map(|bin| (1. + 100. * re[bin].hypot(im[bin])).ln())
+                .collect()
+        })
+        .collect()
+}
+
+/// Returns the frames at which the flux peaks above the threshold set by `sensitivity`
+fn pick_peaks(flux: &[f32], sensitivity: f32) -> Vec<usize> {
+    let max_flux = flux.iter().cloned().fold(0.0f32, f32::max);
+    if max_flux <= 0. {
+        return Vec::new();
+    }
+
+    let delta = (1. - clamp(sensitivity, 0., 1.)) * 0.4 + 0.02;
+    (0..flux.len())
+        .filter(|&frame_ix| {
+            let neighborhood = frame_ix.saturating_sub(THRESHOLD_RADIUS)
+                ..(frame_ix + THRESHOLD_RADIUS + 1).min(flux.len());
+            let local_mean =
+                flux[neighborhood.clone()].iter().sum::<f32>() / neighborhood.len() as f32;
+            let val = flux[frame_ix];
+            let is_local_max = flux[frame_ix.saturating_sub(2)..(frame_ix + 3).min(flux.len())]
+                .iter()
+                .all(|&other| other <= val);
+            is_local_max && val / max_flux > local_mean / max_flux + delta
+        })
+        .collect()
+}
+
+/// Finds the sample where the attack of the transient detected in the frame centered at `center`
+/// starts.  The biggest jump in the amplitude envelope around the frame is found, and the onset is
+/// placed at the first sample leading up to it that rises above the level before the jump, backed
+/// up to the preceding zero crossing so that slicing there doesn't click.
+fn refine_onset(mono: &[f32], center: usize) -> usize {
+    let region_start = center.saturating_sub(FRAME_LEN / 2) / ENVELOPE_BLOCK_LEN;
+    let region_end = (center + FRAME_LEN / 2)
+        .min(mono.len())
+        .div_ceil(ENVELOPE_BLOCK_LEN);
+    // Blocks before the start of the audio are silent
+    let envelope = |block_ix: isize| -> f32 {
+        if block_ix < 0 {
+            return 0.;
+        }
+        let start = (block_ix as usize * ENVELOPE_BLOCK_LEN).min(mono.len());
+        let end = (start + ENVELOPE_BLOCK_LEN).min(mono.len());
+        mono[start..end]
+            .iter()
+            .fold(0.0f32, |acc, sample| acc.max(sample.abs()))
+    };
+
+    let jump = |block_ix: isize| envelope(block_ix) - envelope(block_ix - 1);
+    let jump_block = match (region_start as isize..region_end as isize)
+        .max_by(|&a, &b| jump(a).partial_cmp(&jump(b)).unwrap())
+    {
+        Some(block_ix) => block_ix,
+        None => return center.min(mono.len()),
+    };
+
+    let base = envelope(jump_block - 2);
+    let threshold = base + (envelope(jump_block) - base) * 0.25;
+    let jump_block = jump_block as usize;
+    let search_start = jump_block.saturating_sub(1) * ENVELOPE_BLOCK_LEN;
+    let search_end = ((jump_block + 1) * ENVELOPE_BLOCK_LEN).min(mono.len());
+    let onset = (search_start..search_end)
+        .find(|&i| mono[i].abs() >= threshold)
+        .unwrap_or(jump_block * ENVELOPE_BLOCK_LEN);
+
+    // Back up to the last zero crossing, but not by more than a block
+    let earliest = onset.saturating_sub(ENVELOPE_BLOCK_LEN);
+    (earliest + 1..=onset)
+        .rev()
+        .find(|&i| (mono[i - 1] <= 0.) != (mono[i] <= 0.))
+        .map(|i| i - 1)
+        .unwrap_or(onset)
+}
+
+/// Returns the frames at which transients start in `channels`, in ascending order
+pub fn detect_transients(
+    channels: &[Vec<f32>],
+    sample_rate: u32,
+    conf: &TransientDetectionConf,
+) -> Vec<usize> {
+    let len = channels.first().map(Vec::len).unwrap_or(0);
+    if len == 0 {
+        return Vec::new();
+    }
+    let mono: Vec<f32> = (0..len)
+        .map(|i| channels.iter().map(|channel| channel[i]).sum::<f32>() / channels.len() as f32)
+        .collect();
+
+    // The audio is preceded by silence so that a hit right at the start is detected
+    let spectra = magnitude_spectra(&mono);
+    let silence = vec![0.; FRAME_LEN / 2];
+    let mut flux: Vec<f32> = (0..spectra.len())
+        .map(|frame_ix| {
+            let prev = if frame_ix == 0 {
+                &silence
+            } else {
+                &spectra[frame_ix - 1]
+            };
+            spectra[frame_ix]
+                .iter()
+                .zip(prev.iter())
+                .map(|(cur, prev)| (cur - prev).max(0.))
+                .sum()
+        })
+        .collect();
+    // Frames that run past the end of the audio see it get cut off, which isn't a transient
+    flux.truncate(len.saturating_sub(FRAME_LEN / 2) / HOP_LEN + 1);
+
+    let min_interval = (conf.min_interval_seconds.max(0.) * sample_rate as f32) as usize;
+    let mut transients: Vec<usize> = Vec::new();
+    for frame_ix in pick_peaks(&flux, conf.sensitivity) {
+        let onset = refine_onset(&mono, frame_ix * HOP_LEN);
+        match transients.last() {
+            Some(&last) if onset < last + min_interval => (),
+            _ => transients.push(onset),
+        }
+    }
+    transients
+}
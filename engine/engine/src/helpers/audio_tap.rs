@@ -0,0 +1,14 @@
+//! Views such as the spectrum analyzer and oscilloscope analyze audio that is tapped out of the
+//! graph on the JS side and sent over in blocks.  This is where that audio is taken from.
+
+/// Used until the sample rate of the audio context is known
+pub const DEFAULT_SAMPLE_RATE: f32 = 44_100.;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TapLocation {
+    /// The master output, after the global volume
+    Master,
+    /// Whatever is connected to the view's input in the patch network
+    Input,
+}
@@ -1,3 +1,12 @@
 pub const NOTE_SKIP_LIST_LEVELS: usize = 5;
 pub const NOTES_SLAB_CAPACITY: usize = 32000;
 pub const NODES_SLAB_CAPACITY: usize = 32000;
+
+/// Bounds on the zoom factors that can be applied to the grid in either direction
+pub const MIN_GRID_ZOOM: f32 = 0.25;
+pub const MAX_GRID_ZOOM: f32 = 4.0;
+/// Factor by which the zoom is multiplied or divided for each zoom step
+pub const GRID_ZOOM_STEP: f32 = 1.25;
+/// How close to the edge of a note, in pixels, a click must be to start resizing it rather than
+/// dragging it
+pub const NOTE_RESIZE_HANDLE_WIDTH_PX: usize = 4;
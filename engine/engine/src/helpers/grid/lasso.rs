@@ -0,0 +1,81 @@
+//! Freeform selection of notes by drawing a shape around them.  The lasso is a polygon built from
+//! the points that the mouse moved through, which is implicitly closed by connecting its last
+//! point back to its first.
+
+use super::{note_box::NoteData, selection_box::SelectionRegion, GridConf};
+
+pub struct Lasso {
+    /// Points in beats horizontally and lines vertically, so the lasso keeps its place on the grid
+    /// if it's zoomed while drawing
+    points: Vec<(f32, f32)>,
+    /// `(min_x, min_y, max_x, max_y)` of all points
+    bounds: (f32, f32, f32, f32),
+}
+
+impl Lasso {
+    pub fn new(x: f32, y: f32) -> Self {
+        Lasso {
+            points: vec![(x, y)],
+            bounds: (x, y, x, y),
+        }
+    }
+
+    pub fn push_point(&mut self, x: f32, y: f32) {
+        if self.points.last() == Some(&(x, y)) {
+            return;
+        }
+
+        self.points.push((x, y));
+        let (min_x, min_y, max_x, max_y) = self.bounds;
+        self.bounds = (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y));
+    }
+
+    /// Returns the smallest region containing the whole lasso.  Since points are only ever added,
+    /// this only grows as the lasso is drawn.
+    pub fn bounding_region(&self) -> SelectionRegion<f32> {
+        let (min_x, min_y, max_x, max_y) = self.bounds;
+        SelectionRegion::from_points(min_x, min_y, max_x, max_y)
+    }
+
+    /// Returns `true` if the point is inside of the lasso, determined by counting how many of its
+    /// edges a ray cast from the point crosses.  Where the lasso crosses over itself, the overlap
+    /// is considered to be outside of it.
+    pub fn contains_point(&self, x: f32, y: f32) -> bool {
+        if self.points.len() < 3 {
+            return false;
+        }
+
+        let mut inside = false;
+        let mut prev = self.points[self.points.len() - 1];
+        for &cur in &self.points {
+            let ((x1, y1), (x2, y2)) = (prev, cur);
+            if (y1 > y) != (y2 > y) && x < x1 + (y - y1) / (y2 - y1) * (x2 - x1) {
+                inside = !inside;
+            }
+            prev = cur;
+        }
+        inside
+    }
+
+    /// Returns `true` if the center of the note's rect is inside of the lasso.  Using the center
+    /// rather than any part of the note lets the lasso be drawn across the edges of neighboring
+    /// notes without selecting them.
+    pub fn contains_note<S>(&self, conf: &GridConf, note_data: &NoteData<S>) -> bool {
+        let SelectionRegion {
+            x,
+            y,
+            width,
+            height,
+        } = note_data.get_selection_region(conf);
+        self.contains_point(x + width / 2., y + height / 2.)
+    }
+
+    /// Formats the points of the lasso in pixels for the `points` attribute of an SVG polygon
+    pub fn to_svg_points(&self, conf: &GridConf) -> String {
+        self.points
+            .iter()
+            .map(|&(x, y)| format!("{},{}", conf.beats_to_px(x), conf.lines_to_px(y)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
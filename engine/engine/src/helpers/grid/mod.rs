@@ -1,19 +1,32 @@
 use std::{f32, marker::PhantomData, mem, str};
 
+use common::tempo_map::TimeSignatureChange;
 use fnv::FnvHashSet;
 use uuid::Uuid;
 
 use super::super::prelude::*;
-use crate::view_context::create_empty_audio_connectables;
+use crate::{
+    helpers::{
+        keymap::{KeyAction, KeymapSection},
+        undo::UndoHistory,
+    },
+    view_context::create_empty_audio_connectables,
+};
 
 pub mod constants;
+pub mod lasso;
 pub mod note_box;
 pub mod prelude;
 pub mod render;
 pub mod selection_box;
 pub mod skip_list;
+pub mod touch;
 
-use self::{prelude::*, skip_list::NoteLines};
+use self::{
+    prelude::*,
+    skip_list::NoteLines,
+    touch::{TouchAction, Touches},
+};
 
 pub type DomId = usize;
 
@@ -31,8 +44,71 @@ pub enum Tool {
     DrawNote,
     /// Any note clicked on will be deleted
     DeleteNote,
+    /// Any note clicked on will be cut in two at the clicked beat
+    SplitNote,
+    /// Any note clicked on will be merged with the note following it on the same line
+    JoinNote,
+    /// Clicking on an empty space inserts a chord of notes on the lines given by the handler
+    InsertChord,
+    /// Dragging draws a freeform shape, selecting the notes inside of it
+    Lasso,
 }
 
+/// One of the edges of a note, used to keep track of which one is being dragged while resizing
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum NoteEdge {
+    Start,
+    End,
+}
+
+/// Actions handled by every grid.  Keys that aren't bound to any of them are passed on to the
+/// grid's handler.
+pub static GRID_KEYMAP: KeymapSection = KeymapSection {
+    name: "grid",
+    actions: &[
+        KeyAction {
+            name: "delete_selected_notes",
+            description: "Delete the selected notes",
+            default_keys: &["Backspace", "Delete"],
+        },
+        KeyAction {
+            name: "copy_selected_notes",
+            description: "Copy the selected notes, placing the copies after them",
+            default_keys: &["p"],
+        },
+        KeyAction {
+            name: "select_lines_of_selection",
+            description: "Select every note on the lines of the selected notes",
+            default_keys: &["e"],
+        },
+        KeyAction {
+            name: "select_after_cursor",
+            description: "Select every note after the cursor",
+            default_keys: &["End"],
+        },
+        KeyAction {
+            name: "zoom_in",
+            description: "Zoom in horizontally",
+            default_keys: &["="],
+        },
+        KeyAction {
+            name: "zoom_out",
+            description: "Zoom out horizontally",
+            default_keys: &["-"],
+        },
+        KeyAction {
+            name: "zoom_in_vertical",
+            description: "Zoom in vertically",
+            default_keys: &["+"],
+        },
+        KeyAction {
+            name: "zoom_out_vertical",
+            description: "Zoom out vertically",
+            default_keys: &["_"],
+        },
+    ],
+};
+
 pub trait GridRenderer<S: GridRendererUniqueIdentifier> {
     /// Draws a note on the canvas and returns its DOM id.
     fn create_note(
@@ -77,6 +153,17 @@ pub trait GridRenderer<S: GridRendererUniqueIdentifier> {
         js::set_attr(dom_id, "x1", &x.to_string());
         js::set_attr(dom_id, "x2", &x.to_string());
     }
+
+    /// Set the position and size of an already-rendered note
+    fn set_note_bounds(dom_id: DomId, x: usize, y: usize, width: usize, height: usize) {
+        js::set_attr(dom_id, "x", &x.to_string());
+        js::set_attr(dom_id, "y", &y.to_string());
+        js::set_attr(dom_id, "width", &width.to_string());
+        js::set_attr(dom_id, "height", &height.to_string());
+    }
+
+    /// Update the visualization of an already-rendered note to reflect its velocity
+    fn set_note_velocity(_dom_id: DomId, _velocity: u8) {}
 }
 
 pub trait GridHandler<S: GridRendererUniqueIdentifier, R: GridRenderer<S>> {
@@ -87,6 +174,27 @@ pub trait GridHandler<S: GridRendererUniqueIdentifier, R: GridRenderer<S>> {
     fn hide(&mut self, _vc_id: &str) {}
     fn unhide(&mut self, _vc_id: &str) {}
 
+    /// Called after the viewport of the grid changes size, which is stored in the `GridState`
+    fn on_resize(&mut self, _grid_state: &mut GridState<S>) {}
+
+    /// Called once the grid's background has been rendered so that the handler can style its rows
+    fn on_background_render(&mut self, _grid_state: &mut GridState<S>) {}
+
+    /// Returns the line on which a note should be drawn when the mouse is pressed on `line_ix`.
+    /// This allows handlers to restrict the lines that new notes can be drawn on.
+    fn get_draw_line(&self, _grid_state: &GridState<S>, line_ix: usize) -> usize { line_ix }
+
+    /// Returns the lines of the notes that make up a chord inserted by clicking on `line_ix` with
+    /// the `InsertChord` tool.  If any of them are outside of the grid, no chord is inserted.
+    fn get_chord_lines(&self, _grid_state: &GridState<S>, line_ix: usize) -> Vec<usize> {
+        vec![line_ix]
+    }
+
+    /// If `true`, notes are drawn as hits one snap interval long with a single click rather than
+    /// by dragging, and notes can't be resized by dragging their edges.  This is used for parts
+    /// where the lengths of notes don't matter, like drums.
+    fn draws_hits(&self) -> bool { false }
+
     fn on_note_select(&mut self, _data: &S) {}
 
     fn on_note_click(
@@ -101,6 +209,21 @@ pub trait GridHandler<S: GridRendererUniqueIdentifier, R: GridRenderer<S>> {
 
     fn on_note_deleted(&mut self, _dom_id: DomId) {}
 
+    /// Returns the keymap sections of the actions handled in `on_key_down`
+    fn get_keymap_sections(&self) -> Vec<&'static KeymapSection> { Vec::new() }
+
+    /// Called before the grid handles a key press.  Returning `true` keeps the key from being
+    /// handled any further, which lets handlers take over keys while they're in a special mode.
+    fn capture_key_down(
+        &mut self,
+        _state: &mut GridState<S>,
+        _key: &str,
+        _control_pressed: bool,
+        _shift_pressed: bool,
+    ) -> bool {
+        false
+    }
+
     fn on_key_down(
         &mut self,
         _state: &mut GridState<S>,
@@ -121,17 +244,55 @@ pub trait GridHandler<S: GridRendererUniqueIdentifier, R: GridRenderer<S>> {
 
     fn on_mouse_down(&mut self, _state: &mut GridState<S>, _x: usize, _y: usize) {}
 
+    /// Called when a region of the cursor gutter is selected by dragging across it while holding
+    /// control.  `start_beat` is always less than `end_beat`.
+    fn on_cursor_gutter_region_select(
+        &mut self,
+        _grid_state: &mut GridState<S>,
+        _start_beat: f32,
+        _end_beat: f32,
+    ) {
+    }
+
+    /// Called when the mouse is pressed in the lane area beneath the grid's rows.  `y` is relative
+    /// to the bottom of the grid.  Returning `true` captures the mouse so that the following moves
+    /// and mouse up are sent to the lane area handlers as well.
+    fn on_lane_area_mouse_down(
+        &mut self,
+        _grid_state: &mut GridState<S>,
+        _x: usize,
+        _y: usize,
+    ) -> bool {
+        false
+    }
+
+    fn on_lane_area_mouse_move(&mut self, _grid_state: &mut GridState<S>, _x: usize, _y: usize) {}
+
+    fn on_lane_area_mouse_up(&mut self, _grid_state: &mut GridState<S>, _x: usize, _y: usize) {}
+
+    /// Height in pixels of the lane area beneath the grid's rows, which is scrolled along with them
+    fn lane_area_height(&self) -> usize { 0 }
+
+    /// Called every time that the selection box is changed while it's being dragged.  Use
+    /// `selection_box::iter_selection_changes` to find the notes that should be (de)selected.
     fn on_selection_region_update(
         &mut self,
         _grid: &mut GridState<S>,
-        _retained_region: &Option<SelectionRegion>,
-        _changed_region_1: &ChangedRegion,
-        _changed_region_2: &ChangedRegion,
+        _selection_box: &SelectionBoxData,
     ) {
     }
 
     fn on_selection_box_deleted(&mut self, _grid: &mut GridState<S>) {}
 
+    /// Called after the grid's elements have been repositioned due to a change in zoom so that
+    /// the handler can update any elements that it has rendered itself.
+    fn on_rerender(&mut self, _grid_state: &mut GridState<S>) {}
+
+    /// Called once the grid's notes have been loaded and after each mouse, key, or message event
+    /// handled by the grid, any of which may have changed the notes or the selection.  Handlers can
+    /// use this to update elements that they render from the notes.
+    fn on_notes_changed(&mut self, _grid_state: &mut GridState<S>) {}
+
     fn create_note(
         &mut self,
         grid_state: &mut GridState<S>,
@@ -193,7 +354,21 @@ pub trait GridHandler<S: GridRendererUniqueIdentifier, R: GridRenderer<S>> {
         create_empty_audio_connectables(&uuid.to_string())
     }
 
-    fn save(&self) -> String { "".into() }
+    fn save(&self, _grid_state: &GridState<S>) -> String { "".into() }
+}
+
+/// A serializable snapshot of a grid's notes and view settings which can be used to rebuild it
+/// with `Grid::load`.
+#[derive(Serialize, Deserialize)]
+pub struct SerializedGridState {
+    pub notes: Vec<RawNoteData>,
+    /// Indices into `notes` of the notes that are selected
+    pub selected_note_ixs: Vec<usize>,
+    pub cursor_pos_beats: f32,
+    pub scroll_offset_beats: f32,
+    pub zoom_x: f32,
+    pub zoom_y: f32,
+    pub note_snap_beat_interval: f32,
 }
 
 pub struct GridState<S> {
@@ -201,52 +376,235 @@ pub struct GridState<S> {
     pub data: NoteLines<S>,
     pub selected_notes: FnvHashSet<SelectedNoteData>,
     pub cursor_pos_beats: f32,
+    /// The beat at the left edge of the visible window of the grid
+    pub scroll_offset_beats: f32,
     pub mouse_down: bool,
     pub cursor_moving: bool,
     pub mouse_down_x: usize,
     pub mouse_down_y: usize,
     pub shift_pressed: bool,
     pub control_pressed: bool,
+    /// Snapping is temporarily disabled while alt is held
+    pub snap_bypassed: bool,
     pub cur_note_bounds: (f32, Option<f32>),
     pub cur_tool: Tool,
     pub mouse_x: usize,
     pub mouse_y: usize,
     pub drawing_note_dom_id: Option<usize>,
+    /// The pen generating the current mouse event, if any
+    pub pen: Option<PenState>,
+    /// The highest pressure of the pen drawing the note being drawn, which sets its velocity.  The
+    /// pressure is lowest when the pen is first touched down and lifted, so using the peak keeps
+    /// the velocity from depending on how the note was started or finished.
+    pub drawing_note_peak_pressure: Option<f32>,
     /// (original_dragging_note_start_beat, SelectedNoteData)
     pub dragging_note_data: Option<(f32, SelectedNoteData)>,
+    /// (edge being dragged, SelectedNoteData)
+    pub resizing_note_data: Option<(NoteEdge, SelectedNoteData)>,
     pub selection_box_dom_id: Option<usize>,
+    /// How the selection box or lasso being drawn, if any, changes the selection
+    pub selection_box_mode: SelectionBoxMode,
+    /// The notes that were selected when the selection box or lasso being drawn was started.
+    /// Whether notes are selected while it's being drawn is determined relative to these.
+    pub selection_before_box: FnvHashSet<NoteId>,
+    /// The lasso being drawn, if any, along with the DOM ID of its rendered polygon
+    pub lasso: Option<(Lasso, DomId)>,
+    /// `(start_beat, dom_id)` of the region being selected in the cursor gutter, if any
+    pub cursor_gutter_region: Option<(f32, DomId)>,
+    /// Set while the mouse has been captured by the handler's lane area
+    pub lane_area_mouse_down: bool,
+    pub touches: Touches,
     // TODO: Make this something better, like mapping dom_id to line index and start beat or sth.
     pub cursor_dom_id: usize,
+    pub background: render::GridBackground,
+    /// Notes outside of the background's beat range that were hidden when the zoom changed rather
+    /// than being repositioned.  They're repositioned and shown once they're scrolled into view.
+    pub culled_notes: FnvHashSet<DomId>,
     pub playback_active: bool,
+    /// Snapshots of the grid's notes from before each undoable edit
+    pub note_history: UndoHistory<Vec<RawNoteData>>,
+    /// Size in pixels of the area of the page in which the grid is displayed
+    pub viewport_width: usize,
+    pub viewport_height: usize,
+    /// Set while the grid is the active view and the page isn't hidden.  Animations such as the
+    /// playback cursor don't need to be rendered while this is unset.
+    pub visible: bool,
 }
 
 impl<S: GridRendererUniqueIdentifier> GridState<S> {
     fn new(conf: GridConf) -> Self {
         let row_count = conf.row_count;
+        let (viewport_width, viewport_height) = (conf.grid_width, conf.grid_height());
 
         Self {
             conf,
             data: NoteLines::new(row_count),
             selected_notes: FnvHashSet::default(),
             cursor_pos_beats: 0.0,
+            scroll_offset_beats: 0.0,
             mouse_down: false,
             cursor_moving: false,
             mouse_down_x: 0,
             mouse_down_y: 0,
             shift_pressed: false,
             control_pressed: false,
+            snap_bypassed: false,
             cur_note_bounds: (0., None),
             cur_tool: Tool::DrawNote,
             mouse_x: 0,
             mouse_y: 0,
             drawing_note_dom_id: None,
+            pen: None,
+            drawing_note_peak_pressure: None,
             dragging_note_data: None,
+            resizing_note_data: None,
             selection_box_dom_id: None,
+            selection_box_mode: SelectionBoxMode::Add,
+            selection_before_box: FnvHashSet::default(),
+            lasso: None,
+            cursor_gutter_region: None,
+            lane_area_mouse_down: false,
+            touches: Touches::default(),
             cursor_dom_id: 0,
+            background: render::GridBackground::default(),
+            culled_notes: FnvHashSet::default(),
             playback_active: false,
+            note_history: UndoHistory::default(),
+            viewport_width,
+            viewport_height,
+            visible: true,
         }
     }
 
+    pub fn scroll_offset_px(&self) -> usize { self.conf.beats_to_px(self.scroll_offset_beats) }
+
+    /// Converts the pixel `x` in the grid's coordinates into beats.  Mouse positions are converted
+    /// into the grid's coordinates by adding `scroll_offset_px`, which is rounded down to a whole
+    /// pixel, so the exact scroll offset is used here instead.
+    pub fn grid_px_to_beat(&self, x: usize) -> f32 {
+        let visible_x = x as isize - self.scroll_offset_px() as isize;
+        self.scroll_offset_beats + self.conf.px_to_beat(visible_x)
+    }
+
+    /// Returns the beat on the snap interval closest to the pixel `x` in the grid's coordinates
+    pub fn snap_to_beat_interval(&self, x: usize) -> f32 {
+        let snap_interval = self.snap_beat_interval();
+        (self.grid_px_to_beat(x) / snap_interval).round() * snap_interval
+    }
+
+    /// Returns the `(start_beat, end_beat)` of the part of the grid that fits in the viewport
+    pub fn get_visible_beat_range(&self) -> (f32, f32) {
+        let visible_beats = self.conf.px_to_beat(self.viewport_width);
+        (
+            self.scroll_offset_beats,
+            self.scroll_offset_beats + visible_beats,
+        )
+    }
+
+    /// Returns the `(start_beat, end_beat)` of the part of the grid that should be rendered for the
+    /// current visible window.  A viewport's width is rendered on either side of it so that the
+    /// grid can be scrolled by that much without re-rendering anything.
+    pub fn get_render_beat_range(&self) -> (f32, f32) {
+        let (start_beat, end_beat) = self.get_visible_beat_range();
+        let margin = end_beat - start_beat;
+        ((start_beat - margin).max(0.), end_beat + margin)
+    }
+
+    /// Returns the interval in beats to which notes should currently be snapped.  If snapping is
+    /// disabled or bypassed, this is the width of a single pixel so that notes can be placed
+    /// anywhere.
+    pub fn snap_beat_interval(&self) -> f32 {
+        if self.snap_bypassed || self.conf.note_snap_beat_interval <= 0. {
+            self.conf.px_to_beat(1usize)
+        } else {
+            self.conf.note_snap_beat_interval
+        }
+    }
+
+    /// Returns whether the note with the ID `note_id` should be selected while a selection box is
+    /// being drawn given whether it's inside of the box
+    /// Moves the rendered element of a note to the provided position, showing it again if it was
+    /// culled
+    pub fn position_note<R: GridRenderer<S>>(
+        &mut self,
+        dom_id: DomId,
+        line_ix: usize,
+        start_beat: f32,
+        width: f32,
+    ) {
+        R::set_note_bounds(
+            dom_id,
+            self.conf.beats_to_px(start_beat),
+            self.conf.cursor_gutter_height + self.conf.padded_line_height() * line_ix,
+            self.conf.beats_to_px(width),
+            self.conf.zoomed_line_height(),
+        );
+        if self.culled_notes.remove(&dom_id) {
+            js::remove_class(dom_id, "culled");
+        }
+    }
+
+    pub fn is_selected_by_box(&self, note_id: NoteId, in_box: bool) -> bool {
+        self.selection_box_mode
+            .is_selected(in_box, self.selection_before_box.contains(&note_id))
+    }
+
+    /// Prepares for a new selection box to be drawn, adding notes to the current selection or
+    /// removing them from it if alt is held
+    fn start_selection_box(&mut self) {
+        // Alt bypasses snapping as well, but snapping doesn't apply to selection boxes
+        self.selection_box_mode = if self.snap_bypassed {
+            SelectionBoxMode::Subtract
+        } else {
+            SelectionBoxMode::Add
+        };
+        self.selection_before_box = self.selected_notes.iter().map(|note| note.note_id).collect();
+    }
+
+    /// Selects the provided notes, replacing the current selection unless shift is held
+    pub fn select_notes<R: GridRenderer<S>>(
+        &mut self,
+        notes: impl IntoIterator<Item = SelectedNoteData>,
+    ) {
+        if !self.shift_pressed {
+            for note_data in self.selected_notes.drain() {
+                R::deselect_note(note_data.dom_id);
+            }
+        }
+
+        for note_data in notes {
+            if self.selected_notes.insert(note_data) {
+                R::select_note(note_data.dom_id);
+            }
+        }
+    }
+
+    fn get_line_notes(&self, line_ix: usize) -> Vec<SelectedNoteData> {
+        match self.data.lines.get(line_ix) {
+            Some(line) => line
+                .iter()
+                .map(|note| SelectedNoteData::from_note_box(line_ix, note))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Selects every note on the line with index `line_ix`
+    pub fn select_line<R: GridRenderer<S>>(&mut self, line_ix: usize) {
+        let notes = self.get_line_notes(line_ix);
+        self.select_notes::<R>(notes);
+    }
+
+    /// Selects every note that starts at or after `start_beat` and before `end_beat`
+    pub fn select_beat_range<R: GridRenderer<S>>(&mut self, start_beat: f32, end_beat: f32) {
+        let notes: Vec<SelectedNoteData> = self
+            .data
+            .iter_starting_between(start_beat, end_beat)
+            .map(|note_data| SelectedNoteData::from_note_box(note_data.line_ix, note_data.note_box))
+            .collect();
+        self.select_notes::<R>(notes);
+    }
+
     pub fn get_sorted_selected_notes<'a>(
         &'a self,
         sort_reverse: bool,
@@ -262,26 +620,101 @@ impl<S: GridRendererUniqueIdentifier> GridState<S> {
         notes
     }
 
+    /// Changes the number of rows in the grid, moving the notes on each row to the row returned
+    /// by `remap_line` for it.  The notes on rows that it returns `None` for are deleted.  The
+    /// selection and the note edit history refer to the old rows, so they're cleared.
+    pub fn set_row_count<R: GridRenderer<S>>(
+        &mut self,
+        row_count: usize,
+        remap_line: impl Fn(usize) -> Option<usize>,
+    ) {
+        for note_data in self.selected_notes.drain() {
+            R::deselect_note(note_data.dom_id);
+        }
+        for line in self.data.remap_lines(row_count, remap_line) {
+            for note in line.iter() {
+                js::delete_element(note.data.get_id());
+            }
+        }
+        self.conf.row_count = row_count;
+        self.note_history = UndoHistory::default();
+
+        for grid_line_dom_id in self.background.grid_lines.drain(..) {
+            js::delete_element(grid_line_dom_id);
+        }
+        let beat_range = self.background.beat_range;
+        let px_range = self.background.px_range(&self.conf);
+        self.background.grid_lines = render::draw_grid(&self.conf, px_range);
+        render::update_grid_background(&self.conf, &mut self.background, beat_range);
+        let notes: Vec<(DomId, usize, NoteBoxBounds)> = self
+            .data
+            .iter()
+            .map(|note_data| {
+                let note_box = note_data.note_box;
+                (note_box.data.get_id(), note_data.line_ix, note_box.bounds)
+            })
+            .collect();
+        for (dom_id, line_ix, bounds) in notes {
+            self.position_note::<R>(dom_id, line_ix, bounds.start_beat, bounds.width());
+        }
+        js::set_attr(self.cursor_dom_id, "y2", &self.conf.grid_height().to_string());
+    }
+
     pub fn get_raw_note_data(&self) -> Vec<RawNoteData> {
         self.data
-            .lines
-            .iter()
-            .enumerate()
-            .flat_map(|(line_ix, line)| {
-                line.iter().map(move |note_box| RawNoteData {
-                    line_ix,
-                    start_beat: note_box.bounds.start_beat,
-                    width: note_box.bounds.width(),
-                })
+            .iter_all()
+            .map(|(line_ix, note_box)| RawNoteData {
+                line_ix,
+                start_beat: note_box.bounds.start_beat,
+                width: note_box.bounds.width(),
+                velocity: note_box.velocity,
+                pitch_bend: note_box.pitch_bend.clone(),
+                expression: note_box.expression.clone(),
             })
             .collect()
     }
 
+    /// Records the notes as they are before an edit so that it can be undone
+    pub fn record_note_edit(&mut self) {
+        let notes = self.get_raw_note_data();
+        self.note_history.record(&notes);
+    }
+
     pub fn serialize_to_binary(&self) -> Vec<u8> {
         let all_notes: Vec<RawNoteData> = self.get_raw_note_data();
 
         bincode::serialize(&all_notes).expect("Failed to serialize raw note data into binary")
     }
+
+    pub fn serialize(&self) -> SerializedGridState {
+        let selected_dom_ids: FnvHashSet<DomId> =
+            self.selected_notes.iter().map(|note| note.dom_id).collect();
+        let mut notes = Vec::new();
+        let mut selected_note_ixs = Vec::new();
+        for (line_ix, note_box) in self.data.iter_all() {
+            if selected_dom_ids.contains(&note_box.data.get_id()) {
+                selected_note_ixs.push(notes.len());
+            }
+            notes.push(RawNoteData {
+                line_ix,
+                start_beat: note_box.bounds.start_beat,
+                width: note_box.bounds.width(),
+                velocity: note_box.velocity,
+                pitch_bend: note_box.pitch_bend.clone(),
+                expression: note_box.expression.clone(),
+            });
+        }
+
+        SerializedGridState {
+            notes,
+            selected_note_ixs,
+            cursor_pos_beats: self.cursor_pos_beats,
+            scroll_offset_beats: self.scroll_offset_beats,
+            zoom_x: self.conf.zoom_x,
+            zoom_y: self.conf.zoom_y,
+            note_snap_beat_interval: self.conf.note_snap_beat_interval,
+        }
+    }
 }
 
 /// `Grid` is a view context that consists of a set of horizontal rows in which segments, currently
@@ -291,8 +724,8 @@ impl<S: GridRendererUniqueIdentifier> GridState<S> {
 ///
 /// The `GridHandler` has the job of implementing custom grid logic.  For the MIDI editor, this
 /// includes things like playing the synth when notes are drawn, allowing note movement between
-/// different levels, etc.  For the `ClipCompositor`, this includes switching the view to the
-/// MIDI editor for a specific track when it's double clicked.
+/// different levels, etc.  For the `ClipCompositor`, this includes keeping track of which clip each
+/// of its notes is an instance of.
 ///
 /// Finally, it has a `GridRenderer` which is just a bunch of type-level functions that are used
 /// to render custom versions of the individual elements of the grid.
@@ -301,6 +734,8 @@ pub struct Grid<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHand
     pub state: GridState<S>,
     pub handler: H,
     pub loaded: bool,
+    /// State passed to `Grid::load` that is rendered once the grid is initialized
+    saved_state: Option<SerializedGridState>,
     renderer: PhantomData<R>,
 }
 
@@ -309,12 +744,22 @@ pub struct GridConf {
     pub row_count: usize,
     pub gutter_height: usize,
     pub beat_length_px: usize,
+    /// The interval in beats to which notes are snapped when drawing or moving them.  If this is
+    /// zero, snapping is disabled.
     pub note_snap_beat_interval: f32,
     pub cursor_gutter_height: usize,
     pub line_border_width: usize,
     pub line_height: usize,
+    /// Width in pixels of the visible part of the grid until its size is set by `handle_resize`.
+    /// The grid itself extends indefinitely to the right.
     pub grid_width: usize,
     pub measure_width_px: usize,
+    /// Horizontal zoom factor which scales the width of beats
+    pub zoom_x: f32,
+    /// Vertical zoom factor which scales the height of lines
+    pub zoom_y: f32,
+    /// Determines where the measure and beat lines are drawn.  If empty, the grid is drawn in 4/4.
+    pub time_signature_changes: Vec<TimeSignatureChange>,
 }
 
 /// Helper trait that allows converting pixel units to beats generically
@@ -331,76 +776,56 @@ impl PxUnit for isize {
 }
 
 impl GridConf {
-    pub fn padded_line_height(&self) -> usize { self.line_height + self.line_border_width }
+    pub fn zoomed_beat_length_px(&self) -> f32 { self.beat_length_px as f32 * self.zoom_x }
+
+    pub fn zoomed_line_height(&self) -> usize {
+        ((self.line_height as f32 * self.zoom_y).round() as usize).max(1)
+    }
+
+    pub fn zoomed_measure_width_px(&self) -> usize {
+        (self.measure_width_px as f32 * self.zoom_x).round() as usize
+    }
+
+    pub fn padded_line_height(&self) -> usize {
+        self.zoomed_line_height() + self.line_border_width
+    }
 
     pub fn grid_height(&self) -> usize {
         self.row_count * self.padded_line_height() + self.cursor_gutter_height
     }
 
+    /// Returns the index of the line at `y_px`, or `None` if it's in the cursor gutter or beneath
+    /// the last line
     pub fn get_line_index(&self, y_px: usize) -> Option<usize> {
-        if y_px > self.cursor_gutter_height {
-            Some(
-                ((y_px - self.cursor_gutter_height) as f32 / (self.padded_line_height() as f32))
-                    .trunc() as usize,
-            )
+        if y_px <= self.cursor_gutter_height {
+            return None;
+        }
+
+        let line_ix = ((y_px - self.cursor_gutter_height) as f32
+            / (self.padded_line_height() as f32))
+            .trunc() as usize;
+        if line_ix < self.row_count {
+            Some(line_ix)
         } else {
             None
         }
     }
 
-    pub fn px_to_beat<T: PxUnit>(&self, px: T) -> f32 { px.to_f32() / (self.beat_length_px as f32) }
+    pub fn px_to_beat<T: PxUnit>(&self, px: T) -> f32 { px.to_f32() / self.zoomed_beat_length_px() }
 
-    pub fn beats_to_px(&self, beats: f32) -> usize { (beats * self.beat_length_px as f32) as usize }
-}
-
-fn try_insert<S: GridRendererUniqueIdentifier>(
-    data: &mut NoteLines<S>,
-    mut note: NoteBox<S>,
-    line_ix: usize,
-    start_beat: f32,
-    dragging_note: &mut SelectedNoteData,
-) -> Option<NoteBox<S>> {
-    note.bounds.end_beat = start_beat + note.bounds.width();
-    note.bounds.start_beat = start_beat;
-    trace!(
-        "Trying to insert {:?} at line_ix: {}, start_beat: {}",
-        note,
-        line_ix,
-        start_beat
-    );
-    let insertion_error = data.insert(line_ix, note);
-    if insertion_error.is_none() {
-        trace!("success!");
-        trace!("{:?}", data.lines[line_ix]);
-        dragging_note.start_beat = start_beat;
-        dragging_note.line_ix = line_ix;
-    } else {
-        trace!("failed");
-    }
-    insertion_error
-}
+    pub fn beats_to_px(&self, beats: f32) -> usize {
+        (beats * self.zoomed_beat_length_px()) as usize
+    }
 
-enum InsertionAttemptResult<S> {
-    Inserted { line_ix: usize, start_beat: f32 },
-    Failed(NoteBox<S>),
-}
+    /// Converts a y position in pixels to lines from the top of the first line.  Positions in the
+    /// cursor gutter are negative.
+    pub fn px_to_line(&self, y_px: usize) -> f32 {
+        (y_px as f32 - self.cursor_gutter_height as f32) / self.padded_line_height() as f32
+    }
 
-fn try_insert_many<S: GridRendererUniqueIdentifier>(
-    data: &mut NoteLines<S>,
-    note: NoteBox<S>,
-    positions: &[(usize, f32)],
-    dragging_note: &mut SelectedNoteData,
-) -> InsertionAttemptResult<S> {
-    if let Some(&(line_ix, start_beat)) = positions.first() {
-        match try_insert(data, note, line_ix, start_beat, dragging_note) {
-            Some(note) => try_insert_many(data, note, &positions[1..], dragging_note),
-            None => InsertionAttemptResult::Inserted {
-                line_ix,
-                start_beat,
-            },
-        }
-    } else {
-        InsertionAttemptResult::Failed(note)
+    /// The inverse of `px_to_line`
+    pub fn lines_to_px(&self, lines: f32) -> f32 {
+        lines * self.padded_line_height() as f32 + self.cursor_gutter_height as f32
     }
 }
 
@@ -411,10 +836,279 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
             state: GridState::new(conf),
             handler,
             loaded: false,
+            saved_state: None,
             renderer: PhantomData,
         }
     }
 
+    /// Creates a grid from a snapshot previously created by `GridState::serialize`.  The view
+    /// settings are restored immediately and the notes are rendered and inserted into the skip
+    /// lists once the grid is initialized.
+    pub fn load(
+        mut conf: GridConf,
+        handler: H,
+        uuid: Uuid,
+        saved_state: SerializedGridState,
+    ) -> Self {
+        conf.zoom_x = clamp(saved_state.zoom_x, MIN_GRID_ZOOM, MAX_GRID_ZOOM);
+        conf.zoom_y = clamp(saved_state.zoom_y, MIN_GRID_ZOOM, MAX_GRID_ZOOM);
+        conf.note_snap_beat_interval = saved_state.note_snap_beat_interval.max(0.);
+
+        let mut grid = Grid::new(conf, handler, uuid);
+        grid.state.scroll_offset_beats = saved_state.scroll_offset_beats.max(0.);
+        grid.saved_state = Some(saved_state);
+        grid
+    }
+
+    /// Moves all selected notes by `line_diff` lines and `beat_diff` beats as a group, updating
+    /// their rendered positions and the dragging note if there is one.  If any of the notes would
+    /// be moved outside of the grid or collide with a note that isn't selected, none of them are
+    /// moved.  Returns `true` if the notes were moved.
+    pub fn move_selected_notes(&mut self, line_diff: isize, beat_diff: f32) -> bool {
+        let line_count = self.state.data.lines.len() as isize;
+        let mut moves: Vec<(usize, f32, usize, f32)> =
+            Vec::with_capacity(self.state.selected_notes.len());
+        for note in &self.state.selected_notes {
+            let new_line_ix = note.line_ix as isize + line_diff;
+            let new_start_beat = note.start_beat + beat_diff;
+            if new_line_ix < 0 || new_line_ix >= line_count || new_start_beat < 0. {
+                return false;
+            }
+
+            moves.push((note.line_ix, note.start_beat, new_line_ix as usize, new_start_beat));
+        }
+
+        if self.state.data.move_notes(&moves).is_err() {
+            return false;
+        }
+
+        let moved_notes: Vec<SelectedNoteData> = self
+            .state
+            .selected_notes
+            .drain()
+            .map(|note| SelectedNoteData {
+                line_ix: (note.line_ix as isize + line_diff) as usize,
+                start_beat: note.start_beat + beat_diff,
+                ..note
+            })
+            .collect();
+        for note in moved_notes {
+            self.state
+                .position_note::<R>(note.dom_id, note.line_ix, note.start_beat, note.width);
+            if let Some((_, ref mut dragging_note)) = self.state.dragging_note_data {
+                if dragging_note.dom_id == note.dom_id {
+                    *dragging_note = note;
+                }
+            }
+            self.state.selected_notes.insert(note);
+        }
+
+        true
+    }
+
+    /// Returns the edge of the provided note that the pixel `x` is over the resize handle of, if
+    /// any.  Notes that are too narrow to leave room to grab them between the handles don't have
+    /// any.
+    fn get_note_edge(&self, note: &SelectedNoteData, x: usize) -> Option<NoteEdge> {
+        let start_px = self.state.conf.beats_to_px(note.start_beat);
+        let end_px = self.state.conf.beats_to_px(note.start_beat + note.width);
+        if end_px - start_px < NOTE_RESIZE_HANDLE_WIDTH_PX * 3 {
+            return None;
+        }
+
+        let (start_distance, end_distance) = (x.saturating_sub(start_px), end_px.saturating_sub(x));
+        if start_distance.min(end_distance) > NOTE_RESIZE_HANDLE_WIDTH_PX {
+            return None;
+        }
+
+        Some(tern(start_distance < end_distance, NoteEdge::Start, NoteEdge::End))
+    }
+
+    /// Moves the provided edge of the note being resized to the snap interval closest to the pixel
+    /// `x`, clamping it so that it doesn't overlap neighboring notes or shrink the note to nothing.
+    fn resize_note(&mut self, edge: NoteEdge, note: SelectedNoteData, x: usize) {
+        let snap_interval = self.state.snap_beat_interval();
+        let snapped_beat = self.state.snap_to_beat_interval(x);
+        let end_beat = note.start_beat + note.width;
+        let (new_start_beat, new_end_beat) = match edge {
+            NoteEdge::Start => (snapped_beat.min(end_beat - snap_interval), end_beat),
+            NoteEdge::End => (note.start_beat, snapped_beat.max(note.start_beat + snap_interval)),
+        };
+        if new_start_beat == note.start_beat && new_end_beat == end_beat {
+            return;
+        }
+
+        let new_bounds = match self.state.data.lines[note.line_ix].resize_note(
+            note.start_beat,
+            new_start_beat,
+            new_end_beat,
+        ) {
+            Some(bounds) => bounds,
+            None => {
+                error!("Tried to resize a note that doesn't exist: {:?}", note);
+                self.state.resizing_note_data = None;
+                return;
+            },
+        };
+
+        let resized_note = SelectedNoteData {
+            start_beat: new_bounds.start_beat,
+            width: new_bounds.width(),
+            ..note
+        };
+        // `SelectedNoteData` is hashed by its `DomId`, so this replaces the old entry
+        self.state.selected_notes.replace(resized_note);
+        self.state.resizing_note_data = Some((edge, resized_note));
+        self.state.position_note::<R>(
+            note.dom_id,
+            note.line_ix,
+            resized_note.start_beat,
+            resized_note.width,
+        );
+    }
+
+    /// Cuts the provided note in two at the snap interval closest to the pixel `x`, rendering the
+    /// newly created second half.  Nothing happens if that's at or outside of the note's edges.
+    fn split_note(&mut self, note: SelectedNoteData, x: usize) {
+        let split_beat = self.state.snap_to_beat_interval(x);
+        let end_beat = note.start_beat + note.width;
+        if split_beat <= note.start_beat || split_beat >= end_beat {
+            return;
+        }
+
+        let dom_id = self.render_note(note.line_ix, split_beat, end_beat - split_beat);
+        R::set_note_velocity(dom_id, note.velocity);
+        let note_state =
+            self.handler
+                .create_note(&mut self.state, note.line_ix, split_beat, dom_id);
+        if self.state.data.lines[note.line_ix]
+            .split_at(split_beat, note_state)
+            .is_none()
+        {
+            error!("Tried to split a note that doesn't exist: {:?}", note);
+            js::delete_element(dom_id);
+            return;
+        }
+
+        self.deselect_all_notes();
+        self.state.position_note::<R>(
+            note.dom_id,
+            note.line_ix,
+            note.start_beat,
+            split_beat - note.start_beat,
+        );
+    }
+
+    /// Inserts a chord of notes one snap interval long at the snap interval containing `beat`,
+    /// rooted at `line_ix`.  The lines of the chord's notes are provided by the handler.  If any
+    /// of them are outside of the grid or would intersect another note, nothing is inserted.
+    fn insert_chord(&mut self, line_ix: usize, beat: f32) {
+        let line_count = self.state.data.lines.len();
+        let lines = self.handler.get_chord_lines(&self.state, line_ix);
+        if lines.is_empty() || lines.iter().any(|&line_ix| line_ix >= line_count) {
+            return;
+        }
+
+        let snap_interval = self.state.snap_beat_interval();
+        let start_beat = (beat / snap_interval).trunc() * snap_interval;
+        let notes: Vec<(usize, NoteBox<S>)> = lines
+            .into_iter()
+            .filter_map(|line_ix| {
+                let raw_note = RawNoteData {
+                    line_ix,
+                    start_beat,
+                    width: snap_interval,
+                    velocity: DEFAULT_NOTE_VELOCITY,
+                    pitch_bend: Vec::new(),
+                    expression: NoteExpression::default(),
+                };
+                self.create_raw_note(raw_note).map(|note| (line_ix, note))
+            })
+            .collect();
+        let selected_notes: Vec<SelectedNoteData> = notes
+            .iter()
+            .map(|(line_ix, note)| SelectedNoteData::from_note_box(*line_ix, note))
+            .collect();
+
+        let notes_before_edit = self.state.get_raw_note_data();
+        if let Some(rejected_notes) = self.state.data.insert_group(notes) {
+            for (_, note) in rejected_notes {
+                js::delete_element(note.data.get_id());
+            }
+            return;
+        }
+
+        // The whole chord is undone at once
+        self.state.note_history.record(&notes_before_edit);
+        self.deselect_all_notes();
+        for note_data in selected_notes {
+            R::select_note(note_data.dom_id);
+            self.state.selected_notes.insert(note_data);
+        }
+    }
+
+    /// Restores the notes to how they were before the last undoable edit.  Returns `false` if
+    /// there was nothing to undo.
+    pub fn undo_note_edit(&mut self) -> bool {
+        let mut notes = self.state.get_raw_note_data();
+        if !self.state.note_history.undo(&mut notes) {
+            return false;
+        }
+        self.replace_notes(notes);
+        true
+    }
+
+    /// Re-applies the most recently undone edit to the notes.  Returns `false` if there was
+    /// nothing to redo.
+    pub fn redo_note_edit(&mut self) -> bool {
+        let mut notes = self.state.get_raw_note_data();
+        if !self.state.note_history.redo(&mut notes) {
+            return false;
+        }
+        self.replace_notes(notes);
+        true
+    }
+
+    /// Deletes all notes and inserts the provided ones in their place
+    fn replace_notes(&mut self, raw_notes: Vec<RawNoteData>) {
+        self.deselect_all_notes();
+        for note in self.state.data.iter() {
+            let dom_id = note.note_box.data.get_id();
+            js::delete_element(dom_id);
+            self.handler.on_note_deleted(dom_id);
+        }
+        self.state.data.clear();
+        self.insert_raw_notes(raw_notes);
+    }
+
+    /// Merges the provided note with the note following it on the same line, deleting the
+    /// rendered element of the following note.
+    fn join_note(&mut self, note: SelectedNoteData) {
+        let line = &mut self.state.data.lines[note.line_ix];
+        let next_start_beat = match line
+            .find_node_starting_at(note.start_beat)
+            .and_then(|node_key| line.next_node(line.get_node(node_key)))
+        {
+            Some(next_node) => next_node.val.bounds.start_beat,
+            None => return,
+        };
+        let removed_note = match line.join(note.start_beat, next_start_beat) {
+            Some(removed_note) => removed_note,
+            None => return,
+        };
+
+        self.deselect_all_notes();
+        let removed_dom_id = removed_note.data.get_id();
+        js::delete_element(removed_dom_id);
+        self.handler.on_note_deleted(removed_dom_id);
+        self.state.position_note::<R>(
+            note.dom_id,
+            note.line_ix,
+            note.start_beat,
+            removed_note.bounds.end_beat - note.start_beat,
+        );
+    }
+
     /// This is called when re-initializing
     fn rerender_all_notes(&self) {
         for note_data in self.state.data.iter() {
@@ -426,12 +1120,148 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
         }
     }
 
+    /// Sets the zoom factors of the grid, clamped to the allowed range, and repositions all
+    /// rendered elements to match.
+    pub fn set_zoom(&mut self, zoom_x: f32, zoom_y: f32) {
+        let zoom_x = clamp(zoom_x, MIN_GRID_ZOOM, MAX_GRID_ZOOM);
+        let zoom_y = clamp(zoom_y, MIN_GRID_ZOOM, MAX_GRID_ZOOM);
+        if zoom_x == self.state.conf.zoom_x && zoom_y == self.state.conf.zoom_y {
+            return;
+        }
+
+        self.state.conf.zoom_x = zoom_x;
+        self.state.conf.zoom_y = zoom_y;
+        self.reposition_all();
+    }
+
+    /// Scrolls the grid horizontally so that the visible window starts at `offset_beats`.
+    pub fn set_scroll_offset(&mut self, offset_beats: f32) {
+        self.state.scroll_offset_beats = offset_beats.max(0.);
+        self.sync_scroll_offset();
+        self.render_visible_range(false);
+    }
+
+    /// Shifts the rendered grid by the exact scroll offset rather than `scroll_offset_px` so that
+    /// it lines up with the beats computed by `GridState::grid_px_to_beat`
+    fn sync_scroll_offset(&self) {
+        let offset_px = self.state.scroll_offset_beats * self.state.conf.zoomed_beat_length_px();
+        js::set_grid_scroll_offset(&self.get_id(), offset_px);
+    }
+
+    fn scroll_by_px(&mut self, diff_px: isize) {
+        let diff_beats = self.state.conf.px_to_beat(diff_px);
+        self.set_scroll_offset(self.state.scroll_offset_beats + diff_beats);
+    }
+
+    /// Zooms in or out by one step either horizontally or vertically
+    fn zoom_step(&mut self, zoom_in: bool, vertical: bool) {
+        let factor = tern(zoom_in, GRID_ZOOM_STEP, 1. / GRID_ZOOM_STEP);
+        let (zoom_x, zoom_y) = (self.state.conf.zoom_x, self.state.conf.zoom_y);
+        if vertical {
+            self.set_zoom(zoom_x, zoom_y * factor);
+        } else {
+            self.set_zoom(zoom_x * factor, zoom_y);
+        }
+    }
+
+    /// The cursor isn't moved by playback while the grid isn't visible, so it's moved to where it
+    /// should be when the grid is shown again
+    fn set_visible(&mut self, visible: bool) {
+        self.state.visible = visible;
+        if visible {
+            R::set_cursor_pos(
+                self.state.cursor_dom_id,
+                self.state.conf.beats_to_px(self.state.cursor_pos_beats),
+            );
+        }
+    }
+
+    /// Updates the positions and sizes of all rendered elements to match the current `GridConf`.
+    fn reposition_all(&mut self) {
+        let conf = &self.state.conf;
+        R::set_cursor_pos(
+            self.state.cursor_dom_id,
+            conf.beats_to_px(self.state.cursor_pos_beats),
+        );
+        js::set_attr(self.state.cursor_dom_id, "y2", &conf.grid_height().to_string());
+        // The scroll offset is stored in beats, so its pixel position changes with the zoom
+        self.sync_scroll_offset();
+        self.sync_grid_height();
+
+        self.render_visible_range(true);
+    }
+
+    /// Renders the background, notes, and the handler's lanes for the part of the grid around the
+    /// visible window.  Nothing is done if the visible window is still inside of the range that's
+    /// already rendered unless `conf_changed` is set, in which case all of it is stale.
+    ///
+    /// Only the notes in range are repositioned.  When the conf changes, the notes outside of the
+    /// range are culled by hiding them, and culled notes are shown again once they're in range.
+    fn render_visible_range(&mut self, conf_changed: bool) {
+        let (visible_start_beat, visible_end_beat) = self.state.get_visible_beat_range();
+        let (rendered_start_beat, rendered_end_beat) = self.state.background.beat_range;
+        if !conf_changed
+            && visible_start_beat >= rendered_start_beat
+            && visible_end_beat <= rendered_end_beat
+        {
+            return;
+        }
+
+        let (start_beat, end_beat) = self.state.get_render_beat_range();
+        let culled_notes = &self.state.culled_notes;
+        let notes_in_range: Vec<(DomId, usize, NoteBoxBounds)> = self
+            .state
+            .data
+            .iter_range(start_beat, end_beat)
+            .map(|note_data| {
+                let note_box = note_data.note_box;
+                (note_box.data.get_id(), note_data.line_ix, note_box.bounds)
+            })
+            .filter(|(dom_id, ..)| conf_changed || culled_notes.contains(dom_id))
+            .collect();
+
+        if conf_changed {
+            let dom_ids_in_range: FnvHashSet<DomId> =
+                notes_in_range.iter().map(|&(dom_id, ..)| dom_id).collect();
+            let notes_to_cull: Vec<DomId> = self
+                .state
+                .data
+                .iter()
+                .map(|note_data| note_data.note_box.data.get_id())
+                .filter(|dom_id| {
+                    !dom_ids_in_range.contains(dom_id) && !culled_notes.contains(dom_id)
+                })
+                .collect();
+            for dom_id in notes_to_cull {
+                js::add_class(dom_id, "culled");
+                self.state.culled_notes.insert(dom_id);
+            }
+        }
+        for (dom_id, line_ix, bounds) in notes_in_range {
+            self.state
+                .position_note::<R>(dom_id, line_ix, bounds.start_beat, bounds.width());
+        }
+
+        render::update_grid_background(
+            &self.state.conf,
+            &mut self.state.background,
+            (start_beat, end_beat),
+        );
+        self.handler.on_rerender(&mut self.state);
+    }
+
+    /// Sizes the rendered grid to fit its rows and the handler's lane area
+    fn sync_grid_height(&self) {
+        let height = self.state.conf.grid_height() + self.handler.lane_area_height();
+        js::set_grid_height(&self.get_id(), height);
+    }
+
     pub fn render_note(&self, line_ix: usize, start_beat: f32, width: f32) -> DomId {
         R::create_note(
             self.state.conf.beats_to_px(start_beat),
             self.state.conf.cursor_gutter_height + self.state.conf.padded_line_height() * line_ix,
             self.state.conf.beats_to_px(width),
-            self.state.conf.line_height,
+            self.state.conf.zoomed_line_height(),
             None,
         )
     }
@@ -441,28 +1271,52 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
     for Grid<S, R, H>
 {
     fn init(&mut self) {
-        render::render_initial_grid(&self.state.conf, &self.get_id());
+        self.state.background = render::render_initial_grid(
+            &self.state.conf,
+            &self.get_id(),
+            self.state.get_render_beat_range(),
+        );
+        // All notes are rendered from scratch
+        self.state.culled_notes.clear();
         self.state.cursor_dom_id = R::create_cursor(&self.state.conf, 4.);
+        self.sync_scroll_offset();
+        self.sync_grid_height();
         self.handler.init(&self.get_id(), &self.state.conf);
+        self.handler.on_background_render(&mut self.state);
 
         if !self.loaded {
-            self.try_load_saved_composition();
+            match self.saved_state.take() {
+                Some(saved_state) => self.insert_saved_state(saved_state),
+                None => self.try_load_saved_composition(),
+            }
             self.loaded = true;
         } else {
             self.rerender_all_notes();
         }
+        self.handler.on_notes_changed(&mut self.state);
     }
 
     fn hide(&mut self) {
         js::hide_grid(&self.get_id());
+        self.state.visible = false;
         self.handler.hide(&self.get_id());
     }
 
     fn unhide(&mut self) {
         js::unhide_grid(&self.get_id());
+        self.set_visible(true);
         self.handler.unhide(&self.get_id());
     }
 
+    fn handle_resize(&mut self, width: usize, height: usize) {
+        self.state.viewport_width = width;
+        self.state.viewport_height = height;
+        self.render_visible_range(false);
+        self.handler.on_resize(&mut self.state);
+    }
+
+    fn handle_visibility_change(&mut self, visible: bool) { self.set_visible(visible); }
+
     fn cleanup(&mut self) {
         js::cleanup_grid(&self.get_id());
         self.serialize_and_save();
@@ -470,17 +1324,117 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
         self.handler.cleanup(&mut self.state, &vc_id);
     }
 
-    fn dispose(&mut self) { js::delete_localstorage_key(&self.get_state_key()); }
+    fn save_external_data(&mut self) -> Vec<String> {
+        self.serialize_and_save();
+        vec![self.get_state_key()]
+    }
+
+    fn dispose(&mut self) {
+        get_storage().delete(&self.get_state_key());
+        self.state.data.clear();
+        self.state.selected_notes.clear();
+    }
 
     fn get_id(&self) -> String { self.uuid.to_string() }
 
     fn handle_key_down(&mut self, key: &str, control_pressed: bool, shift_pressed: bool) {
+        self.key_down(key, control_pressed, shift_pressed);
+        self.handler.on_notes_changed(&mut self.state);
+    }
+
+    fn get_keymap_sections(&self) -> Vec<&'static KeymapSection> {
+        let mut sections = vec![&GRID_KEYMAP];
+        sections.extend(self.handler.get_keymap_sections());
+        sections
+    }
+
+    fn handle_key_up(&mut self, key: &str, control_pressed: bool, shift_pressed: bool) {
         self.state.control_pressed = control_pressed;
         self.state.shift_pressed = shift_pressed;
+        if key == "Alt" {
+            self.state.snap_bypassed = false;
+        }
 
-        match key {
-            // Delete all currently selected notes
-            "Backspace" | "Delete" => {
+        self.handler
+            .on_key_up(&mut self.state, key, control_pressed, shift_pressed);
+    }
+
+    fn handle_mouse_down(&mut self, x: usize, y: usize, pen: Option<PenState>) {
+        self.mouse_down(x, y, pen);
+        self.handler.on_notes_changed(&mut self.state);
+    }
+
+    fn handle_mouse_move(&mut self, x: usize, y: usize, pen: Option<PenState>) {
+        self.mouse_move(x, y, pen);
+        // Notes are only changed by moving the mouse while it's pressed
+        if self.state.mouse_down {
+            self.handler.on_notes_changed(&mut self.state);
+        }
+    }
+
+    fn handle_mouse_up(&mut self, x: usize, y: usize) {
+        self.mouse_up(x, y);
+        self.handler.on_notes_changed(&mut self.state);
+    }
+
+    fn handle_touch_start(&mut self, pointer_id: i32, x: usize, y: usize) {
+        let action = self.state.touches.start(pointer_id, x, y);
+        self.handle_touch_action(action);
+    }
+
+    fn handle_touch_move(&mut self, pointer_id: i32, x: usize, y: usize) {
+        let action = self.state.touches.move_to(pointer_id, x, y);
+        self.handle_touch_action(action);
+    }
+
+    fn handle_touch_end(&mut self, pointer_id: i32, x: usize, y: usize) {
+        let action = self.state.touches.end(pointer_id, x, y);
+        self.handle_touch_action(action);
+    }
+
+    fn handle_mouse_wheel(&mut self, ydiff: isize) {
+        // Control + wheel zooms horizontally, control + shift + wheel zooms vertically, and shift +
+        // wheel scrolls horizontally.
+        if self.state.control_pressed && ydiff != 0 {
+            self.zoom_step(ydiff < 0, self.state.shift_pressed);
+        } else if self.state.shift_pressed {
+            self.scroll_by_px(ydiff);
+        }
+    }
+
+    fn handle_message(&mut self, key: &str, val: &[u8]) -> Option<Vec<u8>> {
+        let res = self.message(key, val);
+        self.handler.on_notes_changed(&mut self.state);
+        res
+    }
+
+    fn save(&mut self) -> String { self.handler.save(&self.state) }
+
+    fn get_audio_connectables(&self) -> JsValue { self.handler.get_audio_connectables(self.uuid) }
+}
+
+impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>> Grid<S, R, H> {
+    fn key_down(&mut self, key: &str, control_pressed: bool, shift_pressed: bool) {
+        self.state.control_pressed = control_pressed;
+        self.state.shift_pressed = shift_pressed;
+
+        // Alt bypasses snapping while held rather than triggering an action, so it can't be rebound
+        if key == "Alt" {
+            self.state.snap_bypassed = true;
+            return;
+        }
+        if self
+            .handler
+            .capture_key_down(&mut self.state, key, control_pressed, shift_pressed)
+        {
+            return;
+        }
+
+        match get_vcm().get_key_action(&GRID_KEYMAP, key) {
+            Some("delete_selected_notes") => {
+                if !self.state.selected_notes.is_empty() {
+                    self.state.record_note_edit();
+                }
                 for note_data in self.state.selected_notes.drain() {
                     let removed_note = self
                         .state
@@ -494,29 +1448,51 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
                     debug!("{:?}", self.state.data.lines[note_data.line_ix]);
                 }
             },
-            "p" => self.copy_selected_notes(),
+            Some("copy_selected_notes") => self.copy_selected_notes(),
+            Some("select_lines_of_selection") => {
+                let mut line_ixs: Vec<usize> =
+                    self.state.selected_notes.iter().map(|note| note.line_ix).collect();
+                line_ixs.sort_unstable();
+                line_ixs.dedup();
+                let notes: Vec<SelectedNoteData> = line_ixs
+                    .into_iter()
+                    .flat_map(|line_ix| self.state.get_line_notes(line_ix))
+                    .collect();
+                self.state.select_notes::<R>(notes);
+            },
+            Some("select_after_cursor") => self
+                .state
+                .select_beat_range::<R>(self.state.cursor_pos_beats, f32::INFINITY),
+            Some("zoom_in") => self.zoom_step(true, false),
+            Some("zoom_out") => self.zoom_step(false, false),
+            Some("zoom_in_vertical") => self.zoom_step(true, true),
+            Some("zoom_out_vertical") => self.zoom_step(false, true),
             _ => self
                 .handler
                 .on_key_down(&mut self.state, key, control_pressed, shift_pressed),
         }
     }
 
-    fn handle_key_up(&mut self, key: &str, control_pressed: bool, shift_pressed: bool) {
-        self.state.control_pressed = control_pressed;
-        self.state.shift_pressed = shift_pressed;
-
-        self.handler
-            .on_key_up(&mut self.state, key, control_pressed, shift_pressed);
-    }
-
-    fn handle_mouse_down(&mut self, mut x: usize, y: usize) {
+    fn mouse_down(&mut self, mut x: usize, mut y: usize, pen: Option<PenState>) {
+        self.state.pen = pen;
+        // Convert from the visible window's coordinates into the grid's coordinates
+        x += self.state.scroll_offset_px();
         let mut drawing_dom_id = None;
         let mut selection_box_dom_id = None;
         let mut dragging_note_data = None;
+        let mut resizing_note_data = None;
+
+        let grid_height = self.state.conf.grid_height();
+        if y >= grid_height {
+            self.state.lane_area_mouse_down =
+                self.handler
+                    .on_lane_area_mouse_down(&mut self.state, x, y - grid_height);
+            return;
+        }
 
         // Determine if the requested location intersects an existing note and if not, determine the
         // bounds on the note that will be drawn next.
-        let line_ix = match self.state.conf.get_line_index(y) {
+        let mut line_ix = match self.state.conf.get_line_index(y) {
             Some(line_ix) => line_ix,
             None => {
                 // click must be in the cursor gutter
@@ -525,7 +1501,28 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
             },
         };
         let beat = self.state.conf.px_to_beat(x);
-        let bounds = self.state.data.get_bounds(line_ix, beat);
+        let mut bounds = self.state.data.get_bounds(line_ix, beat);
+
+        // The handler may move notes drawn on empty space onto a different line
+        let drawing = self.state.cur_tool == Tool::DrawNote
+            && !self.state.shift_pressed
+            && !self.state.control_pressed;
+        if let (true, skip_list::Bounds::Bounded(..)) = (drawing, &bounds) {
+            let draw_line_ix = self.handler.get_draw_line(&self.state, line_ix);
+            if draw_line_ix != line_ix {
+                bounds = self.state.data.get_bounds(draw_line_ix, beat);
+                if let skip_list::Bounds::Intersecting { .. } = bounds {
+                    return;
+                }
+                line_ix = draw_line_ix;
+                // Point `mouse_down_y` at the middle of the new line so that the note is created
+                // there once the mouse is released
+                let padded_line_height = self.state.conf.padded_line_height();
+                y = self.state.conf.cursor_gutter_height
+                    + line_ix * padded_line_height
+                    + padded_line_height / 2;
+            }
+        }
 
         match bounds {
             skip_list::Bounds::Intersecting {
@@ -540,6 +1537,10 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
                         .data
                         .remove(selected_note_data.line_ix, selected_note_data.start_beat);
                 },
+                Tool::SplitNote => self.split_note(selected_note_data, x),
+                Tool::JoinNote => self.join_note(selected_note_data),
+                Tool::InsertChord => (),
+                Tool::Lasso => self.init_lasso(x, y),
                 Tool::DrawNote if self.state.shift_pressed => {
                     selection_box_dom_id = self.init_selection_box(x, y);
                 },
@@ -555,14 +1556,29 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
                             .on_note_click(&mut self.state, line_ix, node_slab_key);
                     }
                 },
-                Tool::DrawNote => {
-                    dragging_note_data = Some((selected_note_data.start_beat, selected_note_data));
+                Tool::DrawNote
+                    if !self.handler.draws_hits()
+                        && self.get_note_edge(&selected_note_data, x).is_some() =>
+                {
+                    let edge = self.get_note_edge(&selected_note_data, x).unwrap();
+                    resizing_note_data = Some((edge, selected_note_data));
                     self.deselect_all_notes();
                     self.state.selected_notes.insert(selected_note_data);
                     R::select_note(selected_note_data.dom_id);
                 },
+                Tool::DrawNote => {
+                    dragging_note_data = Some((selected_note_data.start_beat, selected_note_data));
+                    // Grabbing a note that's already selected drags the whole selection with it
+                    if !self.state.selected_notes.contains(&selected_note_data) {
+                        self.deselect_all_notes();
+                        self.state.selected_notes.insert(selected_note_data);
+                        R::select_note(selected_note_data.dom_id);
+                    }
+                },
             },
             skip_list::Bounds::Bounded(lower, upper) => match self.state.cur_tool {
+                Tool::InsertChord => self.insert_chord(line_ix, beat),
+                Tool::Lasso => self.init_lasso(x, y),
                 Tool::DrawNote if self.state.control_pressed => {},
                 Tool::DrawNote if self.state.shift_pressed => {
                     selection_box_dom_id = self.init_selection_box(x, y);
@@ -572,16 +1588,16 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
                 Tool::DrawNote => {
                     // The lower bound is the measure's start beat or preceeding note's end beat,
                     // whichever comes last.
-                    let beat = self.state.conf.px_to_beat(x);
-                    let snap_intervals = beat / self.state.conf.note_snap_beat_interval;
+                    let beat = self.state.grid_px_to_beat(x);
+                    let snap_intervals = beat / self.state.snap_beat_interval();
                     let interval_start_beat =
-                        snap_intervals.trunc() * self.state.conf.note_snap_beat_interval;
+                        snap_intervals.trunc() * self.state.snap_beat_interval();
                     let snapped_lower_px =
                         self.state.conf.beats_to_px(interval_start_beat.max(lower));
                     // The upper bound is the end of the measure or the following note's start
                     // beat, whichever comes first.
                     let interval_end_beat =
-                        interval_start_beat + self.state.conf.note_snap_beat_interval;
+                        interval_start_beat + self.state.snap_beat_interval();
                     let snapped_upper_beat = interval_end_beat.min(upper.unwrap_or(f32::INFINITY));
                     let snapped_upper_px = self.state.conf.beats_to_px(snapped_upper_beat);
 
@@ -605,7 +1621,7 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
                         self.state.conf.cursor_gutter_height
                             + self.state.conf.padded_line_height() * line_ix,
                         width,
-                        self.state.conf.line_height,
+                        self.state.conf.zoomed_line_height(),
                         None,
                     ));
                     self.handler.on_note_draw_start(&mut self.state, line_ix);
@@ -620,8 +1636,10 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
         self.state.mouse_down_x = x;
         self.state.mouse_down_y = y;
         self.state.drawing_note_dom_id = drawing_dom_id;
+        self.state.drawing_note_peak_pressure = pen.map(|pen| pen.pressure);
         self.state.selection_box_dom_id = selection_box_dom_id;
         self.state.dragging_note_data = dragging_note_data;
+        self.state.resizing_note_data = resizing_note_data;
 
         self.handler.on_mouse_down(&mut self.state, x, y);
 
@@ -631,14 +1649,31 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
         }
     }
 
-    fn handle_mouse_move(&mut self, x: usize, y: usize) {
+    fn mouse_move(&mut self, x: usize, y: usize, pen: Option<PenState>) {
+        self.state.pen = pen;
+        if let (Some(peak), Some(pen)) = (&mut self.state.drawing_note_peak_pressure, pen) {
+            *peak = peak.max(pen.pressure);
+        }
+        let x = x + self.state.scroll_offset_px();
         let (last_x, last_y) = (self.state.mouse_x, self.state.mouse_y);
         self.state.mouse_x = x;
         self.state.mouse_y = y;
+        if self.state.lane_area_mouse_down {
+            let y = y.saturating_sub(self.state.conf.grid_height());
+            self.handler.on_lane_area_mouse_move(&mut self.state, x, y);
+            return;
+        }
         if !self.state.mouse_down {
             return;
         }
 
+        if let Some((start_beat, dom_id)) = self.state.cursor_gutter_region {
+            let (start_px, end_px) = self.get_cursor_gutter_region_px(start_beat, x);
+            js::set_attr(dom_id, "x", &start_px.to_string());
+            js::set_attr(dom_id, "width", &(end_px - start_px).to_string());
+            return;
+        }
+
         if self.state.cursor_moving {
             self.state.mouse_y = 1;
             if let Some(selection_box_dom_id) = self.state.selection_box_dom_id {
@@ -659,10 +1694,15 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
             },
             Tool::DrawNote => {
                 if let Some(dom_id) = self.state.drawing_note_dom_id {
+                    if self.handler.draws_hits() {
+                        return;
+                    }
                     let NoteBoxData { x, width } = self.compute_note_box_data(x);
                     js::set_attr(dom_id, "x", &x.to_string());
                     js::set_attr(dom_id, "width", &width.to_string());
-                } else if let Some((first_dragging_note_start_beat, ref mut dragging_note)) =
+                } else if let Some((edge, resizing_note)) = self.state.resizing_note_data {
+                    self.resize_note(edge, resizing_note, x);
+                } else if let Some((first_dragging_note_start_beat, dragging_note)) =
                     self.state.dragging_note_data
                 {
                     // Figure out if we've moved far enough to warrant a move
@@ -679,117 +1719,46 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
                     let horizontal_movement_diff_beats =
                         self.state.conf.px_to_beat(horizontal_movement_diff_px);
                     let horizontal_movement_intervals = (horizontal_movement_diff_beats
-                        / self.state.conf.note_snap_beat_interval)
+                        / self.state.snap_beat_interval())
                         .round();
                     let original_start_beat = dragging_note.start_beat;
                     let new_start_beat = first_dragging_note_start_beat
-                        + (horizontal_movement_intervals * self.state.conf.note_snap_beat_interval);
+                        + (horizontal_movement_intervals * self.state.snap_beat_interval());
 
                     if original_line_ix == new_line_ix && original_start_beat == new_start_beat {
                         return;
                     }
 
-                    // Go with the simple solution: remove from the source line, try to add to the
-                    // destination line, re-insert in source line if it's blocked.
-                    trace!(
-                        "Removing dragging note starting at {}",
-                        dragging_note.start_beat
-                    );
-                    let note = self
-                        .state
-                        .data
-                        .remove(original_line_ix, dragging_note.start_beat)
-                        .unwrap_or_else(|| {
-                            panic!(
-                                "Tried removing original note starting at {} from the original \
-                                 line but it wasn't found",
-                                dragging_note.start_beat
-                            )
+                    // The entire selection is moved along with the dragging note.  We try several
+                    // offsets around the new mouse position, trying each subsequently until one
+                    // works (or none work, in which case we leave the notes where they were).
+                    let line_diff = new_line_ix as isize - original_line_ix as isize;
+                    let beat_diff = new_start_beat - original_start_beat;
+                    let moved = [(line_diff, beat_diff), (0, beat_diff), (line_diff, 0.)]
+                        .iter()
+                        .filter(|&&(line_diff, beat_diff)| line_diff != 0 || beat_diff != 0.)
+                        .any(|&(line_diff, beat_diff)| {
+                            self.move_selected_notes(line_diff, beat_diff)
                         });
-                    trace!("Removed note: {:?}", note);
-
-                    // We try to place the note in several positions around the new mouse position,
-                    // trying each subsequently until one works (or none work, in which case we
-                    // leave the note where it was).
-                    let (new_dragging_note_line_ix, new_dragging_note_start_beat): (usize, f32) =
-                        match try_insert_many(
-                            &mut self.state.data,
-                            note,
-                            &[
-                                (new_line_ix, new_start_beat),
-                                (original_line_ix, new_start_beat),
-                                (new_line_ix, original_start_beat),
-                            ],
-                            dragging_note,
-                        ) {
-                            InsertionAttemptResult::Failed(mut failed_insertion_note) => {
-                                // We failed to move the note at all, so reset everything to its
-                                // original position and re-insert the note
-                                // where we found it.
-                                debug!(
-                                    "Failed to move note; re-inserting at original start beat: \
-                                     {}, line_ix: {}",
-                                    original_start_beat, original_line_ix
-                                );
-                                failed_insertion_note.bounds.start_beat = original_start_beat;
-                                dragging_note.start_beat = original_start_beat;
-                                dragging_note.line_ix = original_line_ix;
-                                let reinsertion_error = self
-                                    .state
-                                    .data
-                                    .insert(original_line_ix, failed_insertion_note);
-                                debug_assert!(reinsertion_error.is_none());
-                                return;
-                            },
-                            InsertionAttemptResult::Inserted {
-                                line_ix,
-                                start_beat,
-                            } => {
-                                trace!(
-                                    "Moved note to start_beat: {}, line_ix: {}",
-                                    start_beat,
-                                    line_ix
-                                );
-                                (line_ix, start_beat)
-                            },
-                        };
-
-                    // We have a custom `Hash` implementation for `SelectedNoteData` that uses its
-                    // `DomId` and ignores its position; that's why this works.
-                    let was_removed = self.state.selected_notes.remove(dragging_note);
-                    debug_assert!(was_removed);
-                    let was_added = self.state.selected_notes.insert(*dragging_note);
-                    debug_assert!(was_added);
-
-                    if dragging_note.start_beat != original_start_beat {
-                        // TODO: move to renderer method
-                        js::set_attr(
-                            dragging_note.dom_id,
-                            "x",
-                            &(self.state.conf.beats_to_px(dragging_note.start_beat) as usize)
-                                .to_string(),
-                        );
-                    }
-                    if dragging_note.line_ix != original_line_ix {
-                        // TODO: move to renderer method
-                        js::set_attr(
-                            dragging_note.dom_id,
-                            "y",
-                            &((dragging_note.line_ix * self.state.conf.padded_line_height()
-                                + self.state.conf.cursor_gutter_height)
-                                .to_string()),
+                    if !moved {
+                        debug!(
+                            "Failed to move selected notes; leaving them at their original \
+                             positions"
                         );
+                        return;
                     }
 
+                    let (_, dragging_note) = self.state.dragging_note_data.unwrap();
                     note_movement_data = Some((
                         dragging_note.dom_id,
                         original_line_ix,
                         original_start_beat,
-                        new_dragging_note_line_ix,
-                        new_dragging_note_start_beat,
+                        dragging_note.line_ix,
+                        dragging_note.start_beat,
                     ))
                 }
             },
+            Tool::Lasso => self.update_lasso(x, y),
             _ => (),
         }
 
@@ -812,7 +1781,14 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
         }
     }
 
-    fn handle_mouse_up(&mut self, x: usize, _y: usize) {
+    fn mouse_up(&mut self, x: usize, y: usize) {
+        let x = x + self.state.scroll_offset_px();
+        if self.state.lane_area_mouse_down {
+            self.state.lane_area_mouse_down = false;
+            let y = y.saturating_sub(self.state.conf.grid_height());
+            self.handler.on_lane_area_mouse_up(&mut self.state, x, y);
+            return;
+        }
         // if `self.state.mouse_down` is not set, the user tried to place an invalid note and we
         // ignore it.
         if !self.state.mouse_down {
@@ -824,6 +1800,24 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
             self.delete_selection_box(selection_box_dom_id);
         }
 
+        if let Some((_lasso, dom_id)) = self.state.lasso.take() {
+            js::delete_element(dom_id);
+            return;
+        }
+
+        if let Some((start_beat, dom_id)) = self.state.cursor_gutter_region.take() {
+            js::delete_element(dom_id);
+            let end_beat = self.state.conf.px_to_beat(x);
+            if start_beat != end_beat {
+                self.handler.on_cursor_gutter_region_select(
+                    &mut self.state,
+                    start_beat.min(end_beat),
+                    start_beat.max(end_beat),
+                );
+            }
+            return;
+        }
+
         if self.state.cursor_moving {
             self.set_cursor_pos(self.state.conf.px_to_beat(x));
             self.state.cursor_moving = false;
@@ -843,6 +1837,8 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
 
         if self.state.cur_tool == Tool::DrawNote {
             if let Some(note_dom_id) = self.state.drawing_note_dom_id {
+                // Hits keep the size that they were drawn with when the mouse was pressed
+                let x = tern(self.handler.draws_hits(), self.state.mouse_down_x, x);
                 let NoteBoxData { x, width } = self.compute_note_box_data(x);
                 if width == 0 {
                     self.handler
@@ -859,24 +1855,36 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
                         .create_note(&mut self.state, line_ix, start_beat, note_dom_id);
 
                 let note: NoteBox<S> = NoteBox {
+                    id: NoteId::next(),
                     data: note_data,
                     bounds: NoteBoxBounds {
                         start_beat,
                         end_beat: self.state.conf.px_to_beat(x_px + width),
                     },
+                    velocity: self
+                        .state
+                        .drawing_note_peak_pressure
+                        .map(pressure_to_velocity)
+                        .unwrap_or(DEFAULT_NOTE_VELOCITY),
+                    pitch_bend: Vec::new(),
+                    expression: NoteExpression::default(),
                 };
+                R::set_note_velocity(note_dom_id, note.velocity);
 
                 self.deselect_all_notes();
                 self.state.selected_notes.insert(SelectedNoteData {
                     line_ix,
                     dom_id: note_dom_id,
+                    note_id: note.id,
                     start_beat,
                     width: note.bounds.width(),
+                    velocity: note.velocity,
                 });
 
                 R::select_note(note_dom_id);
 
                 // Actually insert the node into the skip list
+                self.state.record_note_edit();
                 self.state.data.insert(line_ix, note);
                 debug!("{:?}", self.state.data.lines[line_ix]);
             } else {
@@ -887,12 +1895,10 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
         }
     }
 
-    fn handle_mouse_wheel(&mut self, _ydiff: isize) {}
-
-    fn handle_message(&mut self, key: &str, val: &[u8]) -> Option<Vec<u8>> {
+    fn message(&mut self, key: &str, val: &[u8]) -> Option<Vec<u8>> {
         match key {
             "set_raw_note_data" => {
-                let raw_note_data: Vec<RawNoteData> = match bincode::deserialize(val) {
+                let raw_note_data = match common::deserialize_raw_note_data(val) {
                     Ok(raw_note_data) => raw_note_data,
                     Err(err) => {
                         error!("Error decoding `RawNoteData`: {:?}", err);
@@ -904,20 +1910,114 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
                 self.insert_raw_notes(raw_note_data);
                 return Some(vec![0]);
             },
+            "scroll_by_px" => {
+                assert_eq!(
+                    val.len(),
+                    8,
+                    "Message for \"scroll_by_px\" must be an 8-byte `f64` of the pixels to scroll"
+                );
+                let mut buf = [0u8; 8];
+                buf.copy_from_slice(val);
+                self.scroll_by_px(f64::from_ne_bytes(buf) as isize);
+                None
+            },
+            "select_line_at" => {
+                assert_eq!(
+                    val.len(),
+                    8,
+                    "Message for \"select_line_at\" must be an 8-byte `f64` of the y position in \
+                     pixels"
+                );
+                let mut buf = [0u8; 8];
+                buf.copy_from_slice(val);
+                let y = f64::from_ne_bytes(buf).max(0.) as usize;
+                if let Some(line_ix) = self.state.conf.get_line_index(y) {
+                    self.state.select_line::<R>(line_ix);
+                }
+                None
+            },
+            "undo_note_edit" => Some(vec![self.undo_note_edit() as u8]),
+            "redo_note_edit" => Some(vec![self.redo_note_edit() as u8]),
+            "set_snap_interval" => {
+                assert_eq!(
+                    val.len(),
+                    8,
+                    "Message for \"set_snap_interval\" must be an 8-byte `f64` of the interval in \
+                     beats"
+                );
+                let mut buf = [0u8; 8];
+                buf.copy_from_slice(val);
+                self.state.conf.note_snap_beat_interval = f64::from_ne_bytes(buf).max(0.) as f32;
+                None
+            },
             _ => self.handler.handle_message(&mut self.state, key, val),
         }
     }
 
-    fn save(&mut self) -> String { self.handler.save() }
+    fn handle_touch_action(&mut self, action: TouchAction) {
+        match action {
+            TouchAction::MouseDown(x, y) => self.handle_mouse_down(x, y, None),
+            TouchAction::MouseMove(x, y) => self.handle_mouse_move(x, y, None),
+            TouchAction::MouseUp(x, y) => self.handle_mouse_up(x, y),
+            TouchAction::CancelMouse(x, y) => self.cancel_mouse_interaction(x, y),
+            TouchAction::Pinch {
+                zoom_factor,
+                scroll_px,
+            } => {
+                let (zoom_x, zoom_y) = (self.state.conf.zoom_x, self.state.conf.zoom_y);
+                self.set_zoom(zoom_x * zoom_factor, zoom_y * zoom_factor);
+                self.scroll_by_px(scroll_px);
+            },
+            TouchAction::None => (),
+        }
+    }
 
-    fn get_audio_connectables(&self) -> JsValue { self.handler.get_audio_connectables(self.uuid) }
-}
+    /// Stops whatever was started when the mouse was pressed at `(x, y)` without committing it.
+    /// Notes being drawn are discarded, and anything else is moved back to where it started before
+    /// the mouse is released.
+    fn cancel_mouse_interaction(&mut self, x: usize, y: usize) {
+        if let Some(note_dom_id) = self.state.drawing_note_dom_id.take() {
+            if let Some(line_ix) = self.state.conf.get_line_index(self.state.mouse_down_y) {
+                self.handler
+                    .cancel_note_create(&mut self.state, line_ix, note_dom_id);
+            }
+            js::delete_element(note_dom_id);
+        }
 
-impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>> Grid<S, R, H> {
-    /// Handle a click in the cursor gutter, bulk-selecting notes if shift is pressed or moving
-    /// the cursor otherwise.
+        self.handle_mouse_move(x, y, None);
+        self.handle_mouse_up(x, y);
+    }
+
+    /// Returns the `(start_px, end_px)` of the cursor gutter region being selected from
+    /// `start_beat` to the pixel `x`, which may be on either side of it.
+    fn get_cursor_gutter_region_px(&self, start_beat: f32, x: usize) -> (usize, usize) {
+        let start_px = self.state.conf.beats_to_px(start_beat);
+        (start_px.min(x), start_px.max(x))
+    }
+
+    /// Handle a click in the cursor gutter, starting the selection of a region of the gutter if
+    /// control is pressed, bulk-selecting notes if shift is pressed (or deselecting them if alt is
+    /// pressed as well), or moving the cursor otherwise.
     fn handle_cursor_gutter_click(&mut self, x: usize, y: usize) {
+        if self.state.control_pressed {
+            let start_beat = self.state.conf.px_to_beat(x);
+            let start_px = self.state.conf.beats_to_px(start_beat);
+            let dom_id = js::render_quad(
+                FG_CANVAS_IX,
+                start_px,
+                0,
+                0,
+                self.state.conf.cursor_gutter_height,
+                "cursor-gutter-region",
+                None,
+            );
+            self.state.cursor_gutter_region = Some((start_beat, dom_id));
+            self.state.mouse_down = true;
+            return;
+        }
+
         if self.state.shift_pressed {
+            self.state.start_selection_box();
             // TODO: make dedicated function in `render` probably
             self.state.selection_box_dom_id = Some(js::render_quad(
                 FG_CANVAS_IX,
@@ -973,9 +2073,17 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
             width,
             line_ix,
             dom_id,
+            note_id,
+            velocity,
         } in cur_selected_notes
         {
             R::deselect_note(dom_id);
+            let (pitch_bend, expression) = self
+                .state
+                .data
+                .get_by_id(note_id)
+                .map(|(_, note)| (note.pitch_bend.clone(), note.expression.clone()))
+                .unwrap_or_default();
             let new_start_beat = start_beat + offset_beats;
             let new_end_beat = start_beat + width + offset_beats;
             // try to insert a note `offset_beats` away from the previous note on the same line
@@ -995,7 +2103,9 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
             }
 
             let new_dom_id = self.render_note(line_ix, new_start_beat, width);
+            R::set_note_velocity(new_dom_id, velocity);
             let new_note = NoteBox {
+                id: NoteId::next(),
                 bounds: NoteBoxBounds {
                     start_beat: start_beat + offset_beats,
                     end_beat: start_beat + width + offset_beats,
@@ -1006,6 +2116,9 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
                     new_start_beat,
                     new_dom_id,
                 ),
+                velocity,
+                pitch_bend,
+                expression,
             };
 
             let selected_note_data = SelectedNoteData::from_note_box(line_ix, &new_note);
@@ -1051,9 +2164,9 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
         let high_bound = high_bound.unwrap_or(f32::INFINITY);
 
         let source_beat = self.state.conf.px_to_beat(self.state.mouse_down_x);
-        let source_interval = source_beat / self.state.conf.note_snap_beat_interval;
+        let source_interval = source_beat / self.state.snap_beat_interval();
         let cur_beat = self.state.conf.px_to_beat(x);
-        let cur_interval = cur_beat / self.state.conf.note_snap_beat_interval;
+        let cur_interval = cur_beat / self.state.snap_beat_interval();
 
         let (start_interval, end_interval) = if source_interval > cur_interval {
             (cur_interval, source_interval)
@@ -1062,12 +2175,12 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
         };
 
         let mut start_beat = clamp(
-            start_interval.trunc() * self.state.conf.note_snap_beat_interval,
+            start_interval.trunc() * self.state.snap_beat_interval(),
             low_bound,
             high_bound,
         );
         let mut end_beat = clamp(
-            end_interval.ceil() * self.state.conf.note_snap_beat_interval,
+            end_interval.ceil() * self.state.snap_beat_interval(),
             low_bound,
             high_bound,
         );
@@ -1075,16 +2188,16 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
 
         // If we're trying to draw a note immediately to the right of an existing note and are
         // dragging it left causing its width to get set to zero, try to preserve at least one
-        // `note_snap_beat_interval` of size to the right if we have room for it.
+        // snap interval of size to the right if we have room for it.
         if width_beats == 0. && cur_beat <= source_beat {
             let start_interval = source_interval.trunc();
             start_beat = clamp(
-                start_interval * self.state.conf.note_snap_beat_interval,
+                start_interval * self.state.snap_beat_interval(),
                 low_bound,
                 high_bound,
             );
             end_beat = clamp(
-                start_beat + self.state.conf.note_snap_beat_interval,
+                start_beat + self.state.snap_beat_interval(),
                 low_bound,
                 high_bound,
             );
@@ -1098,11 +2211,11 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
     }
 
     pub fn set_cursor_pos(&mut self, x_beats: f32) -> usize {
-        let intervals = x_beats / self.state.conf.note_snap_beat_interval;
+        let intervals = x_beats / self.state.snap_beat_interval();
         let snapped_x_px = self
             .state
             .conf
-            .beats_to_px(intervals * self.state.conf.note_snap_beat_interval);
+            .beats_to_px(intervals * self.state.snap_beat_interval());
         self.state.cursor_pos_beats = self.state.conf.px_to_beat(snapped_x_px);
         R::set_cursor_pos(self.state.cursor_dom_id, snapped_x_px);
         snapped_x_px
@@ -1115,37 +2228,124 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
     }
 
     /// Inserts all of the notes in the provided array of raw note data, rendering them
-    /// as they are inserted into the internal skip list data structure as well.
-    fn insert_raw_notes(&mut self, raw_notes: Vec<RawNoteData>) {
+    /// as they are inserted into the internal skip list data structure as well.  Notes that are
+    /// outside of the grid or that intersect an already-inserted note are skipped.
+    ///
+    /// Returns the `SelectedNoteData` for each of the raw notes in the order they were provided,
+    /// or `None` for the ones that were skipped.
+    fn insert_raw_notes(&mut self, raw_notes: Vec<RawNoteData>) -> Vec<Option<SelectedNoteData>> {
+        let mut notes_by_line: Vec<Vec<NoteBox<S>>> =
+            self.state.data.lines.iter().map(|_| Vec::new()).collect();
+        let mut inserted_notes = Vec::with_capacity(raw_notes.len());
         for raw_note in raw_notes {
-            let RawNoteData {
-                line_ix,
-                start_beat,
-                width,
-            } = raw_note;
-            let dom_id = self.render_note(line_ix, start_beat, width);
-            let note_state = self
-                .handler
-                .create_note(&mut self.state, line_ix, start_beat, dom_id);
-            trace!(
-                "Inserting note at line_ix: {}, start_beat: {}",
-                line_ix,
-                start_beat
-            );
-            let insertion_error = self.state.data.lines[line_ix as usize].insert(NoteBox {
-                data: note_state,
-                bounds: NoteBoxBounds {
-                    start_beat,
-                    end_beat: start_beat + width,
+            let line_ix = raw_note.line_ix;
+            match self.create_raw_note(raw_note) {
+                Some(note) => {
+                    inserted_notes.push(Some(SelectedNoteData::from_note_box(line_ix, &note)));
+                    notes_by_line[line_ix].push(note);
                 },
+                None => inserted_notes.push(None),
+            }
+        }
+
+        // Sorting each line's notes lets them be inserted in bulk without searching the line
+        let mut rejected_note_ids = FnvHashSet::default();
+        for (line_ix, mut notes) in notes_by_line.into_iter().enumerate() {
+            notes.sort_by(|a, b| {
+                a.bounds
+                    .start_beat
+                    .partial_cmp(&b.bounds.start_beat)
+                    .unwrap()
             });
-            debug_assert!(insertion_error.is_none());
+            for note in self.state.data.lines[line_ix].insert_sorted_batch(notes) {
+                warn!(
+                    "Skipping note at line_ix {}, start_beat {} since it intersects another note",
+                    line_ix, note.bounds.start_beat
+                );
+                js::delete_element(note.data.get_id());
+                rejected_note_ids.insert(note.id);
+            }
         }
+
+        inserted_notes
+            .into_iter()
+            .map(|note_data| {
+                note_data.filter(|note_data| !rejected_note_ids.contains(&note_data.note_id))
+            })
+            .collect()
+    }
+
+    /// Renders a single note and creates the `NoteBox` for it without inserting it, returning
+    /// `None` if it's outside of the grid.
+    fn create_raw_note(&mut self, raw_note: RawNoteData) -> Option<NoteBox<S>> {
+        let RawNoteData {
+            line_ix,
+            start_beat,
+            width,
+            velocity,
+            mut pitch_bend,
+            mut expression,
+        } = raw_note;
+        if line_ix >= self.state.data.lines.len() {
+            warn!("Skipping note at line_ix {} since it's outside of the grid", line_ix);
+            return None;
+        }
+        common::pitch_bend::normalize_curve(&mut pitch_bend, width);
+        expression.normalize(width);
+
+        let dom_id = self.render_note(line_ix, start_beat, width);
+        R::set_note_velocity(dom_id, velocity);
+        let note_state = self
+            .handler
+            .create_note(&mut self.state, line_ix, start_beat, dom_id);
+        trace!(
+            "Inserting note at line_ix: {}, start_beat: {}",
+            line_ix,
+            start_beat
+        );
+        Some(NoteBox {
+            id: NoteId::next(),
+            data: note_state,
+            bounds: NoteBoxBounds {
+                start_beat,
+                end_beat: start_beat + width,
+            },
+            velocity,
+            pitch_bend,
+            expression,
+        })
+    }
+
+    /// Inserts the notes from a `SerializedGridState` and restores their selection and the
+    /// position of the cursor.
+    fn insert_saved_state(&mut self, saved_state: SerializedGridState) {
+        let SerializedGridState {
+            notes,
+            selected_note_ixs,
+            cursor_pos_beats,
+            ..
+        } = saved_state;
+
+        let inserted_notes = self.insert_raw_notes(notes);
+        for i in selected_note_ixs {
+            let selected_note_data = match inserted_notes.get(i) {
+                Some(Some(selected_note_data)) => *selected_note_data,
+                _ => continue,
+            };
+            R::select_note(selected_note_data.dom_id);
+            self.state.selected_notes.insert(selected_note_data);
+        }
+
+        self.set_cursor_pos(cursor_pos_beats);
     }
 
     fn reset(&mut self) {
         let new_state = GridState::new(self.state.conf.clone());
-        let old_state = mem::replace(&mut self.state, new_state);
+        let mut old_state = mem::replace(&mut self.state, new_state);
+        // Keep the already-rendered elements of the grid itself along with the current view
+        self.state.cursor_dom_id = old_state.cursor_dom_id;
+        self.state.background = mem::take(&mut old_state.background);
+        self.state.scroll_offset_beats = old_state.scroll_offset_beats;
 
         // Remove all notes from the DOM
         for note in old_state.data.iter() {
@@ -1158,14 +2358,14 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
     }
 
     pub fn try_load_saved_composition(&mut self) {
-        let base64_data: String = match js::get_localstorage_key(&self.get_state_key()) {
+        let base64_data: String = match get_storage().get(&self.get_state_key()) {
             Some(data) => data,
             None => return,
         };
 
         let decoded_bytes: Vec<u8> =
             base64::decode(&base64_data).expect("Invalid base64 was saved.");
-        let raw_notes: Vec<RawNoteData> = bincode::deserialize(&decoded_bytes)
+        let raw_notes = common::deserialize_raw_note_data(&decoded_bytes)
             .expect("Unable to decode saved composition from raw bytes.");
 
         self.insert_raw_notes(raw_notes);
@@ -1179,37 +2379,94 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
         x: usize,
         y: usize,
     ) {
-        let SelectionBoxData {
-            region:
-                SelectionRegion {
-                    x,
-                    y,
-                    width,
-                    height,
-                },
-            retained_region,
-            changed_region_1,
-            changed_region_2,
-        } = SelectionBoxData::compute(
-            self.state.mouse_down_x,
-            self.state.mouse_down_y,
-            x,
-            y.saturating_sub(self.state.conf.cursor_gutter_height),
-            last_x,
-            last_y.saturating_sub(self.state.conf.cursor_gutter_height),
+        let conf = &self.state.conf;
+        let (mouse_down_x, mouse_down_y) = (self.state.mouse_down_x, self.state.mouse_down_y);
+        let selection_box = SelectionBoxData::compute(
+            conf.px_to_beat(mouse_down_x),
+            conf.px_to_line(mouse_down_y),
+            conf.px_to_beat(x),
+            conf.px_to_line(y),
+            conf.px_to_beat(last_x),
+            conf.px_to_line(last_y),
         );
-        R::set_selection_box(&self.state.conf, selection_box_dom_id, x, y, width, height);
 
-        self.handler.on_selection_region_update(
-            &mut self.state,
-            &retained_region,
-            &changed_region_1,
-            &changed_region_2,
+        // The rendered box is clipped to the top of the first line, below the cursor gutter
+        let cursor_gutter_height = conf.cursor_gutter_height;
+        let SelectionRegion {
+            x,
+            y,
+            width,
+            height,
+        } = SelectionRegion::from_points(
+            mouse_down_x,
+            mouse_down_y.max(cursor_gutter_height),
+            x,
+            y.max(cursor_gutter_height),
         );
+        R::set_selection_box(
+            conf,
+            selection_box_dom_id,
+            x,
+            y - cursor_gutter_height,
+            width,
+            height,
+        );
+
+        self.handler
+            .on_selection_region_update(&mut self.state, &selection_box);
+    }
+
+    /// Starts drawing a lasso at the point where the mouse was pressed.  The lasso replaces the
+    /// current selection unless shift is held, in which case it adds to it, or alt is held, in
+    /// which case it removes from it.
+    fn init_lasso(&mut self, x: usize, y: usize) {
+        if !self.state.shift_pressed && !self.state.snap_bypassed {
+            self.deselect_all_notes();
+        }
+        self.state.start_selection_box();
+
+        let conf = &self.state.conf;
+        let lasso = Lasso::new(conf.px_to_beat(x), conf.px_to_line(y));
+        let dom_id = js::render_polygon(FG_CANVAS_IX, &lasso.to_svg_points(conf), "lasso");
+        self.state.lasso = Some((lasso, dom_id));
+    }
+
+    /// Extends the lasso being drawn to the mouse's new position and updates the selection to
+    /// match it
+    fn update_lasso(&mut self, x: usize, y: usize) {
+        let GridState {
+            conf, data, lasso, ..
+        } = &mut self.state;
+        let (lasso, dom_id) = match lasso {
+            Some(lasso) => lasso,
+            None => return,
+        };
+        lasso.push_point(conf.px_to_beat(x), conf.px_to_line(y));
+        js::set_attr(*dom_id, "points", &lasso.to_svg_points(conf));
+
+        // Notes outside of the lasso's bounds can't be inside of it, and since its bounds only
+        // grow, they can't have been inside of it before either
+        let changes: Vec<(SelectedNoteData, bool)> =
+            selection_box::iter_notes_in_region(conf, data, &lasso.bounding_region())
+                .map(|note_data| {
+                    let in_lasso = lasso.contains_note(conf, &note_data);
+                    let NoteData { line_ix, note_box } = note_data;
+                    (SelectedNoteData::from_note_box(line_ix, note_box), in_lasso)
+                })
+                .collect();
+        for (note, in_lasso) in changes {
+            if self.state.is_selected_by_box(note.note_id, in_lasso) {
+                if self.state.selected_notes.insert(note) {
+                    R::select_note(note.dom_id);
+                }
+            } else if self.state.selected_notes.remove(&note) {
+                R::deselect_note(note.dom_id);
+            }
+        }
     }
 
     fn init_selection_box(&mut self, x: usize, y: usize) -> Option<DomId> {
-        self.deselect_all_notes();
+        self.state.start_selection_box();
 
         // TODO: make dedicated function in `render` probably
         Some(js::render_quad(
@@ -1249,6 +2506,6 @@ impl<S: GridRendererUniqueIdentifier, R: GridRenderer<S>, H: GridHandler<S, R>>
         let base64_str = unsafe { str::from_utf8_unchecked(&base64_data) };
 
         let state_key = self.get_state_key();
-        js::set_localstorage_key(&state_key, base64_str);
+        get_storage().set(&state_key, base64_str);
     }
 }
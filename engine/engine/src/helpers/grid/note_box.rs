@@ -2,14 +2,36 @@ use std::{
     cmp::Ordering,
     fmt::{self, Debug, Formatter},
     hash::{Hash, Hasher},
+    sync::atomic::{self, AtomicU32},
 };
 
 use std::f32;
 
-pub use common::RawNoteData;
+pub use common::{
+    expression::NoteExpression, pitch_bend::PitchBendPoint, RawNoteData, DEFAULT_NOTE_VELOCITY,
+    MAX_NOTE_VELOCITY, MIN_NOTE_VELOCITY,
+};
 
 use crate::helpers::grid::prelude::*;
 
+/// Maps the pressure of a pen, from 0 to 1, to the velocity of a note drawn with it
+pub fn pressure_to_velocity(pressure: f32) -> u8 {
+    let velocity = (clamp(pressure, 0., 1.) * MAX_NOTE_VELOCITY as f32).round() as u8;
+    velocity.max(MIN_NOTE_VELOCITY)
+}
+
+static NEXT_NOTE_ID: AtomicU32 = AtomicU32::new(0);
+
+/// Identifies a note for as long as it exists, even as it's moved between lines or resized.
+/// Unlike the slab keys of the skip list or the DOM IDs of rendered notes, these are never reused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct NoteId(pub u32);
+
+impl NoteId {
+    /// Allocates an ID that hasn't been given to any other note
+    pub fn next() -> Self { NoteId(NEXT_NOTE_ID.fetch_add(1, atomic::Ordering::Relaxed)) }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct NoteBoxBounds {
     pub start_beat: f32,
@@ -46,8 +68,16 @@ impl NoteBoxBounds {
 
 #[derive(Clone)]
 pub struct NoteBox<S> {
+    pub id: NoteId,
     pub bounds: NoteBoxBounds,
     pub data: S,
+    /// MIDI velocity of the note in the range `1..=127`
+    pub velocity: u8,
+    /// Curve describing how the pitch of this note is bent over its length, sorted by offset.
+    /// Empty for notes that aren't bent.
+    pub pitch_bend: Vec<PitchBendPoint>,
+    /// Pressure and timbre curves recorded from MPE controllers
+    pub expression: NoteExpression,
 }
 
 impl<S> NoteBox<S> {
@@ -122,8 +152,10 @@ pub struct NoteBoxData {
 pub struct SelectedNoteData {
     pub line_ix: usize,
     pub dom_id: usize,
+    pub note_id: NoteId,
     pub start_beat: f32,
     pub width: f32,
+    pub velocity: u8,
 }
 
 impl PartialEq for SelectedNoteData {
@@ -160,8 +192,10 @@ impl SelectedNoteData {
         SelectedNoteData {
             line_ix,
             dom_id: note_box.data.get_id(),
+            note_id: note_box.id,
             start_beat: note_box.bounds.start_beat,
             width: note_box.bounds.width(),
+            velocity: note_box.velocity,
         }
     }
 }
@@ -173,19 +207,18 @@ pub struct NoteData<'a, S> {
 }
 
 impl<'a, S> NoteData<'a, S> {
-    pub fn get_selection_region(&self, conf: &GridConf) -> SelectionRegion {
+    /// Returns the rectangle that the note covers in beats horizontally and lines vertically.  It
+    /// doesn't cover the border below its line, matching how it's rendered.
+    pub fn get_selection_region(&self, conf: &GridConf) -> SelectionRegion<f32> {
         SelectionRegion {
-            x: (self.note_box.bounds.start_beat * conf.beat_length_px as f32) as usize,
-            y: self.line_ix * conf.padded_line_height(),
-            width: ((self.note_box.bounds.end_beat - self.note_box.bounds.start_beat)
-                * conf.beat_length_px as f32) as usize,
-            height: conf.line_height,
+            x: self.note_box.bounds.start_beat,
+            y: self.line_ix as f32,
+            width: self.note_box.bounds.width(),
+            height: conf.zoomed_line_height() as f32 / conf.padded_line_height() as f32,
         }
     }
 
-    pub fn intersects_region(&self, conf: &GridConf, region: &SelectionRegion) -> bool {
-        let our_region = self.get_selection_region(conf);
-        // regions intersect if any point bounding our origin is contained in the other region
-        our_region.iter_points().any(|pt| region.contains_point(pt))
+    pub fn intersects_region(&self, conf: &GridConf, region: &SelectionRegion<f32>) -> bool {
+        self.get_selection_region(conf).intersects(region)
     }
 }
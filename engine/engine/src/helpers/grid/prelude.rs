@@ -1,10 +1,11 @@
 pub use super::{
     super::super::prelude::*,
     constants::{self, *},
+    lasso::Lasso,
     note_box::{self, NoteBox, NoteBoxData, SelectedNoteData, *},
     render,
-    selection_box::{self, ChangedRegion, SelectionBoxData, SelectionRegion},
+    selection_box::{self, ChangedRegion, SelectionBoxData, SelectionBoxMode, SelectionRegion},
     skip_list::{self, NodeSlabKey, NoteLines, SlabKey},
     DomId, Grid, GridConf, GridHandler, GridRenderer, GridRendererUniqueIdentifier, GridState,
-    Tool,
+    NoteEdge, SerializedGridState, Tool, GRID_KEYMAP,
 };
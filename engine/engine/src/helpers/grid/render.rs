@@ -1,57 +1,152 @@
+use common::tempo_map::{compute_grid_lines, TimeSignatureChange};
+
 use super::prelude::*;
 
-pub fn draw_grid_line(conf: &GridConf, y: usize) {
+/// Holds the `DomId`s of the static elements that make up the grid's background so that they can
+/// be repositioned when the grid's zoom changes.
+#[derive(Default)]
+pub struct GridBackground {
+    pub cursor_gutter: DomId,
+    pub grid_lines: Vec<DomId>,
+    /// `(beat, dom_id)` for each of the measure and beat lines
+    pub measure_lines: Vec<(f32, DomId)>,
+    /// `(start_beat, end_beat)` of the part of the grid that the background is rendered for.  The
+    /// grid has no end, so only the part of it around the visible window is rendered.
+    pub beat_range: (f32, f32),
+}
+
+impl GridBackground {
+    /// Returns the `(start_px, end_px)` of the part of the grid that the background is rendered for
+    pub fn px_range(&self, conf: &GridConf) -> (usize, usize) {
+        let (start_beat, end_beat) = self.beat_range;
+        (conf.beats_to_px(start_beat), conf.beats_to_px(end_beat))
+    }
+}
+
+fn get_grid_line_y(conf: &GridConf, y: usize) -> usize {
+    conf.cursor_gutter_height + (y * conf.padded_line_height())
+}
+
+pub fn draw_grid_line(conf: &GridConf, y: usize, (start_px, end_px): (usize, usize)) -> DomId {
     let class = tern(y % 2 == 0, "grid-line-1", "grid-line-2");
 
     js::render_quad(
         BG_CANVAS_IX,
-        0,
-        conf.cursor_gutter_height + (y * conf.padded_line_height()),
-        conf.grid_width,
-        conf.line_height,
+        start_px,
+        get_grid_line_y(conf, y),
+        end_px - start_px,
+        conf.zoomed_line_height(),
         class,
         None,
-    );
+    )
 }
 
-/// This renders the background grid that contains the lines for the notes.  It is rendered to a
-/// background SVG that doesn't change.
-pub fn draw_grid(conf: &GridConf) {
-    for y in 0..conf.row_count {
-        draw_grid_line(conf, y);
-    }
+/// This renders the background grid that contains the lines for the notes between the pixels
+/// `px_range`.  It is rendered to a background SVG that doesn't change.
+pub fn draw_grid(conf: &GridConf, px_range: (usize, usize)) -> Vec<DomId> {
+    (0..conf.row_count)
+        .map(|y| draw_grid_line(conf, y, px_range))
+        .collect()
+}
+
+/// Draws a line at the start of every measure and at every beat within them between the beats
+/// `beat_range`, following the time signature changes of the provided `GridConf`.
+pub fn draw_measure_lines(
+    conf: &GridConf,
+    (start_beat, end_beat): (f32, f32),
+) -> Vec<(f32, DomId)> {
+    let default_time_signature_changes = [TimeSignatureChange::default()];
+    let time_signature_changes = if conf.time_signature_changes.is_empty() {
+        &default_time_signature_changes[..]
+    } else {
+        &conf.time_signature_changes[..]
+    };
+
+    compute_grid_lines(time_signature_changes, end_beat as f64)
+        .into_iter()
+        // There's no line needed at the very start of the grid
+        .filter(|line| line.beat > 0. && line.beat >= start_beat as f64)
+        .map(|line| {
+            let beat = line.beat as f32;
+            let x = conf.beats_to_px(beat);
+            let class = tern(line.is_measure_start, "measure-line", "beat-line");
+            let dom_id = js::render_line(FG_CANVAS_IX, x, 0, x, conf.grid_height(), class);
+            (beat, dom_id)
+        })
+        .collect()
 }
 
-pub fn draw_measure_lines(conf: &GridConf) {
-    // TODO: Move `measure_count` into `GridConf`
-    for i in 0..40 {
-        let x = conf.measure_width_px * i;
-        if i != 0 {
-            js::render_line(FG_CANVAS_IX, x, 0, x, conf.grid_height(), "measure-line");
-        }
-        for j in 1..4 {
-            let x = x + ((conf.measure_width_px / 4) * j);
-            js::render_line(FG_CANVAS_IX, x, 0, x, conf.grid_height(), "beat-line");
-        }
+/// Deletes all existing measure and beat lines and draws them again.  This is needed when the time
+/// signature changes since the lines can't just be repositioned.
+pub fn redraw_measure_lines(conf: &GridConf, background: &mut GridBackground) {
+    for (_, dom_id) in background.measure_lines.drain(..) {
+        js::delete_element(dom_id);
     }
+    background.measure_lines = draw_measure_lines(conf, background.beat_range);
 }
 
-pub fn draw_cursor_gutter(conf: &GridConf) {
+pub fn draw_cursor_gutter(conf: &GridConf, (start_px, end_px): (usize, usize)) -> DomId {
     js::render_quad(
         FG_CANVAS_IX,
+        start_px,
         0,
-        0,
-        conf.measure_width_px,
+        end_px - start_px,
         conf.cursor_gutter_height,
         "cursor-gutter",
         None,
-    );
+    )
 }
 
-/// Renders the initial grid with lines, measures, and the cursor gutter.
-pub fn render_initial_grid(conf: &GridConf, vc_id: &str) {
+/// Renders the initial grid with lines, measures, and the cursor gutter between the beats
+/// `beat_range`.
+pub fn render_initial_grid(conf: &GridConf, vc_id: &str, beat_range: (f32, f32)) -> GridBackground {
     js::init_grid(vc_id);
-    draw_cursor_gutter(conf);
-    draw_grid(conf);
-    draw_measure_lines(conf);
+    let px_range = (
+        conf.beats_to_px(beat_range.0),
+        conf.beats_to_px(beat_range.1),
+    );
+    GridBackground {
+        cursor_gutter: draw_cursor_gutter(conf, px_range),
+        grid_lines: draw_grid(conf, px_range),
+        measure_lines: draw_measure_lines(conf, beat_range),
+        beat_range,
+    }
+}
+
+/// Repositions all of the elements of an already-rendered grid background to match the current
+/// zoom of the provided `GridConf` and to cover the beats `beat_range`.  Measure and beat lines
+/// are only redrawn if the range has changed.
+pub fn update_grid_background(
+    conf: &GridConf,
+    background: &mut GridBackground,
+    beat_range: (f32, f32),
+) {
+    let range_changed = beat_range != background.beat_range;
+    background.beat_range = beat_range;
+    let (start_px, end_px) = background.px_range(conf);
+    let (x, width) = (start_px.to_string(), (end_px - start_px).to_string());
+
+    js::set_attr(background.cursor_gutter, "x", &x);
+    js::set_attr(background.cursor_gutter, "width", &width);
+
+    let line_height = conf.zoomed_line_height().to_string();
+    for (y, &dom_id) in background.grid_lines.iter().enumerate() {
+        js::set_attr(dom_id, "x", &x);
+        js::set_attr(dom_id, "width", &width);
+        js::set_attr(dom_id, "y", &get_grid_line_y(conf, y).to_string());
+        js::set_attr(dom_id, "height", &line_height);
+    }
+
+    if range_changed {
+        redraw_measure_lines(conf, background);
+        return;
+    }
+
+    let grid_height = conf.grid_height().to_string();
+    for &(beat, dom_id) in &background.measure_lines {
+        let x = conf.beats_to_px(beat).to_string();
+        js::set_attr(dom_id, "x1", &x);
+        js::set_attr(dom_id, "x2", &x);
+        js::set_attr(dom_id, "y2", &grid_height);
+    }
 }
@@ -1,20 +1,69 @@
+use std::{
+    fmt::Debug,
+    ops::{Add, Sub},
+};
+
+use fnv::FnvHashSet;
+
+use super::{
+    note_box::{NoteData, NoteId},
+    skip_list::NoteLines,
+    GridConf, GridRendererUniqueIdentifier,
+};
+
+/// A coordinate of a `SelectionRegion`.  Pixels are `usize`s, while positions on the grid are
+/// `f32`s of beats and lines which can be fractional or negative.
+pub trait Coord: Copy + PartialOrd + Debug + Add<Output = Self> + Sub<Output = Self> {
+    const ZERO: Self;
+}
+
+impl Coord for usize {
+    const ZERO: usize = 0;
+}
+
+impl Coord for f32 {
+    const ZERO: f32 = 0.;
+}
+
 /// A rectangular region of 2D space
 #[derive(Clone, PartialEq, Debug)]
-pub struct SelectionRegion {
-    pub x: usize,
-    pub y: usize,
-    pub width: usize,
-    pub height: usize,
+pub struct SelectionRegion<T = usize> {
+    pub x: T,
+    pub y: T,
+    pub width: T,
+    pub height: T,
+}
+
+/// Determines how the notes inside of a selection box change the notes that were selected before
+/// it was drawn
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SelectionBoxMode {
+    /// Notes inside of the box are added to the selection.  This is the default, used when
+    /// shift-dragging.
+    Add,
+    /// Notes inside of the box are removed from the selection.  Used when alt is held as well.
+    Subtract,
+}
+
+impl SelectionBoxMode {
+    /// Returns whether a note should be selected given whether it's inside of the box and whether
+    /// it was selected before the box was drawn
+    pub fn is_selected(self, in_box: bool, was_selected: bool) -> bool {
+        match self {
+            SelectionBoxMode::Add => in_box || was_selected,
+            SelectionBoxMode::Subtract => !in_box && was_selected,
+        }
+    }
 }
 
 /// Represents a rectangle of space that was either added or removed from the selection region.
 #[derive(Clone, PartialEq, Debug)]
-pub struct ChangedRegion {
+pub struct ChangedRegion<T = usize> {
     pub was_added: bool,
-    pub region: SelectionRegion,
+    pub region: SelectionRegion<T>,
 }
 
-fn min_max(n1: usize, n2: usize) -> (usize, usize) {
+fn min_max<T: Coord>(n1: T, n2: T) -> (T, T) {
     if n2 < n1 {
         (n2, n1)
     } else {
@@ -22,21 +71,25 @@ fn min_max(n1: usize, n2: usize) -> (usize, usize) {
     }
 }
 
-pub struct SelectionRegionPointIterator<'a> {
+fn min<T: Coord>(n1: T, n2: T) -> T { min_max(n1, n2).0 }
+
+fn max<T: Coord>(n1: T, n2: T) -> T { min_max(n1, n2).1 }
+
+pub struct SelectionRegionPointIterator<'a, T> {
     i: usize,
-    region: &'a SelectionRegion,
+    region: &'a SelectionRegion<T>,
 }
 
-impl<'a> SelectionRegionPointIterator<'a> {
-    pub fn new(region: &'a SelectionRegion) -> Self {
+impl<'a, T: Coord> SelectionRegionPointIterator<'a, T> {
+    pub fn new(region: &'a SelectionRegion<T>) -> Self {
         SelectionRegionPointIterator { i: 0, region }
     }
 }
 
-impl<'a> Iterator for SelectionRegionPointIterator<'a> {
-    type Item = (usize, usize);
+impl<'a, T: Coord> Iterator for SelectionRegionPointIterator<'a, T> {
+    type Item = (T, T);
 
-    fn next(&mut self) -> Option<(usize, usize)> {
+    fn next(&mut self) -> Option<(T, T)> {
         if self.i > 3 {
             return None;
         }
@@ -57,8 +110,8 @@ impl<'a> Iterator for SelectionRegionPointIterator<'a> {
     }
 }
 
-impl SelectionRegion {
-    pub fn from_points(x1: usize, y1: usize, x2: usize, y2: usize) -> Self {
+impl<T: Coord> SelectionRegion<T> {
+    pub fn from_points(x1: T, y1: T, x2: T, y2: T) -> Self {
         let (minx, maxx) = min_max(x1, x2);
         let (miny, maxy) = min_max(y1, y2);
 
@@ -70,25 +123,33 @@ impl SelectionRegion {
         }
     }
 
+    /// Computes how the selection changes when a selection box anchored at `(origin_x, origin_y)`
+    /// changes from `self` to `other`.  Returns the region shared by both boxes along with the two
+    /// regions that were added to or removed from the selection.  If the box flipped over the
+    /// origin, the boxes don't overlap, so there's no retained region and the changed regions are
+    /// the two boxes themselves.
+    ///
+    /// Notes that intersect the retained region keep their selection state, so only notes in the
+    /// changed regions need to be re-tested.
     pub fn diff(
         &self,
-        origin_x: usize,
-        origin_y: usize,
+        origin_x: T,
+        origin_y: T,
         other: &Self,
-    ) -> (Option<SelectionRegion>, ChangedRegion, ChangedRegion) {
-        let sum_origin = (self.x.min(other.x), self.y.min(other.y));
+    ) -> (Option<Self>, ChangedRegion<T>, ChangedRegion<T>) {
+        let sum_origin = (min(self.x, other.x), min(self.y, other.y));
         let sum_rev_origin = (
-            (self.x + self.width).max(other.x + other.width),
-            (self.y + self.height).max(other.y + other.height),
+            max(self.x + self.width, other.x + other.width),
+            max(self.y + self.height, other.y + other.height),
         );
 
         let sum_width = sum_rev_origin.0 - sum_origin.0;
         let sum_height = sum_rev_origin.1 - sum_origin.1;
 
-        let x_diff_left = self.x.max(other.x) - sum_origin.0;
-        let x_diff_right = sum_rev_origin.0 - (self.x + self.width).min(other.x + other.width);
-        let y_diff_top = self.y.max(other.y) - sum_origin.1;
-        let y_diff_bottom = sum_rev_origin.1 - (self.y + self.height).min(other.y + other.height);
+        let x_diff_left = max(self.x, other.x) - sum_origin.0;
+        let x_diff_right = sum_rev_origin.0 - min(self.x + self.width, other.x + other.width);
+        let y_diff_top = max(self.y, other.y) - sum_origin.1;
+        let y_diff_bottom = sum_rev_origin.1 - min(self.y + self.height, other.y + other.height);
 
         let y_crossed = (self.x >= origin_x) != (other.x >= origin_x);
         let x_crossed = (self.y >= origin_y) != (other.y >= origin_y);
@@ -96,7 +157,7 @@ impl SelectionRegion {
         if !x_crossed && !y_crossed {
             // the start and end x coordinates of the difference in horizontal space between
             // the old and new regions
-            let (x_region_added, x_region_bounds) = if x_diff_left > 0 {
+            let (x_region_added, x_region_bounds) = if x_diff_left > T::ZERO {
                 let bounds = (sum_origin.0, sum_origin.0 + x_diff_left);
                 let added = other.x == sum_origin.0;
                 (added, bounds)
@@ -108,7 +169,7 @@ impl SelectionRegion {
 
             // the start and end y coordinates of the difference in vertical space between
             // the old and new regions
-            let (y_region_added, y_region_bounds) = if y_diff_top > 0 {
+            let (y_region_added, y_region_bounds) = if y_diff_top > T::ZERO {
                 let bounds = (sum_origin.1, sum_origin.1 + y_diff_top);
                 let added = other.y == sum_origin.1;
                 (added, bounds)
@@ -121,10 +182,10 @@ impl SelectionRegion {
             let x_region_length = x_region_bounds.1 - x_region_bounds.0;
             let y_region_height = y_region_bounds.1 - y_region_bounds.0;
 
-            let retained_x = self.x.max(other.x);
-            let retained_y = self.y.max(other.y);
-            let min_max_x = (self.x + self.width).min(other.x + other.width);
-            let min_max_y = (self.y + self.height).min(other.y + other.height);
+            let retained_x = max(self.x, other.x);
+            let retained_y = max(self.y, other.y);
+            let min_max_x = min(self.x + self.width, other.x + other.width);
+            let min_max_y = min(self.y + self.height, other.y + other.height);
             let retained_region = SelectionRegion {
                 x: retained_x,
                 y: retained_y,
@@ -143,7 +204,7 @@ impl SelectionRegion {
                         // this region.
                         y: if x_region_added == y_region_added {
                             sum_origin.1
-                        } else if y_diff_top > 0 {
+                        } else if y_diff_top > T::ZERO {
                             // intersecting region is on top, so bound by that region's bottom
                             y_region_bounds.1
                         } else {
@@ -165,7 +226,7 @@ impl SelectionRegion {
                     was_added: y_region_added,
                     region: SelectionRegion {
                         // subtract the intersecting area from this axis
-                        x: if x_diff_left > 0 {
+                        x: if x_diff_left > T::ZERO {
                             // bounded by the right side of the x region
                             x_region_bounds.1
                         } else {
@@ -196,34 +257,106 @@ impl SelectionRegion {
         }
     }
 
-    pub fn iter_points(&'_ self) -> SelectionRegionPointIterator<'_> {
+    pub fn iter_points(&'_ self) -> SelectionRegionPointIterator<'_, T> {
         SelectionRegionPointIterator::new(&self)
     }
 
-    pub fn contains_point(&self, pt: (usize, usize)) -> bool {
+    pub fn contains_point(&self, pt: (T, T)) -> bool {
         pt.0 >= self.x
             && pt.0 <= (self.x + self.width)
             && pt.1 >= self.y
             && pt.1 <= (self.y + self.height)
     }
+
+    /// Returns `true` if the two regions overlap.  Like `contains_point`, this includes their
+    /// edges, so regions that only touch are considered to intersect.
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.x <= other.x + other.width
+            && other.x <= self.x + self.width
+            && self.y <= other.y + other.height
+            && other.y <= self.y + self.height
+    }
+}
+
+/// Returns all notes whose rectangles intersect `region`, which is in beats horizontally and lines
+/// vertically.  Only the lines that `region` spans are searched, and each line only from the first
+/// note that could intersect it.
+pub fn iter_notes_in_region<'a, S: GridRendererUniqueIdentifier>(
+    conf: &'a GridConf,
+    notes: &'a NoteLines<S>,
+    region: &SelectionRegion<f32>,
+) -> impl Iterator<Item = NoteData<'a, S>> + 'a {
+    let last_line_ix = conf.row_count - 1;
+    let get_line_index = |line: f32| (line.max(0.).trunc() as usize).min(last_line_ix);
+    let start_line_ix = get_line_index(region.y);
+    let end_line_ix = get_line_index(region.y + region.height);
+
+    let region = region.clone();
+    notes
+        .iter_region(start_line_ix, end_line_ix, region.x, region.x + region.width)
+        .filter(move |note_data| note_data.intersects_region(conf, &region))
+}
+
+/// Returns the notes whose selection state may have changed with the last update of a selection
+/// box, paired with whether or not they're now inside of it.  Only the changed regions are
+/// searched, and notes that intersect the retained region are skipped since their selection state
+/// can't have changed.  Notes in a removed region that are still inside the new box (because they
+/// extend into an added region, for example) are returned as being inside of it.
+pub fn iter_selection_changes<'a, S: GridRendererUniqueIdentifier>(
+    conf: &'a GridConf,
+    notes: &'a NoteLines<S>,
+    selection_box: &'a SelectionBoxData,
+) -> impl Iterator<Item = (NoteData<'a, S>, bool)> + 'a {
+    let SelectionBoxData {
+        region,
+        retained_region,
+        changed_region_1,
+        changed_region_2,
+    } = selection_box;
+
+    iter_notes_in_region(conf, notes, &changed_region_1.region)
+        .chain(iter_notes_in_region(conf, notes, &changed_region_2.region))
+        .filter(move |note_data| match retained_region {
+            Some(retained_region) => !note_data.intersects_region(conf, retained_region),
+            None => true,
+        })
+        .map(move |note_data| {
+            let is_selected = note_data.intersects_region(conf, region);
+            (note_data, is_selected)
+        })
+}
+
+/// Returns the IDs of all notes whose rectangles intersect `region`.  See `iter_notes_in_region`.
+pub fn get_note_ids_in_region<S: GridRendererUniqueIdentifier>(
+    conf: &GridConf,
+    notes: &NoteLines<S>,
+    region: &SelectionRegion<f32>,
+) -> FnvHashSet<NoteId> {
+    iter_notes_in_region(conf, notes, region)
+        .map(|note_data| note_data.note_box.id)
+        .collect()
 }
 
+/// The state of a selection box after the mouse moved while dragging it, along with how that
+/// changed the selected region.  All regions are in beats horizontally and lines vertically so that
+/// they don't depend on the grid's zoom.
 pub struct SelectionBoxData {
-    pub retained_region: Option<SelectionRegion>,
-    pub region: SelectionRegion,
-    pub changed_region_1: ChangedRegion,
-    pub changed_region_2: ChangedRegion,
+    pub retained_region: Option<SelectionRegion<f32>>,
+    pub region: SelectionRegion<f32>,
+    pub changed_region_1: ChangedRegion<f32>,
+    pub changed_region_2: ChangedRegion<f32>,
 }
 
 impl SelectionBoxData {
-    // TODO: Document this; I forgot what it does
+    /// Computes the selection box spanning from where the mouse was pressed to `(x, y)` and diffs
+    /// it against the box from the previous mouse position at `(last_x, last_y)`.
     pub fn compute(
-        mouse_down_x: usize,
-        mouse_down_y: usize,
-        x: usize,
-        y: usize,
-        last_x: usize,
-        last_y: usize,
+        mouse_down_x: f32,
+        mouse_down_y: f32,
+        x: f32,
+        y: f32,
+        last_x: f32,
+        last_y: f32,
     ) -> Self {
         let region = SelectionRegion::from_points(mouse_down_x, mouse_down_y, x, y);
         let last_region = SelectionRegion::from_points(mouse_down_x, mouse_down_y, last_x, last_y);
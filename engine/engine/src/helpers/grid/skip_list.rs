@@ -3,23 +3,34 @@
 //! note for a given beat, handling cases where the query is within an existing note or unbounded
 //! one or both sides.
 //!
+//! The list itself is generic over anything that implements `SkipListEntry`, which lets other
+//! things that are ordered by beat (automation breakpoints, loop markers, tempo changes) share it
+//! with notes.  `NoteSkipList` is the list specialized to hold `NoteBox`es.
+//!
 //! The time complexity for insertion, removal, and querying is `O(log n)`.
 
-extern crate test;
 use std::{
+    collections::HashMap,
     f32,
     fmt::{self, Debug, Formatter},
     marker::PhantomData,
     mem,
     num::NonZeroU32,
-    ptr, usize,
+    usize,
 };
 
+use common::{
+    pitch_bend::{normalize_curve, pitch_bend_at},
+    RNG_STREAM,
+};
 use rand::prelude::*;
+use rand_pcg::Pcg32;
 use slab::Slab;
 
 use super::prelude::*;
 
+/// A key into a `Slab`.  The slab index is stored offset by one so that `Option<SlabKey<T>>` can
+/// make use of the `NonZeroU32` optimization while still allowing index 0 to be used.
 pub struct SlabKey<T>(NonZeroU32, PhantomData<T>);
 
 // Doing this manually forces `Copy` to be implemented for `SlabKey<T>` even if `T` doesn't impl
@@ -34,72 +45,79 @@ impl<T> PartialEq for SlabKey<T> {
     fn eq(&self, other: &Self) -> bool { self.0 == other.0 }
 }
 
-pub type NodeSlabKey<S> = SlabKey<NoteSkipListNode<S>>;
+pub type SkipListNodeKey<T> = SlabKey<SkipListNode<T>>;
+pub type SkipListPreceedingLinks<T> = [SkipListNodeKey<T>; NOTE_SKIP_LIST_LEVELS];
+pub type SkipListLinks<T> = [Option<SkipListNodeKey<T>>; NOTE_SKIP_LIST_LEVELS];
+
+pub type NoteSkipList<S> = SkipList<NoteBox<S>>;
+pub type NoteSkipListNode<S> = SkipListNode<NoteBox<S>>;
+pub type NodeSlabKey<S> = SkipListNodeKey<NoteBox<S>>;
 pub type NoteBoxSlabKey<S> = SlabKey<NoteBox<S>>;
-pub type PreceedingLinks<S> = [NodeSlabKey<S>; NOTE_SKIP_LIST_LEVELS];
-pub type LinkOpts<S> = [Option<NodeSlabKey<S>>; NOTE_SKIP_LIST_LEVELS];
+pub type PreceedingLinks<S> = SkipListPreceedingLinks<NoteBox<S>>;
+pub type LinkOpts<S> = SkipListLinks<NoteBox<S>>;
 
 impl<T> Debug for SlabKey<T> {
     fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> { write!(fmt, "{}", self.key()) }
 }
 
 impl<T> SlabKey<T> {
-    pub fn key(self) -> usize { self.0.get() as usize }
+    pub fn key(self) -> usize { self.0.get() as usize - 1 }
 }
 
 impl<T> From<usize> for SlabKey<T> {
     fn from(key: usize) -> Self {
-        SlabKey(
-            unsafe { NonZeroU32::new_unchecked(key as u32) },
-            PhantomData,
-        )
+        let offset_key = NonZeroU32::new(key as u32 + 1).expect("Slab key overflowed `u32`");
+        SlabKey(offset_key, PhantomData)
+    }
+}
+
+/// Something that can be stored in a `SkipList`.  Entries are ordered by the start beat of their
+/// bounds, which are `f32`s and so can't go through `Ord`.  Zero-width bounds can be used for
+/// entries that sit at a single beat.
+pub trait SkipListEntry: Debug {
+    fn bounds(&self) -> NoteBoxBounds;
+
+    /// Returns `true` if `self` and `other` can't both be stored in the same list.  By default,
+    /// entries conflict if they overlap or start or end on the same beat; entries that only touch
+    /// at their edges don't conflict.
+    fn conflicts_with(&self, other: &Self) -> bool {
+        self.bounds().intersects_exclusive(&other.bounds())
     }
 }
 
+impl<S> SkipListEntry for NoteBox<S> {
+    fn bounds(&self) -> NoteBoxBounds { self.bounds }
+}
+
 #[derive(Clone, Debug, PartialEq)]
-pub struct NoteSkipListNode<S> {
-    pub val: NoteBox<S>,
+pub struct SkipListNode<T> {
+    pub val: T,
     /// Contains links to the next node in the sequence as well as all shortcuts that exist for
     /// that node.  In the case that there are no shortcuts available
-    pub links: LinkOpts<S>,
+    pub links: SkipListLinks<T>,
+    /// Link to the previous node in the sequence, which allows walking the list backwards.  Only
+    /// level 0 is doubly linked; shortcuts only go forwards.
+    pub prev: Option<SkipListNodeKey<T>>,
 }
 
 /// When debug-printing a `NoteSkipList`, we aren't able to implement debugging of an individual
 /// node as a pure function.  The reason for this that the arrows drawn between the different
 /// nodes depend on what nodes previously linked to it, and the distance can be large.
 ///
-/// This data structure holds a pointer to the next node for each of the levels of the skip list,
-/// allowing equality to be tested for arrow drawing.
-#[thread_local]
-pub static mut SKIP_LIST_NODE_DEBUG_POINTERS: *mut LinkOpts<usize> = ptr::null_mut();
-
-pub fn create_skip_list_dbg_ptrs() {
-    unsafe {
-        skip_list::SKIP_LIST_NODE_DEBUG_POINTERS = Box::into_raw(box skip_list::blank_shortcuts());
-    };
+/// This creates the buffer that holds a pointer to the next node for each of the levels of the
+/// skip list, allowing equality to be tested for arrow drawing.  It's owned by the caller for the
+/// duration of a single debug print so that no state is shared between different lists.
+pub fn init_node_dbg_ptrs<T>(head_key: SkipListNodeKey<T>) -> SkipListLinks<T> {
+    [Some(head_key); NOTE_SKIP_LIST_LEVELS]
 }
 
-pub fn init_node_dbg_ptrs(head_key: NodeSlabKey<usize>) {
-    for p in get_debug_ptrs() {
-        *p = Some(head_key);
-    }
+fn init_preceeding_links<T>(head_key: SkipListNodeKey<T>) -> SkipListPreceedingLinks<T> {
+    [head_key; NOTE_SKIP_LIST_LEVELS]
 }
 
-fn get_debug_ptrs() -> &'static mut LinkOpts<usize> {
-    unsafe { &mut *SKIP_LIST_NODE_DEBUG_POINTERS }
-}
-
-fn init_preceeding_links<S>(head_key: NodeSlabKey<S>) -> PreceedingLinks<S> {
-    let mut preceeding_links: PreceedingLinks<S> = unsafe { mem::uninitialized() };
-    for link in &mut preceeding_links {
-        *link = head_key;
-    }
-    preceeding_links
-}
-
-pub fn debug_preceeding_links<S: GridRendererUniqueIdentifier>(
-    line: &NoteSkipList<S>,
-    links: &PreceedingLinks<S>,
+pub fn debug_preceeding_links<T: SkipListEntry>(
+    line: &SkipList<T>,
+    links: &SkipListPreceedingLinks<T>,
 ) -> String {
     format!(
         "{:?}",
@@ -110,10 +128,7 @@ pub fn debug_preceeding_links<S: GridRendererUniqueIdentifier>(
     )
 }
 
-pub fn debug_links<S: GridRendererUniqueIdentifier>(
-    line: &NoteSkipList<S>,
-    links: &LinkOpts<S>,
-) -> String {
+pub fn debug_links<T: SkipListEntry>(line: &SkipList<T>, links: &SkipListLinks<T>) -> String {
     format!(
         "{:?}",
         links
@@ -128,10 +143,10 @@ pub fn debug_links<S: GridRendererUniqueIdentifier>(
 ///
 /// TODO: Make O(1)?
 #[inline]
-pub fn get_skip_list_level() -> usize {
+pub fn get_skip_list_level<R: Rng>(rng: &mut R) -> usize {
     let mut level = 0;
     for _ in 0..(NOTE_SKIP_LIST_LEVELS - 1) {
-        if rng().gen::<bool>() {
+        if rng.gen::<bool>() {
             break;
         }
         level += 1;
@@ -140,12 +155,8 @@ pub fn get_skip_list_level() -> usize {
 }
 
 #[inline]
-pub fn blank_shortcuts<T>() -> [Option<T>; NOTE_SKIP_LIST_LEVELS] {
-    let mut shortcuts: [Option<T>; NOTE_SKIP_LIST_LEVELS] = unsafe { mem::uninitialized() };
-    for link in &mut shortcuts[..] {
-        unsafe { ptr::write(link, None) };
-    }
-    shortcuts
+pub fn blank_shortcuts<T: Copy>() -> [Option<T>; NOTE_SKIP_LIST_LEVELS] {
+    [None; NOTE_SKIP_LIST_LEVELS]
 }
 
 #[derive(Debug, PartialEq)]
@@ -177,11 +188,47 @@ impl<S: GridRendererUniqueIdentifier> Bounds<S> {
     }
 }
 
+/// How `NoteSkipList::insert_with_policy` handles a new note that overlaps existing notes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OverlapPolicy {
+    /// The new note isn't inserted
+    Reject,
+    /// A note that starts before the new note is shortened to end where it starts.  Notes that
+    /// start inside of the new note are removed.
+    Truncate,
+    /// All overlapping notes are removed
+    Replace,
+}
+
+/// Why `NoteLines::move_notes`, `transpose`, or `shift` couldn't move a set of notes.  Each
+/// variant holds the index of the move or selected note that failed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MoveError {
+    /// There was no note starting at the move's start beat on its source line
+    NoteNotFound(usize),
+    /// The note would collide with a note that isn't being moved at its destination
+    Blocked(usize),
+    /// The note would be moved off of the grid
+    OffGrid(usize),
+}
+
+/// The changes made to a line by `NoteSkipList::insert_with_policy`
+#[derive(Debug)]
+pub struct PolicyInsertion<S> {
+    /// The note that was being inserted if it was rejected
+    pub rejected: Option<NoteBox<S>>,
+    /// The IDs and new bounds of the notes that were shortened to make room for the new note
+    pub truncated: Vec<(NoteId, NoteBoxBounds)>,
+    /// The notes that were removed to make room for the new note
+    pub removed: Vec<NoteBox<S>>,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct NoteEvent {
     pub line_ix: usize,
     pub is_start: bool,
     pub beat: f32,
+    pub velocity: u8,
 }
 
 #[derive(Clone, Copy)]
@@ -216,6 +263,10 @@ impl<'a, S: GridRendererUniqueIdentifier> FrontierNode<'a, S> {
                 FrontierNode::StartBeatConsumed(..) => false,
             },
             beat: self.beat(),
+            velocity: match self {
+                FrontierNode::NoneConsumed(_list, node) => node.val.velocity,
+                FrontierNode::StartBeatConsumed(_list, node) => node.val.velocity,
+            },
         }
     }
 }
@@ -280,30 +331,30 @@ impl<'a, S: GridRendererUniqueIdentifier> NoteEventIterator<'a, S> {
     }
 }
 
-impl<S: GridRendererUniqueIdentifier> NoteSkipListNode<S> {
+impl<T: SkipListEntry> SkipListNode<T> {
     /// Returns the slot index of the last node that has a value less than that of the target
     /// value.  If `target_val` is less than all other values in the collection, then `None`
     /// is returned.
     pub fn search<'a>(
         &'a self,
-        list: &NoteSkipList<S>,
+        list: &SkipList<T>,
         target_val: f32,
-        self_key: NodeSlabKey<S>,
-        levels: &mut PreceedingLinks<S>,
+        self_key: SkipListNodeKey<T>,
+        levels: &mut SkipListPreceedingLinks<T>,
     ) {
         // if we try searching a node greater than the target value, we've messed up badly
-        debug_assert!(self.val.bounds.end_beat <= target_val);
+        debug_assert!(self.val.bounds().end_beat <= target_val);
         // Starting with the top level and working down, check if the value behind the shortcut is
         // higher or lower than the current value.
         let mut link_level = NOTE_SKIP_LIST_LEVELS - 1;
         loop {
             if let Some(shortcut_node_slot_key) = self.links[link_level] {
-                let shortcut_node: &NoteSkipListNode<S> = list.get_node(shortcut_node_slot_key);
+                let shortcut_node: &SkipListNode<T> = list.get_node(shortcut_node_slot_key);
                 let shortcut_note = &shortcut_node.val;
 
                 // if this shortcut value is still smaller, take the shortcut and continue
                 // searching.
-                if shortcut_note.bounds.end_beat <= target_val {
+                if shortcut_note.bounds().end_beat <= target_val {
                     // Record the preceeding index for all levels for which we have a pointer
                     for level in &mut levels[0..=link_level] {
                         *level = shortcut_node_slot_key;
@@ -323,16 +374,17 @@ impl<S: GridRendererUniqueIdentifier> NoteSkipListNode<S> {
     }
 }
 
+/// Holds a set of non-conflicting entries sorted by start beat
 #[derive(Clone)]
-pub struct NoteSkipList<S> {
-    pub nodes: Slab<NoteSkipListNode<S>>,
-    pub head_key: Option<NodeSlabKey<S>>,
+pub struct SkipList<T> {
+    pub nodes: Slab<SkipListNode<T>>,
+    pub head_key: Option<SkipListNodeKey<T>>,
+    /// Used to pick the level of each inserted node.  Each list owns its own so that the shape of
+    /// the list only depends on the notes inserted into it and the seed.
+    rng: Pcg32,
 }
 
-// NOTE: This current implementation is completely unsafe and assumes that `S` is always just
-// `usize`.  This is obviously not valid in the general case, and this will need to be updated
-// to account for that in the future.
-impl<S: GridRendererUniqueIdentifier> Debug for NoteSkipList<S> {
+impl<T: SkipListEntry> Debug for SkipList<T> {
     /// We want the end result to look something like this:
     ///
     /// |1.0, 2.0|------------------------->|4.0, 5.0|->x
@@ -341,12 +393,13 @@ impl<S: GridRendererUniqueIdentifier> Debug for NoteSkipList<S> {
     fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
         let mut node_debug_lines = Vec::new();
         // initialize the debug pointers with the head
-        if let Some(head_key) = self.head_key {
-            init_node_dbg_ptrs(unsafe { mem::transmute(head_key) });
-        }
+        let mut debug_ptrs = match self.head_key {
+            Some(head_key) => init_node_dbg_ptrs(head_key),
+            None => blank_shortcuts(),
+        };
 
         for node in self.iter_nodes() {
-            let debug_s = self.debug_node(node);
+            let debug_s = self.debug_node(node, &mut debug_ptrs);
             // Don't ask why it's "\\n" and not '\n'; I don't know.
             let debug_lines: Vec<String> =
                 debug_s.split('\n').map(|s| s.into()).collect::<Vec<_>>();
@@ -368,36 +421,140 @@ impl<S: GridRendererUniqueIdentifier> Debug for NoteSkipList<S> {
     }
 }
 
-pub struct NoteSkipListIterator<'a, S: GridRendererUniqueIdentifier> {
-    line: &'a NoteSkipList<S>,
-    cur_node: Option<&'a NoteSkipListNode<S>>,
+pub struct SkipListIterator<'a, T: SkipListEntry> {
+    line: &'a SkipList<T>,
+    cur_node: Option<&'a SkipListNode<T>>,
 }
 
-impl<'a, S: GridRendererUniqueIdentifier> Iterator for NoteSkipListIterator<'a, S> {
-    type Item = &'a NoteBox<S>;
+impl<'a, T: SkipListEntry> Iterator for SkipListIterator<'a, T> {
+    type Item = &'a T;
 
-    fn next(&mut self) -> Option<&'a NoteBox<S>> {
+    fn next(&mut self) -> Option<&'a T> {
         let node = self.cur_node?;
         self.cur_node = self.line.next_node(node);
         Some(&node.val)
     }
 }
 
-pub struct NoteSkipListNodeIterator<'a, S: GridRendererUniqueIdentifier> {
-    line: &'a NoteSkipList<S>,
-    cur_node: Option<&'a NoteSkipListNode<S>>,
+pub struct SkipListNodeIterator<'a, T: SkipListEntry> {
+    line: &'a SkipList<T>,
+    cur_node: Option<&'a SkipListNode<T>>,
 }
 
-impl<'a, S: GridRendererUniqueIdentifier> Iterator for NoteSkipListNodeIterator<'a, S> {
-    type Item = &'a NoteSkipListNode<S>;
+impl<'a, T: SkipListEntry> Iterator for SkipListNodeIterator<'a, T> {
+    type Item = &'a SkipListNode<T>;
 
-    fn next(&mut self) -> Option<&'a NoteSkipListNode<S>> {
+    fn next(&mut self) -> Option<&'a SkipListNode<T>> {
         let node = self.cur_node?;
         self.cur_node = self.line.next_node(node);
         Some(node)
     }
 }
 
+pub struct SkipListRevIterator<'a, T: SkipListEntry> {
+    line: &'a SkipList<T>,
+    cur_node: Option<&'a SkipListNode<T>>,
+}
+
+impl<'a, T: SkipListEntry> Iterator for SkipListRevIterator<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let node = self.cur_node?;
+        self.cur_node = self.line.prev_node(node);
+        Some(&node.val)
+    }
+}
+
+/// A position inside of a `SkipList` that can be moved between entries and used to edit the list
+/// around it.  Moving to an adjacent entry follows a single link rather than searching from the
+/// head, which makes it cheap to walk the list a little bit at a time.
+///
+/// When the cursor moves off either end of the list it points at no entry; moving it again wraps
+/// around to the other end, like the cursors of `std::collections::LinkedList`.
+pub struct Cursor<'a, T: SkipListEntry> {
+    list: &'a mut SkipList<T>,
+    cur_key: Option<SkipListNodeKey<T>>,
+}
+
+impl<'a, T: SkipListEntry> Cursor<'a, T> {
+    pub fn current(&self) -> Option<&T> { self.cur_key.map(|key| &self.list.get_node(key).val) }
+
+    /// Moves the cursor to the next entry and returns it
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<&T> {
+        self.cur_key = match self.cur_key {
+            Some(key) => self.list.get_node(key).links[0],
+            None => self.list.head_key,
+        };
+        self.current()
+    }
+
+    /// Moves the cursor to the previous entry and returns it
+    pub fn prev(&mut self) -> Option<&T> {
+        self.cur_key = match self.cur_key {
+            Some(key) => self.list.get_node(key).prev,
+            None => self.list.find_last_node_starting_before(f32::INFINITY),
+        };
+        self.current()
+    }
+
+    /// Inserts `val` directly after the current entry, or at the front of the list if the cursor
+    /// isn't pointing at an entry.  The cursor doesn't move.  Returns `Some(val)` without
+    /// inserting it if it doesn't belong at that position or conflicts with one of its neighbors.
+    pub fn insert_after(&mut self, val: T) -> Option<T> {
+        let start_beat = val.bounds().start_beat;
+        let next_key = match self.cur_key {
+            Some(key) => {
+                let cur_node = self.list.get_node(key);
+                if start_beat < cur_node.val.bounds().start_beat {
+                    return Some(val);
+                }
+                cur_node.links[0]
+            },
+            None => self.list.head_key,
+        };
+        if let Some(next_key) = next_key {
+            if start_beat > self.list.get_node(next_key).val.bounds().start_beat {
+                return Some(val);
+            }
+        }
+
+        // The shortcuts leading to the new node still have to be found, so the regular insertion
+        // path is used to link it in.
+        self.list.insert(val)
+    }
+
+    /// Removes the current entry and returns it, moving the cursor to the entry after it
+    pub fn remove_current(&mut self) -> Option<T> {
+        let cur_node = self.list.get_node(self.cur_key?);
+        let start_beat = cur_node.val.bounds().start_beat;
+        self.cur_key = cur_node.links[0];
+        self.list.remove(start_beat)
+    }
+}
+
+pub struct SkipListRangeIterator<'a, T: SkipListEntry> {
+    line: &'a SkipList<T>,
+    cur_node: Option<&'a SkipListNode<T>>,
+    end_beat: f32,
+}
+
+impl<'a, T: SkipListEntry> Iterator for SkipListRangeIterator<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let node = self.cur_node?;
+        if node.val.bounds().start_beat > self.end_beat {
+            self.cur_node = None;
+            return None;
+        }
+
+        self.cur_node = self.line.next_node(node);
+        Some(&node.val)
+    }
+}
+
 pub struct NoteSkipListRegionIterator<'a, S: GridRendererUniqueIdentifier> {
     pub start_line_ix: usize,
     pub end_line_ix: usize,
@@ -454,8 +611,10 @@ impl<'a> Into<SelectedNoteData> for NoteData<'a, usize> {
         SelectedNoteData {
             line_ix: self.line_ix,
             dom_id: self.note_box.data,
+            note_id: self.note_box.id,
             start_beat: self.note_box.bounds.start_beat,
             width: self.note_box.bounds.width(),
+            velocity: self.note_box.velocity,
         }
     }
 }
@@ -482,72 +641,76 @@ impl<'a, S: GridRendererUniqueIdentifier> Iterator for NoteSkipListRegionIterato
     }
 }
 
-impl<S> Default for NoteSkipList<S> {
-    fn default() -> Self {
-        let mut nodes = Slab::with_capacity(NODES_SLAB_CAPACITY);
-
-        // Insert a placeholder since we can't use index 0 due to the `NonZeroU32` optimization
-        nodes.insert(NoteSkipListNode {
-            val: unsafe { mem::uninitialized() },
-            links: blank_shortcuts(),
-        });
+impl<T> Default for SkipList<T> {
+    /// Creates an empty list seeded from the global PRNG
+    fn default() -> Self { SkipList::with_seed(with_rng(|rng| rng.gen())) }
+}
 
-        NoteSkipList {
+impl<T> SkipList<T> {
+    /// Creates an empty list whose levels are generated from `seed`, making its structure
+    /// reproducible for the same sequence of operations.
+    pub fn with_seed(seed: u64) -> Self {
+        SkipList {
             head_key: None,
-            nodes,
+            nodes: Slab::with_capacity(NODES_SLAB_CAPACITY),
+            rng: Pcg32::new(seed, RNG_STREAM),
         }
     }
 }
 
-impl<S: GridRendererUniqueIdentifier> NoteSkipList<S> {
-    pub fn get_node<'a>(&'a self, key: SlabKey<NoteSkipListNode<S>>) -> &'a NoteSkipListNode<S> {
+impl<T: SkipListEntry> SkipList<T> {
+    pub fn get_node<'a>(&'a self, key: SkipListNodeKey<T>) -> &'a SkipListNode<T> {
         &self.nodes[key.key()]
     }
 
     pub fn get_node_mut<'a>(
         &'a mut self,
-        key: SlabKey<NoteSkipListNode<S>>,
-    ) -> &'a mut NoteSkipListNode<S> {
+        key: SkipListNodeKey<T>,
+    ) -> &'a mut SkipListNode<T> {
         &mut self.nodes[key.key()]
     }
 
-    pub fn head(&self) -> Option<&NoteSkipListNode<S>> { self.head_key.map(|k| self.get_node(k)) }
+    pub fn head(&self) -> Option<&SkipListNode<T>> { self.head_key.map(|k| self.get_node(k)) }
 
-    pub fn head_mut(&mut self) -> Option<&mut NoteSkipListNode<S>> {
+    pub fn head_mut(&mut self) -> Option<&mut SkipListNode<T>> {
         match self.head_key {
             Some(k) => Some(self.get_node_mut(k)),
             None => None,
         }
     }
 
-    pub fn next_node(&self, node: &NoteSkipListNode<S>) -> Option<&NoteSkipListNode<S>> {
+    pub fn next_node(&self, node: &SkipListNode<T>) -> Option<&SkipListNode<T>> {
         node.links[0].map(|p| self.get_node(p))
     }
 
+    pub fn prev_node(&self, node: &SkipListNode<T>) -> Option<&SkipListNode<T>> {
+        node.prev.map(|p| self.get_node(p))
+    }
+
     /// This is a sad function because we can't just give `&'a mut self` and
     /// `&'a mut NoteSkipListNode` due to  lifetime reasons.  It works though :shrug:
     pub fn next_node_mut<'a>(
         &'a mut self,
-        node_key: SlabKey<NoteSkipListNode<S>>,
-    ) -> Option<&'a mut NoteSkipListNode<S>> {
+        node_key: SkipListNodeKey<T>,
+    ) -> Option<&'a mut SkipListNode<T>> {
         let node = &self.nodes[node_key.key()];
         node.links[0].map(move |p| self.get_node_mut(p))
     }
 
-    /// Deallocates the slab slots for both the node and its `NoteBox`, returning the inner
-    /// `NoteBox`.
-    fn dealloc_node(&mut self, node_key: NodeSlabKey<S>) -> NoteBox<S> {
+    /// Deallocates the slab slot for the node, returning the entry it held.
+    fn dealloc_node(&mut self, node_key: SkipListNodeKey<T>) -> T {
         self.nodes.remove(node_key.key()).val
     }
 
     /// Inserts a node into the skip list in order.  Returns `None` if the node was inserted
     /// successfully and `Some(note)` returning the supplied note if there is an intersecting node
     /// blocking it from being inserted.
-    pub fn insert(&mut self, note: NoteBox<S>) -> Option<NoteBox<S>> {
+    pub fn insert(&mut self, note: T) -> Option<T> {
         trace!("Trying to insert note: {:?}", note);
-        let mut new_node = NoteSkipListNode {
+        let mut new_node = SkipListNode {
             val: note,
             links: blank_shortcuts(),
+            prev: None,
         };
 
         if self.head_key.is_none() {
@@ -558,12 +721,12 @@ impl<S: GridRendererUniqueIdentifier> NoteSkipList<S> {
 
         let head_key = self.head_key.unwrap();
 
-        let level = get_skip_list_level();
+        let level = get_skip_list_level(&mut self.rng);
         let head_note = &self.get_node(head_key).val;
         // Only bother searching if the head is smaller than the target value.  If the head is
         // larger, we automatically insert it at the front.
-        if head_note.bounds.end_beat > new_node.val.bounds.start_beat {
-            if head_note.bounds.intersects_exclusive(&new_node.val.bounds) {
+        if head_note.bounds().end_beat > new_node.val.bounds().start_beat {
+            if head_note.conflicts_with(&new_node.val) {
                 return Some(new_node.val);
             }
 
@@ -582,26 +745,27 @@ impl<S: GridRendererUniqueIdentifier> NoteSkipList<S> {
                 };
                 let preceeding_note = &preceeding_node.val;
 
-                debug_assert!(!preceeding_note
-                    .bounds
-                    .intersects_exclusive(&new_node.val.bounds));
+                debug_assert!(!preceeding_note.conflicts_with(&new_node.val));
             }
             new_node.links[old_links_range.clone()]
                 .clone_from_slice(&self.head().unwrap().links[old_links_range]);
-            self.head_key = Some(self.nodes.insert(new_node).into());
+            let new_head_key = self.nodes.insert(new_node).into();
+            self.head_key = Some(new_head_key);
 
             // Erase any links from the old head that are above the newly generated level for the
             // new head; we're going to link to those ourselves.
-            for link in &mut self.head_mut().unwrap().links[(level + 1)..NOTE_SKIP_LIST_LEVELS] {
+            let old_head = self.get_node_mut(head_key);
+            for link in &mut old_head.links[(level + 1)..NOTE_SKIP_LIST_LEVELS] {
                 *link = None;
             }
+            old_head.prev = Some(new_head_key);
             return None;
         }
 
         let mut preceeding_links = init_preceeding_links(head_key);
         self.head().unwrap().search(
             &self,
-            new_node.val.bounds.start_beat,
+            new_node.val.bounds().start_beat,
             head_key,
             &mut preceeding_links,
         );
@@ -609,17 +773,14 @@ impl<S: GridRendererUniqueIdentifier> NoteSkipList<S> {
         // check if the note before the new one intersects it
         let first_link_node = self.get_node(preceeding_links[0]);
         let first_link_note = &first_link_node.val;
-        if first_link_note
-            .bounds
-            .intersects_exclusive(&new_node.val.bounds)
-        {
+        if first_link_note.conflicts_with(&new_node.val) {
             return Some(new_node.val);
         }
 
         // check if the note after the new one intersects it (if it exists)
         if let Some(next_node_key) = first_link_node.links[0] {
             let next_note = &self.get_node(next_node_key).val;
-            if next_note.bounds.intersects_exclusive(&new_node.val.bounds) {
+            if next_note.conflicts_with(&new_node.val) {
                 return Some(new_node.val);
             }
         }
@@ -632,17 +793,19 @@ impl<S: GridRendererUniqueIdentifier> NoteSkipList<S> {
         //
         // We link the new node to the following nodes here.  We can't link the previous nodes to
         // the new one here as well due to lifetime issues.
+        new_node.prev = Some(preceeding_links[0]);
         for i in 0..=level {
             let preceeding_node_for_level = &mut self.get_node(preceeding_links[i]);
             new_node.links[i] = preceeding_node_for_level.links[i];
-            debug_assert!(!preceeding_node_for_level
-                .val
-                .bounds
-                .intersects_exclusive(&new_node.val.bounds));
+            debug_assert!(!preceeding_node_for_level.val.conflicts_with(&new_node.val));
         }
 
         // Actually insert the new node into the nodes slab
-        let new_node_key: SlabKey<NoteSkipListNode<S>> = self.nodes.insert(new_node).into();
+        let next_node_key = new_node.links[0];
+        let new_node_key: SkipListNodeKey<T> = self.nodes.insert(new_node).into();
+        if let Some(next_node_key) = next_node_key {
+            self.get_node_mut(next_node_key).prev = Some(new_node_key);
+        }
 
         for i in 0..=level {
             let preceeding_node_for_level = self.get_node_mut(preceeding_links[i]);
@@ -654,20 +817,91 @@ impl<S: GridRendererUniqueIdentifier> NoteSkipList<S> {
         None
     }
 
-    /// Removes any note box that contains the given beat.
-    pub fn remove(&mut self, start_beat: f32) -> Option<NoteBox<S>> {
-        let head_key = self
-            .head_key
-            .expect("Attempted to remove node from line with no head node");
+    /// Removes all notes from the line, walking the level-0 links to free the slab slot of every
+    /// node.  The list can still be used afterwards.
+    pub fn clear(&mut self) {
+        let mut cur_key = self.head_key.take();
+        while let Some(node_key) = cur_key {
+            cur_key = self.nodes.remove(node_key.key()).links[0];
+        }
+        debug_assert!(self.nodes.is_empty());
+    }
+
+    /// Inserts all of `notes`, returning any that intersect another note and couldn't be inserted.
+    ///
+    /// If the list is empty and `notes` is sorted by start beat, the list is built bottom-up in a
+    /// single pass by linking each new node onto the last node of every level it's part of.  This
+    /// avoids searching the list for every note when loading large compositions.  Otherwise, the
+    /// notes are inserted one at a time.
+    pub fn insert_sorted_batch(&mut self, notes: Vec<T>) -> Vec<T> {
+        let is_sorted = notes
+            .windows(2)
+            .all(|pair| pair[0].bounds().start_beat <= pair[1].bounds().start_beat);
+        if !self.is_empty() || !is_sorted {
+            return notes
+                .into_iter()
+                .filter_map(|note| self.insert(note))
+                .collect();
+        }
+
+        let mut rejected = Vec::new();
+        // The last node inserted into each level, which the next node on that level links from
+        let mut tails: SkipListLinks<T> = blank_shortcuts();
+        for note in notes {
+            if let Some(tail_key) = tails[0] {
+                if self.get_node(tail_key).val.conflicts_with(&note) {
+                    rejected.push(note);
+                    continue;
+                }
+            }
+
+            // The head is linked from on every level, matching what `insert` does.
+            let is_head = self.head_key.is_none();
+            let level = if is_head {
+                NOTE_SKIP_LIST_LEVELS - 1
+            } else {
+                get_skip_list_level(&mut self.rng)
+            };
+            let new_node_key: SkipListNodeKey<T> = self
+                .nodes
+                .insert(SkipListNode {
+                    val: note,
+                    links: blank_shortcuts(),
+                    prev: tails[0],
+                })
+                .into();
+            for (link_level, tail) in tails[0..=level].iter_mut().enumerate() {
+                if let Some(tail_key) = *tail {
+                    self.get_node_mut(tail_key).links[link_level] = Some(new_node_key);
+                }
+                *tail = Some(new_node_key);
+            }
+            if is_head {
+                self.head_key = Some(new_node_key);
+            }
+        }
+
+        rejected
+    }
+
+    /// Removes the note box that starts at exactly `start_beat`, returning it if it was found.
+    ///
+    /// The node is unlinked from every level that it participates in and its slab slot is freed.
+    /// If the removed node is the head, the following node is promoted to be the new head and
+    /// inherits any of the old head's shortcuts that skipped over it.
+    pub fn remove(&mut self, start_beat: f32) -> Option<T> {
+        let head_key = self.head_key?;
         let head_note = &self.get_node(head_key).val;
 
-        if head_note.bounds.start_beat == start_beat {
+        if head_note.bounds().start_beat > start_beat {
+            return None;
+        } else if head_note.bounds().start_beat == start_beat {
             // The head is being removed.  Replace it with the next child (copying over links where
             // applicable) if there is one.
-
             let head_links = self.get_node(head_key).links;
             if let Some(new_head_key) = head_links[0] {
                 let new_head = self.get_node_mut(new_head_key);
+                new_head.prev = None;
                 for level in 1..NOTE_SKIP_LIST_LEVELS {
                     if new_head.links[level].is_none() && head_links[level] != Some(new_head_key) {
                         new_head.links[level] = head_links[level];
@@ -681,45 +915,161 @@ impl<S: GridRendererUniqueIdentifier> NoteSkipList<S> {
             return Some(self.dealloc_node(head_key));
         }
 
+        // Find the last node before the target node for each level.  We compare start beats rather
+        // than using `search` here so that zero-width notes are handled correctly.
         let mut preceeding_links = init_preceeding_links(head_key);
-        let head = self.get_node(head_key);
-        head.search(self, start_beat, head_key, &mut preceeding_links);
+        let mut cur_key = head_key;
+        for level in (0..NOTE_SKIP_LIST_LEVELS).rev() {
+            while let Some(next_key) = self.get_node(cur_key).links[level] {
+                if self.get_node(next_key).val.bounds().start_beat >= start_beat {
+                    break;
+                }
+                cur_key = next_key;
+            }
+            preceeding_links[level] = cur_key;
+        }
+
         let removed_node_key = self.get_node(preceeding_links[0]).links[0]?;
+        if self.get_node(removed_node_key).val.bounds().start_beat != start_beat {
+            return None;
+        }
 
-        // For each preceeding link, sever the link to the node being removed and attach it to
-        // wherever the node being removed is pointing for that level (if anywhere).
+        // For each level that links to the node being removed, sever that link and attach it to
+        // wherever the node being removed is pointing for that level (if anywhere).  Levels that
+        // skip over the removed node are left untouched.
         let removed_node_links = self.get_node(removed_node_key).links;
         for level in 0..NOTE_SKIP_LIST_LEVELS {
-            self.get_node_mut(preceeding_links[level]).links[level] = removed_node_links[level];
+            let preceeding_node = self.get_node_mut(preceeding_links[level]);
+            if preceeding_node.links[level] == Some(removed_node_key) {
+                preceeding_node.links[level] = removed_node_links[level];
+            }
+        }
+        if let Some(next_node_key) = removed_node_links[0] {
+            self.get_node_mut(next_node_key).prev = Some(preceeding_links[0]);
         }
 
-        // free the slab slots for the removed node and note
+        // free the slab slot for the removed node and note
         Some(self.dealloc_node(removed_node_key))
     }
 
-    pub fn iter<'a>(&'a self) -> impl Iterator<Item = &'a NoteBox<S>> + 'a {
-        NoteSkipListIterator {
+    /// Returns the number of notes in this line
+    pub fn len(&self) -> usize { self.nodes.len() }
+
+    pub fn is_empty(&self) -> bool { self.head_key.is_none() }
+
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = &'a T> + 'a {
+        SkipListIterator {
             line: self,
             cur_node: self.head(),
         }
     }
 
-    pub fn iter_nodes<'a>(&'a self) -> impl Iterator<Item = &'a NoteSkipListNode<S>> + 'a {
-        NoteSkipListNodeIterator {
+    /// Returns an iterator that walks backwards from the last entry starting at or before `beat`
+    /// to the start of the list.
+    pub fn iter_rev_from<'a>(&'a self, beat: f32) -> impl Iterator<Item = &'a T> + 'a {
+        SkipListRevIterator {
+            line: self,
+            cur_node: self
+                .find_last_node_starting_before(beat)
+                .map(|key| self.get_node(key)),
+        }
+    }
+
+    /// Returns the key of the last node that starts at or before `beat`, or `None` if every node
+    /// starts after it.
+    pub fn find_last_node_starting_before(&self, beat: f32) -> Option<SkipListNodeKey<T>> {
+        let head_key = self.head_key?;
+        if self.get_node(head_key).val.bounds().start_beat > beat {
+            return None;
+        }
+
+        let mut cur_key = head_key;
+        for level in (0..NOTE_SKIP_LIST_LEVELS).rev() {
+            while let Some(next_key) = self.get_node(cur_key).links[level] {
+                if self.get_node(next_key).val.bounds().start_beat > beat {
+                    break;
+                }
+                cur_key = next_key;
+            }
+        }
+        Some(cur_key)
+    }
+
+    /// Returns the key of the node that starts at exactly `start_beat`, if there is one
+    pub fn find_node_starting_at(&self, start_beat: f32) -> Option<SkipListNodeKey<T>> {
+        let node_key = self.find_last_node_starting_before(start_beat)?;
+        if self.get_node(node_key).val.bounds().start_beat == start_beat {
+            Some(node_key)
+        } else {
+            None
+        }
+    }
+
+    /// Returns a cursor pointing at the first entry in the list
+    pub fn cursor_front<'a>(&'a mut self) -> Cursor<'a, T> {
+        let cur_key = self.head_key;
+        Cursor {
+            list: self,
+            cur_key,
+        }
+    }
+
+    /// Returns a cursor pointing at the last entry that starts at or before `beat`, or at no entry
+    /// if there isn't one.
+    pub fn cursor_at<'a>(&'a mut self, beat: f32) -> Cursor<'a, T> {
+        let cur_key = self.find_last_node_starting_before(beat);
+        Cursor {
+            list: self,
+            cur_key,
+        }
+    }
+
+    pub fn iter_nodes<'a>(&'a self) -> impl Iterator<Item = &'a SkipListNode<T>> + 'a {
+        SkipListNodeIterator {
             line: self,
             cur_node: self.head(),
         }
     }
 
-    fn find_first_node_in_range(
-        &self,
+    /// Returns an iterator over all notes in this line that intersect the range between
+    /// `start_beat` and `end_beat`.  The skip list's shortcuts are used to seek to the first
+    /// matching note, after which level-0 links are followed until the range is exhausted.
+    pub fn iter_range<'a>(
+        &'a self,
         start_beat: f32,
         end_beat: f32,
-    ) -> Option<&NoteSkipListNode<S>> {
+    ) -> impl Iterator<Item = &'a T> + 'a {
+        let range = NoteBoxBounds {
+            start_beat,
+            end_beat,
+        };
+        let cur_node = self
+            .find_first_node_in_range(start_beat, end_beat)
+            .and_then(|node| {
+                if node.val.bounds().intersects(&range) {
+                    Some(node)
+                } else {
+                    // The found node is the last one before the range; its child may be valid
+                    self.next_node(node)
+                }
+            });
+
+        SkipListRangeIterator {
+            line: self,
+            cur_node,
+            end_beat,
+        }
+    }
+
+    fn find_first_node_in_range(&self, start_beat: f32, end_beat: f32) -> Option<&SkipListNode<T>> {
+        let range = NoteBoxBounds {
+            start_beat,
+            end_beat,
+        };
         let head = self.head()?;
-        if head.val.bounds.start_beat > end_beat {
+        if head.val.bounds().start_beat > end_beat {
             return None;
-        } else if head.val.intersects_beats(start_beat, end_beat) {
+        } else if head.val.bounds().intersects(&range) {
             return Some(head);
         }
 
@@ -730,7 +1080,9 @@ impl<S: GridRendererUniqueIdentifier> NoteSkipList<S> {
             for level in (0..=max_level).rev() {
                 match checking_node.links[level] {
                     // shortcut takes us to an invalid node that is still before our desired range
-                    Some(node_key) if self.get_node(node_key).val.bounds.end_beat < start_beat => {
+                    Some(node_key)
+                        if self.get_node(node_key).val.bounds().end_beat < start_beat =>
+                    {
                         let node = self.get_node(node_key);
                         max_level = level;
                         cur_node = &*node;
@@ -738,11 +1090,7 @@ impl<S: GridRendererUniqueIdentifier> NoteSkipList<S> {
                     },
                     // shortcut takes us to a valid node, but one lower down may still lead us to
                     // an earlier one that is still valid so keep checking.
-                    Some(node_key)
-                        if self
-                            .get_node(node_key)
-                            .val
-                            .intersects_beats(start_beat, end_beat) =>
+                    Some(node_key) if self.get_node(node_key).val.bounds().intersects(&range) =>
                         cur_node = &*self.get_node(node_key),
                     _ => (),
                 }
@@ -753,11 +1101,22 @@ impl<S: GridRendererUniqueIdentifier> NoteSkipList<S> {
         Some(cur_node)
     }
 
-    pub fn find_first_node_before_beat(&self, beat: f32) -> Option<SlabKey<NoteSkipListNode<S>>> {
+    /// Returns the note that contains `beat`, if there is one.  If `beat` lies exactly on the edge
+    /// between two adjacent notes, the earlier note is returned.
+    pub fn find_note_at(&self, beat: f32) -> Option<&T> {
+        let node = self.find_first_node_in_range(beat, beat)?;
+        if node.val.bounds().contains(beat) {
+            Some(&node.val)
+        } else {
+            None
+        }
+    }
+
+    pub fn find_first_node_before_beat(&self, beat: f32) -> Option<SkipListNodeKey<T>> {
         let head_key = self.head_key?;
         let head = self.get_node(head_key);
 
-        if head.val.bounds.end_beat > beat {
+        if head.val.bounds().end_beat > beat {
             return None;
         }
 
@@ -768,9 +1127,206 @@ impl<S: GridRendererUniqueIdentifier> NoteSkipList<S> {
     }
 }
 
+impl<S: GridRendererUniqueIdentifier> NoteSkipList<S> {
+    /// Changes the bounds of the note starting at `start_beat` to `new_start_beat` and
+    /// `new_end_beat`, clamping them so that the note doesn't overlap the preceeding or following
+    /// notes in the line.  Since the note can't move past any of its neighbors, it's mutated in
+    /// place.  If the clamped bounds would leave the note with no width, it is left unchanged.
+    ///
+    /// Returns the bounds of the note after resizing or `None` if there's no note starting at
+    /// `start_beat`.
+    pub fn resize_note(
+        &mut self,
+        start_beat: f32,
+        new_start_beat: f32,
+        new_end_beat: f32,
+    ) -> Option<NoteBoxBounds> {
+        let (preceeding_note_end_beat, target_node_key) =
+            match self.find_first_node_before_beat(start_beat) {
+                Some(preceeding_node_key) => {
+                    let preceeding_node = self.get_node(preceeding_node_key);
+                    (preceeding_node.val.bounds.end_beat, preceeding_node.links[0]?)
+                },
+                None => (0.0, self.head_key?),
+            };
+        let target_node = self.get_node(target_node_key);
+        if target_node.val.bounds.start_beat != start_beat {
+            return None;
+        }
+
+        let following_note_start_beat = self
+            .next_node(target_node)
+            .map(|node| node.val.bounds.start_beat)
+            .unwrap_or(f32::INFINITY);
+        let new_start_beat =
+            clamp(new_start_beat, preceeding_note_end_beat, following_note_start_beat);
+        let new_end_beat = clamp(new_end_beat, preceeding_note_end_beat, following_note_start_beat);
+
+        let target_note = &mut self.get_node_mut(target_node_key).val;
+        if new_start_beat < new_end_beat {
+            target_note.bounds.start_beat = new_start_beat;
+            target_note.bounds.end_beat = new_end_beat;
+        }
+
+        Some(target_note.bounds)
+    }
+
+    /// Cuts the note that contains `beat` into two notes that meet at `beat`.  The first half keeps
+    /// the original note's ID and data while the second half is created with `data`.  Pitch bends
+    /// and expression curves are split along with the note so that both halves sound the same as
+    /// the original did.
+    ///
+    /// Returns the ID of the newly created second half, or `None` if there's no note that `beat`
+    /// is strictly inside of.
+    pub fn split_at(&mut self, beat: f32, data: S) -> Option<NoteId> {
+        let node_key = self.find_last_node_starting_before(beat)?;
+        let note = &mut self.get_node_mut(node_key).val;
+        if !note.bounds.contains_exclusive(beat) {
+            return None;
+        }
+
+        let split_offset = beat - note.bounds.start_beat;
+        let mut pitch_bend = Vec::new();
+        if !note.pitch_bend.is_empty() {
+            let split_semitones = pitch_bend_at(&note.pitch_bend, split_offset);
+            let split_ix = note
+                .pitch_bend
+                .iter()
+                .position(|point| point.beat_offset > split_offset)
+                .unwrap_or(note.pitch_bend.len());
+            pitch_bend.push(PitchBendPoint {
+                beat_offset: 0.,
+                semitones: split_semitones,
+            });
+            pitch_bend.extend(
+                note.pitch_bend
+                    .drain(split_ix..)
+                    .map(|point| PitchBendPoint {
+                        beat_offset: point.beat_offset - split_offset,
+                        ..point
+                    }),
+            );
+            note.pitch_bend.push(PitchBendPoint {
+                beat_offset: split_offset,
+                semitones: split_semitones,
+            });
+        }
+        let expression = note.expression.split_off(split_offset);
+
+        let second_half = NoteBox {
+            id: NoteId::next(),
+            bounds: NoteBoxBounds {
+                start_beat: beat,
+                end_beat: note.bounds.end_beat,
+            },
+            data,
+            velocity: note.velocity,
+            pitch_bend,
+            expression,
+        };
+        note.bounds.end_beat = beat;
+        let second_half_id = second_half.id;
+        let insertion_error = self.insert(second_half);
+        debug_assert!(insertion_error.is_none());
+
+        Some(second_half_id)
+    }
+
+    /// Inserts `note`, handling any existing notes that it overlaps according to `policy` rather
+    /// than requiring the caller to make room for it first.  Returns a description of everything
+    /// that changed so that the caller can update anything that mirrors the line.
+    pub fn insert_with_policy(
+        &mut self,
+        note: NoteBox<S>,
+        policy: OverlapPolicy,
+    ) -> PolicyInsertion<S> {
+        let mut insertion = PolicyInsertion {
+            rejected: None,
+            truncated: Vec::new(),
+            removed: Vec::new(),
+        };
+        if policy == OverlapPolicy::Reject {
+            insertion.rejected = self.insert(note);
+            return insertion;
+        }
+
+        let overlapping_start_beats: Vec<f32> = self
+            .iter_range(note.bounds.start_beat, note.bounds.end_beat)
+            .filter(|existing_note| existing_note.conflicts_with(&note))
+            .map(|existing_note| existing_note.bounds.start_beat)
+            .collect();
+        for start_beat in overlapping_start_beats {
+            let new_width = note.bounds.start_beat - start_beat;
+            if policy == OverlapPolicy::Truncate && new_width > 0. {
+                let node_key = self
+                    .find_node_starting_at(start_beat)
+                    .expect("Overlapping note disappeared");
+                let existing_note = &mut self.get_node_mut(node_key).val;
+                existing_note.bounds.end_beat = note.bounds.start_beat;
+                normalize_curve(&mut existing_note.pitch_bend, new_width);
+                existing_note.expression.normalize(new_width);
+                insertion
+                    .truncated
+                    .push((existing_note.id, existing_note.bounds));
+            } else if let Some(removed_note) = self.remove(start_beat) {
+                insertion.removed.push(removed_note);
+            }
+        }
+
+        let insertion_error = self.insert(note);
+        debug_assert!(insertion_error.is_none());
+        insertion
+    }
+
+    /// Merges the notes starting at `start_beat_a` and `start_beat_b`, which must be next to each
+    /// other in the line.  The earlier note is extended to the end of the later one, covering any
+    /// gap between them, and the later note's pitch bend and expression are appended to its own.
+    ///
+    /// Returns the later note after removing it from the line, or `None` if either note doesn't
+    /// exist or there's another note between them.
+    pub fn join(&mut self, start_beat_a: f32, start_beat_b: f32) -> Option<NoteBox<S>> {
+        let (first_start_beat, second_start_beat) = if start_beat_a <= start_beat_b {
+            (start_beat_a, start_beat_b)
+        } else {
+            (start_beat_b, start_beat_a)
+        };
+        let first_node_key = self.find_node_starting_at(first_start_beat)?;
+        let second_node_key = self.get_node(first_node_key).links[0]?;
+        if self.get_node(second_node_key).val.bounds.start_beat != second_start_beat {
+            return None;
+        }
+
+        let second_note = self.remove(second_start_beat)?;
+        let first_note = &mut self.get_node_mut(first_node_key).val;
+        let offset = second_note.bounds.start_beat - first_note.bounds.start_beat;
+        if !first_note.pitch_bend.is_empty() || !second_note.pitch_bend.is_empty() {
+            // The later note's bend implicitly starts from zero, which has to be made explicit now
+            // that it's in the middle of the curve.
+            first_note.pitch_bend.push(PitchBendPoint {
+                beat_offset: offset,
+                semitones: 0.,
+            });
+            first_note
+                .pitch_bend
+                .extend(second_note.pitch_bend.iter().map(|point| PitchBendPoint {
+                    beat_offset: point.beat_offset + offset,
+                    ..*point
+                }));
+        }
+        first_note.expression.join(&second_note.expression, offset);
+        first_note.bounds.end_beat = second_note.bounds.end_beat;
+
+        Some(second_note)
+    }
+}
+
 /// This data structure holds a list of ordered note boxes
 pub struct NoteLines<S> {
     pub lines: Vec<NoteSkipList<S>>,
+    /// The line index and start beat of each note, kept up to date by the methods here that add,
+    /// remove, and move notes.  Notes that are changed through `lines` directly can leave their
+    /// entries missing or stale, so entries are checked before they're trusted.
+    notes_by_id: HashMap<NoteId, (usize, f32)>,
 }
 
 impl<S: GridRendererUniqueIdentifier> NoteLines<S> {
@@ -780,7 +1336,50 @@ impl<S: GridRendererUniqueIdentifier> NoteLines<S> {
             lines.push(NoteSkipList::default());
         }
 
-        NoteLines { lines }
+        NoteLines {
+            lines,
+            notes_by_id: HashMap::new(),
+        }
+    }
+
+    /// Creates empty lines whose levels are generated deterministically from `seed`, for use in
+    /// tests and fuzzing.
+    pub fn with_seed(line_count: usize, seed: u64) -> Self {
+        let lines = (0..line_count)
+            .map(|line_ix| NoteSkipList::with_seed(seed.wrapping_add(line_ix as u64)))
+            .collect();
+        NoteLines {
+            lines,
+            notes_by_id: HashMap::new(),
+        }
+    }
+
+    /// Changes the number of lines to `line_count`, moving the notes of each line to the line
+    /// returned by `remap_line` for its index.  `remap_line` must not map two lines to the same
+    /// one.  The lines that it returns `None` for are removed and returned.
+    pub fn remap_lines(
+        &mut self,
+        line_count: usize,
+        remap_line: impl Fn(usize) -> Option<usize>,
+    ) -> Vec<NoteSkipList<S>> {
+        let old_lines = std::mem::replace(&mut self.lines, NoteLines::new(line_count).lines);
+        let mut removed_lines = Vec::new();
+        for (line_ix, line) in old_lines.into_iter().enumerate() {
+            match remap_line(line_ix) {
+                Some(new_line_ix) => {
+                    debug_assert!(self.lines[new_line_ix].is_empty());
+                    self.lines[new_line_ix] = line;
+                },
+                None => removed_lines.push(line),
+            }
+        }
+        self.notes_by_id = mem::take(&mut self.notes_by_id)
+            .into_iter()
+            .filter_map(|(id, (line_ix, start_beat))| {
+                remap_line(line_ix).map(|new_line_ix| (id, (new_line_ix, start_beat)))
+            })
+            .collect();
+        removed_lines
     }
 
     pub fn get_bounds(&mut self, line_ix: usize, beat: f32) -> Bounds<S> {
@@ -789,10 +1388,7 @@ impl<S: GridRendererUniqueIdentifier> NoteLines<S> {
             Some(node_key) => line.get_node(node_key),
             None => return Bounds::Bounded(0.0, None),
         };
-        let mut preceeding_links: PreceedingLinks<S> = unsafe { mem::uninitialized() };
-        for link in &mut preceeding_links {
-            unsafe { ptr::write(link, line.head_key.unwrap()) };
-        }
+        let mut preceeding_links = init_preceeding_links(line.head_key.unwrap());
         // If the first value is already greater than the new note, we don't have to search and
         // simply bound it on the top side by the head's start beat.
         if head.val.contains_beat(beat) {
@@ -834,11 +1430,129 @@ impl<S: GridRendererUniqueIdentifier> NoteLines<S> {
     /// node was inserted successfully and `Some(note)` if there is an intersecting node blocking
     /// it from being inserted.
     pub fn insert(&mut self, line_ix: usize, note: NoteBox<S>) -> Option<NoteBox<S>> {
-        self.lines[line_ix].insert(note)
+        let (id, start_beat) = (note.id, note.bounds.start_beat);
+        let blocked_note = self.lines[line_ix].insert(note);
+        if blocked_note.is_none() {
+            self.notes_by_id.insert(id, (line_ix, start_beat));
+        }
+        blocked_note
+    }
+
+    /// Inserts a note into line `line_ix`, handling notes that it overlaps according to `policy`.
+    /// See `NoteSkipList::insert_with_policy`.
+    pub fn insert_with_policy(
+        &mut self,
+        line_ix: usize,
+        note: NoteBox<S>,
+        policy: OverlapPolicy,
+    ) -> PolicyInsertion<S> {
+        let (id, start_beat) = (note.id, note.bounds.start_beat);
+        let insertion = self.lines[line_ix].insert_with_policy(note, policy);
+        if insertion.rejected.is_none() {
+            self.notes_by_id.insert(id, (line_ix, start_beat));
+        }
+        for &(truncated_id, bounds) in &insertion.truncated {
+            self.notes_by_id
+                .insert(truncated_id, (line_ix, bounds.start_beat));
+        }
+        for removed_note in &insertion.removed {
+            self.notes_by_id.remove(&removed_note.id);
+        }
+        insertion
+    }
+
+    /// Inserts all of the provided `(line_ix, note)` pairs as a group.  If any of them intersect
+    /// an existing note or each other, none of them are inserted and they're all returned.
+    pub fn insert_group(
+        &mut self,
+        notes: Vec<(usize, NoteBox<S>)>,
+    ) -> Option<Vec<(usize, NoteBox<S>)>> {
+        let mut inserted: Vec<(usize, f32)> = Vec::with_capacity(notes.len());
+        let mut notes = notes.into_iter();
+        while let Some((line_ix, note)) = notes.next() {
+            let start_beat = note.bounds.start_beat;
+            if let Some(blocked_note) = self.insert(line_ix, note) {
+                let mut rejected: Vec<(usize, NoteBox<S>)> = inserted
+                    .into_iter()
+                    .map(|(line_ix, start_beat)| {
+                        (line_ix, self.remove(line_ix, start_beat).unwrap())
+                    })
+                    .collect();
+                rejected.push((line_ix, blocked_note));
+                rejected.extend(notes);
+                return Some(rejected);
+            }
+            inserted.push((line_ix, start_beat));
+        }
+
+        None
     }
 
+    /// Returns the note on line `line_ix` that starts at exactly `start_beat`, if there is one
+    pub fn find_note(&self, line_ix: usize, start_beat: f32) -> Option<&NoteBox<S>> {
+        let line = &self.lines[line_ix];
+        line.find_node_starting_at(start_beat)
+            .map(|node_key| &line.get_node(node_key).val)
+    }
+
+    /// Sets the velocity of the note on line `line_ix` that starts at exactly `start_beat`.
+    /// Returns `false` if there is no such note.
+    pub fn set_velocity(&mut self, line_ix: usize, start_beat: f32, velocity: u8) -> bool {
+        let line = &mut self.lines[line_ix];
+        match line.find_node_starting_at(start_beat) {
+            Some(node_key) => {
+                line.get_node_mut(node_key).val.velocity = velocity;
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Returns the note on line `line_ix` that contains `beat`, if there is one.  Uses the skip
+    /// list's shortcuts rather than scanning the whole line.
+    pub fn find_note_at(&self, line_ix: usize, beat: f32) -> Option<&NoteBox<S>> {
+        self.lines[line_ix].find_note_at(beat)
+    }
+
+    /// Returns the line index and note with the provided ID, if it exists.  Notes are looked up by
+    /// the position recorded for them, falling back to searching every line for notes that were
+    /// added or moved without going through `NoteLines`.
+    pub fn get_by_id(&self, id: NoteId) -> Option<(usize, &NoteBox<S>)> {
+        if let Some(&(line_ix, start_beat)) = self.notes_by_id.get(&id) {
+            let indexed_note = self
+                .lines
+                .get(line_ix)
+                .and_then(|line| {
+                    line.find_node_starting_at(start_beat)
+                        .map(|node_key| &line.get_node(node_key).val)
+                })
+                .filter(|note| note.id == id);
+            if let Some(note) = indexed_note {
+                return Some((line_ix, note));
+            }
+        }
+
+        self.iter_all().find(|(_, note)| note.id == id)
+    }
+
+    /// Removes every note from every line, freeing all of their slab slots.  Used when tearing down
+    /// the grid that owns these lines.
+    pub fn clear(&mut self) {
+        for line in &mut self.lines {
+            line.clear();
+        }
+        self.notes_by_id.clear();
+    }
+
+    /// Returns the total number of notes across all lines
+    pub fn len(&self) -> usize { self.lines.iter().map(NoteSkipList::len).sum() }
+
+    pub fn is_empty(&self) -> bool { self.lines.iter().all(NoteSkipList::is_empty) }
+
     pub fn remove(&mut self, line_ix: usize, start_beat: f32) -> Option<NoteBox<S>> {
-        self.lines[line_ix].remove(start_beat)
+        let note = self.lines[line_ix].remove(start_beat)?;
+        self.notes_by_id.remove(&note.id);
+        Some(note)
     }
 
     /// Attempts to move a note from one line to another, keeping it at the same start and end
@@ -851,10 +1565,10 @@ impl<S: GridRendererUniqueIdentifier> NoteLines<S> {
         dst_line_ix: usize,
         start_beat: f32,
     ) -> bool {
-        if let Some(note) = self.lines[src_line_ix].remove(start_beat) {
-            if let Some(note) = self.lines[dst_line_ix].insert(note) {
+        if let Some(note) = self.remove(src_line_ix, start_beat) {
+            if let Some(note) = self.insert(dst_line_ix, note) {
                 // insertion failed due to a collision; re-insert into the original line.
-                let returned_note = self.lines[src_line_ix].insert(note);
+                let returned_note = self.insert(src_line_ix, note);
                 debug_assert!(returned_note.is_none());
                 true
             } else {
@@ -865,6 +1579,108 @@ impl<S: GridRendererUniqueIdentifier> NoteLines<S> {
         }
     }
 
+    /// Moves a note to a new line and start beat, keeping its width.  If the note would collide
+    /// with another note at its destination or there was no note with the specified `start_beat`
+    /// in the source line, an error is returned and the note is left where it was.
+    pub fn move_note(
+        &mut self,
+        line_ix: usize,
+        start_beat: f32,
+        new_line_ix: usize,
+        new_start_beat: f32,
+    ) -> Result<(), MoveError> {
+        self.move_notes(&[(line_ix, start_beat, new_line_ix, new_start_beat)])
+    }
+
+    /// Atomically moves a set of notes, each specified as `(line_ix, start_beat, new_line_ix,
+    /// new_start_beat)`.  All of the notes are removed before any are re-inserted, so notes in the
+    /// set can move into space previously occupied by one another.  If any of the notes collides
+    /// with another note at its destination, all of the notes are returned to their original
+    /// positions and the move that was blocked is returned.  Nothing is moved if any of the notes
+    /// couldn't be found either.
+    pub fn move_notes(&mut self, moves: &[(usize, f32, usize, f32)]) -> Result<(), MoveError> {
+        let mut notes: Vec<Option<NoteBox<S>>> = Vec::with_capacity(moves.len());
+        for (move_ix, &(line_ix, start_beat, ..)) in moves.iter().enumerate() {
+            match self.remove(line_ix, start_beat) {
+                Some(note) => notes.push(Some(note)),
+                None => {
+                    for (note, &(line_ix, ..)) in notes.into_iter().zip(moves) {
+                        let reinsertion_error = self.insert(line_ix, note.unwrap());
+                        debug_assert!(reinsertion_error.is_none());
+                    }
+                    return Err(MoveError::NoteNotFound(move_ix));
+                },
+            }
+        }
+        let original_bounds: Vec<NoteBoxBounds> =
+            notes.iter().map(|note| note.as_ref().unwrap().bounds).collect();
+
+        for (i, &(_, _, new_line_ix, new_start_beat)) in moves.iter().enumerate() {
+            let mut note = notes[i].take().unwrap();
+            let width = note.bounds.width();
+            note.bounds.start_beat = new_start_beat;
+            note.bounds.end_beat = new_start_beat + width;
+
+            if let Some(blocked_note) = self.insert(new_line_ix, note) {
+                // Undo all of the moves made so far and put every note back where it started
+                notes[i] = Some(blocked_note);
+                for (j, &(_, _, new_line_ix, new_start_beat)) in moves[..i].iter().enumerate() {
+                    notes[j] = self.remove(new_line_ix, new_start_beat);
+                    debug_assert!(notes[j].is_some());
+                }
+                for ((note, bounds), &(line_ix, ..)) in
+                    notes.into_iter().zip(original_bounds).zip(moves)
+                {
+                    let mut note = note.unwrap();
+                    note.bounds = bounds;
+                    let reinsertion_error = self.insert(line_ix, note);
+                    debug_assert!(reinsertion_error.is_none());
+                }
+                return Err(MoveError::Blocked(i));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Moves all notes in `selection` up by `semitones`, or down if it's negative, as a group.
+    /// Line 0 holds the highest pitch, so moving up means moving to a lower line index.  If any of
+    /// the notes would be moved off of the grid or collide with a note that isn't in `selection`,
+    /// none of them are moved.
+    pub fn transpose(
+        &mut self,
+        selection: &[SelectedNoteData],
+        semitones: isize,
+    ) -> Result<(), MoveError> {
+        let line_count = self.lines.len() as isize;
+        let mut moves = Vec::with_capacity(selection.len());
+        for (move_ix, note) in selection.iter().enumerate() {
+            let new_line_ix = note.line_ix as isize - semitones;
+            if new_line_ix < 0 || new_line_ix >= line_count {
+                return Err(MoveError::OffGrid(move_ix));
+            }
+            moves.push((note.line_ix, note.start_beat, new_line_ix as usize, note.start_beat));
+        }
+
+        self.move_notes(&moves)
+    }
+
+    /// Moves all notes in `selection` `beats` beats later, or earlier if it's negative, as a
+    /// group.  If any of the notes would be moved before the start of the line or collide with a
+    /// note that isn't in `selection`, none of them are moved.
+    pub fn shift(&mut self, selection: &[SelectedNoteData], beats: f32) -> Result<(), MoveError> {
+        let mut moves = Vec::with_capacity(selection.len());
+        for (move_ix, note) in selection.iter().enumerate() {
+            let new_start_beat = note.start_beat + beats;
+            if new_start_beat < 0. {
+                return Err(MoveError::OffGrid(move_ix));
+            }
+            moves.push((note.line_ix, note.start_beat, note.line_ix, new_start_beat));
+        }
+
+        self.move_notes(&moves)
+    }
+
     /// Moves a note horizontally a given number of beats, stopping early if it collides with
     /// another note or the beginning of the line.  This can be done by simply mutating the
     /// targeted note since it is guarenteed to not change its line or index in its line.
@@ -906,6 +1722,8 @@ impl<S: GridRendererUniqueIdentifier> NoteLines<S> {
 
         target_note.bounds.start_beat = new_target_node_start;
         target_note.bounds.end_beat = new_target_node_start + target_note_length;
+        self.notes_by_id
+            .insert(target_note.id, (line_ix, new_target_node_start));
 
         new_target_node_start
     }
@@ -939,6 +1757,46 @@ impl<S: GridRendererUniqueIdentifier> NoteLines<S> {
         self.iter_region(0, self.lines.len() - 1, 0.0, f32::INFINITY)
     }
 
+    /// Returns an iterator over every note that intersects the range between `start_beat` and
+    /// `end_beat`, line by line.
+    pub fn iter_range<'a>(
+        &'a self,
+        start_beat: f32,
+        end_beat: f32,
+    ) -> impl Iterator<Item = NoteData<'a, S>> + 'a {
+        self.lines
+            .iter()
+            .enumerate()
+            .flat_map(move |(line_ix, line)| {
+                line.iter_range(start_beat, end_beat)
+                    .map(move |note_box| NoteData { line_ix, note_box })
+            })
+    }
+
+    /// Returns an iterator over every note that starts at or after `start_beat` and before
+    /// `end_beat`, line by line.  Notes that were started before `start_beat` and are still held
+    /// past it aren't included.
+    pub fn iter_starting_between<'a>(
+        &'a self,
+        start_beat: f32,
+        end_beat: f32,
+    ) -> impl Iterator<Item = NoteData<'a, S>> + 'a {
+        self.iter_region(0, self.lines.len() - 1, start_beat, end_beat)
+            .filter(move |note_data| {
+                let note_start_beat = note_data.note_box.bounds.start_beat;
+                note_start_beat >= start_beat && note_start_beat < end_beat
+            })
+    }
+
+    /// Returns an iterator over every note in the composition along with the index of the line
+    /// that it's on.  Notes are yielded line by line, in order of start beat within each line.
+    pub fn iter_all<'a>(&'a self) -> impl Iterator<Item = (usize, &'a NoteBox<S>)> + 'a {
+        self.lines
+            .iter()
+            .enumerate()
+            .flat_map(|(line_ix, line)| line.iter().map(move |note| (line_ix, note)))
+    }
+
     pub fn find_first_node_in_range(
         &self,
         line_ix: usize,
@@ -956,25 +1814,20 @@ impl<S: GridRendererUniqueIdentifier> NoteLines<S> {
     }
 }
 
-impl<S: GridRendererUniqueIdentifier> NoteSkipList<S> {
-    pub fn debug_node(&self, node: &NoteSkipListNode<S>) -> String {
-        let debug_ptrs = get_debug_ptrs();
+impl<T: SkipListEntry> SkipList<T> {
+    pub fn debug_node(&self, node: &SkipListNode<T>, debug_ptrs: &mut SkipListLinks<T>) -> String {
         let next_node_key = &node.links[0];
 
         for (level, next_node_for_level) in node.links.iter().enumerate() {
             if next_node_for_level.is_some()
                 && debug_ptrs[level].is_some()
-                && node.val
-                    != self
-                        .get_node(unsafe { mem::transmute(debug_ptrs[level].unwrap()) })
-                        .val
+                && node.val.bounds() != self.get_node(debug_ptrs[level].unwrap()).val.bounds()
             {
                 // Make sure that the next node in the level is what we expect it to be,
                 // ensuring that none of our fast paths skip nodes in their level.
                 debug_assert_eq!(
-                    debug_ptrs[level]
-                        .map(|p| self.get_node(unsafe { mem::transmute(p) }).val.bounds),
-                    next_node_for_level.map(|p| self.get_node(p).val.bounds)
+                    debug_ptrs[level].map(|p| self.get_node(p).val.bounds()),
+                    next_node_for_level.map(|p| self.get_node(p).val.bounds())
                 );
             }
         }
@@ -999,11 +1852,12 @@ impl<S: GridRendererUniqueIdentifier> NoteSkipList<S> {
                     },
                 };
 
-                if next_valid_node_for_level
-                    .map(|p| self.get_node(unsafe { mem::transmute(p)}).val.bounds) == Some(node.val.bounds) {
+                if next_valid_node_for_level.map(|p| self.get_node(p).val.bounds())
+                    == Some(node.val.bounds())
+                {
                     // If we are the node that was pointed to by the last node in this level,
                     // set the next valid node in the level to be the one we point to.
-                    debug_ptrs[level] = unsafe { mem::transmute(link_opt)};
+                    debug_ptrs[level] = link_opt;
                     let link_s = format!("{:?}", node.val);
                     let string_len = link_s.len();
                     if string_len > longest_link_s {
@@ -0,0 +1,130 @@
+//! Interprets touches on the grid.  A single touch is handled like the mouse so that notes can be
+//! drawn, dragged, and selected with a finger.  Placing a second finger on the grid cancels
+//! whatever the first one was doing and starts a pinch instead, which zooms the grid as the
+//! fingers are spread apart or brought together and scrolls it as they're dragged sideways.
+
+/// What the grid should do in response to a touch event
+#[derive(Clone, Debug, PartialEq)]
+pub enum TouchAction {
+    MouseDown(usize, usize),
+    MouseMove(usize, usize),
+    MouseUp(usize, usize),
+    /// A second touch started while the first one was being handled like the mouse.  Whatever the
+    /// first touch started should be undone; it started at `(x, y)`.
+    CancelMouse(usize, usize),
+    /// The grid should be zoomed by `zoom_factor` and scrolled horizontally by `scroll_px` pixels
+    Pinch {
+        zoom_factor: f32,
+        scroll_px: isize,
+    },
+    None,
+}
+
+struct Pinch {
+    last_distance: f32,
+    last_midpoint_x: f32,
+}
+
+#[derive(Default)]
+pub struct Touches {
+    /// `(pointer_id, x, y)` of every touch in contact with the grid in the order that they started
+    points: Vec<(i32, usize, usize)>,
+    /// `(x, y)` of where the first touch started if it's being handled like the mouse
+    mouse_down_pos: Option<(usize, usize)>,
+    pinch: Option<Pinch>,
+}
+
+impl Touches {
+    /// Returns the distance between the first two touches and the x coordinate of the point
+    /// halfway between them
+    fn measure_pinch(&self) -> (f32, f32) {
+        let (_, x1, y1) = self.points[0];
+        let (_, x2, y2) = self.points[1];
+        let (dx, dy) = (x2 as f32 - x1 as f32, y2 as f32 - y1 as f32);
+        ((dx * dx + dy * dy).sqrt(), (x1 + x2) as f32 / 2.)
+    }
+
+    pub fn start(&mut self, pointer_id: i32, x: usize, y: usize) -> TouchAction {
+        self.points.push((pointer_id, x, y));
+        match self.points.len() {
+            // Touches that start while a pinch is ending aren't handled like the mouse since they
+            // were most likely part of the pinch
+            1 if self.pinch.is_none() => {
+                self.mouse_down_pos = Some((x, y));
+                TouchAction::MouseDown(x, y)
+            },
+            2 => {
+                let (last_distance, last_midpoint_x) = self.measure_pinch();
+                self.pinch = Some(Pinch {
+                    last_distance,
+                    last_midpoint_x,
+                });
+                match self.mouse_down_pos.take() {
+                    Some((x, y)) => TouchAction::CancelMouse(x, y),
+                    None => TouchAction::None,
+                }
+            },
+            _ => TouchAction::None,
+        }
+    }
+
+    pub fn move_to(&mut self, pointer_id: i32, x: usize, y: usize) -> TouchAction {
+        let ix = match self.points.iter().position(|&(id, ..)| id == pointer_id) {
+            Some(ix) => ix,
+            None => return TouchAction::None,
+        };
+        self.points[ix] = (pointer_id, x, y);
+
+        if self.mouse_down_pos.is_some() {
+            return TouchAction::MouseMove(x, y);
+        }
+        // Only the first two touches take part in the pinch
+        if ix > 1 || self.points.len() < 2 {
+            return TouchAction::None;
+        }
+        let (distance, midpoint_x) = self.measure_pinch();
+        let pinch = match &mut self.pinch {
+            Some(pinch) => pinch,
+            None => return TouchAction::None,
+        };
+        // Fingers placed right on top of each other have no meaningful distance to scale
+        let zoom_factor = if pinch.last_distance >= 1. && distance >= 1. {
+            distance / pinch.last_distance
+        } else {
+            1.
+        };
+        let scroll_px = (pinch.last_midpoint_x - midpoint_x) as isize;
+        pinch.last_distance = distance;
+        // Only whole pixels are scrolled, so the remainder is carried over to the next move
+        pinch.last_midpoint_x -= scroll_px as f32;
+
+        TouchAction::Pinch {
+            zoom_factor,
+            scroll_px,
+        }
+    }
+
+    pub fn end(&mut self, pointer_id: i32, x: usize, y: usize) -> TouchAction {
+        let ix = match self.points.iter().position(|&(id, ..)| id == pointer_id) {
+            Some(ix) => ix,
+            None => return TouchAction::None,
+        };
+        self.points.remove(ix);
+
+        if self.mouse_down_pos.take().is_some() {
+            return TouchAction::MouseUp(x, y);
+        }
+        // The pinch ends once every finger is lifted so that the last one doesn't start drawing
+        if self.points.is_empty() {
+            self.pinch = None;
+        } else if ix <= 1 && self.points.len() >= 2 {
+            // A third finger takes the place of the lifted one
+            let (last_distance, last_midpoint_x) = self.measure_pinch();
+            self.pinch = Some(Pinch {
+                last_distance,
+                last_midpoint_x,
+            });
+        }
+        TouchAction::None
+    }
+}
@@ -0,0 +1,135 @@
+//! Maps keys to the actions that they trigger.  View contexts that handle keyboard input register
+//! their actions by name in `KeymapSection`s along with the keys bound to them by default.  Users
+//! can rebind the keys of any action; their bindings are kept by the `ViewContextManager` and
+//! saved with the rest of the workspace.
+//!
+//! Keys are identified by the value of `KeyboardEvent.key`.  Modifiers aren't part of bindings;
+//! actions read them to decide how much to do.
+
+use std::collections::BTreeMap;
+
+pub struct KeyAction {
+    /// Identifies the action in the user's bindings, so it must not change once released
+    pub name: &'static str,
+    pub description: &'static str,
+    pub default_keys: &'static [&'static str],
+}
+
+pub struct KeymapSection {
+    /// Identifies the section in the user's bindings
+    pub name: &'static str,
+    pub actions: &'static [KeyAction],
+}
+
+/// The keys that the user has bound to actions, by section name and then action name.  Actions
+/// that aren't listed are bound to their default keys.
+pub type KeymapOverrides = BTreeMap<String, BTreeMap<String, Vec<String>>>;
+
+/// An action along with the keys bound to it, used to list them in the UI
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyBindingDescription {
+    pub section: &'static str,
+    pub action: &'static str,
+    pub description: &'static str,
+    pub keys: Vec<String>,
+    /// Set if the user has bound different keys than the default ones
+    pub customized: bool,
+}
+
+impl KeymapSection {
+    pub fn get_action_by_name(&self, name: &str) -> Option<&'static KeyAction> {
+        self.actions.iter().find(|action| action.name == name)
+    }
+
+    fn get_bound_keys(&self, action: &KeyAction, overrides: &KeymapOverrides) -> Vec<String> {
+        match overrides
+            .get(self.name)
+            .and_then(|section| section.get(action.name))
+        {
+            Some(keys) => keys.clone(),
+            None => action.default_keys.iter().map(|&key| key.into()).collect(),
+        }
+    }
+
+    /// Returns the name of the action bound to `key`.  If the same key is bound to multiple
+    /// actions, the one registered first wins.
+    pub fn get_action(&self, key: &str, overrides: &KeymapOverrides) -> Option<&'static str> {
+        self.actions
+            .iter()
+            .find(|action| {
+                self.get_bound_keys(action, overrides)
+                    .iter()
+                    .any(|k| k == key)
+            })
+            .map(|action| action.name)
+    }
+
+    /// Binds `keys` to the action named `action_name`, unbinding them from any other actions in
+    /// this section so that every key triggers a single action.  Returns `false` if there's no
+    /// action with that name.
+    pub fn bind(
+        &self,
+        overrides: &mut KeymapOverrides,
+        action_name: &str,
+        keys: Vec<String>,
+    ) -> bool {
+        if self.get_action_by_name(action_name).is_none() {
+            return false;
+        }
+
+        for action in self.actions {
+            if action.name == action_name {
+                continue;
+            }
+            let bound_keys = self.get_bound_keys(action, overrides);
+            if !bound_keys.iter().any(|key| keys.contains(key)) {
+                continue;
+            }
+
+            let remaining_keys = bound_keys
+                .into_iter()
+                .filter(|key| !keys.contains(key))
+                .collect();
+            overrides
+                .entry(self.name.into())
+                .or_default()
+                .insert(action.name.into(), remaining_keys);
+        }
+        overrides
+            .entry(self.name.into())
+            .or_default()
+            .insert(action_name.into(), keys);
+        true
+    }
+
+    /// Binds the action named `action_name` to its default keys again
+    pub fn reset(&self, overrides: &mut KeymapOverrides, action_name: &str) {
+        if let Some(section) = overrides.get_mut(self.name) {
+            section.remove(action_name);
+            if section.is_empty() {
+                overrides.remove(self.name);
+            }
+        }
+    }
+
+    pub fn describe(&self, overrides: &KeymapOverrides) -> Vec<KeyBindingDescription> {
+        self.actions
+            .iter()
+            .map(|action| {
+                let keys = self.get_bound_keys(action, overrides);
+                let customized = keys
+                    .iter()
+                    .map(String::as_str)
+                    .ne(action.default_keys.iter().cloned());
+                KeyBindingDescription {
+                    section: self.name,
+                    action: action.name,
+                    description: action.description,
+                    keys,
+                    customized,
+                }
+            })
+            .collect()
+    }
+}
@@ -1 +1,4 @@
+pub mod audio_tap;
 pub mod grid;
+pub mod keymap;
+pub mod undo;
@@ -0,0 +1,59 @@
+//! Snapshot-based undo/redo history.  Before each edit, a copy of the state being edited is
+//! recorded; undoing swaps the current state with the most recent snapshot.
+
+/// The maximum number of snapshots that are kept.  Once it's reached, the oldest ones are dropped.
+const MAX_HISTORY_LEN: usize = 128;
+
+pub struct UndoHistory<T: Clone> {
+    undo_stack: Vec<T>,
+    redo_stack: Vec<T>,
+}
+
+impl<T: Clone> Default for UndoHistory<T> {
+    fn default() -> Self {
+        UndoHistory {
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+        }
+    }
+}
+
+impl<T: Clone> UndoHistory<T> {
+    /// Records a snapshot of `state` as it is before an edit is made to it.  This clears the redo
+    /// history.
+    pub fn record(&mut self, state: &T) {
+        if self.undo_stack.len() >= MAX_HISTORY_LEN {
+            self.undo_stack.remove(0);
+        }
+        self.undo_stack.push(state.clone());
+        self.redo_stack.clear();
+    }
+
+    /// Restores `state` to the most recently recorded snapshot.  Returns `false` if there was
+    /// nothing to undo.
+    pub fn undo(&mut self, state: &mut T) -> bool {
+        match self.undo_stack.pop() {
+            Some(snapshot) => {
+                self.redo_stack.push(std::mem::replace(state, snapshot));
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Re-applies the most recently undone edit to `state`.  Returns `false` if there was nothing
+    /// to redo.
+    pub fn redo(&mut self, state: &mut T) -> bool {
+        match self.redo_stack.pop() {
+            Some(snapshot) => {
+                self.undo_stack.push(std::mem::replace(state, snapshot));
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn can_undo(&self) -> bool { !self.undo_stack.is_empty() }
+
+    pub fn can_redo(&self) -> bool { !self.redo_stack.is_empty() }
+}
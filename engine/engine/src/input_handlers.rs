@@ -1,46 +1,202 @@
-//! Exports functions to JS that handle events including keyup/keydown, mouse clicks, and
-//! scroll
+//! Exports functions to JS that handle events including keyup/keydown, mouse clicks, touches,
+//! and scroll, along with the ones for viewing and changing key bindings
 
+use std::str::FromStr;
+
+use uuid::Uuid;
 use wasm_bindgen::prelude::*;
 
-use crate::get_vcm;
+use crate::{
+    get_vcm,
+    helpers::keymap::{KeyBindingDescription, KeymapSection},
+    view_context::{Message, PenState},
+};
+
+/// `pen_pressure` is only provided for events generated by a pen
+fn build_pen_state(pen_pressure: Option<f32>, tilt_x: f32, tilt_y: f32) -> Option<PenState> {
+    pen_pressure.map(|pressure| PenState {
+        pressure,
+        tilt_x,
+        tilt_y,
+    })
+}
 
 #[wasm_bindgen]
 pub fn handle_key_down(key: &str, control_pressed: bool, shift_pressed: bool) {
     get_vcm()
-        .get_active_view_mut()
+        .get_active_view_for_input()
         .handle_key_down(key, control_pressed, shift_pressed);
+    get_vcm().dispatch_events();
 }
 
 #[allow(clippy::needless_pass_by_value)]
 #[wasm_bindgen]
 pub fn handle_key_up(key: &str, control_pressed: bool, shift_pressed: bool) {
     get_vcm()
-        .get_active_view_mut()
+        .get_active_view_for_input()
         .handle_key_up(key, control_pressed, shift_pressed);
+    get_vcm().dispatch_events();
 }
 
 #[wasm_bindgen]
-pub fn handle_mouse_down(x: usize, y: usize) {
-    get_vcm().get_active_view_mut().handle_mouse_down(x, y);
+pub fn handle_mouse_down(x: usize, y: usize, pen_pressure: Option<f32>, tilt_x: f32, tilt_y: f32) {
+    let pen = build_pen_state(pen_pressure, tilt_x, tilt_y);
+    get_vcm()
+        .get_active_view_for_input()
+        .handle_mouse_down(x, y, pen);
+    get_vcm().dispatch_events();
 }
 
 #[wasm_bindgen]
-pub fn handle_mouse_move(x: usize, y: usize) {
-    get_vcm().get_active_view_mut().handle_mouse_move(x, y);
+pub fn handle_mouse_move(x: usize, y: usize, pen_pressure: Option<f32>, tilt_x: f32, tilt_y: f32) {
+    let pen = build_pen_state(pen_pressure, tilt_x, tilt_y);
+    get_vcm().get_active_view_mut().handle_mouse_move(x, y, pen);
 }
 
 #[wasm_bindgen]
 pub fn handle_mouse_up(x: usize, y: usize) {
-    get_vcm().get_active_view_mut().handle_mouse_up(x, y);
+    get_vcm().get_active_view_for_input().handle_mouse_up(x, y);
+    get_vcm().dispatch_events();
 }
 
 #[wasm_bindgen]
 pub fn handle_mouse_wheel(ydiff: isize) {
-    get_vcm().get_active_view_mut().handle_mouse_wheel(ydiff);
+    get_vcm()
+        .get_active_view_for_input()
+        .handle_mouse_wheel(ydiff);
+}
+
+#[wasm_bindgen]
+pub fn handle_touch_start(pointer_id: i32, x: usize, y: usize) {
+    get_vcm()
+        .get_active_view_for_input()
+        .handle_touch_start(pointer_id, x, y);
+}
+
+#[wasm_bindgen]
+pub fn handle_touch_move(pointer_id: i32, x: usize, y: usize) {
+    get_vcm()
+        .get_active_view_for_input()
+        .handle_touch_move(pointer_id, x, y);
+}
+
+#[wasm_bindgen]
+pub fn handle_touch_end(pointer_id: i32, x: usize, y: usize) {
+    get_vcm()
+        .get_active_view_for_input()
+        .handle_touch_end(pointer_id, x, y);
 }
 
 #[wasm_bindgen]
 pub fn handle_message(key: &str, val: &[u8]) -> Option<Vec<u8>> {
-    get_vcm().get_active_view_mut().handle_message(key, val)
+    let res = get_vcm()
+        .get_active_view_for_input()
+        .handle_message(key, val);
+    get_vcm().dispatch_events();
+    res
+}
+
+/// Decodes a typed message from its binary form and delivers it to the active view context
+#[wasm_bindgen]
+pub fn handle_binary_message(val: &[u8]) -> Option<Vec<u8>> {
+    let res = match Message::decode(val) {
+        Ok(message) => get_vcm()
+            .get_active_view_for_input()
+            .handle_binary_message(message),
+        Err(err) => {
+            error!("Error decoding binary view context message: {:?}", err);
+            None
+        },
+    };
+    get_vcm().dispatch_events();
+    res
+}
+
+/// Like `handle_message`, but delivers the message to the view context with the provided ID even if
+/// it isn't the active one.  Used for things like MIDI input that can arrive at any time.
+#[wasm_bindgen]
+pub fn handle_vc_message(vc_id: &str, key: &str, val: &[u8]) -> Option<Vec<u8>> {
+    let uuid = Uuid::from_str(vc_id).expect("Invalid UUID string passed to `handle_vc_message`!");
+    let res = match get_vcm().get_vc_by_id_mut(uuid) {
+        Some(entry) => {
+            entry.touched = true;
+            entry.context.handle_message(key, val)
+        },
+        None => {
+            error!("Tried to send message \"{}\" to VC with ID {} but it wasn't found", key, vc_id);
+            None
+        },
+    };
+    get_vcm().dispatch_events();
+    res
+}
+
+/// Finds the keymap section of the active view context with the provided name
+fn get_active_keymap_section(section_name: &str) -> Option<&'static KeymapSection> {
+    let section = get_vcm()
+        .get_active_view()
+        .get_keymap_sections()
+        .into_iter()
+        .find(|section| section.name == section_name);
+    if section.is_none() {
+        error!(
+            "The active view context has no keymap section named \"{}\"",
+            section_name
+        );
+    }
+    section
+}
+
+/// Returns JSON describing the actions handled by the active view context and the keys bound to
+/// them, which is used to list them in the keyboard shortcut help
+#[wasm_bindgen]
+pub fn get_keymap() -> String {
+    let vcm = get_vcm();
+    let bindings: Vec<KeyBindingDescription> = vcm
+        .get_active_view()
+        .get_keymap_sections()
+        .into_iter()
+        .flat_map(|section| section.describe(&vcm.keymap_overrides))
+        .collect();
+    serde_json::to_string(&bindings).expect("Failed to serialize key bindings")
+}
+
+/// Binds the keys in `keys_json`, a JSON array of key names, to an action of the active view
+/// context in place of the ones currently bound to it
+#[wasm_bindgen]
+pub fn set_key_bindings(section_name: &str, action_name: &str, keys_json: &str) {
+    let keys: Vec<String> = match serde_json::from_str(keys_json) {
+        Ok(keys) => keys,
+        Err(err) => {
+            error!(
+                "Failed to deserialize provided key bindings JSON: {:?}",
+                err
+            );
+            return;
+        },
+    };
+    let section = match get_active_keymap_section(section_name) {
+        Some(section) => section,
+        None => return,
+    };
+
+    let vcm = get_vcm();
+    if !section.bind(&mut vcm.keymap_overrides, action_name, keys) {
+        error!(
+            "Keymap section \"{}\" has no action named \"{}\"",
+            section_name, action_name
+        );
+        return;
+    }
+    vcm.save_all();
+}
+
+/// Binds an action of the active view context to its default keys again
+#[wasm_bindgen]
+pub fn reset_key_bindings(section_name: &str, action_name: &str) {
+    if let Some(section) = get_active_keymap_section(section_name) {
+        let vcm = get_vcm();
+        section.reset(&mut vcm.keymap_overrides, action_name);
+        vcm.save_all();
+    }
 }
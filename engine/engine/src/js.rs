@@ -36,6 +36,7 @@ extern "C" {
         y2: usize,
         class: &str,
     ) -> usize;
+    pub fn render_polygon(canvas_index: usize, points: &str, class: &str) -> usize;
     pub fn get_active_attr(key: &str) -> Option<String>;
     pub fn set_active_attr(key: &str, val: &str);
     pub fn set_attr(id: usize, key: &str, val: &str);
@@ -49,6 +50,9 @@ extern "C" {
     pub fn cleanup_grid(vc_id: &str);
     pub fn hide_grid(vc_id: &str);
     pub fn unhide_grid(vc_id: &str);
+    pub fn set_grid_scroll_offset(vc_id: &str, x: f32);
+    pub fn set_grid_height(vc_id: &str, height: usize);
+    pub fn set_grid_line_labels(vc_id: &str, labels_json: &str, top: usize, line_height: usize);
 }
 
 #[wasm_bindgen]
@@ -69,6 +73,15 @@ pub fn set_localstorage_key(key: &str, val: &str) { setItem(key, val); }
 
 pub fn delete_localstorage_key(key: &str) { removeItem(key); }
 
+#[wasm_bindgen(raw_module = "./storage")]
+extern "C" {
+    pub fn list_localstorage_keys() -> String;
+    pub fn get_indexeddb_key(key: &str) -> Option<String>;
+    pub fn set_indexeddb_key(key: &str, val: &str);
+    pub fn delete_indexeddb_key(key: &str);
+    pub fn list_indexeddb_keys() -> String;
+}
+
 #[wasm_bindgen(raw_module = "./faustEditor")]
 extern "C" {
     pub fn init_faust_editor(state_key: &str);
@@ -83,7 +96,7 @@ extern "C" {
 
 #[wasm_bindgen(raw_module = "./graphEditor")]
 extern "C" {
-    pub fn init_graph_editor(state_key: &str);
+    pub fn init_graph_editor(state_key: &str, node_positions: &str);
     pub fn hide_graph_editor(vc_id: &str);
     pub fn unhide_graph_editor(vc_id: &str);
     pub fn cleanup_graph_editor(state_key: &str);
@@ -99,18 +112,80 @@ extern "C" {
     pub fn cleanup_midi_editor_ui(vc_id: &str);
 }
 
+#[wasm_bindgen(raw_module = "./clipCompositor")]
+extern "C" {
+    pub fn init_clip_compositor_ui(vc_id: &str);
+    pub fn cleanup_clip_compositor_ui(vc_id: &str);
+    pub fn hide_clip_compositor_ui(vc_id: &str);
+    pub fn unhide_clip_compositor_ui(vc_id: &str);
+}
+
 #[wasm_bindgen(raw_module = "./midiEditor/synthCbs")]
 extern "C" {
     pub fn midi_editor_trigger_attack(vc_id: &str, note_id: usize);
+    #[wasm_bindgen(js_name = midi_editor_trigger_attack)]
+    pub fn midi_editor_trigger_attack_with_velocity(
+        vc_id: &str,
+        note_id: usize,
+        offset: Option<f64>,
+        velocity: u8,
+    );
     pub fn midi_editor_trigger_release(vc_id: &str, note_id: usize);
     pub fn midi_editor_trigger_attack_release(vc_id: &str, note_id: usize, duration: f32);
     pub fn midi_editor_schedule_events(
         vc_id: &str,
-        events: &[u8],
+        event_types: &[u8],
         note_ids: &[usize],
+        velocities: &[u8],
+        values: &[f32],
         timings: &[f64],
     );
     pub fn midi_editor_cancel_all_events(vc_id: &str, stop_playing_notes: bool);
+    pub fn midi_editor_forward_control_change(vc_id: &str, control_index: u8, value: u8);
+    pub fn midi_editor_set_voice_manager_conf(vc_id: &str, conf_json: &str, voice_count: usize);
+    pub fn midi_editor_set_note_expression(vc_id: &str, note_id: usize, dimension: u8, value: f32);
+    pub fn midi_editor_schedule_metronome_clicks(
+        vc_id: &str,
+        timings: &[f64],
+        is_accent_flags: &[u8],
+        volume: f32,
+    );
+    pub fn midi_editor_cancel_metronome_clicks(vc_id: &str);
+    pub fn midi_editor_send_midi_output(
+        vc_id: &str,
+        port_name: &str,
+        messages: &[u8],
+        timings: &[f64],
+    );
+    pub fn midi_editor_cancel_midi_output(vc_id: &str, port_name: &str);
+    pub fn connect_midi_editor_instrument(
+        from_vc_id: &str,
+        from_name: &str,
+        to_vc_id: &str,
+        to_name: &str,
+    );
+    pub fn disconnect_midi_editor_instrument(
+        from_vc_id: &str,
+        from_name: &str,
+        to_vc_id: &str,
+        to_name: &str,
+    );
+    pub fn midi_editor_schedule_automation(
+        vc_id: &str,
+        target_vc_id: &str,
+        param_name: &str,
+        timings: &[f64],
+        values: &[f32],
+    );
+    pub fn midi_editor_cancel_automation(vc_id: &str);
+    pub fn midi_editor_set_transport_state(
+        vc_id: &str,
+        is_playing: bool,
+        bpm: f64,
+        beat_zero_time: f64,
+        loop_start_beat: Option<f64>,
+        loop_end_beat: Option<f64>,
+    );
     pub fn register_midi_editor_loop_interval(
         cb: &Closure<dyn FnMut(f64)>,
         inteval_ms: usize,
@@ -138,6 +213,10 @@ extern "C" {
     pub fn unhide_synth_designer(vc_id: &str);
     pub fn cleanup_synth_designer(state_key: &str) -> String;
     pub fn get_synth_designer_audio_connectables(state_key: &str) -> JsValue;
+    pub fn set_synth_designer_mod_routes(state_key: &str, routes_json: &str);
+    pub fn get_synth_designer_modules(state_key: &str) -> String;
+    pub fn set_synth_designer_modules(state_key: &str, modules_json: &str);
+    pub fn set_synth_designer_tuning(state_key: &str, frequencies: &[f32]);
 }
 
 #[wasm_bindgen(raw_module = "./midiKeyboard")]
@@ -158,6 +237,45 @@ extern "C" {
     pub fn get_sequencer_audio_connectables(state_key: &str) -> JsValue;
 }
 
+#[wasm_bindgen(raw_module = "./mixer")]
+extern "C" {
+    /// Returns the sample rate of the audio context, which the meters need to time their ballistics
+    pub fn init_mixer(state_key: &str, state_json: &str) -> f32;
+    pub fn cleanup_mixer(state_key: &str);
+    pub fn hide_mixer(state_key: &str);
+    pub fn unhide_mixer(state_key: &str);
+    pub fn get_mixer_audio_connectables(state_key: &str) -> JsValue;
+    pub fn set_mixer_state(state_key: &str, state_json: &str);
+    pub fn set_mixer_track_params(
+        state_key: &str,
+        track_id: u32,
+        gain: f32,
+        pan: f32,
+        effective_gain: f32,
+    );
+    pub fn set_mixer_master_gain(state_key: &str, gain: f32);
+}
+
+#[wasm_bindgen(raw_module = "./clipLauncher")]
+extern "C" {
+    pub fn init_clip_launcher(state_key: &str, state_json: &str);
+    pub fn cleanup_clip_launcher(state_key: &str);
+    pub fn hide_clip_launcher(state_key: &str);
+    pub fn unhide_clip_launcher(state_key: &str);
+    pub fn set_clip_launcher_state(state_key: &str, state_json: &str);
+    pub fn set_clip_launcher_playback_state(state_key: &str, playback_state_json: &str);
+}
+
+#[wasm_bindgen(raw_module = "./drumSequencer")]
+extern "C" {
+    pub fn init_drum_sequencer(state_key: &str, state_json: &str);
+    pub fn cleanup_drum_sequencer(state_key: &str);
+    pub fn hide_drum_sequencer(state_key: &str);
+    pub fn unhide_drum_sequencer(state_key: &str);
+    pub fn get_drum_sequencer_audio_connectables(state_key: &str) -> JsValue;
+    pub fn set_drum_sequencer_state(state_key: &str, state_json: &str);
+}
+
 #[wasm_bindgen(raw_module = "./sampleLibrary")]
 extern "C" {
     pub fn init_sample_library(state_key: &str);
@@ -165,3 +283,32 @@ extern "C" {
     pub fn hide_sample_library(state_key: &str);
     pub fn unhide_sample_library(state_key: &str);
 }
+
+#[wasm_bindgen(raw_module = "./sampleEditor")]
+extern "C" {
+    pub fn init_sample_editor(state_key: &str, state_json: &str);
+    pub fn cleanup_sample_editor(state_key: &str);
+    pub fn hide_sample_editor(state_key: &str);
+    pub fn unhide_sample_editor(state_key: &str);
+}
+
+#[wasm_bindgen(raw_module = "./spectrumAnalyzer")]
+extern "C" {
+    /// Returns the sample rate of the audio context, which is needed to map FFT bins to frequencies
+    pub fn init_spectrum_analyzer(state_key: &str, conf_json: &str) -> f32;
+    pub fn cleanup_spectrum_analyzer(state_key: &str);
+    pub fn hide_spectrum_analyzer(state_key: &str);
+    pub fn unhide_spectrum_analyzer(state_key: &str);
+    pub fn get_spectrum_analyzer_audio_connectables(state_key: &str) -> JsValue;
+}
+
+#[wasm_bindgen(raw_module = "./oscilloscope")]
+extern "C" {
+    /// Returns the sample rate of the audio context, which is needed to convert the time span of
+    /// the display into samples
+    pub fn init_oscilloscope(state_key: &str, conf_json: &str) -> f32;
+    pub fn cleanup_oscilloscope(state_key: &str);
+    pub fn hide_oscilloscope(state_key: &str);
+    pub fn unhide_oscilloscope(state_key: &str);
+    pub fn get_oscilloscope_audio_connectables(state_key: &str) -> JsValue;
+}
@@ -1,4 +1,3 @@
-#![feature(box_syntax, test, thread_local, nll)]
 #![allow(clippy::float_cmp, clippy::needless_range_loop, clippy::manual_memcpy)]
 
 extern crate wasm_bindgen;
@@ -7,29 +6,38 @@ extern crate serde_derive;
 #[macro_use]
 extern crate log;
 
-use std::{ptr, str::FromStr};
+use std::str::FromStr;
 
 use uuid::Uuid;
 use wasm_bindgen::prelude::*;
 
+pub mod audio_graph;
+pub mod audio_recorder;
 pub mod constants;
+pub mod context;
+pub mod dsp;
 pub mod helpers;
 pub mod input_handlers;
 pub mod js;
+pub mod metronome;
+pub mod offline_render;
 pub mod prelude;
+pub mod sample_import;
+pub mod soundfont;
+pub mod storage;
 pub mod util;
 pub mod view_context;
 pub mod views;
 use crate::{
+    context::ctx,
     prelude::*,
     view_context::manager::{build_view, ForeignConnectable},
 };
 
-/// The global view context manager that holds all of the view contexts for the application.
-static mut VIEW_CONTEXT_MANAGER: *mut ViewContextManager = ptr::null_mut();
+pub use common::init_rng;
 
-/// Retrieves the global `ViewContextManager` for the application
-pub fn get_vcm() -> &'static mut ViewContextManager { unsafe { &mut *VIEW_CONTEXT_MANAGER } }
+/// Retrieves the `ViewContextManager` that holds all of the view contexts for the application
+pub fn get_vcm() -> &'static mut ViewContextManager { &mut ctx().vcm }
 
 /// Entrypoint for the application.  This function is called from the JS side as soon as the Wasm
 /// blob is loaded.  It handles setting up application state, rendering the initial UI, and loading
@@ -38,35 +46,34 @@ pub fn get_vcm() -> &'static mut ViewContextManager { unsafe { &mut *VIEW_CONTEX
 pub fn init() {
     common::maybe_init();
 
-    // Check if we have an existing VCM and drop it if we do
-    if unsafe { !VIEW_CONTEXT_MANAGER.is_null() } {
-        let old_vcm = unsafe { Box::from_raw(VIEW_CONTEXT_MANAGER) };
-        drop(old_vcm);
-    }
-
-    // Create the `ViewContextManager` and initialize it, then set it into the global
-    let vcm = box ViewContextManager::default();
-    // We have to store it in the pointer before initializing it since some initializing functions
-    // call into JS code which in turn calls back into Rust code which expects to be able to access
-    // the global VCM via that pointer.
-    unsafe { VIEW_CONTEXT_MANAGER = Box::into_raw(vcm) };
-    let mut vcm = unsafe { Box::from_raw(VIEW_CONTEXT_MANAGER) };
-    vcm.init();
-    unsafe { VIEW_CONTEXT_MANAGER = Box::into_raw(vcm) };
+    // Replace any existing VCM with a fresh one and initialize it.  We have to store it in the
+    // context before initializing it since some initializing functions call into JS code which in
+    // turn calls back into Rust code which expects to be able to access the VCM via the context.
+    ctx().vcm = ViewContextManager::default();
+    get_vcm().init();
 }
 
-/// Creates a new view context from the provided name and sets it as the main view context.
-#[wasm_bindgen]
-pub fn create_view_context(vc_name: String) {
+fn create_view_context_inner(vc_name: String, conf: Option<&str>) {
     let uuid = uuid_v4();
     debug!("Creating VC with name {} with vcId {}", vc_name, uuid);
-    let mut view_context = build_view(&vc_name, None, uuid);
+    let mut view_context = build_view(&vc_name, conf, uuid);
     view_context.init();
     let vcm = get_vcm();
     let new_vc_ix = vcm.add_view_context(uuid, vc_name, view_context);
     vcm.set_active_view(new_vc_ix);
 }
 
+/// Creates a new view context from the provided name and sets it as the main view context.
+#[wasm_bindgen]
+pub fn create_view_context(vc_name: String) { create_view_context_inner(vc_name, None) }
+
+/// Creates a new view context from the provided name and serialized state, as produced by its
+/// `save()` function, and sets it as the main view context.
+#[wasm_bindgen]
+pub fn add_view_context(vc_name: String, conf: &str) {
+    create_view_context_inner(vc_name, Some(conf))
+}
+
 #[wasm_bindgen]
 pub fn handle_window_close() {
     let vcm = get_vcm();
@@ -76,6 +83,23 @@ pub fn handle_window_close() {
     vcm.save_all();
 }
 
+/// Switches the storage of the engine's state to IndexedDB.  JS calls this before `init` once it
+/// has loaded the stored entries so that they can be read synchronously.
+#[wasm_bindgen]
+pub fn use_indexeddb_storage() { storage::set_storage(Box::new(storage::IndexedDBStorage)); }
+
+/// Called periodically with the current time in milliseconds to save VCs that have been edited
+#[wasm_bindgen]
+pub fn autosave(now_ms: f64) { get_vcm().autosave(now_ms); }
+
+/// Called with the size of the area that VCs are rendered into when the window is resized
+#[wasm_bindgen]
+pub fn handle_resize(width: usize, height: usize) { get_vcm().handle_resize(width, height); }
+
+/// Called when the page is hidden or shown, such as when switching browser tabs
+#[wasm_bindgen]
+pub fn handle_visibility_change(visible: bool) { get_vcm().handle_visibility_change(visible); }
+
 #[wasm_bindgen]
 pub fn delete_vc_by_id(id: &str) {
     debug!("delete_vc_by_id(\"{}\")", id);
@@ -90,6 +114,26 @@ pub fn switch_view_context(uuid_str: &str) {
     get_vcm().set_active_view_by_id(uuid);
 }
 
+#[wasm_bindgen]
+pub fn switch_view_context_by_ix(ix: usize) {
+    let vcm = get_vcm();
+    if ix >= vcm.contexts.len() {
+        error!(
+            "Tried to switch to VC index {} but only {} VCs are managed.",
+            ix,
+            vcm.contexts.len()
+        );
+        return;
+    }
+
+    vcm.set_active_view(ix);
+}
+
+#[wasm_bindgen]
+pub fn move_view_context(from_ix: usize, to_ix: usize) {
+    get_vcm().move_view_context(from_ix, to_ix);
+}
+
 #[wasm_bindgen]
 pub fn reset_vcm() {
     info!("Resetting VCM...");
@@ -101,6 +145,40 @@ pub fn reset_vcm() {
     );
 }
 
+/// Returns the entire workspace serialized as a single JSON document
+#[wasm_bindgen]
+pub fn serialize_workspace() -> String { get_vcm().serialize_all() }
+
+/// Replaces the current workspace with one created by `serialize_workspace`, returning `false` if
+/// it couldn't be parsed.
+#[wasm_bindgen]
+pub fn load_workspace(serialized: &str) -> bool {
+    match get_vcm().deserialize_all(serialized) {
+        Ok(()) => true,
+        Err(err) => {
+            error!("Failed to deserialize provided workspace JSON: {:?}", err);
+            false
+        },
+    }
+}
+
+/// Returns the entire workspace along with the data of all VCs as a project file
+#[wasm_bindgen]
+pub fn export_project() -> Vec<u8> { get_vcm().export_project() }
+
+/// Replaces the current workspace with the one in a project file created by `export_project`,
+/// returning `false` if it's invalid.
+#[wasm_bindgen]
+pub fn import_project(bytes: &[u8]) -> bool {
+    match get_vcm().import_project(bytes) {
+        Ok(()) => true,
+        Err(err) => {
+            error!("Failed to import project: {:?}", err);
+            false
+        },
+    }
+}
+
 #[wasm_bindgen]
 pub fn set_vc_title(uuid_str: String, title: String) {
     let uuid = Uuid::from_str(&uuid_str).expect("Invalid UUID string passed to `set_vc_title`!");
@@ -0,0 +1,97 @@
+//! Metronome that clicks on every beat of the time signature during playback and recording, with
+//! an accented click on the first beat of each measure.  Click timings are derived from the tempo
+//! map so that they follow tempo and time signature changes, and the click sounds themselves are
+//! synthesized here so that they don't depend on any samples being loaded.
+
+use std::f32::consts::PI;
+
+use common::tempo_map::TempoMap;
+use wasm_bindgen::prelude::*;
+
+const CLICK_DURATION_SECONDS: f32 = 0.04;
+const CLICK_ATTACK_SECONDS: f32 = 0.001;
+/// Time constant of the click's exponential decay
+const CLICK_DECAY_SECONDS: f32 = 0.008;
+const CLICK_FREQUENCY: f32 = 1000.;
+const ACCENTED_CLICK_FREQUENCY: f32 = 1500.;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MetronomeConf {
+    pub enabled: bool,
+    /// Gain applied to the clicks, from 0 to 1
+    pub volume: f32,
+    /// The number of measures of clicks to play before recording starts
+    pub count_in_bars: u8,
+}
+
+impl Default for MetronomeConf {
+    fn default() -> Self {
+        MetronomeConf {
+            enabled: false,
+            volume: 0.5,
+            count_in_bars: 1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MetronomeClick {
+    pub beat: f64,
+    /// Accented clicks are played on the first beat of each measure
+    pub is_accent: bool,
+}
+
+/// Returns the clicks that fall within `[start_beat, end_beat)`
+pub fn get_clicks(tempo_map: &TempoMap, start_beat: f64, end_beat: f64) -> Vec<MetronomeClick> {
+    tempo_map
+        .grid_lines(end_beat)
+        .into_iter()
+        .filter(|line| line.beat >= start_beat)
+        .map(|line| MetronomeClick {
+            beat: line.beat,
+            is_accent: line.is_measure_start,
+        })
+        .collect()
+}
+
+/// Returns the `(offset_seconds, is_accent)` of the clicks for `bars` measures counting in to
+/// `start_beat`.  Offsets are relative to the time at which `start_beat` plays and so are all
+/// negative.  The count-in uses the tempo and time signature in effect at `start_beat`.
+pub fn get_count_in_clicks(tempo_map: &TempoMap, start_beat: f64, bars: u8) -> Vec<(f64, bool)> {
+    let time_signature = tempo_map.time_signature_at(start_beat);
+    let click_length_seconds = time_signature.beat_unit() * 60. / tempo_map.bpm_at(start_beat);
+    let clicks_per_bar = time_signature.numerator as usize;
+    let click_count = clicks_per_bar * bars as usize;
+
+    (0..click_count)
+        .map(|click_ix| {
+            let offset_seconds = -((click_count - click_ix) as f64 * click_length_seconds);
+            (offset_seconds, click_ix % clicks_per_bar == 0)
+        })
+        .collect()
+}
+
+/// Synthesizes a single click as a short, exponentially decaying sine burst.  Accented clicks are
+/// pitched higher so that the downbeat stands out.
+pub fn synthesize_click(sample_rate: f32, is_accent: bool) -> Vec<f32> {
+    let frequency = if is_accent {
+        ACCENTED_CLICK_FREQUENCY
+    } else {
+        CLICK_FREQUENCY
+    };
+    let frame_count = (CLICK_DURATION_SECONDS * sample_rate) as usize;
+
+    (0..frame_count)
+        .map(|frame_ix| {
+            let t = frame_ix as f32 / sample_rate;
+            let envelope = (t / CLICK_ATTACK_SECONDS).min(1.) * (-t / CLICK_DECAY_SECONDS).exp();
+            (2. * PI * frequency * t).sin() * envelope
+        })
+        .collect()
+}
+
+#[wasm_bindgen]
+pub fn synthesize_metronome_click(sample_rate: f32, is_accent: bool) -> Vec<f32> {
+    synthesize_click(sample_rate, is_accent)
+}
@@ -0,0 +1,388 @@
+//! Offline rendering ("bouncing") of compositions to audio files.  Rather than scheduling notes
+//! against the realtime audio clock, the note events for a range of beats are converted into
+//! sample frame offsets up front.  That allows an instrument to be rendered block by block in a
+//! tight loop, as fast as it can go, with the result encoded into a WAV file.
+//!
+//! Only the sampler can be bounced.  Rendering offline requires an `Instrument` implemented in
+//! Rust, and the voices of the WebAudio synths such as the synth designer and the FM synth only
+//! exist inside of the `AudioContext`.  `render_bounce` accepts any `Instrument`, with voices
+//! allocated by the same voice manager that plays notes in realtime, but `bounce_sampler` is the
+//! only way to bounce from JS.
+
+use audio_block::{Block, BlockProcessor, Instrument, BLOCK_SIZE};
+use common::tempo_map::TempoMap;
+use polysynth::{PolySynth, SynthCallbacks};
+use sampler::{PlaybackMode, Sampler};
+use uuid::Uuid;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    helpers::grid::skip_list::NoteEvent, util::clamp, views::midi_editor::note_layout::NoteLayout,
+};
+
+/// The most notes that can play at once while bouncing, matching the sampler's voice count
+const BOUNCE_VOICE_COUNT: usize = 64;
+/// Rendering continues past the end of the bounced range until the output goes silent, up to this
+/// many seconds, so that releases and one-shot samples can ring out
+const MAX_TAIL_SECONDS: f64 = 10.;
+const SILENCE_THRESHOLD: f32 = 1. / 32768.;
+
+/// A note event positioned at a frame offset from the start of the bounced range
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BounceEvent {
+    pub frame: usize,
+    pub is_attack: bool,
+    pub note_id: usize,
+    pub velocity: u8,
+}
+
+/// The events to render for a bounced range along with its length
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BounceSchedule {
+    pub sample_rate: f64,
+    pub frame_count: usize,
+    pub events: Vec<BounceEvent>,
+}
+
+/// Converts the events of the notes that start within `[start_beat, end_beat)` into a
+/// `BounceSchedule`.  Notes that started before the range are skipped entirely, and notes that
+/// are still held at the end of the range are released there.  `note_layout` is used to convert
+/// line indices into note IDs in the same way as the MIDI editor's realtime scheduler, and beats
+/// are converted into frames by following `tempo_map`.
+pub fn collect_bounce_events(
+    events: impl Iterator<Item = NoteEvent>,
+    note_layout: &NoteLayout,
+    tempo_map: &TempoMap,
+    sample_rate: f64,
+    start_beat: f64,
+    end_beat: f64,
+) -> BounceSchedule {
+    let beat_to_frame = |beat: f64| {
+        let seconds = tempo_map.beats_to_seconds_from(start_beat, beat - start_beat);
+        (seconds * sample_rate).round().max(0.) as usize
+    };
+    let end_frame = beat_to_frame(end_beat);
+
+    let mut held_lines: Vec<usize> = Vec::new();
+    let mut bounce_events = Vec::new();
+    for event in events {
+        let beat = event.beat as f64;
+        if event.is_start {
+            if beat < start_beat || beat >= end_beat {
+                continue;
+            }
+            held_lines.push(event.line_ix);
+        } else {
+            // Releases are only emitted for notes whose attack was included
+            match held_lines.iter().position(|&line_ix| line_ix == event.line_ix) {
+                Some(ix) => held_lines.swap_remove(ix),
+                None => continue,
+            };
+        }
+
+        bounce_events.push(BounceEvent {
+            frame: beat_to_frame(beat).min(end_frame),
+            is_attack: event.is_start,
+            note_id: note_layout.line_note(event.line_ix),
+            velocity: event.velocity,
+        });
+    }
+
+    // Events can only be past the end of the range if they're releases
+    for line_ix in held_lines {
+        bounce_events.push(BounceEvent {
+            frame: end_frame,
+            is_attack: false,
+            note_id: note_layout.line_note(line_ix),
+            velocity: 0,
+        });
+    }
+
+    BounceSchedule {
+        sample_rate,
+        frame_count: end_frame,
+        events: bounce_events,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WavBitDepth {
+    Sixteen,
+    TwentyFour,
+}
+
+impl WavBitDepth {
+    pub fn from_bits(bits: u8) -> Option<Self> {
+        match bits {
+            16 => Some(WavBitDepth::Sixteen),
+            24 => Some(WavBitDepth::TwentyFour),
+            _ => None,
+        }
+    }
+
+    pub fn bytes_per_sample(self) -> usize {
+        match self {
+            WavBitDepth::Sixteen => 2,
+            WavBitDepth::TwentyFour => 3,
+        }
+    }
+
+    fn max_amplitude(self) -> f32 {
+        match self {
+            WavBitDepth::Sixteen => i16::MAX as f32,
+            WavBitDepth::TwentyFour => ((1 << 23) - 1) as f32,
+        }
+    }
+}
+
+/// Renders up to `max_frame_count` frames of the processor's output block by block, returning the
+/// left and right channels.  The processor is handed a silent block each time, and the output of
+/// the last block is truncated to fit.  Rendering stops early once `is_finished` returns `true` for
+/// the processor and the block that it just rendered.
+pub fn render_blocks<P: BlockProcessor + ?Sized>(
+    processor: &mut P,
+    max_frame_count: usize,
+    mut is_finished: impl FnMut(&P, &Block) -> bool,
+) -> [Vec<f32>; 2] {
+    let mut channels = [Vec::new(), Vec::new()];
+    let mut block = Block::default();
+    while channels[0].len() < max_frame_count {
+        block.clear();
+        processor.process(&mut block);
+
+        let block_frames = (max_frame_count - channels[0].len()).min(BLOCK_SIZE);
+        channels[0].extend_from_slice(&block.left[..block_frames]);
+        channels[1].extend_from_slice(&block.right[..block_frames]);
+        if is_finished(processor, &block) {
+            break;
+        }
+    }
+    channels
+}
+
+type BounceVoiceManager = PolySynth<
+    fn(String, usize) -> usize,
+    fn(usize, usize, usize, u8, Option<f32>),
+    fn(usize, usize, usize, Option<f32>),
+    fn(usize, usize, f32, f32),
+    fn(usize, &[u8], &[usize], &[f32]),
+>;
+
+/// Plays the events of a `BounceSchedule` through an instrument.  Each block is rendered up to
+/// each of the events within it, so events are applied at the exact frame they're scheduled for.
+pub struct BounceRenderer<'a> {
+    instrument: &'a mut dyn Instrument,
+    events: &'a [BounceEvent],
+    next_event_ix: usize,
+    rendered_frames: usize,
+    voice_manager: BounceVoiceManager,
+}
+
+impl<'a> BounceRenderer<'a> {
+    pub fn new(instrument: &'a mut dyn Instrument, events: &'a [BounceEvent]) -> Self {
+        // The voice manager is only used to allocate voices, so none of its callbacks are used
+        let voice_manager = BounceVoiceManager::with_voice_count(
+            Uuid::nil(),
+            false,
+            BOUNCE_VOICE_COUNT,
+            SynthCallbacks {
+                init_synth: |_, _| 0,
+                trigger_attack: |_, _, _, _, _| (),
+                trigger_release: |_, _, _, _| (),
+                trigger_attack_release: |_, _, _, _| (),
+                schedule_events: |_, _, _, _| (),
+            },
+        );
+        BounceRenderer {
+            instrument,
+            events,
+            next_event_ix: 0,
+            rendered_frames: 0,
+            voice_manager,
+        }
+    }
+
+    /// Returns `true` once all of the events have been applied
+    pub fn is_done(&self) -> bool { self.next_event_ix >= self.events.len() }
+
+    fn apply_event(&mut self, event: BounceEvent) {
+        if event.is_attack {
+            let attacked = self
+                .voice_manager
+                .trigger_attack_cb(event.note_id, event.velocity, |_, _, _, _| ());
+            if let Some((_, voice_ix, note_id, velocity)) = attacked {
+                self.instrument.trigger_attack(voice_ix, note_id, velocity);
+            }
+        } else if let Some((_, voice_ix)) =
+            self.voice_manager.trigger_release_cb(event.note_id, |_, _, _| ())
+        {
+            self.instrument.trigger_release(voice_ix, event.note_id);
+        }
+    }
+}
+
+impl<'a> BlockProcessor for BounceRenderer<'a> {
+    fn process(&mut self, block: &mut Block) {
+        let block_end = self.rendered_frames + BLOCK_SIZE;
+        let mut rendered_in_block = 0;
+        while let Some(&event) = self.events.get(self.next_event_ix) {
+            if event.frame >= block_end {
+                break;
+            }
+
+            let event_frame =
+                event.frame.saturating_sub(self.rendered_frames).max(rendered_in_block);
+            self.instrument.render(block, rendered_in_block, event_frame);
+            rendered_in_block = event_frame;
+            self.apply_event(event);
+            self.next_event_ix += 1;
+        }
+        self.instrument.render(block, rendered_in_block, BLOCK_SIZE);
+        self.rendered_frames = block_end;
+    }
+}
+
+/// Renders `schedule` through `instrument`, returning the left and right channels.  Rendering
+/// continues past the end of the schedule until the instrument goes silent so that releases can
+/// ring out.
+pub fn render_bounce(instrument: &mut dyn Instrument, schedule: &BounceSchedule) -> [Vec<f32>; 2] {
+    let max_frame_count =
+        schedule.frame_count + (MAX_TAIL_SECONDS * schedule.sample_rate).round() as usize;
+    let mut renderer = BounceRenderer::new(instrument, &schedule.events);
+    render_blocks(&mut renderer, max_frame_count, |renderer, block| {
+        let is_silent = block
+            .left
+            .iter()
+            .chain(block.right.iter())
+            .all(|sample| sample.abs() < SILENCE_THRESHOLD);
+        renderer.rendered_frames >= schedule.frame_count && renderer.is_done() && is_silent
+    })
+}
+
+/// Encodes the provided channels into a PCM WAV file.  Samples are clamped to `[-1, 1]` and the
+/// channels are interleaved; all channels must be the same length.
+pub fn encode_wav(channels: &[&[f32]], sample_rate: u32, bit_depth: WavBitDepth) -> Vec<u8> {
+    let channel_count = channels.len();
+    let frame_count = channels.first().map(|channel| channel.len()).unwrap_or(0);
+    debug_assert!(channels.iter().all(|channel| channel.len() == frame_count));
+
+    let bytes_per_sample = bit_depth.bytes_per_sample();
+    let block_align = channel_count * bytes_per_sample;
+    let data_len = frame_count * block_align;
+
+    let mut buf: Vec<u8> = Vec::with_capacity(44 + data_len);
+    buf.extend_from_slice(b"RIFF");
+    buf.extend_from_slice(&(36 + data_len as u32).to_le_bytes());
+    buf.extend_from_slice(b"WAVE");
+
+    buf.extend_from_slice(b"fmt ");
+    buf.extend_from_slice(&16u32.to_le_bytes());
+    // PCM
+    buf.extend_from_slice(&1u16.to_le_bytes());
+    buf.extend_from_slice(&(channel_count as u16).to_le_bytes());
+    buf.extend_from_slice(&sample_rate.to_le_bytes());
+    buf.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    buf.extend_from_slice(&(block_align as u16).to_le_bytes());
+    buf.extend_from_slice(&((bytes_per_sample * 8) as u16).to_le_bytes());
+
+    buf.extend_from_slice(b"data");
+    buf.extend_from_slice(&(data_len as u32).to_le_bytes());
+
+    let max_amplitude = bit_depth.max_amplitude();
+    for frame_ix in 0..frame_count {
+        for channel in channels {
+            let sample = (clamp(channel[frame_ix], -1., 1.) * max_amplitude).round() as i32;
+            buf.extend_from_slice(&sample.to_le_bytes()[..bytes_per_sample]);
+        }
+    }
+
+    buf
+}
+
+pub(crate) fn parse_bit_depth(bits: u8) -> WavBitDepth {
+    WavBitDepth::from_bits(bits).unwrap_or_else(|| {
+        error!("Unsupported WAV bit depth: {}; using 16 bits", bits);
+        WavBitDepth::Sixteen
+    })
+}
+
+/// Encodes planar samples (all of the first channel's samples followed by the second channel's
+/// and so on) into a WAV file.  `bit_depth` must be 16 or 24.
+#[wasm_bindgen]
+pub fn encode_wav_file(
+    samples: &[f32],
+    channel_count: usize,
+    sample_rate: u32,
+    bit_depth: u8,
+) -> Vec<u8> {
+    let bit_depth = parse_bit_depth(bit_depth);
+    let channel_count = channel_count.max(1);
+    let channels: Vec<&[f32]> = samples.chunks((samples.len() / channel_count).max(1)).collect();
+    encode_wav(&channels[..channel_count.min(channels.len())], sample_rate, bit_depth)
+}
+
+/// Settings of the sampler that a bounce is rendered through, mirroring `SamplerParams` in
+/// `src/graphEditor/nodes/CustomAudio/Sampler/Sampler.ts`
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SamplerBounceConf {
+    pub root_note: f32,
+    pub looping: bool,
+    /// Loop points as fractions of the length of the sample
+    pub loop_start: f64,
+    pub loop_end: f64,
+    pub release_seconds: f32,
+    pub channel_count: usize,
+    pub sample_rate: f32,
+}
+
+/// Renders the bounce schedule produced by the MIDI editor's `get_bounce_events` message through a
+/// sampler playing `sample`, returning a WAV file of the result.  `sample` holds all of the first
+/// channel's samples followed by the second channel's.
+#[wasm_bindgen]
+pub fn bounce_sampler(
+    schedule_json: &[u8],
+    conf_json: &str,
+    sample: Vec<f32>,
+    bit_depth: u8,
+) -> Vec<u8> {
+    let schedule: BounceSchedule = match serde_json::from_slice(schedule_json) {
+        Ok(schedule) => schedule,
+        Err(err) => {
+            error!("Error decoding bounce schedule: {:?}", err);
+            return Vec::new();
+        },
+    };
+    let conf: SamplerBounceConf = match serde_json::from_str(conf_json) {
+        Ok(conf) => conf,
+        Err(err) => {
+            error!("Error decoding sampler bounce conf: {:?}", err);
+            return Vec::new();
+        },
+    };
+
+    let sample_len = sample.len() / conf.channel_count.max(1);
+    let loop_start = conf.loop_start.min(conf.loop_end);
+    let loop_end = conf.loop_start.max(conf.loop_end);
+    let mut sampler = Sampler::new(schedule.sample_rate as f32);
+    sampler.set_sample(sample, conf.channel_count, conf.sample_rate);
+    sampler.set_root_note(conf.root_note);
+    sampler.set_mode(if conf.looping {
+        PlaybackMode::Loop {
+            start: (loop_start * sample_len as f64).floor() as usize,
+            end: (loop_end * sample_len as f64).ceil() as usize,
+        }
+    } else {
+        PlaybackMode::OneShot
+    });
+    sampler.set_release_time(conf.release_seconds);
+
+    let [left, right] = render_bounce(&mut sampler, &schedule);
+    encode_wav(
+        &[&left, &right],
+        schedule.sample_rate as u32,
+        parse_bit_depth(bit_depth),
+    )
+}
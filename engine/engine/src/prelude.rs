@@ -3,17 +3,18 @@
 
 pub use wasm_bindgen::prelude::*;
 
-pub use common::{rng, uuid_v4, RNG};
+pub use common::{uuid_v4, with_rng};
 
 pub use super::{
     constants::*,
     get_vcm,
     helpers::grid::GridRendererUniqueIdentifier,
     js,
+    storage::{get_storage, LocalStorage, Storage},
     util::{self, *},
     view_context::{
         self,
         manager::{ConnectionDescriptor, ViewContextDefinition},
-        ViewContext, ViewContextManager,
+        PenState, ViewContext, ViewContextManager,
     },
 };
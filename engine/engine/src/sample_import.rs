@@ -0,0 +1,475 @@
+//! Imports audio files into samples that can be played back by the sampler.  WAV files are decoded
+//! here directly; other formats are decoded by the browser and their PCM is handed over instead.
+//! Either way, samples are converted into planar `f32`s at the sample rate of the audio context
+//! and registered in the sample pool.
+//!
+//! The pool keeps track of how much memory the imported samples take up and refuses imports that
+//! would take it over its limit.  Samplers that load the same sample share a single entry, which is
+//! freed once all of them have released it.
+
+use std::collections::BTreeMap;
+
+use wasm_bindgen::prelude::*;
+
+use crate::context::ctx;
+
+/// At 48kHz, that's about 23 minutes of stereo `f32` samples.
+pub const DEFAULT_MAX_POOL_BYTES: usize = 512 * 1024 * 1024;
+/// Only the first two channels are kept since that's all that the sampler plays
+pub const MAX_CHANNEL_COUNT: usize = 2;
+/// Number of zero crossings of the resampling filter on each side of the center tap
+const RESAMPLER_HALF_TAPS: usize = 16;
+
+const WAVE_FORMAT_PCM: u16 = 1;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+#[derive(Debug, PartialEq)]
+pub enum SampleImportError {
+    NotAWavFile,
+    MissingChunk(&'static str),
+    UnsupportedFormat {
+        format_tag: u16,
+        bits_per_sample: u16,
+    },
+    /// The file declares zero channels or a sample rate of zero
+    InvalidFormat,
+    PoolFull {
+        needed_bytes: usize,
+        available_bytes: usize,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportedSample {
+    /// One buffer per channel, all the same length
+    pub channels: Vec<Vec<f32>>,
+    pub sample_rate: u32,
+}
+
+impl ImportedSample {
+    /// Builds a sample from planar samples (all of the first channel's samples followed by the
+    /// second channel's and so on), dropping any channels past `MAX_CHANNEL_COUNT`.
+    pub fn from_planar(
+        samples: &[f32],
+        channel_count: usize,
+        sample_rate: u32,
+    ) -> Result<Self, SampleImportError> {
+        if channel_count == 0 || sample_rate == 0 {
+            return Err(SampleImportError::InvalidFormat);
+        }
+
+        let len_frames = samples.len() / channel_count;
+        let channels = (0..channel_count.min(MAX_CHANNEL_COUNT))
+            .map(|channel_ix| {
+                samples[channel_ix * len_frames..(channel_ix + 1) * len_frames].to_vec()
+            })
+            .collect();
+        Ok(ImportedSample {
+            channels,
+            sample_rate,
+        })
+    }
+
+    pub fn channel_count(&self) -> usize { self.channels.len() }
+
+    pub fn len_frames(&self) -> usize { self.channels.first().map(Vec::len).unwrap_or(0) }
+
+    /// Number of bytes taken up by the sample data
+    pub fn size_bytes(&self) -> usize {
+        self.channel_count() * self.len_frames() * std::mem::size_of::<f32>()
+    }
+
+    pub fn planar_samples(&self) -> Vec<f32> { self.channels.concat() }
+
+    /// Converts all channels to `target_sample_rate`.  Does nothing if the sample is already at
+    /// that rate.
+    pub fn resample(&mut self, target_sample_rate: u32) {
+        if self.sample_rate == target_sample_rate {
+            return;
+        }
+
+        for channel in &mut self.channels {
+            *channel = resample(channel, self.sample_rate, target_sample_rate);
+        }
+        self.sample_rate = target_sample_rate;
+    }
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut buf = [0u8; 4];
+    buf.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(buf)
+}
+
+/// Returns `true` if the bytes start with the header of a RIFF WAVE file
+pub fn is_wav(bytes: &[u8]) -> bool {
+    bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WAVE"
+}
+
+struct WavFormat {
+    format_tag: u16,
+    channel_count: usize,
+    sample_rate: u32,
+    bits_per_sample: u16,
+}
+
+/// Converts a single sample of the given format into a `f32` in `[-1, 1]`
+fn decode_sample(format: &WavFormat, bytes: &[u8]) -> f32 {
+    match (format.format_tag, format.bits_per_sample) {
+        // 8-bit WAV samples are unsigned
+        (WAVE_FORMAT_PCM, 8) => (bytes[0] as f32 - 128.) / 128.,
+        (WAVE_FORMAT_PCM, 16) => i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.,
+        // Shifted into the top of an `i32` so that the sign is extended
+        (WAVE_FORMAT_PCM, 24) =>
+            i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) as f32 / 2_147_483_648.,
+        (WAVE_FORMAT_PCM, 32) =>
+            i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32 / 2_147_483_648.,
+        (WAVE_FORMAT_IEEE_FLOAT, 32) =>
+            f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        (WAVE_FORMAT_IEEE_FLOAT, 64) => {
+            let mut buf = [0u8; 8];
+            buf.copy_from_slice(&bytes[..8]);
+            f64::from_le_bytes(buf) as f32
+        },
+        _ => unreachable!(),
+    }
+}
+
+fn parse_fmt_chunk(chunk: &[u8]) -> Result<WavFormat, SampleImportError> {
+    if chunk.len() < 16 {
+        return Err(SampleImportError::InvalidFormat);
+    }
+
+    let mut format_tag = read_u16(chunk, 0);
+    // Extensible files keep the actual format in the first two bytes of the sub-format GUID
+    if format_tag == WAVE_FORMAT_EXTENSIBLE && chunk.len() >= 26 {
+        format_tag = read_u16(chunk, 24);
+    }
+    let format = WavFormat {
+        format_tag,
+        channel_count: read_u16(chunk, 2) as usize,
+        sample_rate: read_u32(chunk, 4),
+        bits_per_sample: read_u16(chunk, 14),
+    };
+
+    if format.channel_count == 0 || format.sample_rate == 0 {
+        return Err(SampleImportError::InvalidFormat);
+    }
+    match (format.format_tag, format.bits_per_sample) {
+        (WAVE_FORMAT_PCM, 8)
+        | (WAVE_FORMAT_PCM, 16)
+        | (WAVE_FORMAT_PCM, 24)
+        | (WAVE_FORMAT_PCM, 32) => (),
+        (WAVE_FORMAT_IEEE_FLOAT, 32) | (WAVE_FORMAT_IEEE_FLOAT, 64) => (),
+        (format_tag, bits_per_sample) =>
+            return Err(SampleImportError::UnsupportedFormat {
+                format_tag,
+                bits_per_sample,
+            }),
+    }
+    Ok(format)
+}
+
+/// Decodes a WAV file containing integer PCM samples of 8, 16, 24, or 32 bits or floating point
+/// samples of 32 or 64 bits.  Chunks other than `fmt ` and `data` are skipped, and a `data` chunk
+/// that runs past the end of the file is truncated rather than rejected since some recorders write
+/// the wrong length.
+pub fn decode_wav(bytes: &[u8]) -> Result<ImportedSample, SampleImportError> {
+    if !is_wav(bytes) {
+        return Err(SampleImportError::NotAWavFile);
+    }
+
+    let mut format: Option<WavFormat> = None;
+    let mut data: Option<&[u8]> = None;
+    let mut offset = 12;
+    while offset + 8 <= bytes.len() {
+        let chunk_id = &bytes[offset..offset + 4];
+        let chunk_len = read_u32(bytes, offset + 4) as usize;
+        let chunk_start = offset + 8;
+        let chunk_end = chunk_start.saturating_add(chunk_len).min(bytes.len());
+        let chunk = &bytes[chunk_start..chunk_end];
+
+        match chunk_id {
+            b"fmt " => format = Some(parse_fmt_chunk(chunk)?),
+            b"data" => data = Some(chunk),
+            _ => (),
+        }
+        // Chunks are padded to an even length
+        offset = chunk_start
+            .saturating_add(chunk_len)
+            .saturating_add(chunk_len % 2);
+    }
+
+    let format = format.ok_or(SampleImportError::MissingChunk("fmt "))?;
+    let data = data.ok_or(SampleImportError::MissingChunk("data"))?;
+
+    let bytes_per_sample = format.bits_per_sample as usize / 8;
+    let bytes_per_frame = bytes_per_sample * format.channel_count;
+    let len_frames = data.len() / bytes_per_frame;
+    let kept_channel_count = format.channel_count.min(MAX_CHANNEL_COUNT);
+    let mut channels = vec![Vec::with_capacity(len_frames); kept_channel_count];
+    for frame in data.chunks_exact(bytes_per_frame) {
+        for (channel_ix, channel) in channels.iter_mut().enumerate() {
+            let sample_offset = channel_ix * bytes_per_sample;
+            channel.push(decode_sample(
+                &format,
+                &frame[sample_offset..sample_offset + bytes_per_sample],
+            ));
+        }
+    }
+
+    Ok(ImportedSample {
+        channels,
+        sample_rate: format.sample_rate,
+    })
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-9 {
+        1.
+    } else {
+        let x = x * std::f64::consts::PI;
+        x.sin() / x
+    }
+}
+
+/// Blackman window over `[-1, 1]`
+fn blackman(x: f64) -> f64 {
+    let phase = std::f64::consts::PI * (x + 1.);
+    0.42 - 0.5 * phase.cos() + 0.08 * (2. * phase).cos()
+}
+
+/// Converts `samples` from `from_rate` to `to_rate` with a windowed sinc filter.  When converting
+/// down to a lower rate, the filter's cutoff is lowered to the new Nyquist frequency so that
+/// content above it is removed rather than aliased.
+pub fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    if from_rate == to_rate {
+        return samples.to_vec();
+    }
+
+    resample_by_ratio(samples, to_rate as f64 / from_rate as f64)
+}
+
+/// Resamples `samples` so that they're `ratio` times as long, in the same way as `resample`
+pub fn resample_by_ratio(samples: &[f32], ratio: f64) -> Vec<f32> {
+    if samples.is_empty() {
+        return Vec::new();
+    }
+
+    let cutoff = ratio.min(1.);
+    // The filter gets wider as the cutoff drops, measured in input samples
+    let half_width = RESAMPLER_HALF_TAPS as f64 / cutoff;
+    let out_len = (samples.len() as f64 * ratio).round() as usize;
+    let last_ix = samples.len() as isize - 1;
+
+    (0..out_len)
+        .map(|out_ix| {
+            let pos = out_ix as f64 / ratio;
+            let first_tap = (pos - half_width).ceil() as isize;
+            let last_tap = (pos + half_width).floor() as isize;
+
+            let (mut sum, mut weight_sum) = (0.0f64, 0.0f64);
+            for tap_ix in first_tap..=last_tap {
+                let offset = pos - tap_ix as f64;
+                let weight = sinc(offset * cutoff) * blackman(offset / half_width);
+                weight_sum += weight;
+                // Samples past either end of the input are treated as silence
+                if tap_ix >= 0 && tap_ix <= last_ix {
+                    sum += samples[tap_ix as usize] as f64 * weight;
+                }
+            }
+            (sum / weight_sum) as f32
+        })
+        .collect()
+}
+
+struct PoolEntry {
+    name: String,
+    sample: ImportedSample,
+    ref_count: usize,
+}
+
+/// Holds all imported samples and keeps track of how much memory they take up
+pub struct SamplePool {
+    pub max_bytes: usize,
+    bytes_used: usize,
+    next_id: u32,
+    entries: BTreeMap<u32, PoolEntry>,
+}
+
+impl Default for SamplePool {
+    fn default() -> Self { SamplePool::new(DEFAULT_MAX_POOL_BYTES) }
+}
+
+impl SamplePool {
+    pub fn new(max_bytes: usize) -> Self {
+        SamplePool {
+            max_bytes,
+            bytes_used: 0,
+            next_id: 0,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    pub fn bytes_used(&self) -> usize { self.bytes_used }
+
+    pub fn len(&self) -> usize { self.entries.len() }
+
+    pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+
+    pub fn get(&self, id: u32) -> Option<&ImportedSample> {
+        self.entries.get(&id).map(|entry| &entry.sample)
+    }
+
+    /// If a sample with the provided name has already been imported at `sample_rate`, adds a
+    /// reference to it and returns its ID.  This allows decoding to be skipped entirely for samples
+    /// that are already loaded.
+    pub fn acquire_existing(&mut self, name: &str, sample_rate: u32) -> Option<u32> {
+        let (&id, entry) = self
+            .entries
+            .iter_mut()
+            .find(|(_, entry)| entry.name == name && entry.sample.sample_rate == sample_rate)?;
+        entry.ref_count += 1;
+        Some(id)
+    }
+
+    /// Adds a sample to the pool with a single reference, returning its ID.  If a sample with the
+    /// same name and sample rate has already been imported, a reference to that one is returned
+    /// instead.
+    pub fn register(
+        &mut self,
+        name: &str,
+        sample: ImportedSample,
+    ) -> Result<u32, SampleImportError> {
+        if let Some(id) = self.acquire_existing(name, sample.sample_rate) {
+            return Ok(id);
+        }
+
+        let needed_bytes = sample.size_bytes();
+        let available_bytes = self.max_bytes.saturating_sub(self.bytes_used);
+        if needed_bytes > available_bytes {
+            return Err(SampleImportError::PoolFull {
+                needed_bytes,
+                available_bytes,
+            });
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.bytes_used += needed_bytes;
+        self.entries.insert(
+            id,
+            PoolEntry {
+                name: name.to_owned(),
+                sample,
+                ref_count: 1,
+            },
+        );
+        Ok(id)
+    }
+
+    /// Removes a reference to a sample, freeing it once it has no references left.  Returns `false`
+    /// if there is no sample with the provided ID.
+    pub fn release(&mut self, id: u32) -> bool {
+        let entry = match self.entries.get_mut(&id) {
+            Some(entry) => entry,
+            None => return false,
+        };
+
+        entry.ref_count -= 1;
+        if entry.ref_count == 0 {
+            let entry = self.entries.remove(&id).unwrap();
+            self.bytes_used -= entry.sample.size_bytes();
+        }
+        true
+    }
+}
+
+/// The sample pool shared by all samplers in the application
+pub fn get_sample_pool() -> &'static mut SamplePool { &mut ctx().sample_pool }
+
+fn register_imported_sample(
+    name: &str,
+    sample: Result<ImportedSample, SampleImportError>,
+    target_sample_rate: u32,
+) -> Option<u32> {
+    let res = sample.and_then(|mut sample| {
+        sample.resample(target_sample_rate);
+        get_sample_pool().register(name, sample)
+    });
+    match res {
+        Ok(id) => Some(id),
+        Err(err) => {
+            error!("Failed to import sample \"{}\": {:?}", name, err);
+            None
+        },
+    }
+}
+
+#[wasm_bindgen]
+pub fn is_wav_file(bytes: &[u8]) -> bool { is_wav(bytes) }
+
+/// Decodes a WAV file and adds it to the sample pool at `target_sample_rate`, returning its ID.
+/// Returns nothing if the file can't be decoded or there isn't enough room for it in the pool.
+#[wasm_bindgen]
+pub fn import_wav_sample(name: &str, bytes: &[u8], target_sample_rate: u32) -> Option<u32> {
+    register_imported_sample(name, decode_wav(bytes), target_sample_rate)
+}
+
+/// Adds a sample that has already been decoded to the sample pool at `target_sample_rate`,
+/// returning its ID.  `samples` are planar, with all of the first channel's samples followed by the
+/// second channel's and so on.
+#[wasm_bindgen]
+pub fn import_pcm_sample(
+    name: &str,
+    samples: &[f32],
+    channel_count: usize,
+    sample_rate: u32,
+    target_sample_rate: u32,
+) -> Option<u32> {
+    let sample = ImportedSample::from_planar(samples, channel_count, sample_rate);
+    register_imported_sample(name, sample, target_sample_rate)
+}
+
+#[wasm_bindgen]
+pub fn acquire_imported_sample(name: &str, sample_rate: u32) -> Option<u32> {
+    get_sample_pool().acquire_existing(name, sample_rate)
+}
+
+#[wasm_bindgen]
+pub fn release_imported_sample(id: u32) {
+    if !get_sample_pool().release(id) {
+        warn!(
+            "Tried to release imported sample {} which doesn't exist",
+            id
+        );
+    }
+}
+
+#[wasm_bindgen]
+pub fn get_imported_sample_channel_count(id: u32) -> usize {
+    get_sample_pool()
+        .get(id)
+        .map(ImportedSample::channel_count)
+        .unwrap_or(0)
+}
+
+/// Returns all of the first channel's samples followed by all of the second channel's, if any
+#[wasm_bindgen]
+pub fn get_imported_sample_planar(id: u32) -> Vec<f32> {
+    get_sample_pool()
+        .get(id)
+        .map(ImportedSample::planar_samples)
+        .unwrap_or_default()
+}
+
+#[wasm_bindgen]
+pub fn get_sample_pool_bytes_used() -> usize { get_sample_pool().bytes_used() }
+
+#[wasm_bindgen]
+pub fn set_sample_pool_max_bytes(max_bytes: usize) { get_sample_pool().max_bytes = max_bytes; }
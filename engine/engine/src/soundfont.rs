@@ -0,0 +1,768 @@
+//! Loads SoundFont 2 (.sf2) files so that their presets can be played by the SoundFont player.
+//!
+//! A SoundFont is a hierarchy of presets, instruments, and samples.  Presets are made up of zones
+//! that each map a range of keys and velocities to an instrument, and instruments are made up of
+//! zones that map ranges to samples.  Both levels set generators, which are the parameters of the
+//! sound such as its tuning, volume envelope, and loop points.  Here, that hierarchy is flattened
+//! into a list of regions for a single preset, each of which plays one sample with all of its
+//! generators resolved.  That's all that the player needs to know about the preset.
+//!
+//! Loaded SoundFonts are kept in a registry that's shared by all players in the application.  The
+//! samples of a preset are copied into the sample pool when the preset is loaded so that memory
+//! limits apply to them like they do to any other sample.
+
+use std::collections::BTreeMap;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    context::ctx,
+    sample_import::{get_sample_pool, ImportedSample},
+    util::clamp,
+};
+
+const PHDR_RECORD_LEN: usize = 38;
+const BAG_RECORD_LEN: usize = 4;
+const GEN_RECORD_LEN: usize = 4;
+const INST_RECORD_LEN: usize = 22;
+const SHDR_RECORD_LEN: usize = 46;
+/// Names are stored in fixed-size fields padded with zeros
+const NAME_LEN: usize = 20;
+
+/// Number of generators defined by the SoundFont 2.04 spec
+const GENERATOR_COUNT: usize = 61;
+/// Generators are referred to by their index in the spec
+mod gen {
+    pub const START_ADDRS_OFFSET: usize = 0;
+    pub const END_ADDRS_OFFSET: usize = 1;
+    pub const STARTLOOP_ADDRS_OFFSET: usize = 2;
+    pub const ENDLOOP_ADDRS_OFFSET: usize = 3;
+    pub const START_ADDRS_COARSE_OFFSET: usize = 4;
+    pub const END_ADDRS_COARSE_OFFSET: usize = 12;
+    pub const PAN: usize = 17;
+    pub const DELAY_VOL_ENV: usize = 33;
+    pub const ATTACK_VOL_ENV: usize = 34;
+    pub const HOLD_VOL_ENV: usize = 35;
+    pub const DECAY_VOL_ENV: usize = 36;
+    pub const SUSTAIN_VOL_ENV: usize = 37;
+    pub const RELEASE_VOL_ENV: usize = 38;
+    pub const INSTRUMENT: usize = 41;
+    pub const KEY_RANGE: usize = 43;
+    pub const VEL_RANGE: usize = 44;
+    pub const STARTLOOP_ADDRS_COARSE_OFFSET: usize = 45;
+    pub const INITIAL_ATTENUATION: usize = 48;
+    pub const ENDLOOP_ADDRS_COARSE_OFFSET: usize = 50;
+    pub const COARSE_TUNE: usize = 51;
+    pub const FINE_TUNE: usize = 52;
+    pub const SAMPLE_ID: usize = 53;
+    pub const SAMPLE_MODES: usize = 54;
+    pub const SCALE_TUNING: usize = 56;
+    pub const EXCLUSIVE_CLASS: usize = 57;
+    pub const OVERRIDING_ROOT_KEY: usize = 58;
+}
+
+/// Generators that are only valid in instrument zones and are ignored when set by presets
+const INSTRUMENT_ONLY_GENERATORS: &[usize] = &[
+    gen::START_ADDRS_OFFSET,
+    gen::END_ADDRS_OFFSET,
+    gen::STARTLOOP_ADDRS_OFFSET,
+    gen::ENDLOOP_ADDRS_OFFSET,
+    gen::START_ADDRS_COARSE_OFFSET,
+    gen::END_ADDRS_COARSE_OFFSET,
+    gen::STARTLOOP_ADDRS_COARSE_OFFSET,
+    gen::ENDLOOP_ADDRS_COARSE_OFFSET,
+    gen::SAMPLE_MODES,
+    gen::EXCLUSIVE_CLASS,
+    gen::OVERRIDING_ROOT_KEY,
+];
+
+/// Set on the sample type of samples that live in ROM rather than in the file
+const ROM_SAMPLE_FLAG: u16 = 0x8000;
+
+#[derive(Debug, PartialEq)]
+pub enum SoundFontError {
+    NotASoundFont,
+    MissingChunk(&'static str),
+    /// A chunk's length isn't a multiple of its record size or it's missing its terminal record
+    InvalidChunk(&'static str),
+    PresetNotFound {
+        bank: u16,
+        program: u16,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PresetHeader {
+    pub name: String,
+    pub program: u16,
+    pub bank: u16,
+    #[serde(skip)]
+    bag_ix: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct InstrumentHeader {
+    bag_ix: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SampleHeader {
+    pub name: String,
+    /// Frames in the sample data chunk.  The end is exclusive.
+    pub start: usize,
+    pub end: usize,
+    pub loop_start: usize,
+    pub loop_end: usize,
+    pub sample_rate: u32,
+    pub original_pitch: u8,
+    /// In cents
+    pub pitch_correction: i8,
+    pub sample_type: u16,
+}
+
+/// The generators set by a zone, indexed by generator.  Range generators hold their low value in
+/// the low byte and their high value in the high byte.
+type Generators = [Option<i16>; GENERATOR_COUNT];
+
+#[derive(Clone, Debug)]
+struct Zone {
+    generators: Generators,
+}
+
+impl Zone {
+    fn get(&self, generator: usize) -> Option<i16> { self.generators[generator] }
+
+    fn range(&self, generator: usize) -> (u8, u8) {
+        match self.get(generator) {
+            Some(amount) => {
+                let amount = amount as u16;
+                ((amount & 0xff) as u8, (amount >> 8) as u8)
+            },
+            None => (0, 127),
+        }
+    }
+
+    /// Combines a global zone with a local one, with the local zone's generators taking priority
+    fn with_global(&self, global: Option<&Zone>) -> Zone {
+        let mut generators = self.generators;
+        if let Some(global) = global {
+            for (generator, global_amount) in generators.iter_mut().zip(global.generators.iter()) {
+                if generator.is_none() {
+                    *generator = *global_amount;
+                }
+            }
+        }
+        Zone { generators }
+    }
+}
+
+/// Intersects two inclusive ranges, returning `None` if they don't overlap
+fn intersect_ranges((lo_a, hi_a): (u8, u8), (lo_b, hi_b): (u8, u8)) -> Option<(u8, u8)> {
+    let (lo, hi) = (lo_a.max(lo_b), hi_a.min(hi_b));
+    if lo <= hi {
+        Some((lo, hi))
+    } else {
+        None
+    }
+}
+
+/// Envelope times are stored in timecents, with the default of -12000 being about a millisecond
+fn timecents_to_seconds(timecents: i32) -> f32 { 2f32.powf(timecents as f32 / 1200.) }
+
+/// Attenuations are stored in centibels
+fn centibels_to_gain(centibels: i32) -> f32 { 10f32.powf(-centibels.max(0) as f32 / 200.) }
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoopMode {
+    NoLoop,
+    /// The loop is played for as long as the note lasts, including its release
+    Continuous,
+    /// The loop is played until the note is released, after which the rest of the sample plays
+    UntilRelease,
+}
+
+/// A sample played over a range of keys and velocities with all of the generators that apply to
+/// it resolved.  Positions are in frames from the start of the region's sample.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Region {
+    pub key_lo: u8,
+    pub key_hi: u8,
+    pub vel_lo: u8,
+    pub vel_hi: u8,
+    /// Index of the sample header in the SoundFont
+    pub sample_ix: usize,
+    pub start: usize,
+    pub end: usize,
+    pub loop_mode: LoopMode,
+    pub loop_start: usize,
+    pub loop_end: usize,
+    /// The key at which the sample plays back at its original pitch
+    pub root_key: u8,
+    /// Total tuning in cents, including the sample's pitch correction
+    pub tune_cents: f32,
+    /// Cents of pitch change per key
+    pub scale_tuning: f32,
+    pub gain: f32,
+    /// In `[-1, 1]`, from hard left to hard right
+    pub pan: f32,
+    /// Volume envelope stage lengths in seconds
+    pub delay: f32,
+    pub attack: f32,
+    pub hold: f32,
+    pub decay: f32,
+    /// Linear gain held while the note is held after the decay
+    pub sustain_level: f32,
+    pub release: f32,
+    /// Starting a note in a non-zero exclusive class stops all other notes in that class, which is
+    /// used for things like open and closed hi-hats
+    pub exclusive_class: u16,
+}
+
+pub struct SoundFont {
+    pub name: String,
+    presets: Vec<PresetHeader>,
+    preset_zones: Vec<Zone>,
+    instruments: Vec<InstrumentHeader>,
+    instrument_zones: Vec<Zone>,
+    pub samples: Vec<SampleHeader>,
+    /// The 16-bit sample data, plus the low byte of each sample for 24-bit files
+    smpl: Vec<i16>,
+    sm24: Option<Vec<u8>>,
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut buf = [0u8; 4];
+    buf.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(buf)
+}
+
+fn read_name(bytes: &[u8]) -> String {
+    let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..len]).trim().to_owned()
+}
+
+/// Returns the ID and contents of each chunk in `bytes`, stopping at the first chunk that runs past
+/// the end
+fn read_chunks(bytes: &[u8]) -> Vec<(&[u8], &[u8])> {
+    let mut chunks = Vec::new();
+    let mut offset = 0;
+    while offset + 8 <= bytes.len() {
+        let chunk_len = read_u32(bytes, offset + 4) as usize;
+        let chunk_start = offset + 8;
+        let chunk_end = match chunk_start.checked_add(chunk_len) {
+            Some(end) if end <= bytes.len() => end,
+            _ => break,
+        };
+        chunks.push((&bytes[offset..offset + 4], &bytes[chunk_start..chunk_end]));
+        // Chunks are padded to an even length
+        offset = chunk_end + chunk_len % 2;
+    }
+    chunks
+}
+
+/// Returns the contents of the `LIST` chunk with the provided type
+fn find_list<'a>(chunks: &[(&[u8], &'a [u8])], list_type: &[u8]) -> Option<&'a [u8]> {
+    chunks
+        .iter()
+        .find(|(id, data)| *id == b"LIST" && data.len() >= 4 && &data[..4] == list_type)
+        .map(|(_, data)| &data[4..])
+}
+
+fn find_chunk<'a>(chunks: &[(&[u8], &'a [u8])], id: &[u8]) -> Option<&'a [u8]> {
+    chunks
+        .iter()
+        .find(|(chunk_id, _)| *chunk_id == id)
+        .map(|(_, data)| *data)
+}
+
+/// Splits a chunk of fixed-size records, dropping the terminal record that ends each of the
+/// `pdta` chunks
+fn records<'a>(
+    chunks: &[(&[u8], &'a [u8])],
+    id: &'static str,
+    record_len: usize,
+) -> Result<Vec<&'a [u8]>, SoundFontError> {
+    let data = find_chunk(chunks, id.as_bytes()).ok_or(SoundFontError::MissingChunk(id))?;
+    if data.len() % record_len != 0 || data.len() < record_len {
+        return Err(SoundFontError::InvalidChunk(id));
+    }
+    let mut records: Vec<&[u8]> = data.chunks_exact(record_len).collect();
+    records.pop();
+    Ok(records)
+}
+
+/// Reads the zones of the preset or instrument level, which are the bags that index into the
+/// generator list
+fn read_zones(bags: &[&[u8]], gens: &[&[u8]]) -> Vec<Zone> {
+    // The terminal bag isn't included in `bags`, but its index marks the end of the last zone
+    let bag_gen_ix = |bag_ix: usize| -> usize {
+        match bags.get(bag_ix) {
+            Some(bag) => read_u16(bag, 0) as usize,
+            None => gens.len(),
+        }
+    };
+
+    (0..bags.len())
+        .map(|bag_ix| {
+            let mut generators: Generators = [None; GENERATOR_COUNT];
+            let (start, end) = (bag_gen_ix(bag_ix), bag_gen_ix(bag_ix + 1).min(gens.len()));
+            for gen_record in gens.get(start..end).unwrap_or(&[]) {
+                let generator = read_u16(gen_record, 0) as usize;
+                if generator < GENERATOR_COUNT {
+                    generators[generator] = Some(read_u16(gen_record, 2) as i16);
+                }
+            }
+            Zone { generators }
+        })
+        .collect()
+}
+
+/// Returns the global zone of a preset or instrument, if it has one, along with its other zones.
+/// The global zone is the first one if it doesn't end with `terminal_generator`, which is the
+/// generator that links a zone to the level below.
+fn split_global_zone(zones: &[Zone], terminal_generator: usize) -> (Option<&Zone>, &[Zone]) {
+    match zones.first() {
+        Some(first) if first.get(terminal_generator).is_none() => (Some(first), &zones[1..]),
+        _ => (None, zones),
+    }
+}
+
+impl SoundFont {
+    pub fn parse(name: &str, bytes: &[u8]) -> Result<Self, SoundFontError> {
+        if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"sfbk" {
+            return Err(SoundFontError::NotASoundFont);
+        }
+
+        let top_level = read_chunks(&bytes[12..]);
+        let sdta = read_chunks(
+            find_list(&top_level, b"sdta").ok_or(SoundFontError::MissingChunk("sdta"))?,
+        );
+        let pdta = read_chunks(
+            find_list(&top_level, b"pdta").ok_or(SoundFontError::MissingChunk("pdta"))?,
+        );
+
+        let smpl: Vec<i16> = find_chunk(&sdta, b"smpl")
+            .ok_or(SoundFontError::MissingChunk("smpl"))?
+            .chunks_exact(2)
+            .map(|sample| i16::from_le_bytes([sample[0], sample[1]]))
+            .collect();
+        // The low bytes are ignored if there aren't enough of them for the 16-bit data
+        let sm24 = find_chunk(&sdta, b"sm24")
+            .filter(|sm24| sm24.len() >= smpl.len())
+            .map(|sm24| sm24.to_vec());
+
+        let phdr = records(&pdta, "phdr", PHDR_RECORD_LEN)?;
+        let inst = records(&pdta, "inst", INST_RECORD_LEN)?;
+        let pbag = records(&pdta, "pbag", BAG_RECORD_LEN)?;
+        let ibag = records(&pdta, "ibag", BAG_RECORD_LEN)?;
+
+        let presets = phdr
+            .iter()
+            .map(|record| PresetHeader {
+                name: read_name(&record[..NAME_LEN]),
+                program: read_u16(record, 20),
+                bank: read_u16(record, 22),
+                bag_ix: read_u16(record, 24) as usize,
+            })
+            .collect();
+        let instruments = inst
+            .iter()
+            .map(|record| InstrumentHeader {
+                bag_ix: read_u16(record, NAME_LEN) as usize,
+            })
+            .collect();
+        let samples = records(&pdta, "shdr", SHDR_RECORD_LEN)?
+            .iter()
+            .map(|record| SampleHeader {
+                name: read_name(&record[..NAME_LEN]),
+                start: read_u32(record, 20) as usize,
+                end: read_u32(record, 24) as usize,
+                loop_start: read_u32(record, 28) as usize,
+                loop_end: read_u32(record, 32) as usize,
+                sample_rate: read_u32(record, 36),
+                original_pitch: record[40],
+                pitch_correction: record[41] as i8,
+                sample_type: read_u16(record, 44),
+            })
+            .collect();
+
+        Ok(SoundFont {
+            name: name.to_owned(),
+            presets,
+            preset_zones: read_zones(&pbag, &records(&pdta, "pgen", GEN_RECORD_LEN)?),
+            instruments,
+            instrument_zones: read_zones(&ibag, &records(&pdta, "igen", GEN_RECORD_LEN)?),
+            samples,
+            smpl,
+            sm24,
+        })
+    }
+
+    /// Returns all presets in the SoundFont, sorted by bank and then program
+    pub fn presets(&self) -> Vec<PresetHeader> {
+        let mut presets = self.presets.clone();
+        presets.sort_by_key(|preset| (preset.bank, preset.program));
+        presets
+    }
+
+    /// Returns the zones of a preset or instrument given the bag index of its header and of the
+    /// header after it
+    fn zones(zones: &[Zone], bag_ix: usize, next_bag_ix: Option<usize>) -> &[Zone] {
+        let end = next_bag_ix.unwrap_or(zones.len()).min(zones.len());
+        zones.get(bag_ix..end).unwrap_or(&[])
+    }
+
+    /// Builds the regions that are played by the preset in the provided bank with the provided
+    /// program number
+    pub fn preset_regions(&self, bank: u16, program: u16) -> Result<Vec<Region>, SoundFontError> {
+        let preset_ix = self
+            .presets
+            .iter()
+            .position(|preset| preset.bank == bank && preset.program == program)
+            .ok_or(SoundFontError::PresetNotFound { bank, program })?;
+        let preset_zones = Self::zones(
+            &self.preset_zones,
+            self.presets[preset_ix].bag_ix,
+            self.presets.get(preset_ix + 1).map(|next| next.bag_ix),
+        );
+        let (global_preset_zone, preset_zones) = split_global_zone(preset_zones, gen::INSTRUMENT);
+
+        let mut regions = Vec::new();
+        for preset_zone in preset_zones {
+            let preset_zone = preset_zone.with_global(global_preset_zone);
+            let instrument_ix = match preset_zone.get(gen::INSTRUMENT) {
+                Some(ix) if (ix as u16 as usize) < self.instruments.len() => ix as u16 as usize,
+                _ => continue,
+            };
+            let instrument_zones = Self::zones(
+                &self.instrument_zones,
+                self.instruments[instrument_ix].bag_ix,
+                self.instruments
+                    .get(instrument_ix + 1)
+                    .map(|next| next.bag_ix),
+            );
+            let (global_instrument_zone, instrument_zones) =
+                split_global_zone(instrument_zones, gen::SAMPLE_ID);
+
+            for instrument_zone in instrument_zones {
+                let instrument_zone = instrument_zone.with_global(global_instrument_zone);
+                if let Some(region) = self.build_region(&preset_zone, &instrument_zone) {
+                    regions.push(region);
+                }
+            }
+        }
+        Ok(regions)
+    }
+
+    /// Resolves the generators of an instrument zone played through a preset zone.  Preset
+    /// generators are offsets that are added to the instrument's.
+    fn build_region(&self, preset_zone: &Zone, instrument_zone: &Zone) -> Option<Region> {
+        let sample_ix = instrument_zone.get(gen::SAMPLE_ID)? as u16 as usize;
+        let sample = self.samples.get(sample_ix)?;
+        if sample.sample_type & ROM_SAMPLE_FLAG != 0 || sample.end <= sample.start {
+            return None;
+        }
+
+        let (key_lo, key_hi) = intersect_ranges(
+            preset_zone.range(gen::KEY_RANGE),
+            instrument_zone.range(gen::KEY_RANGE),
+        )?;
+        let (vel_lo, vel_hi) = intersect_ranges(
+            preset_zone.range(gen::VEL_RANGE),
+            instrument_zone.range(gen::VEL_RANGE),
+        )?;
+
+        let get = |generator: usize, default: i16| -> i32 {
+            let instrument_amount = instrument_zone.get(generator).unwrap_or(default) as i32;
+            if INSTRUMENT_ONLY_GENERATORS.contains(&generator) {
+                return instrument_amount;
+            }
+            instrument_amount + preset_zone.get(generator).unwrap_or(0) as i32
+        };
+        // Positions are made relative to the start of the sample and kept within it
+        let sample_len = (sample.end - sample.start) as i64;
+        let address = |base: usize, fine: usize, coarse: usize| -> usize {
+            let pos = base as i64 - sample.start as i64
+                + get(fine, 0) as i64
+                + get(coarse, 0) as i64 * 32768;
+            pos.clamp(0, sample_len) as usize
+        };
+
+        let start = address(
+            sample.start,
+            gen::START_ADDRS_OFFSET,
+            gen::START_ADDRS_COARSE_OFFSET,
+        );
+        let end = address(
+            sample.end,
+            gen::END_ADDRS_OFFSET,
+            gen::END_ADDRS_COARSE_OFFSET,
+        );
+        let loop_start = address(
+            sample.loop_start,
+            gen::STARTLOOP_ADDRS_OFFSET,
+            gen::STARTLOOP_ADDRS_COARSE_OFFSET,
+        );
+        let loop_end = address(
+            sample.loop_end,
+            gen::ENDLOOP_ADDRS_OFFSET,
+            gen::ENDLOOP_ADDRS_COARSE_OFFSET,
+        );
+        if end <= start {
+            return None;
+        }
+        let loop_mode = match get(gen::SAMPLE_MODES, 0) & 0b11 {
+            _ if loop_end <= loop_start => LoopMode::NoLoop,
+            1 => LoopMode::Continuous,
+            3 => LoopMode::UntilRelease,
+            _ => LoopMode::NoLoop,
+        };
+
+        let root_key = match get(gen::OVERRIDING_ROOT_KEY, -1) {
+            key @ 0..=127 => key as u8,
+            // Pitches past 127 mean that the sample is unpitched
+            _ if sample.original_pitch > 127 => 60,
+            _ => sample.original_pitch,
+        };
+        let tune_cents = get(gen::COARSE_TUNE, 0) * 100
+            + get(gen::FINE_TUNE, 0)
+            + sample.pitch_correction as i32;
+
+        Some(Region {
+            key_lo,
+            key_hi,
+            vel_lo,
+            vel_hi,
+            sample_ix,
+            start,
+            end,
+            loop_mode,
+            loop_start,
+            loop_end,
+            root_key,
+            tune_cents: tune_cents as f32,
+            scale_tuning: get(gen::SCALE_TUNING, 100) as f32,
+            gain: centibels_to_gain(get(gen::INITIAL_ATTENUATION, 0)),
+            pan: clamp(get(gen::PAN, 0) as f32 / 500., -1., 1.),
+            delay: timecents_to_seconds(get(gen::DELAY_VOL_ENV, -12000)),
+            attack: timecents_to_seconds(get(gen::ATTACK_VOL_ENV, -12000)),
+            hold: timecents_to_seconds(get(gen::HOLD_VOL_ENV, -12000)),
+            decay: timecents_to_seconds(get(gen::DECAY_VOL_ENV, -12000)),
+            sustain_level: centibels_to_gain(get(gen::SUSTAIN_VOL_ENV, 0).min(1440)),
+            release: timecents_to_seconds(get(gen::RELEASE_VOL_ENV, -12000)),
+            exclusive_class: get(gen::EXCLUSIVE_CLASS, 0).max(0) as u16,
+        })
+    }
+
+    /// Returns the data of the sample with the provided index as `f32`s in `[-1, 1]`
+    pub fn sample_data(&self, sample_ix: usize) -> Vec<f32> {
+        let sample = match self.samples.get(sample_ix) {
+            Some(sample) => sample,
+            None => return Vec::new(),
+        };
+        let start = sample.start.min(self.smpl.len());
+        let end = sample.end.min(self.smpl.len()).max(start);
+        match &self.sm24 {
+            Some(sm24) => (start..end)
+                .map(|i| ((self.smpl[i] as i32) << 8 | sm24[i] as i32) as f32 / 8_388_608.)
+                .collect(),
+            None => self.smpl[start..end]
+                .iter()
+                .map(|&sample| sample as f32 / 32768.)
+                .collect(),
+        }
+    }
+}
+
+struct RegistryEntry {
+    font: SoundFont,
+    ref_count: usize,
+}
+
+/// Holds all loaded SoundFonts.  Players that load the same file share a single entry, which is
+/// freed once all of them have released it.
+#[derive(Default)]
+pub struct SoundFontRegistry {
+    next_id: u32,
+    entries: BTreeMap<u32, RegistryEntry>,
+}
+
+impl SoundFontRegistry {
+    pub fn get(&self, id: u32) -> Option<&SoundFont> {
+        self.entries.get(&id).map(|entry| &entry.font)
+    }
+
+    /// Adds a reference to the SoundFont with the provided name if it's already been loaded
+    pub fn acquire_existing(&mut self, name: &str) -> Option<u32> {
+        let (&id, entry) = self
+            .entries
+            .iter_mut()
+            .find(|(_, entry)| entry.font.name == name)?;
+        entry.ref_count += 1;
+        Some(id)
+    }
+
+    pub fn register(&mut self, font: SoundFont) -> u32 {
+        if let Some(id) = self.acquire_existing(&font.name) {
+            return id;
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.entries
+            .insert(id, RegistryEntry { font, ref_count: 1 });
+        id
+    }
+
+    /// Removes a reference to a SoundFont, freeing it once it has no references left.  Returns
+    /// `false` if there is no SoundFont with the provided ID.
+    pub fn release(&mut self, id: u32) -> bool {
+        let entry = match self.entries.get_mut(&id) {
+            Some(entry) => entry,
+            None => return false,
+        };
+
+        entry.ref_count -= 1;
+        if entry.ref_count == 0 {
+            self.entries.remove(&id);
+        }
+        true
+    }
+}
+
+/// The SoundFont registry shared by all SoundFont players in the application
+pub fn get_soundfont_registry() -> &'static mut SoundFontRegistry {
+    &mut ctx().soundfont_registry
+}
+
+/// A preset that has been loaded for playback.  Its regions' `sample_ix` index into `samples`
+/// rather than the SoundFont's sample headers.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadedPreset {
+    pub regions: Vec<Region>,
+    pub samples: Vec<LoadedPresetSample>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadedPresetSample {
+    /// ID of the sample in the sample pool, which must be released once the preset is unloaded
+    pub pool_id: u32,
+    pub sample_rate: u32,
+}
+
+/// Copies the samples used by a preset into the sample pool at their original sample rates.  The
+/// player resamples them while playing anyway since each note plays them at a different pitch.
+pub fn load_preset(font: &SoundFont, bank: u16, program: u16) -> Option<LoadedPreset> {
+    let mut regions = match font.preset_regions(bank, program) {
+        Ok(regions) => regions,
+        Err(err) => {
+            error!(
+                "Failed to load preset from SoundFont \"{}\": {:?}",
+                font.name, err
+            );
+            return None;
+        },
+    };
+
+    let pool = get_sample_pool();
+    let mut sample_ixs: Vec<usize> = Vec::new();
+    let mut samples: Vec<LoadedPresetSample> = Vec::new();
+    for region in &mut regions {
+        if let Some(ix) = sample_ixs.iter().position(|&ix| ix == region.sample_ix) {
+            region.sample_ix = ix;
+            continue;
+        }
+
+        let header = &font.samples[region.sample_ix];
+        let sample = ImportedSample {
+            channels: vec![font.sample_data(region.sample_ix)],
+            sample_rate: header.sample_rate,
+        };
+        let pool_name = format!("sf:{}:{}", font.name, region.sample_ix);
+        match pool.register(&pool_name, sample) {
+            Ok(pool_id) => samples.push(LoadedPresetSample {
+                pool_id,
+                sample_rate: header.sample_rate,
+            }),
+            Err(err) => {
+                error!(
+                    "Failed to load SoundFont sample \"{}\": {:?}",
+                    header.name, err
+                );
+                for sample in samples {
+                    pool.release(sample.pool_id);
+                }
+                return None;
+            },
+        }
+        sample_ixs.push(region.sample_ix);
+        region.sample_ix = samples.len() - 1;
+    }
+
+    Some(LoadedPreset { regions, samples })
+}
+
+/// Parses a SoundFont and adds it to the registry, returning its ID.  If a SoundFont with the same
+/// name has already been loaded, a reference to it is returned instead.
+#[wasm_bindgen]
+pub fn load_soundfont(name: &str, bytes: &[u8]) -> Option<u32> {
+    let registry = get_soundfont_registry();
+    if let Some(id) = registry.acquire_existing(name) {
+        return Some(id);
+    }
+
+    match SoundFont::parse(name, bytes) {
+        Ok(font) => Some(registry.register(font)),
+        Err(err) => {
+            error!("Failed to load SoundFont \"{}\": {:?}", name, err);
+            None
+        },
+    }
+}
+
+#[wasm_bindgen]
+pub fn acquire_soundfont(name: &str) -> Option<u32> {
+    get_soundfont_registry().acquire_existing(name)
+}
+
+#[wasm_bindgen]
+pub fn release_soundfont(id: u32) {
+    if !get_soundfont_registry().release(id) {
+        warn!("Tried to release SoundFont {} which doesn't exist", id);
+    }
+}
+
+/// Returns the presets of the SoundFont as JSON
+#[wasm_bindgen]
+pub fn get_soundfont_presets(id: u32) -> String {
+    let presets = get_soundfont_registry()
+        .get(id)
+        .map(SoundFont::presets)
+        .unwrap_or_default();
+    serde_json::to_string(&presets).unwrap()
+}
+
+/// Loads the samples of a preset into the sample pool, returning the preset as JSON.  Returns
+/// nothing if the preset doesn't exist or its samples don't fit in the pool.
+#[wasm_bindgen]
+pub fn load_soundfont_preset(id: u32, bank: u16, program: u16) -> Option<String> {
+    let font = match get_soundfont_registry().get(id) {
+        Some(font) => font,
+        None => {
+            error!(
+                "Tried to load preset from SoundFont {} which doesn't exist",
+                id
+            );
+            return None;
+        },
+    };
+    load_preset(font, bank, program).map(|preset| serde_json::to_string(&preset).unwrap())
+}
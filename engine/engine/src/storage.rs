@@ -0,0 +1,96 @@
+//! Persistent key-value storage for saved state.  All of the engine's saving and loading goes
+//! through the `Storage` trait so that the place where state is kept can be swapped out.
+//!
+//! State that's owned by the engine, such as the VCM state, VC definitions, and note data, is kept
+//! in the storage returned by `get_storage`.  State of VCs whose UIs are implemented in JS is read
+//! from `localStorage` by JS directly, so it's always kept in `LocalStorage`.
+//!
+//! The engine reads its state synchronously while initializing.  Backends built on asynchronous
+//! APIs such as IndexedDB load their entries into memory before the engine is initialized and
+//! write changes back in the background, so the trait itself is synchronous.
+
+use std::collections::BTreeMap;
+
+use crate::{context::ctx, js};
+
+pub trait Storage {
+    fn get(&self, key: &str) -> Option<String>;
+    fn set(&mut self, key: &str, val: &str);
+    fn delete(&mut self, key: &str);
+    fn list_keys(&self) -> Vec<String>;
+}
+
+/// Stores state in the browser's `localStorage`, which is synchronous but limited to a few
+/// megabytes in total.
+pub struct LocalStorage;
+
+impl Storage for LocalStorage {
+    fn get(&self, key: &str) -> Option<String> { js::get_localstorage_key(key) }
+
+    fn set(&mut self, key: &str, val: &str) { js::set_localstorage_key(key, val); }
+
+    fn delete(&mut self, key: &str) { js::delete_localstorage_key(key); }
+
+    fn list_keys(&self) -> Vec<String> { parse_keys(&js::list_localstorage_keys()) }
+}
+
+/// Stores state in IndexedDB, which can hold much more data than `localStorage`.  Entries that
+/// are still in `localStorage`, either from before IndexedDB was used or from importing a
+/// composition, take precedence and are moved into IndexedDB the next time that they're saved.
+pub struct IndexedDBStorage;
+
+impl Storage for IndexedDBStorage {
+    fn get(&self, key: &str) -> Option<String> {
+        js::get_localstorage_key(key).or_else(|| js::get_indexeddb_key(key))
+    }
+
+    fn set(&mut self, key: &str, val: &str) {
+        js::set_indexeddb_key(key, val);
+        js::delete_localstorage_key(key);
+    }
+
+    fn delete(&mut self, key: &str) {
+        js::delete_indexeddb_key(key);
+        js::delete_localstorage_key(key);
+    }
+
+    fn list_keys(&self) -> Vec<String> {
+        let mut keys = parse_keys(&js::list_indexeddb_keys());
+        keys.extend(parse_keys(&js::list_localstorage_keys()));
+        keys.sort_unstable();
+        keys.dedup();
+        keys
+    }
+}
+
+/// Keeps state in memory only, losing it when the page is closed
+#[derive(Default)]
+pub struct MemoryStorage {
+    entries: BTreeMap<String, String>,
+}
+
+impl Storage for MemoryStorage {
+    fn get(&self, key: &str) -> Option<String> { self.entries.get(key).cloned() }
+
+    fn set(&mut self, key: &str, val: &str) { self.entries.insert(key.into(), val.into()); }
+
+    fn delete(&mut self, key: &str) { self.entries.remove(key); }
+
+    fn list_keys(&self) -> Vec<String> { self.entries.keys().cloned().collect() }
+}
+
+/// Keys are listed by JS as a JSON array
+fn parse_keys(keys_json: &str) -> Vec<String> {
+    serde_json::from_str(keys_json).unwrap_or_else(|err| {
+        error!("Error parsing listed storage keys: {:?}", err);
+        Vec::new()
+    })
+}
+
+/// The storage used for state owned by the engine.  It's `LocalStorage` unless it's replaced with
+/// `set_storage`.
+pub fn get_storage() -> &'static mut dyn Storage { &mut *ctx().storage }
+
+/// Replaces the storage used for state owned by the engine.  This should be done before the VCM is
+/// initialized so that its state is loaded from the new storage.
+pub fn set_storage(storage: Box<dyn Storage>) { ctx().storage = storage; }
@@ -7,3 +7,21 @@ pub fn tern<T>(cond: bool, if_true: T, if_false: T) -> T {
 }
 
 pub fn clamp(val: f32, min: f32, max: f32) -> f32 { val.max(min).min(max) }
+
+/// Decodes a buffer of native-endian `f32`s such as the bytes of a `Float32Array` sent from JS.
+/// Trailing bytes that don't make up a full sample are ignored.
+pub fn f32s_from_bytes(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|chunk| f32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect()
+}
+
+/// Encodes `samples` as native-endian bytes that can be viewed as a `Float32Array` in JS
+pub fn f32s_to_bytes(samples: &[f32]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(samples.len() * 4);
+    for sample in samples {
+        bytes.extend_from_slice(&sample.to_ne_bytes());
+    }
+    bytes
+}
@@ -0,0 +1,30 @@
+//! Periodically saves the view contexts that have been touched since they were last saved so that
+//! work isn't lost if the page is closed without `handle_window_close` being called.  JS calls
+//! `autosave` on a timer, and saves are throttled so that a burst of edits results in a single
+//! save rather than serializing the touched view contexts over and over.
+
+/// The minimum time between autosaves.  Edits made within this long after a save are saved
+/// together by the next one.
+pub const MIN_AUTOSAVE_INTERVAL_MS: f64 = 5000.;
+
+#[derive(Debug, Default)]
+pub struct Autosaver {
+    last_save_time_ms: Option<f64>,
+}
+
+impl Autosaver {
+    /// Returns `true` if touched view contexts should be saved at `now_ms`.  `has_touched` is
+    /// whether any view contexts have been touched since they were last saved.
+    pub fn should_save(&self, now_ms: f64, has_touched: bool) -> bool {
+        if !has_touched {
+            return false;
+        }
+
+        match self.last_save_time_ms {
+            Some(last_save_time_ms) => now_ms - last_save_time_ms >= MIN_AUTOSAVE_INTERVAL_MS,
+            None => true,
+        }
+    }
+
+    pub fn record_save(&mut self, now_ms: f64) { self.last_save_time_ms = Some(now_ms); }
+}
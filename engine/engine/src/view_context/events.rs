@@ -0,0 +1,92 @@
+//! Events emitted by view contexts for other view contexts to react to.  View contexts subscribe to
+//! the kinds of events that they're interested in through the `ViewContextManager`, which delivers
+//! each emitted event to every subscriber's `handle_event`.
+//!
+//! Events aren't delivered as soon as they're emitted.  They're queued and delivered once the input
+//! that caused them has been fully handled so that view contexts never receive events while they or
+//! the view context that emitted them are in the middle of handling something.
+
+use uuid::Uuid;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EngineEventKind {
+    TransportStarted,
+    TransportStopped,
+    TempoChanged,
+    NoteTriggered,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EngineEvent {
+    /// The MIDI editor with the ID `vc_id` started playing
+    TransportStarted { vc_id: String },
+    TransportStopped { vc_id: String },
+    /// The base tempo of the MIDI editor with the ID `vc_id` was changed
+    TempoChanged { vc_id: String, bpm: f64 },
+    /// A note was played live through the MIDI editor with the ID `vc_id`
+    NoteTriggered {
+        vc_id: String,
+        note_id: usize,
+        velocity: u8,
+    },
+}
+
+impl EngineEvent {
+    pub fn kind(&self) -> EngineEventKind {
+        match self {
+            EngineEvent::TransportStarted { .. } => EngineEventKind::TransportStarted,
+            EngineEvent::TransportStopped { .. } => EngineEventKind::TransportStopped,
+            EngineEvent::TempoChanged { .. } => EngineEventKind::TempoChanged,
+            EngineEvent::NoteTriggered { .. } => EngineEventKind::NoteTriggered,
+        }
+    }
+
+    /// Returns the ID of the view context that emitted the event
+    pub fn vc_id(&self) -> &str {
+        match self {
+            EngineEvent::TransportStarted { vc_id }
+            | EngineEvent::TransportStopped { vc_id }
+            | EngineEvent::TempoChanged { vc_id, .. }
+            | EngineEvent::NoteTriggered { vc_id, .. } => vc_id,
+        }
+    }
+}
+
+/// The view contexts subscribed to each kind of event
+#[derive(Debug, Default)]
+pub struct EventSubscriptions {
+    subscriptions: Vec<(EngineEventKind, Uuid)>,
+}
+
+impl EventSubscriptions {
+    /// Subscribes the view context with the ID `vc_id` to events of `kind`.  Subscribing more than
+    /// once has no effect.
+    pub fn subscribe(&mut self, kind: EngineEventKind, vc_id: Uuid) {
+        if !self.subscriptions.contains(&(kind, vc_id)) {
+            self.subscriptions.push((kind, vc_id));
+        }
+    }
+
+    pub fn unsubscribe(&mut self, kind: EngineEventKind, vc_id: Uuid) {
+        self.subscriptions
+            .retain(|subscription| *subscription != (kind, vc_id));
+    }
+
+    /// Removes every subscription of the view context with the ID `vc_id`
+    pub fn unsubscribe_all(&mut self, vc_id: Uuid) {
+        self.subscriptions
+            .retain(|(_, subscriber_id)| *subscriber_id != vc_id);
+    }
+
+    pub fn clear(&mut self) { self.subscriptions.clear(); }
+
+    /// Returns the IDs of the view contexts subscribed to events of `kind` in the order that they
+    /// subscribed
+    pub fn get_subscribers(&self, kind: EngineEventKind) -> Vec<Uuid> {
+        self.subscriptions
+            .iter()
+            .filter(|(subscribed_kind, _)| *subscribed_kind == kind)
+            .map(|(_, vc_id)| *vc_id)
+            .collect()
+    }
+}
@@ -1,17 +1,32 @@
+use std::collections::BTreeMap;
+
 use serde_json;
 use uuid::Uuid;
 
 use crate::{
+    helpers::keymap::{KeymapOverrides, KeymapSection},
     prelude::*,
+    view_context::{
+        autosave::Autosaver,
+        events::{EngineEvent, EngineEventKind, EventSubscriptions},
+        migrations::{migrate_conf, SaveMigration},
+        project::{Project, ProjectFileError},
+    },
     views::{
         clip_compositor::mk_clip_compositor,
+        clip_launcher::mk_clip_launcher,
         composition_sharing::mk_composition_sharing,
+        drum_sequencer::mk_drum_sequencer,
         faust_editor::{mk_faust_editor, FaustEditor},
         graph_editor::mk_graph_editor,
-        midi_editor::mk_midi_editor,
+        midi_editor::{mk_midi_editor, MIDI_EDITOR_SAVE_MIGRATIONS},
         midi_keyboard::mk_midi_keyboard,
+        mixer::mk_mixer,
+        oscilloscope::mk_oscilloscope,
+        sample_editor::mk_sample_editor,
         sample_library::mk_sample_library,
         sequencer::mk_sequencer,
+        spectrum_analyzer::mk_spectrum_analyzer,
         synth_designer::mk_synth_designer,
     },
     ViewContext,
@@ -40,6 +55,20 @@ pub struct ViewContextEntry {
     pub touched: bool,
 }
 
+impl ViewContextEntry {
+    /// Serializes the VC and saves it to its `localStorage` key, clearing its `touched` flag
+    fn save(&mut self) -> ViewContextDefinition {
+        let view_context_definition: ViewContextDefinition = (&mut *self).into();
+        get_storage().set(
+            &get_vc_key(view_context_definition.minimal_def.uuid),
+            &serde_json::to_string(&view_context_definition)
+                .expect("Error while serializing `ViewContextDefinition`"),
+        );
+        self.touched = false;
+        view_context_definition
+    }
+}
+
 impl ::std::fmt::Debug for ViewContextEntry {
     fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
         f.debug_struct("ViewContextEntry")
@@ -64,6 +93,15 @@ pub struct ViewContextManager {
     pub contexts: Vec<ViewContextEntry>,
     pub connections: Vec<(ConnectionDescriptor, ConnectionDescriptor)>,
     pub foreign_connectables: Vec<ForeignConnectable>,
+    /// Keys that the user has bound to the actions of VCs in place of the default ones
+    pub keymap_overrides: KeymapOverrides,
+    pub subscriptions: EventSubscriptions,
+    /// Events that have been emitted but not yet delivered to their subscribers
+    pending_events: Vec<EngineEvent>,
+    /// The last size of the area that VCs are rendered into reported by `handle_resize`
+    pub viewport_size: Option<(usize, usize)>,
+    pub page_visible: bool,
+    autosaver: Autosaver,
 }
 
 impl Default for ViewContextManager {
@@ -73,6 +111,12 @@ impl Default for ViewContextManager {
             contexts: Vec::new(),
             connections: Vec::new(),
             foreign_connectables: Vec::new(),
+            keymap_overrides: KeymapOverrides::new(),
+            subscriptions: EventSubscriptions::default(),
+            pending_events: Vec::new(),
+            viewport_size: None,
+            page_visible: true,
+            autosaver: Autosaver::default(),
         }
     }
 }
@@ -83,18 +127,18 @@ pub struct ViewContextDefinition {
     pub conf: String,
 }
 
-impl<'a> Into<ViewContextDefinition> for &'a mut ViewContextEntry {
-    fn into(self) -> ViewContextDefinition {
+impl From<&mut ViewContextEntry> for ViewContextDefinition {
+    fn from(entry: &mut ViewContextEntry) -> Self {
         ViewContextDefinition {
-            minimal_def: self.definition.clone(),
-            conf: self.context.save(),
+            minimal_def: entry.definition.clone(),
+            conf: entry.context.save(),
         }
     }
 }
 
 /// Represents a connection between two `ViewContext`s.  It holds the ID of the src and dst VC along
 /// with the name of the input and output that are connected.
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ConnectionDescriptor {
     #[serde(rename = "vcId")]
     pub vc_id: String,
@@ -111,6 +155,21 @@ struct ViewContextManagerState {
     pub active_view_ix: usize,
     pub patch_network_connections: Vec<(ConnectionDescriptor, ConnectionDescriptor)>,
     pub foreign_connectables: Vec<ForeignConnectable>,
+    #[serde(default)]
+    pub keymap_overrides: KeymapOverrides,
+}
+
+/// A snapshot of the entire workspace in a single document, as produced by
+/// `ViewContextManager::serialize_all`.  Unlike `ViewContextManagerState`, it contains the full
+/// definitions of all VCs in order rather than only their IDs.
+#[derive(Serialize, Deserialize)]
+pub struct SerializedWorkspace {
+    pub view_contexts: Vec<ViewContextDefinition>,
+    pub active_view_ix: usize,
+    pub patch_network_connections: Vec<(ConnectionDescriptor, ConnectionDescriptor)>,
+    pub foreign_connectables: Vec<ForeignConnectable>,
+    #[serde(default)]
+    pub keymap_overrides: KeymapOverrides,
 }
 
 fn get_vc_key(uuid: Uuid) -> String { format!("vc_{}", uuid) }
@@ -120,8 +179,11 @@ impl ViewContextManager {
     fn add_view_context_inner(
         &mut self,
         definition: MinimalViewContextDefinition,
-        view_context: Box<dyn ViewContext>,
+        mut view_context: Box<dyn ViewContext>,
     ) -> usize {
+        if let Some((width, height)) = self.viewport_size {
+            view_context.handle_resize(width, height);
+        }
         self.contexts.push(ViewContextEntry {
             definition,
             context: view_context,
@@ -180,7 +242,7 @@ impl ViewContextManager {
 
     fn init_from_state_snapshot(&mut self, vcm_state: ViewContextManagerState) {
         for vc_id in vcm_state.view_context_ids {
-            let definition_str = match js::get_localstorage_key(&format!("vc_{}", vc_id)) {
+            let definition_str = match get_storage().get(&get_vc_key(vc_id)) {
                 Some(definition) => definition,
                 None => {
                     error!(
@@ -199,21 +261,32 @@ impl ViewContextManager {
                 },
             };
 
-            let mut view_context = build_view(
-                &definition.minimal_def.name,
-                Some(&definition.conf),
-                definition.minimal_def.uuid,
-            );
-
-            view_context.init();
-            view_context.hide();
-
-            self.add_view_context_inner(definition.minimal_def, view_context);
+            self.add_view_context_from_definition(definition);
         }
 
         self.active_context_ix = vcm_state.active_view_ix;
         self.connections = vcm_state.patch_network_connections;
         self.foreign_connectables = vcm_state.foreign_connectables;
+        self.keymap_overrides = vcm_state.keymap_overrides;
+    }
+
+    /// Builds, initializes, and hides the VC described by the provided definition and adds it to
+    /// the managed contexts.  Its conf is upgraded to the current version of its save format first.
+    fn add_view_context_from_definition(&mut self, definition: ViewContextDefinition) {
+        let name = &definition.minimal_def.name;
+        let conf = match migrate_conf(&definition.conf, get_save_migrations(name)) {
+            Ok(conf) => conf,
+            Err(err) => {
+                error!("Error migrating saved conf of {} VC: {:?}", name, err);
+                definition.conf.as_str().into()
+            },
+        };
+        let mut view_context = build_view(name, Some(&conf), definition.minimal_def.uuid);
+
+        view_context.init();
+        view_context.hide();
+
+        self.add_view_context_inner(definition.minimal_def, view_context);
     }
 
     /// Initializes the VCM with the default view context and state from scratch
@@ -253,13 +326,14 @@ impl ViewContextManager {
         );
 
         let faust_editor_content = include_str!("../../static/rain.dsp");
-        js::set_localstorage_key(&state_key, faust_editor_content);
+        // The Faust editor's UI reads its state from `localStorage` itself
+        LocalStorage.set(&state_key, faust_editor_content);
 
         self.active_context_ix = 1;
     }
 
     fn load_vcm_state() -> Option<ViewContextManagerState> {
-        let vcm_state_str_opt = js::get_localstorage_key(VCM_STATE_KEY);
+        let vcm_state_str_opt = get_storage().get(VCM_STATE_KEY);
         vcm_state_str_opt.and_then(|vcm_state_str| match serde_json::from_str(&vcm_state_str) {
             Ok(vcm_state) => Some(vcm_state),
             Err(err) => {
@@ -300,6 +374,15 @@ impl ViewContextManager {
         &mut *self.contexts[self.active_context_ix].context
     }
 
+    /// Retrieves the active VC in order to pass it input that may change its state, marking it as
+    /// touched so that it's saved by the next autosave
+    pub fn get_active_view_for_input(&mut self) -> &mut dyn ViewContext {
+        if let Some(vc_entry) = self.contexts.get_mut(self.active_context_ix) {
+            vc_entry.touched = true;
+        }
+        self.get_active_view_mut()
+    }
+
     /// Updates the UI with an up-to-date listing of active view contexts and persist the current
     /// VCM state to `localStorage`.
     pub fn commit(&mut self) {
@@ -347,8 +430,9 @@ impl ViewContextManager {
         vc_entry.context.cleanup();
         // And clean up any of its attached resources of storage assets
         vc_entry.context.dispose();
+        self.subscriptions.unsubscribe_all(id);
         // Finally delete the VC entry for the VC itself
-        js::delete_localstorage_key(&get_vc_key(id));
+        get_storage().delete(&get_vc_key(id));
 
         let old_active_vc_ix = self.active_context_ix;
         if self.active_context_ix == ix {
@@ -356,7 +440,7 @@ impl ViewContextManager {
             self.active_context_ix = ix.saturating_sub(1);
         } else if self.active_context_ix > ix {
             // If the active view context is above the one that was removed, shift it one down
-            self.active_context_ix = self.active_context_ix - 1;
+            self.active_context_ix -= 1;
         }
 
         if let Some(vc_entry) = self.contexts.get_mut(self.active_context_ix) {
@@ -374,21 +458,12 @@ impl ViewContextManager {
 
     /// Serializes all managed view contexts and saves them to persistent storage.
     pub fn save_all(&mut self) {
-        // TODO: Periodically call this, probably from inside of the VCMs themselves, in order
-        // to keep the state up to date.
-        // TODO: Actually make use of the `touched` flag optimization here.
         let mut view_context_definitions = Vec::new();
         let mut view_context_ids = Vec::new();
 
         for entry in &mut self.contexts {
             view_context_ids.push(entry.definition.uuid);
-            let view_context_definition: ViewContextDefinition = entry.into();
-            js::set_localstorage_key(
-                &get_vc_key(view_context_definition.minimal_def.uuid),
-                &serde_json::to_string(&view_context_definition)
-                    .expect("Error while serializing `ViewContextDefinition`"),
-            );
-            view_context_definitions.push(view_context_definition);
+            view_context_definitions.push(entry.save());
         }
 
         let state = ViewContextManagerState {
@@ -396,12 +471,179 @@ impl ViewContextManager {
             active_view_ix: self.active_context_ix,
             patch_network_connections: self.connections.clone(),
             foreign_connectables: self.foreign_connectables.clone(),
+            keymap_overrides: self.keymap_overrides.clone(),
         };
 
         let serialized_state: String = serde_json::to_string(&state)
             .expect("Error while serializing `ViewContextManagerState` to string");
 
-        js::set_localstorage_key(VCM_STATE_KEY, &serialized_state);
+        get_storage().set(VCM_STATE_KEY, &serialized_state);
+    }
+
+    /// Saves the VCs that have been touched since they were last saved, unless the last autosave
+    /// was too recent.  `now_ms` is the current time in milliseconds.
+    pub fn autosave(&mut self, now_ms: f64) {
+        let has_touched = self.contexts.iter().any(|vc_entry| vc_entry.touched);
+        if !self.autosaver.should_save(now_ms, has_touched) {
+            return;
+        }
+
+        for vc_entry in self.contexts.iter_mut().filter(|vc_entry| vc_entry.touched) {
+            vc_entry.save();
+        }
+        self.autosaver.record_save(now_ms);
+    }
+
+    /// Serializes the full set of managed VCs, their ordering, the active VC, and the patch
+    /// network into a single JSON document which can be restored with `deserialize_all`.
+    pub fn serialize_all(&mut self) -> String {
+        serde_json::to_string(&self.get_workspace())
+            .expect("Error while serializing `SerializedWorkspace`")
+    }
+
+    fn get_workspace(&mut self) -> SerializedWorkspace {
+        let view_contexts: Vec<ViewContextDefinition> =
+            self.contexts.iter_mut().map(Into::into).collect();
+        SerializedWorkspace {
+            view_contexts,
+            active_view_ix: self.active_context_ix,
+            patch_network_connections: self.connections.clone(),
+            foreign_connectables: self.foreign_connectables.clone(),
+            keymap_overrides: self.keymap_overrides.clone(),
+        }
+    }
+
+    /// Replaces all managed VCs with the ones from a document created by `serialize_all`.  If the
+    /// document can't be parsed, the current state is left untouched and the error is returned.
+    pub fn deserialize_all(&mut self, serialized: &str) -> Result<(), serde_json::Error> {
+        let workspace: SerializedWorkspace = serde_json::from_str(serialized)?;
+        self.load_workspace(workspace, BTreeMap::new());
+        Ok(())
+    }
+
+    /// Bundles the full workspace along with all of the data that VCs keep outside of their
+    /// definitions into a project file which can be loaded with `import_project`.
+    pub fn export_project(&mut self) -> Vec<u8> {
+        let mut external_data = BTreeMap::new();
+        for vc_entry in &mut self.contexts {
+            for key in vc_entry.context.save_external_data() {
+                if let Some(val) = get_storage().get(&key) {
+                    external_data.insert(key, val);
+                }
+            }
+        }
+
+        let project = Project {
+            workspace: self.get_workspace(),
+            external_data,
+        };
+        project.encode()
+    }
+
+    /// Replaces the current workspace with the one from a project file created by
+    /// `export_project`.  If the file is invalid, the current state is left untouched and the
+    /// error is returned.
+    pub fn import_project(&mut self, bytes: &[u8]) -> Result<(), ProjectFileError> {
+        let project = Project::decode(bytes)?;
+        self.load_workspace(project.workspace, project.external_data);
+        Ok(())
+    }
+
+    /// Replaces all managed VCs with the ones in `workspace`.  `external_data` is written to
+    /// storage after the existing VCs have been disposed and before the new ones are created so
+    /// that they can load it.
+    fn load_workspace(
+        &mut self,
+        workspace: SerializedWorkspace,
+        external_data: BTreeMap<String, String>,
+    ) {
+        // Tear down all existing VCs
+        for mut vc_entry in self.contexts.drain(..) {
+            vc_entry.context.cleanup();
+            vc_entry.context.dispose();
+            get_storage().delete(&get_vc_key(vc_entry.definition.uuid));
+            js::delete_view_context(&vc_entry.definition.uuid.to_string());
+        }
+        self.subscriptions.clear();
+        self.pending_events.clear();
+
+        // Some of this data is read directly from `localStorage` by JS.  Engine-owned storage
+        // backends also read from `localStorage` first, so it's written there for all of it.
+        for (key, val) in &external_data {
+            LocalStorage.set(key, val);
+        }
+
+        for definition in workspace.view_contexts {
+            self.add_view_context_from_definition(definition);
+        }
+        if self.contexts.is_empty() {
+            self.init_default_state();
+        } else {
+            self.active_context_ix = workspace.active_view_ix.min(self.contexts.len() - 1);
+        }
+        self.connections = workspace.patch_network_connections;
+        self.foreign_connectables = workspace.foreign_connectables;
+        self.keymap_overrides = workspace.keymap_overrides;
+
+        self.contexts[self.active_context_ix].context.unhide();
+        self.commit();
+    }
+
+    /// Returns the name of the action in `section` that `key` is bound to, taking the user's
+    /// bindings into account
+    pub fn get_key_action(&self, section: &KeymapSection, key: &str) -> Option<&'static str> {
+        section.get_action(key, &self.keymap_overrides)
+    }
+
+    /// Subscribes the VC with the ID `vc_id` to events of `kind`, which will be passed to its
+    /// `handle_event` whenever any VC emits one
+    pub fn subscribe(&mut self, kind: EngineEventKind, vc_id: Uuid) {
+        self.subscriptions.subscribe(kind, vc_id);
+    }
+
+    pub fn unsubscribe(&mut self, kind: EngineEventKind, vc_id: Uuid) {
+        self.subscriptions.unsubscribe(kind, vc_id);
+    }
+
+    /// Queues `event` to be delivered to its subscribers the next time that `dispatch_events` is
+    /// called, which happens after every input that the engine handles.  Delivering them later
+    /// means that VCs can emit events while borrowed mutably themselves.
+    pub fn emit(&mut self, event: EngineEvent) { self.pending_events.push(event); }
+
+    /// Delivers all queued events to the VCs subscribed to them, including any events that are
+    /// emitted by subscribers while handling them.
+    pub fn dispatch_events(&mut self) {
+        while !self.pending_events.is_empty() {
+            let events = std::mem::take(&mut self.pending_events);
+            for event in events {
+                for vc_id in self.subscriptions.get_subscribers(event.kind()) {
+                    match self.get_vc_by_id_mut(vc_id) {
+                        Some(vc_entry) => vc_entry.context.handle_event(&event),
+                        None => warn!("Subscriber {} of event {:?} wasn't found", vc_id, event),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Lets all VCs know that the area that they're rendered into changed size
+    pub fn handle_resize(&mut self, width: usize, height: usize) {
+        self.viewport_size = Some((width, height));
+        for vc_entry in &mut self.contexts {
+            vc_entry.context.handle_resize(width, height);
+        }
+    }
+
+    /// Lets the active VC know that the page was hidden or shown
+    pub fn handle_visibility_change(&mut self, visible: bool) {
+        if visible == self.page_visible {
+            return;
+        }
+
+        self.page_visible = visible;
+        if let Some(vc_entry) = self.contexts.get_mut(self.active_context_ix) {
+            vc_entry.context.handle_visibility_change(visible);
+        }
     }
 
     pub fn set_active_view(&mut self, view_ix: usize) {
@@ -412,6 +654,29 @@ impl ViewContextManager {
         js::set_active_vc_ix(view_ix);
     }
 
+    /// Moves the VC at `from_ix` so that it is at `to_ix`, shifting the VCs in between.  The same
+    /// VC stays active.
+    pub fn move_view_context(&mut self, from_ix: usize, to_ix: usize) {
+        if from_ix >= self.contexts.len() || to_ix >= self.contexts.len() {
+            error!(
+                "Tried to move VC from index {} to {} but only {} VCs are managed.",
+                from_ix,
+                to_ix,
+                self.contexts.len()
+            );
+            return;
+        }
+
+        let active_vc_id = self.contexts[self.active_context_ix].definition.uuid;
+        let vc_entry = self.contexts.remove(from_ix);
+        self.contexts.insert(to_ix, vc_entry);
+        self.active_context_ix = self
+            .get_vc_position(active_vc_id)
+            .expect("Active VC went missing while reordering");
+
+        self.commit();
+    }
+
     pub fn set_connections(
         &mut self,
         new_connections: Vec<(ConnectionDescriptor, ConnectionDescriptor)>,
@@ -445,12 +710,15 @@ impl ViewContextManager {
         }
 
         // Delete the VCM root state key itself
-        js::delete_localstorage_key(VCM_STATE_KEY);
+        get_storage().delete(VCM_STATE_KEY);
 
         // Re-initialize from scratch
         self.contexts.clear();
         self.connections.clear();
         self.foreign_connectables.clear();
+        self.keymap_overrides.clear();
+        self.subscriptions.clear();
+        self.pending_events.clear();
         self.init();
     }
 }
@@ -466,6 +734,21 @@ pub fn build_view(name: &str, conf: Option<&str>, uuid: Uuid) -> Box<dyn ViewCon
         "midi_keyboard" => mk_midi_keyboard(conf, uuid),
         "sequencer" => mk_sequencer(conf, uuid),
         "sample_library" => mk_sample_library(conf, uuid),
+        "mixer" => mk_mixer(conf, uuid),
+        "clip_launcher" => mk_clip_launcher(conf, uuid),
+        "drum_sequencer" => mk_drum_sequencer(conf, uuid),
+        "spectrum_analyzer" => mk_spectrum_analyzer(conf, uuid),
+        "oscilloscope" => mk_oscilloscope(conf, uuid),
+        "sample_editor" => mk_sample_editor(conf, uuid),
         _ => panic!("No handler for view context with name {}", name),
     }
 }
+
+/// Returns the migrations between the versions of the save format of the VC with the provided
+/// name.  See the `migrations` module.
+pub fn get_save_migrations(name: &str) -> &'static [SaveMigration] {
+    match name {
+        "midi_editor" => MIDI_EDITOR_SAVE_MIGRATIONS,
+        _ => &[],
+    }
+}
@@ -0,0 +1,81 @@
+//! Typed messages for view contexts, which are sent from JS in a compact binary format rather than
+//! being identified by a string key with a payload that each view context decodes by hand.  They're
+//! used for messages sent many times per second, such as parameter changes from dragging a slider.
+//!
+//! Messages are encoded with `bincode`: a little-endian `u32` index of the variant followed by its
+//! fields in order.  Integers are encoded as little-endian values of their full width and `bool`s
+//! as a single byte.  New variants must only ever be added to the end so that existing indices
+//! stay the same.
+
+/// A message that can be sent to a view context in binary form
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Message {
+    SetTrackGain { track_id: u32, gain: f32 },
+    SetTrackPan { track_id: u32, pan: f32 },
+    SetTrackMuted { track_id: u32, muted: bool },
+    SetTrackSoloed { track_id: u32, soloed: bool },
+    SetMasterGain { gain: f32 },
+    SetModRouteDepth { route_id: u32, depth: f32 },
+}
+
+/// Identifies the kind of a `Message` without its contents
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MessageKind {
+    SetTrackGain,
+    SetTrackPan,
+    SetTrackMuted,
+    SetTrackSoloed,
+    SetMasterGain,
+    SetModRouteDepth,
+}
+
+impl Message {
+    pub fn kind(&self) -> MessageKind {
+        match self {
+            Message::SetTrackGain { .. } => MessageKind::SetTrackGain,
+            Message::SetTrackPan { .. } => MessageKind::SetTrackPan,
+            Message::SetTrackMuted { .. } => MessageKind::SetTrackMuted,
+            Message::SetTrackSoloed { .. } => MessageKind::SetTrackSoloed,
+            Message::SetMasterGain { .. } => MessageKind::SetMasterGain,
+            Message::SetModRouteDepth { .. } => MessageKind::SetModRouteDepth,
+        }
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, bincode::Error> { bincode::deserialize(bytes) }
+
+    pub fn encode(&self) -> Vec<u8> {
+        bincode::serialize(self).expect("Failed to serialize view context message")
+    }
+}
+
+pub type MessageHandler<T> = fn(&mut T, Message) -> Option<Vec<u8>>;
+
+/// Maps the kinds of messages that a view context accepts to the functions that handle them
+pub struct MessageRegistry<T: 'static> {
+    pub handlers: &'static [(MessageKind, MessageHandler<T>)],
+}
+
+impl<T> MessageRegistry<T> {
+    pub fn handles(&self, kind: MessageKind) -> bool {
+        self.handlers
+            .iter()
+            .any(|(handled_kind, _)| *handled_kind == kind)
+    }
+
+    /// Calls the handler registered for the kind of `message`, returning its response.  Messages
+    /// of kinds that aren't registered are ignored.
+    pub fn dispatch(&self, target: &mut T, message: Message) -> Option<Vec<u8>> {
+        let kind = message.kind();
+        match self
+            .handlers
+            .iter()
+            .find(|(handled_kind, _)| *handled_kind == kind)
+        {
+            Some((_, handler)) => handler(target, message),
+            None => {
+                warn!("Ignoring unhandled message of kind {:?}", kind);
+                None
+            },
+        }
+    }
+}
@@ -0,0 +1,57 @@
+//! Upgrades the output of `ViewContext::save` from older versions of a VC's save format so that
+//! existing saves keep loading when it changes.
+//!
+//! Saves embed the version of their format in a `version` field, with saves that don't have one
+//! being version 0.  Each VC provides a list of migrations in which the migration at index `i`
+//! upgrades a save from version `i` to version `i + 1`, making the number of migrations the
+//! current version.  Saves are upgraded step by step through all of the migrations after their
+//! version when they're loaded.
+
+use std::borrow::Cow;
+
+use serde_json::{self, Value};
+
+/// Upgrades a save by one version in place
+pub type SaveMigration = fn(&mut serde_json::Map<String, Value>);
+
+#[derive(Debug)]
+pub enum SaveMigrationError {
+    /// The save was created by a newer version of the application
+    UnsupportedVersion { version: u64, current_version: u64 },
+    InvalidConf(serde_json::Error),
+}
+
+/// Applies the migrations after the version embedded in `conf` to it, returning the upgraded save.
+/// Saves that are already up to date, empty, or not JSON objects are returned unchanged.
+pub fn migrate_conf<'a>(
+    conf: &'a str,
+    migrations: &[SaveMigration],
+) -> Result<Cow<'a, str>, SaveMigrationError> {
+    if migrations.is_empty() || conf.is_empty() {
+        return Ok(Cow::Borrowed(conf));
+    }
+
+    let mut obj = match serde_json::from_str(conf).map_err(SaveMigrationError::InvalidConf)? {
+        Value::Object(obj) => obj,
+        _ => return Ok(Cow::Borrowed(conf)),
+    };
+    let version = obj.get("version").and_then(Value::as_u64).unwrap_or(0);
+    let current_version = migrations.len() as u64;
+    if version > current_version {
+        return Err(SaveMigrationError::UnsupportedVersion {
+            version,
+            current_version,
+        });
+    } else if version == current_version {
+        return Ok(Cow::Borrowed(conf));
+    }
+
+    for migrate in &migrations[version as usize..] {
+        migrate(&mut obj);
+    }
+    obj.insert("version".into(), current_version.into());
+
+    serde_json::to_string(&obj)
+        .map(Cow::Owned)
+        .map_err(SaveMigrationError::InvalidConf)
+}
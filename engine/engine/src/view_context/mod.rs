@@ -1,13 +1,31 @@
 use wasm_bindgen::prelude::*;
 
+use crate::helpers::keymap::KeymapSection;
+
+pub mod autosave;
+pub mod events;
 pub mod manager;
-pub use self::manager::ViewContextManager;
+pub mod message;
+pub mod migrations;
+pub mod project;
+pub use self::{events::EngineEvent, manager::ViewContextManager, message::Message};
 
 #[wasm_bindgen(raw_module = "./patchNetwork")]
 extern "C" {
     pub fn create_empty_audio_connectables(vc_id: &str) -> JsValue;
 }
 
+/// The state of a pen that's generating mouse events
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PenState {
+    /// How hard the pen is being pressed, from 0 to 1
+    pub pressure: f32,
+    /// The angle in degrees between the pen and the screen along the x axis, from -90 to 90
+    pub tilt_x: f32,
+    /// The angle in degrees between the pen and the screen along the y axis, from -90 to 90
+    pub tilt_y: f32,
+}
+
 pub trait ViewContext {
     /// Set up the view context to be the primary/active view of the application.  This may involve
     /// things like subscribing to/loading external data sources, creating DOM nodes, etc.
@@ -40,21 +58,57 @@ pub trait ViewContext {
     /// and referenced by a `localStorage` key or something similar.  The reason for this is that
     /// these definitions are created, read, and transferred between WebAssembly and JavaScript
     /// regularly, and storing large data in them will cause that to become slow.
+    ///
+    /// When the format changes, a migration from the previous one should be registered in
+    /// `manager::get_save_migrations` so that existing saves can still be loaded.
     fn save(&mut self) -> String { "".into() }
 
+    /// Saves the data that the view context keeps in storage outside of its definition, as
+    /// described for `save`, and returns the storage keys that it's kept under.  This is used to
+    /// include that data when exporting the project.
+    fn save_external_data(&mut self) -> Vec<String> { Vec::new() }
+
     // input handlers
     fn handle_key_down(&mut self, _key: &str, _control_pressed: bool, _shift_pressed: bool) {}
     fn handle_key_up(&mut self, _key: &str, _control_pressed: bool, _shift_pressed: bool) {}
-    fn handle_mouse_down(&mut self, _x: usize, _y: usize) {}
-    fn handle_mouse_move(&mut self, _x: usize, _y: usize) {}
+    /// `pen` is set if the mouse events are being generated by a pen rather than a mouse
+    fn handle_mouse_down(&mut self, _x: usize, _y: usize, _pen: Option<PenState>) {}
+    fn handle_mouse_move(&mut self, _x: usize, _y: usize, _pen: Option<PenState>) {}
     fn handle_mouse_up(&mut self, _x: usize, _y: usize) {}
     fn handle_mouse_wheel(&mut self, _ydiff: isize) {}
+    /// Touch handlers are called for every finger in contact with the screen.  `pointer_id`
+    /// identifies each of them and stays the same from when it starts touching until it's lifted.
+    fn handle_touch_start(&mut self, _pointer_id: i32, _x: usize, _y: usize) {}
+    fn handle_touch_move(&mut self, _pointer_id: i32, _x: usize, _y: usize) {}
+    fn handle_touch_end(&mut self, _pointer_id: i32, _x: usize, _y: usize) {}
+    /// Returns the sections of the keymap holding the actions that this VC handles in
+    /// `handle_key_down`, which are listed in the keyboard shortcut help along with their keys
+    fn get_keymap_sections(&self) -> Vec<&'static KeymapSection> { Vec::new() }
 
     /// A function that will be called with arbitrary messages containing binary data to be handled
     /// in an arbitrary manner by the view context.  Each message includes a type which can be used
     /// to identify it.
     fn handle_message(&mut self, _key: &str, _val: &[u8]) -> Option<Vec<u8>> { None }
 
+    /// Handles a typed message decoded from the binary format described in `message`.  View
+    /// contexts that accept them usually dispatch them with a `MessageRegistry`.
+    fn handle_binary_message(&mut self, _message: Message) -> Option<Vec<u8>> { None }
+
+    /// Handles an event emitted by another VC.  Only events of the kinds that the VC subscribed to
+    /// through `ViewContextManager::subscribe` are passed here.
+    fn handle_event(&mut self, _event: &EngineEvent) {}
+
+    /// Called with the size in pixels of the area of the page that view contexts are rendered into
+    /// whenever it changes, as well as once after the view context is created.  This is called
+    /// for all view contexts, not just the active one, so that they're laid out correctly when
+    /// they're shown.
+    fn handle_resize(&mut self, _width: usize, _height: usize) {}
+
+    /// Called on the active view context when the page is hidden or shown again, such as when the
+    /// browser tab is switched.  Expensive rendering such as animations can be paused while hidden.
+    /// Other view contexts are already hidden, so they aren't notified.
+    fn handle_visibility_change(&mut self, _visible: bool) {}
+
     /// Returns a JavaScript object that contains WebAudio constructs that can be used to connect
     /// this `ViewContext` to other `ViewContext`s programatically.  This function should return
     /// the same object throughout the life of the view context.
@@ -0,0 +1,59 @@
+//! Project files bundle everything needed to recreate a workspace on another machine into a single
+//! document: the definitions of all VCs (including the notes of every clip and their automation),
+//! the patch network, and the data that VCs keep in storage outside of their definitions such as
+//! synth designer patches and the notes of clip compositors.  Samples themselves aren't included,
+//! only the references to them that the sample library stores.
+//!
+//! A project file starts with `PROJECT_FILE_MAGIC` followed by the little-endian `u32` version of
+//! the format and then the project as JSON.  Compression is applied on top of the whole file by JS
+//! when exporting and removed before importing.
+
+use std::collections::BTreeMap;
+
+use super::manager::SerializedWorkspace;
+
+pub const PROJECT_FILE_MAGIC: &[u8; 8] = b"WSYNPROJ";
+pub const PROJECT_FILE_VERSION: u32 = 1;
+const HEADER_LENGTH: usize = PROJECT_FILE_MAGIC.len() + 4;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Project {
+    pub workspace: SerializedWorkspace,
+    /// Data that VCs keep outside of their definitions, keyed by the storage key that it's kept
+    /// under.  See `ViewContext::save_external_data`.
+    pub external_data: BTreeMap<String, String>,
+}
+
+#[derive(Debug)]
+pub enum ProjectFileError {
+    NotAProjectFile,
+    /// The file was created by a newer version of the application
+    UnsupportedVersion(u32),
+    InvalidContents(serde_json::Error),
+}
+
+impl Project {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(PROJECT_FILE_MAGIC);
+        bytes.extend_from_slice(&PROJECT_FILE_VERSION.to_le_bytes());
+        serde_json::to_writer(&mut bytes, self).expect("Failed to serialize project");
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, ProjectFileError> {
+        if bytes.len() < HEADER_LENGTH || !bytes.starts_with(PROJECT_FILE_MAGIC) {
+            return Err(ProjectFileError::NotAProjectFile);
+        }
+
+        let mut version_bytes = [0u8; 4];
+        version_bytes.copy_from_slice(&bytes[PROJECT_FILE_MAGIC.len()..HEADER_LENGTH]);
+        let version = u32::from_le_bytes(version_bytes);
+        if version > PROJECT_FILE_VERSION {
+            return Err(ProjectFileError::UnsupportedVersion(version));
+        }
+
+        serde_json::from_slice(&bytes[HEADER_LENGTH..]).map_err(ProjectFileError::InvalidContents)
+    }
+}
@@ -1,10 +1,25 @@
+//! The clip compositor arranges the clips of a MIDI editor on a timeline.  Each row of its grid is
+//! a track, and each note on it is an instance of one of the MIDI editor's clips.  Whenever the
+//! instances are changed, they're sent to the MIDI editor as its arrangement which determines the
+//! order in which its transport plays the clips.
+//!
+//! Only the placement of the instances is saved with the clip compositor.  The notes of the clips
+//! themselves belong to the MIDI editor and are saved along with it.
+
+use std::str::FromStr;
+
 use uuid::Uuid;
 
-use crate::{helpers::grid::prelude::*, view_context::ViewContext};
+use crate::{
+    helpers::{grid::prelude::*, keymap::KeymapSection},
+    view_context::ViewContext,
+    views::midi_editor::clips::ClipInstance,
+};
 
-struct ClipCompositorNoteData {
+pub struct ClipCompositorNoteData {
     pub dom_id: DomId,
-    pub note_data_key: usize,
+    /// The ID of the MIDI editor clip that this is an instance of
+    pub clip_id: Uuid,
 }
 
 impl GridRendererUniqueIdentifier for ClipCompositorNoteData {
@@ -15,13 +30,65 @@ struct ClipCompositorRenderer;
 
 impl GridRenderer<ClipCompositorNoteData> for ClipCompositorRenderer {}
 
-pub struct ClipCompositorHandler {}
+/// An instance of a clip along with the track that it's placed on
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArrangedClip {
+    pub track_ix: usize,
+    #[serde(flatten)]
+    pub instance: ClipInstance,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ClipCompositorConf {
+    /// The MIDI editor whose clips are arranged and whose transport plays the arrangement
+    pub midi_editor_vc_id: Option<Uuid>,
+    /// The clip that newly drawn instances are created from
+    pub active_clip_id: Option<Uuid>,
+    pub instances: Vec<ArrangedClip>,
+    /// View settings of the grid.  Its notes are always empty since they're stored as `instances`.
+    pub grid: Option<SerializedGridState>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ClipCompositorInfo {
+    midi_editor_vc_id: Option<Uuid>,
+    active_clip_id: Option<Uuid>,
+}
 
-impl Default for ClipCompositorHandler {
-    fn default() -> Self { ClipCompositorHandler {} }
+#[derive(Default)]
+pub struct ClipCompositorHandler {
+    pub midi_editor_vc_id: Option<Uuid>,
+    pub active_clip_id: Option<Uuid>,
+}
+
+impl ClipCompositorHandler {
+    /// Sends a message to the MIDI editor whose clips are being arranged, if it exists
+    fn send_midi_editor_message(&self, key: &str, val: &[u8]) -> Option<Vec<u8>> {
+        let vc_id = self.midi_editor_vc_id?;
+        match get_vcm().get_vc_by_id_mut(vc_id) {
+            Some(entry) => entry.context.handle_message(key, val),
+            None => {
+                warn!("MIDI editor with ID {} for the clip compositor wasn't found", vc_id);
+                None
+            },
+        }
+    }
 }
 
 impl GridHandler<ClipCompositorNoteData, ClipCompositorRenderer> for ClipCompositorHandler {
+    fn init(&mut self, vc_id: &str, _grid_conf: &GridConf) { js::init_clip_compositor_ui(vc_id); }
+
+    fn cleanup(&mut self, _grid_state: &mut GridState<ClipCompositorNoteData>, vc_id: &str) {
+        js::cleanup_clip_compositor_ui(vc_id);
+    }
+
+    fn hide(&mut self, vc_id: &str) { js::hide_clip_compositor_ui(vc_id); }
+
+    fn unhide(&mut self, vc_id: &str) { js::unhide_clip_compositor_ui(vc_id); }
+
     fn create_note(
         &mut self,
         _grid_state: &mut GridState<ClipCompositorNoteData>,
@@ -31,7 +98,8 @@ impl GridHandler<ClipCompositorNoteData, ClipCompositorRenderer> for ClipComposi
     ) -> ClipCompositorNoteData {
         ClipCompositorNoteData {
             dom_id,
-            note_data_key: 0, // TODO
+            // Instances of clips that don't exist are ignored by the MIDI editor
+            clip_id: self.active_clip_id.unwrap_or_else(Uuid::nil),
         }
     }
 }
@@ -47,18 +115,321 @@ fn get_default_clip_compositor_grid_conf() -> GridConf {
         note_snap_beat_interval: 0.5,
         grid_width: 600,
         measure_width_px: 80,
+        zoom_x: 1.0,
+        zoom_y: 1.0,
+        time_signature_changes: Vec::new(),
     }
 }
 
 type ClipCompositorGrid =
     Grid<ClipCompositorNoteData, ClipCompositorRenderer, ClipCompositorHandler>;
 
-pub fn mk_clip_compositor(_config: Option<&str>, uuid: Uuid) -> Box<dyn ViewContext> {
-    // TODO: Parse the config and use that rather than the constants
-    let conf: GridConf = get_default_clip_compositor_grid_conf();
+/// Wraps the grid of the clip compositor so that the instances on it can be saved and sent to the
+/// MIDI editor after they're edited.
+pub struct ClipCompositor {
+    grid: ClipCompositorGrid,
+    /// Instances from the saved definition which are inserted once the grid is first initialized
+    pending_instances: Option<Vec<ArrangedClip>>,
+    /// The arrangement that was last sent to the MIDI editor
+    synced_arrangement: Vec<ClipInstance>,
+}
+
+impl ClipCompositor {
+    /// Returns all instances on the grid ordered by track and then by start beat
+    fn get_instances(&self) -> Vec<ArrangedClip> {
+        if let Some(pending_instances) = &self.pending_instances {
+            return pending_instances.clone();
+        }
+
+        self.grid
+            .state
+            .data
+            .iter_all()
+            .map(|(track_ix, note)| ArrangedClip {
+                track_ix,
+                instance: ClipInstance {
+                    clip_id: note.data.clip_id,
+                    start_beat: note.bounds.start_beat as f64,
+                    length_beats: note.bounds.width() as f64,
+                },
+            })
+            .collect()
+    }
+
+    /// Renders and inserts an instance, returning its `SelectedNoteData` if it didn't intersect
+    /// any existing instances.
+    fn insert_instance(&mut self, arranged_clip: ArrangedClip) -> Option<SelectedNoteData> {
+        let ArrangedClip { track_ix, instance } = arranged_clip;
+        if track_ix >= self.grid.state.data.lines.len() {
+            warn!("Skipping clip instance on track {} since it's outside of the grid", track_ix);
+            return None;
+        }
+
+        let (start_beat, length_beats) = (instance.start_beat as f32, instance.length_beats as f32);
+        let dom_id = self.grid.render_note(track_ix, start_beat, length_beats);
+        let note = NoteBox {
+            id: NoteId::next(),
+            bounds: NoteBoxBounds {
+                start_beat,
+                end_beat: start_beat + length_beats,
+            },
+            data: ClipCompositorNoteData {
+                dom_id,
+                clip_id: instance.clip_id,
+            },
+            velocity: DEFAULT_NOTE_VELOCITY,
+            pitch_bend: Vec::new(),
+            expression: NoteExpression::default(),
+        };
+        let note_data = SelectedNoteData::from_note_box(track_ix, &note);
+        match self.grid.state.data.insert(track_ix, note) {
+            None => Some(note_data),
+            Some(_) => {
+                warn!(
+                    "Skipping clip instance on track {} at beat {} since it intersects another",
+                    track_ix, start_beat
+                );
+                js::delete_element(dom_id);
+                None
+            },
+        }
+    }
+
+    /// Places a copy of the selected instances directly after them and selects the copies.
+    /// Copies that would intersect an existing instance are skipped.
+    pub fn duplicate_selected_instances(&mut self) {
+        let state = &mut self.grid.state;
+        let selection: Vec<SelectedNoteData> = state.selected_notes.drain().collect();
+        let span_start_beat = selection
+            .iter()
+            .map(|note| note.start_beat)
+            .fold(f32::INFINITY, f32::min);
+        let span_end_beat = selection
+            .iter()
+            .map(|note| note.start_beat + note.width)
+            .fold(0., f32::max);
+        let offset_beats = span_end_beat - span_start_beat;
+
+        let copies: Vec<ArrangedClip> = selection
+            .iter()
+            .filter_map(|note_data| {
+                ClipCompositorRenderer::deselect_note(note_data.dom_id);
+                let (_, note) = state.data.get_by_id(note_data.note_id)?;
+                Some(ArrangedClip {
+                    track_ix: note_data.line_ix,
+                    instance: ClipInstance {
+                        clip_id: note.data.clip_id,
+                        start_beat: (note_data.start_beat + offset_beats) as f64,
+                        length_beats: note_data.width as f64,
+                    },
+                })
+            })
+            .collect();
+
+        for copy in copies {
+            if let Some(note_data) = self.insert_instance(copy) {
+                ClipCompositorRenderer::select_note(note_data.dom_id);
+                self.grid.state.selected_notes.insert(note_data);
+            }
+        }
+    }
+
+    /// Sends the instances on the grid to the MIDI editor as its arrangement if they've changed
+    /// since they were last sent.
+    fn sync_arrangement(&mut self) {
+        let arrangement: Vec<ClipInstance> = self
+            .get_instances()
+            .into_iter()
+            .map(|arranged_clip| arranged_clip.instance)
+            .collect();
+        if arrangement == self.synced_arrangement {
+            return;
+        }
+
+        let arrangement_json =
+            serde_json::to_vec(&arrangement).expect("Failed to serialize arrangement");
+        self.grid
+            .handler
+            .send_midi_editor_message("set_arrangement", &arrangement_json);
+        self.synced_arrangement = arrangement;
+    }
+}
+
+impl ViewContext for ClipCompositor {
+    fn init(&mut self) {
+        self.grid.init();
+        if let Some(instances) = self.pending_instances.take() {
+            for instance in instances {
+                self.insert_instance(instance);
+            }
+        }
+    }
+
+    fn get_id(&self) -> String { self.grid.get_id() }
+
+    fn cleanup(&mut self) { self.grid.cleanup(); }
+
+    fn dispose(&mut self) { self.grid.dispose(); }
+
+    fn save_external_data(&mut self) -> Vec<String> { self.grid.save_external_data() }
+
+    fn hide(&mut self) { self.grid.hide(); }
+
+    fn unhide(&mut self) { self.grid.unhide(); }
+
+    fn save(&mut self) -> String {
+        let mut grid = self.grid.state.serialize();
+        grid.notes.clear();
+        grid.selected_note_ixs.clear();
+        let conf = ClipCompositorConf {
+            midi_editor_vc_id: self.grid.handler.midi_editor_vc_id,
+            active_clip_id: self.grid.handler.active_clip_id,
+            instances: self.get_instances(),
+            grid: Some(grid),
+        };
+        serde_json::to_string(&conf).expect("Failed to serialize `ClipCompositorConf`")
+    }
+
+    fn handle_key_down(&mut self, key: &str, control_pressed: bool, shift_pressed: bool) {
+        match get_vcm().get_key_action(&GRID_KEYMAP, key) {
+            // The grid's own copying would create the copies from the active clip
+            Some("copy_selected_notes") => self.duplicate_selected_instances(),
+            _ => self
+                .grid
+                .handle_key_down(key, control_pressed, shift_pressed),
+        }
+        self.sync_arrangement();
+    }
+
+    fn get_keymap_sections(&self) -> Vec<&'static KeymapSection> {
+        self.grid.get_keymap_sections()
+    }
+
+    fn handle_key_up(&mut self, key: &str, control_pressed: bool, shift_pressed: bool) {
+        self.grid.handle_key_up(key, control_pressed, shift_pressed);
+    }
+
+    fn handle_mouse_down(&mut self, x: usize, y: usize, pen: Option<PenState>) {
+        self.grid.handle_mouse_down(x, y, pen);
+        self.sync_arrangement();
+    }
+
+    fn handle_mouse_move(&mut self, x: usize, y: usize, pen: Option<PenState>) {
+        self.grid.handle_mouse_move(x, y, pen);
+    }
+
+    fn handle_mouse_up(&mut self, x: usize, y: usize) {
+        self.grid.handle_mouse_up(x, y);
+        self.sync_arrangement();
+    }
+
+    fn handle_mouse_wheel(&mut self, ydiff: isize) { self.grid.handle_mouse_wheel(ydiff); }
+
+    fn handle_touch_start(&mut self, pointer_id: i32, x: usize, y: usize) {
+        self.grid.handle_touch_start(pointer_id, x, y);
+        self.sync_arrangement();
+    }
+
+    fn handle_touch_move(&mut self, pointer_id: i32, x: usize, y: usize) {
+        self.grid.handle_touch_move(pointer_id, x, y);
+    }
+
+    fn handle_touch_end(&mut self, pointer_id: i32, x: usize, y: usize) {
+        self.grid.handle_touch_end(pointer_id, x, y);
+        self.sync_arrangement();
+    }
+
+    fn handle_resize(&mut self, width: usize, height: usize) {
+        self.grid.handle_resize(width, height);
+    }
+
+    fn handle_visibility_change(&mut self, visible: bool) {
+        self.grid.handle_visibility_change(visible);
+    }
+
+    fn handle_message(&mut self, key: &str, val: &[u8]) -> Option<Vec<u8>> {
+        match key {
+            "get_clip_compositor_info" => {
+                let info = ClipCompositorInfo {
+                    midi_editor_vc_id: self.grid.handler.midi_editor_vc_id,
+                    active_clip_id: self.grid.handler.active_clip_id,
+                };
+                Some(serde_json::to_vec(&info).expect("Failed to serialize clip compositor info"))
+            },
+            "set_midi_editor" | "set_active_clip" => {
+                let id = match Uuid::from_str(&String::from_utf8_lossy(val)) {
+                    Ok(id) => id,
+                    Err(err) => {
+                        error!("Invalid UUID passed with \"{}\" message: {:?}", key, err);
+                        return None;
+                    },
+                };
+                if key == "set_midi_editor" {
+                    self.grid.handler.midi_editor_vc_id = Some(id);
+                    // Send the arrangement to the new MIDI editor even if it hasn't changed
+                    self.synced_arrangement.clear();
+                    self.sync_arrangement();
+                } else {
+                    self.grid.handler.active_clip_id = Some(id);
+                }
+                None
+            },
+            "duplicate_selection" => {
+                self.duplicate_selected_instances();
+                self.sync_arrangement();
+                None
+            },
+            // Snapshots of the grid's notes don't include the clips that they're instances of, so
+            // restoring them would lose track of which clip each instance plays.
+            "undo_note_edit" | "redo_note_edit" => Some(vec![0]),
+            _ => {
+                let res = self.grid.handle_message(key, val);
+                self.sync_arrangement();
+                res
+            },
+        }
+    }
+
+    fn get_audio_connectables(&self) -> JsValue { self.grid.get_audio_connectables() }
+}
+
+pub fn mk_clip_compositor(config: Option<&str>, uuid: Uuid) -> Box<dyn ViewContext> {
+    let conf: ClipCompositorConf = match config {
+        Some(config) => match serde_json::from_str(config) {
+            Ok(conf) => conf,
+            Err(err) => {
+                error!("Error deserializing clip compositor conf: {:?}", err);
+                ClipCompositorConf::default()
+            },
+        },
+        None => ClipCompositorConf::default(),
+    };
 
-    let view_context = ClipCompositorHandler::default();
-    let grid: Box<ClipCompositorGrid> = box Grid::new(conf, view_context, uuid);
+    let grid_conf = get_default_clip_compositor_grid_conf();
+    let saved_grid_state = conf.grid.unwrap_or_else(|| SerializedGridState {
+        notes: Vec::new(),
+        selected_note_ixs: Vec::new(),
+        cursor_pos_beats: 0.,
+        scroll_offset_beats: 0.,
+        zoom_x: grid_conf.zoom_x,
+        zoom_y: grid_conf.zoom_y,
+        note_snap_beat_interval: grid_conf.note_snap_beat_interval,
+    });
+    let handler = ClipCompositorHandler {
+        midi_editor_vc_id: conf.midi_editor_vc_id,
+        active_clip_id: conf.active_clip_id,
+    };
+    // Loading from a saved state even when there isn't one keeps the grid from loading notes from
+    // its `localStorage` entry, since the instances are inserted separately.
+    let grid = Grid::load(grid_conf, handler, uuid, saved_grid_state);
 
-    grid
+    Box::new(ClipCompositor {
+        grid,
+        synced_arrangement: conf
+            .instances
+            .iter()
+            .map(|arranged_clip| arranged_clip.instance)
+            .collect(),
+        pending_instances: Some(conf.instances),
+    })
 }
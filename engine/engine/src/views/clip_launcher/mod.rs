@@ -0,0 +1,369 @@
+//! Defines a session view for launching the clips of MIDI editors live.  Each track is bound to a
+//! MIDI editor and holds a column of cells, each of which can hold one of its clips.  Launching a
+//! cell or stopping a track takes effect at the start of the next measure, and launching a scene
+//! launches every cell in its row at once.
+
+use serde_json;
+use uuid::Uuid;
+
+use crate::{
+    helpers::grid::prelude::*,
+    view_context::{
+        events::{EngineEvent, EngineEventKind},
+        ViewContext,
+    },
+    views::midi_editor::clips::{LaunchClip, LaunchedClips, SESSION_END_BEAT},
+};
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClipLauncherTrack {
+    /// The MIDI editor whose clips are launched by this track
+    pub midi_editor_vc_id: Option<Uuid>,
+    /// The clip held in each scene, if any
+    pub cells: Vec<Option<Uuid>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClipLauncherState {
+    pub tracks: Vec<ClipLauncherTrack>,
+    pub scene_count: usize,
+}
+
+impl Default for ClipLauncherState {
+    fn default() -> Self {
+        ClipLauncherState {
+            tracks: vec![ClipLauncherTrack {
+                midi_editor_vc_id: None,
+                cells: vec![None; 4],
+            }],
+            scene_count: 4,
+        }
+    }
+}
+
+/// What a track of the clip launcher is playing, derived from the clips launched in its MIDI editor
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackPlaybackState {
+    pub playing_clip_id: Option<Uuid>,
+    /// The clip that will start playing at the start of the next measure
+    pub queued_clip_id: Option<Uuid>,
+    /// Set if the playing clip will be stopped at the start of the next measure without another
+    /// one taking its place
+    pub stop_queued: bool,
+}
+
+impl TrackPlaybackState {
+    pub fn from_launched_clips(launched: &LaunchedClips) -> Self {
+        let cur_beat = match launched.cur_beat {
+            Some(cur_beat) => cur_beat,
+            None => return TrackPlaybackState::default(),
+        };
+        let playing = launched
+            .instances
+            .iter()
+            .find(|instance| instance.start_beat <= cur_beat && instance.end_beat() > cur_beat);
+        let queued = launched
+            .instances
+            .iter()
+            .find(|instance| instance.start_beat > cur_beat);
+
+        TrackPlaybackState {
+            playing_clip_id: playing.map(|instance| instance.clip_id),
+            queued_clip_id: queued.map(|instance| instance.clip_id),
+            stop_queued: queued.is_none()
+                && playing
+                    .map(|instance| instance.end_beat() < SESSION_END_BEAT)
+                    .unwrap_or(false),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CellMessage {
+    track_ix: usize,
+    scene_ix: usize,
+    #[serde(default)]
+    clip_id: Option<Uuid>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrackMidiEditorMessage {
+    track_ix: usize,
+    vc_id: Option<Uuid>,
+}
+
+/// Reads a `u32` index out of a message
+fn read_index(key: &str, val: &[u8]) -> usize {
+    assert_eq!(
+        val.len(),
+        4,
+        "Message for \"{}\" must be a 4-byte `u32` index",
+        key
+    );
+    u32::from_ne_bytes([val[0], val[1], val[2], val[3]]) as usize
+}
+
+/// The launcher's state lives here and the UI is implemented in JS, which is sent the full state
+/// every time that it changes.  The clips themselves and their playback live in the MIDI editors.
+#[derive(Serialize, Deserialize)]
+pub struct ClipLauncher {
+    pub uuid: Uuid,
+    #[serde(default)]
+    pub state: ClipLauncherState,
+}
+
+impl ClipLauncher {
+    pub fn new(uuid: Uuid) -> Self {
+        ClipLauncher {
+            uuid,
+            state: ClipLauncherState::default(),
+        }
+    }
+
+    pub fn get_state_key(&self) -> String { format!("clipLauncher_{}", self.uuid) }
+
+    fn serialize_state(&self) -> String {
+        serde_json::to_string(&self.state).expect("Failed to serialize clip launcher state")
+    }
+
+    fn sync_state(&self) -> String {
+        let serialized = self.serialize_state();
+        js::set_clip_launcher_state(&self.get_state_key(), &serialized);
+        serialized
+    }
+
+    fn send_midi_editor_message(vc_id: Uuid, key: &str, val: &[u8]) -> Option<Vec<u8>> {
+        match get_vcm().get_vc_by_id_mut(vc_id) {
+            Some(entry) => entry.context.handle_message(key, val),
+            None => {
+                warn!("MIDI editor with ID {} for the clip launcher wasn't found", vc_id);
+                None
+            },
+        }
+    }
+
+    fn get_launched_clips(vc_id: Uuid) -> LaunchedClips {
+        Self::send_midi_editor_message(vc_id, "get_launched_clips", &[])
+            .and_then(|res| serde_json::from_slice(&res).ok())
+            .unwrap_or_default()
+    }
+
+    /// Returns the time at which MIDI editors that aren't playing should be started so that the
+    /// clips launched in them line up with the ones that are already playing
+    fn get_sync_start_time(&self) -> Option<f64> {
+        self.state
+            .tracks
+            .iter()
+            .filter_map(|track| track.midi_editor_vc_id)
+            .map(Self::get_launched_clips)
+            .find(|launched| !launched.instances.is_empty())
+            .and_then(|launched| launched.next_measure_time)
+    }
+
+    /// Launches `clip_id` in the MIDI editor of the track at `track_ix`, or stops it if `None`
+    fn launch(&self, track_ix: usize, clip_id: Option<Uuid>, start_time: Option<f64>) {
+        let vc_id = match self.state.tracks.get(track_ix) {
+            Some(ClipLauncherTrack {
+                midi_editor_vc_id: Some(vc_id),
+                ..
+            }) => *vc_id,
+            Some(_) => return,
+            None => {
+                warn!("Tried to launch clip in nonexistent clip launcher track {}", track_ix);
+                return;
+            },
+        };
+
+        let launch = LaunchClip {
+            clip_id,
+            cur_time: js::get_cur_audio_ctx_time(),
+            start_time,
+        };
+        let serialized = serde_json::to_vec(&launch).expect("Failed to serialize clip launch");
+        Self::send_midi_editor_message(vc_id, "launch_clip", &serialized);
+    }
+
+    fn launch_cell(&self, track_ix: usize, scene_ix: usize) {
+        let clip_id = match self
+            .state
+            .tracks
+            .get(track_ix)
+            .and_then(|track| track.cells.get(scene_ix))
+        {
+            Some(Some(clip_id)) => *clip_id,
+            _ => return,
+        };
+        self.launch(track_ix, Some(clip_id), self.get_sync_start_time());
+    }
+
+    /// Launches every cell in the scene, stopping the tracks whose cell is empty.  MIDI editors
+    /// that aren't playing are all started at the same time so that they stay in sync.
+    fn launch_scene(&self, scene_ix: usize) {
+        let start_time = self
+            .get_sync_start_time()
+            .unwrap_or_else(js::get_cur_audio_ctx_time);
+        for (track_ix, track) in self.state.tracks.iter().enumerate() {
+            let clip_id = track.cells.get(scene_ix).and_then(|cell| *cell);
+            self.launch(track_ix, clip_id, Some(start_time));
+        }
+    }
+
+    fn get_playback_state(&self) -> Vec<TrackPlaybackState> {
+        self.state
+            .tracks
+            .iter()
+            .map(|track| match track.midi_editor_vc_id {
+                Some(vc_id) => {
+                    let launched = Self::get_launched_clips(vc_id);
+                    TrackPlaybackState::from_launched_clips(&launched)
+                },
+                None => TrackPlaybackState::default(),
+            })
+            .collect()
+    }
+
+    fn sync_playback_state(&self) {
+        let serialized = serde_json::to_string(&self.get_playback_state())
+            .expect("Failed to serialize clip launcher playback state");
+        js::set_clip_launcher_playback_state(&self.get_state_key(), &serialized);
+    }
+}
+
+impl ViewContext for ClipLauncher {
+    fn init(&mut self) {
+        js::init_clip_launcher(&self.get_state_key(), &self.serialize_state());
+        // Playback state is polled by the UI as well since launches take effect on measure
+        // boundaries, but starting and stopping are reflected right away
+        get_vcm().subscribe(EngineEventKind::TransportStarted, self.uuid);
+        get_vcm().subscribe(EngineEventKind::TransportStopped, self.uuid);
+    }
+
+    fn cleanup(&mut self) { js::cleanup_clip_launcher(&self.get_state_key()); }
+
+    fn get_id(&self) -> String { self.uuid.to_string() }
+
+    fn hide(&mut self) { js::hide_clip_launcher(&self.get_state_key()); }
+
+    fn unhide(&mut self) { js::unhide_clip_launcher(&self.get_state_key()); }
+
+    fn handle_message(&mut self, key: &str, val: &[u8]) -> Option<Vec<u8>> {
+        match key {
+            "get_state" => return Some(self.serialize_state().into_bytes()),
+            "get_playback_state" =>
+                return Some(
+                    serde_json::to_vec(&self.get_playback_state())
+                        .expect("Failed to serialize clip launcher playback state"),
+                ),
+            "add_track" => self.state.tracks.push(ClipLauncherTrack {
+                midi_editor_vc_id: None,
+                cells: vec![None; self.state.scene_count],
+            }),
+            "remove_track" => {
+                let track_ix = read_index(key, val);
+                if track_ix < self.state.tracks.len() {
+                    self.state.tracks.remove(track_ix);
+                }
+            },
+            "add_scene" => {
+                self.state.scene_count += 1;
+                for track in &mut self.state.tracks {
+                    track.cells.push(None);
+                }
+            },
+            "remove_scene" => {
+                let scene_ix = read_index(key, val);
+                if scene_ix < self.state.scene_count {
+                    self.state.scene_count -= 1;
+                    for track in &mut self.state.tracks {
+                        track.cells.remove(scene_ix);
+                    }
+                }
+            },
+            "set_track_midi_editor" => match serde_json::from_slice(val) {
+                Ok(TrackMidiEditorMessage { track_ix, vc_id }) =>
+                    if let Some(track) = self.state.tracks.get_mut(track_ix) {
+                        track.midi_editor_vc_id = vc_id;
+                    },
+                Err(err) => {
+                    error!("Error decoding clip launcher track MIDI editor: {:?}", err);
+                    return None;
+                },
+            },
+            "set_cell" => match serde_json::from_slice(val) {
+                Ok(CellMessage {
+                    track_ix,
+                    scene_ix,
+                    clip_id,
+                }) =>
+                    if let Some(cell) = self
+                        .state
+                        .tracks
+                        .get_mut(track_ix)
+                        .and_then(|track| track.cells.get_mut(scene_ix))
+                    {
+                        *cell = clip_id;
+                    },
+                Err(err) => {
+                    error!("Error decoding clip launcher cell: {:?}", err);
+                    return None;
+                },
+            },
+            "launch_cell" => {
+                match serde_json::from_slice(val) {
+                    Ok(CellMessage {
+                        track_ix, scene_ix, ..
+                    }) => self.launch_cell(track_ix, scene_ix),
+                    Err(err) => error!("Error decoding clip launcher cell: {:?}", err),
+                }
+                return None;
+            },
+            "stop_track" => {
+                self.launch(read_index(key, val), None, None);
+                return None;
+            },
+            "launch_scene" => {
+                self.launch_scene(read_index(key, val));
+                return None;
+            },
+            "stop_all" => {
+                for track_ix in 0..self.state.tracks.len() {
+                    self.launch(track_ix, None, None);
+                }
+                return None;
+            },
+            _ => return None,
+        }
+
+        Some(self.sync_state().into_bytes())
+    }
+
+    fn handle_event(&mut self, event: &EngineEvent) {
+        let is_own_track = self
+            .state
+            .tracks
+            .iter()
+            .filter_map(|track| track.midi_editor_vc_id)
+            .any(|vc_id| vc_id.to_string() == event.vc_id());
+        if is_own_track {
+            self.sync_playback_state();
+        }
+    }
+
+    fn save(&mut self) -> String {
+        serde_json::to_string(self).expect("Error serializing `ClipLauncher` to String")
+    }
+}
+
+pub fn mk_clip_launcher(definition_opt: Option<&str>, uuid: Uuid) -> Box<dyn ViewContext> {
+    let clip_launcher: ClipLauncher = match definition_opt {
+        Some(definition) =>
+            serde_json::from_str(definition).expect("Error while deserializing `ClipLauncher`"),
+        None => ClipLauncher::new(uuid),
+    };
+    Box::new(clip_launcher)
+}
@@ -30,7 +30,9 @@ impl ViewContext for CompositionSharing {
 
     fn unhide(&mut self) { js::unhide_composition_sharing(&self.get_id()); }
 
-    fn dispose(&mut self) { js::delete_localstorage_key(&self.get_state_key()); }
+    fn dispose(&mut self) { LocalStorage.delete(&self.get_state_key()); }
+
+    fn save_external_data(&mut self) -> Vec<String> { vec![self.get_state_key()] }
 
     fn save(&mut self) -> String {
         serde_json::to_string(self).expect("Error serializing `CompositionSharing` to String")
@@ -43,5 +45,5 @@ pub fn mk_composition_sharing(definition_opt: Option<&str>, uuid: Uuid) -> Box<d
             .expect("Error while deserializing `CompositionSharing`"),
         None => CompositionSharing::new(uuid),
     };
-    box composition_sharing
+    Box::new(composition_sharing)
 }
@@ -0,0 +1,308 @@
+//! Rows, patterns, and the pattern chain of a drum sequencer.  The state is owned and persisted
+//! here, and the JS side of the drum sequencer schedules it against the transport.
+
+use std::collections::BTreeMap;
+
+pub const VALID_STEP_COUNTS: [u8; 2] = [16, 32];
+pub const MAX_VELOCITY: u8 = 127;
+/// Probabilities are stored as a percentage
+pub const MAX_PROBABILITY: u8 = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SynthDrum {
+    Kick,
+    Snare,
+    ClosedHat,
+    OpenHat,
+    Clap,
+}
+
+/// Mirrors the `SampleDescriptor` used by the sample library on the JS side
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SampleDescriptor {
+    #[serde(rename = "isLocal")]
+    pub is_local: bool,
+    pub name: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RowSource {
+    Sample { sample: Option<SampleDescriptor> },
+    SynthDrum { drum: SynthDrum },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DrumRow {
+    pub id: u32,
+    pub name: String,
+    pub source: RowSource,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Step {
+    pub velocity: u8,
+    pub probability: u8,
+}
+
+impl Step {
+    pub fn new(velocity: u8, probability: u8) -> Self {
+        Step {
+            velocity: velocity.clamp(1, MAX_VELOCITY),
+            probability: probability.min(MAX_PROBABILITY),
+        }
+    }
+}
+
+/// Patterns only store the steps that are active, keyed by `(row_id, step_ix)`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(from = "CompactPattern", into = "CompactPattern")]
+pub struct Pattern {
+    pub id: u32,
+    pub name: String,
+    steps: BTreeMap<(u32, u8), Step>,
+}
+
+/// Patterns are serialized with each active step as a `[row_id, step_ix, velocity, probability]`
+/// tuple, which keeps saved compositions small since most steps of most patterns are empty.
+#[derive(Serialize, Deserialize)]
+struct CompactPattern {
+    id: u32,
+    name: String,
+    steps: Vec<(u32, u8, u8, u8)>,
+}
+
+impl From<CompactPattern> for Pattern {
+    fn from(compact: CompactPattern) -> Self {
+        Pattern {
+            id: compact.id,
+            name: compact.name,
+            steps: compact
+                .steps
+                .into_iter()
+                .map(|(row_id, step_ix, velocity, probability)| {
+                    ((row_id, step_ix), Step::new(velocity, probability))
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<Pattern> for CompactPattern {
+    fn from(pattern: Pattern) -> Self {
+        CompactPattern {
+            id: pattern.id,
+            name: pattern.name,
+            steps: pattern
+                .steps
+                .into_iter()
+                .map(|((row_id, step_ix), step)| (row_id, step_ix, step.velocity, step.probability))
+                .collect(),
+        }
+    }
+}
+
+impl Pattern {
+    pub fn get_step(&self, row_id: u32, step_ix: u8) -> Option<Step> {
+        self.steps.get(&(row_id, step_ix)).copied()
+    }
+
+    /// Returns all active steps as `((row_id, step_ix), step)` ordered by row and then step
+    pub fn steps(&self) -> impl Iterator<Item = ((u32, u8), Step)> + '_ {
+        self.steps.iter().map(|(key, step)| (*key, *step))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DrumSequencerState {
+    step_count: u8,
+    rows: Vec<DrumRow>,
+    patterns: Vec<Pattern>,
+    /// The IDs of the patterns to play, in order.  Playback loops back to the start of the chain
+    /// once it reaches the end.
+    chain: Vec<u32>,
+    next_row_id: u32,
+    next_pattern_id: u32,
+}
+
+impl Default for DrumSequencerState {
+    fn default() -> Self {
+        let mut state = DrumSequencerState {
+            step_count: VALID_STEP_COUNTS[0],
+            rows: Vec::new(),
+            patterns: Vec::new(),
+            chain: Vec::new(),
+            next_row_id: 0,
+            next_pattern_id: 0,
+        };
+        for &drum in &[
+            SynthDrum::Kick,
+            SynthDrum::Snare,
+            SynthDrum::ClosedHat,
+            SynthDrum::Clap,
+        ] {
+            state.add_row(RowSource::SynthDrum { drum });
+        }
+        let pattern_id = state.add_pattern();
+        state.chain.push(pattern_id);
+        state
+    }
+}
+
+impl DrumSequencerState {
+    pub fn step_count(&self) -> u8 { self.step_count }
+
+    pub fn rows(&self) -> &[DrumRow] { &self.rows }
+
+    pub fn patterns(&self) -> &[Pattern] { &self.patterns }
+
+    pub fn chain(&self) -> &[u32] { &self.chain }
+
+    pub fn get_pattern(&self, id: u32) -> Option<&Pattern> {
+        self.patterns.iter().find(|pattern| pattern.id == id)
+    }
+
+    fn get_pattern_mut(&mut self, id: u32) -> Option<&mut Pattern> {
+        self.patterns.iter_mut().find(|pattern| pattern.id == id)
+    }
+
+    /// Returns `false` if the step count isn't one of `VALID_STEP_COUNTS`.  Steps past the end of
+    /// the new step count are dropped.
+    pub fn set_step_count(&mut self, step_count: u8) -> bool {
+        if !VALID_STEP_COUNTS.contains(&step_count) {
+            return false;
+        }
+
+        self.step_count = step_count;
+        for pattern in &mut self.patterns {
+            pattern
+                .steps
+                .retain(|&(_, step_ix), _| step_ix < step_count);
+        }
+        true
+    }
+
+    /// Adds a new row that plays the provided source, returning its ID
+    pub fn add_row(&mut self, source: RowSource) -> u32 {
+        let id = self.next_row_id;
+        self.next_row_id += 1;
+        let name = match &source {
+            RowSource::SynthDrum { drum } => format!("{:?}", drum),
+            RowSource::Sample { .. } => format!("Row {}", id + 1),
+        };
+        self.rows.push(DrumRow { id, name, source });
+        id
+    }
+
+    /// Removes the row along with all of its steps in every pattern
+    pub fn remove_row(&mut self, id: u32) -> Option<DrumRow> {
+        let ix = self.rows.iter().position(|row| row.id == id)?;
+        for pattern in &mut self.patterns {
+            pattern.steps.retain(|&(row_id, _), _| row_id != id);
+        }
+        Some(self.rows.remove(ix))
+    }
+
+    /// Returns `false` if no row with the provided ID exists
+    pub fn set_row_source(&mut self, id: u32, source: RowSource) -> bool {
+        self.rows
+            .iter_mut()
+            .find(|row| row.id == id)
+            .map(|row| row.source = source)
+            .is_some()
+    }
+
+    /// Sets or clears (if `step` is `None`) a step of a pattern.  Returns `false` if the pattern or
+    /// row doesn't exist or the step index is out of range.
+    pub fn set_step(
+        &mut self,
+        pattern_id: u32,
+        row_id: u32,
+        step_ix: u8,
+        step: Option<Step>,
+    ) -> bool {
+        if step_ix >= self.step_count || !self.rows.iter().any(|row| row.id == row_id) {
+            return false;
+        }
+        let pattern = match self.get_pattern_mut(pattern_id) {
+            Some(pattern) => pattern,
+            None => return false,
+        };
+
+        match step {
+            Some(step) => {
+                pattern.steps.insert(
+                    (row_id, step_ix),
+                    Step::new(step.velocity, step.probability),
+                );
+            },
+            None => {
+                pattern.steps.remove(&(row_id, step_ix));
+            },
+        }
+        true
+    }
+
+    /// Adds a new empty pattern, returning its ID.  It isn't added to the chain.
+    pub fn add_pattern(&mut self) -> u32 {
+        let id = self.next_pattern_id;
+        self.next_pattern_id += 1;
+        self.patterns.push(Pattern {
+            id,
+            name: format!("Pattern {}", id + 1),
+            steps: BTreeMap::new(),
+        });
+        id
+    }
+
+    /// Adds a copy of the pattern with the provided ID, returning the ID of the copy
+    pub fn duplicate_pattern(&mut self, id: u32) -> Option<u32> {
+        let steps = self.get_pattern(id)?.steps.clone();
+        let new_id = self.add_pattern();
+        self.get_pattern_mut(new_id).unwrap().steps = steps;
+        Some(new_id)
+    }
+
+    /// Removes the pattern and all of its entries in the chain.  The last remaining pattern can't
+    /// be removed.  If the chain ends up empty, it's reset to the first remaining pattern.
+    pub fn remove_pattern(&mut self, id: u32) -> Option<Pattern> {
+        if self.patterns.len() <= 1 {
+            return None;
+        }
+        let ix = self.patterns.iter().position(|pattern| pattern.id == id)?;
+        let removed = self.patterns.remove(ix);
+
+        self.chain.retain(|&pattern_id| pattern_id != id);
+        if self.chain.is_empty() {
+            self.chain.push(self.patterns[0].id);
+        }
+        Some(removed)
+    }
+
+    /// Returns `false` without changing anything if the chain is empty or references a pattern
+    /// that doesn't exist
+    pub fn set_chain(&mut self, chain: Vec<u32>) -> bool {
+        if chain.is_empty() || !chain.iter().all(|&id| self.get_pattern(id).is_some()) {
+            return false;
+        }
+
+        self.chain = chain;
+        true
+    }
+
+    /// Returns the pattern and step index that is played at the provided step of the chain,
+    /// wrapping around once the end of the chain is reached.
+    pub fn resolve_chain_step(&self, chain_step: usize) -> (&Pattern, u8) {
+        let step_count = self.step_count as usize;
+        let pattern_id = self.chain[(chain_step / step_count) % self.chain.len()];
+        let pattern = self
+            .get_pattern(pattern_id)
+            .expect("Chain references a pattern that doesn't exist");
+        (pattern, (chain_step % step_count) as u8)
+    }
+
+    pub fn serialize(&self) -> String {
+        serde_json::to_string(self).expect("Error serializing `DrumSequencerState` to String")
+    }
+}
@@ -0,0 +1,168 @@
+//! Defines a view for a classic 16/32-step drum sequencer.  Each row plays either a sample or a
+//! synthesized drum, and patterns can be chained together to build longer sequences.
+
+use serde_json;
+use uuid::Uuid;
+
+use crate::{helpers::grid::prelude::*, view_context::ViewContext};
+
+pub mod drum_sequencer_state;
+
+use self::drum_sequencer_state::{DrumSequencerState, RowSource, Step};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StepValue {
+    velocity: u8,
+    probability: u8,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetStepMessage {
+    pattern_id: u32,
+    row_id: u32,
+    step: u8,
+    /// `None` clears the step
+    value: Option<StepValue>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RowSourceMessage {
+    row_id: u32,
+    source: RowSource,
+}
+
+/// The drum sequencer's patterns live here, but scheduling them against the transport, playing
+/// samples and synth drums, and the UI are all implemented in JS.  The JS side is sent the full
+/// state every time that it changes.
+#[derive(Serialize, Deserialize)]
+pub struct DrumSequencer {
+    pub uuid: Uuid,
+    #[serde(default)]
+    pub state: DrumSequencerState,
+}
+
+fn read_u32(key: &str, val: &[u8]) -> u32 {
+    assert_eq!(
+        val.len(),
+        4,
+        "Message for \"{}\" must be a 4-byte `u32`",
+        key
+    );
+    u32::from_ne_bytes([val[0], val[1], val[2], val[3]])
+}
+
+impl DrumSequencer {
+    pub fn new(uuid: Uuid) -> Self {
+        DrumSequencer {
+            uuid,
+            state: DrumSequencerState::default(),
+        }
+    }
+
+    pub fn get_state_key(&self) -> String { format!("drumSequencer_{}", self.uuid) }
+
+    /// Sends the current state to JS so that it can update its scheduler, returning the serialized
+    /// state.
+    fn sync_state(&self) -> String {
+        let serialized = self.state.serialize();
+        js::set_drum_sequencer_state(&self.get_state_key(), &serialized);
+        serialized
+    }
+}
+
+impl ViewContext for DrumSequencer {
+    fn init(&mut self) {
+        js::init_drum_sequencer(&self.get_state_key(), &self.state.serialize());
+    }
+
+    fn cleanup(&mut self) { js::cleanup_drum_sequencer(&self.get_state_key()); }
+
+    fn get_id(&self) -> String { self.uuid.to_string() }
+
+    fn hide(&mut self) { js::hide_drum_sequencer(&self.get_state_key()); }
+
+    fn unhide(&mut self) { js::unhide_drum_sequencer(&self.get_state_key()); }
+
+    fn get_audio_connectables(&self) -> JsValue {
+        js::get_drum_sequencer_audio_connectables(&self.get_state_key())
+    }
+
+    fn handle_message(&mut self, key: &str, val: &[u8]) -> Option<Vec<u8>> {
+        let applied = match key {
+            "get_state" => return Some(self.state.serialize().into_bytes()),
+            "set_step" => match serde_json::from_slice(val) {
+                Ok(SetStepMessage {
+                    pattern_id,
+                    row_id,
+                    step,
+                    value,
+                }) => {
+                    let value = value.map(|value| Step::new(value.velocity, value.probability));
+                    self.state.set_step(pattern_id, row_id, step, value)
+                },
+                Err(err) => {
+                    error!("Error decoding drum sequencer step: {:?}", err);
+                    return None;
+                },
+            },
+            "set_step_count" => {
+                let step_count = read_u32(key, val);
+                step_count <= u8::MAX as u32 && self.state.set_step_count(step_count as u8)
+            },
+            "add_row" => match serde_json::from_slice(val) {
+                Ok(source) => {
+                    self.state.add_row(source);
+                    true
+                },
+                Err(err) => {
+                    error!("Error decoding drum sequencer row source: {:?}", err);
+                    return None;
+                },
+            },
+            "remove_row" => self.state.remove_row(read_u32(key, val)).is_some(),
+            "set_row_source" => match serde_json::from_slice(val) {
+                Ok(RowSourceMessage { row_id, source }) =>
+                    self.state.set_row_source(row_id, source),
+                Err(err) => {
+                    error!("Error decoding drum sequencer row source: {:?}", err);
+                    return None;
+                },
+            },
+            "add_pattern" => {
+                self.state.add_pattern();
+                true
+            },
+            "duplicate_pattern" => self.state.duplicate_pattern(read_u32(key, val)).is_some(),
+            "remove_pattern" => self.state.remove_pattern(read_u32(key, val)).is_some(),
+            "set_chain" => match serde_json::from_slice(val) {
+                Ok(chain) => self.state.set_chain(chain),
+                Err(err) => {
+                    error!("Error decoding drum sequencer pattern chain: {:?}", err);
+                    return None;
+                },
+            },
+            _ => return None,
+        };
+
+        if !applied {
+            warn!("Drum sequencer message \"{}\" couldn't be applied to the current state", key);
+        }
+        Some(self.sync_state().into_bytes())
+    }
+
+    fn save(&mut self) -> String {
+        serde_json::to_string(self).expect("Error serializing `DrumSequencer` to String")
+    }
+}
+
+pub fn mk_drum_sequencer(definition_opt: Option<&str>, uuid: Uuid) -> Box<dyn ViewContext> {
+    let drum_sequencer: DrumSequencer = match definition_opt {
+        Some(definition) =>
+            serde_json::from_str(definition).expect("Error while deserializing `DrumSequencer`"),
+        None => DrumSequencer::new(uuid),
+    };
+    Box::new(drum_sequencer)
+}
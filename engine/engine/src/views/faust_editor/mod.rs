@@ -30,7 +30,9 @@ impl ViewContext for FaustEditor {
 
     fn get_id(&self) -> String { self.uuid.to_string() }
 
-    fn dispose(&mut self) { js::delete_localstorage_key(&self.get_state_key()); }
+    fn dispose(&mut self) { LocalStorage.delete(&self.get_state_key()); }
+
+    fn save_external_data(&mut self) -> Vec<String> { vec![self.get_state_key()] }
 
     fn save(&mut self) -> String {
         serde_json::to_string(self).expect("Error serializing `FaustEditor` to String")
@@ -55,5 +57,5 @@ pub fn mk_faust_editor(definition_opt: Option<&str>, uuid: Uuid) -> Box<dyn View
             serde_json::from_str(definition).expect("Error while deserializing `FaustEditor`"),
         None => FaustEditor::new(uuid),
     };
-    box faust_editor
+    Box::new(faust_editor)
 }
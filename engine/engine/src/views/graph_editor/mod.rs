@@ -1,26 +1,127 @@
 //! Defines a view that allows connecting between components of an audio composition together
 
+use std::collections::BTreeMap;
+
 use serde_json;
 use uuid::Uuid;
 
-use crate::{helpers::grid::prelude::*, view_context::ViewContext};
+use crate::{
+    audio_graph::{AudioGraph, NodeDefinition, NodeId, PortRef},
+    helpers::grid::prelude::*,
+    view_context::ViewContext,
+};
+
+/// A node in the patch network as sent over by the JS side of the graph editor, identified by the
+/// ID of the VC or foreign node that it represents
+#[derive(Deserialize)]
+struct PatchNetworkNode {
+    id: String,
+    #[serde(flatten)]
+    definition: NodeDefinition,
+}
+
+#[derive(Deserialize)]
+struct PatchNetworkSnapshot {
+    nodes: Vec<PatchNetworkNode>,
+    connections: Vec<(ConnectionDescriptor, ConnectionDescriptor)>,
+}
 
-/// This is just a shim to the JS-based graph editor.  Since there really aren't any complicated
-/// interactive or graphical components of this view context, the actual implementation for this
-/// is done in JS.
+/// The rendering and interaction for this view context (drawing nodes and cables, dragging nodes,
+/// and creating connections) is implemented in JS.  This holds the layout of the graph along with
+/// a mirror of the patch network as an `AudioGraph` which is used to validate new connections
+/// before they're made.
 #[derive(Serialize, Deserialize)]
 pub struct GraphEditor {
     pub uuid: Uuid,
+    /// Positions of nodes in the editor keyed by the ID of the VC or foreign node they represent
+    #[serde(default)]
+    pub node_positions: BTreeMap<String, (f32, f32)>,
+    #[serde(default)]
+    pub audio_graph: AudioGraph,
+    /// Maps the IDs of VCs and foreign nodes to the IDs of the nodes representing them in
+    /// `audio_graph`
+    #[serde(default)]
+    node_ids: BTreeMap<String, NodeId>,
 }
 
 impl GraphEditor {
-    pub fn new(uuid: Uuid) -> Self { GraphEditor { uuid } }
+    pub fn new(uuid: Uuid) -> Self {
+        GraphEditor {
+            uuid,
+            node_positions: BTreeMap::new(),
+            audio_graph: AudioGraph::default(),
+            node_ids: BTreeMap::new(),
+        }
+    }
 
     pub fn get_state_key(&self) -> String { format!("graphEditor_{}", self.uuid) }
+
+    fn get_port_ref(&self, descriptor: &ConnectionDescriptor) -> Option<PortRef> {
+        self.node_ids
+            .get(&descriptor.vc_id)
+            .map(|&node_id| PortRef::new(node_id, &descriptor.name))
+    }
+
+    /// Rebuilds the mirrored audio graph to match the patch network.  Connections that aren't valid
+    /// in the audio graph are left out of it.
+    fn sync_audio_graph(&mut self, snapshot: PatchNetworkSnapshot) {
+        self.audio_graph = AudioGraph::default();
+        self.node_ids.clear();
+        for PatchNetworkNode { id, definition } in snapshot.nodes {
+            let node_id = self.audio_graph.add_node(definition);
+            self.node_ids.insert(id, node_id);
+        }
+
+        let node_ids = &self.node_ids;
+        self.node_positions
+            .retain(|id, _| node_ids.contains_key(id));
+
+        for (from, to) in snapshot.connections {
+            let (from_ref, to_ref) = match (self.get_port_ref(&from), self.get_port_ref(&to)) {
+                (Some(from_ref), Some(to_ref)) => (from_ref, to_ref),
+                _ => {
+                    warn!(
+                        "Connection from {}:{} to {}:{} references a node that doesn't exist",
+                        from.vc_id, from.name, to.vc_id, to.name
+                    );
+                    continue;
+                },
+            };
+
+            if let Err(err) = self.audio_graph.connect(from_ref, to_ref) {
+                warn!(
+                    "Connection from {}:{} to {}:{} is invalid: {}",
+                    from.vc_id, from.name, to.vc_id, to.name, err
+                );
+            }
+        }
+    }
+
+    /// Tries to add the connection to the audio graph, returning a description of why it's invalid
+    /// if it can't be made.
+    fn connect(
+        &mut self,
+        from: &ConnectionDescriptor,
+        to: &ConnectionDescriptor,
+    ) -> Option<String> {
+        let (from_ref, to_ref) = match (self.get_port_ref(from), self.get_port_ref(to)) {
+            (Some(from_ref), Some(to_ref)) => (from_ref, to_ref),
+            _ => return Some("One of the connected nodes doesn't exist".into()),
+        };
+
+        self.audio_graph
+            .connect(from_ref, to_ref)
+            .err()
+            .map(|err| err.to_string())
+    }
 }
 
 impl ViewContext for GraphEditor {
-    fn init(&mut self) { js::init_graph_editor(&self.get_state_key()); }
+    fn init(&mut self) {
+        let node_positions = serde_json::to_string(&self.node_positions)
+            .expect("Error serializing graph editor node positions");
+        js::init_graph_editor(&self.get_state_key(), &node_positions);
+    }
 
     fn cleanup(&mut self) { js::cleanup_graph_editor(&self.get_state_key()); }
 
@@ -30,7 +131,40 @@ impl ViewContext for GraphEditor {
 
     fn unhide(&mut self) { js::unhide_graph_editor(&self.get_id()); }
 
-    fn dispose(&mut self) { js::delete_localstorage_key(&self.get_state_key()); }
+    fn dispose(&mut self) { LocalStorage.delete(&self.get_state_key()); }
+
+    fn save_external_data(&mut self) -> Vec<String> { vec![self.get_state_key()] }
+
+    fn handle_message(&mut self, key: &str, val: &[u8]) -> Option<Vec<u8>> {
+        match key {
+            "sync_audio_graph" => match serde_json::from_slice(val) {
+                Ok(snapshot) => self.sync_audio_graph(snapshot),
+                Err(err) => error!("Error decoding patch network snapshot: {:?}", err),
+            },
+            "connect" => {
+                let (from, to): (ConnectionDescriptor, ConnectionDescriptor) =
+                    match serde_json::from_slice(val) {
+                        Ok(connection) => connection,
+                        Err(err) => {
+                            error!("Error decoding connection: {:?}", err);
+                            return None;
+                        },
+                    };
+
+                // An empty response indicates that the connection was made successfully
+                return Some(self.connect(&from, &to).unwrap_or_default().into_bytes());
+            },
+            "set_node_positions" => {
+                match serde_json::from_slice::<BTreeMap<String, (f32, f32)>>(val) {
+                    Ok(positions) => self.node_positions.extend(positions),
+                    Err(err) => error!("Error decoding node positions: {:?}", err),
+                }
+            },
+            _ => (),
+        }
+
+        None
+    }
 
     fn save(&mut self) -> String {
         serde_json::to_string(self).expect("Error serializing `GraphEditor` to String")
@@ -43,5 +177,5 @@ pub fn mk_graph_editor(definition_opt: Option<&str>, uuid: Uuid) -> Box<dyn View
             serde_json::from_str(definition).expect("Error while deserializing `GraphEditor`"),
         None => GraphEditor::new(uuid),
     };
-    box graph_editor
+    Box::new(graph_editor)
 }
@@ -0,0 +1,326 @@
+//! Arpeggiator that plays the notes of a chord one after another.  It can either run live on the
+//! notes held down on MIDI input, or be applied to the selected notes of the editor to replace
+//! each chord with the notes of its arpeggio.
+
+use common::theory::NOTES_PER_OCTAVE;
+use rand::Rng;
+
+use super::{
+    scheduler::{ScheduledEvents, SchedulerLoopHandle},
+    *,
+};
+
+/// How often the live arpeggiator schedules its upcoming steps
+const LIVE_ARPEGGIATOR_INTERVAL_MS: usize = 25;
+/// How far ahead of the current time the live arpeggiator schedules steps.  This must be longer
+/// than `LIVE_ARPEGGIATOR_INTERVAL_MS` so that there are no gaps between steps.
+const LIVE_ARPEGGIATOR_LOOKAHEAD_SECONDS: f64 = 0.1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ArpeggiatorPattern {
+    Up,
+    Down,
+    UpDown,
+    /// Each step plays a random note of the chord
+    Random,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ArpeggiatorConf {
+    /// If set, notes held on MIDI input are arpeggiated instead of being played directly
+    pub live: bool,
+    pub pattern: ArpeggiatorPattern,
+    /// Length of each step in beats
+    pub rate: f32,
+    /// The number of octaves that the arpeggio spans, starting at the octave of the chord
+    pub octaves: u8,
+    /// Length of each note as a fraction of the length of its step
+    pub gate: f32,
+}
+
+impl Default for ArpeggiatorConf {
+    fn default() -> Self {
+        ArpeggiatorConf {
+            live: false,
+            pattern: ArpeggiatorPattern::Up,
+            rate: 0.25,
+            octaves: 1,
+            gate: 1.,
+        }
+    }
+}
+
+impl ArpeggiatorConf {
+    /// Returns one cycle of the arpeggio of the chord made up of `notes` in the order in which
+    /// they're played.  For random patterns, this is all of the notes that can be picked from.
+    pub fn build_sequence(&self, notes: &[usize]) -> Vec<usize> {
+        let mut chord = notes.to_vec();
+        chord.sort_unstable();
+        chord.dedup();
+        let mut sequence: Vec<usize> = (0..self.octaves.max(1) as usize)
+            .flat_map(|octave| {
+                chord
+                    .iter()
+                    .map(move |&note| note + octave * NOTES_PER_OCTAVE)
+            })
+            .collect();
+
+        match self.pattern {
+            ArpeggiatorPattern::Up | ArpeggiatorPattern::Random => (),
+            ArpeggiatorPattern::Down => sequence.reverse(),
+            ArpeggiatorPattern::UpDown => {
+                if sequence.len() > 2 {
+                    // The top and bottom notes aren't repeated when the direction changes
+                    let descending: Vec<usize> = sequence[1..sequence.len() - 1]
+                        .iter()
+                        .rev()
+                        .cloned()
+                        .collect();
+                    sequence.extend(descending);
+                }
+            },
+        }
+        sequence
+    }
+
+    /// Returns the note played by step `step_ix` of an arpeggio with the provided sequence
+    pub fn step_note(&self, sequence: &[usize], step_ix: usize) -> usize {
+        match self.pattern {
+            ArpeggiatorPattern::Random => {
+                let ix = with_rng(|rng| rng.gen_range(0, sequence.len()));
+                sequence[ix]
+            },
+            _ => sequence[step_ix % sequence.len()],
+        }
+    }
+
+    /// Returns the `(note, start_beat, end_beat)` of each step of an arpeggio of `notes` from
+    /// `start_beat` to `end_beat`.  The last step is cut off at `end_beat`.
+    pub fn arpeggiate(
+        &self,
+        notes: &[usize],
+        start_beat: f32,
+        end_beat: f32,
+    ) -> Vec<(usize, f32, f32)> {
+        if notes.is_empty() || self.rate <= 0. || end_beat <= start_beat {
+            return Vec::new();
+        }
+
+        let sequence = self.build_sequence(notes);
+        let step_count = ((end_beat - start_beat) / self.rate).ceil() as usize;
+        (0..step_count)
+            .map(|step_ix| {
+                let step_start_beat = start_beat + step_ix as f32 * self.rate;
+                let step_end_beat = (step_start_beat + self.rate * self.gate).min(end_beat);
+                (
+                    self.step_note(&sequence, step_ix),
+                    step_start_beat,
+                    step_end_beat,
+                )
+            })
+            .collect()
+    }
+}
+
+/// State of the arpeggiator while it's running live on held MIDI input
+pub struct LiveArpeggiator {
+    /// `(note_id, velocity)` of each of the held notes
+    held_notes: Vec<(usize, u8)>,
+    step_ix: usize,
+    /// The time at which the next step that hasn't been scheduled yet plays
+    next_step_time: f64,
+    interval_handle: SchedulerLoopHandle,
+    _cb: Closure<dyn FnMut(f64)>,
+}
+
+impl MIDIEditorGridHandler {
+    pub fn arpeggiator_note_on(&mut self, cur_time: f64, note_id: usize, velocity: u8) {
+        if let Some(live_arpeggiator) = &mut self.live_arpeggiator {
+            live_arpeggiator.held_notes.push((note_id, velocity));
+            return;
+        }
+
+        // The handler lives as long as the interval since it's cancelled when the handler is
+        // cleaned up
+        let handler_ptr: *mut MIDIEditorGridHandler = self;
+        let cb = Closure::wrap(Box::new(move |cur_time: f64| unsafe {
+            (*handler_ptr).run_live_arpeggiator(cur_time)
+        }) as Box<dyn FnMut(f64)>);
+        let interval_handle =
+            js::register_midi_editor_loop_interval(&cb, LIVE_ARPEGGIATOR_INTERVAL_MS);
+        self.live_arpeggiator = Some(LiveArpeggiator {
+            held_notes: vec![(note_id, velocity)],
+            step_ix: 0,
+            next_step_time: cur_time,
+            interval_handle,
+            _cb: cb,
+        });
+        self.run_live_arpeggiator(cur_time);
+    }
+
+    pub fn arpeggiator_note_off(&mut self, note_id: usize) {
+        let live_arpeggiator = match &mut self.live_arpeggiator {
+            Some(live_arpeggiator) => live_arpeggiator,
+            None => return,
+        };
+
+        live_arpeggiator
+            .held_notes
+            .retain(|&(held_note_id, _)| held_note_id != note_id);
+        if live_arpeggiator.held_notes.is_empty() {
+            self.stop_live_arpeggiator();
+        }
+    }
+
+    /// Stops the live arpeggiator.  Steps that have already been scheduled still play.
+    pub fn stop_live_arpeggiator(&mut self) {
+        if let Some(live_arpeggiator) = self.live_arpeggiator.take() {
+            js::cancel_midi_editor_loop_interval(live_arpeggiator.interval_handle);
+        }
+    }
+
+    /// Schedules all steps of the live arpeggio that play before the lookahead window ends
+    fn run_live_arpeggiator(&mut self, cur_time: f64) {
+        let conf = self.arpeggiator;
+        let step_seconds = 60. / self.tempo_map.base_bpm() * conf.rate as f64;
+        let live_arpeggiator = match &mut self.live_arpeggiator {
+            Some(live_arpeggiator) if step_seconds > 0. => live_arpeggiator,
+            _ => return,
+        };
+
+        let notes: Vec<usize> = live_arpeggiator
+            .held_notes
+            .iter()
+            .map(|&(note_id, _)| note_id)
+            .collect();
+        let velocity = live_arpeggiator
+            .held_notes
+            .iter()
+            .map(|&(_, velocity)| velocity)
+            .max()
+            .unwrap_or(DEFAULT_NOTE_VELOCITY);
+        let sequence = conf.build_sequence(&notes);
+
+        let mut events = ScheduledEvents::default();
+        // Steps that were missed because the interval ran late are skipped rather than played all
+        // at once
+        live_arpeggiator.next_step_time = live_arpeggiator.next_step_time.max(cur_time);
+        while live_arpeggiator.next_step_time < cur_time + LIVE_ARPEGGIATOR_LOOKAHEAD_SECONDS {
+            let step_time = live_arpeggiator.next_step_time;
+            let note_id = conf.step_note(&sequence, live_arpeggiator.step_ix);
+            events.push_note(
+                note_id,
+                velocity,
+                step_time,
+                step_time + step_seconds * conf.gate as f64,
+            );
+            live_arpeggiator.step_ix += 1;
+            live_arpeggiator.next_step_time += step_seconds;
+        }
+        events.schedule(self);
+    }
+
+    /// Replaces each chord of selected notes, made up of the notes that start at the same beat,
+    /// with its arpeggio.  The arpeggio lasts as long as the longest note of the chord.  If any of
+    /// the arpeggios' notes would intersect a note that isn't selected, nothing is changed.
+    pub fn arpeggiate_selected_notes(&mut self, grid_state: &mut GridState<usize>) {
+        let mut selection: Vec<SelectedNoteData> =
+            grid_state.selected_notes.iter().cloned().collect();
+        selection.sort_by(|a, b| a.start_beat.partial_cmp(&b.start_beat).unwrap());
+
+        let conf = &grid_state.conf;
+        let mut arpeggio_notes: Vec<(usize, NoteBox<usize>)> = Vec::new();
+        let mut chord_start_ix = 0;
+        while chord_start_ix < selection.len() {
+            let start_beat = selection[chord_start_ix].start_beat;
+            let chord_len = selection[chord_start_ix..]
+                .iter()
+                .take_while(|note| note.start_beat == start_beat)
+                .count();
+            let chord = &selection[chord_start_ix..chord_start_ix + chord_len];
+            chord_start_ix += chord_len;
+
+            let notes: Vec<usize> = chord
+                .iter()
+                .map(|note| self.note_layout.line_note(note.line_ix))
+                .collect();
+            let end_beat = chord
+                .iter()
+                .map(|note| start_beat + note.width)
+                .fold(start_beat, f32::max);
+            let velocity = chord.iter().map(|note| note.velocity).max().unwrap();
+
+            for (note, step_start_beat, step_end_beat) in
+                self.arpeggiator.arpeggiate(&notes, start_beat, end_beat)
+            {
+                let line_ix = match self.note_layout.note_line(note) {
+                    Some(line_ix) => line_ix,
+                    None => continue,
+                };
+                let dom_id = MidiEditorGridRenderer::create_note(
+                    conf.beats_to_px(step_start_beat),
+                    conf.cursor_gutter_height + conf.padded_line_height() * line_ix,
+                    conf.beats_to_px(step_end_beat - step_start_beat),
+                    conf.zoomed_line_height(),
+                    None,
+                );
+                MidiEditorGridRenderer::set_note_velocity(dom_id, velocity);
+                arpeggio_notes.push((
+                    line_ix,
+                    NoteBox {
+                        id: NoteId::next(),
+                        data: dom_id,
+                        bounds: NoteBoxBounds {
+                            start_beat: step_start_beat,
+                            end_beat: step_end_beat,
+                        },
+                        velocity,
+                        pitch_bend: Vec::new(),
+                        expression: NoteExpression::default(),
+                    },
+                ));
+            }
+        }
+        if arpeggio_notes.is_empty() {
+            return;
+        }
+
+        let notes_before_edit = grid_state.get_raw_note_data();
+        let removed_notes: Vec<(usize, NoteBox<usize>)> = selection
+            .iter()
+            .filter_map(|note| {
+                grid_state
+                    .data
+                    .remove(note.line_ix, note.start_beat)
+                    .map(|note_box| (note.line_ix, note_box))
+            })
+            .collect();
+        let new_selected_notes: Vec<SelectedNoteData> = arpeggio_notes
+            .iter()
+            .map(|(line_ix, note)| SelectedNoteData::from_note_box(*line_ix, note))
+            .collect();
+
+        if let Some(rejected_notes) = grid_state.data.insert_group(arpeggio_notes) {
+            for (_, note) in rejected_notes {
+                js::delete_element(note.data);
+            }
+            for (line_ix, note) in removed_notes {
+                let reinsertion_error = grid_state.data.insert(line_ix, note);
+                debug_assert!(reinsertion_error.is_none());
+            }
+            return;
+        }
+
+        grid_state.note_history.record(&notes_before_edit);
+        for (_, note) in removed_notes {
+            js::delete_element(note.data);
+        }
+        grid_state.selected_notes.clear();
+        for note_data in new_selected_notes {
+            MidiEditorGridRenderer::select_note(note_data.dom_id);
+            grid_state.selected_notes.insert(note_data);
+        }
+    }
+}
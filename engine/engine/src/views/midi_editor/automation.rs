@@ -0,0 +1,666 @@
+//! Automation lanes for synth and effect parameters.  Each lane holds `(beat, value)` breakpoints
+//! for a single parameter in the patch network.  During playback, lanes are sampled along with
+//! the notes that are scheduled for each pass and the sampled values are sent to JS to be applied
+//! to the parameter.  The active lane is rendered beneath the grid where its breakpoints can be
+//! added, dragged, and deleted with the mouse.
+//!
+//! Lanes can also be bound to a MIDI controller with MIDI learn: after learning is started for a
+//! lane, the next control change that comes in through the MIDI input binds its controller to the
+//! lane.  Moving a bound controller sets the lane's parameter live, and while MIDI is being
+//! recorded its values are recorded into the lane as breakpoints, replacing the breakpoints that
+//! were there before.
+
+use super::{
+    constants::{AUTOMATION_LANE_HEIGHT_PX, AUTOMATION_LANE_OFFSET_PX, LANE_MARGIN_PX},
+    *,
+};
+use crate::helpers::undo::UndoHistory;
+
+/// Lanes are sampled at this interval while scheduling.  The parameter is ramped linearly between
+/// samples, so this only matters for curved segments.
+pub const AUTOMATION_SAMPLE_INTERVAL_BEATS: f64 = 1. / 16.;
+/// The number of line segments used to render each curved segment of a lane
+const CURVE_RENDER_SEGMENT_COUNT: usize = 12;
+const BREAKPOINT_SIZE_PX: usize = 6;
+/// Clicks within this many pixels of a breakpoint grab it
+const BREAKPOINT_HIT_RADIUS_PX: usize = 6;
+
+/// How a lane's value changes between a breakpoint and the one after it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Interpolation {
+    #[default]
+    Linear,
+    /// The progress through the segment is raised to `exponent`.  Exponents greater than 1 start
+    /// slow and end fast and exponents less than 1 do the opposite.
+    Curve {
+        exponent: f32,
+    },
+}
+
+impl Interpolation {
+    /// Interpolates between `start_value` and `end_value` with `progress` from 0 to 1
+    pub fn interpolate(self, start_value: f32, end_value: f32, progress: f32) -> f32 {
+        let progress = clamp(progress, 0., 1.);
+        let progress = match self {
+            Interpolation::Linear => progress,
+            Interpolation::Curve { exponent } => progress.powf(exponent),
+        };
+        start_value + (end_value - start_value) * progress
+    }
+
+    /// Returns the next interpolation in the cycle used when control-clicking a breakpoint
+    pub fn cycle(self) -> Self {
+        match self {
+            Interpolation::Linear => Interpolation::Curve { exponent: 3. },
+            Interpolation::Curve { exponent } if exponent > 1. =>
+                Interpolation::Curve { exponent: 1. / 3. },
+            Interpolation::Curve { .. } => Interpolation::Linear,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AutomationBreakpoint {
+    pub beat: f64,
+    pub value: f32,
+    /// Interpolation used for the segment between this breakpoint and the next one
+    #[serde(default)]
+    pub interpolation: Interpolation,
+}
+
+/// Identifies a parameter in the patch network by the ID of the VC that owns it and the name of
+/// its input
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutomationTarget {
+    pub vc_id: String,
+    pub param_name: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutomationLane {
+    pub target: AutomationTarget,
+    /// The range of values that the lane covers when it's rendered and edited
+    pub min_value: f32,
+    pub max_value: f32,
+    /// Breakpoints sorted by beat
+    #[serde(default)]
+    pub breakpoints: Vec<AutomationBreakpoint>,
+    /// The MIDI CC controller that's bound to the lane with MIDI learn
+    #[serde(default)]
+    pub control_index: Option<u8>,
+}
+
+impl AutomationLane {
+    pub fn new(target: AutomationTarget, min_value: f32, max_value: f32) -> Self {
+        AutomationLane {
+            target,
+            min_value,
+            max_value,
+            breakpoints: Vec::new(),
+            control_index: None,
+        }
+    }
+
+    /// Returns the value of the lane at `beat`.  The value is held constant before the first
+    /// breakpoint and after the last one.  Returns `None` if the lane has no breakpoints.
+    pub fn value_at(&self, beat: f64) -> Option<f32> {
+        let next_ix = self
+            .breakpoints
+            .iter()
+            .position(|breakpoint| breakpoint.beat > beat);
+        match next_ix {
+            None => self.breakpoints.last().map(|breakpoint| breakpoint.value),
+            Some(0) => Some(self.breakpoints[0].value),
+            Some(next_ix) => {
+                let (start, end) = (&self.breakpoints[next_ix - 1], &self.breakpoints[next_ix]);
+                let progress = (beat - start.beat) / (end.beat - start.beat);
+                Some(
+                    start
+                        .interpolation
+                        .interpolate(start.value, end.value, progress as f32),
+                )
+            },
+        }
+    }
+
+    /// Samples the lane every `interval` beats within `[start_beat, end_beat)`, making sure that
+    /// every breakpoint in that range is included as well.  Returns `(beat, value)` pairs sorted
+    /// by beat.
+    pub fn sample(&self, start_beat: f64, end_beat: f64, interval: f64) -> Vec<(f64, f32)> {
+        if self.breakpoints.is_empty() || end_beat <= start_beat {
+            return Vec::new();
+        }
+
+        let mut beats: Vec<f64> = Vec::new();
+        let mut beat = start_beat;
+        while beat < end_beat {
+            beats.push(beat);
+            beat += interval;
+        }
+        beats.extend(
+            self.breakpoints
+                .iter()
+                .map(|breakpoint| breakpoint.beat)
+                .filter(|&beat| beat > start_beat && beat < end_beat),
+        );
+        beats.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        beats.dedup();
+
+        beats
+            .into_iter()
+            .map(|beat| (beat, self.value_at(beat).unwrap()))
+            .collect()
+    }
+
+    fn clamp_value(&self, value: f32) -> f32 {
+        clamp(
+            value,
+            self.min_value.min(self.max_value),
+            self.max_value.max(self.min_value),
+        )
+    }
+
+    /// Inserts a breakpoint, replacing any existing breakpoint at the same beat.  Returns the
+    /// index of the breakpoint.
+    pub fn insert_breakpoint(&mut self, beat: f64, value: f32) -> usize {
+        let breakpoint = AutomationBreakpoint {
+            beat: beat.max(0.),
+            value: self.clamp_value(value),
+            interpolation: Interpolation::default(),
+        };
+        match self
+            .breakpoints
+            .iter()
+            .position(|existing| existing.beat >= breakpoint.beat)
+        {
+            Some(ix) if self.breakpoints[ix].beat == breakpoint.beat => {
+                self.breakpoints[ix].value = breakpoint.value;
+                ix
+            },
+            Some(ix) => {
+                self.breakpoints.insert(ix, breakpoint);
+                ix
+            },
+            None => {
+                self.breakpoints.push(breakpoint);
+                self.breakpoints.len() - 1
+            },
+        }
+    }
+
+    /// Moves the breakpoint at `ix` to a new position, keeping the breakpoints sorted.  Returns
+    /// the breakpoint's new index.
+    pub fn move_breakpoint(&mut self, ix: usize, beat: f64, value: f32) -> usize {
+        let interpolation = self.breakpoints.remove(ix).interpolation;
+        let new_ix = self.insert_breakpoint(beat, value);
+        self.breakpoints[new_ix].interpolation = interpolation;
+        new_ix
+    }
+
+    pub fn remove_breakpoint(&mut self, ix: usize) -> AutomationBreakpoint {
+        self.breakpoints.remove(ix)
+    }
+
+    /// Records a breakpoint at `beat`, removing the breakpoints after `since_beat`, which is the
+    /// beat of the last value recorded into the lane, so that the recorded values replace the
+    /// ones that were there before.
+    pub fn record_value(&mut self, since_beat: Option<f64>, beat: f64, value: f32) {
+        if let Some(since_beat) = since_beat {
+            self.breakpoints
+                .retain(|breakpoint| breakpoint.beat <= since_beat || breakpoint.beat >= beat);
+        }
+        self.insert_breakpoint(beat, value);
+    }
+
+    /// Maps the value of a MIDI control change onto the lane's range
+    pub fn control_change_value(&self, value: u8) -> f32 { self.denormalize(value as f32 / 127.) }
+
+    /// Converts a value to a fraction of the lane's range from 0 to 1
+    fn normalize(&self, value: f32) -> f32 {
+        if self.max_value == self.min_value {
+            return 0.;
+        }
+        (value - self.min_value) / (self.max_value - self.min_value)
+    }
+
+    /// The inverse of `normalize`
+    fn denormalize(&self, normalized: f32) -> f32 {
+        self.min_value + normalized * (self.max_value - self.min_value)
+    }
+}
+
+#[derive(Default)]
+pub struct AutomationState {
+    pub lanes: Vec<AutomationLane>,
+    /// Index of the lane that is rendered beneath the grid
+    pub active_lane_ix: Option<usize>,
+    pub history: UndoHistory<Vec<AutomationLane>>,
+    /// Index of the breakpoint in the active lane that is being dragged
+    pub dragging_breakpoint_ix: Option<usize>,
+    pub dom_ids: Vec<DomId>,
+    /// Index of the lane that the next controller that's moved will be bound to
+    pub learning_lane_ix: Option<usize>,
+    /// The index of each lane that has had values recorded into it since MIDI recording started
+    /// along with the beat of the last one
+    recorded_beats: Vec<(usize, f64)>,
+}
+
+impl AutomationState {
+    pub fn new(lanes: Vec<AutomationLane>) -> Self {
+        AutomationState {
+            active_lane_ix: tern(lanes.is_empty(), None, Some(0)),
+            lanes,
+            ..AutomationState::default()
+        }
+    }
+
+    pub fn active_lane(&self) -> Option<&AutomationLane> {
+        self.active_lane_ix.and_then(|ix| self.lanes.get(ix))
+    }
+
+    pub fn active_lane_mut(&mut self) -> Option<&mut AutomationLane> {
+        match self.active_lane_ix {
+            Some(ix) => self.lanes.get_mut(ix),
+            None => None,
+        }
+    }
+
+    /// Records the lanes as they are before an edit so that it can be undone
+    pub fn record_edit(&mut self) { self.history.record(&self.lanes); }
+
+    /// Makes sure that the active lane still exists after lanes have been removed
+    fn fix_active_lane_ix(&mut self) {
+        self.active_lane_ix = match self.active_lane_ix {
+            _ if self.lanes.is_empty() => None,
+            Some(ix) => Some(ix.min(self.lanes.len() - 1)),
+            None => Some(0),
+        };
+    }
+
+    /// Binds the controller `control_index` to the lane that MIDI learn was started for, taking
+    /// it from any other lane that it was bound to.  Returns `false` if MIDI learn wasn't started.
+    pub fn learn_control_change(&mut self, control_index: u8) -> bool {
+        let lane_ix = match self.learning_lane_ix.take() {
+            Some(lane_ix) if lane_ix < self.lanes.len() => lane_ix,
+            _ => return false,
+        };
+
+        self.record_edit();
+        for (i, lane) in self.lanes.iter_mut().enumerate() {
+            if i == lane_ix {
+                lane.control_index = Some(control_index);
+            } else if lane.control_index == Some(control_index) {
+                lane.control_index = None;
+            }
+        }
+        true
+    }
+
+    /// Records the value of a control change at `beat` into the lanes bound to its controller.
+    /// Everything recorded during a single MIDI recording is undone together.  Returns `true` if
+    /// any lane was changed.
+    pub fn record_control_change(&mut self, beat: f64, control_index: u8, value: u8) -> bool {
+        let lane_ixs: Vec<usize> = self
+            .lanes
+            .iter()
+            .enumerate()
+            .filter(|(_, lane)| lane.control_index == Some(control_index))
+            .map(|(lane_ix, _)| lane_ix)
+            .collect();
+        if lane_ixs.is_empty() {
+            return false;
+        }
+        if self.recorded_beats.is_empty() {
+            self.record_edit();
+        }
+
+        for lane_ix in lane_ixs {
+            let recorded_ix = self
+                .recorded_beats
+                .iter()
+                .position(|&(ix, _)| ix == lane_ix);
+            let since_beat = recorded_ix.map(|recorded_ix| self.recorded_beats[recorded_ix].1);
+            let lane = &mut self.lanes[lane_ix];
+            lane.record_value(since_beat, beat, lane.control_change_value(value));
+            match recorded_ix {
+                Some(recorded_ix) => self.recorded_beats[recorded_ix].1 = beat,
+                None => self.recorded_beats.push((lane_ix, beat)),
+            }
+        }
+        true
+    }
+
+    /// Called when MIDI recording stops so that the next recording starts a new edit
+    pub fn finish_recording(&mut self) { self.recorded_beats.clear(); }
+
+    pub fn undo(&mut self) -> bool {
+        let undone = self.history.undo(&mut self.lanes);
+        self.fix_active_lane_ix();
+        undone
+    }
+
+    pub fn redo(&mut self) -> bool {
+        let redone = self.history.redo(&mut self.lanes);
+        self.fix_active_lane_ix();
+        redone
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AutomationLanesInfo<'a> {
+    lanes: &'a [AutomationLane],
+    active_lane_ix: Option<usize>,
+    learning_lane_ix: Option<usize>,
+    can_undo: bool,
+    can_redo: bool,
+}
+
+fn lane_top_px(conf: &GridConf) -> usize {
+    conf.grid_height() + AUTOMATION_LANE_OFFSET_PX + LANE_MARGIN_PX
+}
+
+fn value_to_y(lane: &AutomationLane, conf: &GridConf, value: f32) -> usize {
+    let normalized = clamp(lane.normalize(value), 0., 1.);
+    lane_top_px(conf) + ((1. - normalized) * AUTOMATION_LANE_HEIGHT_PX as f32).round() as usize
+}
+
+/// Converts a y position relative to the bottom of the grid into a value of the lane
+fn y_to_value(lane: &AutomationLane, y: usize) -> f32 {
+    let y = y
+        .saturating_sub(AUTOMATION_LANE_OFFSET_PX + LANE_MARGIN_PX)
+        .min(AUTOMATION_LANE_HEIGHT_PX);
+    lane.denormalize(1. - (y as f32 / AUTOMATION_LANE_HEIGHT_PX as f32))
+}
+
+/// Returns the value set by the mouse or pen at `y`.  The pressure of a pen sets the value rather
+/// than its position so that lanes can be drawn by pressing harder or softer as it's moved.
+fn get_input_value(grid_state: &GridState<usize>, lane: &AutomationLane, y: usize) -> f32 {
+    match grid_state.pen {
+        Some(pen) => lane.denormalize(clamp(pen.pressure, 0., 1.)),
+        None => y_to_value(lane, y),
+    }
+}
+
+impl MIDIEditorGridHandler {
+    /// Re-renders the active automation lane from scratch for the rendered part of the grid
+    pub fn render_automation_lane(&mut self, grid_state: &GridState<usize>) {
+        for dom_id in self.automation.dom_ids.drain(..) {
+            js::delete_element(dom_id);
+        }
+        let lane = match self.automation.active_lane() {
+            Some(lane) => lane,
+            None => return,
+        };
+
+        let conf = &grid_state.conf;
+        let (start_px, end_px) = grid_state.background.px_range(conf);
+        let mut dom_ids = vec![js::render_quad(
+            BG_CANVAS_IX,
+            start_px,
+            lane_top_px(conf),
+            end_px - start_px,
+            AUTOMATION_LANE_HEIGHT_PX,
+            "automation-lane",
+            None,
+        )];
+        let point =
+            |beat: f64, value: f32| (conf.beats_to_px(beat as f32), value_to_y(lane, conf, value));
+        let mut render_segment = |(x1, y1): (usize, usize), (x2, y2): (usize, usize)| {
+            dom_ids.push(js::render_line(
+                FG_CANVAS_IX,
+                x1,
+                y1,
+                x2,
+                y2,
+                "automation-line",
+            ))
+        };
+
+        if let (Some(first), Some(last)) = (lane.breakpoints.first(), lane.breakpoints.last()) {
+            let (first_x, first_y) = point(first.beat, first.value);
+            render_segment((0, first_y), (first_x, first_y));
+            let (last_x, last_y) = point(last.beat, last.value);
+            render_segment((last_x, last_y), (end_px.max(last_x), last_y));
+        }
+        for segment in lane.breakpoints.windows(2) {
+            let (start, end) = (&segment[0], &segment[1]);
+            let segment_count = match start.interpolation {
+                Interpolation::Linear => 1,
+                Interpolation::Curve { .. } => CURVE_RENDER_SEGMENT_COUNT,
+            };
+            let segment_length_beats = end.beat - start.beat;
+            let mut last_point = point(start.beat, start.value);
+            for i in 1..=segment_count {
+                let progress = i as f32 / segment_count as f32;
+                let beat = start.beat + segment_length_beats * progress as f64;
+                let value = start
+                    .interpolation
+                    .interpolate(start.value, end.value, progress);
+                let next_point = point(beat, value);
+                render_segment(last_point, next_point);
+                last_point = next_point;
+            }
+        }
+
+        for breakpoint in &lane.breakpoints {
+            let (x, y) = point(breakpoint.beat, breakpoint.value);
+            dom_ids.push(js::render_quad(
+                FG_CANVAS_IX,
+                x.saturating_sub(BREAKPOINT_SIZE_PX / 2),
+                y.saturating_sub(BREAKPOINT_SIZE_PX / 2),
+                BREAKPOINT_SIZE_PX,
+                BREAKPOINT_SIZE_PX,
+                "automation-breakpoint",
+                None,
+            ));
+        }
+        self.automation.dom_ids = dom_ids;
+    }
+
+    /// Returns the index of the breakpoint in the active lane under the provided point, if any.
+    /// `y` is relative to the bottom of the grid.
+    fn get_breakpoint_at(&self, conf: &GridConf, x: usize, y: usize) -> Option<usize> {
+        let lane = self.automation.active_lane()?;
+        let y = y + conf.grid_height();
+        let is_near = |a: usize, b: usize| a.max(b) - a.min(b) <= BREAKPOINT_HIT_RADIUS_PX;
+        lane.breakpoints.iter().position(|breakpoint| {
+            is_near(conf.beats_to_px(breakpoint.beat as f32), x)
+                && is_near(value_to_y(lane, conf, breakpoint.value), y)
+        })
+    }
+
+    pub fn handle_automation_lane_mouse_down(
+        &mut self,
+        grid_state: &GridState<usize>,
+        x: usize,
+        y: usize,
+    ) -> bool {
+        if self.automation.active_lane().is_none() {
+            return false;
+        }
+
+        let clicked_breakpoint_ix = self.get_breakpoint_at(&grid_state.conf, x, y);
+        self.automation.record_edit();
+        match clicked_breakpoint_ix {
+            Some(ix) if grid_state.shift_pressed => {
+                self.automation
+                    .active_lane_mut()
+                    .unwrap()
+                    .remove_breakpoint(ix);
+            },
+            Some(ix) if grid_state.control_pressed => {
+                let breakpoint = &mut self.automation.active_lane_mut().unwrap().breakpoints[ix];
+                breakpoint.interpolation = breakpoint.interpolation.cycle();
+            },
+            Some(ix) => self.automation.dragging_breakpoint_ix = Some(ix),
+            None => {
+                let beat = snap_beat(grid_state, x);
+                let lane = self.automation.active_lane_mut().unwrap();
+                let value = get_input_value(grid_state, lane, y);
+                let ix = lane.insert_breakpoint(beat, value);
+                self.automation.dragging_breakpoint_ix = Some(ix);
+            },
+        }
+
+        self.render_automation_lane(grid_state);
+        self.automation.dragging_breakpoint_ix.is_some()
+    }
+
+    pub fn handle_automation_lane_mouse_move(
+        &mut self,
+        grid_state: &GridState<usize>,
+        x: usize,
+        y: usize,
+    ) {
+        let ix = match self.automation.dragging_breakpoint_ix {
+            Some(ix) => ix,
+            None => return,
+        };
+        let beat = snap_beat(grid_state, x);
+        let lane = match self.automation.active_lane_mut() {
+            Some(lane) => lane,
+            None => return,
+        };
+        let value = get_input_value(grid_state, lane, y);
+        self.automation.dragging_breakpoint_ix = Some(lane.move_breakpoint(ix, beat, value));
+        self.render_automation_lane(grid_state);
+    }
+
+    pub fn handle_automation_lane_mouse_up(&mut self) {
+        self.automation.dragging_breakpoint_ix = None;
+    }
+
+    /// Samples all automation lanes within `[start_beat, end_beat)` and sends the values to be
+    /// applied to their parameters at the times returned by `beat_to_time`.
+    pub fn schedule_automation(
+        &self,
+        start_beat: f64,
+        end_beat: f64,
+        beat_to_time: impl Fn(f64) -> f64,
+    ) {
+        for lane in &self.automation.lanes {
+            let (timings, values): (Vec<f64>, Vec<f32>) = lane
+                .sample(start_beat, end_beat, AUTOMATION_SAMPLE_INTERVAL_BEATS)
+                .into_iter()
+                .map(|(beat, value)| (beat_to_time(beat), value))
+                .unzip();
+            if timings.is_empty() {
+                continue;
+            }
+
+            js::midi_editor_schedule_automation(
+                &self.vc_id,
+                &lane.target.vc_id,
+                &lane.target.param_name,
+                &timings,
+                &values,
+            );
+        }
+    }
+
+    /// Binds the controller to a lane if MIDI learn was started and sets the parameters of the
+    /// lanes that it's bound to
+    pub fn handle_automation_control_change(
+        &mut self,
+        cur_time: f64,
+        control_index: u8,
+        value: u8,
+    ) {
+        self.automation.learn_control_change(control_index);
+        for lane in &self.automation.lanes {
+            if lane.control_index != Some(control_index) {
+                continue;
+            }
+            js::midi_editor_schedule_automation(
+                &self.vc_id,
+                &lane.target.vc_id,
+                &lane.target.param_name,
+                &[cur_time],
+                &[lane.control_change_value(value)],
+            );
+        }
+    }
+
+    pub fn handle_automation_message(
+        &mut self,
+        grid_state: &GridState<usize>,
+        key: &str,
+        val: &[u8],
+    ) -> Option<Vec<u8>> {
+        match key {
+            "get_automation_lanes" => {
+                let info = AutomationLanesInfo {
+                    lanes: &self.automation.lanes,
+                    active_lane_ix: self.automation.active_lane_ix,
+                    learning_lane_ix: self.automation.learning_lane_ix,
+                    can_undo: self.automation.history.can_undo(),
+                    can_redo: self.automation.history.can_redo(),
+                };
+                return Some(
+                    serde_json::to_vec(&info).expect("Failed to serialize automation lanes"),
+                );
+            },
+            "add_automation_lane" => {
+                let lane: AutomationLane = match serde_json::from_slice(val) {
+                    Ok(lane) => lane,
+                    Err(err) => {
+                        error!("Error deserializing automation lane: {:?}", err);
+                        return None;
+                    },
+                };
+                self.automation.record_edit();
+                self.automation.lanes.push(lane);
+                self.automation.active_lane_ix = Some(self.automation.lanes.len() - 1);
+            },
+            "remove_automation_lane"
+            | "set_active_automation_lane"
+            | "learn_automation_cc"
+            | "clear_automation_cc" => {
+                assert_eq!(
+                    val.len(),
+                    1,
+                    "Message for \"{}\" must be a 1-byte index of the lane",
+                    key
+                );
+                let lane_ix = val[0] as usize;
+                if lane_ix >= self.automation.lanes.len() {
+                    error!("Automation lane index {} out of range", lane_ix);
+                    return None;
+                }
+
+                match key {
+                    "remove_automation_lane" => {
+                        self.automation.record_edit();
+                        self.automation.lanes.remove(lane_ix);
+                        self.automation.fix_active_lane_ix();
+                        self.automation.learning_lane_ix = None;
+                    },
+                    "learn_automation_cc" => self.automation.learning_lane_ix = Some(lane_ix),
+                    "clear_automation_cc" => {
+                        self.automation.record_edit();
+                        self.automation.lanes[lane_ix].control_index = None;
+                    },
+                    _ => self.automation.active_lane_ix = Some(lane_ix),
+                }
+            },
+            "undo_automation" => {
+                self.automation.undo();
+            },
+            "redo_automation" => {
+                self.automation.redo();
+            },
+            _ => return None,
+        }
+
+        self.render_automation_lane(grid_state);
+        None
+    }
+}
+
+fn snap_beat(grid_state: &GridState<usize>, x: usize) -> f64 {
+    grid_state.snap_to_beat_interval(x) as f64
+}
@@ -0,0 +1,583 @@
+//! Clips split a composition into multiple named patterns, each with its own notes.  The MIDI
+//! editor's grid edits one clip at a time, and clips are placed on a timeline by adding instances
+//! of them to the arrangement.  When arrangement playback is enabled, the transport plays the
+//! arrangement instead of just the clip being edited.
+//!
+//! Clips can also be launched from a clip launcher.  Launched clips loop until another clip is
+//! launched in their place or they're stopped, and they take priority over everything else that
+//! the transport would play.
+
+use common::RawNoteData;
+use uuid::Uuid;
+
+use super::*;
+use crate::helpers::{grid::skip_list::NoteEvent, undo::UndoHistory};
+
+/// Length of newly created clips and of the clip that's created for saves from before clips
+pub const DEFAULT_CLIP_LENGTH_BEATS: f64 = 16.;
+/// The beat at which launched clips stop looping.  They play until they're stopped, so this only
+/// needs to be further out than anyone will keep listening.
+pub const SESSION_END_BEAT: f64 = 1_000_000.;
+
+pub struct Clip {
+    pub id: Uuid,
+    pub name: String,
+    /// Length of the clip in beats.  Instances of the clip that are longer than this loop it.
+    pub length_beats: f64,
+    /// The notes of the clip.  The notes of the active clip are kept in the grid instead, so this
+    /// is empty for it.  The `data` of these notes is meaningless since they aren't rendered.
+    pub notes: NoteLines<usize>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializedClip {
+    pub id: Uuid,
+    pub name: String,
+    pub length_beats: f64,
+    /// Empty for the active clip since its notes are saved with the grid
+    #[serde(default)]
+    pub notes: Vec<RawNoteData>,
+}
+
+/// A placement of a clip on the arrangement's timeline
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClipInstance {
+    pub clip_id: Uuid,
+    pub start_beat: f64,
+    pub length_beats: f64,
+}
+
+impl ClipInstance {
+    pub fn end_beat(&self) -> f64 { self.start_beat + self.length_beats }
+}
+
+pub struct ClipState {
+    pub clips: Vec<Clip>,
+    /// Index of the clip that's being edited in the grid
+    pub active_clip_ix: usize,
+    pub arrangement: Vec<ClipInstance>,
+    /// If set, the transport plays the arrangement rather than just the active clip
+    pub play_arrangement: bool,
+    /// Instances of the clips that have been launched, in the order in which they play.  Each one
+    /// lasts until the next one starts.  This is cleared when the transport is stopped.
+    pub session: Vec<ClipInstance>,
+}
+
+/// Launches or stops a clip at the start of the next measure
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchClip {
+    /// The clip to launch, or `None` to stop the launched clip
+    pub clip_id: Option<Uuid>,
+    pub cur_time: f64,
+    /// If the transport is stopped, it's started at this time rather than `cur_time` so that the
+    /// clip can line up with ones already playing elsewhere
+    #[serde(default)]
+    pub start_time: Option<f64>,
+}
+
+/// The clips launched in a MIDI editor along with the position of its transport
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchedClips {
+    pub instances: Vec<ClipInstance>,
+    /// The beat that the transport is at, if it's playing
+    pub cur_beat: Option<f64>,
+    /// The time at which the next measure starts, if the transport is playing
+    pub next_measure_time: Option<f64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ClipInfo<'a> {
+    id: Uuid,
+    name: &'a str,
+    length_beats: f64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ClipsInfo<'a> {
+    clips: Vec<ClipInfo<'a>>,
+    active_clip_ix: usize,
+    arrangement: &'a [ClipInstance],
+    play_arrangement: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ClipUpdate {
+    clip_ix: usize,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    length_beats: Option<f64>,
+}
+
+fn build_note_lines(line_count: usize, raw_notes: Vec<RawNoteData>) -> NoteLines<usize> {
+    let mut notes = NoteLines::new(line_count);
+    for raw_note in raw_notes {
+        if raw_note.line_ix >= line_count {
+            warn!(
+                "Skipping clip note at line_ix {} since it's outside of the grid",
+                raw_note.line_ix
+            );
+            continue;
+        }
+        let insertion_error = notes.insert(raw_note.line_ix, NoteBox {
+            id: NoteId::next(),
+            data: 0,
+            bounds: NoteBoxBounds {
+                start_beat: raw_note.start_beat,
+                end_beat: raw_note.start_beat + raw_note.width,
+            },
+            velocity: raw_note.velocity,
+            pitch_bend: raw_note.pitch_bend,
+            expression: raw_note.expression,
+        });
+        if insertion_error.is_some() {
+            warn!("Skipping clip note that intersects another note");
+        }
+    }
+    notes
+}
+
+fn serialize_note_lines(notes: &NoteLines<usize>) -> Vec<RawNoteData> {
+    notes
+        .iter_all()
+        .map(|(line_ix, note)| RawNoteData {
+            line_ix,
+            start_beat: note.bounds.start_beat,
+            width: note.bounds.width(),
+            velocity: note.velocity,
+            pitch_bend: note.pitch_bend.clone(),
+            expression: note.expression.clone(),
+        })
+        .collect()
+}
+
+impl ClipState {
+    /// Builds the clip state from a save.  If there are no clips, a single one is created to hold
+    /// the notes that are in the grid.
+    pub fn new(
+        line_count: usize,
+        clips: Vec<SerializedClip>,
+        active_clip_ix: usize,
+        arrangement: Vec<ClipInstance>,
+        play_arrangement: bool,
+    ) -> Self {
+        let mut clips: Vec<Clip> = clips
+            .into_iter()
+            .map(|clip| Clip {
+                id: clip.id,
+                name: clip.name,
+                length_beats: clip.length_beats,
+                notes: build_note_lines(line_count, clip.notes),
+            })
+            .collect();
+        if clips.is_empty() {
+            clips.push(Clip {
+                id: uuid_v4(),
+                name: "Clip 1".into(),
+                length_beats: DEFAULT_CLIP_LENGTH_BEATS,
+                notes: NoteLines::new(line_count),
+            });
+        }
+
+        ClipState {
+            active_clip_ix: active_clip_ix.min(clips.len() - 1),
+            clips,
+            arrangement,
+            play_arrangement,
+            session: Vec::new(),
+        }
+    }
+
+    pub fn serialize(&self) -> Vec<SerializedClip> {
+        self.clips
+            .iter()
+            .map(|clip| SerializedClip {
+                id: clip.id,
+                name: clip.name.clone(),
+                length_beats: clip.length_beats,
+                notes: serialize_note_lines(&clip.notes),
+            })
+            .collect()
+    }
+
+    /// Returns the notes and length of the clip with the id `clip_id`, reading the notes of the
+    /// active clip out of the grid.
+    pub fn get_clip_notes<'a>(
+        &'a self,
+        grid_state: &'a GridState<usize>,
+        clip_id: Uuid,
+    ) -> Option<(&'a NoteLines<usize>, f64)> {
+        let clip_ix = self.clips.iter().position(|clip| clip.id == clip_id)?;
+        let clip = &self.clips[clip_ix];
+        if clip_ix == self.active_clip_ix {
+            Some((&grid_state.data, clip.length_beats))
+        } else {
+            Some((&clip.notes, clip.length_beats))
+        }
+    }
+
+    /// Returns the beat at which the last clip instance in the arrangement ends
+    pub fn arrangement_end_beat(&self) -> f64 {
+        self.arrangement
+            .iter()
+            .map(ClipInstance::end_beat)
+            .fold(0., f64::max)
+    }
+
+    pub fn is_playing_arrangement(&self) -> bool {
+        self.play_arrangement && !self.arrangement.is_empty()
+    }
+
+    pub fn is_playing_session(&self) -> bool { !self.session.is_empty() }
+
+    /// Returns the clip instances that the transport plays in place of the notes in the grid, if
+    /// any.  Launched clips take priority over the arrangement.
+    pub fn get_playing_instances(&self) -> Option<&[ClipInstance]> {
+        if self.is_playing_session() {
+            Some(&self.session)
+        } else if self.is_playing_arrangement() {
+            Some(&self.arrangement)
+        } else {
+            None
+        }
+    }
+}
+
+/// Cuts off all launched clips in `session` at `launch_beat`, dropping the ones that haven't
+/// started by then, and launches the clip with the id `clip_id` from there if it's set.
+pub fn launch_session_clip(
+    session: &mut Vec<ClipInstance>,
+    clip_id: Option<Uuid>,
+    launch_beat: f64,
+) {
+    session.retain(|instance| instance.start_beat < launch_beat);
+    for instance in session.iter_mut() {
+        instance.length_beats = instance.length_beats.min(launch_beat - instance.start_beat);
+    }
+    if let Some(clip_id) = clip_id {
+        session.push(ClipInstance {
+            clip_id,
+            start_beat: launch_beat,
+            length_beats: SESSION_END_BEAT - launch_beat,
+        });
+    }
+}
+
+/// An event of a note in an instance of a clip in the arrangement
+pub struct ArrangementEvent<'a> {
+    /// The notes of the clip that the event belongs to
+    pub notes: &'a NoteLines<usize>,
+    /// The beat of the arrangement at which the clip's beat 0 plays
+    pub offset_beats: f64,
+    /// The beat of the clip at which this instance of it is cut off
+    pub end_beat: f64,
+    /// The event with its beat relative to the start of the clip
+    pub event: NoteEvent,
+}
+
+impl<'a> ArrangementEvent<'a> {
+    /// The beat of the arrangement at which the event plays
+    pub fn beat(&self) -> f64 { self.offset_beats + self.event.beat as f64 }
+}
+
+/// Returns the events of all clip instances in `arrangement` that play within
+/// `[pass_start_beat, pass_end_beat)`, sorted by the beat at which they play.  Instances that are
+/// longer than their clip loop it, and notes that are still held at the end of an instance or of a
+/// loop of its clip are released there.  `get_clip` returns the notes and length of a clip.
+pub fn get_arrangement_pass_events<'a>(
+    arrangement: &[ClipInstance],
+    get_clip: impl Fn(Uuid) -> Option<(&'a NoteLines<usize>, f64)>,
+    pass_start_beat: f64,
+    pass_end_beat: f64,
+) -> Vec<ArrangementEvent<'a>> {
+    let mut events: Vec<ArrangementEvent<'a>> = Vec::new();
+    for instance in arrangement {
+        let (notes, clip_length_beats) = match get_clip(instance.clip_id) {
+            Some(clip) if clip.1 > 0. => clip,
+            _ => continue,
+        };
+
+        let mut loop_start_beat = instance.start_beat;
+        while loop_start_beat < instance.end_beat() && loop_start_beat <= pass_end_beat {
+            let loop_length_beats = clip_length_beats.min(instance.end_beat() - loop_start_beat);
+            if loop_start_beat + loop_length_beats <= pass_start_beat {
+                loop_start_beat += clip_length_beats;
+                continue;
+            }
+
+            let loop_events = scheduler::get_loop_pass_events(
+                notes.iter_events(None),
+                (0., loop_length_beats),
+                pass_start_beat - loop_start_beat,
+                pass_end_beat - loop_start_beat,
+            );
+            events.extend(loop_events.into_iter().map(|event| ArrangementEvent {
+                notes,
+                offset_beats: loop_start_beat,
+                end_beat: loop_length_beats,
+                event,
+            }));
+            loop_start_beat += clip_length_beats;
+        }
+    }
+
+    // Releases are placed before attacks on the same beat so that a note that ends right where
+    // another instance starts one on the same line doesn't cut it off
+    events.sort_by(|a, b| {
+        a.beat()
+            .partial_cmp(&b.beat())
+            .unwrap()
+            .then(a.event.is_start.cmp(&b.event.is_start))
+    });
+    events
+}
+
+impl MIDIEditorGridHandler {
+    /// Swaps the notes of the active clip out of the grid and the notes of the clip at `clip_ix`
+    /// into it.  The note edit history only applies to one clip, so it's cleared.
+    pub fn set_active_clip(&mut self, grid_state: &mut GridState<usize>, clip_ix: usize) {
+        if clip_ix >= self.clips.clips.len() || clip_ix == self.clips.active_clip_ix {
+            return;
+        }
+
+        grid_state.selected_notes.clear();
+        for (_, note) in grid_state.data.iter_all() {
+            js::delete_element(note.data);
+        }
+        let line_count = grid_state.data.lines.len();
+        let active_notes = std::mem::replace(&mut grid_state.data, NoteLines::new(line_count));
+        self.clips.clips[self.clips.active_clip_ix].notes = active_notes;
+        self.clips.active_clip_ix = clip_ix;
+
+        let clip_notes = std::mem::replace(
+            &mut self.clips.clips[clip_ix].notes,
+            NoteLines::new(line_count),
+        );
+        let conf = &grid_state.conf;
+        for (line_ix, note) in clip_notes.iter_all() {
+            let dom_id = MidiEditorGridRenderer::create_note(
+                conf.beats_to_px(note.bounds.start_beat),
+                conf.cursor_gutter_height + conf.padded_line_height() * line_ix,
+                conf.beats_to_px(note.bounds.width()),
+                conf.zoomed_line_height(),
+                None,
+            );
+            MidiEditorGridRenderer::set_note_velocity(dom_id, note.velocity);
+            let insertion_error = grid_state.data.insert(line_ix, NoteBox {
+                data: dom_id,
+                ..note.clone()
+            });
+            debug_assert!(insertion_error.is_none());
+        }
+        grid_state.note_history = UndoHistory::default();
+    }
+
+    /// Removes the clip at `clip_ix` along with all of its instances in the arrangement.  The last
+    /// remaining clip can't be deleted.
+    fn delete_clip(&mut self, grid_state: &mut GridState<usize>, clip_ix: usize) {
+        if self.clips.clips.len() <= 1 || clip_ix >= self.clips.clips.len() {
+            return;
+        }
+        if clip_ix == self.clips.active_clip_ix {
+            let new_active_clip_ix = if clip_ix == 0 { 1 } else { clip_ix - 1 };
+            self.set_active_clip(grid_state, new_active_clip_ix);
+        }
+
+        let clip = self.clips.clips.remove(clip_ix);
+        if self.clips.active_clip_ix > clip_ix {
+            self.clips.active_clip_ix -= 1;
+        }
+        self.clips
+            .arrangement
+            .retain(|instance| instance.clip_id != clip.id);
+        self.clips
+            .session
+            .retain(|instance| instance.clip_id != clip.id);
+    }
+
+    /// Launches a clip or stops the launched clip at the start of the next measure.  If the
+    /// transport is stopped, it's started from the beginning with the launched clip instead.
+    pub fn launch_clip(&mut self, grid_state: &mut GridState<usize>, launch: LaunchClip) {
+        if let Some(clip_id) = launch.clip_id {
+            if !self.clips.clips.iter().any(|clip| clip.id == clip_id) {
+                error!("Tried to launch clip with ID {} which doesn't exist", clip_id);
+                return;
+            }
+        }
+
+        let loop_handle = match self.loop_handle {
+            Some(loop_handle) => loop_handle,
+            None => {
+                let clip_id = match launch.clip_id {
+                    Some(clip_id) => clip_id,
+                    None => return,
+                };
+                self.clips.session.clear();
+                launch_session_clip(&mut self.clips.session, Some(clip_id), 0.);
+                self.set_cursor_pos_beats(grid_state, 0.);
+                let start_time = launch
+                    .start_time
+                    .unwrap_or(launch.cur_time)
+                    .max(launch.cur_time);
+                self.loop_handle = scheduler::init_scheduler_loop(start_time, 0., self, grid_state);
+                return;
+            },
+        };
+        if launch.clip_id.is_none() && !self.clips.is_playing_session() {
+            return;
+        }
+
+        let cur_beat = unsafe { (*loop_handle).get_cur_cursor_pos_beats(launch.cur_time) };
+        launch_session_clip(
+            &mut self.clips.session,
+            launch.clip_id,
+            self.tempo_map.next_measure_start(cur_beat),
+        );
+        self.maybe_reschedule_loop(launch.cur_time, self.tempo_map.clone());
+    }
+
+    fn get_launched_clips(&self) -> LaunchedClips {
+        let loop_handle = match self.loop_handle {
+            Some(loop_handle) => loop_handle,
+            None => return LaunchedClips::default(),
+        };
+
+        let cur_time = js::get_cur_audio_ctx_time();
+        let cur_beat = unsafe { (*loop_handle).get_cur_cursor_pos_beats(cur_time) };
+        let next_measure_beat = self.tempo_map.next_measure_start(cur_beat);
+        LaunchedClips {
+            instances: self.clips.session.clone(),
+            cur_beat: Some(cur_beat),
+            next_measure_time: Some(
+                cur_time
+                    + self
+                        .tempo_map
+                        .beats_to_seconds_from(cur_beat, next_measure_beat - cur_beat),
+            ),
+        }
+    }
+
+    pub fn handle_clip_message(
+        &mut self,
+        grid_state: &mut GridState<usize>,
+        key: &str,
+        val: &[u8],
+    ) -> Option<Vec<u8>> {
+        match key {
+            "get_clips" => {
+                let info = ClipsInfo {
+                    clips: self
+                        .clips
+                        .clips
+                        .iter()
+                        .map(|clip| ClipInfo {
+                            id: clip.id,
+                            name: &clip.name,
+                            length_beats: clip.length_beats,
+                        })
+                        .collect(),
+                    active_clip_ix: self.clips.active_clip_ix,
+                    arrangement: &self.clips.arrangement,
+                    play_arrangement: self.clips.play_arrangement,
+                };
+                return Some(serde_json::to_vec(&info).expect("Failed to serialize clips"));
+            },
+            "add_clip" => {
+                let name = String::from_utf8_lossy(val).into_owned();
+                self.clips.clips.push(Clip {
+                    id: uuid_v4(),
+                    name,
+                    length_beats: DEFAULT_CLIP_LENGTH_BEATS,
+                    notes: NoteLines::new(grid_state.data.lines.len()),
+                });
+                self.set_active_clip(grid_state, self.clips.clips.len() - 1);
+            },
+            "update_clip" => {
+                let update: ClipUpdate = match serde_json::from_slice(val) {
+                    Ok(update) => update,
+                    Err(err) => {
+                        error!("Error deserializing clip update: {:?}", err);
+                        return None;
+                    },
+                };
+                let clip = match self.clips.clips.get_mut(update.clip_ix) {
+                    Some(clip) => clip,
+                    None => {
+                        error!("Clip index {} out of range", update.clip_ix);
+                        return None;
+                    },
+                };
+                if let Some(name) = update.name {
+                    clip.name = name;
+                }
+                if let Some(length_beats) = update.length_beats.filter(|&length| length > 0.) {
+                    clip.length_beats = length_beats;
+                }
+            },
+            "delete_clip" | "set_active_clip" => {
+                assert_eq!(
+                    val.len(),
+                    1,
+                    "Message for \"{}\" must be a 1-byte index of the clip",
+                    key
+                );
+                let clip_ix = val[0] as usize;
+                if key == "delete_clip" {
+                    self.delete_clip(grid_state, clip_ix);
+                } else {
+                    self.set_active_clip(grid_state, clip_ix);
+                }
+            },
+            "set_arrangement" => {
+                let arrangement: Vec<ClipInstance> = match serde_json::from_slice(val) {
+                    Ok(arrangement) => arrangement,
+                    Err(err) => {
+                        error!("Error deserializing arrangement: {:?}", err);
+                        return None;
+                    },
+                };
+                let clips = &self.clips.clips;
+                let arrangement = arrangement
+                    .into_iter()
+                    .filter(|instance| {
+                        instance.length_beats > 0.
+                            && clips.iter().any(|clip| clip.id == instance.clip_id)
+                    })
+                    .collect();
+                self.clips.arrangement = arrangement;
+            },
+            "launch_clip" => {
+                match serde_json::from_slice(val) {
+                    Ok(launch) => self.launch_clip(grid_state, launch),
+                    Err(err) => error!("Error deserializing clip launch: {:?}", err),
+                }
+                return None;
+            },
+            "get_launched_clips" =>
+                return Some(
+                    serde_json::to_vec(&self.get_launched_clips())
+                        .expect("Failed to serialize launched clips"),
+                ),
+            "set_play_arrangement" => {
+                assert_eq!(
+                    val.len(),
+                    1,
+                    "Message for \"set_play_arrangement\" must be a single boolean byte"
+                );
+                self.clips.play_arrangement = val[0] != 0;
+            },
+            _ => return None,
+        }
+
+        // Clips that are playing may have changed
+        self.maybe_reschedule_loop(js::get_cur_audio_ctx_time(), self.tempo_map.clone());
+        None
+    }
+}
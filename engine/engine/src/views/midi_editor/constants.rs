@@ -12,9 +12,19 @@ pub const GRID_HEIGHT: usize = LINE_COUNT * PADDED_LINE_HEIGHT - 1;
 /// How long one beat is in pixels
 pub const MEASURE_COUNT: usize = 16;
 pub const BEATS_PER_MEASURE: usize = 4;
-pub const GRID_WIDTH: usize = 1000;
+/// Width of the visible part of the grid until the size of the page is reported
+pub const INITIAL_GRID_VIEWPORT_WIDTH: usize = 1000;
 pub const BEAT_LENGTH_PX: usize = 20;
 
+/// Default interval to which notes are snapped; it can be changed from the MIDI editor controls
 pub const NOTE_SNAP_BEAT_INTERVAL: f32 = 0.5;
 
 pub const BPM: f32 = 50.0;
+
+/// Space between the bottom of the grid and the velocity lane, and between the velocity lane and
+/// the automation lane
+pub const LANE_MARGIN_PX: usize = 8;
+pub const VELOCITY_LANE_HEIGHT_PX: usize = 64;
+/// Distance from the bottom of the grid to the top of the automation lane's margin
+pub const AUTOMATION_LANE_OFFSET_PX: usize = LANE_MARGIN_PX + VELOCITY_LANE_HEIGHT_PX;
+pub const AUTOMATION_LANE_HEIGHT_PX: usize = 100;
@@ -0,0 +1,107 @@
+//! Swing for the MIDI editor's transport.  Notes on off-beats are delayed by a fraction of the
+//! swung division as they're scheduled, leaving the notes in the grid untouched.  The groove can
+//! also be applied to the notes themselves, moving them to where they'd be played.
+
+use std::collections::HashSet;
+
+use super::*;
+
+/// Swing is capped below 1 so that the second half of each pair of divisions never collapses
+const MAX_SWING: f64 = 0.9;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GrooveConf {
+    /// How far off-beats are delayed as a fraction of `division`.  0 plays everything straight,
+    /// and 1/3 gives a triplet feel.
+    pub swing: f32,
+    /// Length of the swung division in beats.  Every other division starts on an off-beat.
+    pub division: f32,
+}
+
+impl Default for GrooveConf {
+    fn default() -> Self {
+        GrooveConf {
+            swing: 0.,
+            division: 0.5,
+        }
+    }
+}
+
+impl GrooveConf {
+    pub fn is_enabled(&self) -> bool { self.swing > 0. && self.division > 0. }
+
+    /// Returns the beat at which an event at `beat` is played with the groove applied.  Off-beats
+    /// are delayed by `swing * division` beats, and everything in between is stretched or squeezed
+    /// around them so that events keep their order.  On-beats aren't moved.
+    pub fn apply(&self, beat: f64) -> f64 {
+        if !self.is_enabled() {
+            return beat;
+        }
+
+        let swing = (self.swing as f64).min(MAX_SWING);
+        let division = self.division as f64;
+        let pair_start_beat = (beat / (division * 2.)).floor() * division * 2.;
+        let offset = beat - pair_start_beat;
+        let swung_offset = if offset < division {
+            offset * (1. + swing)
+        } else {
+            division * (1. + swing) + (offset - division) * (1. - swing)
+        };
+        pair_start_beat + swung_offset
+    }
+}
+
+impl MIDIEditorGridHandler {
+    /// Moves the start and end of every note to where they're played with the current groove and
+    /// then turns the groove off so that it isn't applied twice.  Since the groove never changes
+    /// the order of events, notes can't end up intersecting each other.
+    pub fn apply_groove(&mut self, grid_state: &mut GridState<usize>) {
+        if !self.groove.is_enabled() || grid_state.data.is_empty() {
+            return;
+        }
+
+        let notes_before_edit = grid_state.get_raw_note_data();
+        let selected_dom_ids: HashSet<DomId> = grid_state
+            .selected_notes
+            .drain()
+            .map(|note_data| note_data.dom_id)
+            .collect();
+        let notes: Vec<(usize, NoteBox<usize>)> = grid_state
+            .data
+            .iter_all()
+            .map(|(line_ix, note)| (line_ix, note.clone()))
+            .collect();
+        grid_state.data.clear();
+
+        for (line_ix, mut note) in notes {
+            let old_width = note.bounds.width();
+            note.bounds = NoteBoxBounds {
+                start_beat: self.groove.apply(note.bounds.start_beat as f64) as f32,
+                end_beat: self.groove.apply(note.bounds.end_beat as f64) as f32,
+            };
+            let new_width = note.bounds.width();
+            for point in &mut note.pitch_bend {
+                point.beat_offset *= new_width / old_width;
+            }
+
+            let conf = &grid_state.conf;
+            js::set_attr(
+                note.data,
+                "x",
+                &conf.beats_to_px(note.bounds.start_beat).to_string(),
+            );
+            js::set_attr(note.data, "width", &conf.beats_to_px(new_width).to_string());
+            if selected_dom_ids.contains(&note.data) {
+                grid_state
+                    .selected_notes
+                    .insert(SelectedNoteData::from_note_box(line_ix, &note));
+            }
+            let insertion_error = grid_state.data.insert(line_ix, note);
+            debug_assert!(insertion_error.is_none());
+        }
+
+        grid_state.note_history.record(&notes_before_edit);
+        self.groove.swing = 0.;
+    }
+}
@@ -0,0 +1,228 @@
+//! Commands that apply random changes to the selected notes.  Humanizing nudges the start, length,
+//! and velocity of each note by a small bounded amount so that they sound less mechanical, while
+//! randomizing picks entirely new values within the selection's span for generative workflows.
+
+use common::{pitch_bend::normalize_curve, RawNoteData};
+use rand::Rng;
+
+use super::*;
+
+/// Notes are never made shorter than this by humanizing them
+const MIN_HUMANIZED_NOTE_WIDTH_BEATS: f32 = 1. / 64.;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HumanizeConf {
+    /// The most that the start of a note is moved by in either direction, in beats
+    pub max_start_offset: f32,
+    /// The most that the length of a note is changed by in either direction, in beats
+    pub max_length_offset: f32,
+    /// The most that the velocity of a note is changed by in either direction
+    pub max_velocity_offset: u8,
+}
+
+impl Default for HumanizeConf {
+    fn default() -> Self {
+        HumanizeConf {
+            max_start_offset: 0.03,
+            max_length_offset: 0.03,
+            max_velocity_offset: 12,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RandomizeConf {
+    /// The shortest length in beats that a randomized note can have
+    pub min_length: f32,
+    /// The longest length in beats that a randomized note can have
+    pub max_length: f32,
+    pub min_velocity: u8,
+    pub max_velocity: u8,
+}
+
+impl Default for RandomizeConf {
+    fn default() -> Self {
+        RandomizeConf {
+            min_length: 0.25,
+            max_length: 1.,
+            min_velocity: 40,
+            max_velocity: MAX_NOTE_VELOCITY,
+        }
+    }
+}
+
+/// Returns a random value in `[-max, max)`, or 0 if `max` isn't positive
+fn random_offset<R: Rng>(rng: &mut R, max: f32) -> f32 {
+    if max <= 0. {
+        return 0.;
+    }
+    rng.gen_range(-max, max)
+}
+
+fn clamp_velocity(velocity: i16) -> u8 {
+    velocity.clamp(MIN_NOTE_VELOCITY as i16, MAX_NOTE_VELOCITY as i16) as u8
+}
+
+impl HumanizeConf {
+    /// Returns a copy of `note` with its start, length, and velocity randomly offset.  The note
+    /// is kept from starting before beat 0.
+    pub fn humanize<R: Rng>(&self, rng: &mut R, note: &RawNoteData) -> RawNoteData {
+        let max_velocity_offset = self.max_velocity_offset as i16;
+        let velocity_offset = rng.gen_range(-max_velocity_offset, max_velocity_offset + 1);
+
+        RawNoteData {
+            line_ix: note.line_ix,
+            start_beat: (note.start_beat + random_offset(rng, self.max_start_offset)).max(0.),
+            width: (note.width + random_offset(rng, self.max_length_offset))
+                .max(MIN_HUMANIZED_NOTE_WIDTH_BEATS),
+            velocity: clamp_velocity(note.velocity as i16 + velocity_offset),
+            pitch_bend: note.pitch_bend.clone(),
+            expression: note.expression.clone(),
+        }
+    }
+}
+
+impl RandomizeConf {
+    /// Returns a copy of `note` with a random start in `[span_start_beat, span_end_beat)` and a
+    /// random length and velocity within the configured ranges.  If `snap_interval` is positive,
+    /// the start and length are multiples of it.
+    pub fn randomize<R: Rng>(
+        &self,
+        rng: &mut R,
+        note: &RawNoteData,
+        span_start_beat: f32,
+        span_end_beat: f32,
+        snap_interval: f32,
+    ) -> RawNoteData {
+        let span_width = (span_end_beat - span_start_beat).max(0.);
+        let min_length = self.min_length.min(self.max_length);
+        let max_length = self.min_length.max(self.max_length);
+        let mut width = min_length + rng.gen::<f32>() * (max_length - min_length);
+        let start_beat = if snap_interval > 0. {
+            let slot_count = ((span_width / snap_interval).ceil() as usize).max(1);
+            width = ((width / snap_interval).round() * snap_interval).max(snap_interval);
+            span_start_beat + rng.gen_range(0, slot_count) as f32 * snap_interval
+        } else {
+            span_start_beat + rng.gen::<f32>() * span_width
+        };
+        let min_velocity = self.min_velocity.min(self.max_velocity);
+        let max_velocity = self.min_velocity.max(self.max_velocity);
+
+        RawNoteData {
+            line_ix: note.line_ix,
+            start_beat,
+            width: width.max(MIN_HUMANIZED_NOTE_WIDTH_BEATS),
+            velocity: clamp_velocity(rng.gen_range(min_velocity as i16, max_velocity as i16 + 1)),
+            pitch_bend: note.pitch_bend.clone(),
+            expression: note.expression.clone(),
+        }
+    }
+}
+
+impl MIDIEditorGridHandler {
+    pub fn humanize_selected_notes(&mut self, grid_state: &mut GridState<usize>) {
+        let conf = self.humanize;
+        self.transform_selected_notes(grid_state, |note| with_rng(|rng| conf.humanize(rng, note)));
+    }
+
+    /// Randomizes the selected notes within the span of beats that they currently cover
+    pub fn randomize_selected_notes(&mut self, grid_state: &mut GridState<usize>) {
+        let span_start_beat = grid_state
+            .selected_notes
+            .iter()
+            .map(|note| note.start_beat)
+            .fold(f32::INFINITY, f32::min);
+        let span_end_beat = grid_state
+            .selected_notes
+            .iter()
+            .map(|note| note.start_beat + note.width)
+            .fold(0., f32::max);
+        let snap_interval = grid_state.snap_beat_interval();
+        let conf = self.randomize;
+        self.transform_selected_notes(grid_state, |note| {
+            with_rng(|rng| {
+                conf.randomize(rng, note, span_start_beat, span_end_beat, snap_interval)
+            })
+        });
+    }
+
+    /// Moves, resizes, and sets the velocity of each of the selected notes to those of the note
+    /// returned by `transform`.  Notes that would intersect another note are left as they were.
+    /// All of the changes are undone together.
+    fn transform_selected_notes(
+        &mut self,
+        grid_state: &mut GridState<usize>,
+        mut transform: impl FnMut(&RawNoteData) -> RawNoteData,
+    ) {
+        let mut selection: Vec<SelectedNoteData> = grid_state.selected_notes.drain().collect();
+        selection.sort_unstable();
+        let notes_before_edit = grid_state.get_raw_note_data();
+        let mut changed = false;
+
+        for note_data in selection {
+            let mut note = grid_state
+                .data
+                .remove(note_data.line_ix, note_data.start_beat)
+                .expect("Selected note wasn't found");
+            let original_bounds = note.bounds;
+            let original_velocity = note.velocity;
+            let original_pitch_bend = note.pitch_bend.clone();
+            let original_expression = note.expression.clone();
+
+            let new_note = transform(&RawNoteData {
+                line_ix: note_data.line_ix,
+                start_beat: note_data.start_beat,
+                width: note_data.width,
+                velocity: note_data.velocity,
+                pitch_bend: note.pitch_bend.clone(),
+                expression: note.expression.clone(),
+            });
+            note.bounds = NoteBoxBounds {
+                start_beat: new_note.start_beat,
+                end_beat: new_note.start_beat + new_note.width,
+            };
+            note.velocity = new_note.velocity;
+            normalize_curve(&mut note.pitch_bend, new_note.width);
+            note.expression.normalize(new_note.width);
+            let new_note_data = SelectedNoteData::from_note_box(note_data.line_ix, &note);
+
+            match grid_state.data.insert(note_data.line_ix, note) {
+                None => {
+                    changed = true;
+                    let conf = &grid_state.conf;
+                    js::set_attr(
+                        note_data.dom_id,
+                        "x",
+                        &conf.beats_to_px(new_note_data.start_beat).to_string(),
+                    );
+                    js::set_attr(
+                        note_data.dom_id,
+                        "width",
+                        &conf.beats_to_px(new_note_data.width).to_string(),
+                    );
+                    MidiEditorGridRenderer::set_note_velocity(
+                        note_data.dom_id,
+                        new_note_data.velocity,
+                    );
+                    grid_state.selected_notes.insert(new_note_data);
+                },
+                Some(mut rejected_note) => {
+                    rejected_note.bounds = original_bounds;
+                    rejected_note.velocity = original_velocity;
+                    rejected_note.pitch_bend = original_pitch_bend;
+                    rejected_note.expression = original_expression;
+                    let reinsertion_error =
+                        grid_state.data.insert(note_data.line_ix, rejected_note);
+                    debug_assert!(reinsertion_error.is_none());
+                    grid_state.selected_notes.insert(note_data);
+                },
+            }
+        }
+
+        if changed {
+            grid_state.note_history.record(&notes_before_edit);
+        }
+    }
+}
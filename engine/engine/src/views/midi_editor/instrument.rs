@@ -0,0 +1,147 @@
+//! Each MIDI editor can be assigned an instrument that its notes (including the notes of all of its
+//! clips) are played on.  Assigning an instrument connects the MIDI editor's MIDI output to the
+//! instrument's MIDI input in the patch network, replacing the connection to the previously
+//! assigned instrument.  The assignment is saved with the MIDI editor so that it's kept across
+//! reloads and exported projects.
+
+use super::*;
+use crate::view_context::manager::{
+    ConnectionDescriptor, ForeignConnectable, MinimalViewContextDefinition,
+};
+
+/// Name of the MIDI editor output that is connected to the assigned instrument
+const MIDI_EDITOR_OUTPUT_NAME: &str = "midi_output";
+/// Name of the MIDI input of all instruments
+const INSTRUMENT_INPUT_NAME: &str = "midi";
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstrumentKind {
+    /// A synth designer VC
+    SubtractiveSynth,
+    /// An FM synth node in the graph editor
+    FmSynth,
+    /// A sampler node in the graph editor
+    Sampler,
+    /// A SoundFont player node in the graph editor
+    SoundFont,
+}
+
+impl InstrumentKind {
+    /// Returns the kind of instrument that a VC with the provided name is, if any
+    fn from_vc_name(name: &str) -> Option<Self> {
+        match name {
+            "synth_designer" => Some(InstrumentKind::SubtractiveSynth),
+            _ => None,
+        }
+    }
+
+    /// Returns the kind of instrument that a foreign connectable with the provided type is, if any
+    fn from_foreign_connectable_type(_type: &str) -> Option<Self> {
+        match _type {
+            "customAudio/fmSynth" => Some(InstrumentKind::FmSynth),
+            "customAudio/sampler" => Some(InstrumentKind::Sampler),
+            "customAudio/soundFont" => Some(InstrumentKind::SoundFont),
+            _ => None,
+        }
+    }
+}
+
+/// Identifies the instrument that a MIDI editor plays on.  `id` is the VC ID of synth designers and
+/// the ID of the foreign connectable for the others.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InstrumentAssignment {
+    pub kind: InstrumentKind,
+    pub id: String,
+}
+
+impl InstrumentAssignment {
+    /// The patch network connection between the MIDI editor with the provided VC ID and the
+    /// instrument
+    pub fn connection(
+        &self,
+        midi_editor_vc_id: &str,
+    ) -> (ConnectionDescriptor, ConnectionDescriptor) {
+        let from = ConnectionDescriptor {
+            vc_id: midi_editor_vc_id.into(),
+            name: MIDI_EDITOR_OUTPUT_NAME.into(),
+        };
+        let to = ConnectionDescriptor {
+            vc_id: self.id.clone(),
+            name: INSTRUMENT_INPUT_NAME.into(),
+        };
+        (from, to)
+    }
+}
+
+/// An instrument that exists in the current workspace and can be assigned to a MIDI editor
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AvailableInstrument {
+    #[serde(flatten)]
+    pub assignment: InstrumentAssignment,
+    pub title: Option<String>,
+}
+
+/// Lists all of the instruments in the workspace, with synth designers first
+pub fn list_instruments(
+    vcs: &[MinimalViewContextDefinition],
+    foreign_connectables: &[ForeignConnectable],
+) -> Vec<AvailableInstrument> {
+    let vc_instruments = vcs.iter().filter_map(|def| {
+        InstrumentKind::from_vc_name(&def.name).map(|kind| AvailableInstrument {
+            assignment: InstrumentAssignment {
+                kind,
+                id: def.uuid.to_string(),
+            },
+            title: def.title.clone(),
+        })
+    });
+    let foreign_instruments = foreign_connectables.iter().filter_map(|fc| {
+        InstrumentKind::from_foreign_connectable_type(&fc._type).map(|kind| AvailableInstrument {
+            assignment: InstrumentAssignment {
+                kind,
+                id: fc.id.clone(),
+            },
+            title: None,
+        })
+    });
+    vc_instruments.chain(foreign_instruments).collect()
+}
+
+pub(super) fn get_workspace_instruments() -> Vec<AvailableInstrument> {
+    let vcm = get_vcm();
+    let vcs: Vec<_> = vcm
+        .contexts
+        .iter()
+        .map(|entry| entry.definition.clone())
+        .collect();
+    list_instruments(&vcs, &vcm.foreign_connectables)
+}
+
+impl MIDIEditorGridHandler {
+    /// Assigns the instrument that the MIDI editor plays on, moving its MIDI output connection from
+    /// the previous instrument to the new one.  `None` unassigns the current instrument.
+    pub(super) fn set_instrument(&mut self, instrument: Option<InstrumentAssignment>) {
+        if let Some(new_instrument) = &instrument {
+            let exists = get_workspace_instruments()
+                .iter()
+                .any(|available| &available.assignment == new_instrument);
+            if !exists {
+                error!("Tried to assign missing instrument: {:?}", new_instrument);
+                return;
+            }
+        }
+
+        if let Some(old_instrument) = self.instrument.take() {
+            // Notes that are still playing would otherwise be held forever
+            js::midi_editor_cancel_all_events(&self.vc_id, true);
+            let (from, to) = old_instrument.connection(&self.vc_id);
+            js::disconnect_midi_editor_instrument(&from.vc_id, &from.name, &to.vc_id, &to.name);
+        }
+        if let Some(new_instrument) = &instrument {
+            let (from, to) = new_instrument.connection(&self.vc_id);
+            js::connect_midi_editor_instrument(&from.vc_id, &from.name, &to.vc_id, &to.name);
+        }
+        self.instrument = instrument;
+    }
+}
@@ -0,0 +1,113 @@
+//! Plays the MIDI editor's instrument with the computer keyboard for users without a MIDI
+//! controller.  The middle row of letter keys plays the white keys of a piano starting at C and the
+//! row above it plays the black keys, spanning an octave and a half.  "z" and "x" shift the played
+//! notes down and up by an octave.
+//!
+//! Notes are sent through the same path as live MIDI input, so they're recorded, arpeggiated, and
+//! sent to the MIDI output just like notes played on a controller.
+
+use super::midi_input::MIDIInputEvent;
+
+/// The keys that play each semitone, starting from the C of the current octave
+const PIANO_KEYS: &[&str] = &[
+    "a", "w", "s", "e", "d", "f", "t", "g", "y", "h", "u", "j", "k", "o", "l", "p", ";", "'",
+];
+const OCTAVE_DOWN_KEY: &str = "z";
+const OCTAVE_UP_KEY: &str = "x";
+
+/// Note ID of the C played by the first piano key before the octave is shifted
+pub const DEFAULT_BASE_NOTE_ID: usize = 60;
+const MAX_NOTE_ID: usize = 127;
+pub const KEYBOARD_PIANO_VELOCITY: u8 = 100;
+
+pub struct KeyboardPiano {
+    pub enabled: bool,
+    /// Note ID of the C played by the first piano key
+    base_note_id: usize,
+    /// Keys that are currently held and the notes that they're playing.  Notes are released based
+    /// on this so that shifting the octave while holding a key doesn't leave its note stuck.
+    held_keys: Vec<(String, usize)>,
+}
+
+impl Default for KeyboardPiano {
+    fn default() -> Self {
+        KeyboardPiano {
+            enabled: false,
+            base_note_id: DEFAULT_BASE_NOTE_ID,
+            held_keys: Vec::new(),
+        }
+    }
+}
+
+/// Letters are reported as uppercase while shift is held
+fn normalize_key(key: &str) -> String { key.to_lowercase() }
+
+impl KeyboardPiano {
+    /// Handles a key being pressed.  Returns `None` if the key isn't handled by the piano and
+    /// should be handled normally, otherwise the notes that should be played.
+    pub fn key_down(&mut self, key: &str) -> Option<Vec<MIDIInputEvent>> {
+        if !self.enabled {
+            return None;
+        }
+        let key = normalize_key(key);
+
+        match key.as_str() {
+            OCTAVE_DOWN_KEY => {
+                self.base_note_id = self.base_note_id.saturating_sub(12);
+                return Some(Vec::new());
+            },
+            OCTAVE_UP_KEY => {
+                if self.base_note_id + 12 <= MAX_NOTE_ID {
+                    self.base_note_id += 12;
+                }
+                return Some(Vec::new());
+            },
+            _ => (),
+        }
+
+        let semitone = PIANO_KEYS.iter().position(|&piano_key| piano_key == key)?;
+        // Holding a key down repeatedly fires key down events
+        if self.held_keys.iter().any(|(held_key, _)| *held_key == key) {
+            return Some(Vec::new());
+        }
+        let note_id = self.base_note_id + semitone;
+        if note_id > MAX_NOTE_ID {
+            return Some(Vec::new());
+        }
+
+        self.held_keys.push((key, note_id));
+        Some(vec![MIDIInputEvent::NoteOn {
+            note_id,
+            velocity: KEYBOARD_PIANO_VELOCITY,
+        }])
+    }
+
+    /// Handles a key being released, returning the note that should be released if it was playing
+    /// one.  This works even if the piano has been disabled since the key was pressed.
+    pub fn key_up(&mut self, key: &str) -> Option<MIDIInputEvent> {
+        let key = normalize_key(key);
+        let ix = self
+            .held_keys
+            .iter()
+            .position(|(held_key, _)| *held_key == key)?;
+        let (_, note_id) = self.held_keys.remove(ix);
+        Some(MIDIInputEvent::NoteOff { note_id })
+    }
+
+    /// Returns `true` if `key` is one of the keys that the piano handles while enabled
+    pub fn handles_key(&self, key: &str) -> bool {
+        let key = normalize_key(key);
+        self.enabled
+            && (key == OCTAVE_DOWN_KEY
+                || key == OCTAVE_UP_KEY
+                || PIANO_KEYS.contains(&key.as_str()))
+    }
+
+    /// Releases all held keys, returning the notes that should be released
+    pub fn release_all(&mut self) -> Vec<MIDIInputEvent> {
+        self.held_keys
+            .drain(..)
+            .map(|(_, note_id)| MIDIInputEvent::NoteOff { note_id })
+            .collect()
+    }
+}
@@ -0,0 +1,196 @@
+//! Live MIDI input for the MIDI editor.  Raw MIDI messages are pushed in from JS (from Web MIDI
+//! devices or other MIDI nodes connected to the editor's input) and played through the editor's
+//! instrument right away.  If MIDI is being recorded, notes are also recorded into the grid with
+//! their start and end beats quantized to the grid's snap interval.  If MIDI output is enabled,
+//! input is passed through to the output port as well.  If the live arpeggiator is enabled, the
+//! notes that are held are arpeggiated instead of being played directly.  Control changes from
+//! controllers that are bound to automation lanes set the lanes' parameters and are recorded into
+//! the lanes while MIDI is being recorded.  Per-note expression from MPE controllers is routed to
+//! the voices playing the notes and recorded into the notes' expression curves.
+
+use common::expression::ExpressionDimension;
+
+use super::*;
+
+pub const STATUS_NOTE_OFF: u8 = 0x80;
+pub const STATUS_NOTE_ON: u8 = 0x90;
+pub const STATUS_CONTROL_CHANGE: u8 = 0xB0;
+pub const STATUS_CHANNEL_PRESSURE: u8 = 0xD0;
+pub const STATUS_PITCH_BEND: u8 = 0xE0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MIDIInputEvent {
+    NoteOn {
+        note_id: usize,
+        velocity: u8,
+    },
+    NoteOff {
+        note_id: usize,
+    },
+    ControlChange {
+        control_index: u8,
+        value: u8,
+    },
+    /// Expression of a single held note from an MPE controller.  Pitch bends are in semitones
+    /// while pressure and timbre are in the range [0, 1].
+    NoteExpression {
+        note_id: usize,
+        dimension: ExpressionDimension,
+        value: f32,
+    },
+}
+
+impl MIDIInputEvent {
+    /// Parses a raw 3-byte MIDI message, ignoring its channel.  Note on messages with a velocity of
+    /// zero are treated as note off messages as per the MIDI spec.  Returns `None` for malformed
+    /// messages and message types that aren't supported.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 3 || bytes[1] > 127 || bytes[2] > 127 {
+            return None;
+        }
+        let (status, data_1, data_2) = (bytes[0], bytes[1], bytes[2]);
+
+        match status & 0xF0 {
+            STATUS_NOTE_ON if data_2 > 0 => Some(MIDIInputEvent::NoteOn {
+                note_id: data_1 as usize,
+                velocity: data_2,
+            }),
+            STATUS_NOTE_ON | STATUS_NOTE_OFF => Some(MIDIInputEvent::NoteOff {
+                note_id: data_1 as usize,
+            }),
+            STATUS_CONTROL_CHANGE => Some(MIDIInputEvent::ControlChange {
+                control_index: data_1,
+                value: data_2,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Rounds `beat` to the nearest multiple of `interval`.  An interval of zero disables quantization.
+pub fn quantize_beat(beat: f64, interval: f64) -> f64 {
+    if interval <= 0. {
+        return beat;
+    }
+
+    (beat / interval).round() * interval
+}
+
+/// Quantizes the start and end of a recorded note to `interval`, making sure that notes too short
+/// to span an interval don't get quantized away entirely.
+pub fn quantize_note_bounds(start_beat: f64, end_beat: f64, interval: f64) -> (f64, f64) {
+    let start_beat = quantize_beat(start_beat, interval);
+    let end_beat = quantize_beat(end_beat, interval);
+    if interval > 0. && end_beat <= start_beat {
+        return (start_beat, start_beat + interval);
+    }
+
+    (start_beat, end_beat)
+}
+
+impl MIDIEditorGridHandler {
+    /// Stops playing a note started by live input without recording its release
+    pub fn release_live_note(&mut self, note_id: usize) {
+        if self.arpeggiator.live {
+            self.arpeggiator_note_off(note_id);
+        } else {
+            js::midi_editor_trigger_release(&self.vc_id, note_id);
+            self.queue_midi_output_notes(std::iter::once((0., note_id, 0, false)));
+            self.flush_midi_output();
+        }
+    }
+
+    pub fn handle_midi_input(&mut self, cur_time: f64, event: MIDIInputEvent) {
+        match event {
+            MIDIInputEvent::NoteOn { note_id, velocity } => {
+                if self.arpeggiator.live {
+                    self.arpeggiator_note_on(cur_time, note_id, velocity);
+                } else {
+                    js::midi_editor_trigger_attack_with_velocity(
+                        &self.vc_id,
+                        note_id,
+                        None,
+                        velocity,
+                    );
+                    self.queue_midi_output_notes(std::iter::once((0., note_id, velocity, true)));
+                    self.flush_midi_output();
+                }
+                get_vcm().emit(EngineEvent::NoteTriggered {
+                    vc_id: self.vc_id.clone(),
+                    note_id,
+                    velocity,
+                });
+                if !is_recordable_note(&self.note_layout, note_id) {
+                    return;
+                }
+                if let Some(recording_ctx_ptr) = self.midi_recording_ctx {
+                    midi_recording::midi_editor_record_note_down(
+                        recording_ctx_ptr,
+                        cur_time,
+                        note_id,
+                        velocity,
+                    );
+                }
+            },
+            MIDIInputEvent::NoteOff { note_id } => {
+                self.release_live_note(note_id);
+                if !is_recordable_note(&self.note_layout, note_id) {
+                    return;
+                }
+                if let Some(recording_ctx_ptr) = self.midi_recording_ctx {
+                    midi_recording::midi_editor_record_note_up(
+                        recording_ctx_ptr,
+                        cur_time,
+                        note_id,
+                    );
+                }
+            },
+            MIDIInputEvent::ControlChange {
+                control_index,
+                value,
+            } => {
+                js::midi_editor_forward_control_change(&self.vc_id, control_index, value);
+                self.queue_midi_output_control_change(0., control_index, value);
+                self.flush_midi_output();
+                self.handle_automation_control_change(cur_time, control_index, value);
+                if let Some(recording_ctx_ptr) = self.midi_recording_ctx {
+                    midi_recording::midi_editor_record_control_change(
+                        recording_ctx_ptr,
+                        cur_time,
+                        control_index,
+                        value,
+                    );
+                }
+            },
+            MIDIInputEvent::NoteExpression {
+                note_id,
+                dimension,
+                value,
+            } => {
+                // Arpeggiated notes aren't played by the voices of the held notes.  Expression
+                // isn't sent to MIDI output since notes are output without their MPE channels.
+                if self.arpeggiator.live {
+                    return;
+                }
+                js::midi_editor_set_note_expression(&self.vc_id, note_id, dimension as u8, value);
+                if !is_recordable_note(&self.note_layout, note_id) {
+                    return;
+                }
+                if let Some(recording_ctx_ptr) = self.midi_recording_ctx {
+                    midi_recording::record_note_expression(
+                        recording_ctx_ptr,
+                        cur_time,
+                        note_id,
+                        dimension,
+                        value,
+                    );
+                }
+            },
+        }
+    }
+}
+
+/// Notes outside of the range of the grid are still played, but they can't be recorded.
+fn is_recordable_note(note_layout: &NoteLayout, note_id: usize) -> bool {
+    note_layout.note_line(note_id).is_some()
+}
@@ -0,0 +1,140 @@
+//! Outbound MIDI for sequencing external hardware.  When MIDI output is enabled, the notes that the
+//! MIDI editor plays are also queued up as timestamped raw MIDI messages that are flushed to JS,
+//! which forwards them to the selected Web MIDI output port.
+
+use super::*;
+
+const STATUS_NOTE_OFF: u8 = 0x80;
+const STATUS_NOTE_ON: u8 = 0x90;
+const STATUS_CONTROL_CHANGE: u8 = 0xB0;
+const CONTROL_ALL_NOTES_OFF: u8 = 123;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MIDIOutputConf {
+    pub enabled: bool,
+    /// Name of the Web MIDI output port to send to.  Nothing is sent if this is `None`.
+    pub port_name: Option<String>,
+    /// MIDI channel to send on, from 0 to 15
+    pub channel: u8,
+}
+
+/// Raw MIDI messages waiting to be sent along with the audio context times at which to send them
+#[derive(Default)]
+pub struct MIDIOutputQueue {
+    /// Flattened 3-byte MIDI messages
+    pub messages: Vec<u8>,
+    pub timings: Vec<f64>,
+}
+
+impl MIDIOutputQueue {
+    pub fn len(&self) -> usize { self.timings.len() }
+
+    pub fn is_empty(&self) -> bool { self.timings.is_empty() }
+
+    fn push(&mut self, time: f64, status: u8, channel: u8, data_1: u8, data_2: u8) {
+        self.messages
+            .extend_from_slice(&[status | (channel & 0x0F), data_1 & 0x7F, data_2 & 0x7F]);
+        self.timings.push(time);
+    }
+
+    pub fn push_note_on(&mut self, time: f64, channel: u8, note_id: usize, velocity: u8) {
+        // A velocity of 0 would be interpreted as a note off
+        let velocity = velocity.max(1);
+        self.push(time, STATUS_NOTE_ON, channel, note_id as u8, velocity);
+    }
+
+    pub fn push_note_off(&mut self, time: f64, channel: u8, note_id: usize) {
+        self.push(time, STATUS_NOTE_OFF, channel, note_id as u8, 0);
+    }
+
+    pub fn push_control_change(&mut self, time: f64, channel: u8, control_index: u8, value: u8) {
+        self.push(time, STATUS_CONTROL_CHANGE, channel, control_index, value);
+    }
+
+    pub fn push_all_notes_off(&mut self, time: f64, channel: u8) {
+        self.push_control_change(time, channel, CONTROL_ALL_NOTES_OFF, 0);
+    }
+
+    pub fn clear(&mut self) {
+        self.messages.clear();
+        self.timings.clear();
+    }
+}
+
+impl MIDIEditorGridHandler {
+    fn midi_output_port(&self) -> Option<&str> {
+        if !self.midi_output.enabled {
+            return None;
+        }
+        self.midi_output.port_name.as_deref()
+    }
+
+    /// Queues note events for MIDI output if it's enabled.  Events are `(time, note_id, velocity,
+    /// is_attack)`.  Notes that can't be represented in MIDI are skipped.
+    pub fn queue_midi_output_notes(
+        &mut self,
+        events: impl Iterator<Item = (f64, usize, u8, bool)>,
+    ) {
+        if self.midi_output_port().is_none() {
+            return;
+        }
+
+        let channel = self.midi_output.channel;
+        for (time, note_id, velocity, is_attack) in events {
+            if note_id > 127 {
+                continue;
+            }
+
+            if is_attack {
+                self.midi_output_queue
+                    .push_note_on(time, channel, note_id, velocity);
+            } else {
+                self.midi_output_queue.push_note_off(time, channel, note_id);
+            }
+        }
+    }
+
+    /// Queues a control change message for MIDI output if it's enabled.
+    pub fn queue_midi_output_control_change(&mut self, time: f64, control_index: u8, value: u8) {
+        if self.midi_output_port().is_none() {
+            return;
+        }
+
+        let channel = self.midi_output.channel;
+        self.midi_output_queue
+            .push_control_change(time, channel, control_index, value);
+    }
+
+    /// Sends all queued MIDI output messages to JS to be forwarded to the output port.
+    pub fn flush_midi_output(&mut self) {
+        if self.midi_output_queue.is_empty() {
+            return;
+        }
+
+        if let Some(port_name) = self.midi_output_port() {
+            js::midi_editor_send_midi_output(
+                &self.vc_id,
+                port_name,
+                &self.midi_output_queue.messages,
+                &self.midi_output_queue.timings,
+            );
+        }
+        self.midi_output_queue.clear();
+    }
+
+    /// Drops any messages that have been sent but not yet played by the output port and silences
+    /// all notes on the output channel so that nothing hangs on the external instrument.
+    pub fn cancel_midi_output(&mut self) {
+        self.midi_output_queue.clear();
+        let port_name = match self.midi_output_port() {
+            Some(port_name) => port_name,
+            None => return,
+        };
+
+        js::midi_editor_cancel_midi_output(&self.vc_id, port_name);
+        let channel = self.midi_output.channel;
+        self.midi_output_queue.push_all_notes_off(0., channel);
+        self.flush_midi_output();
+    }
+}
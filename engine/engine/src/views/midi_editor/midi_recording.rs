@@ -1,20 +1,31 @@
+use common::expression::ExpressionDimension;
+use fnv::FnvHashMap;
 use wasm_bindgen::prelude::*;
 
-use super::*;
+use super::{mpe::RecordedExpression, *};
+
+/// Metronome clicks are scheduled this far ahead of the current time while recording
+const METRONOME_LOOKAHEAD_SECONDS: f64 = 0.2;
 
 #[derive(Clone, Copy)]
 pub struct ActiveVoice {
     pub playing_start_time_seconds: f64,
     pub note_id: usize,
     pub dom_id: DomId,
+    pub velocity: u8,
 }
 
 pub struct MIDIRecordingContext {
     pub initial_cursor_pos_beats: f64,
+    /// The time at which recording actually starts, after any count-in
     pub start_time_seconds: f64,
+    /// The beat up to which metronome clicks have been scheduled
+    pub metronome_scheduled_through_beat: f64,
     pub state: &'static mut MIDIEditorGridHandler,
     pub grid_state: &'static mut GridState<usize>,
     pub active_voices: [Option<ActiveVoice>; 32],
+    /// Expression recorded from MPE input for each of the active voices, keyed by note id
+    pub recorded_expressions: FnvHashMap<usize, RecordedExpression>,
     pub animation_cb: Closure<(dyn std::ops::FnMut(f64) + 'static)>,
     pub animation_loop_handle: usize,
 }
@@ -28,14 +39,55 @@ impl MIDIRecordingContext {
         MIDIRecordingContext {
             initial_cursor_pos_beats: grid_state.cursor_pos_beats as f64,
             start_time_seconds,
+            metronome_scheduled_through_beat: grid_state.cursor_pos_beats as f64,
             // We assume that the underlying MIDI editor doesn't get destroyed while this exists...
             state: unsafe { std::mem::transmute(state) },
             grid_state: unsafe { std::mem::transmute(grid_state) },
             active_voices: [None; 32],
-            animation_cb: Closure::new(|_| {}),
+            recorded_expressions: FnvHashMap::default(),
+            animation_cb: Closure::wrap(Box::new(|_| {}) as Box<dyn FnMut(f64)>),
             animation_loop_handle: 0,
         }
     }
+
+    /// Returns the beat in the composition that plays at `time_seconds`, following the tempo map
+    /// from where recording started.  Times during the count-in map to the starting beat.
+    fn time_to_beat(&self, time_seconds: f64) -> f64 {
+        self.initial_cursor_pos_beats
+            + self.state.tempo_map.seconds_to_beats_from(
+                self.initial_cursor_pos_beats,
+                (time_seconds - self.start_time_seconds).max(0.),
+            )
+    }
+
+    fn beat_to_time(&self, beat: f64) -> f64 {
+        self.start_time_seconds
+            + self.state.tempo_map.beats_to_seconds_from(
+                self.initial_cursor_pos_beats,
+                beat - self.initial_cursor_pos_beats,
+            )
+    }
+
+    /// Schedules metronome clicks through a short window ahead of `cur_time`.  If the transport
+    /// is playing, its scheduler already handles the clicks.
+    fn schedule_metronome_clicks(&mut self, cur_time: f64) {
+        if self.state.loop_handle.is_some() {
+            return;
+        }
+
+        let end_beat = self.time_to_beat(cur_time + METRONOME_LOOKAHEAD_SECONDS);
+        let clicks = metronome::get_clicks(
+            &self.state.tempo_map,
+            self.metronome_scheduled_through_beat,
+            end_beat,
+        );
+        self.state.schedule_metronome_clicks(
+            clicks
+                .into_iter()
+                .map(|click| (self.beat_to_time(click.beat), click.is_accent)),
+        );
+        self.metronome_scheduled_through_beat = end_beat.max(self.metronome_scheduled_through_beat);
+    }
 }
 
 /// RAII-style helper that derefs a raw pointer to a `MIDIEditorRecordingContext`, runs the provided
@@ -51,9 +103,8 @@ fn with_ctx<F: Fn(&mut MIDIRecordingContext) -> ()>(
 
 fn do_midi_recorder_animation_tick(ctx_ptr: *mut MIDIRecordingContext, cur_time: f64) {
     with_ctx(ctx_ptr, |recording_ctx| {
-        let total_seconds_recorded = cur_time - recording_ctx.start_time_seconds;
-        let total_beats_recorded = recording_ctx.state.time_to_beats(total_seconds_recorded);
-        let cur_cursor_pos_beats = recording_ctx.initial_cursor_pos_beats + total_beats_recorded;
+        recording_ctx.schedule_metronome_clicks(cur_time);
+        let cur_cursor_pos_beats = recording_ctx.time_to_beat(cur_time);
         let cursor_pos_px = recording_ctx
             .grid_state
             .conf
@@ -69,8 +120,8 @@ fn do_midi_recorder_animation_tick(ctx_ptr: *mut MIDIRecordingContext, cur_time:
         // Visually extend all currently playing notes
         for entry_opt in &recording_ctx.active_voices {
             if let Some(entry) = entry_opt {
-                let note_length_seconds = cur_time - entry.playing_start_time_seconds;
-                let note_length_beats = recording_ctx.state.time_to_beats(note_length_seconds);
+                let note_length_beats = cur_cursor_pos_beats
+                    - recording_ctx.time_to_beat(entry.playing_start_time_seconds);
 
                 js::set_attr(
                     entry.dom_id,
@@ -91,13 +142,34 @@ pub fn start_recording_midi(
     grid_state: &mut GridState<usize>,
     cur_time: f64,
 ) -> *mut MIDIRecordingContext {
-    let recording_ctx = box MIDIRecordingContext::new(state, grid_state, cur_time);
-    let ctx_ptr = Box::into_raw(recording_ctx);
-    let animation_cb_closure = Closure::wrap(
-        (box move |cur_time: f64| {
-            do_midi_recorder_animation_tick(ctx_ptr, cur_time);
-        }) as Box<dyn FnMut(f64)>,
+    // Count in before recording starts if the metronome is enabled.  If the transport is already
+    // playing, there's nothing to count in to.
+    let cursor_pos_beats = grid_state.cursor_pos_beats as f64;
+    let count_in_clicks = if state.metronome.enabled && state.loop_handle.is_none() {
+        metronome::get_count_in_clicks(
+            &state.tempo_map,
+            cursor_pos_beats,
+            state.metronome.count_in_bars,
+        )
+    } else {
+        Vec::new()
+    };
+    let start_time = cur_time
+        - count_in_clicks
+            .first()
+            .map(|&(offset_seconds, _)| offset_seconds)
+            .unwrap_or(0.);
+    state.schedule_metronome_clicks(
+        count_in_clicks
+            .into_iter()
+            .map(|(offset_seconds, is_accent)| (start_time + offset_seconds, is_accent)),
     );
+
+    let recording_ctx = Box::new(MIDIRecordingContext::new(state, grid_state, start_time));
+    let ctx_ptr = Box::into_raw(recording_ctx);
+    let animation_cb_closure = Closure::wrap(Box::new(move |cur_time: f64| {
+        do_midi_recorder_animation_tick(ctx_ptr, cur_time);
+    }) as Box<dyn FnMut(f64)>);
     let animation_loop_handle = js::midi_editor_register_animation_frame(&animation_cb_closure);
     unsafe {
         (*ctx_ptr).animation_cb = animation_cb_closure;
@@ -117,8 +189,12 @@ pub fn stop_recording_midi(recording_ctx_ptr: *mut MIDIRecordingContext, _cur_ti
         }
     }
 
-    // Cancel the animation loop
+    // Cancel the animation loop and any clicks that haven't played yet
     js::midi_editor_cancel_animation_frame(recording_ctx.animation_loop_handle);
+    if recording_ctx.state.loop_handle.is_none() {
+        js::midi_editor_cancel_metronome_clicks(&recording_ctx.state.vc_id);
+    }
+    recording_ctx.state.automation.finish_recording();
 
     drop(recording_ctx);
 }
@@ -128,8 +204,17 @@ pub fn midi_editor_record_note_down(
     recording_ctx_ptr: *mut MIDIRecordingContext,
     cur_time: f64,
     note_id: usize,
+    velocity: u8,
 ) {
     with_ctx(recording_ctx_ptr, |recording_ctx| {
+        let line_ix = match recording_ctx.state.note_layout.note_line(note_id) {
+            Some(line_ix) => line_ix,
+            None => {
+                warn!("Can't record note id {} since it's not on the grid", note_id);
+                return;
+            },
+        };
+
         // Check that the note isn't already playing
         //
         // Iteration of a fixed-size 32 elem array is almost certainly faster than hashmap or
@@ -152,27 +237,29 @@ pub fn midi_editor_record_note_down(
 
         if let Some(first_empty_ix) = first_empty_ix {
             // TODO: Support time offsets for input delay
-            let start_beat = recording_ctx
-                .state
-                .time_to_beats(cur_time - recording_ctx.start_time_seconds)
-                + recording_ctx.initial_cursor_pos_beats;
-            let line_ix = recording_ctx.grid_state.conf.row_count - note_id;
+            let start_beat = recording_ctx.time_to_beat(cur_time);
 
             let dom_id = MidiEditorGridRenderer::create_note(
                 recording_ctx.grid_state.conf.beats_to_px(start_beat as f32),
                 recording_ctx.grid_state.conf.cursor_gutter_height
                     + recording_ctx.grid_state.conf.padded_line_height() * line_ix,
                 0,
-                recording_ctx.grid_state.conf.line_height,
+                recording_ctx.grid_state.conf.zoomed_line_height(),
                 None,
             );
             MidiEditorGridRenderer::select_note(dom_id);
+            let velocity = velocity.clamp(MIN_NOTE_VELOCITY, MAX_NOTE_VELOCITY);
+            MidiEditorGridRenderer::set_note_velocity(dom_id, velocity);
 
             recording_ctx.active_voices[first_empty_ix] = Some(ActiveVoice {
                 note_id,
                 playing_start_time_seconds: cur_time,
                 dom_id,
+                velocity,
             });
+            recording_ctx
+                .recorded_expressions
+                .insert(note_id, RecordedExpression::default());
         } else {
             warn!("No non-playing voices in midi recorder; ignoring note down event...");
             return;
@@ -209,24 +296,57 @@ pub fn midi_editor_record_note_up(
             std::mem::replace(&mut recording_ctx.active_voices[voice_entry_ix], None).unwrap();
         // Commit this new note to the skip list and render it officially so that the grid knows
         // about it and can delete/move it etc.
-        let note_start_beat = recording_ctx
-            .state
-            .time_to_beats(entry.playing_start_time_seconds - recording_ctx.start_time_seconds)
-            + recording_ctx.initial_cursor_pos_beats;
-        let note_length_seconds = cur_time - entry.playing_start_time_seconds;
-        let note_length_beats = recording_ctx.state.time_to_beats(note_length_seconds);
+        // Notes are quantized to the grid's snap interval, so disabling snapping records them
+        // exactly as they were played.
+        let played_start_beat = recording_ctx.time_to_beat(entry.playing_start_time_seconds);
+        let (note_start_beat, note_end_beat) = midi_input::quantize_note_bounds(
+            played_start_beat,
+            recording_ctx.time_to_beat(cur_time),
+            recording_ctx.grid_state.snap_beat_interval() as f64,
+        );
+        let (pitch_bend, expression) = recording_ctx
+            .recorded_expressions
+            .remove(&note_id)
+            .map(|recorded| {
+                recorded.into_note_curves(
+                    (played_start_beat - note_start_beat) as f32,
+                    (note_end_beat - note_start_beat) as f32,
+                )
+            })
+            .unwrap_or_default();
 
         let note: NoteBox<usize> = NoteBox {
+            id: NoteId::next(),
             data: entry.dom_id,
             bounds: NoteBoxBounds {
                 start_beat: note_start_beat as f32,
-                // TODO: snap to beat
-                end_beat: (note_start_beat + note_length_beats) as f32,
+                end_beat: note_end_beat as f32,
             },
+            velocity: entry.velocity,
+            pitch_bend,
+            expression,
         };
         MidiEditorGridRenderer::deselect_note(entry.dom_id);
 
-        let line_ix = recording_ctx.grid_state.conf.row_count - entry.note_id;
+        // The note layout can't be changed while recording, so the note is still on the grid
+        let line_ix = recording_ctx
+            .state
+            .note_layout
+            .note_line(entry.note_id)
+            .expect("Recorded note isn't on the grid");
+        let conf = &recording_ctx.grid_state.conf;
+        js::set_attr(
+            entry.dom_id,
+            "x",
+            &conf.beats_to_px(note_start_beat as f32).to_string(),
+        );
+        js::set_attr(
+            entry.dom_id,
+            "width",
+            &conf
+                .beats_to_px((note_end_beat - note_start_beat) as f32)
+                .to_string(),
+        );
         let insertion_err = recording_ctx.grid_state.data.insert(line_ix, note);
         if let Some(_) = insertion_err {
             error!("Unable to insert note in MIDI recorder due to intersecting note");
@@ -234,3 +354,52 @@ pub fn midi_editor_record_note_up(
         }
     });
 }
+
+/// Records a control change into the automation lanes that are bound to its controller at the
+/// beat that's playing at `cur_time`
+#[wasm_bindgen]
+pub fn midi_editor_record_control_change(
+    recording_ctx_ptr: *mut MIDIRecordingContext,
+    cur_time: f64,
+    control_index: u8,
+    value: u8,
+) {
+    with_ctx(recording_ctx_ptr, |recording_ctx| {
+        let beat = recording_ctx.time_to_beat(cur_time);
+        let recorded = recording_ctx
+            .state
+            .automation
+            .record_control_change(beat, control_index, value);
+        if recorded {
+            recording_ctx
+                .state
+                .render_automation_lane(&recording_ctx.grid_state);
+        }
+    });
+}
+
+/// Records the expression of a note that's being recorded, which is ignored if the note isn't held
+pub fn record_note_expression(
+    recording_ctx_ptr: *mut MIDIRecordingContext,
+    cur_time: f64,
+    note_id: usize,
+    dimension: ExpressionDimension,
+    value: f32,
+) {
+    with_ctx(recording_ctx_ptr, |recording_ctx| {
+        let start_time = match recording_ctx
+            .active_voices
+            .iter()
+            .flatten()
+            .find(|voice| voice.note_id == note_id)
+        {
+            Some(voice) => voice.playing_start_time_seconds,
+            None => return,
+        };
+        let beat_offset =
+            recording_ctx.time_to_beat(cur_time) - recording_ctx.time_to_beat(start_time);
+        if let Some(recorded) = recording_ctx.recorded_expressions.get_mut(&note_id) {
+            recorded.record(beat_offset as f32, dimension, value);
+        }
+    });
+}
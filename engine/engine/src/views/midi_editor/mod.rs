@@ -4,16 +4,59 @@
 
 use std::str;
 
+use common::{
+    tempo_map::{TempoMap, TimeSignature},
+    theory::ChordShape,
+};
 use uuid::Uuid;
 
-use crate::{helpers::grid::prelude::*, view_context::ViewContext};
-
+use crate::{
+    helpers::{
+        grid::prelude::*,
+        keymap::{KeyAction, KeymapSection},
+    },
+    metronome::{self, MetronomeConf},
+    offline_render,
+    view_context::{migrations::SaveMigration, EngineEvent, ViewContext},
+};
+
+pub mod arpeggiator;
+pub mod automation;
+pub mod clips;
 pub mod constants;
+pub mod groove;
+pub mod humanize;
+pub mod instrument;
+pub mod keyboard_piano;
+pub mod midi_input;
+pub mod midi_output;
 pub mod midi_recording;
+pub mod mpe;
+pub mod note_layout;
+pub mod pitch_bend;
 pub mod prelude;
+pub mod scale;
 pub mod scheduler;
-
-use self::scheduler::SchedulerStateHandle;
+pub mod slices;
+pub mod velocity_lane;
+pub mod voice_manager;
+
+use self::{
+    arpeggiator::{ArpeggiatorConf, LiveArpeggiator},
+    automation::{AutomationLane, AutomationState},
+    clips::{ClipInstance, ClipState, SerializedClip},
+    groove::GrooveConf,
+    humanize::{HumanizeConf, RandomizeConf},
+    instrument::InstrumentAssignment,
+    keyboard_piano::KeyboardPiano,
+    midi_output::{MIDIOutputConf, MIDIOutputQueue},
+    mpe::{MPEConf, MPEInput},
+    note_layout::NoteLayout,
+    scale::ScaleConf,
+    scheduler::SchedulerStateHandle,
+    velocity_lane::VelocityLaneState,
+    voice_manager::VoiceManagerConf,
+};
 
 fn render_loop_mark(conf: &GridConf, class_name: &str, measure: usize) -> DomId {
     let px = conf.beats_to_px(measure as f32);
@@ -25,37 +68,183 @@ pub struct LoopMarkDescriptor {
     pub dom_id: DomId,
 }
 
+/// Commands that can be sent to the MIDI editor's transport with the `"transport"` message.  The
+/// message consists of the command byte followed by an `f64` of `cur_time`.  `Seek` is followed by
+/// an additional `f64` of the beat to seek to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TransportCommand {
+    Play,
+    Pause,
+    Stop,
+    Seek,
+}
+
+impl TransportCommand {
+    pub fn from_u8(val: u8) -> Option<Self> {
+        match val {
+            0 => Some(TransportCommand::Play),
+            1 => Some(TransportCommand::Pause),
+            2 => Some(TransportCommand::Stop),
+            3 => Some(TransportCommand::Seek),
+            _ => None,
+        }
+    }
+}
+
+fn read_f64(bytes: &[u8]) -> f64 {
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(&bytes[..8]);
+    f64::from_ne_bytes(buf)
+}
+
 pub struct MIDIEditorGridHandler {
     pub vc_id: String,
-    pub bpm: f64,
+    pub tempo_map: TempoMap,
+    pub metronome: MetronomeConf,
+    pub midi_output: MIDIOutputConf,
+    pub midi_output_queue: MIDIOutputQueue,
+    /// The instrument that the MIDI editor's MIDI output is connected to
+    pub instrument: Option<InstrumentAssignment>,
+    pub groove: GrooveConf,
+    pub automation: AutomationState,
+    pub clips: ClipState,
+    pub scale: ScaleConf,
+    /// The notes shown by the lines of the grid
+    pub note_layout: NoteLayout,
+    /// The chord inserted by the `InsertChord` tool
+    pub chord_shape: ChordShape,
+    pub arpeggiator: ArpeggiatorConf,
+    pub live_arpeggiator: Option<LiveArpeggiator>,
+    pub humanize: HumanizeConf,
+    pub randomize: RandomizeConf,
     pub loop_start_mark_measure: Option<LoopMarkDescriptor>,
     pub loop_end_mark_measure: Option<LoopMarkDescriptor>,
     pub loop_handle: Option<SchedulerStateHandle>,
     pub midi_recording_ctx: Option<*mut midi_recording::MIDIRecordingContext>,
+    pub keyboard_piano: KeyboardPiano,
+    pub velocity_lane: VelocityLaneState,
+    pub mpe: MPEConf,
+    pub mpe_input: MPEInput,
+    pub voice_manager: VoiceManagerConf,
 }
 
+/// Migrations between the versions of the format produced by `MIDIEditorGridHandler::save`
+pub const MIDI_EDITOR_SAVE_MIGRATIONS: &[SaveMigration] = &[
+    // Saves without a version (version 0) store their notes separately in `localStorage`.  The
+    // grid still loads notes from there when a save doesn't have any, so nothing else changes.
+    |_conf| {},
+];
+
+/// Version of the format produced by `MIDIEditorGridHandler::save`
+pub const MIDI_EDITOR_SAVE_VERSION: u32 = MIDI_EDITOR_SAVE_MIGRATIONS.len() as u32;
+
 #[derive(Serialize, Deserialize)]
 pub struct MIDIEditorConf {
+    #[serde(default)]
+    pub version: u32,
+    /// The starting tempo.  Saves from before tempo maps were added only have this.
     pub bpm: f64,
+    #[serde(default)]
+    pub tempo_map: Option<TempoMap>,
+    #[serde(default)]
+    pub metronome: MetronomeConf,
+    #[serde(default)]
+    pub midi_output: MIDIOutputConf,
+    #[serde(default)]
+    pub instrument: Option<InstrumentAssignment>,
+    #[serde(default)]
+    pub groove: GrooveConf,
+    #[serde(default)]
+    pub automation_lanes: Vec<AutomationLane>,
+    /// Clips other than the active one have their notes saved here.  The active clip's notes are
+    /// saved with the grid.
+    #[serde(default)]
+    pub clips: Vec<SerializedClip>,
+    #[serde(default)]
+    pub active_clip_ix: usize,
+    #[serde(default)]
+    pub arrangement: Vec<ClipInstance>,
+    #[serde(default)]
+    pub play_arrangement: bool,
+    #[serde(default)]
+    pub scale: ScaleConf,
+    /// The line indices of all saved notes are relative to this
+    #[serde(default)]
+    pub note_layout: NoteLayout,
+    #[serde(default)]
+    pub chord_shape: ChordShape,
+    #[serde(default)]
+    pub arpeggiator: ArpeggiatorConf,
+    #[serde(default)]
+    pub humanize: HumanizeConf,
+    #[serde(default)]
+    pub randomize: RandomizeConf,
+    #[serde(default)]
+    pub mpe: MPEConf,
+    #[serde(default)]
+    pub voice_manager: VoiceManagerConf,
     pub loop_start_mark_measure: Option<usize>,
     pub loop_end_mark_measure: Option<usize>,
+    #[serde(default)]
+    pub grid: Option<SerializedGridState>,
 }
 
 impl Default for MIDIEditorConf {
     fn default() -> Self {
         MIDIEditorConf {
+            version: MIDI_EDITOR_SAVE_VERSION,
             bpm: 120.0,
+            tempo_map: None,
+            metronome: MetronomeConf::default(),
+            midi_output: MIDIOutputConf::default(),
+            instrument: None,
+            groove: GrooveConf::default(),
+            automation_lanes: Vec::new(),
+            clips: Vec::new(),
+            active_clip_ix: 0,
+            arrangement: Vec::new(),
+            play_arrangement: false,
+            scale: ScaleConf::default(),
+            note_layout: NoteLayout::default(),
+            chord_shape: ChordShape::default(),
+            arpeggiator: ArpeggiatorConf::default(),
+            humanize: HumanizeConf::default(),
+            randomize: RandomizeConf::default(),
+            mpe: MPEConf::default(),
+            voice_manager: VoiceManagerConf::default(),
             loop_start_mark_measure: None,
             loop_end_mark_measure: None,
+            grid: None,
         }
     }
 }
 
 impl MIDIEditorGridHandler {
-    fn new(_grid_conf: &GridConf, vc_id: Uuid, conf: MIDIEditorConf) -> Self {
+    fn new(vc_id: Uuid, conf: MIDIEditorConf) -> Self {
+        let bpm = conf.bpm;
         MIDIEditorGridHandler {
             vc_id: vc_id.to_string(),
-            bpm: conf.bpm,
+            tempo_map: conf.tempo_map.unwrap_or_else(|| TempoMap::new(bpm)),
+            metronome: conf.metronome,
+            midi_output: conf.midi_output,
+            midi_output_queue: MIDIOutputQueue::default(),
+            instrument: conf.instrument,
+            groove: conf.groove,
+            automation: AutomationState::new(conf.automation_lanes),
+            clips: ClipState::new(
+                conf.note_layout.line_count(),
+                conf.clips,
+                conf.active_clip_ix,
+                conf.arrangement,
+                conf.play_arrangement,
+            ),
+            scale: conf.scale,
+            note_layout: conf.note_layout,
+            chord_shape: conf.chord_shape,
+            arpeggiator: conf.arpeggiator,
+            live_arpeggiator: None,
+            humanize: conf.humanize,
+            randomize: conf.randomize,
             loop_start_mark_measure: conf.loop_start_mark_measure.map(|measure| {
                 LoopMarkDescriptor {
                     measure,
@@ -70,13 +259,98 @@ impl MIDIEditorGridHandler {
                 }),
             loop_handle: None,
             midi_recording_ctx: None,
+            keyboard_piano: KeyboardPiano::default(),
+            velocity_lane: VelocityLaneState::default(),
+            mpe: conf.mpe,
+            mpe_input: MPEInput::default(),
+            voice_manager: conf.voice_manager,
+        }
+    }
+
+    fn maybe_reschedule_loop(&mut self, cur_time: f64, old_tempo_map: TempoMap) {
+        match self.loop_handle {
+            Some(loop_handle) => scheduler::reschedule(cur_time, loop_handle, old_tempo_map),
+            // Rescheduling updates the transport state itself, but we still need to let things
+            // synced to the transport know about the new BPM if we're not playing
+            None => self.set_transport_state(false, self.tempo_map.base_bpm(), 0.),
+        }
+    }
+
+    /// Schedules metronome clicks at the provided `(time, is_accent)` pairs if the metronome is
+    /// enabled
+    pub fn schedule_metronome_clicks(&self, clicks: impl Iterator<Item = (f64, bool)>) {
+        if !self.metronome.enabled {
+            return;
+        }
+
+        let (timings, is_accent_flags): (Vec<f64>, Vec<u8>) = clicks
+            .map(|(time, is_accent)| (time, tern(is_accent, 1, 0)))
+            .unzip();
+        if timings.is_empty() {
+            return;
+        }
+        js::midi_editor_schedule_metronome_clicks(
+            &self.vc_id,
+            &timings,
+            &is_accent_flags,
+            self.metronome.volume,
+        );
+    }
+
+    /// Lets anything synced to the transport know about the current playback state along with the
+    /// loop region, if one is set.
+    pub fn set_transport_state(&self, is_playing: bool, bpm: f64, beat_zero_time: f64) {
+        let loop_start_beat = self
+            .loop_start_mark_measure
+            .as_ref()
+            .map(|descriptor| descriptor.measure as f64);
+        let loop_end_beat = self
+            .loop_end_mark_measure
+            .as_ref()
+            .map(|descriptor| descriptor.measure as f64);
+
+        js::midi_editor_set_transport_state(
+            &self.vc_id,
+            is_playing,
+            bpm,
+            beat_zero_time,
+            loop_start_beat,
+            loop_end_beat,
+        );
+    }
+
+    /// Lets subscribed VCs know that the tempo changed if the tempo map differs from the old one
+    fn emit_tempo_changed(&self, old_tempo_map: &TempoMap) {
+        if self.tempo_map == *old_tempo_map {
+            return;
         }
+
+        get_vcm().emit(EngineEvent::TempoChanged {
+            vc_id: self.vc_id.clone(),
+            bpm: self.tempo_map.base_bpm(),
+        });
     }
 
-    fn maybe_reschedule_loop(&mut self, cur_time: f64, old_bpm: f64) {
-        if let Some(loop_handle) = self.loop_handle {
-            scheduler::reschedule(cur_time, loop_handle, old_bpm);
+    /// Applies a change to the tempo map, rescheduling playback and redrawing the grid's measure
+    /// lines to match.  Returns the updated tempo map serialized as JSON.
+    fn update_tempo_map(
+        &mut self,
+        grid_state: &mut GridState<usize>,
+        cur_time: f64,
+        update: impl FnOnce(&mut TempoMap),
+    ) -> Vec<u8> {
+        let old_tempo_map = self.tempo_map.clone();
+        update(&mut self.tempo_map);
+
+        if self.tempo_map.time_signature_changes() != old_tempo_map.time_signature_changes() {
+            grid_state.conf.time_signature_changes =
+                self.tempo_map.time_signature_changes().to_vec();
+            render::redraw_measure_lines(&grid_state.conf, &mut grid_state.background);
         }
+        self.emit_tempo_changed(&old_tempo_map);
+        self.maybe_reschedule_loop(cur_time, old_tempo_map);
+
+        serde_json::to_vec(&self.tempo_map).expect("Failed to serialize tempo map")
     }
 }
 
@@ -108,8 +382,8 @@ fn update_loop_descriptor(
 }
 
 impl MIDIEditorGridHandler {
-    fn set_loop_start(&mut self, grid_state: &GridState<usize>) {
-        let new_measure = grid_state.cursor_pos_beats.round() as usize;
+    fn set_loop_start(&mut self, grid_state: &GridState<usize>, beat: f32) {
+        let new_measure = beat.round() as usize;
         if let Some(LoopMarkDescriptor { measure, .. }) = &self.loop_end_mark_measure {
             // Prevent start mark from being placed on or after end mark
             if new_measure >= *measure {
@@ -119,15 +393,15 @@ impl MIDIEditorGridHandler {
 
         let old_descriptor_opt = std::mem::replace(&mut self.loop_start_mark_measure, None);
         self.loop_start_mark_measure = Some(update_loop_descriptor(
-            grid_state.cursor_pos_beats,
+            beat,
             old_descriptor_opt,
             &grid_state.conf,
             "loop-start-marker",
         ))
     }
 
-    fn set_loop_end(&mut self, grid_state: &GridState<usize>) {
-        let new_measure = grid_state.cursor_pos_beats.round() as usize;
+    fn set_loop_end(&mut self, grid_state: &GridState<usize>, beat: f32) {
+        let new_measure = beat.round() as usize;
         if let Some(LoopMarkDescriptor { measure, .. }) = &self.loop_start_mark_measure {
             // Prevent end mark from being placed on or before end mark
             if new_measure <= *measure {
@@ -137,25 +411,163 @@ impl MIDIEditorGridHandler {
 
         let old_descriptor_opt = std::mem::replace(&mut self.loop_end_mark_measure, None);
         self.loop_end_mark_measure = Some(update_loop_descriptor(
-            grid_state.cursor_pos_beats,
+            beat,
             old_descriptor_opt,
             &grid_state.conf,
             "loop-end-marker",
         ))
     }
+
+    /// Sets both loop marks at once.  The existing marks are cleared first so that the new region
+    /// can be placed anywhere relative to the old one.
+    fn set_loop_region(&mut self, grid_state: &GridState<usize>, start_beat: f32, end_beat: f32) {
+        if start_beat.round() >= end_beat.round() {
+            return;
+        }
+
+        self.clear_loop_marks();
+        self.set_loop_start(grid_state, start_beat);
+        self.set_loop_end(grid_state, end_beat);
+    }
+
+    fn clear_loop_marks(&mut self) {
+        for descriptor in self
+            .loop_start_mark_measure
+            .take()
+            .into_iter()
+            .chain(self.loop_end_mark_measure.take())
+        {
+            js::delete_element(descriptor.dom_id);
+        }
+    }
 }
 
 pub struct MidiEditorGridRenderer;
 
 type MidiGrid = Grid<usize, MidiEditorGridRenderer, MIDIEditorGridHandler>;
 
-impl GridRenderer<usize> for MidiEditorGridRenderer {}
+impl GridRenderer<usize> for MidiEditorGridRenderer {
+    /// Quieter notes are rendered more transparently so that dynamics are visible at a glance
+    fn set_note_velocity(dom_id: DomId, velocity: u8) {
+        let opacity = 0.25 + 0.75 * (velocity as f32 / MAX_NOTE_VELOCITY as f32);
+        js::set_attr(dom_id, "fill-opacity", &opacity.to_string());
+    }
+}
+
+/// Holding shift or control moves and resizes notes by larger amounts
+pub static MIDI_EDITOR_KEYMAP: KeymapSection = KeymapSection {
+    name: "midi_editor",
+    actions: &[
+        KeyAction {
+            name: "transpose_up",
+            description: "Move the selected notes up",
+            default_keys: &["ArrowUp", "w"],
+        },
+        KeyAction {
+            name: "transpose_down",
+            description: "Move the selected notes down",
+            default_keys: &["ArrowDown", "s"],
+        },
+        KeyAction {
+            name: "shift_left",
+            description: "Move the selected notes earlier",
+            default_keys: &["ArrowLeft", "a"],
+        },
+        KeyAction {
+            name: "shift_right",
+            description: "Move the selected notes later",
+            default_keys: &["ArrowRight", "d"],
+        },
+        KeyAction {
+            name: "play_selected_notes",
+            description: "Play the selected notes",
+            default_keys: &["q"],
+        },
+        KeyAction {
+            name: "extend_note_starts",
+            description: "Move the starts of the selected notes earlier",
+            default_keys: &["z"],
+        },
+        KeyAction {
+            name: "shrink_note_starts",
+            description: "Move the starts of the selected notes later",
+            default_keys: &["x"],
+        },
+        KeyAction {
+            name: "shorten_notes",
+            description: "Move the ends of the selected notes earlier",
+            default_keys: &["c"],
+        },
+        KeyAction {
+            name: "lengthen_notes",
+            description: "Move the ends of the selected notes later",
+            default_keys: &["v"],
+        },
+        // Shift changes the reported key for brackets on most layouts
+        KeyAction {
+            name: "decrease_velocity",
+            description: "Decrease the velocity of the selected notes",
+            default_keys: &["[", "{"],
+        },
+        KeyAction {
+            name: "increase_velocity",
+            description: "Increase the velocity of the selected notes",
+            default_keys: &["]", "}"],
+        },
+        KeyAction {
+            name: "set_loop_start",
+            description: "Start the loop at the cursor",
+            default_keys: &["1"],
+        },
+        KeyAction {
+            name: "set_loop_end",
+            description: "End the loop at the cursor",
+            default_keys: &["2"],
+        },
+        KeyAction {
+            name: "clear_loop",
+            description: "Clear the loop",
+            default_keys: &["3"],
+        },
+        KeyAction {
+            name: "select_loop_notes",
+            description: "Select every note that starts inside of the loop",
+            default_keys: &["4"],
+        },
+        KeyAction {
+            name: "start_playback",
+            description: "Start playback",
+            default_keys: &[" "],
+        },
+        KeyAction {
+            name: "toggle_split_tool",
+            description: "Toggle the knife tool, which splits clicked notes",
+            default_keys: &["k"],
+        },
+        KeyAction {
+            name: "toggle_join_tool",
+            description: "Toggle the glue tool, which joins clicked notes to the next ones",
+            default_keys: &["g"],
+        },
+        KeyAction {
+            name: "toggle_chord_tool",
+            description: "Toggle the chord tool, which inserts chords where clicked",
+            default_keys: &["h"],
+        },
+        KeyAction {
+            name: "toggle_lasso_tool",
+            description: "Toggle the lasso, which selects the notes inside of a drawn shape",
+            default_keys: &["l"],
+        },
+    ],
+};
 
 impl GridHandler<usize, MidiEditorGridRenderer> for MIDIEditorGridHandler {
-    fn init(&mut self, vc_id: &str, grid_conf: &GridConf) {
-        skip_list::create_skip_list_dbg_ptrs();
+    fn get_keymap_sections(&self) -> Vec<&'static KeymapSection> { vec![&MIDI_EDITOR_KEYMAP] }
 
+    fn init(&mut self, vc_id: &str, grid_conf: &GridConf) {
         js::init_midi_editor_ui(vc_id);
+        self.voice_manager.sync(vc_id);
 
         // Render loop marks
         if let Some(descriptor) = &mut self.loop_start_mark_measure {
@@ -166,17 +578,132 @@ impl GridHandler<usize, MidiEditorGridRenderer> for MIDIEditorGridHandler {
         }
     }
 
-    fn hide(&mut self, vc_id: &str) { js::hide_midi_editor(vc_id) }
+    fn on_background_render(&mut self, grid_state: &mut GridState<usize>) {
+        self.render_scale_highlighting(grid_state);
+        self.render_line_labels(&grid_state.conf);
+        self.render_velocity_lane(grid_state);
+        self.render_automation_lane(grid_state);
+    }
+
+    fn get_draw_line(&self, _grid_state: &GridState<usize>, line_ix: usize) -> usize {
+        self.snap_line_to_scale(line_ix)
+    }
+
+    fn get_chord_lines(&self, _grid_state: &GridState<usize>, line_ix: usize) -> Vec<usize> {
+        self.chord_lines(line_ix)
+    }
+
+    fn hide(&mut self, vc_id: &str) {
+        // Keys released while another view is active are never seen
+        for event in self.keyboard_piano.release_all() {
+            if let midi_input::MIDIInputEvent::NoteOff { note_id } = event {
+                self.release_live_note(note_id);
+            }
+        }
+        js::hide_midi_editor(vc_id)
+    }
 
     fn unhide(&mut self, vc_id: &str) { js::unhide_midi_editor(vc_id) }
 
+    fn on_cursor_gutter_region_select(
+        &mut self,
+        grid_state: &mut GridState<usize>,
+        start_beat: f32,
+        end_beat: f32,
+    ) {
+        self.set_loop_region(grid_state, start_beat, end_beat);
+        self.maybe_reschedule_loop(js::get_cur_audio_ctx_time(), self.tempo_map.clone());
+    }
+
+    fn on_rerender(&mut self, grid_state: &mut GridState<usize>) {
+        let grid_height = grid_state.conf.grid_height().to_string();
+        for descriptor in self
+            .loop_start_mark_measure
+            .iter()
+            .chain(self.loop_end_mark_measure.iter())
+        {
+            let px_str = grid_state
+                .conf
+                .beats_to_px(descriptor.measure as f32)
+                .to_string();
+            js::set_attr(descriptor.dom_id, "x1", &px_str);
+            js::set_attr(descriptor.dom_id, "x2", &px_str);
+            js::set_attr(descriptor.dom_id, "y2", &grid_height);
+        }
+
+        self.render_automation_lane(grid_state);
+        self.render_line_labels(&grid_state.conf);
+        self.render_velocity_lane(grid_state);
+    }
+
+    fn on_notes_changed(&mut self, grid_state: &mut GridState<usize>) {
+        self.render_velocity_lane(grid_state);
+    }
+
+    fn on_lane_area_mouse_down(
+        &mut self,
+        grid_state: &mut GridState<usize>,
+        x: usize,
+        y: usize,
+    ) -> bool {
+        if y < constants::AUTOMATION_LANE_OFFSET_PX {
+            self.handle_velocity_lane_mouse_down(grid_state, x, y)
+        } else {
+            self.handle_automation_lane_mouse_down(grid_state, x, y)
+        }
+    }
+
+    fn on_lane_area_mouse_move(&mut self, grid_state: &mut GridState<usize>, x: usize, y: usize) {
+        if self.velocity_lane.is_dragging() {
+            self.handle_velocity_lane_mouse_move(grid_state, x, y)
+        } else {
+            self.handle_automation_lane_mouse_move(grid_state, x, y)
+        }
+    }
+
+    fn on_lane_area_mouse_up(&mut self, grid_state: &mut GridState<usize>, _x: usize, _y: usize) {
+        if self.velocity_lane.is_dragging() {
+            self.handle_velocity_lane_mouse_up(grid_state)
+        } else {
+            self.handle_automation_lane_mouse_up()
+        }
+    }
+
+    fn draws_hits(&self) -> bool { self.note_layout.is_drum_map() }
+
+    fn lane_area_height(&self) -> usize {
+        constants::AUTOMATION_LANE_OFFSET_PX
+            + constants::LANE_MARGIN_PX
+            + constants::AUTOMATION_LANE_HEIGHT_PX
+    }
+
     fn cleanup(&mut self, _: &mut GridState<usize>, vc_id: &str) {
+        self.stop_live_arpeggiator();
         js::cleanup_midi_editor_ui(vc_id);
     }
 
-    fn save(&self) -> String {
+    fn save(&self, grid_state: &GridState<usize>) -> String {
         let state = MIDIEditorConf {
-            bpm: self.bpm,
+            version: MIDI_EDITOR_SAVE_VERSION,
+            bpm: self.tempo_map.base_bpm(),
+            tempo_map: Some(self.tempo_map.clone()),
+            metronome: self.metronome,
+            midi_output: self.midi_output.clone(),
+            instrument: self.instrument.clone(),
+            groove: self.groove,
+            automation_lanes: self.automation.lanes.clone(),
+            clips: self.clips.serialize(),
+            active_clip_ix: self.clips.active_clip_ix,
+            arrangement: self.clips.arrangement.clone(),
+            play_arrangement: self.clips.play_arrangement,
+            scale: self.scale,
+            note_layout: self.note_layout.clone(),
+            chord_shape: self.chord_shape.clone(),
+            arpeggiator: self.arpeggiator,
+            humanize: self.humanize,
+            randomize: self.randomize,
+            mpe: self.mpe,
+            voice_manager: self.voice_manager,
             loop_start_mark_measure: self
                 .loop_start_mark_measure
                 .as_ref()
@@ -185,6 +712,7 @@ impl GridHandler<usize, MidiEditorGridRenderer> for MIDIEditorGridHandler {
                 .loop_end_mark_measure
                 .as_ref()
                 .map(|descriptor| descriptor.measure),
+            grid: Some(grid_state.serialize()),
         };
         serde_json::to_string(&state).expect("Failed to serialize `MIDIEditorConf`")
     }
@@ -202,36 +730,94 @@ impl GridHandler<usize, MidiEditorGridRenderer> for MIDIEditorGridHandler {
             (false, false) => (1, 1.0),
         };
 
-        match key {
-            "ArrowUp" | "w" => self.move_notes_vertical(true, grid_state, line_diff_vertical),
-            "ArrowDown" | "s" => self.move_notes_vertical(false, grid_state, line_diff_vertical),
-            "ArrowLeft" | "a" =>
-                self.move_selected_notes_horizontal(grid_state, false, beat_diff_horizontal),
-            "ArrowRight" | "d" =>
-                self.move_selected_notes_horizontal(grid_state, true, beat_diff_horizontal),
-            "q" => self.play_selected_notes(grid_state),
-            "z" | "x" | "c" | "v" => {
-                let direction_multiplier = tern(key == "z" || key == "c", -1., 1.);
+        let action = match get_vcm().get_key_action(&MIDI_EDITOR_KEYMAP, key) {
+            Some(action) => action,
+            None => return,
+        };
+        match action {
+            "transpose_up" => self.transpose_selected_notes(grid_state, line_diff_vertical),
+            "transpose_down" => self.transpose_selected_notes(grid_state, -line_diff_vertical),
+            "shift_left" => self.shift_selected_notes(grid_state, -beat_diff_horizontal),
+            "shift_right" => self.shift_selected_notes(grid_state, beat_diff_horizontal),
+            "play_selected_notes" => self.play_selected_notes(grid_state),
+            "extend_note_starts" | "shrink_note_starts" | "shorten_notes" | "lengthen_notes" => {
+                let direction_multiplier =
+                    tern(action == "extend_note_starts" || action == "shorten_notes", -1., 1.);
                 let adjustment_amount = 0.25
                     * tern(control_pressed, 2., 1.)
                     * tern(shift_pressed, 2., 1.)
                     * direction_multiplier;
-                let is_left = key == "z" || key == "x";
+                let is_left = action == "extend_note_starts" || action == "shrink_note_starts";
                 self.adjust_note_lengths(grid_state, is_left, adjustment_amount);
             },
-            "1" => {
-                self.set_loop_start(&*grid_state);
-                self.maybe_reschedule_loop(js::get_cur_audio_ctx_time(), self.bpm);
+            "decrease_velocity" | "increase_velocity" => {
+                let direction_multiplier = tern(action == "decrease_velocity", -1, 1);
+                let adjustment_amount = tern(shift_pressed, 32, 8) * direction_multiplier;
+                self.adjust_note_velocities(grid_state, adjustment_amount);
+            },
+            "set_loop_start" => {
+                self.set_loop_start(&*grid_state, grid_state.cursor_pos_beats);
+                self.maybe_reschedule_loop(js::get_cur_audio_ctx_time(), self.tempo_map.clone());
             },
-            "2" => {
-                self.set_loop_end(&*grid_state);
-                self.maybe_reschedule_loop(js::get_cur_audio_ctx_time(), self.bpm);
+            "set_loop_end" => {
+                self.set_loop_end(&*grid_state, grid_state.cursor_pos_beats);
+                self.maybe_reschedule_loop(js::get_cur_audio_ctx_time(), self.tempo_map.clone());
+            },
+            "clear_loop" => {
+                self.clear_loop_marks();
+                self.maybe_reschedule_loop(js::get_cur_audio_ctx_time(), self.tempo_map.clone());
+            },
+            "select_loop_notes" => {
+                let start_beat = self
+                    .loop_start_mark_measure
+                    .as_ref()
+                    .map(|descriptor| descriptor.measure as f32)
+                    .unwrap_or(0.);
+                let end_beat = self
+                    .loop_end_mark_measure
+                    .as_ref()
+                    .map(|descriptor| descriptor.measure as f32)
+                    .unwrap_or(f32::INFINITY);
+                grid_state.select_beat_range::<MidiEditorGridRenderer>(start_beat, end_beat);
+            },
+            "start_playback" => self.start_playback(grid_state),
+            "toggle_split_tool" | "toggle_join_tool" | "toggle_chord_tool"
+            | "toggle_lasso_tool" => {
+                let tool = match action {
+                    "toggle_split_tool" => Tool::SplitNote,
+                    "toggle_join_tool" => Tool::JoinNote,
+                    "toggle_lasso_tool" => Tool::Lasso,
+                    _ => Tool::InsertChord,
+                };
+                grid_state.cur_tool = tern(grid_state.cur_tool == tool, Tool::DrawNote, tool);
             },
-            " " => self.start_playback(grid_state),
             _ => (),
         }
     }
 
+    fn capture_key_down(
+        &mut self,
+        _grid_state: &mut GridState<usize>,
+        key: &str,
+        control_pressed: bool,
+        _shift_pressed: bool,
+    ) -> bool {
+        if control_pressed {
+            return false;
+        }
+
+        match self.keyboard_piano.key_down(key) {
+            Some(events) => {
+                let cur_time = js::get_cur_audio_ctx_time();
+                for event in events {
+                    self.handle_midi_input(cur_time, event);
+                }
+                true
+            },
+            None => false,
+        }
+    }
+
     fn on_key_up(
         &mut self,
         grid_state: &mut GridState<usize>,
@@ -239,8 +825,17 @@ impl GridHandler<usize, MidiEditorGridRenderer> for MIDIEditorGridHandler {
         _control_pressed: bool,
         _shift_pressed: bool,
     ) {
-        match key {
-            "z" | "x" => self.release_selected_notes(grid_state),
+        if let Some(event) = self.keyboard_piano.key_up(key) {
+            self.handle_midi_input(js::get_cur_audio_ctx_time(), event);
+            return;
+        }
+        if self.keyboard_piano.handles_key(key) {
+            return;
+        }
+
+        match get_vcm().get_key_action(&MIDI_EDITOR_KEYMAP, key) {
+            Some("extend_note_starts") | Some("shrink_note_starts") =>
+                self.release_selected_notes(grid_state),
             _ => (),
         }
     }
@@ -259,62 +854,31 @@ impl GridHandler<usize, MidiEditorGridRenderer> for MIDIEditorGridHandler {
 
         trace!("Triggering attack of line_ix {}", line_ix);
         if grid_state.cur_tool == Tool::DrawNote && !grid_state.shift_pressed {
-            js::midi_editor_trigger_attack(&self.vc_id, grid_state.conf.row_count - line_ix);
+            js::midi_editor_trigger_attack(&self.vc_id, self.note_layout.line_note(line_ix));
         }
     }
 
     fn on_selection_region_update(
         &mut self,
         grid_state: &mut GridState<usize>,
-        retained_region: &Option<SelectionRegion>,
-        changed_region_1: &ChangedRegion,
-        changed_region_2: &ChangedRegion,
+        selection_box: &SelectionBoxData,
     ) {
-        // Look for all notes in the added/removed regions and add/remove them from the
-        // selected notes set and select/deselect their UI representations
-        for (was_added, region) in &[
-            (changed_region_1.was_added, &changed_region_1.region),
-            (changed_region_2.was_added, &changed_region_2.region),
-        ] {
-            let min_beat = grid_state.conf.px_to_beat(region.x);
-            let max_beat = grid_state.conf.px_to_beat(region.x + region.width);
-            let start_line_ix = (region.y - (region.y % grid_state.conf.padded_line_height()))
-                / grid_state.conf.padded_line_height();
-
-            // Convert the pixels of the region into line indices and beats
-            let end_px_ix = region.y + region.height;
-            let end_line_ix = ((end_px_ix - (end_px_ix % grid_state.conf.padded_line_height()))
-                / grid_state.conf.padded_line_height())
-            .min(grid_state.conf.row_count - 1);
-            for note_data in
-                grid_state
-                    .data
-                    .iter_region(start_line_ix, end_line_ix, min_beat, max_beat)
-            {
-                // Ignore notes that are also contained in the retained region
-                if let Some(retained_region) = retained_region.as_ref() {
-                    if note_data.intersects_region(&grid_state.conf, &retained_region) {
-                        continue;
-                    }
-                }
-
-                let dom_id = note_data.note_box.data.get_id();
-                let selected_note_data: SelectedNoteData =
-                    SelectedNoteData::from_note_box(note_data.line_ix, note_data.note_box);
-                let line_ix = selected_note_data.line_ix;
-                if *was_added && grid_state.selected_notes.insert(selected_note_data) {
-                    MidiEditorGridRenderer::select_note(dom_id);
-                    js::midi_editor_trigger_attack(
-                        &self.vc_id,
-                        grid_state.conf.row_count - line_ix,
-                    );
-                } else if !*was_added && grid_state.selected_notes.remove(&selected_note_data) {
-                    MidiEditorGridRenderer::deselect_note(dom_id);
-                    js::midi_editor_trigger_release(
-                        &self.vc_id,
-                        grid_state.conf.row_count - line_ix,
-                    );
-                }
+        // Add/remove the notes whose selection state changed from the selected notes set and
+        // select/deselect their UI representations
+        for (note_data, in_box) in
+            selection_box::iter_selection_changes(&grid_state.conf, &grid_state.data, selection_box)
+        {
+            let is_selected = grid_state.is_selected_by_box(note_data.note_box.id, in_box);
+            let dom_id = note_data.note_box.data.get_id();
+            let selected_note_data: SelectedNoteData =
+                SelectedNoteData::from_note_box(note_data.line_ix, note_data.note_box);
+            let line_ix = selected_note_data.line_ix;
+            if is_selected && grid_state.selected_notes.insert(selected_note_data) {
+                MidiEditorGridRenderer::select_note(dom_id);
+                js::midi_editor_trigger_attack(&self.vc_id, self.note_layout.line_note(line_ix));
+            } else if !is_selected && grid_state.selected_notes.remove(&selected_note_data) {
+                MidiEditorGridRenderer::deselect_note(dom_id);
+                js::midi_editor_trigger_release(&self.vc_id, self.note_layout.line_note(line_ix));
             }
         }
     }
@@ -323,20 +887,20 @@ impl GridHandler<usize, MidiEditorGridRenderer> for MIDIEditorGridHandler {
         for note_data in grid_state.selected_notes.iter() {
             js::midi_editor_trigger_release(
                 &self.vc_id,
-                grid_state.conf.row_count - note_data.line_ix,
+                self.note_layout.line_note(note_data.line_ix),
             );
         }
     }
 
     fn create_note(
         &mut self,
-        grid_state: &mut GridState<usize>,
+        _grid_state: &mut GridState<usize>,
         line_ix: usize,
         _start_beat: f32,
         dom_id: usize,
     ) -> DomId {
         trace!("Triggering release of note on line_ix {}", line_ix);
-        js::midi_editor_trigger_release(&self.vc_id, grid_state.conf.row_count - line_ix);
+        js::midi_editor_trigger_release(&self.vc_id, self.note_layout.line_note(line_ix));
 
         // Right now, we don't have any additional data to store for notes outside of their actual
         // position on the grid and line index, so we just use their `dom_id` as their state.
@@ -345,17 +909,17 @@ impl GridHandler<usize, MidiEditorGridRenderer> for MIDIEditorGridHandler {
 
     fn cancel_note_create(
         &mut self,
-        grid_state: &mut GridState<usize>,
+        _grid_state: &mut GridState<usize>,
         line_ix: usize,
         _note_dom_id: DomId,
     ) {
         trace!("Triggering release of note on line_ix {}", line_ix);
-        js::midi_editor_trigger_release(&self.vc_id, grid_state.conf.row_count - line_ix);
+        js::midi_editor_trigger_release(&self.vc_id, self.note_layout.line_note(line_ix));
     }
 
     fn on_note_move(
         &mut self,
-        grid_state: &mut GridState<usize>,
+        _grid_state: &mut GridState<usize>,
         _dom_id: DomId,
         old_line_ix: usize,
         _old_start_beat: f32,
@@ -366,18 +930,18 @@ impl GridHandler<usize, MidiEditorGridRenderer> for MIDIEditorGridHandler {
             return;
         }
 
-        js::midi_editor_trigger_release(&self.vc_id, grid_state.conf.row_count - old_line_ix);
-        js::midi_editor_trigger_attack(&self.vc_id, grid_state.conf.row_count - new_line_ix);
+        js::midi_editor_trigger_release(&self.vc_id, self.note_layout.line_note(old_line_ix));
+        js::midi_editor_trigger_attack(&self.vc_id, self.note_layout.line_note(new_line_ix));
     }
 
-    fn on_note_draw_start(&mut self, grid_state: &mut GridState<usize>, line_ix: usize) {
+    fn on_note_draw_start(&mut self, _grid_state: &mut GridState<usize>, line_ix: usize) {
         trace!("triggering attack on line_ix {}", line_ix);
-        js::midi_editor_trigger_attack(&self.vc_id, grid_state.conf.row_count - line_ix);
+        js::midi_editor_trigger_attack(&self.vc_id, self.note_layout.line_note(line_ix));
     }
 
     fn on_note_drag_start(
         &mut self,
-        grid_state: &mut GridState<usize>,
+        _grid_state: &mut GridState<usize>,
         dragging_note_data: &(f32, SelectedNoteData),
     ) {
         trace!(
@@ -386,13 +950,13 @@ impl GridHandler<usize, MidiEditorGridRenderer> for MIDIEditorGridHandler {
         );
         js::midi_editor_trigger_attack(
             &self.vc_id,
-            grid_state.conf.row_count - dragging_note_data.1.line_ix,
+            self.note_layout.line_note(dragging_note_data.1.line_ix),
         );
     }
 
     fn on_note_drag_stop(
         &mut self,
-        grid_state: &mut GridState<usize>,
+        _grid_state: &mut GridState<usize>,
         dragging_note_data: &(f32, SelectedNoteData),
     ) {
         trace!(
@@ -401,7 +965,7 @@ impl GridHandler<usize, MidiEditorGridRenderer> for MIDIEditorGridHandler {
         );
         js::midi_editor_trigger_release(
             &self.vc_id,
-            grid_state.conf.row_count - dragging_note_data.1.line_ix,
+            self.note_layout.line_note(dragging_note_data.1.line_ix),
         );
     }
 
@@ -413,6 +977,22 @@ impl GridHandler<usize, MidiEditorGridRenderer> for MIDIEditorGridHandler {
     ) -> Option<Vec<u8>> {
         match key {
             "export_midi" => Some(grid_state.serialize_to_binary()),
+            "adjust_velocity" => {
+                assert_eq!(
+                    val.len(),
+                    1,
+                    "Message for \"adjust_velocity\" must be a 1-byte `i8` of the velocity change"
+                );
+                self.adjust_note_velocities(grid_state, val[0] as i8 as i16);
+                None
+            },
+            "set_pitch_bend" => {
+                match serde_json::from_slice(val) {
+                    Ok(curve) => self.set_selected_notes_pitch_bend(grid_state, curve),
+                    Err(err) => error!("Error deserializing pitch bend curve: {:?}", err),
+                }
+                None
+            },
             "set_bpm" => {
                 assert_eq!(
                     val.len(),
@@ -429,13 +1009,288 @@ impl GridHandler<usize, MidiEditorGridRenderer> for MIDIEditorGridHandler {
                         val[8], val[9], val[10], val[11], val[12], val[13], val[14], val[15],
                     ))
                 };
-                let old_bpm = self.bpm;
-                self.bpm = bpm;
+                let old_tempo_map = self.tempo_map.clone();
+                self.tempo_map.set_base_bpm(bpm);
 
-                self.maybe_reschedule_loop(cur_time, old_bpm);
+                self.emit_tempo_changed(&old_tempo_map);
+                self.maybe_reschedule_loop(cur_time, old_tempo_map);
 
                 None
             },
+            "get_metronome_conf" => Some(
+                serde_json::to_vec(&self.metronome).expect("Failed to serialize metronome conf"),
+            ),
+            "set_metronome_conf" => {
+                match serde_json::from_slice(val) {
+                    Ok(conf) => self.metronome = conf,
+                    Err(err) => error!("Error deserializing metronome conf: {:?}", err),
+                }
+                if !self.metronome.enabled {
+                    js::midi_editor_cancel_metronome_clicks(&self.vc_id);
+                }
+                None
+            },
+            "get_midi_output_conf" => Some(
+                serde_json::to_vec(&self.midi_output)
+                    .expect("Failed to serialize MIDI output conf"),
+            ),
+            "set_midi_output_conf" => {
+                match serde_json::from_slice(val) {
+                    Ok(conf) => {
+                        // Silence anything still playing on the old port or channel
+                        self.cancel_midi_output();
+                        self.midi_output = conf;
+                    },
+                    Err(err) => error!("Error deserializing MIDI output conf: {:?}", err),
+                }
+                None
+            },
+            "list_instruments" => Some(
+                serde_json::to_vec(&instrument::get_workspace_instruments())
+                    .expect("Failed to serialize instruments"),
+            ),
+            "get_instrument" => Some(
+                serde_json::to_vec(&self.instrument).expect("Failed to serialize instrument"),
+            ),
+            "set_instrument" => {
+                match serde_json::from_slice(val) {
+                    Ok(instrument) => self.set_instrument(instrument),
+                    Err(err) => error!("Error deserializing instrument: {:?}", err),
+                }
+                None
+            },
+            "get_scale_conf" =>
+                Some(serde_json::to_vec(&self.scale).expect("Failed to serialize scale conf")),
+            "set_scale_conf" => {
+                match serde_json::from_slice(val) {
+                    Ok(conf) => self.scale = conf,
+                    Err(err) => error!("Error deserializing scale conf: {:?}", err),
+                }
+                self.render_scale_highlighting(grid_state);
+                None
+            },
+            "get_note_layout" => Some(
+                serde_json::to_vec(&self.note_layout).expect("Failed to serialize note layout"),
+            ),
+            "set_note_range" => {
+                let range = match serde_json::from_slice(val) {
+                    Ok(range) => range,
+                    Err(err) => {
+                        error!("Error deserializing note range: {:?}", err);
+                        return Some(vec![0]);
+                    },
+                };
+                Some(vec![self.set_note_range(grid_state, range) as u8])
+            },
+            "set_fold_to_used_notes" => {
+                assert_eq!(
+                    val.len(),
+                    1,
+                    "Message for \"set_fold_to_used_notes\" must be a single byte"
+                );
+                Some(vec![self.set_fold_to_used_notes(grid_state, val[0] != 0) as u8])
+            },
+            "set_drum_map" => {
+                let sounds = match serde_json::from_slice(val) {
+                    Ok(sounds) => sounds,
+                    Err(err) => {
+                        error!("Error deserializing drum map: {:?}", err);
+                        return Some(vec![0]);
+                    },
+                };
+                Some(vec![self.set_drum_map(grid_state, sounds) as u8])
+            },
+            "map_slices" => match serde_json::from_slice(val) {
+                Ok(mapping) => Some(vec![self.map_slices(grid_state, &mapping) as u8]),
+                Err(err) => {
+                    error!("Error deserializing slice mapping: {:?}", err);
+                    Some(vec![0])
+                },
+            },
+            "get_chord_shape" => Some(
+                serde_json::to_vec(&self.chord_shape).expect("Failed to serialize chord shape"),
+            ),
+            "set_chord_shape" => {
+                match serde_json::from_slice(val) {
+                    Ok(chord_shape) => self.chord_shape = chord_shape,
+                    Err(err) => error!("Error deserializing chord shape: {:?}", err),
+                }
+                None
+            },
+            "get_arpeggiator_conf" => Some(
+                serde_json::to_vec(&self.arpeggiator)
+                    .expect("Failed to serialize arpeggiator conf"),
+            ),
+            "set_arpeggiator_conf" => {
+                match serde_json::from_slice(val) {
+                    Ok(arpeggiator) => self.arpeggiator = arpeggiator,
+                    Err(err) => error!("Error deserializing arpeggiator conf: {:?}", err),
+                }
+                if !self.arpeggiator.live {
+                    self.stop_live_arpeggiator();
+                }
+                None
+            },
+            "get_mpe_conf" =>
+                Some(serde_json::to_vec(&self.mpe).expect("Failed to serialize MPE conf")),
+            "set_mpe_conf" => {
+                match serde_json::from_slice(val) {
+                    Ok(mpe) => self.mpe = mpe,
+                    Err(err) => error!("Error deserializing MPE conf: {:?}", err),
+                }
+                if !self.mpe.enabled {
+                    self.mpe_input.reset();
+                }
+                None
+            },
+            "get_voice_manager_conf" => Some(
+                serde_json::to_vec(&self.voice_manager)
+                    .expect("Failed to serialize voice manager conf"),
+            ),
+            "set_voice_manager_conf" => {
+                match serde_json::from_slice(val) {
+                    Ok(voice_manager) => self.voice_manager = voice_manager,
+                    Err(err) => error!("Error deserializing voice manager conf: {:?}", err),
+                }
+                self.voice_manager.sync(&self.vc_id);
+                None
+            },
+            "set_keyboard_piano_enabled" => {
+                assert_eq!(
+                    val.len(),
+                    1,
+                    "Message for \"set_keyboard_piano_enabled\" must be a single byte"
+                );
+                self.keyboard_piano.enabled = val[0] != 0;
+                if !self.keyboard_piano.enabled {
+                    let cur_time = js::get_cur_audio_ctx_time();
+                    for event in self.keyboard_piano.release_all() {
+                        self.handle_midi_input(cur_time, event);
+                    }
+                }
+                None
+            },
+            "arpeggiate_selection" => {
+                self.arpeggiate_selected_notes(grid_state);
+                None
+            },
+            "get_groove_conf" => Some(
+                serde_json::to_vec(&self.groove).expect("Failed to serialize groove conf"),
+            ),
+            "set_groove_conf" => {
+                match serde_json::from_slice(val) {
+                    Ok(groove) => self.groove = groove,
+                    Err(err) => error!("Error deserializing groove conf: {:?}", err),
+                }
+                self.maybe_reschedule_loop(js::get_cur_audio_ctx_time(), self.tempo_map.clone());
+                None
+            },
+            "apply_groove" => {
+                self.apply_groove(grid_state);
+                None
+            },
+            "get_humanize_conf" => Some(
+                serde_json::to_vec(&self.humanize).expect("Failed to serialize humanize conf"),
+            ),
+            "set_humanize_conf" => {
+                match serde_json::from_slice(val) {
+                    Ok(humanize) => self.humanize = humanize,
+                    Err(err) => error!("Error deserializing humanize conf: {:?}", err),
+                }
+                None
+            },
+            "get_randomize_conf" => Some(
+                serde_json::to_vec(&self.randomize).expect("Failed to serialize randomize conf"),
+            ),
+            "set_randomize_conf" => {
+                match serde_json::from_slice(val) {
+                    Ok(randomize) => self.randomize = randomize,
+                    Err(err) => error!("Error deserializing randomize conf: {:?}", err),
+                }
+                None
+            },
+            "humanize_selection" => {
+                self.humanize_selected_notes(grid_state);
+                None
+            },
+            "randomize_selection" => {
+                self.randomize_selected_notes(grid_state);
+                None
+            },
+            "get_clips"
+            | "add_clip"
+            | "update_clip"
+            | "delete_clip"
+            | "set_active_clip"
+            | "set_arrangement"
+            | "set_play_arrangement"
+            | "launch_clip"
+            | "get_launched_clips" => self.handle_clip_message(grid_state, key, val),
+            "get_automation_lanes"
+            | "add_automation_lane"
+            | "remove_automation_lane"
+            | "set_active_automation_lane"
+            | "learn_automation_cc"
+            | "clear_automation_cc"
+            | "undo_automation"
+            | "redo_automation" => self.handle_automation_message(grid_state, key, val),
+            "get_tempo_map" =>
+                Some(serde_json::to_vec(&self.tempo_map).expect("Failed to serialize tempo map")),
+            "set_tempo" => {
+                assert_eq!(
+                    val.len(),
+                    24,
+                    "Message for \"set_tempo\" must be a 24-byte `(f64, f64, f64)` of `(beat, \
+                     bpm, cur_time)`"
+                );
+                let (beat, bpm) = (read_f64(&val[..8]), read_f64(&val[8..16]));
+                Some(self.update_tempo_map(grid_state, read_f64(&val[16..]), |tempo_map| {
+                    tempo_map.set_tempo(beat, bpm)
+                }))
+            },
+            "remove_tempo_change" => {
+                assert_eq!(
+                    val.len(),
+                    16,
+                    "Message for \"remove_tempo_change\" must be a 16-byte `(f64, f64)` of \
+                     `(beat, cur_time)`"
+                );
+                let beat = read_f64(&val[..8]);
+                Some(self.update_tempo_map(grid_state, read_f64(&val[8..]), |tempo_map| {
+                    tempo_map.remove_tempo_change(beat);
+                }))
+            },
+            "set_time_signature" => {
+                assert_eq!(
+                    val.len(),
+                    18,
+                    "Message for \"set_time_signature\" must be a 16-byte `(f64, f64)` of \
+                     `(beat, cur_time)` followed by the numerator and denominator bytes"
+                );
+                let time_signature = match TimeSignature::new(val[16], val[17]) {
+                    Some(time_signature) => time_signature,
+                    None => {
+                        error!("Invalid time signature: {}/{}", val[16], val[17]);
+                        return None;
+                    },
+                };
+                let beat = read_f64(&val[..8]);
+                Some(self.update_tempo_map(grid_state, read_f64(&val[8..16]), |tempo_map| {
+                    tempo_map.set_time_signature(beat, time_signature)
+                }))
+            },
+            "remove_time_signature_change" => {
+                assert_eq!(
+                    val.len(),
+                    16,
+                    "Message for \"remove_time_signature_change\" must be a 16-byte `(f64, f64)` \
+                     of `(beat, cur_time)`"
+                );
+                let beat = read_f64(&val[..8]);
+                Some(self.update_tempo_map(grid_state, read_f64(&val[8..]), |tempo_map| {
+                    tempo_map.remove_time_signature_change(beat);
+                }))
+            },
             "toggle_loop" => {
                 assert_eq!(
                     val.len(),
@@ -452,6 +1307,7 @@ impl GridHandler<usize, MidiEditorGridRenderer> for MIDIEditorGridHandler {
                     Some(loop_handle) => {
                         scheduler::cancel_loop(loop_handle, true);
                         self.loop_handle = None;
+                        self.clips.session.clear();
                     },
                     None =>
                         self.loop_handle = scheduler::init_scheduler_loop(
@@ -464,6 +1320,69 @@ impl GridHandler<usize, MidiEditorGridRenderer> for MIDIEditorGridHandler {
 
                 None
             },
+            "transport" => {
+                assert!(
+                    val.len() >= 9,
+                    "Message for \"transport\" must be a command byte followed by an `f64` of \
+                     `cur_time`"
+                );
+                let cur_time = read_f64(&val[1..]);
+
+                match TransportCommand::from_u8(val[0]) {
+                    Some(TransportCommand::Play) => self.transport_play(grid_state, cur_time),
+                    Some(TransportCommand::Pause) => {
+                        self.transport_pause(grid_state, cur_time);
+                    },
+                    Some(TransportCommand::Stop) => self.transport_stop(grid_state, cur_time),
+                    Some(TransportCommand::Seek) => {
+                        assert_eq!(
+                            val.len(),
+                            17,
+                            "Seek transport message must include an `f64` of the beat to seek to"
+                        );
+                        let beat = read_f64(&val[9..]);
+                        self.transport_seek(grid_state, cur_time, beat);
+                    },
+                    None => error!("Unknown MIDI editor transport command: {}", val[0]),
+                }
+
+                // Respond with the current beat position of the cursor
+                Some((grid_state.cursor_pos_beats as f64).to_ne_bytes().to_vec())
+            },
+            "get_bounce_events" => {
+                assert_eq!(
+                    val.len(),
+                    24,
+                    "Message for \"get_bounce_events\" must be a 24-byte `(f64, f64, f64)` of \
+                     `(start_beat, end_beat, sample_rate)`"
+                );
+                let (start_beat, end_beat) = (read_f64(&val[..8]), read_f64(&val[8..16]));
+                let groove = self.groove;
+                let schedule = offline_render::collect_bounce_events(
+                    grid_state.data.iter_events(None).map(|event| skip_list::NoteEvent {
+                        beat: groove.apply(event.beat as f64) as f32,
+                        ..event
+                    }),
+                    &self.note_layout,
+                    &self.tempo_map,
+                    read_f64(&val[16..]),
+                    start_beat,
+                    end_beat.max(start_beat),
+                );
+                Some(serde_json::to_vec(&schedule).expect("Failed to serialize bounce events"))
+            },
+            "midi_input" => {
+                assert!(
+                    val.len() == 10 || val.len() == 11,
+                    "Message for \"midi_input\" must be an 8-byte `f64` of `cur_time` followed by \
+                     a 2 or 3-byte raw MIDI message"
+                );
+                match self.mpe_input.parse(&self.mpe, &val[8..]) {
+                    Some(event) => self.handle_midi_input(read_f64(&val[..8]), event),
+                    None => trace!("Ignoring unsupported MIDI input message: {:?}", &val[8..]),
+                }
+                None
+            },
             "toggle_recording_midi" => {
                 assert_eq!(
                     val.len(),
@@ -479,6 +1398,7 @@ impl GridHandler<usize, MidiEditorGridRenderer> for MIDIEditorGridHandler {
                 match self.midi_recording_ctx {
                     Some(ctx) => {
                         midi_recording::stop_recording_midi(ctx, cur_time);
+                        self.midi_recording_ctx = None;
                         None
                     },
                     None => {
@@ -506,139 +1426,72 @@ impl MIDIEditorGridHandler {
         // Get an iterator of sorted attack/release events to process
         let events = grid_state.data.iter_events(None);
 
-        // Trigger all of the events with a custom callback that records the voice index to use for
-        // each of them.
-        //
-        // `scheduled_events` is an array of `is_attack` flags represented as bytes for transfer
-        // across the FFI.
-        let mut is_attack_flags: Vec<u8> = Vec::with_capacity(events.size_hint().0 * 2);
-        let mut note_ids: Vec<usize> = Vec::with_capacity(events.size_hint().0 / 2);
-        let mut event_timings: Vec<f64> = Vec::with_capacity(events.size_hint().0);
+        let mut scheduled_events = scheduler::ScheduledEvents::default();
         for event in events {
-            let note_id = grid_state.conf.row_count - event.line_ix;
-            note_ids.push(note_id);
-            is_attack_flags.push(tern(event.is_start, 1, 0));
-
-            let event_time_seconds = ((event.beat as f64 / self.bpm) * 60.0) / 4.0;
-            event_timings.push(event_time_seconds);
+            scheduled_events.push_note_event(
+                &grid_state.data,
+                &self.note_layout,
+                event,
+                f64::INFINITY,
+                |beat| self.tempo_map.beat_to_seconds(beat),
+            );
         }
 
         // Ship all of these events over to be scheduled and played
-        js::midi_editor_schedule_events(&self.vc_id, &is_attack_flags, &note_ids, &event_timings);
+        scheduled_events.schedule(self);
     }
 
-    fn move_note_vertical(
-        &self,
-        up: bool,
-        grid_state: &mut GridState<usize>,
-        notes_to_play: &mut Vec<usize>,
-        mut note_data: SelectedNoteData,
-        line_diff_vertical: usize,
-    ) -> SelectedNoteData {
-        let cond = tern(
-            up,
-            note_data.line_ix >= line_diff_vertical,
-            note_data.line_ix + line_diff_vertical < grid_state.conf.row_count,
-        );
-        if !cond {
-            return note_data;
-        }
-
-        let dst_line_ix = if up {
-            note_data.line_ix - line_diff_vertical
-        } else {
-            note_data.line_ix + line_diff_vertical
-        };
-        notes_to_play.push(grid_state.conf.row_count - dst_line_ix);
-
-        let move_failed = grid_state.data.move_note_vertical(
-            note_data.line_ix,
-            dst_line_ix,
-            note_data.start_beat,
-        );
-        if !move_failed {
-            note_data.line_ix = dst_line_ix;
-            js::set_attr(
-                note_data.dom_id,
-                "y",
-                &(note_data.line_ix * grid_state.conf.padded_line_height()
-                    + grid_state.conf.cursor_gutter_height)
-                    .to_string(),
-            );
+    /// Moves all selected notes `semitones` semitones up as a group, playing them at their new
+    /// pitches.  If any of them can't be moved, none of them are.  While the grid is folded to the
+    /// used notes, they're moved by lines instead.
+    fn transpose_selected_notes(&mut self, grid_state: &mut GridState<usize>, semitones: isize) {
+        let selection: Vec<SelectedNoteData> = grid_state.selected_notes.iter().cloned().collect();
+        if grid_state.data.transpose(&selection, semitones).is_err() {
+            return;
         }
 
-        note_data
-    }
-
-    fn move_notes_vertical(
-        &mut self,
-        up: bool,
-        grid_state: &mut GridState<usize>,
-        line_diff_vertical: usize,
-    ) {
-        let (mut notes_to_play, sorted_selected_notes): (Vec<usize>, Vec<SelectedNoteData>) = {
-            let notes = grid_state.get_sorted_selected_notes(!up);
-            let notes_to_play: Vec<usize> = Vec::with_capacity(notes.len());
-
-            // We have to `.collect()` these since the reference is retained in the iterator type.
-            // Very sad.
-            (notes_to_play, notes.into_iter().cloned().collect())
-        };
-
-        grid_state.selected_notes = sorted_selected_notes
+        let conf = &grid_state.conf;
+        grid_state.selected_notes = selection
             .into_iter()
-            .map(|note_data| {
-                self.move_note_vertical(
-                    up,
-                    grid_state,
-                    &mut notes_to_play,
-                    note_data,
-                    line_diff_vertical,
-                )
+            .map(|mut note_data| {
+                note_data.line_ix = (note_data.line_ix as isize - semitones) as usize;
+                js::set_attr(
+                    note_data.dom_id,
+                    "y",
+                    &(note_data.line_ix * conf.padded_line_height() + conf.cursor_gutter_height)
+                        .to_string(),
+                );
+                js::midi_editor_trigger_attack_release(
+                    &self.vc_id,
+                    self.note_layout.line_note(note_data.line_ix),
+                    0.08,
+                );
+                note_data
             })
             .collect();
-
-        for note_id in notes_to_play {
-            js::midi_editor_trigger_attack_release(&self.vc_id, note_id, 0.08);
-        }
     }
 
-    fn move_selected_notes_horizontal(
-        &mut self,
-        grid_state: &mut GridState<usize>,
-        right: bool,
-        beat_diff_horizontal: f32,
-    ) {
-        let beats_to_move = beat_diff_horizontal * tern(right, 1.0, -1.0);
-        let cloned_conf = grid_state.conf.clone();
-
-        let sorted_selected_notes: Vec<SelectedNoteData> = grid_state
-            .get_sorted_selected_notes(right)
-            .into_iter()
-            .cloned()
-            .collect();
-
-        let move_note_horizontal = move |data: &mut NoteLines<usize>,
-                                         mut note_data: SelectedNoteData|
-              -> SelectedNoteData {
-            let new_start_beat =
-                data.move_note_horizontal(note_data.line_ix, note_data.start_beat, beats_to_move);
-
-            js::set_attr(
-                note_data.dom_id,
-                "x",
-                &(cloned_conf.beats_to_px(new_start_beat)).to_string(),
-            );
-
-            note_data.start_beat = new_start_beat;
-            note_data
-        };
+    /// Moves all selected notes `beats` beats later as a group.  If any of them can't be moved,
+    /// none of them are.
+    fn shift_selected_notes(&mut self, grid_state: &mut GridState<usize>, beats: f32) {
+        let selection: Vec<SelectedNoteData> = grid_state.selected_notes.iter().cloned().collect();
+        if grid_state.data.shift(&selection, beats).is_err() {
+            return;
+        }
 
-        let new_selected_notes = sorted_selected_notes
+        let conf = &grid_state.conf;
+        grid_state.selected_notes = selection
             .into_iter()
-            .map(|note_data| move_note_horizontal(&mut grid_state.data, note_data))
+            .map(|mut note_data| {
+                note_data.start_beat += beats;
+                js::set_attr(
+                    note_data.dom_id,
+                    "x",
+                    &conf.beats_to_px(note_data.start_beat).to_string(),
+                );
+                note_data
+            })
             .collect();
-        grid_state.selected_notes = new_selected_notes;
     }
 
     fn adjust_note_lengths(
@@ -647,6 +1500,11 @@ impl MIDIEditorGridHandler {
         is_left: bool,
         adjustment_amount_beats: f32,
     ) {
+        // Hits in drum maps are one-shots, so their lengths are left alone
+        if self.note_layout.is_drum_map() {
+            return;
+        }
+
         let mut old_selected_notes = grid_state.selected_notes.drain().collect::<Vec<_>>();
         // We need to sort the selected notes so that those on the side towards which we are
         // adjusting them are updated first, giving the maximum opportunity for movement.
@@ -716,11 +1574,15 @@ impl MIDIEditorGridHandler {
             let dom_id = removed_note.data.get_id();
             debug_assert!(dom_id == selected_note_data.dom_id);
             let new_note = NoteBox {
+                id: removed_note.id,
                 bounds: NoteBoxBounds {
                     start_beat: new_note_start_beat,
                     end_beat: new_note_end_beat,
                 },
                 data: removed_note.data,
+                velocity: removed_note.velocity,
+                pitch_bend: removed_note.pitch_bend,
+                expression: removed_note.expression,
             };
             new_selected_notes.insert(SelectedNoteData::from_note_box(
                 selected_note_data.line_ix,
@@ -743,44 +1605,111 @@ impl MIDIEditorGridHandler {
         }
     }
 
+    /// Adds `adjustment_amount` to the velocity of all selected notes, clamping them to the valid
+    /// MIDI velocity range.
+    fn adjust_note_velocities(
+        &mut self,
+        grid_state: &mut GridState<usize>,
+        adjustment_amount: i16,
+    ) {
+        let old_selected_notes = grid_state.selected_notes.drain().collect::<Vec<_>>();
+        let new_selected_notes = &mut grid_state.selected_notes;
+
+        for selected_note_data in old_selected_notes {
+            let new_velocity = (selected_note_data.velocity as i16 + adjustment_amount)
+                .clamp(MIN_NOTE_VELOCITY as i16, MAX_NOTE_VELOCITY as i16) as u8;
+
+            let line = &mut grid_state.data.lines[selected_note_data.line_ix];
+            let mut note = line
+                .remove(selected_note_data.start_beat)
+                .expect("Tried removing existing note but it wasn't found");
+            debug_assert!(note.data.get_id() == selected_note_data.dom_id);
+            note.velocity = new_velocity;
+            let insert_err = line.insert(note);
+            debug_assert!(insert_err.is_none());
+
+            MidiEditorGridRenderer::set_note_velocity(selected_note_data.dom_id, new_velocity);
+            new_selected_notes.insert(SelectedNoteData {
+                velocity: new_velocity,
+                ..selected_note_data
+            });
+        }
+    }
+
     pub fn play_selected_notes(&mut self, grid_state: &GridState<usize>) {
         for SelectedNoteData { line_ix, .. } in grid_state.selected_notes.iter() {
-            js::midi_editor_trigger_attack(&self.vc_id, grid_state.conf.row_count - *line_ix);
+            js::midi_editor_trigger_attack(&self.vc_id, self.note_layout.line_note(*line_ix));
         }
     }
 
     pub fn release_selected_notes(&mut self, grid_state: &GridState<usize>) {
         for SelectedNoteData { line_ix, .. } in grid_state.selected_notes.iter() {
-            js::midi_editor_trigger_release(&self.vc_id, grid_state.conf.row_count - *line_ix);
+            js::midi_editor_trigger_release(&self.vc_id, self.note_layout.line_note(*line_ix));
+        }
+    }
+
+    fn set_cursor_pos_beats(&self, grid_state: &mut GridState<usize>, beats: f64) {
+        grid_state.cursor_pos_beats = beats as f32;
+        let cursor_pos_px = grid_state.conf.beats_to_px(beats as f32);
+        MidiEditorGridRenderer::set_cursor_pos(grid_state.cursor_dom_id, cursor_pos_px);
+    }
+
+    /// Starts playing from the current cursor position if not already playing.  If no loop end mark
+    /// is set, the whole composition is looped.
+    pub fn transport_play(&mut self, grid_state: &mut GridState<usize>, cur_time: f64) {
+        if self.loop_handle.is_some() {
+            return;
         }
+
+        self.loop_handle = scheduler::init_scheduler_loop(
+            cur_time,
+            grid_state.cursor_pos_beats as f64,
+            self,
+            grid_state,
+        );
     }
 
-    pub fn time_to_beats(&self, time_seconds: f64) -> f64 {
-        let time_minutes = time_seconds / 60.;
-        time_minutes * self.bpm
+    /// Stops playback, leaving the cursor where it currently is so that playback can be resumed
+    /// from that point.  Returns `true` if playback was active.
+    pub fn transport_pause(&mut self, grid_state: &mut GridState<usize>, cur_time: f64) -> bool {
+        let loop_handle = match self.loop_handle.take() {
+            Some(loop_handle) => loop_handle,
+            None => return false,
+        };
+
+        let cur_pos_beats = unsafe { (*loop_handle).get_cur_cursor_pos_beats(cur_time) };
+        scheduler::cancel_loop(loop_handle, true);
+        self.clips.session.clear();
+        self.set_cursor_pos_beats(grid_state, cur_pos_beats);
+        true
     }
 
-    pub fn beats_to_seconds(&self, beats: f64) -> f64 {
-        let beats_per_second = self.bpm / 60.;
-        beats / beats_per_second
+    /// Stops playback and moves the cursor back to the loop start mark or the beginning.
+    pub fn transport_stop(&mut self, grid_state: &mut GridState<usize>, cur_time: f64) {
+        self.transport_pause(grid_state, cur_time);
+
+        let start_beat = self
+            .loop_start_mark_measure
+            .as_ref()
+            .map(|descriptor| descriptor.measure as f64)
+            .unwrap_or(0.);
+        self.set_cursor_pos_beats(grid_state, start_beat);
+    }
+
+    /// Moves the cursor to the provided beat, continuing playback from there if it was playing.
+    pub fn transport_seek(&mut self, grid_state: &mut GridState<usize>, cur_time: f64, beat: f64) {
+        let was_playing = self.transport_pause(grid_state, cur_time);
+        self.set_cursor_pos_beats(grid_state, beat.max(0.));
+
+        if was_playing {
+            self.transport_play(grid_state, cur_time);
+        }
     }
 }
 
 /// Return `MidiEditor` instance as a `ViewContext` given the provided config string.
 pub fn mk_midi_editor(config: Option<&str>, uuid: Uuid) -> Box<dyn ViewContext> {
-    let grid_conf = GridConf {
-        gutter_height: constants::CURSOR_GUTTER_HEIGHT,
-        row_count: constants::LINE_COUNT,
-        beat_length_px: constants::BEAT_LENGTH_PX,
-        cursor_gutter_height: constants::CURSOR_GUTTER_HEIGHT,
-        line_border_width: constants::LINE_BORDER_WIDTH,
-        line_height: constants::LINE_HEIGHT,
-        note_snap_beat_interval: constants::NOTE_SNAP_BEAT_INTERVAL,
-        grid_width: constants::GRID_WIDTH,
-        measure_width_px: constants::BEATS_PER_MEASURE * constants::BEAT_LENGTH_PX,
-    };
-
-    let conf = if let Some(config) = config {
+    let mut conf = if let Some(config) = config {
         match serde_json::from_str(config) {
             Ok(conf) => conf,
             Err(err) => {
@@ -792,8 +1721,27 @@ pub fn mk_midi_editor(config: Option<&str>, uuid: Uuid) -> Box<dyn ViewContext>
         MIDIEditorConf::default()
     };
 
-    let view_context = MIDIEditorGridHandler::new(&grid_conf, uuid, conf);
-    let grid: Box<MidiGrid> = box Grid::new(grid_conf, view_context, uuid);
+    let saved_grid_state = conf.grid.take();
+    let view_context = MIDIEditorGridHandler::new(uuid, conf);
+    let grid_conf = GridConf {
+        gutter_height: constants::CURSOR_GUTTER_HEIGHT,
+        row_count: view_context.note_layout.line_count(),
+        beat_length_px: constants::BEAT_LENGTH_PX,
+        cursor_gutter_height: constants::CURSOR_GUTTER_HEIGHT,
+        line_border_width: constants::LINE_BORDER_WIDTH,
+        line_height: constants::LINE_HEIGHT,
+        note_snap_beat_interval: constants::NOTE_SNAP_BEAT_INTERVAL,
+        grid_width: constants::INITIAL_GRID_VIEWPORT_WIDTH,
+        measure_width_px: constants::BEATS_PER_MEASURE * constants::BEAT_LENGTH_PX,
+        zoom_x: 1.0,
+        zoom_y: 1.0,
+        time_signature_changes: view_context.tempo_map.time_signature_changes().to_vec(),
+    };
+    let grid: Box<MidiGrid> = match saved_grid_state {
+        Some(saved_grid_state) =>
+            Box::new(Grid::load(grid_conf, view_context, uuid, saved_grid_state)),
+        None => Box::new(Grid::new(grid_conf, view_context, uuid)),
+    };
 
     grid
 }
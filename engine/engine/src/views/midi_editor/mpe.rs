@@ -0,0 +1,171 @@
+//! MPE (MIDI Polyphonic Expression) input for the MIDI editor.  MPE controllers play each note on
+//! its own MIDI channel so that its pitch bend, pressure, and timbre (CC 74) can change
+//! independently of the other notes that are held.  When MPE is enabled, messages on those member
+//! channels are demultiplexed into expression events for the notes playing on them, which are
+//! routed to the voices playing the notes and recorded into the notes' expression curves.
+//!
+//! Only the lower zone is supported, so channel 1 is the master channel and channels 2-16 are
+//! member channels.  Messages on the master channel are handled the same as when MPE is disabled.
+
+use common::{
+    expression::{ExpressionDimension, ExpressionPoint},
+    pitch_bend::{PitchBendPoint, MIDI_PITCH_BEND_CENTER},
+};
+
+use super::{midi_input::*, *};
+
+const MASTER_CHANNEL: u8 = 0;
+const TIMBRE_CONTROL_INDEX: u8 = 74;
+/// The pitch bend range of member channels recommended by the MPE spec
+const DEFAULT_MPE_PITCH_BEND_RANGE_SEMITONES: f32 = 48.;
+/// Recorded values that come sooner than this after the last recorded point of their curve replace
+/// its value rather than adding a new point, since controllers send expression very often.
+const MIN_RECORDED_POINT_SPACING_BEATS: f32 = 1. / 64.;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MPEConf {
+    pub enabled: bool,
+    /// The range in semitones of pitch bends on member channels
+    pub pitch_bend_range: f32,
+}
+
+impl Default for MPEConf {
+    fn default() -> Self {
+        MPEConf {
+            enabled: false,
+            pitch_bend_range: DEFAULT_MPE_PITCH_BEND_RANGE_SEMITONES,
+        }
+    }
+}
+
+/// Demultiplexes MPE input by tracking the note that's playing on each member channel
+#[derive(Default)]
+pub struct MPEInput {
+    channel_notes: [Option<usize>; 16],
+}
+
+impl MPEInput {
+    /// Parses a raw 2 or 3-byte MIDI message.  If MPE is enabled, pitch bends, channel pressure,
+    /// and CC 74 on member channels are turned into expression events for the note playing on the
+    /// channel and are dropped if there isn't one.  Everything else is parsed with
+    /// `MIDIInputEvent::from_bytes`.
+    pub fn parse(&mut self, conf: &MPEConf, bytes: &[u8]) -> Option<MIDIInputEvent> {
+        if !conf.enabled || bytes.is_empty() || bytes[0] & 0x0F == MASTER_CHANNEL {
+            return MIDIInputEvent::from_bytes(bytes);
+        }
+        if bytes[1..].iter().any(|&byte| byte > 127) {
+            return None;
+        }
+
+        let channel = (bytes[0] & 0x0F) as usize;
+        let channel_note = self.channel_notes[channel];
+        let expression = |dimension: ExpressionDimension, value: f32| {
+            channel_note.map(|note_id| MIDIInputEvent::NoteExpression {
+                note_id,
+                dimension,
+                value,
+            })
+        };
+        match (bytes[0] & 0xF0, bytes.len()) {
+            (STATUS_CHANNEL_PRESSURE, 2) =>
+                expression(ExpressionDimension::Pressure, bytes[1] as f32 / 127.),
+            (STATUS_PITCH_BEND, 3) => {
+                let value = bytes[1] as u16 | (bytes[2] as u16) << 7;
+                let center = MIDI_PITCH_BEND_CENTER as f32;
+                let semitones = (value as f32 - center) / center * conf.pitch_bend_range;
+                expression(ExpressionDimension::PitchBend, semitones)
+            },
+            (STATUS_CONTROL_CHANGE, 3) if bytes[1] == TIMBRE_CONTROL_INDEX =>
+                expression(ExpressionDimension::Timbre, bytes[2] as f32 / 127.),
+            _ => {
+                let event = MIDIInputEvent::from_bytes(bytes);
+                match event {
+                    Some(MIDIInputEvent::NoteOn { note_id, .. }) =>
+                        self.channel_notes[channel] = Some(note_id),
+                    Some(MIDIInputEvent::NoteOff { note_id }) if channel_note == Some(note_id) =>
+                        self.channel_notes[channel] = None,
+                    _ => (),
+                }
+                event
+            },
+        }
+    }
+
+    /// Forgets the notes playing on all channels
+    pub fn reset(&mut self) { self.channel_notes = [None; 16]; }
+}
+
+/// Expression recorded for a note while it's held.  Offsets are in beats from when the note
+/// started playing.
+#[derive(Default)]
+pub struct RecordedExpression {
+    pitch_bend: Vec<ExpressionPoint>,
+    expression: NoteExpression,
+}
+
+impl RecordedExpression {
+    fn curve_mut(&mut self, dimension: ExpressionDimension) -> &mut Vec<ExpressionPoint> {
+        match self.expression.curve_mut(dimension) {
+            Some(curve) => curve,
+            None => &mut self.pitch_bend,
+        }
+    }
+
+    /// Records `value` at `beat_offset`.  Values must be recorded in the order that they're played.
+    pub fn record(&mut self, beat_offset: f32, dimension: ExpressionDimension, value: f32) {
+        let curve = self.curve_mut(dimension);
+        match curve.last_mut() {
+            Some(last) if beat_offset - last.beat_offset < MIN_RECORDED_POINT_SPACING_BEATS =>
+                last.value = value,
+            _ => curve.push(ExpressionPoint { beat_offset, value }),
+        }
+    }
+
+    /// Returns the pitch bend curve and expression of the recorded note once it's been quantized
+    /// to be `width` beats long.  `start_offset` is how many beats after the quantized start of the
+    /// note that it started playing.  Values played before the quantized start are moved to it.
+    pub fn into_note_curves(
+        mut self,
+        start_offset: f32,
+        width: f32,
+    ) -> (Vec<PitchBendPoint>, NoteExpression) {
+        for curve in &mut [
+            &mut self.pitch_bend,
+            &mut self.expression.pressure,
+            &mut self.expression.timbre,
+        ] {
+            for point in curve.iter_mut() {
+                point.beat_offset = (point.beat_offset + start_offset).max(0.);
+            }
+        }
+
+        let mut pitch_bend: Vec<PitchBendPoint> = self
+            .pitch_bend
+            .into_iter()
+            .map(|point| PitchBendPoint {
+                beat_offset: point.beat_offset,
+                semitones: point.value,
+            })
+            .collect();
+        common::pitch_bend::normalize_curve(&mut pitch_bend, width);
+        self.expression.normalize(width);
+        (pitch_bend, self.expression)
+    }
+}
+
+/// Returns `(beat, dimension, value)` for each point of `note`'s expression that is reached before
+/// both the end of the note and `end_beat`.
+pub fn get_note_expression_events<S>(
+    note: &NoteBox<S>,
+    end_beat: f64,
+) -> Vec<(f64, ExpressionDimension, f32)> {
+    let end_beat = end_beat.min(note.bounds.end_beat as f64);
+    let start_beat = note.bounds.start_beat as f64;
+    note.expression
+        .events()
+        .into_iter()
+        .map(|(beat_offset, dimension, value)| (start_beat + beat_offset as f64, dimension, value))
+        .take_while(|&(beat, ..)| beat <= end_beat)
+        .collect()
+}
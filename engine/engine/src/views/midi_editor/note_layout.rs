@@ -0,0 +1,288 @@
+//! Controls which notes the rows of the MIDI editor's grid show.  Each MIDI editor shows a range of
+//! MIDI notes with the highest one at the top, and the range is saved with it.  When the grid is
+//! folded to the used notes, only the rows of notes that are used by any of the editor's clips are
+//! shown, which makes it easier to work with sparse parts like drums.
+//!
+//! In drum map mode, each line is a named drum sound rather than a pitch.  Drum sounds are
+//! one-shots, so notes are drawn as hits with a single click and their lengths can't be changed.
+//!
+//! Notes are stored by the index of the line that they're on, so changing the layout moves all of
+//! the notes in the grid and in the other clips to the lines that show their notes in the new one.
+
+use common::tuning::MIDI_NOTE_COUNT;
+
+use super::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteRange {
+    pub lowest_note: usize,
+    pub line_count: usize,
+}
+
+/// The range that every MIDI editor showed before it could be changed
+impl Default for NoteRange {
+    fn default() -> Self {
+        NoteRange {
+            lowest_note: 1,
+            line_count: constants::LINE_COUNT,
+        }
+    }
+}
+
+impl NoteRange {
+    /// Returns `None` if the range is empty or extends past the highest MIDI note
+    pub fn new(lowest_note: usize, line_count: usize) -> Option<Self> {
+        if line_count == 0 || lowest_note + line_count > MIDI_NOTE_COUNT {
+            return None;
+        }
+        Some(NoteRange {
+            lowest_note,
+            line_count,
+        })
+    }
+
+    pub fn highest_note(&self) -> usize { self.lowest_note + self.line_count - 1 }
+
+    pub fn contains(&self, note: usize) -> bool {
+        note >= self.lowest_note && note <= self.highest_note()
+    }
+}
+
+/// A line of a drum map, which plays `note` and is labeled with `name`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DrumSound {
+    pub name: String,
+    pub note: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteLayout {
+    pub range: NoteRange,
+    /// Set while the grid is folded to the used notes.  Holds the note shown by each line, from
+    /// the highest note to the lowest.
+    #[serde(default)]
+    pub folded_notes: Option<Vec<usize>>,
+    /// Set while the grid is in drum map mode, in which case it holds the sound of each line from
+    /// the top down and takes the place of the range.  The range is kept so that it's restored
+    /// when leaving drum map mode.
+    #[serde(default)]
+    pub drum_map: Option<Vec<DrumSound>>,
+}
+
+impl NoteLayout {
+    pub fn unfolded(range: NoteRange) -> Self {
+        NoteLayout {
+            range,
+            folded_notes: None,
+            drum_map: None,
+        }
+    }
+
+    /// Only shows the lines of the notes in `used_notes` that are in `range`.  If there aren't any,
+    /// the whole range is shown since the grid needs at least one line to draw notes on.
+    pub fn folded(range: NoteRange, used_notes: impl IntoIterator<Item = usize>) -> Self {
+        let mut notes: Vec<usize> = used_notes
+            .into_iter()
+            .filter(|&note| range.contains(note))
+            .collect();
+        if notes.is_empty() {
+            return NoteLayout::unfolded(range);
+        }
+
+        notes.sort_unstable_by(|a, b| b.cmp(a));
+        notes.dedup();
+        NoteLayout {
+            range,
+            folded_notes: Some(notes),
+            drum_map: None,
+        }
+    }
+
+    /// Shows a line for each of `sounds`.  Returns `None` if there are no sounds, if any of them
+    /// plays a note past the highest MIDI note, or if two of them play the same note.
+    pub fn with_drum_map(range: NoteRange, sounds: Vec<DrumSound>) -> Option<Self> {
+        let is_valid = |(i, sound): (usize, &DrumSound)| {
+            sound.note < MIDI_NOTE_COUNT && sounds[..i].iter().all(|other| other.note != sound.note)
+        };
+        if sounds.is_empty() || !sounds.iter().enumerate().all(is_valid) {
+            return None;
+        }
+
+        Some(NoteLayout {
+            range,
+            folded_notes: None,
+            drum_map: Some(sounds),
+        })
+    }
+
+    pub fn is_folded(&self) -> bool { self.folded_notes.is_some() }
+
+    pub fn is_drum_map(&self) -> bool { self.drum_map.is_some() }
+
+    pub fn line_count(&self) -> usize {
+        match (&self.drum_map, &self.folded_notes) {
+            (Some(sounds), _) => sounds.len(),
+            (None, Some(notes)) => notes.len(),
+            (None, None) => self.range.line_count,
+        }
+    }
+
+    /// Returns the MIDI note played by the line at `line_ix`, which must be on the grid
+    pub fn line_note(&self, line_ix: usize) -> usize {
+        match (&self.drum_map, &self.folded_notes) {
+            (Some(sounds), _) => sounds[line_ix].note,
+            (None, Some(notes)) => notes[line_ix],
+            (None, None) => self.range.highest_note() - line_ix,
+        }
+    }
+
+    /// Returns the index of the line that plays the MIDI note `note`, if it's shown
+    pub fn note_line(&self, note: usize) -> Option<usize> {
+        match (&self.drum_map, &self.folded_notes) {
+            (Some(sounds), _) => sounds.iter().position(|sound| sound.note == note),
+            (None, Some(notes)) => notes.iter().position(|&folded_note| folded_note == note),
+            (None, None) if self.range.contains(note) => Some(self.range.highest_note() - note),
+            (None, None) => None,
+        }
+    }
+
+    /// Returns the label of each line, which are only shown in drum map mode
+    pub fn line_labels(&self) -> Vec<&str> {
+        match &self.drum_map {
+            Some(sounds) => sounds.iter().map(|sound| sound.name.as_str()).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the index of the line in `other` that shows the same note as the line at `line_ix`
+    pub fn remap_line(&self, other: &NoteLayout, line_ix: usize) -> Option<usize> {
+        other.note_line(self.line_note(line_ix))
+    }
+}
+
+impl MIDIEditorGridHandler {
+    /// Returns the MIDI notes of all of the lines that have notes in any clip
+    fn used_notes(&self, grid_state: &GridState<usize>) -> Vec<usize> {
+        let clip_notes = self.clips.clips.iter().map(|clip| &clip.notes);
+        std::iter::once(&grid_state.data)
+            .chain(clip_notes)
+            .flat_map(|notes| notes.lines.iter().enumerate())
+            .filter(|(_, line)| !line.is_empty())
+            .map(|(line_ix, _)| self.note_layout.line_note(line_ix))
+            .collect()
+    }
+
+    /// Switches the grid to `layout`, moving the notes of all clips to the lines that show their
+    /// notes in it.  Returns `false` without changing anything if any of the notes wouldn't be
+    /// shown or if MIDI is being recorded.
+    fn set_note_layout(&mut self, grid_state: &mut GridState<usize>, layout: NoteLayout) -> bool {
+        if self.midi_recording_ctx.is_some() {
+            warn!("Can't change the note layout while recording MIDI");
+            return false;
+        }
+        let used_notes = self.used_notes(grid_state);
+        if let Some(note) = used_notes.iter().find(|&&note| layout.note_line(note).is_none()) {
+            warn!("Can't change the note layout since it would hide note {}", note);
+            return false;
+        }
+
+        let line_count = layout.line_count();
+        let old_layout = std::mem::replace(&mut self.note_layout, layout);
+        let new_layout = &self.note_layout;
+        let remap_line = |line_ix: usize| old_layout.remap_line(new_layout, line_ix);
+        grid_state.set_row_count::<MidiEditorGridRenderer>(line_count, remap_line);
+        for clip in &mut self.clips.clips {
+            clip.notes.remap_lines(line_count, remap_line);
+        }
+
+        self.render_scale_highlighting(grid_state);
+        self.on_rerender(grid_state);
+        let grid_height = grid_state.conf.grid_height() + self.lane_area_height();
+        js::set_grid_height(&self.vc_id, grid_height);
+        true
+    }
+
+    /// Labels the lines of the grid with the names of the sounds of the drum map, or removes the
+    /// labels if the grid isn't in drum map mode.
+    pub(super) fn render_line_labels(&self, conf: &GridConf) {
+        let labels = serde_json::to_string(&self.note_layout.line_labels())
+            .expect("Failed to serialize line labels");
+        js::set_grid_line_labels(
+            &self.vc_id,
+            &labels,
+            conf.cursor_gutter_height,
+            conf.padded_line_height(),
+        );
+    }
+
+    /// Shows the notes in `range`, keeping the grid folded if it is.  Returns `false` if the range
+    /// is invalid or doesn't include all of the notes.  In drum map mode, the range is only stored
+    /// for when the mode is left.
+    pub(super) fn set_note_range(
+        &mut self,
+        grid_state: &mut GridState<usize>,
+        range: NoteRange,
+    ) -> bool {
+        let range = match NoteRange::new(range.lowest_note, range.line_count) {
+            Some(range) => range,
+            None => {
+                error!("Invalid note range: {:?}", range);
+                return false;
+            },
+        };
+
+        if self.note_layout.is_drum_map() {
+            self.note_layout.range = range;
+            return true;
+        }
+        let layout = if self.note_layout.is_folded() {
+            NoteLayout::folded(range, self.used_notes(grid_state))
+        } else {
+            NoteLayout::unfolded(range)
+        };
+        self.set_note_layout(grid_state, layout)
+    }
+
+    /// Folds the grid to the lines of the notes that are used or unfolds it to show the full range
+    pub(super) fn set_fold_to_used_notes(
+        &mut self,
+        grid_state: &mut GridState<usize>,
+        fold: bool,
+    ) -> bool {
+        if self.note_layout.is_drum_map() {
+            warn!("The grid can't be folded in drum map mode");
+            return false;
+        }
+        let range = self.note_layout.range;
+        let layout = if fold {
+            NoteLayout::folded(range, self.used_notes(grid_state))
+        } else {
+            NoteLayout::unfolded(range)
+        };
+        self.set_note_layout(grid_state, layout)
+    }
+
+    /// Switches the grid to drum map mode with a line for each of `sounds`, or back to showing the
+    /// range of notes if `sounds` is `None`.  Returns `false` if the drum map is invalid or doesn't
+    /// include all of the notes.
+    pub(super) fn set_drum_map(
+        &mut self,
+        grid_state: &mut GridState<usize>,
+        sounds: Option<Vec<DrumSound>>,
+    ) -> bool {
+        let range = self.note_layout.range;
+        let layout = match sounds {
+            Some(sounds) => match NoteLayout::with_drum_map(range, sounds) {
+                Some(layout) => layout,
+                None => {
+                    error!("Invalid drum map");
+                    return false;
+                },
+            },
+            None => NoteLayout::unfolded(range),
+        };
+        self.set_note_layout(grid_state, layout)
+    }
+}
@@ -0,0 +1,45 @@
+//! Per-note pitch bend.  Each note can carry a curve that bends the pitch of the voice playing it,
+//! letting individual notes glide or vibrate.  Bends are scheduled immediately after the attack of
+//! the note that they belong to so that they're routed to whichever voice ends up playing it.
+
+use common::pitch_bend::{self, PitchBendPoint};
+
+use super::*;
+
+/// Returns `(beat, semitones)` for each point of `note`'s pitch bend curve that is reached before
+/// both the end of the note and `end_beat`.
+pub fn get_note_pitch_bend_events<S>(note: &NoteBox<S>, end_beat: f64) -> Vec<(f64, f32)> {
+    let end_beat = end_beat.min(note.bounds.end_beat as f64);
+    note.pitch_bend
+        .iter()
+        .map(|point| {
+            let beat = note.bounds.start_beat as f64 + point.beat_offset as f64;
+            (beat, point.semitones)
+        })
+        .take_while(|&(beat, _)| beat <= end_beat)
+        .collect()
+}
+
+impl MIDIEditorGridHandler {
+    /// Replaces the pitch bend curves of all selected notes with `curve`.  Points past the end of
+    /// each note are dropped, and an empty curve removes the bend entirely.
+    pub fn set_selected_notes_pitch_bend(
+        &mut self,
+        grid_state: &mut GridState<usize>,
+        curve: Vec<PitchBendPoint>,
+    ) {
+        for selected_note_data in grid_state.selected_notes.iter() {
+            let line = &mut grid_state.data.lines[selected_note_data.line_ix];
+            let mut note = line
+                .remove(selected_note_data.start_beat)
+                .expect("Tried removing existing note but it wasn't found");
+            debug_assert!(note.data.get_id() == selected_note_data.dom_id);
+
+            let mut note_curve = curve.clone();
+            pitch_bend::normalize_curve(&mut note_curve, note.bounds.width());
+            note.pitch_bend = note_curve;
+            let insert_err = line.insert(note);
+            debug_assert!(insert_err.is_none());
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//! Key and scale highlighting for the MIDI editor.  Rows for notes that aren't in the selected key
+//! are rendered differently, and notes that are drawn can optionally be snapped to the nearest row
+//! that is.  The key is also used to build the chords inserted by the chord tool.
+
+use common::theory::Key;
+
+use super::*;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ScaleConf {
+    pub key: Key,
+    /// If set, newly drawn notes are moved to the nearest line that's in `key`
+    pub snap_to_scale: bool,
+}
+
+impl MIDIEditorGridHandler {
+    /// Marks the background rows of all notes that aren't in the current key as out of scale.
+    pub fn render_scale_highlighting(&self, grid_state: &GridState<usize>) {
+        for (line_ix, &dom_id) in grid_state.background.grid_lines.iter().enumerate() {
+            if self.scale.key.contains(self.note_layout.line_note(line_ix)) {
+                js::remove_class(dom_id, "out-of-scale");
+            } else {
+                js::add_class(dom_id, "out-of-scale");
+            }
+        }
+    }
+
+    /// Returns the line closest to `line_ix` that's in the current key if snapping to the scale is
+    /// enabled.  If it's disabled or there's no such line on the grid, `line_ix` is returned.
+    pub fn snap_line_to_scale(&self, line_ix: usize) -> usize {
+        if !self.scale.snap_to_scale {
+            return line_ix;
+        }
+
+        let note = self
+            .scale
+            .key
+            .nearest_in_key(self.note_layout.line_note(line_ix));
+        self.note_layout.note_line(note).unwrap_or(line_ix)
+    }
+
+    /// Returns the lines of the notes of the current chord shape rooted at `line_ix`.  If any of
+    /// them are off of the grid, nothing is returned.
+    pub fn chord_lines(&self, line_ix: usize) -> Vec<usize> {
+        let notes = self
+            .chord_shape
+            .notes(&self.scale.key, self.note_layout.line_note(line_ix));
+        notes
+            .into_iter()
+            .map(|note| self.note_layout.note_line(note))
+            .collect::<Option<Vec<_>>>()
+            .unwrap_or_default()
+    }
+}
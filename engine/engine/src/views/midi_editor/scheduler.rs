@@ -1,8 +1,19 @@
 //! Scheduler for notes of the MIDI editor.  Allows for a composition to be played through or for
 //! part of it to be looped continuously.
 
-use super::{LoopMarkDescriptor, MIDIEditorGridHandler, MidiEditorGridRenderer};
-use crate::helpers::grid::prelude::*;
+use common::{expression::ExpressionDimension, tempo_map::TempoMap};
+
+use super::{
+    clips::{get_arrangement_pass_events, ArrangementEvent, SESSION_END_BEAT},
+    mpe,
+    note_layout::NoteLayout,
+    pitch_bend, LoopMarkDescriptor, MIDIEditorGridHandler, MidiEditorGridRenderer,
+};
+use crate::{
+    helpers::grid::{prelude::*, skip_list::NoteEvent},
+    metronome,
+    view_context::EngineEvent,
+};
 
 pub type SchedulerStateHandle = *mut SchedulerState;
 pub type SchedulerLoopHandle = usize;
@@ -11,9 +22,13 @@ pub struct SchedulerState {
     pub start_time: f64,
     pub interval_handle: SchedulerLoopHandle,
     pub cursor_animation_frame_handle: SchedulerLoopHandle,
-    pub end_time_of_last_scheduling_period: f64,
-    pub total_previously_scheduled_beats: f64,
+    /// The number of full passes through the loop that have been scheduled
+    pub scheduled_loop_count: usize,
+    /// The beat within the loop up to which events have been scheduled for the current pass
+    pub scheduled_through_beat: f64,
     pub schedule_offset_seconds: f64,
+    /// The `(start_beat, end_beat)` of the region that is being looped
+    pub loop_bounds: (f64, f64),
     pub state: &'static mut MIDIEditorGridHandler,
     pub grid_state: &'static mut GridState<usize>,
     pub cb: Closure<(dyn std::ops::FnMut(f64) + 'static)>,
@@ -22,29 +37,230 @@ pub struct SchedulerState {
 
 impl SchedulerState {
     pub fn get_cur_cursor_pos_beats(&self, cur_time: f64) -> f64 {
-        let start_mark_pos_beats: f64 = self
-            .state
-            .loop_start_mark_measure
-            .as_ref()
-            .map(|descriptor| descriptor.measure as f64)
-            .unwrap_or(0.);
-        let end_mark_pos_beats = match self.state.loop_end_mark_measure {
-            Some(LoopMarkDescriptor { measure, .. }) => measure as f64,
-            None => panic!(
-                "Tried to schedule a loop cursor animation without a loop end mark being set"
-            ),
-        };
-        let loop_length_beats = end_mark_pos_beats - start_mark_pos_beats;
+        let (start_mark_pos_beats, end_mark_pos_beats) = self.loop_bounds;
+        let tempo_map = &self.state.tempo_map;
+        let loop_length_seconds = tempo_map
+            .beats_to_seconds_from(start_mark_pos_beats, end_mark_pos_beats - start_mark_pos_beats);
 
         let time_since_start = cur_time - self.start_time;
-        let beats_since_start = self.state.time_to_beats(time_since_start);
-        let loops_since_start = beats_since_start / loop_length_beats;
-        let cur_loop_percentage_finished = loops_since_start.fract();
-        let cur_loop_beats_from_start = cur_loop_percentage_finished * loop_length_beats;
-        start_mark_pos_beats + cur_loop_beats_from_start
+        let loops_since_start = time_since_start / loop_length_seconds;
+        let cur_loop_seconds_from_start = loops_since_start.fract() * loop_length_seconds;
+        start_mark_pos_beats
+            + tempo_map.seconds_to_beats_from(start_mark_pos_beats, cur_loop_seconds_from_start)
+    }
+
+    /// Returns the time at which `beat` plays during the loop pass with index `loop_ix`
+    fn get_loop_beat_time(&self, loop_ix: usize, beat: f64) -> f64 {
+        let (start_mark_pos_beats, end_mark_pos_beats) = self.loop_bounds;
+        let tempo_map = &self.state.tempo_map;
+        let loop_length_seconds = tempo_map
+            .beats_to_seconds_from(start_mark_pos_beats, end_mark_pos_beats - start_mark_pos_beats);
+
+        self.start_time
+            + loop_ix as f64 * loop_length_seconds
+            + tempo_map.beats_to_seconds_from(start_mark_pos_beats, beat - start_mark_pos_beats)
+    }
+
+    /// The time up to which events have been scheduled
+    fn get_scheduled_through_time(&self) -> f64 {
+        self.get_loop_beat_time(self.scheduled_loop_count, self.scheduled_through_beat)
     }
 }
 
+/// Types of the events sent to JS by `ScheduledEvents::schedule`
+pub const EVENT_TYPE_RELEASE: u8 = 0;
+pub const EVENT_TYPE_ATTACK: u8 = 1;
+pub const EVENT_TYPE_PITCH_BEND: u8 = 2;
+pub const EVENT_TYPE_PRESSURE: u8 = 3;
+pub const EVENT_TYPE_TIMBRE: u8 = 4;
+
+/// A batch of note events waiting to be sent to JS to be played.  Pitch bends and expression are
+/// placed directly after the attack of the note that they belong to so that JS can apply them to
+/// the voice that was picked to play it.
+#[derive(Default)]
+pub struct ScheduledEvents {
+    pub event_types: Vec<u8>,
+    pub note_ids: Vec<usize>,
+    pub velocities: Vec<u8>,
+    /// Bend in semitones of pitch bend events and the value of pressure and timbre events; zero
+    /// for all other events
+    pub values: Vec<f32>,
+    pub timings: Vec<f64>,
+}
+
+impl ScheduledEvents {
+    fn push(&mut self, event_type: u8, note_id: usize, velocity: u8, value: f32, time: f64) {
+        self.event_types.push(event_type);
+        self.note_ids.push(note_id);
+        self.velocities.push(velocity);
+        self.values.push(value);
+        self.timings.push(time);
+    }
+
+    /// Adds the attack and release of a note that isn't in the grid
+    pub fn push_note(&mut self, note_id: usize, velocity: u8, start_time: f64, end_time: f64) {
+        self.push(EVENT_TYPE_ATTACK, note_id, velocity, 0., start_time);
+        self.push(EVENT_TYPE_RELEASE, note_id, velocity, 0., end_time);
+    }
+
+    /// Adds `event`, followed by the pitch bends and expression of its note in `notes` if it's an
+    /// attack.  Those that come after `end_beat` are skipped.
+    pub fn push_note_event(
+        &mut self,
+        notes: &NoteLines<usize>,
+        note_layout: &NoteLayout,
+        event: NoteEvent,
+        end_beat: f64,
+        beat_to_time: impl Fn(f64) -> f64,
+    ) {
+        let note_id = note_layout.line_note(event.line_ix);
+        let event_type = tern(event.is_start, EVENT_TYPE_ATTACK, EVENT_TYPE_RELEASE);
+        self.push(event_type, note_id, event.velocity, 0., beat_to_time(event.beat as f64));
+        if !event.is_start {
+            return;
+        }
+
+        let note = match notes.find_note(event.line_ix, event.beat) {
+            Some(note) => note,
+            None => return,
+        };
+        for (beat, semitones) in pitch_bend::get_note_pitch_bend_events(note, end_beat) {
+            self.push(EVENT_TYPE_PITCH_BEND, note_id, 0, semitones, beat_to_time(beat));
+        }
+        for (beat, dimension, value) in mpe::get_note_expression_events(note, end_beat) {
+            let event_type = match dimension {
+                ExpressionDimension::PitchBend => EVENT_TYPE_PITCH_BEND,
+                ExpressionDimension::Pressure => EVENT_TYPE_PRESSURE,
+                ExpressionDimension::Timbre => EVENT_TYPE_TIMBRE,
+            };
+            self.push(event_type, note_id, 0, value, beat_to_time(beat));
+        }
+    }
+
+    /// Sends the events to JS to be played and queues up their notes for MIDI output.
+    pub fn schedule(self, state: &mut MIDIEditorGridHandler) {
+        js::midi_editor_schedule_events(
+            &state.vc_id,
+            &self.event_types,
+            &self.note_ids,
+            &self.velocities,
+            &self.values,
+            &self.timings,
+        );
+        state.queue_midi_output_notes(
+            (0..self.timings.len())
+                .filter(|&i| {
+                    let event_type = self.event_types[i];
+                    event_type == EVENT_TYPE_ATTACK || event_type == EVENT_TYPE_RELEASE
+                })
+                .map(|i| {
+                    let is_attack = self.event_types[i] == EVENT_TYPE_ATTACK;
+                    (self.timings[i], self.note_ids[i], self.velocities[i], is_attack)
+                }),
+        );
+        state.flush_midi_output();
+    }
+}
+
+/// Returns the events that should be scheduled for a pass over `[pass_start_beat, pass_end_beat)`
+/// within the loop.  Notes that start before the loop are ignored, and notes that are still held
+/// at the end of the loop are released there so that they don't hang when it wraps around.
+///
+/// Attacks are included if they're within `[pass_start_beat, pass_end_beat)` and releases if
+/// they're within `(pass_start_beat, pass_end_beat]`.  That way, consecutive passes never trigger
+/// the same event twice, and an attack at the very end of the loop is left to the loop's start.
+pub fn get_loop_pass_events(
+    events: impl Iterator<Item = NoteEvent>,
+    (loop_start_beat, loop_end_beat): (f64, f64),
+    pass_start_beat: f64,
+    pass_end_beat: f64,
+) -> Vec<NoteEvent> {
+    let is_in_pass = |beat: f64, is_start: bool| {
+        if is_start {
+            beat >= pass_start_beat && beat < pass_end_beat
+        } else {
+            beat > pass_start_beat && beat <= pass_end_beat
+        }
+    };
+
+    let mut held_lines: Vec<usize> = Vec::new();
+    let mut pass_events = Vec::new();
+    for event in events {
+        let beat = event.beat as f64;
+        if event.is_start {
+            if beat >= loop_end_beat {
+                break;
+            } else if beat < loop_start_beat {
+                continue;
+            }
+            held_lines.push(event.line_ix);
+        } else {
+            // Releases are only emitted for notes whose attack was within the loop
+            match held_lines.iter().position(|&line_ix| line_ix == event.line_ix) {
+                Some(ix) => held_lines.swap_remove(ix),
+                None => continue,
+            };
+        }
+
+        let beat = beat.min(loop_end_beat);
+        if is_in_pass(beat, event.is_start) {
+            pass_events.push(NoteEvent {
+                beat: beat as f32,
+                ..event
+            });
+        }
+    }
+
+    if is_in_pass(loop_end_beat, false) {
+        pass_events.extend(held_lines.into_iter().map(|line_ix| NoteEvent {
+            line_ix,
+            is_start: false,
+            beat: loop_end_beat as f32,
+            velocity: 0,
+        }));
+    }
+    pass_events
+}
+
+/// Returns the `(start_beat, end_beat)` of the region that should be played by the scheduler.  If
+/// the loop end mark is set, the region between the loop marks is used.  Otherwise, everything
+/// from the start mark (or the beginning) through the end of the measure containing the last note
+/// is played, or the last clip instance if the arrangement is being played.  Returns `None` if
+/// that region is empty.  Launched clips ignore the loop marks and play from the beginning.
+pub fn get_loop_bounds_beats(
+    state: &MIDIEditorGridHandler,
+    grid_state: &GridState<usize>,
+) -> Option<(f64, f64)> {
+    if state.clips.is_playing_session() {
+        return Some((0., SESSION_END_BEAT));
+    }
+
+    let start_mark_pos_beats: f64 = state
+        .loop_start_mark_measure
+        .as_ref()
+        .map(|descriptor| descriptor.measure as f64)
+        .unwrap_or(0.);
+    let end_mark_pos_beats = match state.loop_end_mark_measure {
+        Some(LoopMarkDescriptor { measure, .. }) => measure as f64,
+        None if state.clips.is_playing_arrangement() => state
+            .tempo_map
+            .next_measure_start(state.clips.arrangement_end_beat()),
+        None => {
+            let last_note_end_beat = grid_state
+                .data
+                .iter()
+                .map(|note_data| note_data.note_box.bounds.end_beat)
+                .fold(0., f32::max) as f64;
+            state.tempo_map.next_measure_start(last_note_end_beat)
+        },
+    };
+
+    if end_mark_pos_beats <= start_mark_pos_beats {
+        return None;
+    }
+    Some((start_mark_pos_beats, end_mark_pos_beats))
+}
+
 const RESCHEDULE_INTERVAL_MS: usize = 2222;
 
 pub fn run_midi_editor_loop_scheduler(scheduler_state_handle: SchedulerStateHandle, cur_time: f64) {
@@ -54,11 +270,10 @@ pub fn run_midi_editor_loop_scheduler(scheduler_state_handle: SchedulerStateHand
 }
 
 fn init_scheduler_interval(scheduler_state: SchedulerState) -> SchedulerStateHandle {
-    let state_handle: SchedulerStateHandle = Box::into_raw(box scheduler_state);
-    let cb = Closure::wrap(
-        (box move |cur_time: f64| run_midi_editor_loop_scheduler(state_handle, cur_time))
-            as Box<dyn FnMut(f64)>,
-    );
+    let state_handle: SchedulerStateHandle = Box::into_raw(Box::new(scheduler_state));
+    let cb = Closure::wrap(Box::new(move |cur_time: f64| {
+        run_midi_editor_loop_scheduler(state_handle, cur_time)
+    }) as Box<dyn FnMut(f64)>);
     let interval_handle = js::register_midi_editor_loop_interval(&cb, RESCHEDULE_INTERVAL_MS);
     unsafe {
         (*state_handle).interval_handle = interval_handle;
@@ -77,53 +292,77 @@ fn animate_cursor(scheduler_state_handle: SchedulerStateHandle, cur_time: f64) {
         .beats_to_px(cursor_pos_beats as f32);
 
     scheduler_state.grid_state.cursor_pos_beats = cursor_pos_beats as f32;
-    MidiEditorGridRenderer::set_cursor_pos(scheduler_state.grid_state.cursor_dom_id, cursor_pos_px);
+    // The cursor's position is still tracked while hidden so that it's correct once shown again
+    if scheduler_state.grid_state.visible {
+        MidiEditorGridRenderer::set_cursor_pos(
+            scheduler_state.grid_state.cursor_dom_id,
+            cursor_pos_px,
+        );
+    }
 
     std::mem::forget(scheduler_state);
 }
 
 fn init_cursor_animation_interval(scheduler_state_handle: SchedulerStateHandle) {
     let mut scheduler_state = unsafe { Box::from_raw(scheduler_state_handle) };
-    let cb = Closure::wrap(
-        (box (move |cur_time: f64| animate_cursor(scheduler_state_handle, cur_time)))
-            as Box<dyn FnMut(f64)>,
-    );
+    let cb = Closure::wrap(Box::new(move |cur_time: f64| {
+        animate_cursor(scheduler_state_handle, cur_time)
+    }) as Box<dyn FnMut(f64)>);
     let animation_frame_handle = js::midi_editor_register_animation_frame(&cb);
     scheduler_state.cursor_animation_frame_handle = animation_frame_handle;
     scheduler_state.cursor_animation_cb = cb;
     std::mem::forget(scheduler_state);
 }
 
+/// Stops playback, letting subscribed VCs know that the transport has stopped
+// Only called by the MIDI editor with the handle that it got from `init_scheduler_loop`
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub fn cancel_loop(scheduler_state_handle: SchedulerStateHandle, stop_playing_notes: bool) {
+    let vc_id = unsafe { (*scheduler_state_handle).state.vc_id.clone() };
+    stop_scheduler(scheduler_state_handle, stop_playing_notes);
+    get_vcm().emit(EngineEvent::TransportStopped { vc_id });
+}
+
+/// Starts playing from `cursor_pos_beats`, letting subscribed VCs know that the transport has
+/// started.  Returns `None` if there's nothing to play.
+pub fn init_scheduler_loop(
+    start_time: f64,
+    cursor_pos_beats: f64,
+    state: &mut MIDIEditorGridHandler,
+    grid_state: &mut GridState<usize>,
+) -> Option<SchedulerStateHandle> {
+    let vc_id = state.vc_id.clone();
+    let handle = start_scheduler(start_time, cursor_pos_beats, state, grid_state)?;
+    get_vcm().emit(EngineEvent::TransportStarted { vc_id });
+    Some(handle)
+}
+
+fn stop_scheduler(scheduler_state_handle: SchedulerStateHandle, stop_playing_notes: bool) {
     let scheduler_state = unsafe { Box::from_raw(scheduler_state_handle) };
     js::cancel_midi_editor_loop_interval(scheduler_state.interval_handle);
     js::midi_editor_cancel_animation_frame(scheduler_state.cursor_animation_frame_handle);
     js::midi_editor_cancel_all_events(&scheduler_state.state.vc_id, stop_playing_notes);
+    js::midi_editor_cancel_metronome_clicks(&scheduler_state.state.vc_id);
+    js::midi_editor_cancel_automation(&scheduler_state.state.vc_id);
+    scheduler_state.state.cancel_midi_output();
+    let bpm = scheduler_state.state.tempo_map.base_bpm();
+    scheduler_state.state.set_transport_state(false, bpm, 0.);
     drop(scheduler_state);
 }
 
-pub fn init_scheduler_loop(
+fn start_scheduler(
     start_time: f64,
     cursor_pos_beats: f64,
     state: &mut MIDIEditorGridHandler,
     grid_state: &mut GridState<usize>,
 ) -> Option<SchedulerStateHandle> {
-    let end_mark_pos = match state
-        .loop_end_mark_measure
-        .as_ref()
-        .map(|descriptor| descriptor.measure as f64)
-    {
-        Some(pos) => pos,
+    let (start_mark_pos, end_mark_pos) = match get_loop_bounds_beats(state, grid_state) {
+        Some(bounds) => bounds,
         None => {
-            error!("Tried to schedule loop without having a loop end position set");
+            error!("Tried to schedule loop without any notes or a loop end position set");
             return None;
         },
     };
-    let start_mark_pos = state
-        .loop_start_mark_measure
-        .as_ref()
-        .map(|descriptor| descriptor.measure as f64)
-        .unwrap_or(0.0);
     let start_beat = if cursor_pos_beats > end_mark_pos || cursor_pos_beats < start_mark_pos {
         start_mark_pos
     } else {
@@ -132,20 +371,31 @@ pub fn init_scheduler_loop(
 
     // Pretend we've already scheduled up to the start cursor offset
     let beats_to_skip = start_beat - start_mark_pos;
-    let time_to_skip = state.beats_to_seconds(beats_to_skip);
+    let time_to_skip = state
+        .tempo_map
+        .beats_to_seconds_from(start_mark_pos, beats_to_skip);
 
     let scheduler_state = SchedulerState {
-        cb: Closure::new(box |_: f64| {}),
-        cursor_animation_cb: Closure::new(box |_: f64| {}),
+        cb: Closure::wrap(Box::new(|_: f64| {}) as Box<dyn FnMut(f64)>),
+        cursor_animation_cb: Closure::wrap(Box::new(|_: f64| {}) as Box<dyn FnMut(f64)>),
         start_time: start_time - time_to_skip,
         schedule_offset_seconds: time_to_skip,
         interval_handle: 0,
         cursor_animation_frame_handle: 0,
-        end_time_of_last_scheduling_period: start_time + time_to_skip,
-        total_previously_scheduled_beats: beats_to_skip,
+        scheduled_loop_count: 0,
+        scheduled_through_beat: start_beat,
+        loop_bounds: (start_mark_pos, end_mark_pos),
         state: unsafe { std::mem::transmute(state) },
         grid_state: unsafe { std::mem::transmute(grid_state) },
     };
+    // Let anything synced to the transport know the time at which beat 0 of the composition
+    // would have played so that it can derive its phase from it
+    let tempo_map = &scheduler_state.state.tempo_map;
+    let beat_zero_time = scheduler_state.start_time - tempo_map.beat_to_seconds(start_mark_pos);
+    let bpm = tempo_map.bpm_at(start_beat);
+    scheduler_state
+        .state
+        .set_transport_state(true, bpm, beat_zero_time);
     let handle = init_scheduler_interval(scheduler_state);
     init_cursor_animation_interval(handle);
     // Schedule once immediately
@@ -153,15 +403,19 @@ pub fn init_scheduler_loop(
     Some(handle)
 }
 
-/// Clears all pending events and re-schedules starting at the current time
-pub fn reschedule(cur_time: f64, scheduler_state_handle: SchedulerStateHandle, old_bpm: f64) {
+/// Clears all pending events and re-schedules starting at the current time.  `old_tempo_map` is
+/// the tempo map that was used to schedule the pending events.
+pub fn reschedule(
+    cur_time: f64,
+    scheduler_state_handle: SchedulerStateHandle,
+    old_tempo_map: TempoMap,
+) {
     let scheduler_state = unsafe { Box::from_raw(scheduler_state_handle) };
 
     // Find where the cursor is the instant that we're rescheduling
-    let new_bpm = scheduler_state.state.bpm;
-    scheduler_state.state.bpm = old_bpm;
+    let new_tempo_map = std::mem::replace(&mut scheduler_state.state.tempo_map, old_tempo_map);
     let cur_cursor_pos_beats = scheduler_state.get_cur_cursor_pos_beats(cur_time);
-    scheduler_state.state.bpm = new_bpm;
+    scheduler_state.state.tempo_map = new_tempo_map;
 
     // Delightfully unsafe
     let grid_state: &'static mut GridState<usize> =
@@ -170,109 +424,115 @@ pub fn reschedule(cur_time: f64, scheduler_state_handle: SchedulerStateHandle, o
         unsafe { std::mem::transmute(scheduler_state.state as *mut _) };
     std::mem::forget(scheduler_state);
 
-    // Cancel the current scheduler and initialize a new one, freshly updated with the current state
-    cancel_loop(scheduler_state_handle, true);
-    let new_loop_handle = init_scheduler_loop(cur_time, cur_cursor_pos_beats, state, grid_state);
+    // Cancel the current scheduler and initialize a new one, freshly updated with the current
+    // state.  Playback doesn't stop from the user's perspective, so no transport events are sent.
+    stop_scheduler(scheduler_state_handle, true);
+    let new_loop_handle = start_scheduler(cur_time, cur_cursor_pos_beats, state, grid_state);
     state.loop_handle = new_loop_handle;
 }
 
 fn run_scheduler(scheduler_state: &mut SchedulerState, cur_time: f64) {
     trace!("SCHED ENTER");
-    let start_mark_pos_beats: f64 = scheduler_state
-        .state
-        .loop_start_mark_measure
-        .as_ref()
-        .map(|descriptor| descriptor.measure as f64)
-        .unwrap_or(0.);
-    let cur_sched_period_start_time = scheduler_state.end_time_of_last_scheduling_period;
-    let end_mark_pos_beats = match scheduler_state.state.loop_end_mark_measure {
-        Some(LoopMarkDescriptor { measure, .. }) => measure as f64,
-        None => {
-            error!("Tried to schedule a loop without a loop end mark being set");
-            return;
-        },
-    };
-    let loop_length_beats = end_mark_pos_beats - start_mark_pos_beats;
-    let loop_length_seconds = scheduler_state.state.beats_to_seconds(loop_length_beats);
-    let total_previously_scheduled_full_loops =
-        (scheduler_state.total_previously_scheduled_beats / loop_length_beats).trunc();
+    let (start_mark_pos_beats, end_mark_pos_beats) = scheduler_state.loop_bounds;
 
     // Schedule a leeway of 3 secheduling periods ahead of the current time
     let end_time_of_cur_sched_window = cur_time + ((RESCHEDULE_INTERVAL_MS * 3) as f64 / 1000.);
     let cur_sched_period_length_seconds =
-        end_time_of_cur_sched_window - cur_sched_period_start_time;
-    let cur_sched_period_length_beats = scheduler_state
+        end_time_of_cur_sched_window - scheduler_state.get_scheduled_through_time();
+    // The tempo can change within the loop, so the scheduling period is converted into beats
+    // starting from where this pass picks up
+    let pass_start_beat = scheduler_state.scheduled_through_beat;
+    let beats_to_schedule = scheduler_state
         .state
-        .time_to_beats(cur_sched_period_length_seconds);
-    let beats_to_schedule = cur_sched_period_length_beats;
+        .tempo_map
+        .seconds_to_beats_from(pass_start_beat, cur_sched_period_length_seconds);
     trace!("beats_to_schedule: {}", beats_to_schedule);
-
-    // Schedule the loop repeatedly at increasing offsets until all necessary beats have been
-    // covered
-    let mut is_attack_flags: Vec<u8> = Vec::new();
-    let mut note_ids: Vec<usize> = Vec::new();
-    let mut event_timings: Vec<f64> = Vec::new();
-
-    let beats_from_start_of_cur_loop =
-        scheduler_state.total_previously_scheduled_beats % loop_length_beats;
-    let relative_start_beat = start_mark_pos_beats + beats_from_start_of_cur_loop;
-    let beats_remaining_in_cur_loop = loop_length_beats - beats_from_start_of_cur_loop;
-
-    let relative_end_beat = if beats_to_schedule < beats_remaining_in_cur_loop {
-        relative_start_beat + beats_to_schedule
-    } else {
-        relative_start_beat + beats_remaining_in_cur_loop
-    };
+    if beats_to_schedule <= 0. {
+        trace!("SCHED EXIT; already scheduled through the current window");
+        return;
+    }
+    let pass_end_beat = (pass_start_beat + beats_to_schedule).min(end_mark_pos_beats);
 
     trace!(
         "Scheduling from relative beats {} to {}",
-        relative_start_beat,
-        relative_end_beat
+        pass_start_beat,
+        pass_end_beat
     );
-    let events = scheduler_state
-        .grid_state
-        .data
-        .iter_events(None)
-        .skip_while(|event| event.beat < (relative_start_beat as f32))
-        .take_while(|event| event.beat <= (relative_end_beat as f32));
-
-    for event in events {
-        let note_id = scheduler_state.grid_state.conf.row_count - event.line_ix;
-        note_ids.push(note_id);
-        is_attack_flags.push(tern(event.is_start, 1, 0));
-        let event_time_seconds = scheduler_state.start_time
-            + (total_previously_scheduled_full_loops * loop_length_seconds)
-            + scheduler_state.state.beats_to_seconds(event.beat as f64);
-        event_timings.push(event_time_seconds);
+    // Swing only changes when events are played, so it's applied after picking the events for
+    // the pass
+    let groove = scheduler_state.state.groove;
+    let note_layout = &scheduler_state.state.note_layout;
+    let get_time = |beat: f64| {
+        let beat = groove.apply(beat).min(end_mark_pos_beats);
+        scheduler_state.get_loop_beat_time(scheduler_state.scheduled_loop_count, beat)
+    };
+    let mut scheduled_events = ScheduledEvents::default();
+    let clips = &scheduler_state.state.clips;
+    if let Some(instances) = clips.get_playing_instances() {
+        let grid_state: &GridState<usize> = scheduler_state.grid_state;
+        let events = get_arrangement_pass_events(
+            instances,
+            |clip_id| clips.get_clip_notes(grid_state, clip_id),
+            pass_start_beat,
+            pass_end_beat,
+        );
+        for ArrangementEvent {
+            notes,
+            offset_beats,
+            end_beat,
+            event,
+        } in events
+        {
+            scheduled_events.push_note_event(notes, note_layout, event, end_beat, |beat| {
+                get_time(offset_beats + beat)
+            });
+        }
+    } else {
+        let events = get_loop_pass_events(
+            scheduler_state.grid_state.data.iter_events(None),
+            (start_mark_pos_beats, end_mark_pos_beats),
+            pass_start_beat,
+            pass_end_beat,
+        );
+        for event in events {
+            scheduled_events.push_note_event(
+                &scheduler_state.grid_state.data,
+                note_layout,
+                event,
+                end_mark_pos_beats,
+                get_time,
+            );
+        }
     }
-    js::midi_editor_schedule_events(
-        &scheduler_state.state.vc_id,
-        &is_attack_flags,
-        &note_ids,
-        &event_timings,
-    );
+    scheduled_events.schedule(scheduler_state.state);
 
-    let scheduled_beats = relative_end_beat - relative_start_beat;
-    scheduler_state.total_previously_scheduled_beats += scheduled_beats;
-    trace!(
-        "scheduled_beats: {}, total_previously_scheduled_beats: {}",
-        scheduled_beats,
-        scheduler_state.total_previously_scheduled_beats
-    );
-    // We finished scheduling a full loop, but we still have more beats to schedule.  Queue up
-    // another (potentially partial) loop to be scheduled
-    if scheduled_beats < beats_to_schedule {
-        scheduler_state.end_time_of_last_scheduling_period +=
-            scheduler_state.state.beats_to_seconds(scheduled_beats);
+    let clicks =
+        metronome::get_clicks(&scheduler_state.state.tempo_map, pass_start_beat, pass_end_beat);
+    scheduler_state.state.schedule_metronome_clicks(clicks.into_iter().map(|click| {
+        let time =
+            scheduler_state.get_loop_beat_time(scheduler_state.scheduled_loop_count, click.beat);
+        (time, click.is_accent)
+    }));
+
+    scheduler_state
+        .state
+        .schedule_automation(pass_start_beat, pass_end_beat, |beat| {
+            scheduler_state.get_loop_beat_time(scheduler_state.scheduled_loop_count, beat)
+        });
+
+    // We reached the end of the loop, but the scheduling window extends past it.  Wrap around to
+    // the start of the loop and schedule another (potentially partial) pass.
+    if pass_end_beat >= end_mark_pos_beats {
+        scheduler_state.scheduled_loop_count += 1;
+        scheduler_state.scheduled_through_beat = start_mark_pos_beats;
         trace!(
-            "Need to schedule another (potentially partial) loop; \
-             end_time_of_last_scheduling_period: {}",
-            scheduler_state.end_time_of_last_scheduling_period
+            "Need to schedule another (potentially partial) loop; scheduled_loop_count: {}",
+            scheduler_state.scheduled_loop_count
         );
         return run_scheduler(scheduler_state, cur_time);
     }
 
-    scheduler_state.end_time_of_last_scheduling_period = end_time_of_cur_sched_window;
+    scheduler_state.scheduled_through_beat = pass_end_beat;
     trace!(
         "SCHED EXIT; scheduled through time {}",
         end_time_of_cur_sched_window
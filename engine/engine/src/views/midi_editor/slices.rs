@@ -0,0 +1,139 @@
+//! Maps the slices of a sampled loop onto the lines of the grid.  Each slice gets its own line of a
+//! drum map, which plays the note that the sampler plays that slice on, and a pattern is written
+//! into the grid that plays the slices at the beats they fall on in the loop.  Played back at the
+//! tempo that the loop was sliced at, the pattern reproduces the loop, and it can be rearranged or
+//! played at other tempos without the pitch of the loop changing.
+
+use common::tuning::MIDI_NOTE_COUNT;
+
+use super::{note_layout::DrumSound, *};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SliceMapping {
+    /// The note that the first slice is played on.  Each slice after it plays the next note up.
+    pub base_note: usize,
+    /// The beat that each slice starts at, in ascending order
+    pub slice_start_beats: Vec<f32>,
+    /// The beat at which the last slice ends, which is the length of the loop
+    pub end_beat: f32,
+}
+
+impl SliceMapping {
+    /// Returns a drum map with a line for each slice from the first slice down, or `None` if there
+    /// are no slices or they'd be played on notes past the highest MIDI note
+    pub fn drum_map(&self) -> Option<Vec<DrumSound>> {
+        let slice_count = self.slice_start_beats.len();
+        if slice_count == 0 || self.base_note + slice_count > MIDI_NOTE_COUNT {
+            return None;
+        }
+
+        let sounds = (0..slice_count)
+            .map(|slice_ix| DrumSound {
+                name: format!("Slice {}", slice_ix + 1),
+                note: self.base_note + slice_ix,
+            })
+            .collect();
+        Some(sounds)
+    }
+
+    /// Returns the `(line_ix, start_beat, end_beat)` of the notes that play the loop.  Each slice
+    /// is played until the next one starts.
+    pub fn pattern(&self) -> Vec<(usize, f32, f32)> {
+        let starts = &self.slice_start_beats;
+        (0..starts.len())
+            .map(|slice_ix| {
+                let end_beat = starts.get(slice_ix + 1).cloned().unwrap_or(self.end_beat);
+                (slice_ix, starts[slice_ix], end_beat)
+            })
+            .filter(|&(_, start_beat, end_beat)| end_beat > start_beat && start_beat >= 0.)
+            .collect()
+    }
+}
+
+impl MIDIEditorGridHandler {
+    /// Switches the grid to a drum map of the slices and replaces its notes with a pattern that
+    /// plays the loop.  Since the note layout changes, this can't be undone.  Returns `false`
+    /// without changing anything if the mapping is invalid or the notes of other clips wouldn't
+    /// fit in the drum map.
+    pub(super) fn map_slices(
+        &mut self,
+        grid_state: &mut GridState<usize>,
+        mapping: &SliceMapping,
+    ) -> bool {
+        let drum_map = match mapping.drum_map() {
+            Some(drum_map) => drum_map,
+            None => {
+                error!("Invalid slice mapping: {:?}", mapping);
+                return false;
+            },
+        };
+        if self.midi_recording_ctx.is_some() {
+            warn!("Can't map slices while recording MIDI");
+            return false;
+        }
+
+        // The grid's notes are replaced, so they're taken out before switching the layout so that
+        // they don't keep it from being switched
+        let old_notes: Vec<(usize, f32)> = grid_state
+            .data
+            .iter_all()
+            .map(|(line_ix, note)| (line_ix, note.bounds.start_beat))
+            .collect();
+        let removed_notes: Vec<(usize, NoteBox<usize>)> = old_notes
+            .into_iter()
+            .filter_map(|(line_ix, start_beat)| {
+                grid_state
+                    .data
+                    .remove(line_ix, start_beat)
+                    .map(|note| (line_ix, note))
+            })
+            .collect();
+        for note_data in grid_state.selected_notes.drain() {
+            MidiEditorGridRenderer::deselect_note(note_data.dom_id);
+        }
+
+        if !self.set_drum_map(grid_state, Some(drum_map)) {
+            for (line_ix, note) in removed_notes {
+                let reinsertion_error = grid_state.data.insert(line_ix, note);
+                debug_assert!(reinsertion_error.is_none());
+            }
+            return false;
+        }
+        for (_, note) in removed_notes {
+            js::delete_element(note.data);
+        }
+
+        let conf = &grid_state.conf;
+        let pattern: Vec<(usize, NoteBox<usize>)> = mapping
+            .pattern()
+            .into_iter()
+            .map(|(line_ix, start_beat, end_beat)| {
+                let dom_id = MidiEditorGridRenderer::create_note(
+                    conf.beats_to_px(start_beat),
+                    conf.cursor_gutter_height + conf.padded_line_height() * line_ix,
+                    conf.beats_to_px(end_beat - start_beat),
+                    conf.zoomed_line_height(),
+                    None,
+                );
+                MidiEditorGridRenderer::set_note_velocity(dom_id, DEFAULT_NOTE_VELOCITY);
+                let note = NoteBox {
+                    id: NoteId::next(),
+                    data: dom_id,
+                    bounds: NoteBoxBounds {
+                        start_beat,
+                        end_beat,
+                    },
+                    velocity: DEFAULT_NOTE_VELOCITY,
+                    pitch_bend: Vec::new(),
+                    expression: NoteExpression::default(),
+                };
+                (line_ix, note)
+            })
+            .collect();
+        // Every slice is on its own line, so none of the notes can intersect
+        let rejected_notes = grid_state.data.insert_group(pattern);
+        debug_assert!(rejected_notes.is_none());
+        true
+    }
+}
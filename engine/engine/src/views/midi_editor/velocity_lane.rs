@@ -0,0 +1,301 @@
+//! The velocity lane is rendered between the grid and the automation lane.  It shows the velocity
+//! of each note as a bar at the note's start.  Dragging across the lane sets the velocity of the
+//! notes whose bars the mouse passes over to the height of the mouse, and holding shift while
+//! dragging draws a ramp that's applied to all of the notes beneath it once the mouse is released.
+//!
+//! If any notes are selected, only the selected notes are edited.  Edits are recorded in the same
+//! undo history as all other note edits.
+
+use std::collections::HashSet;
+
+use super::{
+    constants::{LANE_MARGIN_PX, VELOCITY_LANE_HEIGHT_PX},
+    *,
+};
+
+const VELOCITY_BAR_WIDTH_PX: usize = 3;
+/// Bars within this many pixels of the mouse are edited by it
+const VELOCITY_BAR_HIT_RADIUS_PX: usize = 3;
+
+/// Converts a y position relative to the bottom of the grid into the velocity at that height of
+/// the lane
+pub fn y_to_velocity(y: usize) -> u8 {
+    let y = y
+        .saturating_sub(LANE_MARGIN_PX)
+        .min(VELOCITY_LANE_HEIGHT_PX);
+    pressure_to_velocity(1. - (y as f32 / VELOCITY_LANE_HEIGHT_PX as f32))
+}
+
+/// Returns the velocity at `x` of a ramp drawn from `start` to `end`, which are both
+/// `(x, velocity)` pairs.  Velocities beyond the ends of the ramp are the same as at the closest
+/// end.
+pub fn ramp_velocity(start: (usize, u8), end: (usize, u8), x: usize) -> u8 {
+    let ((start_x, start_velocity), (end_x, end_velocity)) = (start, end);
+    if start_x == end_x {
+        return end_velocity;
+    }
+
+    let progress = (x as f32 - start_x as f32) / (end_x as f32 - start_x as f32);
+    let progress = clamp(progress, 0., 1.);
+    (start_velocity as f32 + (end_velocity as f32 - start_velocity as f32) * progress).round() as u8
+}
+
+#[derive(Clone, Copy)]
+struct VelocityLaneDrag {
+    /// `(x, velocity)` of where the mouse was pressed
+    start: (usize, u8),
+    last_x: usize,
+    /// Set while drawing a ramp.  Holds the `(x, velocity)` of the end of the ramp and the
+    /// `DomId` of the rendered line.
+    ramp: Option<((usize, u8), DomId)>,
+    /// Whether the notes have been recorded in the undo history during this drag yet
+    recorded_edit: bool,
+}
+
+#[derive(Default)]
+pub struct VelocityLaneState {
+    dom_ids: Vec<DomId>,
+    drag: Option<VelocityLaneDrag>,
+}
+
+impl VelocityLaneState {
+    pub fn is_dragging(&self) -> bool { self.drag.is_some() }
+}
+
+/// A note whose velocity can be edited from the lane
+struct VelocityBar {
+    line_ix: usize,
+    start_beat: f32,
+    dom_id: DomId,
+    x: usize,
+}
+
+fn lane_top_px(conf: &GridConf) -> usize { conf.grid_height() + LANE_MARGIN_PX }
+
+/// Converts a y position relative to the bottom of the grid into one relative to the top of the
+/// grid that's clamped to the lane, for drawing ramps
+fn clamp_y_to_lane(conf: &GridConf, y: usize) -> usize {
+    let lane_top = lane_top_px(conf);
+    (conf.grid_height() + y).clamp(lane_top, lane_top + VELOCITY_LANE_HEIGHT_PX)
+}
+
+/// Returns the velocity set by the mouse or pen at `y`.  The pressure of a pen sets the velocity
+/// rather than its position, in the same way as when drawing notes.
+fn get_input_velocity(grid_state: &GridState<usize>, y: usize) -> u8 {
+    match grid_state.pen {
+        Some(pen) => pressure_to_velocity(pen.pressure),
+        None => y_to_velocity(y),
+    }
+}
+
+/// Returns the bars of the notes that start between the pixels `start_x` and `end_x`, only
+/// including selected notes if there are any
+fn get_bars_between(
+    grid_state: &GridState<usize>,
+    start_x: usize,
+    end_x: usize,
+) -> Vec<VelocityBar> {
+    let (start_x, end_x) = (start_x.min(end_x), start_x.max(end_x));
+    let start_x = start_x.saturating_sub(VELOCITY_BAR_HIT_RADIUS_PX);
+    let end_x = end_x + VELOCITY_BAR_HIT_RADIUS_PX;
+    let only_selected = !grid_state.selected_notes.is_empty();
+    let selected_dom_ids: HashSet<DomId> = grid_state
+        .selected_notes
+        .iter()
+        .map(|note| note.dom_id)
+        .collect();
+
+    let conf = &grid_state.conf;
+    grid_state
+        .data
+        .iter_range(conf.px_to_beat(start_x), conf.px_to_beat(end_x))
+        .map(|note_data| VelocityBar {
+            line_ix: note_data.line_ix,
+            start_beat: note_data.note_box.bounds.start_beat,
+            dom_id: note_data.note_box.data,
+            x: grid_state
+                .conf
+                .beats_to_px(note_data.note_box.bounds.start_beat),
+        })
+        .filter(|bar| bar.x >= start_x && bar.x <= end_x)
+        .filter(|bar| !only_selected || selected_dom_ids.contains(&bar.dom_id))
+        .collect()
+}
+
+impl MIDIEditorGridHandler {
+    /// Re-renders the velocity lane from scratch for the rendered part of the grid, highlighting
+    /// the bars of selected notes
+    pub fn render_velocity_lane(&mut self, grid_state: &GridState<usize>) {
+        for dom_id in self.velocity_lane.dom_ids.drain(..) {
+            js::delete_element(dom_id);
+        }
+
+        let conf = &grid_state.conf;
+        let lane_top = lane_top_px(conf);
+        let (start_px, end_px) = grid_state.background.px_range(conf);
+        let mut dom_ids = vec![js::render_quad(
+            BG_CANVAS_IX,
+            start_px,
+            lane_top,
+            end_px - start_px,
+            VELOCITY_LANE_HEIGHT_PX,
+            "velocity-lane",
+            None,
+        )];
+        let selected_dom_ids: HashSet<DomId> = grid_state
+            .selected_notes
+            .iter()
+            .map(|note| note.dom_id)
+            .collect();
+        let (start_beat, end_beat) = grid_state.background.beat_range;
+        for note_data in grid_state.data.iter_range(start_beat, end_beat) {
+            let note = note_data.note_box;
+            let height = (note.velocity as f32 / MAX_NOTE_VELOCITY as f32
+                * VELOCITY_LANE_HEIGHT_PX as f32)
+                .round() as usize;
+            let dom_id = js::render_quad(
+                FG_CANVAS_IX,
+                conf.beats_to_px(note.bounds.start_beat),
+                lane_top + VELOCITY_LANE_HEIGHT_PX - height,
+                VELOCITY_BAR_WIDTH_PX,
+                height,
+                "velocity-bar",
+                None,
+            );
+            if selected_dom_ids.contains(&note.data) {
+                js::add_class(dom_id, "selected");
+            }
+            dom_ids.push(dom_id);
+        }
+        self.velocity_lane.dom_ids = dom_ids;
+    }
+
+    /// Sets the velocities of the notes of the provided bars, recording the notes in the undo
+    /// history before the first change of the current drag
+    fn set_bar_velocities(
+        &mut self,
+        grid_state: &mut GridState<usize>,
+        bars: impl Iterator<Item = (VelocityBar, u8)>,
+    ) {
+        for (bar, velocity) in bars {
+            let drag = self
+                .velocity_lane
+                .drag
+                .as_mut()
+                .expect("Velocities are only edited while dragging");
+            if !drag.recorded_edit {
+                grid_state.record_note_edit();
+                drag.recorded_edit = true;
+            }
+            if !grid_state
+                .data
+                .set_velocity(bar.line_ix, bar.start_beat, velocity)
+            {
+                error!("Tried to set the velocity of a note that doesn't exist");
+                continue;
+            }
+            MidiEditorGridRenderer::set_note_velocity(bar.dom_id, velocity);
+
+            let selected_note = grid_state
+                .selected_notes
+                .iter()
+                .find(|note| note.dom_id == bar.dom_id)
+                .copied();
+            if let Some(selected_note) = selected_note {
+                // `SelectedNoteData` is hashed by its `DomId`, so this replaces the old entry
+                grid_state.selected_notes.replace(SelectedNoteData {
+                    velocity,
+                    ..selected_note
+                });
+            }
+        }
+    }
+
+    /// Sets the velocity of the notes between the last position of the mouse and `x`
+    fn paint_velocities(&mut self, grid_state: &mut GridState<usize>, x: usize, velocity: u8) {
+        let last_x = match &mut self.velocity_lane.drag {
+            Some(drag) => std::mem::replace(&mut drag.last_x, x),
+            None => return,
+        };
+        let bars = get_bars_between(grid_state, last_x, x);
+        self.set_bar_velocities(grid_state, bars.into_iter().map(|bar| (bar, velocity)));
+    }
+
+    pub fn handle_velocity_lane_mouse_down(
+        &mut self,
+        grid_state: &mut GridState<usize>,
+        x: usize,
+        y: usize,
+    ) -> bool {
+        let velocity = get_input_velocity(grid_state, y);
+        let ramp = if grid_state.shift_pressed {
+            let y = clamp_y_to_lane(&grid_state.conf, y);
+            let dom_id = js::render_line(FG_CANVAS_IX, x, y, x, y, "velocity-ramp");
+            Some(((x, velocity), dom_id))
+        } else {
+            None
+        };
+        self.velocity_lane.drag = Some(VelocityLaneDrag {
+            start: (x, velocity),
+            last_x: x,
+            ramp,
+            recorded_edit: false,
+        });
+
+        if ramp.is_none() {
+            self.paint_velocities(grid_state, x, velocity);
+        }
+        true
+    }
+
+    pub fn handle_velocity_lane_mouse_move(
+        &mut self,
+        grid_state: &mut GridState<usize>,
+        x: usize,
+        y: usize,
+    ) {
+        let velocity = get_input_velocity(grid_state, y);
+        let drag = match &mut self.velocity_lane.drag {
+            Some(drag) => drag,
+            None => return,
+        };
+        match &mut drag.ramp {
+            Some((end, dom_id)) => {
+                *end = (x, velocity);
+                let y = clamp_y_to_lane(&grid_state.conf, y);
+                js::set_attr(*dom_id, "x2", &x.to_string());
+                js::set_attr(*dom_id, "y2", &y.to_string());
+            },
+            None => {
+                self.paint_velocities(grid_state, x, velocity);
+                self.render_velocity_lane(grid_state);
+            },
+        }
+    }
+
+    /// Applies the ramp being drawn, if any, and finishes the drag
+    pub fn handle_velocity_lane_mouse_up(&mut self, grid_state: &mut GridState<usize>) {
+        let (start, (end, dom_id)) = match self.velocity_lane.drag {
+            Some(VelocityLaneDrag {
+                start,
+                ramp: Some(ramp),
+                ..
+            }) => (start, ramp),
+            _ => {
+                self.velocity_lane.drag = None;
+                return;
+            },
+        };
+
+        js::delete_element(dom_id);
+        let bars = get_bars_between(grid_state, start.0, end.0);
+        self.set_bar_velocities(
+            grid_state,
+            bars.into_iter().map(|bar| {
+                let velocity = ramp_velocity(start, end, bar.x);
+                (bar, velocity)
+            }),
+        );
+        self.velocity_lane.drag = None;
+    }
+}
@@ -0,0 +1,118 @@
+//! Settings for the voice manager that allocates the voices of the instrument played by the MIDI
+//! editor.  The voice manager itself lives in the `polysynth` crate and is driven from JavaScript,
+//! so these are sent over to it whenever they change.
+
+use polysynth::{adsr::AdsrParams, POLY_SYNTH_VOICE_COUNT};
+
+use super::*;
+
+/// Mirrors `polysynth::voice_stealing::VoiceStealPolicy`, which determines which voice plays a new
+/// note when all voices are already playing
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum VoiceStealPolicy {
+    #[default]
+    StealOldest,
+    StealQuietest,
+    StealSamePitch,
+    DropNew,
+}
+
+/// Mirrors `polysynth::adsr::AdsrParams`, the amplitude envelope that the voice manager plays
+/// voices with
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AdsrConf {
+    pub attack_ms: f32,
+    pub decay_ms: f32,
+    /// The level from 0 to 1 that is held for as long as the note is held
+    pub sustain: f32,
+    /// How long notes ring out for after they're released
+    pub release_ms: f32,
+}
+
+impl Default for AdsrConf {
+    fn default() -> Self { AdsrParams::default().into() }
+}
+
+impl From<AdsrParams> for AdsrConf {
+    fn from(params: AdsrParams) -> Self {
+        let AdsrParams {
+            attack_ms,
+            decay_ms,
+            sustain,
+            release_ms,
+        } = params;
+        AdsrConf {
+            attack_ms,
+            decay_ms,
+            sustain,
+            release_ms,
+        }
+    }
+}
+
+impl From<AdsrConf> for AdsrParams {
+    fn from(conf: AdsrConf) -> Self {
+        AdsrParams {
+            attack_ms: conf.attack_ms.max(0.),
+            decay_ms: conf.decay_ms.max(0.),
+            sustain: clamp(conf.sustain, 0., 1.),
+            release_ms: conf.release_ms.max(0.),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct VoiceManagerConf {
+    /// Plays a single voice at a time, with each new note taking over the voice of the last
+    pub mono: bool,
+    /// The most notes that can play at once when not in mono mode
+    pub polyphony: usize,
+    pub steal_policy: VoiceStealPolicy,
+    /// How long it takes to slide to the pitch of a new note from the pitch of the note played
+    /// before it.  Glide is disabled if this is zero.
+    pub glide_ms: f32,
+    /// Glides between notes even when not in mono mode as long as the previous note is still held
+    pub legato_glide: bool,
+    pub adsr: AdsrConf,
+}
+
+impl Default for VoiceManagerConf {
+    fn default() -> Self {
+        VoiceManagerConf {
+            mono: false,
+            polyphony: POLY_SYNTH_VOICE_COUNT,
+            steal_policy: VoiceStealPolicy::default(),
+            glide_ms: 0.,
+            legato_glide: false,
+            adsr: AdsrConf::default(),
+        }
+    }
+}
+
+impl VoiceManagerConf {
+    /// Returns the number of voices that the voice manager plays notes with.  Instruments have
+    /// `POLY_SYNTH_VOICE_COUNT` voices, so polyphony is limited to that.
+    pub fn voice_count(&self) -> usize {
+        if self.mono {
+            1
+        } else {
+            self.polyphony.clamp(1, POLY_SYNTH_VOICE_COUNT)
+        }
+    }
+
+    /// Applies these settings to the voice manager of the MIDI editor with id `vc_id`.  The
+    /// envelope is clamped to valid `AdsrParams` before it's sent since the voice manager uses it
+    /// as-is.
+    pub fn sync(&self, vc_id: &str) {
+        let conf = VoiceManagerConf {
+            adsr: AdsrParams::from(self.adsr).into(),
+            ..*self
+        };
+        let conf_json =
+            serde_json::to_string(&conf).expect("Failed to serialize voice manager conf");
+        js::midi_editor_set_voice_manager_conf(vc_id, &conf_json, self.voice_count());
+    }
+}
@@ -19,7 +19,7 @@ impl ViewContext for MIDIKeyboard {
 
     fn cleanup(&mut self) {
         let state = js::cleanup_midi_keyboard(&self.get_state_key());
-        js::set_localstorage_key(&self.get_state_key(), &state);
+        LocalStorage.set(&self.get_state_key(), &state);
     }
 
     fn get_id(&self) -> String { self.uuid.to_string() }
@@ -28,7 +28,9 @@ impl ViewContext for MIDIKeyboard {
 
     fn unhide(&mut self) { js::unhide_midi_keyboard(&self.get_state_key()); }
 
-    fn dispose(&mut self) { js::delete_localstorage_key(&self.get_state_key()); }
+    fn dispose(&mut self) { LocalStorage.delete(&self.get_state_key()); }
+
+    fn save_external_data(&mut self) -> Vec<String> { vec![self.get_state_key()] }
 
     fn get_audio_connectables(&self) -> JsValue {
         js::get_midi_keyboard_audio_connectables(&self.get_state_key())
@@ -36,5 +38,5 @@ impl ViewContext for MIDIKeyboard {
 }
 
 pub fn mk_midi_keyboard(_definition_opt: Option<&str>, uuid: Uuid) -> Box<dyn ViewContext> {
-    box MIDIKeyboard { uuid }
+    Box::new(MIDIKeyboard { uuid })
 }
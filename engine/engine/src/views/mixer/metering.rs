@@ -0,0 +1,107 @@
+//! Meters for each of the mixer's tracks and its master bus.  The JS side of the mixer summarizes
+//! each block of audio in the audio thread and sends the summaries over in batches; the UI then
+//! polls the levels of all meters at once every animation frame.
+
+use std::collections::BTreeMap;
+
+use crate::{
+    dsp::meter::{BlockLevels, MeterBallistics, MeterReading, MeterTap},
+    helpers::audio_tap::DEFAULT_SAMPLE_RATE,
+    util::{f32s_from_bytes, f32s_to_bytes},
+};
+
+/// The ID used in place of a track ID for the master bus's meter
+pub const MASTER_METER_ID: u32 = u32::MAX;
+
+pub struct MixerMeters {
+    sample_rate: f32,
+    tracks: BTreeMap<u32, MeterTap>,
+    master: MeterTap,
+}
+
+impl Default for MixerMeters {
+    fn default() -> Self { MixerMeters::new(DEFAULT_SAMPLE_RATE) }
+}
+
+impl MixerMeters {
+    pub fn new(sample_rate: f32) -> Self {
+        MixerMeters {
+            sample_rate,
+            tracks: BTreeMap::new(),
+            master: MeterTap::new(MeterBallistics::default(), sample_rate),
+        }
+    }
+
+    /// `meter_id` is either a track ID or `MASTER_METER_ID`
+    pub fn process_blocks(&mut self, meter_id: u32, blocks: &[BlockLevels]) {
+        let sample_rate = self.sample_rate;
+        let tap = if meter_id == MASTER_METER_ID {
+            &mut self.master
+        } else {
+            self.tracks
+                .entry(meter_id)
+                .or_insert_with(|| MeterTap::new(MeterBallistics::default(), sample_rate))
+        };
+        for &block in blocks {
+            tap.process_levels(block);
+        }
+    }
+
+    /// Drops the meters of tracks that no longer exist
+    pub fn retain_tracks(&mut self, track_ids: &[u32]) {
+        self.tracks.retain(|id, _| track_ids.contains(id));
+    }
+
+    pub fn reset_clipped(&mut self) {
+        self.master.meter.reset_clipped();
+        for tap in self.tracks.values_mut() {
+            tap.meter.reset_clipped();
+        }
+    }
+
+    /// Returns the readings of the provided tracks' meters in order, followed by the master's.
+    /// Tracks that haven't been sent any audio read as silent.
+    pub fn readings(&self, track_ids: impl Iterator<Item = u32>) -> Vec<MeterReading> {
+        track_ids
+            .map(|id| {
+                self.tracks
+                    .get(&id)
+                    .map(|tap| tap.meter.reading())
+                    .unwrap_or_default()
+            })
+            .chain(std::iter::once(self.master.meter.reading()))
+            .collect()
+    }
+}
+
+/// Decodes a batch of block summaries sent from JS.  The message is the `u32` meter ID and the
+/// `u32` number of frames in each block followed by the peak and mean square of each block as
+/// `f32`s.
+pub fn decode_meter_blocks(val: &[u8]) -> Option<(u32, Vec<BlockLevels>)> {
+    if val.len() < 8 {
+        return None;
+    }
+    let meter_id = u32::from_ne_bytes([val[0], val[1], val[2], val[3]]);
+    let frame_count = u32::from_ne_bytes([val[4], val[5], val[6], val[7]]) as usize;
+    let blocks = f32s_from_bytes(&val[8..])
+        .chunks_exact(2)
+        .map(|levels| BlockLevels {
+            peak: levels[0],
+            mean_square: levels[1],
+            frame_count,
+        })
+        .collect();
+    Some((meter_id, blocks))
+}
+
+/// Encodes readings as four `f32`s each: peak, RMS, held peak, and 1 if clipped or 0 otherwise
+pub fn encode_readings(readings: &[MeterReading]) -> Vec<u8> {
+    let values: Vec<f32> = readings
+        .iter()
+        .flat_map(|reading| {
+            let clipped = if reading.clipped { 1. } else { 0. };
+            vec![reading.peak, reading.rms, reading.held_peak, clipped]
+        })
+        .collect();
+    f32s_to_bytes(&values)
+}
@@ -0,0 +1,155 @@
+//! The tracks and master bus of a mixer.  The state is owned and persisted here, and the JS side of
+//! the mixer builds the audio graph that implements it.
+
+use crate::util::clamp;
+
+const MAX_GAIN: f32 = 2.;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MixerTrack {
+    pub id: u32,
+    pub name: String,
+    /// Linear gain applied to the track, in the range [0, 2]
+    pub gain: f32,
+    /// Stereo pan position from -1 (hard left) to 1 (hard right)
+    pub pan: f32,
+    pub muted: bool,
+    pub soloed: bool,
+    /// The ID of the VC whose audio output is routed through this track.  If it's a MIDI editor,
+    /// the synths that it's connected to are routed instead.
+    pub source_vc_id: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MixerState {
+    tracks: Vec<MixerTrack>,
+    pub master_gain: f32,
+    next_track_id: u32,
+}
+
+impl Default for MixerState {
+    fn default() -> Self {
+        let mut state = MixerState {
+            tracks: Vec::new(),
+            master_gain: 1.,
+            next_track_id: 0,
+        };
+        state.add_track();
+        state
+    }
+}
+
+#[derive(Serialize)]
+struct SerializedTrack<'a> {
+    #[serde(flatten)]
+    track: &'a MixerTrack,
+    effective_gain: f32,
+}
+
+/// The state sent to the JS side of the mixer, which includes the effective gain of each track so
+/// that it doesn't have to handle mute and solo itself
+#[derive(Serialize)]
+struct SerializedMixerState<'a> {
+    tracks: Vec<SerializedTrack<'a>>,
+    master_gain: f32,
+}
+
+impl MixerState {
+    pub fn tracks(&self) -> &[MixerTrack] { &self.tracks }
+
+    pub fn get_track(&self, id: u32) -> Option<&MixerTrack> {
+        self.tracks.iter().find(|track| track.id == id)
+    }
+
+    pub fn get_track_mut(&mut self, id: u32) -> Option<&mut MixerTrack> {
+        self.tracks.iter_mut().find(|track| track.id == id)
+    }
+
+    /// Adds a new track at unity gain with nothing routed to it, returning its ID
+    pub fn add_track(&mut self) -> u32 {
+        let id = self.next_track_id;
+        self.next_track_id += 1;
+        self.tracks.push(MixerTrack {
+            id,
+            name: format!("Track {}", id + 1),
+            gain: 1.,
+            pan: 0.,
+            muted: false,
+            soloed: false,
+            source_vc_id: None,
+        });
+        id
+    }
+
+    pub fn remove_track(&mut self, id: u32) -> Option<MixerTrack> {
+        let ix = self.tracks.iter().position(|track| track.id == id)?;
+        Some(self.tracks.remove(ix))
+    }
+
+    /// Returns `false` if no track with the provided ID exists
+    pub fn set_track_gain(&mut self, id: u32, gain: f32) -> bool {
+        self.get_track_mut(id)
+            .map(|track| track.gain = clamp(gain, 0., MAX_GAIN))
+            .is_some()
+    }
+
+    /// Returns `false` if no track with the provided ID exists
+    pub fn set_track_pan(&mut self, id: u32, pan: f32) -> bool {
+        self.get_track_mut(id)
+            .map(|track| track.pan = clamp(pan, -1., 1.))
+            .is_some()
+    }
+
+    /// Returns `false` if no track with the provided ID exists
+    pub fn set_track_muted(&mut self, id: u32, muted: bool) -> bool {
+        self.get_track_mut(id)
+            .map(|track| track.muted = muted)
+            .is_some()
+    }
+
+    /// Returns `false` if no track with the provided ID exists
+    pub fn set_track_soloed(&mut self, id: u32, soloed: bool) -> bool {
+        self.get_track_mut(id)
+            .map(|track| track.soloed = soloed)
+            .is_some()
+    }
+
+    /// Returns `false` if no track with the provided ID exists
+    pub fn set_track_source(&mut self, id: u32, source_vc_id: Option<String>) -> bool {
+        self.get_track_mut(id)
+            .map(|track| track.source_vc_id = source_vc_id)
+            .is_some()
+    }
+
+    pub fn set_master_gain(&mut self, gain: f32) { self.master_gain = clamp(gain, 0., MAX_GAIN); }
+
+    /// A track is audible if it isn't muted and either it's soloed or no tracks are soloed
+    pub fn is_track_audible(&self, track: &MixerTrack) -> bool {
+        !track.muted && (track.soloed || !self.tracks.iter().any(|track| track.soloed))
+    }
+
+    /// Returns the gain that should actually be applied to the track, taking mute and solo into
+    /// account.
+    pub fn get_effective_gain(&self, track: &MixerTrack) -> f32 {
+        if self.is_track_audible(track) {
+            track.gain
+        } else {
+            0.
+        }
+    }
+
+    pub fn serialize(&self) -> String {
+        let serialized = SerializedMixerState {
+            tracks: self
+                .tracks
+                .iter()
+                .map(|track| SerializedTrack {
+                    track,
+                    effective_gain: self.get_effective_gain(track),
+                })
+                .collect(),
+            master_gain: self.master_gain,
+        };
+        serde_json::to_string(&serialized).expect("Error serializing `MixerState` to String")
+    }
+}
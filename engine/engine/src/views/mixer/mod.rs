@@ -0,0 +1,210 @@
+//! Defines a view for mixing the audio output of other VCs together, with per-track gain, pan,
+//! mute, and solo feeding into a master bus.  Every track and the master bus are metered.
+
+use serde_json;
+use uuid::Uuid;
+
+use crate::{
+    helpers::grid::prelude::*,
+    view_context::{
+        message::{MessageKind, MessageRegistry},
+        Message, ViewContext,
+    },
+};
+
+pub mod metering;
+pub mod mixer_state;
+
+use self::{
+    metering::{decode_meter_blocks, encode_readings, MixerMeters},
+    mixer_state::MixerState,
+};
+
+#[derive(Deserialize)]
+struct TrackSourceMessage {
+    id: u32,
+    #[serde(rename = "vcId")]
+    vc_id: Option<String>,
+}
+
+/// The mixer's state and meters live here, but the audio graph and UI are implemented in JS.  The
+/// JS side is sent the full state every time that it changes, except for changes to gains and pans
+/// which only send the values that changed.
+#[derive(Serialize, Deserialize)]
+pub struct Mixer {
+    pub uuid: Uuid,
+    #[serde(default)]
+    pub state: MixerState,
+    #[serde(skip)]
+    meters: MixerMeters,
+}
+
+/// Parameter changes are sent as binary messages since they're sent continuously while dragging
+/// sliders
+static MIXER_MESSAGE_HANDLERS: MessageRegistry<Mixer> = MessageRegistry {
+    handlers: &[
+        (MessageKind::SetTrackGain, Mixer::handle_param_message),
+        (MessageKind::SetTrackPan, Mixer::handle_param_message),
+        (MessageKind::SetTrackMuted, Mixer::handle_param_message),
+        (MessageKind::SetTrackSoloed, Mixer::handle_param_message),
+        (MessageKind::SetMasterGain, Mixer::handle_param_message),
+    ],
+};
+
+impl Mixer {
+    pub fn new(uuid: Uuid) -> Self {
+        Mixer {
+            uuid,
+            state: MixerState::default(),
+            meters: MixerMeters::default(),
+        }
+    }
+
+    pub fn get_state_key(&self) -> String { format!("mixer_{}", self.uuid) }
+
+    /// Sends the current state to JS so that it can update the audio graph, returning the
+    /// serialized state.
+    fn sync_state(&self) -> String {
+        let serialized = self.state.serialize();
+        js::set_mixer_state(&self.get_state_key(), &serialized);
+        serialized
+    }
+
+    /// Sends the gain and pan of a single track to JS.  Returns `false` if no track with the
+    /// provided ID exists.
+    fn sync_track_params(&self, track_id: u32) -> bool {
+        let track = match self.state.get_track(track_id) {
+            Some(track) => track,
+            None => return false,
+        };
+        js::set_mixer_track_params(
+            &self.get_state_key(),
+            track.id,
+            track.gain,
+            track.pan,
+            self.state.get_effective_gain(track),
+        );
+        true
+    }
+
+    /// JS is sent the changes itself, so nothing is returned
+    fn handle_param_message(&mut self, message: Message) -> Option<Vec<u8>> {
+        let found = match message {
+            // These are sent continuously while dragging sliders, so only the changed values are
+            // sent to JS rather than the full state
+            Message::SetTrackGain { track_id, gain } =>
+                self.state.set_track_gain(track_id, gain) && self.sync_track_params(track_id),
+            Message::SetTrackPan { track_id, pan } =>
+                self.state.set_track_pan(track_id, pan) && self.sync_track_params(track_id),
+            Message::SetMasterGain { gain } => {
+                self.state.set_master_gain(gain);
+                js::set_mixer_master_gain(&self.get_state_key(), self.state.master_gain);
+                true
+            },
+            // Muting or soloing a track can change the effective gains of all of the other tracks
+            Message::SetTrackMuted { track_id, muted } => {
+                let found = self.state.set_track_muted(track_id, muted);
+                self.sync_state();
+                found
+            },
+            Message::SetTrackSoloed { track_id, soloed } => {
+                let found = self.state.set_track_soloed(track_id, soloed);
+                self.sync_state();
+                found
+            },
+            _ => return None,
+        };
+
+        if !found {
+            warn!("Mixer message {:?} referenced a track that doesn't exist", message);
+        }
+        None
+    }
+}
+
+impl ViewContext for Mixer {
+    fn init(&mut self) {
+        let sample_rate = js::init_mixer(&self.get_state_key(), &self.state.serialize());
+        self.meters = MixerMeters::new(sample_rate);
+    }
+
+    fn cleanup(&mut self) { js::cleanup_mixer(&self.get_state_key()); }
+
+    fn get_id(&self) -> String { self.uuid.to_string() }
+
+    fn hide(&mut self) { js::hide_mixer(&self.get_state_key()); }
+
+    fn unhide(&mut self) { js::unhide_mixer(&self.get_state_key()); }
+
+    fn get_audio_connectables(&self) -> JsValue {
+        js::get_mixer_audio_connectables(&self.get_state_key())
+    }
+
+    fn handle_message(&mut self, key: &str, val: &[u8]) -> Option<Vec<u8>> {
+        let found = match key {
+            "get_state" => return Some(self.state.serialize().into_bytes()),
+            "push_meter_blocks" => {
+                match decode_meter_blocks(val) {
+                    Some((meter_id, blocks)) => self.meters.process_blocks(meter_id, &blocks),
+                    None => error!("Invalid message for \"push_meter_blocks\""),
+                }
+                return None;
+            },
+            "get_meter_levels" => {
+                let track_ids = self.state.tracks().iter().map(|track| track.id);
+                return Some(encode_readings(&self.meters.readings(track_ids)));
+            },
+            "reset_meter_clips" => {
+                self.meters.reset_clipped();
+                return None;
+            },
+            "add_track" => {
+                self.state.add_track();
+                true
+            },
+            "remove_track" => {
+                assert_eq!(
+                    val.len(),
+                    4,
+                    "Message for \"remove_track\" must be a 4-byte `u32` of the track ID"
+                );
+                let id = u32::from_ne_bytes([val[0], val[1], val[2], val[3]]);
+                let removed = self.state.remove_track(id).is_some();
+                let track_ids: Vec<u32> =
+                    self.state.tracks().iter().map(|track| track.id).collect();
+                self.meters.retain_tracks(&track_ids);
+                removed
+            },
+            "set_track_source" => match serde_json::from_slice(val) {
+                Ok(TrackSourceMessage { id, vc_id }) => self.state.set_track_source(id, vc_id),
+                Err(err) => {
+                    error!("Error decoding mixer track source: {:?}", err);
+                    return None;
+                },
+            },
+            _ => return None,
+        };
+
+        if !found {
+            warn!("Mixer message \"{}\" referenced a track that doesn't exist", key);
+        }
+        Some(self.sync_state().into_bytes())
+    }
+
+    fn handle_binary_message(&mut self, message: Message) -> Option<Vec<u8>> {
+        MIXER_MESSAGE_HANDLERS.dispatch(self, message)
+    }
+
+    fn save(&mut self) -> String {
+        serde_json::to_string(self).expect("Error serializing `Mixer` to String")
+    }
+}
+
+pub fn mk_mixer(definition_opt: Option<&str>, uuid: Uuid) -> Box<dyn ViewContext> {
+    let mixer: Mixer = match definition_opt {
+        Some(definition) =>
+            serde_json::from_str(definition).expect("Error while deserializing `Mixer`"),
+        None => Mixer::new(uuid),
+    };
+    Box::new(mixer)
+}
@@ -1,9 +1,15 @@
 pub mod clip_compositor;
+pub mod clip_launcher;
 pub mod composition_sharing;
+pub mod drum_sequencer;
 pub mod faust_editor;
 pub mod graph_editor;
 pub mod midi_editor;
 pub mod midi_keyboard;
+pub mod mixer;
+pub mod oscilloscope;
+pub mod sample_editor;
 pub mod sample_library;
 pub mod sequencer;
+pub mod spectrum_analyzer;
 pub mod synth_designer;
@@ -0,0 +1,104 @@
+//! Defines a view that displays the waveform of audio tapped from the master output or from
+//! anywhere else in the patch network, which is useful for getting feedback while designing sounds.
+
+use serde_json;
+use uuid::Uuid;
+
+use crate::{
+    helpers::grid::prelude::*,
+    util::{f32s_from_bytes, f32s_to_bytes},
+    view_context::ViewContext,
+};
+
+pub mod scope;
+
+use self::scope::{Scope, ScopeConf};
+
+/// The JS side taps the audio and sends it over in blocks, which are buffered and triggered here.
+/// The UI polls for the current frame while the view is visible and renders it.
+#[derive(Serialize, Deserialize)]
+pub struct Oscilloscope {
+    pub uuid: Uuid,
+    #[serde(default)]
+    pub conf: ScopeConf,
+    #[serde(skip)]
+    scope: Scope,
+}
+
+impl Oscilloscope {
+    pub fn new(uuid: Uuid) -> Self {
+        Oscilloscope {
+            uuid,
+            conf: ScopeConf::default(),
+            scope: Scope::default(),
+        }
+    }
+
+    pub fn get_state_key(&self) -> String { format!("oscilloscope_{}", self.uuid) }
+
+    fn serialize_conf(&self) -> String {
+        serde_json::to_string(&self.conf).expect("Error serializing `ScopeConf`")
+    }
+}
+
+impl ViewContext for Oscilloscope {
+    fn init(&mut self) {
+        let sample_rate = js::init_oscilloscope(&self.get_state_key(), &self.serialize_conf());
+        self.scope = Scope::new(self.conf.clone(), sample_rate);
+        self.conf = self.scope.conf().clone();
+    }
+
+    fn cleanup(&mut self) {
+        js::cleanup_oscilloscope(&self.get_state_key());
+    }
+
+    fn get_id(&self) -> String { self.uuid.to_string() }
+
+    fn hide(&mut self) {
+        js::hide_oscilloscope(&self.get_state_key());
+    }
+
+    fn unhide(&mut self) {
+        js::unhide_oscilloscope(&self.get_state_key());
+    }
+
+    fn get_audio_connectables(&self) -> JsValue {
+        js::get_oscilloscope_audio_connectables(&self.get_state_key())
+    }
+
+    fn handle_message(&mut self, key: &str, val: &[u8]) -> Option<Vec<u8>> {
+        match key {
+            "push_samples" => {
+                self.scope.push_samples(&f32s_from_bytes(val));
+                None
+            },
+            "get_frame" => Some(f32s_to_bytes(self.scope.frame())),
+            "get_conf" => Some(self.serialize_conf().into_bytes()),
+            "set_conf" => {
+                match serde_json::from_slice(val) {
+                    Ok(conf) => {
+                        self.scope.set_conf(conf);
+                        self.conf = self.scope.conf().clone();
+                    },
+                    Err(err) => error!("Error decoding oscilloscope config: {:?}", err),
+                }
+                Some(self.serialize_conf().into_bytes())
+            },
+            _ => None,
+        }
+    }
+
+    fn save(&mut self) -> String {
+        serde_json::to_string(self).expect("Error serializing `Oscilloscope` to String")
+    }
+}
+
+pub fn mk_oscilloscope(definition_opt: Option<&str>, uuid: Uuid) -> Box<dyn ViewContext> {
+    let oscilloscope: Oscilloscope = match definition_opt {
+        Some(definition) => {
+            serde_json::from_str(definition).expect("Error while deserializing `Oscilloscope`")
+        },
+        None => Oscilloscope::new(uuid),
+    };
+    Box::new(oscilloscope)
+}
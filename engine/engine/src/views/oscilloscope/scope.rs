@@ -0,0 +1,198 @@
+//! Buffers the audio sent over from the tap point and picks out the frames that are displayed by
+//! the oscilloscope.  Periodic waveforms are kept steady on screen by starting each frame at the
+//! point where the signal crosses the trigger level, interpolated to a fraction of a sample so that
+//! the waveform doesn't jitter back and forth.
+
+use crate::{
+    helpers::audio_tap::{TapLocation, DEFAULT_SAMPLE_RATE},
+    util::clamp,
+};
+
+pub const MIN_TIME_SPAN_MS: f32 = 1.;
+pub const MAX_TIME_SPAN_MS: f32 = 1000.;
+pub const MIN_AMPLITUDE_SCALE: f32 = 0.1;
+pub const MAX_AMPLITUDE_SCALE: f32 = 20.;
+/// Frames spanning more samples than this are downsampled before being sent to the UI
+pub const MAX_DISPLAY_POINTS: usize = 2048;
+/// How far the signal has to move back past the trigger level before another crossing counts.
+/// This keeps noise riding on the signal from triggering at random points on the waveform.
+const TRIGGER_HYSTERESIS: f32 = 0.01;
+/// If no trigger is found for this many frames' worth of samples, the scope starts free-running
+/// so that signals that never cross the trigger level are still displayed.
+const AUTO_TRIGGER_FRAMES: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerMode {
+    /// Always display the most recent samples
+    Free,
+    /// Start frames where the signal rises through the trigger level
+    Rising,
+    /// Start frames where the signal falls through the trigger level
+    Falling,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScopeConf {
+    /// Length of time covered by each displayed frame
+    pub time_span_ms: f32,
+    /// Multiplier applied to the signal before it's displayed
+    pub amplitude_scale: f32,
+    pub trigger_mode: TriggerMode,
+    pub trigger_level: f32,
+    pub tap: TapLocation,
+}
+
+impl Default for ScopeConf {
+    fn default() -> Self {
+        ScopeConf {
+            time_span_ms: 20.,
+            amplitude_scale: 1.,
+            trigger_mode: TriggerMode::Rising,
+            trigger_level: 0.,
+            tap: TapLocation::Master,
+        }
+    }
+}
+
+impl ScopeConf {
+    /// Clamps all values into their valid ranges
+    pub fn sanitize(&mut self) {
+        self.time_span_ms = clamp(self.time_span_ms, MIN_TIME_SPAN_MS, MAX_TIME_SPAN_MS);
+        self.amplitude_scale = clamp(
+            self.amplitude_scale,
+            MIN_AMPLITUDE_SCALE,
+            MAX_AMPLITUDE_SCALE,
+        );
+        self.trigger_level = clamp(self.trigger_level, -1., 1.);
+    }
+}
+
+pub struct Scope {
+    conf: ScopeConf,
+    sample_rate: f32,
+    /// Number of samples covered by each displayed frame
+    frame_len: usize,
+    /// The most recent samples, oldest first
+    buffer: Vec<f32>,
+    /// Samples pushed since the last frame that was triggered
+    samples_since_trigger: usize,
+    /// The currently displayed frame, already scaled.  It's held until a new trigger arrives.
+    frame: Vec<f32>,
+}
+
+impl Default for Scope {
+    fn default() -> Self { Scope::new(ScopeConf::default(), DEFAULT_SAMPLE_RATE) }
+}
+
+impl Scope {
+    pub fn new(mut conf: ScopeConf, sample_rate: f32) -> Self {
+        conf.sanitize();
+        let frame_len = ((conf.time_span_ms / 1000. * sample_rate).round() as usize).max(2);
+        Scope {
+            conf,
+            sample_rate,
+            frame_len,
+            buffer: Vec::with_capacity(frame_len * 3),
+            samples_since_trigger: 0,
+            frame: vec![0.; frame_len.min(MAX_DISPLAY_POINTS)],
+        }
+    }
+
+    pub fn conf(&self) -> &ScopeConf { &self.conf }
+
+    pub fn sample_rate(&self) -> f32 { self.sample_rate }
+
+    /// The samples of the current frame scaled by the amplitude scale, evenly spaced over the
+    /// configured time span
+    pub fn frame(&self) -> &[f32] { &self.frame }
+
+    /// Updates the amplitude scale and trigger without clearing the buffered audio
+    pub fn set_conf(&mut self, mut conf: ScopeConf) {
+        conf.sanitize();
+        if conf.time_span_ms != self.conf.time_span_ms {
+            *self = Scope::new(conf, self.sample_rate);
+        } else {
+            self.conf = conf;
+        }
+    }
+
+    pub fn push_samples(&mut self, samples: &[f32]) {
+        self.buffer.extend_from_slice(samples);
+        self.samples_since_trigger += samples.len();
+
+        let is_free_running = self.conf.trigger_mode == TriggerMode::Free
+            || self.samples_since_trigger > self.frame_len * AUTO_TRIGGER_FRAMES;
+        if let Some(trigger_pos) = self.find_trigger() {
+            self.render_frame(trigger_pos);
+            self.samples_since_trigger = 0;
+        } else if is_free_running && self.buffer.len() >= self.frame_len {
+            self.render_frame((self.buffer.len() - self.frame_len) as f32);
+        }
+
+        // Enough history is kept to search a full frame back for a trigger
+        let max_len = self.frame_len * 2 + samples.len();
+        if self.buffer.len() > max_len {
+            self.buffer.drain(..self.buffer.len() - max_len);
+        }
+    }
+
+    /// Returns the fractional position in the buffer of the latest trigger point that has a full
+    /// frame of samples after it
+    fn find_trigger(&self) -> Option<f32> {
+        let invert = match self.conf.trigger_mode {
+            TriggerMode::Free => return None,
+            TriggerMode::Rising => false,
+            TriggerMode::Falling => true,
+        };
+        // Falling edges are found by looking for rising edges in the inverted signal
+        let level = if invert {
+            -self.conf.trigger_level
+        } else {
+            self.conf.trigger_level
+        };
+        let sample = |ix: usize| {
+            if invert {
+                -self.buffer[ix]
+            } else {
+                self.buffer[ix]
+            }
+        };
+
+        let last_start = self.buffer.len().checked_sub(self.frame_len)?;
+        let mut armed = false;
+        let mut trigger_pos = None;
+        for ix in 0..=last_start {
+            let cur = sample(ix);
+            if cur < level - TRIGGER_HYSTERESIS {
+                armed = true;
+            } else if armed && cur >= level {
+                let prev = sample(ix - 1);
+                let offset = if cur > prev {
+                    (level - prev) / (cur - prev)
+                } else {
+                    1.
+                };
+                trigger_pos = Some((ix - 1) as f32 + clamp(offset, 0., 1.));
+                armed = false;
+            }
+        }
+        trigger_pos
+    }
+
+    /// Fills the displayed frame with the `frame_len` samples starting at `start_pos`,
+    /// interpolating between samples when `start_pos` falls between them
+    fn render_frame(&mut self, start_pos: f32) {
+        let point_count = self.frame.len();
+        let step = (self.frame_len - 1) as f32 / (point_count - 1) as f32;
+        let last_ix = self.buffer.len() - 1;
+        for (point_ix, point) in self.frame.iter_mut().enumerate() {
+            let pos = start_pos + point_ix as f32 * step;
+            let ix = (pos.floor() as usize).min(last_ix);
+            let next = self.buffer[(ix + 1).min(last_ix)];
+            let mix = pos - ix as f32;
+            *point = (self.buffer[ix] * (1. - mix) + next * mix) * self.conf.amplitude_scale;
+        }
+    }
+}
@@ -0,0 +1,215 @@
+//! Destructive edits that can be applied to the sample opened in the sample editor.  Every edit
+//! applies to a range of frames, which is the current selection in the UI, and to all channels.
+
+use crate::{
+    dsp::time_stretch::{pitch_shift, stretched_len, time_stretch},
+    sample_import::ImportedSample,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FadeCurve {
+    Linear,
+    /// Changes slowly at the quiet end and quickly at the loud end, which sounds more even than a
+    /// linear fade
+    Exponential,
+    /// Keeps the power constant when a fade out is overlapped with a matching fade in
+    EqualPower,
+}
+
+impl FadeCurve {
+    /// Returns the gain at `pos` through a fade in, from 0 at the start to 1 at the end
+    pub fn gain(self, pos: f32) -> f32 {
+        match self {
+            FadeCurve::Linear => pos,
+            FadeCurve::Exponential => pos * pos * pos,
+            FadeCurve::EqualPower => (pos * std::f32::consts::FRAC_PI_2).sin(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SampleEdit {
+    /// Removes everything outside of the range
+    Trim {
+        start: usize,
+        end: usize,
+    },
+    /// Scales the range so that its loudest sample is at `peak_db` dBFS
+    Normalize {
+        start: usize,
+        end: usize,
+        peak_db: f32,
+    },
+    Reverse {
+        start: usize,
+        end: usize,
+    },
+    FadeIn {
+        start: usize,
+        end: usize,
+        curve: FadeCurve,
+    },
+    FadeOut {
+        start: usize,
+        end: usize,
+        curve: FadeCurve,
+    },
+    /// Changes the length of the range by a factor of `stretch` without changing its pitch, which
+    /// is used to conform loops to the tempo of the project
+    TimeStretch {
+        start: usize,
+        end: usize,
+        stretch: f64,
+    },
+    /// Changes the pitch of the range without changing its length
+    PitchShift {
+        start: usize,
+        end: usize,
+        semitones: f32,
+    },
+}
+
+/// Multiplies the frames in `[start, end)` by `gain`, which is called with each frame's position
+/// through the range.  The first and last frames of the range are at positions 0 and 1 exactly.
+fn apply_fade(sample: &mut ImportedSample, start: usize, end: usize, gain: impl Fn(f32) -> f32) {
+    let last_ix = ((end - start - 1) as f32).max(1.);
+    for channel in &mut sample.channels {
+        for (i, sample) in channel[start..end].iter_mut().enumerate() {
+            *sample *= gain(i as f32 / last_ix);
+        }
+    }
+}
+
+/// Replaces the frames in `[start, end)` of all channels with the result of `process`, which may
+/// return a different number of frames than it's given
+fn replace_range(
+    sample: &mut ImportedSample,
+    start: usize,
+    end: usize,
+    process: impl Fn(&[Vec<f32>]) -> Vec<Vec<f32>>,
+) {
+    let range: Vec<Vec<f32>> = sample
+        .channels
+        .iter()
+        .map(|channel| channel[start..end].to_vec())
+        .collect();
+    for (channel, processed) in sample.channels.iter_mut().zip(process(&range)) {
+        channel.splice(start..end, processed);
+    }
+}
+
+impl SampleEdit {
+    fn range(&self) -> (usize, usize) {
+        match *self {
+            SampleEdit::Trim { start, end }
+            | SampleEdit::Normalize { start, end, .. }
+            | SampleEdit::Reverse { start, end }
+            | SampleEdit::FadeIn { start, end, .. }
+            | SampleEdit::FadeOut { start, end, .. }
+            | SampleEdit::TimeStretch { start, end, .. }
+            | SampleEdit::PitchShift { start, end, .. } => (start, end),
+        }
+    }
+
+    /// Applies the edit to `sample`, returning `false` without changing anything if its range
+    /// doesn't contain any frames of the sample.
+    pub fn apply(&self, sample: &mut ImportedSample) -> bool {
+        let (start, end) = self.range();
+        let end = end.min(sample.len_frames());
+        if start >= end {
+            return false;
+        }
+
+        match *self {
+            SampleEdit::Trim { .. } => {
+                for channel in &mut sample.channels {
+                    channel.truncate(end);
+                    channel.drain(..start);
+                }
+            },
+            SampleEdit::Normalize { peak_db, .. } => {
+                let peak = sample
+                    .channels
+                    .iter()
+                    .flat_map(|channel| channel[start..end].iter())
+                    .fold(0.0f32, |acc, sample| acc.max(sample.abs()));
+                // Silence can't be normalized
+                if peak == 0. {
+                    return false;
+                }
+
+                let gain = 10f32.powf(peak_db / 20.) / peak;
+                for channel in &mut sample.channels {
+                    channel[start..end]
+                        .iter_mut()
+                        .for_each(|sample| *sample *= gain);
+                }
+            },
+            SampleEdit::Reverse { .. } => {
+                for channel in &mut sample.channels {
+                    channel[start..end].reverse();
+                }
+            },
+            SampleEdit::FadeIn { curve, .. } =>
+                apply_fade(sample, start, end, |pos| curve.gain(pos)),
+            SampleEdit::FadeOut { curve, .. } =>
+                apply_fade(sample, start, end, |pos| curve.gain(1. - pos)),
+            SampleEdit::TimeStretch { stretch, .. } => {
+                if !(stretch > 0. && stretch.is_finite()) {
+                    return false;
+                }
+
+                let sample_rate = sample.sample_rate;
+                replace_range(sample, start, end, |range| {
+                    time_stretch(range, sample_rate, stretch)
+                });
+            },
+            SampleEdit::PitchShift { semitones, .. } => {
+                let sample_rate = sample.sample_rate;
+                replace_range(sample, start, end, |range| {
+                    pitch_shift(range, sample_rate, semitones)
+                });
+            },
+        }
+        true
+    }
+
+    /// Returns where a loop point at `pos`, a fraction of the length of the sample before the edit,
+    /// ends up after the edit has been applied to a sample of `len_frames` frames.  Only trimming
+    /// and time-stretching move the audio that loop points refer to.
+    pub fn adjust_loop_point(&self, pos: f64, len_frames: usize) -> f64 {
+        let (start, end) = self.range();
+        let end = end.min(len_frames);
+        if start >= end {
+            return pos;
+        }
+
+        let frame = pos * len_frames as f64;
+        match *self {
+            SampleEdit::Trim { .. } =>
+                ((frame - start as f64) / (end - start) as f64).clamp(0., 1.),
+            SampleEdit::TimeStretch { stretch, .. } if stretch > 0. && stretch.is_finite() => {
+                let range_len = (end - start) as f64;
+                let new_range_len = stretched_len(end - start, stretch) as f64;
+                let new_len = len_frames as f64 - range_len + new_range_len;
+                if new_len == 0. {
+                    return 0.;
+                }
+
+                // Loop points inside the range are stretched along with it and ones after it are
+                // moved by the change in its length
+                let new_frame = if frame < start as f64 {
+                    frame
+                } else if frame < end as f64 {
+                    start as f64 + (frame - start as f64) * new_range_len / range_len
+                } else {
+                    frame + new_range_len - range_len
+                };
+                (new_frame / new_len).clamp(0., 1.)
+            },
+            _ => pos,
+        }
+    }
+}
@@ -0,0 +1,355 @@
+//! Defines a view for viewing and editing samples.  The sample is opened from the sample pool and
+//! copied so that it can be edited without affecting samplers that are playing it.  Edited samples
+//! are saved back into the sample library as new samples.  Loop points are edited here as well and
+//! sent over to the sampler that the sample was opened from.
+//!
+//! Loops can be sliced at their transients so that each hit can be played on its own line of a
+//! MIDI editor by the sampler.
+
+use serde_json;
+use uuid::Uuid;
+
+use crate::{
+    dsp::transients::{detect_transients, TransientDetectionConf},
+    helpers::grid::prelude::*,
+    offline_render::{encode_wav, WavBitDepth},
+    sample_import::{get_sample_pool, ImportedSample},
+    util::f32s_to_bytes,
+    view_context::ViewContext,
+    views::drum_sequencer::drum_sequencer_state::SampleDescriptor,
+};
+
+pub mod edits;
+pub mod waveform;
+
+use self::{edits::SampleEdit, waveform::WaveformPeaks};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SampleEditorState {
+    /// The sample that's opened in the editor.  Edits only exist in memory until they're saved as
+    /// a new sample, at which point this is updated to point to it.
+    pub sample: Option<SampleDescriptor>,
+    /// VC ID of the sampler that the sample was opened from, which loop points are sent to
+    pub sampler_vc_id: Option<String>,
+    /// Loop points as a fraction of the length of the sample in the range [0, 1], in the same way
+    /// as the sampler's
+    pub loop_start: f64,
+    pub loop_end: f64,
+    /// Start of each slice as a fraction of the length of the sample, in ascending order.  Unless
+    /// there are no slices, the first one starts at 0.
+    pub slices: Vec<f64>,
+}
+
+impl Default for SampleEditorState {
+    fn default() -> Self {
+        SampleEditorState {
+            sample: None,
+            sampler_vc_id: None,
+            loop_start: 0.,
+            loop_end: 1.,
+            slices: Vec::new(),
+        }
+    }
+}
+
+/// Sorts the slices and removes any that are duplicates or outside of the sample, adding one at
+/// the start if it's missing
+fn normalize_slices(slices: &mut Vec<f64>) {
+    slices.retain(|pos| (0. ..1.).contains(pos));
+    slices.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    slices.dedup();
+    if !slices.is_empty() && slices[0] != 0. {
+        slices.insert(0, 0.);
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LoadSampleMessage {
+    sample: SampleDescriptor,
+    /// ID of the sample in the sample pool.  The sample editor takes over the reference to it.
+    pool_id: u32,
+    /// Set when the sample is the saved result of editing the previously opened sample, in which
+    /// case the loop points still apply to it
+    #[serde(default)]
+    keep_loop_points: bool,
+}
+
+#[derive(Deserialize)]
+struct LoopPointsMessage {
+    start: f64,
+    end: f64,
+}
+
+/// Sent to JS after every change so that it can update the UI
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SampleEditorInfo<'a> {
+    state: &'a SampleEditorState,
+    is_loaded: bool,
+    len_frames: usize,
+    channel_count: usize,
+    sample_rate: u32,
+    /// Set once the sample has been edited and until it's saved or reverted
+    is_modified: bool,
+}
+
+struct LoadedSample {
+    pool_id: u32,
+    sample: ImportedSample,
+    peaks: WaveformPeaks,
+    is_modified: bool,
+}
+
+impl LoadedSample {
+    fn new(pool_id: u32, sample: ImportedSample) -> Self {
+        LoadedSample {
+            pool_id,
+            peaks: WaveformPeaks::new(&sample.channels),
+            sample,
+            is_modified: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SampleEditor {
+    pub uuid: Uuid,
+    #[serde(default)]
+    pub state: SampleEditorState,
+    #[serde(skip)]
+    loaded: Option<LoadedSample>,
+}
+
+fn read_u32s(key: &str, val: &[u8]) -> Vec<u32> {
+    assert_eq!(
+        val.len() % 4,
+        0,
+        "Message for \"{}\" must be made up of `u32`s",
+        key
+    );
+    val.chunks_exact(4)
+        .map(|bytes| u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .collect()
+}
+
+impl SampleEditor {
+    pub fn new(uuid: Uuid) -> Self {
+        SampleEditor {
+            uuid,
+            state: SampleEditorState::default(),
+            loaded: None,
+        }
+    }
+
+    pub fn get_state_key(&self) -> String { format!("sampleEditor_{}", self.uuid) }
+
+    fn serialize_state(&self) -> String {
+        serde_json::to_string(&self.state).expect("Error serializing `SampleEditorState`")
+    }
+
+    fn serialize_info(&self) -> Vec<u8> {
+        let loaded = self.loaded.as_ref();
+        let info = SampleEditorInfo {
+            state: &self.state,
+            is_loaded: loaded.is_some(),
+            len_frames: loaded.map(|loaded| loaded.sample.len_frames()).unwrap_or(0),
+            channel_count: loaded.map(|loaded| loaded.sample.channel_count()).unwrap_or(0),
+            sample_rate: loaded.map(|loaded| loaded.sample.sample_rate).unwrap_or(0),
+            is_modified: loaded.map(|loaded| loaded.is_modified).unwrap_or(false),
+        };
+        serde_json::to_vec(&info).expect("Error serializing `SampleEditorInfo`")
+    }
+
+    /// Drops the loaded sample, releasing its reference in the sample pool
+    fn unload(&mut self) {
+        if let Some(loaded) = self.loaded.take() {
+            get_sample_pool().release(loaded.pool_id);
+        }
+    }
+
+    /// Opens the sample with the provided ID in the sample pool, taking over the reference to it.
+    /// The loop points are reset if a different sample is opened unless `keep_loop_points` is set.
+    fn load_sample(&mut self, descriptor: SampleDescriptor, pool_id: u32, keep_loop_points: bool) {
+        let sample = match get_sample_pool().get(pool_id) {
+            Some(sample) => sample.clone(),
+            None => {
+                error!("Tried to open sample {} which isn't in the sample pool", pool_id);
+                return;
+            },
+        };
+
+        self.unload();
+        self.loaded = Some(LoadedSample::new(pool_id, sample));
+        if self.state.sample.as_ref() != Some(&descriptor) && !keep_loop_points {
+            self.state.loop_start = 0.;
+            self.state.loop_end = 1.;
+            self.state.slices.clear();
+        }
+        self.state.sample = Some(descriptor);
+    }
+
+    /// Discards all edits, going back to the copy of the sample in the sample pool
+    fn revert(&mut self) {
+        let loaded = match self.loaded.as_mut() {
+            Some(loaded) => loaded,
+            None => return,
+        };
+        if let Some(sample) = get_sample_pool().get(loaded.pool_id) {
+            *loaded = LoadedSample::new(loaded.pool_id, sample.clone());
+        }
+    }
+
+    fn apply_edit(&mut self, edit: SampleEdit) -> bool {
+        let loaded = match self.loaded.as_mut() {
+            Some(loaded) => loaded,
+            None => return false,
+        };
+
+        let len_frames = loaded.sample.len_frames();
+        if !edit.apply(&mut loaded.sample) {
+            return false;
+        }
+        loaded.peaks = WaveformPeaks::new(&loaded.sample.channels);
+        loaded.is_modified = true;
+        self.state.loop_start = edit.adjust_loop_point(self.state.loop_start, len_frames);
+        self.state.loop_end = edit.adjust_loop_point(self.state.loop_end, len_frames);
+        for slice in &mut self.state.slices {
+            *slice = edit.adjust_loop_point(*slice, len_frames);
+        }
+        normalize_slices(&mut self.state.slices);
+        true
+    }
+
+    /// Slices the sample at each of its transients
+    fn detect_slices(&mut self, conf: &TransientDetectionConf) {
+        let sample = match self.loaded.as_ref() {
+            Some(loaded) => &loaded.sample,
+            None => return,
+        };
+        let len_frames = sample.len_frames() as f64;
+        let mut slices: Vec<f64> = detect_transients(&sample.channels, sample.sample_rate, conf)
+            .into_iter()
+            .map(|frame| frame as f64 / len_frames)
+            .collect();
+        normalize_slices(&mut slices);
+        self.state.slices = slices;
+    }
+
+    /// Returns the min/max peaks of each channel over the frames in `[start, end)` for display,
+    /// `pixel_count` `(min, max)` pairs for the first channel followed by the second and so on
+    fn get_peaks(&self, start: usize, end: usize, pixel_count: usize) -> Vec<f32> {
+        let loaded = match self.loaded.as_ref() {
+            Some(loaded) => loaded,
+            None => return Vec::new(),
+        };
+
+        let mut out = Vec::with_capacity(loaded.sample.channel_count() * pixel_count * 2);
+        for (channel_ix, channel) in loaded.sample.channels.iter().enumerate() {
+            for peak in loaded.peaks.peaks(channel_ix, channel, start, end, pixel_count) {
+                out.push(peak.min);
+                out.push(peak.max);
+            }
+        }
+        out
+    }
+}
+
+impl ViewContext for SampleEditor {
+    fn init(&mut self) { js::init_sample_editor(&self.get_state_key(), &self.serialize_state()); }
+
+    fn cleanup(&mut self) {
+        self.unload();
+        js::cleanup_sample_editor(&self.get_state_key());
+    }
+
+    fn get_id(&self) -> String { self.uuid.to_string() }
+
+    fn hide(&mut self) { js::hide_sample_editor(&self.get_state_key()); }
+
+    fn unhide(&mut self) { js::unhide_sample_editor(&self.get_state_key()); }
+
+    fn get_audio_connectables(&self) -> JsValue {
+        crate::view_context::create_empty_audio_connectables(self.uuid.to_string().as_str())
+    }
+
+    fn handle_message(&mut self, key: &str, val: &[u8]) -> Option<Vec<u8>> {
+        match key {
+            "get_info" => (),
+            "load_sample" => match serde_json::from_slice(val) {
+                Ok(LoadSampleMessage {
+                    sample,
+                    pool_id,
+                    keep_loop_points,
+                }) => self.load_sample(sample, pool_id, keep_loop_points),
+                Err(err) => error!("Error decoding sample to load into sample editor: {:?}", err),
+            },
+            "set_sampler" => match serde_json::from_slice(val) {
+                Ok(sampler_vc_id) => self.state.sampler_vc_id = sampler_vc_id,
+                Err(err) => error!("Error decoding sample editor sampler VC ID: {:?}", err),
+            },
+            "set_loop_points" => match serde_json::from_slice(val) {
+                Ok(LoopPointsMessage { start, end }) => {
+                    let (start, end) = (start.clamp(0., 1.), end.clamp(0., 1.));
+                    self.state.loop_start = start.min(end);
+                    self.state.loop_end = start.max(end);
+                },
+                Err(err) => error!("Error decoding sample editor loop points: {:?}", err),
+            },
+            "apply_edit" => match serde_json::from_slice(val) {
+                Ok(edit) =>
+                    if !self.apply_edit(edit) {
+                        warn!("Sample edit couldn't be applied to the loaded sample");
+                    },
+                Err(err) => error!("Error decoding sample edit: {:?}", err),
+            },
+            "revert" => self.revert(),
+            "detect_slices" => match serde_json::from_slice(val) {
+                Ok(conf) => self.detect_slices(&conf),
+                Err(err) => error!("Error decoding transient detection conf: {:?}", err),
+            },
+            "set_slices" => match serde_json::from_slice(val) {
+                Ok(mut slices) => {
+                    normalize_slices(&mut slices);
+                    self.state.slices = slices;
+                },
+                Err(err) => error!("Error decoding sample editor slices: {:?}", err),
+            },
+            // Returns the peaks directly rather than the info
+            "get_peaks" => {
+                let args = read_u32s(key, val);
+                assert_eq!(args.len(), 3, "`get_peaks` takes a start, end, and pixel count");
+                let peaks = self.get_peaks(args[0] as usize, args[1] as usize, args[2] as usize);
+                return Some(f32s_to_bytes(&peaks));
+            },
+            // Returns the edited sample as a 24-bit WAV file
+            "export_wav" => {
+                let loaded = self.loaded.as_ref()?;
+                let channels: Vec<&[f32]> =
+                    loaded.sample.channels.iter().map(Vec::as_slice).collect();
+                return Some(encode_wav(
+                    &channels,
+                    loaded.sample.sample_rate,
+                    WavBitDepth::TwentyFour,
+                ));
+            },
+            _ => return None,
+        }
+
+        Some(self.serialize_info())
+    }
+
+    fn save(&mut self) -> String {
+        serde_json::to_string(self).expect("Error serializing `SampleEditor` to String")
+    }
+}
+
+pub fn mk_sample_editor(definition_opt: Option<&str>, uuid: Uuid) -> Box<dyn ViewContext> {
+    let sample_editor: SampleEditor = match definition_opt {
+        Some(definition) =>
+            serde_json::from_str(definition).expect("Error while deserializing `SampleEditor`"),
+        None => SampleEditor::new(uuid),
+    };
+    Box::new(sample_editor)
+}
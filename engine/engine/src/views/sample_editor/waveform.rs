@@ -0,0 +1,122 @@
+//! Computes the min/max peaks that are drawn for a sample's waveform.  Going through every frame of
+//! a long sample each time the view is zoomed or scrolled would be too slow, so the peaks of fixed
+//! size blocks are computed up front at several resolutions.  Each displayed pixel is then built
+//! out of the blocks of the coarsest resolution that's still finer than a pixel, only falling back
+//! to the raw samples when zoomed in closer than the finest resolution.
+
+/// Number of frames summarized by each peak at the finest level
+pub const BASE_BLOCK_SIZE: usize = 64;
+/// How many blocks of each level are merged into a single block of the next level
+const LEVEL_FACTOR: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Peak {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Peak {
+    /// Contains nothing, so merging it with another peak returns the other peak unchanged
+    const EMPTY: Peak = Peak {
+        min: f32::INFINITY,
+        max: f32::NEG_INFINITY,
+    };
+
+    fn merge(self, other: Peak) -> Peak {
+        Peak {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    fn merge_all(peaks: &[Peak]) -> Peak {
+        peaks.iter().fold(Peak::EMPTY, |acc, &peak| acc.merge(peak))
+    }
+
+    fn of_samples(samples: &[f32]) -> Peak {
+        samples.iter().fold(Peak::EMPTY, |peak, &sample| {
+            peak.merge(Peak {
+                min: sample,
+                max: sample,
+            })
+        })
+    }
+}
+
+pub struct WaveformPeaks {
+    /// The peaks of each channel at each level.  The blocks at level `n` cover
+    /// `BASE_BLOCK_SIZE * LEVEL_FACTOR^n` frames each; the last block of a level may be partial.
+    levels: Vec<Vec<Vec<Peak>>>,
+}
+
+impl WaveformPeaks {
+    pub fn new(channels: &[Vec<f32>]) -> Self {
+        let levels = channels
+            .iter()
+            .map(|channel| {
+                let mut levels: Vec<Vec<Peak>> = vec![channel
+                    .chunks(BASE_BLOCK_SIZE)
+                    .map(Peak::of_samples)
+                    .collect()];
+                while levels.last().unwrap().len() > 1 {
+                    let next_level = levels
+                        .last()
+                        .unwrap()
+                        .chunks(LEVEL_FACTOR)
+                        .map(Peak::merge_all)
+                        .collect();
+                    levels.push(next_level);
+                }
+                levels
+            })
+            .collect();
+        WaveformPeaks { levels }
+    }
+
+    fn block_size(level: usize) -> usize {
+        BASE_BLOCK_SIZE * LEVEL_FACTOR.pow(level as u32)
+    }
+
+    /// Returns `pixel_count` peaks evenly spanning the frames of `channel` from `start_frame` to
+    /// `end_frame`.  `channel` must be the same channel that these peaks were computed from.  When
+    /// zoomed in past a frame per pixel, pixels between two frames take the value of the earlier
+    /// one.
+    pub fn peaks(
+        &self,
+        channel_ix: usize,
+        channel: &[f32],
+        start_frame: usize,
+        end_frame: usize,
+        pixel_count: usize,
+    ) -> Vec<Peak> {
+        let end_frame = end_frame.min(channel.len());
+        if pixel_count == 0 || start_frame >= end_frame {
+            return vec![Peak { min: 0., max: 0. }; pixel_count];
+        }
+
+        let frames_per_pixel = (end_frame - start_frame) as f64 / pixel_count as f64;
+        let level = (0..self.levels[channel_ix].len())
+            .take_while(|&level| Self::block_size(level) as f64 <= frames_per_pixel)
+            .last();
+
+        (0..pixel_count)
+            .map(|pixel_ix| {
+                let pixel_start = start_frame as f64 + pixel_ix as f64 * frames_per_pixel;
+                let first_frame = pixel_start.floor() as usize;
+                let last_frame = ((pixel_start + frames_per_pixel).ceil() as usize)
+                    .max(first_frame + 1)
+                    .min(end_frame);
+
+                match level {
+                    Some(level) => {
+                        let block_size = Self::block_size(level);
+                        let first_block = first_frame / block_size;
+                        let last_block = last_frame.div_ceil(block_size);
+                        Peak::merge_all(&self.levels[channel_ix][level][first_block..last_block])
+                    },
+                    None => Peak::of_samples(&channel[first_frame..last_frame]),
+                }
+            })
+            .collect()
+    }
+}
@@ -24,7 +24,9 @@ impl ViewContext for SampleLibrary {
 
     fn unhide(&mut self) { js::unhide_sample_library(&self.get_state_key()); }
 
-    fn dispose(&mut self) { js::delete_localstorage_key(&self.get_state_key()); }
+    fn dispose(&mut self) { LocalStorage.delete(&self.get_state_key()); }
+
+    fn save_external_data(&mut self) -> Vec<String> { vec![self.get_state_key()] }
 
     fn get_audio_connectables(&self) -> JsValue {
         crate::view_context::create_empty_audio_connectables(self.uuid.to_string().as_str())
@@ -32,5 +34,5 @@ impl ViewContext for SampleLibrary {
 }
 
 pub fn mk_sample_library(_definition_opt: Option<&str>, uuid: Uuid) -> Box<dyn ViewContext> {
-    box SampleLibrary { uuid }
+    Box::new(SampleLibrary { uuid })
 }
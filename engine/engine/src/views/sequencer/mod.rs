@@ -24,7 +24,9 @@ impl ViewContext for Sequencer {
 
     fn unhide(&mut self) { js::unhide_sequencer(&self.get_state_key()); }
 
-    fn dispose(&mut self) { js::delete_localstorage_key(&self.get_state_key()); }
+    fn dispose(&mut self) { LocalStorage.delete(&self.get_state_key()); }
+
+    fn save_external_data(&mut self) -> Vec<String> { vec![self.get_state_key()] }
 
     fn get_audio_connectables(&self) -> JsValue {
         js::get_sequencer_audio_connectables(&self.get_id())
@@ -32,5 +34,5 @@ impl ViewContext for Sequencer {
 }
 
 pub fn mk_sequencer(_definition_opt: Option<&str>, uuid: Uuid) -> Box<dyn ViewContext> {
-    box Sequencer { uuid }
+    Box::new(Sequencer { uuid })
 }
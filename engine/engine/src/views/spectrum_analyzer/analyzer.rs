@@ -0,0 +1,192 @@
+//! Converts the audio sent over from the tap point into the bins that are rendered by the UI.
+//! Samples are kept in a ring buffer of the most recent `fft_size` frames.  Each time that a block
+//! arrives the buffer is windowed and transformed, the magnitudes are smoothed against those of the
+//! previous frame, and then they're grouped into logarithmically spaced bins.
+
+use crate::{
+    dsp::fft::{fft_in_place, hann_window},
+    helpers::audio_tap::{TapLocation, DEFAULT_SAMPLE_RATE},
+    util::clamp,
+};
+
+pub const MIN_FFT_SIZE: usize = 256;
+pub const MAX_FFT_SIZE: usize = 32768;
+pub const MIN_BIN_COUNT: usize = 16;
+pub const MAX_BIN_COUNT: usize = 1024;
+/// The level that bins bottom out at; silence and anything quieter is reported as this
+pub const MIN_DB: f32 = -120.;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnalyzerConf {
+    /// Number of frames in each FFT; must be a power of 2
+    pub fft_size: usize,
+    /// How much of the previous frame's magnitudes are kept when a new frame is analyzed, from 0
+    /// (no smoothing) to just below 1
+    pub smoothing: f32,
+    /// Number of log-spaced bins between `min_freq` and `max_freq` that are exposed for rendering
+    pub bin_count: usize,
+    pub min_freq: f32,
+    pub max_freq: f32,
+    pub tap: TapLocation,
+}
+
+impl Default for AnalyzerConf {
+    fn default() -> Self {
+        AnalyzerConf {
+            fft_size: 4096,
+            smoothing: 0.8,
+            bin_count: 256,
+            min_freq: 20.,
+            max_freq: 20_000.,
+            tap: TapLocation::Master,
+        }
+    }
+}
+
+impl AnalyzerConf {
+    /// Clamps all values into their valid ranges, rounding the FFT size up to a power of 2
+    pub fn sanitize(&mut self) {
+        self.fft_size = self
+            .fft_size
+            .clamp(MIN_FFT_SIZE, MAX_FFT_SIZE)
+            .next_power_of_two();
+        self.smoothing = clamp(self.smoothing, 0., 0.99);
+        self.bin_count = self.bin_count.clamp(MIN_BIN_COUNT, MAX_BIN_COUNT);
+        self.min_freq = self.min_freq.max(1.);
+        if self.max_freq.is_nan() || self.max_freq <= self.min_freq {
+            self.max_freq = self.min_freq * 2.;
+        }
+    }
+}
+
+pub struct Analyzer {
+    conf: AnalyzerConf,
+    sample_rate: f32,
+    window: Vec<f32>,
+    /// The most recent `fft_size` samples.  `history_ix` is the index of the oldest one.
+    history: Vec<f32>,
+    history_ix: usize,
+    re: Vec<f32>,
+    im: Vec<f32>,
+    /// Smoothed linear magnitudes of each FFT bin from DC up to Nyquist
+    magnitudes: Vec<f32>,
+    /// The smoothed magnitudes grouped into log-spaced bins, in dB
+    bins: Vec<f32>,
+}
+
+impl Default for Analyzer {
+    fn default() -> Self { Analyzer::new(AnalyzerConf::default(), DEFAULT_SAMPLE_RATE) }
+}
+
+impl Analyzer {
+    pub fn new(mut conf: AnalyzerConf, sample_rate: f32) -> Self {
+        conf.sanitize();
+        let fft_size = conf.fft_size;
+        Analyzer {
+            sample_rate,
+            window: hann_window(fft_size),
+            history: vec![0.; fft_size],
+            history_ix: 0,
+            re: vec![0.; fft_size],
+            im: vec![0.; fft_size],
+            magnitudes: vec![0.; fft_size / 2 + 1],
+            bins: vec![MIN_DB; conf.bin_count],
+            conf,
+        }
+    }
+
+    pub fn conf(&self) -> &AnalyzerConf { &self.conf }
+
+    pub fn sample_rate(&self) -> f32 { self.sample_rate }
+
+    /// The current bins in dB, ordered from lowest to highest frequency
+    pub fn bins(&self) -> &[f32] { &self.bins }
+
+    /// Returns the center frequency of each bin in Hz
+    pub fn bin_frequencies(&self) -> Vec<f32> {
+        (0..self.conf.bin_count)
+            .map(|bin_ix| self.bin_edge_freq(bin_ix as f32 + 0.5))
+            .collect()
+    }
+
+    /// Returns the frequency at `pos` bins from the bottom of the analyzed range
+    fn bin_edge_freq(&self, pos: f32) -> f32 {
+        let AnalyzerConf {
+            min_freq,
+            max_freq,
+            bin_count,
+            ..
+        } = self.conf;
+        min_freq * (max_freq / min_freq).powf(pos / bin_count as f32)
+    }
+
+    /// Appends the provided samples to the history and analyzes the most recent frame
+    pub fn push_samples(&mut self, samples: &[f32]) {
+        if samples.is_empty() {
+            return;
+        }
+
+        let fft_size = self.conf.fft_size;
+        // Only the last `fft_size` samples can make it into the frame
+        let samples = &samples[samples.len().saturating_sub(fft_size)..];
+        for &sample in samples {
+            self.history[self.history_ix] = sample;
+            self.history_ix = (self.history_ix + 1) % fft_size;
+        }
+        self.analyze();
+    }
+
+    fn analyze(&mut self) {
+        let fft_size = self.conf.fft_size;
+        for i in 0..fft_size {
+            let sample = self.history[(self.history_ix + i) % fft_size];
+            self.re[i] = sample * self.window[i];
+            self.im[i] = 0.;
+        }
+        fft_in_place(&mut self.re, &mut self.im);
+
+        // Scaled so that a full-scale sine wave centered in a bin reads as 0 dB
+        let scale = 2. / self.window.iter().sum::<f32>();
+        let smoothing = self.conf.smoothing;
+        for (i, magnitude) in self.magnitudes.iter_mut().enumerate() {
+            let new_magnitude = self.re[i].hypot(self.im[i]) * scale;
+            *magnitude = *magnitude * smoothing + new_magnitude * (1. - smoothing);
+        }
+
+        self.compute_bins();
+    }
+
+    /// Groups the FFT magnitudes into log-spaced bins.  Each bin takes the loudest FFT bin that
+    /// falls within it.  At low frequencies the bins can be narrower than the FFT's resolution, in
+    /// which case the magnitude at the bin's center is interpolated instead.
+    fn compute_bins(&mut self) {
+        let hz_per_fft_bin = self.sample_rate / self.conf.fft_size as f32;
+        let max_fft_bin = self.magnitudes.len() - 1;
+        let to_fft_pos = |freq: f32| (freq / hz_per_fft_bin).min(max_fft_bin as f32);
+
+        for bin_ix in 0..self.conf.bin_count {
+            let start = to_fft_pos(self.bin_edge_freq(bin_ix as f32));
+            let end = to_fft_pos(self.bin_edge_freq(bin_ix as f32 + 1.));
+            let (first, last) = (start.ceil() as usize, end.floor() as usize);
+
+            let magnitude = if first <= last {
+                self.magnitudes[first..=last]
+                    .iter()
+                    .fold(0.0f32, |acc, &magnitude| acc.max(magnitude))
+            } else {
+                let center = (start + end) / 2.;
+                let lower_ix = (center.floor() as usize).min(max_fft_bin);
+                let upper_ix = (lower_ix + 1).min(max_fft_bin);
+                let mix = center - lower_ix as f32;
+                self.magnitudes[lower_ix] * (1. - mix) + self.magnitudes[upper_ix] * mix
+            };
+
+            self.bins[bin_ix] = if magnitude > 0. {
+                (20. * magnitude.log10()).max(MIN_DB)
+            } else {
+                MIN_DB
+            };
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! Defines a view that displays the frequency spectrum of audio tapped from the master output or
+//! from anywhere else in the patch network.
+
+use serde_json;
+use uuid::Uuid;
+
+use crate::{
+    helpers::grid::prelude::*,
+    util::{f32s_from_bytes, f32s_to_bytes},
+    view_context::ViewContext,
+};
+
+pub mod analyzer;
+
+use self::analyzer::{Analyzer, AnalyzerConf};
+
+/// The JS side taps the audio and sends it over in blocks, which are analyzed here.  The UI polls
+/// for the resulting bins each frame while the view is visible and renders them.
+#[derive(Serialize, Deserialize)]
+pub struct SpectrumAnalyzer {
+    pub uuid: Uuid,
+    #[serde(default)]
+    pub conf: AnalyzerConf,
+    #[serde(skip)]
+    analyzer: Analyzer,
+}
+
+impl SpectrumAnalyzer {
+    pub fn new(uuid: Uuid) -> Self {
+        SpectrumAnalyzer {
+            uuid,
+            conf: AnalyzerConf::default(),
+            analyzer: Analyzer::default(),
+        }
+    }
+
+    pub fn get_state_key(&self) -> String { format!("spectrumAnalyzer_{}", self.uuid) }
+
+    fn serialize_conf(&self) -> String {
+        serde_json::to_string(&self.conf).expect("Error serializing `AnalyzerConf`")
+    }
+
+    /// Rebuilds the analyzer from the current config, clearing any buffered audio
+    fn rebuild_analyzer(&mut self, sample_rate: f32) {
+        self.analyzer = Analyzer::new(self.conf.clone(), sample_rate);
+        self.conf = self.analyzer.conf().clone();
+    }
+}
+
+impl ViewContext for SpectrumAnalyzer {
+    fn init(&mut self) {
+        let sample_rate = js::init_spectrum_analyzer(&self.get_state_key(), &self.serialize_conf());
+        self.rebuild_analyzer(sample_rate);
+    }
+
+    fn cleanup(&mut self) { js::cleanup_spectrum_analyzer(&self.get_state_key()); }
+
+    fn get_id(&self) -> String { self.uuid.to_string() }
+
+    fn hide(&mut self) { js::hide_spectrum_analyzer(&self.get_state_key()); }
+
+    fn unhide(&mut self) { js::unhide_spectrum_analyzer(&self.get_state_key()); }
+
+    fn get_audio_connectables(&self) -> JsValue {
+        js::get_spectrum_analyzer_audio_connectables(&self.get_state_key())
+    }
+
+    fn handle_message(&mut self, key: &str, val: &[u8]) -> Option<Vec<u8>> {
+        match key {
+            "push_samples" => {
+                self.analyzer.push_samples(&f32s_from_bytes(val));
+                None
+            },
+            "get_bins" => Some(f32s_to_bytes(self.analyzer.bins())),
+            "get_conf" => Some(self.serialize_conf().into_bytes()),
+            "set_conf" => {
+                match serde_json::from_slice(val) {
+                    Ok(conf) => {
+                        self.conf = conf;
+                        self.rebuild_analyzer(self.analyzer.sample_rate());
+                    },
+                    Err(err) => error!("Error decoding spectrum analyzer config: {:?}", err),
+                }
+                Some(self.serialize_conf().into_bytes())
+            },
+            _ => None,
+        }
+    }
+
+    fn save(&mut self) -> String {
+        serde_json::to_string(self).expect("Error serializing `SpectrumAnalyzer` to String")
+    }
+}
+
+pub fn mk_spectrum_analyzer(definition_opt: Option<&str>, uuid: Uuid) -> Box<dyn ViewContext> {
+    let spectrum_analyzer: SpectrumAnalyzer = match definition_opt {
+        Some(definition) =>
+            serde_json::from_str(definition).expect("Error while deserializing `SpectrumAnalyzer`"),
+        None => SpectrumAnalyzer::new(uuid),
+    };
+    Box::new(spectrum_analyzer)
+}
@@ -1,9 +1,32 @@
 //! Defines a view that allows creating and customizing a synthesizer
 
+use common::tuning::TuningConf;
+use polysynth::unison::UnisonConf;
 use serde_json;
 use uuid::Uuid;
 
-use crate::{helpers::grid::prelude::*, view_context::ViewContext};
+use crate::{
+    helpers::grid::prelude::*,
+    view_context::{
+        message::{MessageKind, MessageRegistry},
+        Message, ViewContext,
+    },
+};
+
+pub mod mod_matrix;
+pub mod presets;
+
+use self::{
+    mod_matrix::{ModMatrix, ModRouteDefinition},
+    presets::{SynthModulePatch, SynthPatch, SynthPreset, SynthPresetBank},
+};
+
+static SYNTH_DESIGNER_MESSAGE_HANDLERS: MessageRegistry<SynthDesigner> = MessageRegistry {
+    handlers: &[(
+        MessageKind::SetModRouteDepth,
+        SynthDesigner::handle_mod_route_depth_message,
+    )],
+};
 
 /// This is just a shim to the JS-based synth designer.  Since there really aren't any complicated
 /// interactive or graphical components of this view context, the actual implementation for this
@@ -11,21 +34,158 @@ use crate::{helpers::grid::prelude::*, view_context::ViewContext};
 #[derive(Serialize, Deserialize)]
 pub struct SynthDesigner {
     pub uuid: Uuid,
+    #[serde(default)]
+    pub mod_matrix: ModMatrix,
+    /// Presets saved by the user
+    #[serde(default)]
+    pub presets: SynthPresetBank,
+    /// The tuning that notes are played in.  Notes are played in 12-TET if this is `None`.
+    #[serde(default)]
+    pub tuning: Option<TuningConf>,
 }
 
 impl SynthDesigner {
-    pub fn new(uuid: Uuid) -> Self { SynthDesigner { uuid } }
+    pub fn new(uuid: Uuid) -> Self {
+        SynthDesigner {
+            uuid,
+            mod_matrix: ModMatrix::default(),
+            presets: SynthPresetBank::default(),
+            tuning: None,
+        }
+    }
 
     pub fn get_state_key(&self) -> String { format!("synthDesigner_{}", self.uuid) }
+
+    /// Sends the current set of modulation routes to JS so that it can rebuild the audio graph that
+    /// implements them, returning the serialized routes.
+    fn sync_mod_routes(&self) -> String {
+        let routes = self.mod_matrix.serialize_routes();
+        js::set_synth_designer_mod_routes(&self.get_state_key(), &routes);
+        routes
+    }
+
+    /// Sends the frequency of every MIDI note in the current tuning to JS.  An empty table is sent
+    /// for 12-TET.
+    fn sync_tuning(&self) {
+        let table = match self.tuning.as_ref().map(TuningConf::parse) {
+            Some(Ok(tuning)) => tuning.build_table(),
+            Some(Err(err)) => {
+                error!("Error parsing saved synth designer tuning: {:?}", err);
+                Vec::new()
+            },
+            None => Vec::new(),
+        };
+        js::set_synth_designer_tuning(&self.get_state_key(), &table);
+    }
+
+    /// Handles the `"set_tuning"` message, which takes a JSON-encoded `TuningConf` or `null` to
+    /// switch back to 12-TET.  Returns an error message if the tuning couldn't be parsed.
+    fn set_tuning(&mut self, val: &[u8]) -> Option<Vec<u8>> {
+        let tuning: Option<TuningConf> = match serde_json::from_slice(val) {
+            Ok(tuning) => tuning,
+            Err(err) => {
+                error!("Error decoding tuning: {:?}", err);
+                return None;
+            },
+        };
+        if let Some(Err(err)) = tuning.as_ref().map(TuningConf::parse) {
+            return Some(err.to_string().into_bytes());
+        }
+
+        self.tuning = tuning;
+        self.sync_tuning();
+        None
+    }
+
+    /// Snapshots the current settings of all synth modules and mod routes
+    fn get_patch(&self) -> Option<SynthPatch> {
+        let modules_json = js::get_synth_designer_modules(&self.get_state_key());
+        let modules: Vec<SynthModulePatch> = match serde_json::from_str(&modules_json) {
+            Ok(modules) => modules,
+            Err(err) => {
+                error!("Error decoding synth designer modules: {:?}", err);
+                return None;
+            },
+        };
+        let mod_routes = self
+            .mod_matrix
+            .routes()
+            .iter()
+            .map(|route| route.definition.clone())
+            .collect();
+
+        Some(SynthPatch {
+            modules,
+            mod_routes,
+        })
+    }
+
+    fn load_patch(&mut self, patch: SynthPatch) {
+        let modules_json =
+            serde_json::to_string(&patch.modules).expect("Error serializing synth modules");
+        js::set_synth_designer_modules(&self.get_state_key(), &modules_json);
+
+        self.mod_matrix = ModMatrix::default();
+        for definition in patch.mod_routes {
+            self.mod_matrix.add_route(definition);
+        }
+        self.sync_mod_routes();
+    }
+
+    /// Handles the messages for managing presets, which all take the name of a preset as UTF-8
+    /// and return the updated list of presets.
+    fn handle_preset_message(&mut self, key: &str, val: &[u8]) -> Option<Vec<u8>> {
+        let name = String::from_utf8_lossy(val);
+        match key {
+            "list_presets" => (),
+            "save_preset" =>
+                if let Some(patch) = self.get_patch() {
+                    let preset = SynthPreset {
+                        name: name.into_owned(),
+                        patch,
+                    };
+                    if let Err(err) = self.presets.save(preset) {
+                        warn!("Failed to save synth preset: {:?}", err);
+                    }
+                },
+            "load_preset" => match self.presets.get(&name) {
+                Some(preset) => self.load_patch(preset.patch),
+                None => warn!("Tried to load synth preset \"{}\" but none exists", name),
+            },
+            "delete_preset" =>
+                if !self.presets.delete(&name) {
+                    warn!("Tried to delete synth preset \"{}\" but none exists", name);
+                },
+            _ => return None,
+        }
+
+        let presets_json =
+            serde_json::to_string(&self.presets.list()).expect("Error serializing synth presets");
+        Some(presets_json.into_bytes())
+    }
+
+    fn handle_mod_route_depth_message(&mut self, message: Message) -> Option<Vec<u8>> {
+        if let Message::SetModRouteDepth { route_id, depth } = message {
+            if !self.mod_matrix.set_route_depth(route_id, depth) {
+                warn!("Tried to set depth of mod route with id {} but none exists", route_id);
+            }
+            self.sync_mod_routes();
+        }
+        None
+    }
 }
 
 impl ViewContext for SynthDesigner {
-    fn init(&mut self) { js::init_synth_designer(&self.get_state_key()); }
+    fn init(&mut self) {
+        js::init_synth_designer(&self.get_state_key());
+        self.sync_mod_routes();
+        self.sync_tuning();
+    }
 
     fn cleanup(&mut self) {
         let state_key = self.get_state_key();
         let serialized_state = js::cleanup_synth_designer(&state_key);
-        js::set_localstorage_key(&state_key, &serialized_state)
+        LocalStorage.set(&state_key, &serialized_state)
     }
 
     fn get_audio_connectables(&self) -> JsValue {
@@ -38,7 +198,50 @@ impl ViewContext for SynthDesigner {
 
     fn unhide(&mut self) { js::unhide_synth_designer(&self.get_state_key()); }
 
-    fn dispose(&mut self) { js::delete_localstorage_key(&self.get_state_key()); }
+    fn dispose(&mut self) { LocalStorage.delete(&self.get_state_key()); }
+
+    fn save_external_data(&mut self) -> Vec<String> { vec![self.get_state_key()] }
+
+    fn handle_message(&mut self, key: &str, val: &[u8]) -> Option<Vec<u8>> {
+        if let Some(res) = self.handle_preset_message(key, val) {
+            return Some(res);
+        }
+
+        match key {
+            "list_mod_routes" => return Some(self.mod_matrix.serialize_routes().into_bytes()),
+            "get_tuning" =>
+                return Some(serde_json::to_vec(&self.tuning).expect("Error serializing tuning")),
+            "set_tuning" => return self.set_tuning(val),
+            "add_mod_route" => {
+                let definition: ModRouteDefinition = match serde_json::from_slice(val) {
+                    Ok(definition) => definition,
+                    Err(err) => {
+                        error!("Error decoding mod route definition: {:?}", err);
+                        return None;
+                    },
+                };
+                self.mod_matrix.add_route(definition);
+            },
+            "remove_mod_route" => {
+                assert_eq!(
+                    val.len(),
+                    4,
+                    "Message for \"remove_mod_route\" must be a 4-byte `u32` of the route ID"
+                );
+                let id = u32::from_ne_bytes([val[0], val[1], val[2], val[3]]);
+                if self.mod_matrix.remove_route(id).is_none() {
+                    warn!("Tried to remove mod route with id {} but none exists", id);
+                }
+            },
+            _ => return None,
+        }
+
+        Some(self.sync_mod_routes().into_bytes())
+    }
+
+    fn handle_binary_message(&mut self, message: Message) -> Option<Vec<u8>> {
+        SYNTH_DESIGNER_MESSAGE_HANDLERS.dispatch(self, message)
+    }
 
     fn save(&mut self) -> String {
         serde_json::to_string(self).expect("Error serializing `SynthDesigner` to String")
@@ -51,5 +254,26 @@ pub fn mk_synth_designer(definition_opt: Option<&str>, uuid: Uuid) -> Box<dyn Vi
             serde_json::from_str(definition).expect("Error while deserializing `SynthDesigner`"),
         None => SynthDesigner::new(uuid),
     };
-    box synth_designer
+    Box::new(synth_designer)
+}
+
+/// Returns the detune in cents and pan of each oscillator of a synth designer voice played with
+/// `unison` oscillators, interleaved.  Fewer than `unison` oscillators are returned if playing that
+/// many for each of `polyphony` voices would exceed the CPU budget for unison.
+#[wasm_bindgen]
+pub fn get_unison_layout(
+    unison: usize,
+    detune_cents: f32,
+    stereo_spread: f32,
+    polyphony: usize,
+) -> Vec<f32> {
+    let conf = UnisonConf {
+        voices: unison,
+        detune_cents,
+        stereo_spread,
+    };
+    conf.layout(polyphony)
+        .into_iter()
+        .flat_map(|voice| vec![voice.detune_cents, voice.pan])
+        .collect()
 }
@@ -0,0 +1,115 @@
+//! A modulation matrix that routes modulation sources to the parameters of the synth designer.
+//! The routes are defined and persisted here, and the JS side of the synth designer is in charge
+//! of actually building the audio graph that implements them.
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LfoWaveform {
+    Sine,
+    Triangle,
+    Square,
+    Sawtooth,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoiseColor {
+    White,
+    /// Noise with equal power per octave, which sounds more natural than white noise
+    Pink,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ModSource {
+    Lfo {
+        waveform: LfoWaveform,
+        frequency: f32,
+    },
+    /// A monophonic envelope that is re-triggered every time a note is played and released once
+    /// all held notes are released.  Outputs values in the range [0, 1].
+    Envelope {
+        attack_ms: f32,
+        decay_ms: f32,
+        sustain: f32,
+        release_ms: f32,
+    },
+    /// Audio-rate noise in the range [-1, 1]
+    Noise {
+        color: NoiseColor,
+    },
+    /// A random value in the range [-1, 1] that's held until a new one is picked, which happens
+    /// `frequency` times per second
+    SampleAndHold {
+        frequency: f32,
+    },
+    /// The velocity of the most recently played note, scaled to the range [0, 1]
+    Velocity,
+    /// The MPE pressure of the most recently expressed note, in the range [0, 1]
+    Pressure,
+    /// The MPE timbre (CC 74 on the note's channel) of the most recently expressed note, in the
+    /// range [0, 1]
+    Timbre,
+    /// Shorthand for `MidiCc { cc: 1 }`
+    ModWheel,
+    MidiCc {
+        cc: u8,
+    },
+}
+
+/// The parts of a route provided by the UI when creating it
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ModRouteDefinition {
+    pub source: ModSource,
+    /// The name of a `number` input in the synth designer's audio connectables, such as
+    /// `synth_0_filter_frequency`
+    pub destination: String,
+    /// The output of the source, which has a range of [-1, 1] or [0, 1], is multiplied by this
+    /// before being added to the destination.
+    pub depth: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ModRoute {
+    pub id: u32,
+    #[serde(flatten)]
+    pub definition: ModRouteDefinition,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ModMatrix {
+    routes: Vec<ModRoute>,
+    next_route_id: u32,
+}
+
+impl ModMatrix {
+    pub fn routes(&self) -> &[ModRoute] { &self.routes }
+
+    /// Adds a new route, returning its ID
+    pub fn add_route(&mut self, definition: ModRouteDefinition) -> u32 {
+        let id = self.next_route_id;
+        self.next_route_id += 1;
+        self.routes.push(ModRoute { id, definition });
+        id
+    }
+
+    pub fn remove_route(&mut self, id: u32) -> Option<ModRoute> {
+        let ix = self.routes.iter().position(|route| route.id == id)?;
+        Some(self.routes.remove(ix))
+    }
+
+    /// Returns `false` if no route with the provided ID exists
+    pub fn set_route_depth(&mut self, id: u32, depth: f32) -> bool {
+        match self.routes.iter_mut().find(|route| route.id == id) {
+            Some(route) => {
+                route.definition.depth = depth;
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn serialize_routes(&self) -> String {
+        serde_json::to_string(&self.routes).expect("Error serializing mod matrix routes")
+    }
+}
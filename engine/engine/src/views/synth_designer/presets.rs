@@ -0,0 +1,332 @@
+//! Presets are named snapshots of all of the parameters of a synth designer: the oscillator,
+//! envelope, and filter settings of each of its synth modules along with its mod matrix routes.
+//! The synth module settings are owned by the JS side of the synth designer, so the types here
+//! mirror the format produced by `serializeSynthModule`.
+//!
+//! A bank of factory presets is built into the engine.  Presets saved by the user are stored in the
+//! definition of the synth designer that they were saved from, which means that they're included in
+//! exported projects as well.
+
+use super::mod_matrix::{LfoWaveform, ModRouteDefinition, ModSource};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Waveform {
+    Sine,
+    Square,
+    Sawtooth,
+    Triangle,
+    Custom,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterType {
+    Lowpass,
+    Highpass,
+    Bandpass,
+    Lowshelf,
+    Highshelf,
+    Peaking,
+    Notch,
+    Allpass,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EffectType {
+    Bitcrusher,
+    Distortion,
+    Reverb,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EnvelopePoint {
+    /// How far through the envelope the point is, from 0 to 1
+    pub pos: f64,
+    pub magnitude: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Envelope {
+    pub attack: EnvelopePoint,
+    pub decay: EnvelopePoint,
+    pub release: EnvelopePoint,
+}
+
+impl Envelope {
+    fn new(attack: (f64, f64), decay: (f64, f64), release: (f64, f64)) -> Self {
+        let point = |(pos, magnitude)| EnvelopePoint { pos, magnitude };
+        Envelope {
+            attack: point(attack),
+            decay: point(decay),
+            release: point(release),
+        }
+    }
+}
+
+impl Default for Envelope {
+    fn default() -> Self { Envelope::new((0.04, 0.8), (0.14, 0.35), (0.9, 0.35)) }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FilterParams {
+    #[serde(rename = "type")]
+    pub filter_type: FilterType,
+    pub frequency: f64,
+    /// Only used by some filter types
+    #[serde(rename = "Q", default, skip_serializing_if = "Option::is_none")]
+    pub q: Option<f64>,
+    /// Only used by some filter types
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gain: Option<f64>,
+    pub detune: f64,
+}
+
+impl FilterParams {
+    fn lowpass(frequency: f64, q: f64) -> Self {
+        FilterParams {
+            filter_type: FilterType::Lowpass,
+            frequency,
+            q: Some(q),
+            gain: None,
+            detune: 0.,
+        }
+    }
+}
+
+/// The settings of a single synth module of a synth designer
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SynthModulePatch {
+    pub waveform: Waveform,
+    /// The number of oscillators per voice
+    pub unison: usize,
+    /// The distance in cents between the two most detuned oscillators of each voice
+    #[serde(default)]
+    pub unison_detune: f64,
+    /// How far the oscillators of each voice are spread across the stereo field, from 0 to 1
+    #[serde(default)]
+    pub unison_spread: f64,
+    /// If set, each oscillator of a voice starts at a random phase every time a note is played
+    #[serde(default)]
+    pub unison_phase_randomization: bool,
+    pub detune: f64,
+    pub filter: FilterParams,
+    pub master_gain: f64,
+    pub selected_effect_type: EffectType,
+    pub gain_envelope: Envelope,
+    #[serde(rename = "gainADSRLength")]
+    pub gain_adsr_length_ms: f64,
+    pub filter_envelope: Envelope,
+    #[serde(rename = "filterADSRLength")]
+    pub filter_adsr_length_ms: f64,
+}
+
+impl Default for SynthModulePatch {
+    fn default() -> Self {
+        SynthModulePatch {
+            waveform: Waveform::Sine,
+            unison: 1,
+            unison_detune: 0.,
+            unison_spread: 0.,
+            unison_phase_randomization: false,
+            detune: 0.,
+            filter: FilterParams::lowpass(4400., 0.001),
+            master_gain: 0.,
+            selected_effect_type: EffectType::Reverb,
+            gain_envelope: Envelope::default(),
+            gain_adsr_length_ms: 1000.,
+            filter_envelope: Envelope::default(),
+            filter_adsr_length_ms: 1200.,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SynthPatch {
+    pub modules: Vec<SynthModulePatch>,
+    pub mod_routes: Vec<ModRouteDefinition>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SynthPreset {
+    pub name: String,
+    pub patch: SynthPatch,
+}
+
+impl SynthPreset {
+    fn new(
+        name: &str,
+        modules: Vec<SynthModulePatch>,
+        mod_routes: Vec<ModRouteDefinition>,
+    ) -> Self {
+        SynthPreset {
+            name: name.into(),
+            patch: SynthPatch {
+                modules,
+                mod_routes,
+            },
+        }
+    }
+}
+
+/// The presets that are built into the engine
+pub fn factory_presets() -> Vec<SynthPreset> {
+    vec![
+        SynthPreset::new("Init", vec![SynthModulePatch::default()], Vec::new()),
+        SynthPreset::new(
+            "Warm Pad",
+            vec![SynthModulePatch {
+                waveform: Waveform::Sawtooth,
+                unison: 4,
+                unison_detune: 24.,
+                unison_spread: 0.8,
+                unison_phase_randomization: true,
+                detune: 12.,
+                filter: FilterParams::lowpass(1800., 0.7),
+                gain_envelope: Envelope::new((0.35, 0.8), (0.5, 0.7), (0.95, 0.7)),
+                gain_adsr_length_ms: 3200.,
+                filter_envelope: Envelope::new((0.5, 0.6), (0.7, 0.4), (0.95, 0.4)),
+                filter_adsr_length_ms: 4000.,
+                ..SynthModulePatch::default()
+            }],
+            vec![ModRouteDefinition {
+                source: ModSource::Lfo {
+                    waveform: LfoWaveform::Sine,
+                    frequency: 0.2,
+                },
+                destination: "synth_0_filter_frequency".into(),
+                depth: 400.,
+            }],
+        ),
+        SynthPreset::new(
+            "Pluck",
+            vec![SynthModulePatch {
+                waveform: Waveform::Triangle,
+                filter: FilterParams::lowpass(3200., 4.),
+                gain_envelope: Envelope::new((0.01, 1.), (0.2, 0.1), (0.9, 0.1)),
+                gain_adsr_length_ms: 600.,
+                filter_envelope: Envelope::new((0.01, 1.), (0.15, 0.2), (0.9, 0.2)),
+                filter_adsr_length_ms: 500.,
+                ..SynthModulePatch::default()
+            }],
+            vec![ModRouteDefinition {
+                source: ModSource::Velocity,
+                destination: "synth_0_filter_frequency".into(),
+                depth: 2400.,
+            }],
+        ),
+        SynthPreset::new(
+            "Sub Bass",
+            vec![
+                SynthModulePatch {
+                    waveform: Waveform::Sine,
+                    filter: FilterParams::lowpass(400., 0.5),
+                    gain_envelope: Envelope::new((0.02, 1.), (0.3, 0.85), (0.9, 0.85)),
+                    gain_adsr_length_ms: 800.,
+                    ..SynthModulePatch::default()
+                },
+                SynthModulePatch {
+                    waveform: Waveform::Square,
+                    detune: -1200.,
+                    filter: FilterParams::lowpass(600., 2.),
+                    master_gain: -0.6,
+                    gain_envelope: Envelope::new((0.02, 1.), (0.3, 0.85), (0.9, 0.85)),
+                    gain_adsr_length_ms: 800.,
+                    ..SynthModulePatch::default()
+                },
+            ],
+            Vec::new(),
+        ),
+        SynthPreset::new(
+            "Wobble Bass",
+            vec![SynthModulePatch {
+                waveform: Waveform::Sawtooth,
+                unison: 2,
+                unison_detune: 10.,
+                detune: 7.,
+                filter: FilterParams::lowpass(900., 12.),
+                gain_envelope: Envelope::new((0.01, 1.), (0.1, 0.9), (0.95, 0.9)),
+                gain_adsr_length_ms: 1000.,
+                ..SynthModulePatch::default()
+            }],
+            vec![ModRouteDefinition {
+                source: ModSource::Lfo {
+                    waveform: LfoWaveform::Triangle,
+                    frequency: 4.,
+                },
+                destination: "synth_0_filter_frequency".into(),
+                depth: 800.,
+            }],
+        ),
+    ]
+}
+
+/// An entry in the list of presets shown to the user
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SynthPresetListing {
+    pub name: String,
+    pub is_factory: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SavePresetError {
+    EmptyName,
+    /// Factory presets can't be overwritten
+    FactoryPresetName,
+}
+
+/// The presets saved by the user, which are looked up alongside the factory presets
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SynthPresetBank {
+    presets: Vec<SynthPreset>,
+}
+
+impl SynthPresetBank {
+    /// Saves the preset, replacing any existing user preset with the same name
+    pub fn save(&mut self, preset: SynthPreset) -> Result<(), SavePresetError> {
+        let has_name = |other: &SynthPreset| other.name == preset.name;
+        if preset.name.trim().is_empty() {
+            return Err(SavePresetError::EmptyName);
+        } else if factory_presets().iter().any(has_name) {
+            return Err(SavePresetError::FactoryPresetName);
+        }
+
+        match self.presets.iter().position(has_name) {
+            Some(ix) => self.presets[ix] = preset,
+            None => self.presets.push(preset),
+        }
+        Ok(())
+    }
+
+    /// Returns `false` if there's no user preset with the provided name
+    pub fn delete(&mut self, name: &str) -> bool {
+        let len_before = self.presets.len();
+        self.presets.retain(|preset| preset.name != name);
+        self.presets.len() != len_before
+    }
+
+    pub fn get(&self, name: &str) -> Option<SynthPreset> {
+        factory_presets()
+            .into_iter()
+            .chain(self.presets.iter().cloned())
+            .find(|preset| preset.name == name)
+    }
+
+    /// Lists the factory presets followed by the user's presets
+    pub fn list(&self) -> Vec<SynthPresetListing> {
+        let factory = factory_presets()
+            .into_iter()
+            .map(|preset| (preset.name, true));
+        let user = self
+            .presets
+            .iter()
+            .map(|preset| (preset.name.clone(), false));
+        factory
+            .chain(user)
+            .map(|(name, is_factory)| SynthPresetListing { name, is_factory })
+            .collect()
+    }
+}
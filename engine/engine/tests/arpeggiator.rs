@@ -0,0 +1,69 @@
+extern crate engine;
+
+use engine::views::midi_editor::arpeggiator::{ArpeggiatorConf, ArpeggiatorPattern};
+
+fn conf(pattern: ArpeggiatorPattern, octaves: u8) -> ArpeggiatorConf {
+    ArpeggiatorConf {
+        pattern,
+        octaves,
+        ..ArpeggiatorConf::default()
+    }
+}
+
+#[test]
+fn sequences_follow_the_pattern() {
+    // Notes are sorted and deduplicated regardless of the order they're held in
+    let chord = [67, 60, 64, 60];
+    assert_eq!(
+        conf(ArpeggiatorPattern::Up, 1).build_sequence(&chord),
+        vec![60, 64, 67]
+    );
+    assert_eq!(
+        conf(ArpeggiatorPattern::Down, 1).build_sequence(&chord),
+        vec![67, 64, 60]
+    );
+    // The top and bottom notes aren't repeated when the direction changes
+    assert_eq!(
+        conf(ArpeggiatorPattern::UpDown, 1).build_sequence(&chord),
+        vec![60, 64, 67, 64]
+    );
+    assert_eq!(
+        conf(ArpeggiatorPattern::Up, 2).build_sequence(&chord),
+        vec![60, 64, 67, 72, 76, 79]
+    );
+    assert_eq!(
+        conf(ArpeggiatorPattern::UpDown, 1).build_sequence(&[60, 64]),
+        vec![60, 64]
+    );
+}
+
+#[test]
+fn arpeggios_fill_the_chord_and_are_cut_off_at_its_end() {
+    let conf = ArpeggiatorConf {
+        rate: 0.5,
+        gate: 0.5,
+        ..ArpeggiatorConf::default()
+    };
+    assert_eq!(
+        conf.arpeggiate(&[64, 60], 1., 2.75),
+        vec![
+            (60, 1., 1.25),
+            (64, 1.5, 1.75),
+            (60, 2., 2.25),
+            (64, 2.5, 2.75),
+        ]
+    );
+    assert!(conf.arpeggiate(&[], 1., 2.).is_empty());
+
+    // Random patterns only ever pick notes from the chord
+    let conf = ArpeggiatorConf {
+        pattern: ArpeggiatorPattern::Random,
+        octaves: 2,
+        ..conf
+    };
+    let steps = conf.arpeggiate(&[60, 63], 0., 8.);
+    assert_eq!(steps.len(), 16);
+    assert!(steps
+        .iter()
+        .all(|(note, ..)| [60, 63, 72, 75].contains(note)));
+}
@@ -0,0 +1,261 @@
+extern crate engine;
+
+use std::{cell::RefCell, rc::Rc};
+
+use audio_block::{Block, BlockProcessor};
+use engine::{audio_graph::*, offline_render::render_blocks};
+
+fn node(kind: NodeKind, name: &str, inputs: Vec<Port>, outputs: Vec<Port>) -> NodeDefinition {
+    NodeDefinition {
+        kind,
+        name: name.into(),
+        inputs,
+        outputs,
+    }
+}
+
+fn effect(name: &str) -> NodeDefinition {
+    node(
+        NodeKind::Effect,
+        name,
+        vec![
+            Port::new("input", PortType::Audio),
+            Port::new("wet", PortType::Cv),
+        ],
+        vec![Port::new("output", PortType::Audio)],
+    )
+}
+
+#[test]
+fn audio_graph_connect_validation() {
+    let mut graph = AudioGraph::default();
+    let synth = graph.add_node(node(
+        NodeKind::Instrument,
+        "synth",
+        vec![Port::new("midi", PortType::Midi)],
+        vec![Port::new("output", PortType::Audio)],
+    ));
+    let delay = graph.add_node(effect("delay"));
+    let reverb = graph.add_node(effect("reverb"));
+
+    assert_eq!(
+        graph.connect(PortRef::new(synth, "output"), PortRef::new(delay, "wet")),
+        Err(ConnectError::PortTypeMismatch {
+            from: PortType::Audio,
+            to: PortType::Cv
+        })
+    );
+    assert_eq!(
+        graph.connect(PortRef::new(synth, "output"), PortRef::new(delay, "nope")),
+        Err(ConnectError::UnknownPort(PortRef::new(delay, "nope")))
+    );
+    assert_eq!(
+        graph.connect(PortRef::new(synth, "output"), PortRef::new(99, "input")),
+        Err(ConnectError::UnknownNode(99))
+    );
+
+    graph
+        .connect(PortRef::new(synth, "output"), PortRef::new(delay, "input"))
+        .unwrap();
+    graph
+        .connect(PortRef::new(delay, "output"), PortRef::new(reverb, "input"))
+        .unwrap();
+    assert_eq!(
+        graph.connect(PortRef::new(synth, "output"), PortRef::new(delay, "input")),
+        Err(ConnectError::AlreadyConnected)
+    );
+    assert_eq!(
+        graph.connect(PortRef::new(reverb, "output"), PortRef::new(delay, "input")),
+        Err(ConnectError::WouldCreateCycle)
+    );
+    assert_eq!(
+        graph.connect(PortRef::new(delay, "output"), PortRef::new(delay, "input")),
+        Err(ConnectError::WouldCreateCycle)
+    );
+    assert_eq!(graph.connections().len(), 2);
+
+    assert!(graph.disconnect(&PortRef::new(delay, "output"), &PortRef::new(reverb, "input")));
+    assert!(!graph.disconnect(&PortRef::new(delay, "output"), &PortRef::new(reverb, "input")));
+    // With the cycle broken, the reverse connection is allowed
+    graph
+        .connect(PortRef::new(reverb, "output"), PortRef::new(delay, "input"))
+        .unwrap();
+
+    graph.remove_node(delay).unwrap();
+    assert!(graph.connections().is_empty());
+    assert!(graph.get_node(delay).is_none());
+}
+
+#[test]
+fn audio_graph_topological_order() {
+    let mut graph = AudioGraph::default();
+    let output = graph.add_node(node(
+        NodeKind::Output,
+        "output",
+        vec![Port::new("input", PortType::Audio)],
+        Vec::new(),
+    ));
+    let mixer = graph.add_node(node(
+        NodeKind::Mixer,
+        "mixer",
+        vec![
+            Port::new("input_0", PortType::Audio),
+            Port::new("input_1", PortType::Audio),
+        ],
+        vec![Port::new("output", PortType::Audio)],
+    ));
+    let reverb = graph.add_node(effect("reverb"));
+    let delay = graph.add_node(effect("delay"));
+
+    graph
+        .connect(PortRef::new(mixer, "output"), PortRef::new(output, "input"))
+        .unwrap();
+    graph
+        .connect(PortRef::new(delay, "output"), PortRef::new(reverb, "input"))
+        .unwrap();
+    graph
+        .connect(PortRef::new(reverb, "output"), PortRef::new(mixer, "input_0"))
+        .unwrap();
+    graph
+        .connect(PortRef::new(delay, "output"), PortRef::new(mixer, "input_1"))
+        .unwrap();
+
+    assert_eq!(graph.topological_order(), vec![delay, reverb, mixer, output]);
+}
+
+#[test]
+fn audio_graph_serialization() {
+    let mut graph = AudioGraph::default();
+    let delay = graph.add_node(effect("delay"));
+    let reverb = graph.add_node(effect("reverb"));
+    graph
+        .connect(PortRef::new(delay, "output"), PortRef::new(reverb, "input"))
+        .unwrap();
+
+    let deserialized = AudioGraph::deserialize(&graph.serialize()).unwrap();
+    assert_eq!(deserialized.nodes(), graph.nodes());
+    assert_eq!(deserialized.connections(), graph.connections());
+
+    // Cycles in serialized graphs are rejected
+    let cyclic = graph.serialize().replace(
+        "\"connections\":[",
+        "\"connections\":[{\"from\":{\"node\":1,\"port\":\"output\"},\"to\":{\"node\":0,\"port\":\
+         \"input\"}},",
+    );
+    assert!(AudioGraph::deserialize(&cyclic).is_err());
+}
+
+struct Constant(f32);
+
+impl BlockProcessor for Constant {
+    fn process(&mut self, block: &mut Block) { *block = Block::filled(self.0); }
+}
+
+struct Gain(f32);
+
+impl BlockProcessor for Gain {
+    fn process(&mut self, block: &mut Block) { block.apply_gain(self.0); }
+}
+
+#[test]
+fn audio_graph_execution() {
+    let mut graph = AudioGraph::default();
+    let instrument = |name: &str| {
+        node(
+            NodeKind::Instrument,
+            name,
+            vec![Port::new("midi", PortType::Midi)],
+            vec![Port::new("output", PortType::Audio)],
+        )
+    };
+    let synth = graph.add_node(instrument("synth"));
+    let sampler = graph.add_node(instrument("sampler"));
+    let gain = graph.add_node(effect("gain"));
+    let delay = graph.add_node(effect("delay"));
+    let output = graph.add_node(node(
+        NodeKind::Output,
+        "output",
+        vec![Port::new("input", PortType::Audio)],
+        Vec::new(),
+    ));
+
+    graph
+        .connect(PortRef::new(synth, "output"), PortRef::new(gain, "input"))
+        .unwrap();
+    graph
+        .connect(PortRef::new(gain, "output"), PortRef::new(delay, "input"))
+        .unwrap();
+    graph
+        .connect(PortRef::new(sampler, "output"), PortRef::new(delay, "input"))
+        .unwrap();
+    graph
+        .connect(PortRef::new(delay, "output"), PortRef::new(output, "input"))
+        .unwrap();
+
+    let mut executor = GraphExecutor::new(&graph);
+    assert!(executor.set_processor(synth, Box::new(Constant(0.25))));
+    assert!(executor.set_processor(sampler, Box::new(Constant(0.25))));
+    assert!(executor.set_processor(gain, Box::new(Gain(2.))));
+    assert!(!executor.set_processor(99, Box::new(Gain(2.))));
+
+    // The delay has no processor, so it passes the sum of its inputs straight through
+    let [left, right] = render_blocks(&mut executor, 300, |_, _| false);
+    assert_eq!(left.len(), 300);
+    assert!(left.iter().chain(right.iter()).all(|&sample| sample == 0.75));
+    assert_eq!(executor.node_output(gain).unwrap().left[0], 0.5);
+}
+
+/// Records the ID of its node each time that it's processed
+struct Recorder {
+    id: NodeId,
+    log: Rc<RefCell<Vec<NodeId>>>,
+}
+
+impl BlockProcessor for Recorder {
+    fn process(&mut self, _block: &mut Block) { self.log.borrow_mut().push(self.id); }
+}
+
+#[test]
+fn audio_graph_execution_order() {
+    let mut graph = AudioGraph::default();
+    // Nodes are added in the reverse of the order that they have to be processed in
+    let output = graph.add_node(node(
+        NodeKind::Output,
+        "output",
+        vec![Port::new("input", PortType::Audio)],
+        Vec::new(),
+    ));
+    let reverb = graph.add_node(effect("reverb"));
+    let delay = graph.add_node(effect("delay"));
+    let synth = graph.add_node(node(
+        NodeKind::Instrument,
+        "synth",
+        vec![Port::new("midi", PortType::Midi)],
+        vec![Port::new("output", PortType::Audio)],
+    ));
+
+    graph
+        .connect(PortRef::new(synth, "output"), PortRef::new(delay, "input"))
+        .unwrap();
+    graph
+        .connect(PortRef::new(delay, "output"), PortRef::new(reverb, "input"))
+        .unwrap();
+    graph
+        .connect(PortRef::new(reverb, "output"), PortRef::new(output, "input"))
+        .unwrap();
+    graph
+        .connect(PortRef::new(synth, "output"), PortRef::new(output, "input"))
+        .unwrap();
+
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let mut executor = GraphExecutor::new(&graph);
+    for &id in &[output, reverb, delay, synth] {
+        let log = Rc::clone(&log);
+        assert!(executor.set_processor(id, Box::new(Recorder { id, log })));
+    }
+
+    executor.process(&mut Block::default());
+    assert_eq!(*log.borrow(), vec![synth, delay, reverb, output]);
+    executor.process(&mut Block::default());
+    assert_eq!(*log.borrow(), vec![synth, delay, reverb, output, synth, delay, reverb, output]);
+}
@@ -0,0 +1,29 @@
+extern crate engine;
+
+use engine::{audio_recorder::AudioRecorder, offline_render::WavBitDepth};
+
+#[test]
+fn recording_stops_at_max_length() {
+    // 10 frames/second for 1 second
+    let mut recorder = AudioRecorder::new(10, 1.);
+    let block = [0.5; 4];
+
+    assert!(recorder.append_block(&block, &block));
+    assert!(recorder.append_block(&block, &block));
+    assert_eq!(recorder.len_frames(), 8);
+    assert!(!recorder.is_full());
+
+    // Only 2 of the 4 frames fit
+    assert!(!recorder.append_block(&block, &[-0.5; 4]));
+    assert!(recorder.is_full());
+    assert_eq!(recorder.len_seconds(), 1.);
+    assert!(!recorder.append_block(&block, &block));
+
+    let samples = recorder.get_planar_samples();
+    assert_eq!(samples.len(), 20);
+    assert_eq!(&samples[16..], &[0.5, 0.5, -0.5, -0.5]);
+    assert_eq!(recorder.encode_wav(WavBitDepth::Sixteen).len(), 44 + 10 * 2 * 2);
+
+    recorder.clear();
+    assert_eq!(recorder.len_frames(), 0);
+}
@@ -0,0 +1,98 @@
+extern crate engine;
+
+use engine::{
+    helpers::undo::UndoHistory,
+    views::midi_editor::automation::{
+        AutomationLane, AutomationState, AutomationTarget, Interpolation,
+    },
+};
+
+fn lane() -> AutomationLane {
+    let target = AutomationTarget {
+        vc_id: "vc".into(),
+        param_name: "frequency".into(),
+    };
+    let mut lane = AutomationLane::new(target, 0., 100.);
+    lane.insert_breakpoint(4., 100.);
+    lane.insert_breakpoint(2., 0.);
+    lane
+}
+
+#[test]
+fn lanes_interpolate_between_breakpoints() {
+    let mut lane = lane();
+    assert_eq!(lane.value_at(0.), Some(0.));
+    assert_eq!(lane.value_at(3.), Some(50.));
+    assert_eq!(lane.value_at(8.), Some(100.));
+
+    lane.breakpoints[0].interpolation = Interpolation::Curve { exponent: 2. };
+    assert_eq!(lane.value_at(3.), Some(25.));
+
+    // Breakpoints are always included in the samples
+    let samples = lane.sample(3.5, 4.5, 0.75);
+    assert_eq!(
+        samples.iter().map(|&(beat, _)| beat).collect::<Vec<_>>(),
+        vec![3.5, 4., 4.25]
+    );
+
+    // Moving a breakpoint past another keeps them sorted, and values are clamped to the range
+    assert_eq!(lane.move_breakpoint(0, 6., 150.), 1);
+    assert_eq!(lane.breakpoints[1].value, 100.);
+    assert_eq!(lane.breakpoints[1].interpolation, Interpolation::Curve {
+        exponent: 2.
+    });
+}
+
+#[test]
+fn automation_edits_can_be_undone_and_redone() {
+    let mut lanes = vec![lane()];
+    let mut history = UndoHistory::default();
+
+    history.record(&lanes);
+    lanes[0].remove_breakpoint(0);
+    assert_eq!(lanes[0].breakpoints.len(), 1);
+
+    assert!(history.undo(&mut lanes));
+    assert_eq!(lanes[0].breakpoints.len(), 2);
+    assert!(!history.undo(&mut lanes));
+
+    assert!(history.redo(&mut lanes));
+    assert_eq!(lanes[0].breakpoints.len(), 1);
+    assert!(!history.can_redo());
+}
+
+#[test]
+fn controllers_are_learned_and_recorded() {
+    let mut automation = AutomationState::new(vec![lane(), lane()]);
+    // Control changes are ignored until a controller is bound to a lane
+    assert!(!automation.learn_control_change(74));
+    assert!(!automation.record_control_change(1., 74, 127));
+
+    automation.learning_lane_ix = Some(1);
+    assert!(automation.learn_control_change(74));
+    assert_eq!(automation.lanes[1].control_index, Some(74));
+    assert_eq!(automation.learning_lane_ix, None);
+    // Learning a controller for another lane takes it from the first one
+    automation.learning_lane_ix = Some(0);
+    assert!(automation.learn_control_change(74));
+    assert_eq!(automation.lanes[0].control_index, Some(74));
+    assert_eq!(automation.lanes[1].control_index, None);
+
+    // Recorded values replace the breakpoints that were recorded over
+    assert!(automation.record_control_change(1., 74, 0));
+    assert!(automation.record_control_change(3., 74, 127));
+    assert!(automation.record_control_change(5., 74, 127));
+    let beats: Vec<f64> = automation.lanes[0]
+        .breakpoints
+        .iter()
+        .map(|breakpoint| breakpoint.beat)
+        .collect();
+    assert_eq!(beats, vec![1., 3., 5.]);
+    assert_eq!(automation.lanes[0].value_at(2.), Some(50.));
+    automation.finish_recording();
+
+    // The whole recording is undone at once
+    assert!(automation.undo());
+    assert_eq!(automation.lanes[0].breakpoints, lane().breakpoints);
+    assert_eq!(automation.lanes[0].control_index, Some(74));
+}
@@ -0,0 +1,18 @@
+extern crate engine;
+
+use engine::view_context::autosave::{Autosaver, MIN_AUTOSAVE_INTERVAL_MS};
+
+#[test]
+fn saves_are_throttled() {
+    let mut autosaver = Autosaver::default();
+    assert!(!autosaver.should_save(0., false));
+    assert!(autosaver.should_save(0., true));
+    autosaver.record_save(0.);
+
+    // Edits made soon after a save wait for the next one
+    assert!(!autosaver.should_save(1000., true));
+    assert!(!autosaver.should_save(MIN_AUTOSAVE_INTERVAL_MS - 1., true));
+    assert!(autosaver.should_save(MIN_AUTOSAVE_INTERVAL_MS, true));
+    // Nothing is saved if nothing was touched
+    assert!(!autosaver.should_save(MIN_AUTOSAVE_INTERVAL_MS * 2., false));
+}
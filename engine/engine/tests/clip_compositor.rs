@@ -0,0 +1,43 @@
+extern crate engine;
+extern crate serde_json;
+extern crate uuid;
+
+use engine::views::{
+    clip_compositor::{ArrangedClip, ClipCompositorConf},
+    midi_editor::clips::ClipInstance,
+};
+use uuid::Uuid;
+
+#[test]
+fn arranged_clips_are_saved_without_clip_notes() {
+    let clip_id = Uuid::from_u128(1);
+    let conf = ClipCompositorConf {
+        midi_editor_vc_id: Some(Uuid::from_u128(2)),
+        active_clip_id: Some(clip_id),
+        instances: vec![ArrangedClip {
+            track_ix: 1,
+            instance: ClipInstance {
+                clip_id,
+                start_beat: 4.,
+                length_beats: 8.,
+            },
+        }],
+        grid: None,
+    };
+
+    let serialized = serde_json::to_value(&conf).unwrap();
+    // Instances only reference their clips, and their placement is flattened into them
+    assert_eq!(
+        serialized["instances"][0],
+        serde_json::json!({
+            "trackIx": 1,
+            "clipId": clip_id.to_string(),
+            "startBeat": 4.,
+            "lengthBeats": 8.,
+        })
+    );
+
+    let deserialized: ClipCompositorConf = serde_json::from_value(serialized).unwrap();
+    assert_eq!(deserialized.instances, conf.instances);
+    assert_eq!(deserialized.midi_editor_vc_id, conf.midi_editor_vc_id);
+}
@@ -0,0 +1,59 @@
+extern crate engine;
+extern crate uuid;
+
+use engine::views::{
+    clip_launcher::TrackPlaybackState,
+    midi_editor::clips::{launch_session_clip, ClipInstance, LaunchedClips, SESSION_END_BEAT},
+};
+use uuid::Uuid;
+
+#[test]
+fn launching_clips_replaces_the_playing_clip_at_the_launch_beat() {
+    let (clip_a, clip_b) = (Uuid::from_u128(1), Uuid::from_u128(2));
+    let mut session = Vec::new();
+    launch_session_clip(&mut session, Some(clip_a), 0.);
+    launch_session_clip(&mut session, Some(clip_b), 8.);
+    // Launching again before the queued clip starts replaces it
+    launch_session_clip(&mut session, Some(clip_a), 8.);
+
+    assert_eq!(session, vec![
+        ClipInstance {
+            clip_id: clip_a,
+            start_beat: 0.,
+            length_beats: 8.,
+        },
+        ClipInstance {
+            clip_id: clip_a,
+            start_beat: 8.,
+            length_beats: SESSION_END_BEAT - 8.,
+        },
+    ]);
+
+    let launched = LaunchedClips {
+        instances: session.clone(),
+        cur_beat: Some(6.),
+        next_measure_time: Some(1.),
+    };
+    assert_eq!(
+        TrackPlaybackState::from_launched_clips(&launched),
+        TrackPlaybackState {
+            playing_clip_id: Some(clip_a),
+            queued_clip_id: Some(clip_a),
+            stop_queued: false,
+        }
+    );
+
+    launch_session_clip(&mut session, None, 8.);
+    let launched = LaunchedClips {
+        instances: session,
+        ..launched
+    };
+    assert_eq!(
+        TrackPlaybackState::from_launched_clips(&launched),
+        TrackPlaybackState {
+            playing_clip_id: Some(clip_a),
+            queued_clip_id: None,
+            stop_queued: true,
+        }
+    );
+}
@@ -0,0 +1,87 @@
+extern crate engine;
+extern crate uuid;
+
+use engine::{
+    helpers::grid::{note_box::NoteBox, skip_list::*},
+    views::midi_editor::{clips::*, prelude::*},
+};
+use uuid::Uuid;
+
+fn mkclip(notes: &[(usize, f32, f32)]) -> NoteLines<usize> {
+    let mut lines = NoteLines::new(4);
+    for &(line_ix, start_beat, end_beat) in notes {
+        lines.insert(line_ix, NoteBox {
+            bounds: NoteBoxBounds {
+                start_beat,
+                end_beat,
+            },
+            data: 0,
+            velocity: 100,
+            pitch_bend: Vec::new(),
+            expression: Default::default(),
+            id: NoteId::next(),
+        });
+    }
+    lines
+}
+
+#[test]
+fn arrangement_events_follow_clip_instances() {
+    let (clip_a, clip_b) = (Uuid::from_u128(1), Uuid::from_u128(2));
+    // Clip A is 2 beats long and has a note that's held past its end
+    let notes_a = mkclip(&[(0, 0., 1.), (1, 1.5, 3.)]);
+    let notes_b = mkclip(&[(2, 0., 0.5)]);
+    let get_clip = |clip_id: Uuid| {
+        if clip_id == clip_a {
+            Some((&notes_a, 2.))
+        } else if clip_id == clip_b {
+            Some((&notes_b, 1.))
+        } else {
+            None
+        }
+    };
+    let arrangement = vec![
+        // Loops clip A twice
+        ClipInstance {
+            clip_id: clip_a,
+            start_beat: 0.,
+            length_beats: 4.,
+        },
+        ClipInstance {
+            clip_id: clip_b,
+            start_beat: 4.,
+            length_beats: 1.,
+        },
+        // Instances of clips that don't exist are skipped
+        ClipInstance {
+            clip_id: Uuid::from_u128(3),
+            start_beat: 0.,
+            length_beats: 8.,
+        },
+    ];
+    let get_pass = |start: f64, end: f64| -> Vec<(usize, bool, f64)> {
+        get_arrangement_pass_events(&arrangement, get_clip, start, end)
+            .into_iter()
+            .map(|event| (event.event.line_ix, event.event.is_start, event.beat()))
+            .collect()
+    };
+
+    let all_events = vec![
+        (0, true, 0.),
+        (0, false, 1.),
+        (1, true, 1.5),
+        (1, false, 2.),
+        (0, true, 2.),
+        (0, false, 3.),
+        (1, true, 3.5),
+        (1, false, 4.),
+        (2, true, 4.),
+        (2, false, 4.5),
+    ];
+    assert_eq!(get_pass(0., 8.), all_events);
+    // Splitting the arrangement into passes doesn't drop or repeat any events
+    let mut split_events = get_pass(0., 2.);
+    split_events.extend(get_pass(2., 3.75));
+    split_events.extend(get_pass(3.75, 8.));
+    assert_eq!(split_events, all_events);
+}
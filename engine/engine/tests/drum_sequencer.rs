@@ -0,0 +1,71 @@
+extern crate engine;
+
+use engine::views::drum_sequencer::drum_sequencer_state::*;
+
+#[test]
+fn drum_sequencer_steps_round_trip() {
+    let mut state = DrumSequencerState::default();
+    let pattern_id = state.chain()[0];
+    let kick = state.rows()[0].id;
+    let snare = state.rows()[1].id;
+
+    assert!(state.set_step(pattern_id, kick, 0, Some(Step::new(127, 100))));
+    // Velocity and probability are clamped
+    assert!(state.set_step(pattern_id, snare, 4, Some(Step::new(0, 250))));
+    assert!(state.set_step(pattern_id, kick, 8, Some(Step::new(90, 50))));
+    assert!(state.set_step(pattern_id, kick, 8, None));
+    assert!(!state.set_step(pattern_id, kick, 16, Some(Step::new(90, 50))));
+    assert!(!state.set_step(pattern_id + 1, kick, 0, Some(Step::new(90, 50))));
+
+    let pattern = state.get_pattern(pattern_id).unwrap();
+    assert_eq!(pattern.get_step(snare, 4), Some(Step::new(1, 100)));
+    assert_eq!(pattern.get_step(kick, 8), None);
+
+    // Only active steps are serialized, as compact tuples
+    let serialized = state.serialize();
+    assert!(serialized.contains("\"steps\":[[0,0,127,100],[1,4,1,100]]"));
+    let deserialized: DrumSequencerState = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(deserialized, state);
+
+    // Shrinking and removing rows drops their steps
+    assert!(state.set_step_count(32));
+    assert!(state.set_step(pattern_id, kick, 20, Some(Step::new(100, 100))));
+    assert!(!state.set_step_count(24));
+    assert!(state.set_step_count(16));
+    state.remove_row(snare).unwrap();
+    let steps: Vec<_> = state.get_pattern(pattern_id).unwrap().steps().collect();
+    assert_eq!(steps, vec![((kick, 0), Step::new(127, 100))]);
+}
+
+#[test]
+fn drum_sequencer_pattern_chain() {
+    let mut state = DrumSequencerState::default();
+    let a = state.chain()[0];
+    let b = state.add_pattern();
+    let kick = state.rows()[0].id;
+    state.set_step(a, kick, 0, Some(Step::new(127, 100)));
+    let c = state.duplicate_pattern(a).unwrap();
+    assert_eq!(state.get_pattern(c).unwrap().get_step(kick, 0), Some(Step::new(127, 100)));
+
+    assert!(!state.set_chain(vec![]));
+    assert!(!state.set_chain(vec![a, 100]));
+    assert!(state.set_chain(vec![a, b, b, c]));
+
+    let resolve = |state: &DrumSequencerState, chain_step| {
+        let (pattern, step) = state.resolve_chain_step(chain_step);
+        (pattern.id, step)
+    };
+    assert_eq!(resolve(&state, 3), (a, 3));
+    assert_eq!(resolve(&state, 17), (b, 1));
+    assert_eq!(resolve(&state, 40), (b, 8));
+    assert_eq!(resolve(&state, 63), (c, 15));
+    assert_eq!(resolve(&state, 64), (a, 0));
+
+    state.remove_pattern(b).unwrap();
+    assert_eq!(state.chain(), &[a, c]);
+    state.remove_pattern(a).unwrap();
+    state.remove_pattern(c);
+    // The last pattern can't be removed
+    assert_eq!(state.chain(), &[c]);
+    assert_eq!(state.patterns().len(), 1);
+}
@@ -0,0 +1,117 @@
+extern crate engine;
+extern crate uuid;
+
+use std::{cell::RefCell, rc::Rc};
+
+use engine::view_context::{
+    events::{EngineEvent, EngineEventKind, EventSubscriptions},
+    manager::{MinimalViewContextDefinition, ViewContextEntry},
+    ViewContext, ViewContextManager,
+};
+use uuid::Uuid;
+
+struct RecordingViewContext {
+    uuid: Uuid,
+    received: Rc<RefCell<Vec<(Uuid, EngineEvent)>>>,
+}
+
+impl ViewContext for RecordingViewContext {
+    fn get_id(&self) -> String { self.uuid.to_string() }
+
+    fn handle_event(&mut self, event: &EngineEvent) {
+        self.received.borrow_mut().push((self.uuid, event.clone()));
+    }
+
+    fn save(&mut self) -> String { String::new() }
+}
+
+fn add_recording_vc(
+    vcm: &mut ViewContextManager,
+    uuid: Uuid,
+    received: &Rc<RefCell<Vec<(Uuid, EngineEvent)>>>,
+) {
+    vcm.contexts.push(ViewContextEntry {
+        definition: MinimalViewContextDefinition {
+            name: "recording".into(),
+            uuid,
+            title: None,
+        },
+        context: Box::new(RecordingViewContext {
+            uuid,
+            received: Rc::clone(received),
+        }),
+        touched: false,
+    });
+}
+
+#[test]
+fn subscriptions() {
+    let (vc_a, vc_b) = (Uuid::from_u128(1), Uuid::from_u128(2));
+    let mut subscriptions = EventSubscriptions::default();
+    subscriptions.subscribe(EngineEventKind::TransportStarted, vc_a);
+    subscriptions.subscribe(EngineEventKind::TransportStarted, vc_b);
+    subscriptions.subscribe(EngineEventKind::TransportStarted, vc_a);
+    subscriptions.subscribe(EngineEventKind::TempoChanged, vc_a);
+
+    assert_eq!(
+        subscriptions.get_subscribers(EngineEventKind::TransportStarted),
+        vec![vc_a, vc_b]
+    );
+    assert!(subscriptions
+        .get_subscribers(EngineEventKind::NoteTriggered)
+        .is_empty());
+
+    subscriptions.unsubscribe(EngineEventKind::TransportStarted, vc_b);
+    assert_eq!(
+        subscriptions.get_subscribers(EngineEventKind::TransportStarted),
+        vec![vc_a]
+    );
+
+    subscriptions.unsubscribe_all(vc_a);
+    assert!(subscriptions
+        .get_subscribers(EngineEventKind::TransportStarted)
+        .is_empty());
+    assert!(subscriptions
+        .get_subscribers(EngineEventKind::TempoChanged)
+        .is_empty());
+}
+
+#[test]
+fn events_are_delivered_to_subscribers_once_dispatched() {
+    let (vc_a, vc_b) = (Uuid::from_u128(1), Uuid::from_u128(2));
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let mut vcm = ViewContextManager::default();
+    add_recording_vc(&mut vcm, vc_a, &received);
+    add_recording_vc(&mut vcm, vc_b, &received);
+    vcm.subscribe(EngineEventKind::TempoChanged, vc_a);
+    vcm.subscribe(EngineEventKind::TempoChanged, vc_b);
+    vcm.subscribe(EngineEventKind::TransportStopped, vc_b);
+
+    let tempo_changed = EngineEvent::TempoChanged {
+        vc_id: "editor".into(),
+        bpm: 140.,
+    };
+    let note_triggered = EngineEvent::NoteTriggered {
+        vc_id: "editor".into(),
+        note_id: 60,
+        velocity: 100,
+    };
+    let transport_stopped = EngineEvent::TransportStopped {
+        vc_id: "editor".into(),
+    };
+    vcm.emit(tempo_changed.clone());
+    vcm.emit(note_triggered);
+    vcm.emit(transport_stopped.clone());
+    assert!(received.borrow().is_empty());
+
+    vcm.dispatch_events();
+    assert_eq!(*received.borrow(), vec![
+        (vc_a, tempo_changed.clone()),
+        (vc_b, tempo_changed),
+        (vc_b, transport_stopped),
+    ]);
+
+    // Events are only delivered once
+    vcm.dispatch_events();
+    assert_eq!(received.borrow().len(), 3);
+}
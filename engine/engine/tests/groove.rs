@@ -0,0 +1,28 @@
+extern crate engine;
+
+use engine::views::midi_editor::groove::GrooveConf;
+
+#[test]
+fn off_beats_are_delayed_by_the_swing() {
+    let groove = GrooveConf {
+        swing: 0.5,
+        division: 0.5,
+    };
+    // On-beats stay put while off-beats are pushed back by half of a division
+    assert_eq!(groove.apply(0.), 0.);
+    assert_eq!(groove.apply(0.5), 0.75);
+    assert_eq!(groove.apply(1.), 1.);
+    assert_eq!(groove.apply(3.5), 3.75);
+    // Everything in between is scaled around the off-beat
+    assert_eq!(groove.apply(0.25), 0.375);
+    assert_eq!(groove.apply(0.75), 0.875);
+
+    // The order of events is always preserved
+    let beats: Vec<f64> = (0..64).map(|i| groove.apply(i as f64 / 16.)).collect();
+    assert!(beats.windows(2).all(|pair| pair[0] < pair[1]));
+
+    // Without any swing, nothing is moved
+    let straight = GrooveConf::default();
+    assert!(!straight.is_enabled());
+    assert_eq!(straight.apply(0.5), 0.5);
+}
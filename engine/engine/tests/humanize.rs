@@ -0,0 +1,74 @@
+extern crate common;
+extern crate engine;
+extern crate rand_pcg;
+
+use common::{RawNoteData, MAX_NOTE_VELOCITY, MIN_NOTE_VELOCITY};
+use engine::views::midi_editor::humanize::{HumanizeConf, RandomizeConf};
+use rand_pcg::Pcg32;
+
+fn note(start_beat: f32, width: f32, velocity: u8) -> RawNoteData {
+    RawNoteData {
+        line_ix: 10,
+        start_beat,
+        width,
+        velocity,
+        pitch_bend: Vec::new(),
+        expression: Default::default(),
+    }
+}
+
+#[test]
+fn humanized_notes_stay_within_the_configured_bounds() {
+    let mut rng = Pcg32::new(7, 1);
+    let conf = HumanizeConf {
+        max_start_offset: 0.1,
+        max_length_offset: 0.2,
+        max_velocity_offset: 10,
+    };
+    for _ in 0..200 {
+        let humanized = conf.humanize(&mut rng, &note(4., 1., 100));
+        assert_eq!(humanized.line_ix, 10);
+        assert!((humanized.start_beat - 4.).abs() <= 0.1);
+        assert!((humanized.width - 1.).abs() <= 0.2);
+        assert!(humanized.velocity >= 90 && humanized.velocity <= 110);
+    }
+
+    // Notes are kept from starting before the first beat or leaving the velocity range
+    for _ in 0..200 {
+        let humanized = conf.humanize(&mut rng, &note(0., 0.1, MAX_NOTE_VELOCITY));
+        assert!(humanized.start_beat >= 0.);
+        assert!(humanized.width > 0.);
+        assert!(humanized.velocity >= MIN_NOTE_VELOCITY && humanized.velocity <= MAX_NOTE_VELOCITY);
+    }
+
+    // Humanizing with no offsets leaves notes unchanged
+    let humanized = HumanizeConf {
+        max_start_offset: 0.,
+        max_length_offset: 0.,
+        max_velocity_offset: 0,
+    }
+    .humanize(&mut rng, &note(2., 0.5, 64));
+    assert_eq!(
+        (humanized.start_beat, humanized.width, humanized.velocity),
+        (2., 0.5, 64)
+    );
+}
+
+#[test]
+fn randomized_notes_are_snapped_within_the_span() {
+    let mut rng = Pcg32::new(7, 1);
+    let conf = RandomizeConf {
+        min_length: 0.25,
+        max_length: 1.,
+        min_velocity: 50,
+        max_velocity: 80,
+    };
+    for _ in 0..200 {
+        let randomized = conf.randomize(&mut rng, &note(4., 1., 100), 2., 6., 0.5);
+        assert!(randomized.start_beat >= 2. && randomized.start_beat < 6.);
+        assert_eq!(randomized.start_beat % 0.5, 0.);
+        assert!(randomized.width >= 0.5 && randomized.width <= 1.);
+        assert_eq!(randomized.width % 0.5, 0.);
+        assert!(randomized.velocity >= 50 && randomized.velocity <= 80);
+    }
+}
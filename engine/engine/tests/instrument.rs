@@ -0,0 +1,86 @@
+extern crate engine;
+extern crate serde_json;
+extern crate uuid;
+
+use engine::{
+    view_context::manager::{ForeignConnectable, MinimalViewContextDefinition},
+    views::midi_editor::{
+        instrument::{list_instruments, InstrumentAssignment, InstrumentKind},
+        MIDIEditorConf,
+    },
+};
+use serde_json::json;
+use uuid::Uuid;
+
+fn mk_vc(name: &str, id: u128, title: Option<&str>) -> MinimalViewContextDefinition {
+    MinimalViewContextDefinition {
+        name: name.into(),
+        uuid: Uuid::from_u128(id),
+        title: title.map(Into::into),
+    }
+}
+
+fn mk_foreign_connectable(_type: &str, id: &str) -> ForeignConnectable {
+    ForeignConnectable {
+        _type: _type.into(),
+        id: id.into(),
+        serialized_state: None,
+    }
+}
+
+#[test]
+fn instruments_in_the_workspace_are_listed() {
+    let vcs = [
+        mk_vc("midi_editor", 1, None),
+        mk_vc("synth_designer", 2, Some("Lead")),
+        mk_vc("synth_designer", 3, None),
+    ];
+    let foreign_connectables = [
+        mk_foreign_connectable("customAudio/sampler", "10"),
+        mk_foreign_connectable("customAudio/delay", "11"),
+        mk_foreign_connectable("customAudio/fmSynth", "12"),
+    ];
+
+    let instruments = serde_json::to_value(list_instruments(&vcs, &foreign_connectables)).unwrap();
+    assert_eq!(
+        instruments,
+        json!([
+            { "kind": "subtractive_synth", "id": Uuid::from_u128(2).to_string(), "title": "Lead" },
+            { "kind": "subtractive_synth", "id": Uuid::from_u128(3).to_string(), "title": null },
+            { "kind": "sampler", "id": "10", "title": null },
+            { "kind": "fm_synth", "id": "12", "title": null },
+        ])
+    );
+}
+
+#[test]
+fn assigned_instruments_are_connected_and_saved() {
+    let instrument = InstrumentAssignment {
+        kind: InstrumentKind::FmSynth,
+        id: "12".into(),
+    };
+    let (from, to) = instrument.connection("midi_editor");
+    assert_eq!(
+        (from.vc_id.as_str(), from.name.as_str()),
+        ("midi_editor", "midi_output")
+    );
+    assert_eq!((to.vc_id.as_str(), to.name.as_str()), ("12", "midi"));
+
+    let conf: MIDIEditorConf = serde_json::from_value(json!({
+        "bpm": 120.0,
+        "loop_start_mark_measure": null,
+        "loop_end_mark_measure": null,
+        "instrument": { "kind": "fm_synth", "id": "12" },
+    }))
+    .unwrap();
+    assert_eq!(conf.instrument, Some(instrument));
+
+    // Saves from before instruments could be assigned don't have one
+    let conf: MIDIEditorConf = serde_json::from_value(json!({
+        "bpm": 120.0,
+        "loop_start_mark_measure": null,
+        "loop_end_mark_measure": null,
+    }))
+    .unwrap();
+    assert_eq!(conf.instrument, None);
+}
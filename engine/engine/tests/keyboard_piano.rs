@@ -0,0 +1,50 @@
+extern crate engine;
+
+use engine::views::midi_editor::{
+    keyboard_piano::{KeyboardPiano, DEFAULT_BASE_NOTE_ID, KEYBOARD_PIANO_VELOCITY},
+    midi_input::MIDIInputEvent,
+};
+
+fn note_on(note_id: usize) -> MIDIInputEvent {
+    MIDIInputEvent::NoteOn {
+        note_id,
+        velocity: KEYBOARD_PIANO_VELOCITY,
+    }
+}
+
+#[test]
+fn keys_play_notes_and_shift_octaves() {
+    let mut piano = KeyboardPiano::default();
+    assert_eq!(piano.key_down("a"), None);
+
+    piano.enabled = true;
+    assert_eq!(
+        piano.key_down("a"),
+        Some(vec![note_on(DEFAULT_BASE_NOTE_ID)])
+    );
+    // Repeated key down events from holding the key don't retrigger the note
+    assert_eq!(piano.key_down("a"), Some(Vec::new()));
+    assert_eq!(
+        piano.key_down("w"),
+        Some(vec![note_on(DEFAULT_BASE_NOTE_ID + 1)])
+    );
+    assert_eq!(piano.key_down("q"), None);
+
+    // Notes held while shifting the octave are released at the pitch they started at
+    assert_eq!(piano.key_down("x"), Some(Vec::new()));
+    assert_eq!(piano.key_up("A"), Some(MIDIInputEvent::NoteOff {
+        note_id: DEFAULT_BASE_NOTE_ID,
+    }));
+    assert_eq!(piano.key_down("a"), Some(vec![note_on(DEFAULT_BASE_NOTE_ID + 12)]));
+
+    piano.enabled = false;
+    assert_eq!(piano.release_all(), vec![
+        MIDIInputEvent::NoteOff {
+            note_id: DEFAULT_BASE_NOTE_ID + 1,
+        },
+        MIDIInputEvent::NoteOff {
+            note_id: DEFAULT_BASE_NOTE_ID + 12,
+        },
+    ]);
+    assert_eq!(piano.key_up("a"), None);
+}
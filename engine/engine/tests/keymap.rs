@@ -0,0 +1,69 @@
+extern crate engine;
+
+use engine::{
+    helpers::{
+        grid::GRID_KEYMAP,
+        keymap::{KeymapOverrides, KeymapSection},
+    },
+    views::midi_editor::MIDI_EDITOR_KEYMAP,
+};
+
+#[test]
+fn default_bindings_dont_conflict() {
+    for section in &[&GRID_KEYMAP, &MIDI_EDITOR_KEYMAP] {
+        let overrides = KeymapOverrides::new();
+        for action in section.actions {
+            assert_eq!(
+                section.get_action_by_name(action.name).unwrap().name,
+                action.name
+            );
+            for key in action.default_keys {
+                assert_eq!(section.get_action(key, &overrides), Some(action.name));
+            }
+        }
+    }
+
+    // Keys bound by the grid are never passed on to the MIDI editor
+    for action in MIDI_EDITOR_KEYMAP.actions {
+        for key in action.default_keys {
+            assert_eq!(GRID_KEYMAP.get_action(key, &KeymapOverrides::new()), None);
+        }
+    }
+}
+
+#[test]
+fn rebinding_a_key_moves_it_between_actions() {
+    let section: &KeymapSection = &MIDI_EDITOR_KEYMAP;
+    let mut overrides = KeymapOverrides::new();
+
+    assert!(section.bind(&mut overrides, "play_selected_notes", vec!["w".into()]));
+    assert_eq!(
+        section.get_action("w", &overrides),
+        Some("play_selected_notes")
+    );
+    assert_eq!(section.get_action("q", &overrides), None);
+    // The action that `w` was taken from keeps its other keys
+    assert_eq!(
+        section.get_action("ArrowUp", &overrides),
+        Some("transpose_up")
+    );
+
+    let bindings = section.describe(&overrides);
+    let transpose_up = bindings
+        .iter()
+        .find(|b| b.action == "transpose_up")
+        .unwrap();
+    assert_eq!(transpose_up.keys, vec!["ArrowUp".to_owned()]);
+    assert!(transpose_up.customized);
+    let shift_left = bindings.iter().find(|b| b.action == "shift_left").unwrap();
+    assert!(!shift_left.customized);
+
+    section.reset(&mut overrides, "play_selected_notes");
+    assert_eq!(
+        section.get_action("q", &overrides),
+        Some("play_selected_notes")
+    );
+    assert_eq!(section.get_action("w", &overrides), None);
+
+    assert!(!section.bind(&mut overrides, "nonexistent_action", vec!["q".into()]));
+}
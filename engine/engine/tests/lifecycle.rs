@@ -0,0 +1,75 @@
+extern crate engine;
+extern crate uuid;
+
+use std::{cell::RefCell, rc::Rc};
+
+use engine::view_context::{
+    manager::{MinimalViewContextDefinition, ViewContextEntry},
+    ViewContext, ViewContextManager,
+};
+use uuid::Uuid;
+
+#[derive(Debug, PartialEq)]
+enum LifecycleCall {
+    Resize(usize, usize),
+    VisibilityChange(bool),
+}
+
+struct RecordingViewContext {
+    uuid: Uuid,
+    calls: Rc<RefCell<Vec<(Uuid, LifecycleCall)>>>,
+}
+
+impl ViewContext for RecordingViewContext {
+    fn get_id(&self) -> String { self.uuid.to_string() }
+
+    fn handle_resize(&mut self, width: usize, height: usize) {
+        self.calls
+            .borrow_mut()
+            .push((self.uuid, LifecycleCall::Resize(width, height)));
+    }
+
+    fn handle_visibility_change(&mut self, visible: bool) {
+        self.calls
+            .borrow_mut()
+            .push((self.uuid, LifecycleCall::VisibilityChange(visible)));
+    }
+
+    fn save(&mut self) -> String { String::new() }
+}
+
+#[test]
+fn resizes_reach_every_vc_and_visibility_changes_reach_the_active_one() {
+    let (vc_a, vc_b) = (Uuid::from_u128(1), Uuid::from_u128(2));
+    let calls = Rc::new(RefCell::new(Vec::new()));
+    let mut vcm = ViewContextManager::default();
+    for &uuid in &[vc_a, vc_b] {
+        vcm.contexts.push(ViewContextEntry {
+            definition: MinimalViewContextDefinition {
+                name: "recording".into(),
+                uuid,
+                title: None,
+            },
+            context: Box::new(RecordingViewContext {
+                uuid,
+                calls: Rc::clone(&calls),
+            }),
+            touched: false,
+        });
+    }
+    vcm.active_context_ix = 1;
+
+    vcm.handle_resize(1280, 680);
+    assert_eq!(vcm.viewport_size, Some((1280, 680)));
+    vcm.handle_visibility_change(false);
+    // Only actual changes in visibility are passed on
+    vcm.handle_visibility_change(false);
+    vcm.handle_visibility_change(true);
+
+    assert_eq!(*calls.borrow(), vec![
+        (vc_a, LifecycleCall::Resize(1280, 680)),
+        (vc_b, LifecycleCall::Resize(1280, 680)),
+        (vc_b, LifecycleCall::VisibilityChange(false)),
+        (vc_b, LifecycleCall::VisibilityChange(true)),
+    ]);
+}
@@ -0,0 +1,59 @@
+extern crate engine;
+
+use engine::{
+    helpers::grid::skip_list::NoteEvent, views::midi_editor::scheduler::get_loop_pass_events,
+};
+
+fn event(line_ix: usize, is_start: bool, beat: f32) -> NoteEvent {
+    NoteEvent {
+        line_ix,
+        is_start,
+        beat,
+        velocity: 100,
+    }
+}
+
+fn summarize(events: Vec<NoteEvent>) -> Vec<(usize, bool, f32)> {
+    events
+        .into_iter()
+        .map(|event| (event.line_ix, event.is_start, event.beat))
+        .collect()
+}
+
+#[test]
+fn loop_passes_wrap_without_dropping_or_repeating_events() {
+    let events = vec![
+        // Starts before the loop; never scheduled
+        event(1, true, 0.),
+        event(1, false, 5.),
+        event(2, true, 4.),
+        event(2, false, 6.),
+        // Held past the end of the loop
+        event(3, true, 7.),
+        // Starts right at the end of the loop
+        event(4, true, 8.),
+        event(3, false, 9.),
+        event(4, false, 9.),
+    ];
+    let loop_bounds = (4., 8.);
+    let get_pass = |start: f64, end: f64| {
+        summarize(get_loop_pass_events(
+            events.clone().into_iter(),
+            loop_bounds,
+            start,
+            end,
+        ))
+    };
+
+    // Splitting the loop at a release doesn't schedule it twice
+    assert_eq!(get_pass(4., 6.), vec![(2, true, 4.), (2, false, 6.)]);
+    assert_eq!(get_pass(6., 8.), vec![(3, true, 7.), (3, false, 8.)]);
+    // The whole loop in one pass is the same as both halves together
+    assert_eq!(get_pass(4., 8.), vec![
+        (2, true, 4.),
+        (2, false, 6.),
+        (3, true, 7.),
+        (3, false, 8.)
+    ]);
+    assert_eq!(get_pass(7.5, 7.5), vec![]);
+}
@@ -0,0 +1,59 @@
+extern crate engine;
+
+use engine::view_context::{
+    message::{MessageKind, MessageRegistry},
+    Message,
+};
+
+#[test]
+fn messages_use_the_documented_binary_format() {
+    let message = Message::SetTrackMuted {
+        track_id: 2,
+        muted: true,
+    };
+    let encoded = message.encode();
+    assert_eq!(encoded, vec![2, 0, 0, 0, 2, 0, 0, 0, 1]);
+    assert_eq!(Message::decode(&encoded).unwrap(), message);
+
+    let mut encoded = vec![4, 0, 0, 0];
+    encoded.extend_from_slice(&0.5f32.to_le_bytes());
+    assert_eq!(
+        Message::decode(&encoded).unwrap(),
+        Message::SetMasterGain { gain: 0.5 }
+    );
+
+    assert!(Message::decode(&[4, 0, 0]).is_err());
+    assert!(Message::decode(&[255, 0, 0, 0]).is_err());
+}
+
+#[derive(Default)]
+struct Gain(f32);
+
+fn set_gain(gain: &mut Gain, message: Message) -> Option<Vec<u8>> {
+    if let Message::SetMasterGain { gain: new_gain } = message {
+        gain.0 = new_gain;
+    }
+    Some(vec![1])
+}
+
+static GAIN_MESSAGE_HANDLERS: MessageRegistry<Gain> = MessageRegistry {
+    handlers: &[(MessageKind::SetMasterGain, set_gain)],
+};
+
+#[test]
+fn messages_are_dispatched_to_registered_handlers() {
+    let mut gain = Gain::default();
+    assert!(GAIN_MESSAGE_HANDLERS.handles(MessageKind::SetMasterGain));
+    assert!(!GAIN_MESSAGE_HANDLERS.handles(MessageKind::SetTrackGain));
+
+    let res = GAIN_MESSAGE_HANDLERS.dispatch(&mut gain, Message::SetMasterGain { gain: 0.5 });
+    assert_eq!(res, Some(vec![1]));
+    assert_eq!(gain.0, 0.5);
+
+    let res = GAIN_MESSAGE_HANDLERS.dispatch(&mut gain, Message::SetTrackGain {
+        track_id: 0,
+        gain: 1.,
+    });
+    assert_eq!(res, None);
+    assert_eq!(gain.0, 0.5);
+}
@@ -0,0 +1,116 @@
+extern crate engine;
+
+use std::f32::consts::PI;
+
+use audio_block::{Block, BlockProcessor};
+use engine::{
+    dsp::meter::{BlockLevels, Meter, MeterBallistics, MeterTap},
+    views::mixer::metering::{decode_meter_blocks, MixerMeters, MASTER_METER_ID},
+};
+
+const SAMPLE_RATE: f32 = 48_000.;
+const BLOCK_LEN: usize = 128;
+
+fn sine_block(block_ix: usize, amplitude: f32) -> BlockLevels {
+    let samples: Vec<f32> = (0..BLOCK_LEN)
+        .map(|i| {
+            let t = (block_ix * BLOCK_LEN + i) as f32 / SAMPLE_RATE;
+            (2. * PI * 1000. * t).sin() * amplitude
+        })
+        .collect();
+    BlockLevels::from_samples(&samples)
+}
+
+fn silent_block() -> BlockLevels {
+    BlockLevels {
+        peak: 0.,
+        mean_square: 0.,
+        frame_count: BLOCK_LEN,
+    }
+}
+
+/// Number of blocks that make up `seconds` of audio
+fn blocks(seconds: f32) -> usize { (seconds * SAMPLE_RATE / BLOCK_LEN as f32) as usize }
+
+#[test]
+fn meter_ballistics() {
+    let ballistics = MeterBallistics::default();
+    let mut meter = Meter::default();
+    for block_ix in 0..blocks(2.) {
+        meter.process_block(&ballistics, sine_block(block_ix, 0.5), SAMPLE_RATE);
+    }
+    let reading = meter.reading();
+    assert!((reading.peak - 0.5).abs() < 0.01);
+    // The RMS level of a sine wave is its amplitude over sqrt(2)
+    assert!((reading.rms - 0.5 / 2f32.sqrt()).abs() < 0.01);
+    assert!(!reading.clipped);
+
+    // Half a second of silence lets the peak fall by 12 dB, but the held peak stays put
+    for _ in 0..blocks(0.5) {
+        meter.process_block(&ballistics, silent_block(), SAMPLE_RATE);
+    }
+    let reading = meter.reading();
+    assert!((reading.peak - 0.5 / 4.).abs() < 0.01);
+    assert!((reading.held_peak - 0.5).abs() < 0.01);
+    assert!(reading.rms < 0.2);
+
+    // Once the hold time is up, the held peak falls along with the peak
+    for _ in 0..blocks(1.5) {
+        meter.process_block(&ballistics, silent_block(), SAMPLE_RATE);
+    }
+    let reading = meter.reading();
+    assert_eq!(reading.held_peak, reading.peak);
+    assert!(reading.peak < 0.01);
+}
+
+#[test]
+fn meter_clipping_is_sticky() {
+    let ballistics = MeterBallistics::default();
+    let mut meter = Meter::default();
+    meter.process_block(&ballistics, sine_block(0, 1.2), SAMPLE_RATE);
+    for _ in 0..blocks(5.) {
+        meter.process_block(&ballistics, silent_block(), SAMPLE_RATE);
+    }
+    assert!(meter.reading().clipped);
+    meter.reset_clipped();
+    assert!(!meter.reading().clipped);
+}
+
+#[test]
+fn meter_tap_passes_audio_through() {
+    let mut tap = MeterTap::new(MeterBallistics::default(), SAMPLE_RATE);
+    let mut block = Block::default();
+    block.left[0] = -0.5;
+    block.right[0] = 0.25;
+    let input = block.clone();
+    tap.process(&mut block);
+
+    assert_eq!(block, input);
+    assert_eq!(tap.meter.reading().peak, 0.5);
+}
+
+#[test]
+fn mixer_meters_are_read_in_track_order() {
+    let mut meters = MixerMeters::new(SAMPLE_RATE);
+    let mut message: Vec<u8> = Vec::new();
+    message.extend_from_slice(&3u32.to_ne_bytes());
+    message.extend_from_slice(&(BLOCK_LEN as u32).to_ne_bytes());
+    for &(peak, mean_square) in &[(0.25f32, 0.01f32), (0.5, 0.02)] {
+        message.extend_from_slice(&peak.to_ne_bytes());
+        message.extend_from_slice(&mean_square.to_ne_bytes());
+    }
+    let (meter_id, blocks) = decode_meter_blocks(&message).unwrap();
+    assert_eq!(meter_id, 3);
+    assert_eq!(blocks.len(), 2);
+    meters.process_blocks(meter_id, &blocks);
+    meters.process_blocks(MASTER_METER_ID, &[sine_block(0, 0.1)]);
+
+    let readings = meters.readings(vec![1, 3].into_iter());
+    assert_eq!(readings.len(), 3);
+    assert_eq!(readings[0].peak, 0.);
+    assert_eq!(readings[1].peak, 0.5);
+    assert!((readings[2].peak - 0.1).abs() < 0.01);
+
+    meters.retain_tracks(&[1]);
+    assert_eq!(meters.readings(vec![3].into_iter())[0].peak, 0.);
+}
@@ -0,0 +1,25 @@
+extern crate common;
+extern crate engine;
+
+use common::tempo_map::{TempoMap, TimeSignature};
+use engine::metronome::*;
+
+#[test]
+fn clicks_follow_the_time_signature() {
+    let mut tempo_map = TempoMap::new(60.);
+    tempo_map.set_time_signature(0., TimeSignature::new(3, 4).unwrap());
+
+    let clicks: Vec<(f64, bool)> = get_clicks(&tempo_map, 2., 5.)
+        .into_iter()
+        .map(|click| (click.beat, click.is_accent))
+        .collect();
+    assert_eq!(clicks, vec![(2., false), (3., true), (4., false)]);
+
+    // One bar of 3/4 at 60 BPM counting in to beat 3
+    assert_eq!(get_count_in_clicks(&tempo_map, 3., 1), vec![
+        (-3., true),
+        (-2., false),
+        (-1., false)
+    ]);
+    assert!(get_count_in_clicks(&tempo_map, 3., 0).is_empty());
+}
@@ -0,0 +1,41 @@
+extern crate engine;
+
+use engine::views::midi_editor::midi_input::*;
+
+#[test]
+fn raw_midi_messages_are_parsed() {
+    assert_eq!(
+        MIDIInputEvent::from_bytes(&[0x93, 60, 100]),
+        Some(MIDIInputEvent::NoteOn {
+            note_id: 60,
+            velocity: 100
+        })
+    );
+    // Note on with zero velocity is a note off
+    assert_eq!(
+        MIDIInputEvent::from_bytes(&[0x90, 60, 0]),
+        Some(MIDIInputEvent::NoteOff { note_id: 60 })
+    );
+    assert_eq!(
+        MIDIInputEvent::from_bytes(&[0x80, 60, 64]),
+        Some(MIDIInputEvent::NoteOff { note_id: 60 })
+    );
+    assert_eq!(
+        MIDIInputEvent::from_bytes(&[0xB0, 1, 127]),
+        Some(MIDIInputEvent::ControlChange {
+            control_index: 1,
+            value: 127
+        })
+    );
+    assert_eq!(MIDIInputEvent::from_bytes(&[0xE0, 0, 64]), None);
+    assert_eq!(MIDIInputEvent::from_bytes(&[0x90, 60]), None);
+}
+
+#[test]
+fn recorded_notes_are_quantized() {
+    assert_eq!(quantize_note_bounds(1.1, 1.9, 0.5), (1., 2.));
+    // Notes shorter than the snap interval are kept one interval long
+    assert_eq!(quantize_note_bounds(3.05, 3.1, 0.25), (3., 3.25));
+    // Snapping disabled
+    assert_eq!(quantize_note_bounds(1.1, 1.9, 0.), (1.1, 1.9));
+}
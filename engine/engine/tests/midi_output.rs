@@ -0,0 +1,21 @@
+extern crate engine;
+
+use engine::views::midi_editor::midi_output::MIDIOutputQueue;
+
+#[test]
+fn output_messages_are_encoded_on_the_channel() {
+    let mut queue = MIDIOutputQueue::default();
+    queue.push_note_on(1., 2, 60, 100);
+    // Zero velocity note ons are bumped so that they aren't treated as note offs
+    queue.push_note_on(1.5, 2, 62, 0);
+    queue.push_note_off(2., 2, 60);
+    queue.push_all_notes_off(3., 15);
+
+    assert_eq!(queue.messages, vec![
+        0x92, 60, 100, 0x92, 62, 1, 0x82, 60, 0, 0xBF, 123, 0
+    ]);
+    assert_eq!(queue.timings, vec![1., 1.5, 2., 3.]);
+
+    queue.clear();
+    assert!(queue.is_empty());
+}
@@ -0,0 +1,67 @@
+extern crate engine;
+extern crate serde_json;
+
+use engine::view_context::{
+    manager::get_save_migrations,
+    migrations::{migrate_conf, SaveMigration, SaveMigrationError},
+};
+use serde_json::{json, Value};
+
+const MIGRATIONS: &[SaveMigration] = &[
+    // v0 -> v1: `bpm` was renamed to `tempo`
+    |conf| {
+        if let Some(bpm) = conf.remove("bpm") {
+            conf.insert("tempo".into(), bpm);
+        }
+    },
+    // v1 -> v2: `tempo` was moved into `transport`
+    |conf| {
+        let tempo = conf.remove("tempo").unwrap_or(Value::Null);
+        conf.insert("transport".into(), json!({ "tempo": tempo }));
+    },
+];
+
+fn migrate(conf: &str) -> Value {
+    serde_json::from_str(&migrate_conf(conf, MIGRATIONS).expect("Failed to migrate conf")).unwrap()
+}
+
+#[test]
+fn saves_are_upgraded_step_by_step() {
+    assert_eq!(
+        migrate(r#"{"bpm":140,"name":"a"}"#),
+        json!({ "version": 2, "transport": { "tempo": 140 }, "name": "a" })
+    );
+    assert_eq!(
+        migrate(r#"{"version":1,"tempo":90}"#),
+        json!({ "version": 2, "transport": { "tempo": 90 } })
+    );
+
+    let current = r#"{"version":2,"transport":{"tempo":120}}"#;
+    assert_eq!(migrate_conf(current, MIGRATIONS).unwrap(), current);
+    // Saves of VCs without migrations and empty saves are left alone
+    let unversioned = r#"{"bpm":140}"#;
+    assert_eq!(migrate_conf(unversioned, &[]).unwrap(), unversioned);
+    assert_eq!(migrate_conf("", MIGRATIONS).unwrap(), "");
+}
+
+#[test]
+fn saves_that_cant_be_migrated() {
+    match migrate_conf(r#"{"version":3}"#, MIGRATIONS) {
+        Err(SaveMigrationError::UnsupportedVersion {
+            version: 3,
+            current_version: 2,
+        }) => (),
+        other => panic!("Expected an unsupported version error, got {:?}", other),
+    }
+    match migrate_conf("{\"version\":", MIGRATIONS) {
+        Err(SaveMigrationError::InvalidConf(_)) => (),
+        other => panic!("Expected an invalid conf error, got {:?}", other),
+    }
+}
+
+#[test]
+fn midi_editor_saves_without_a_version_are_upgraded() {
+    let migrated = migrate_conf(r#"{"bpm":120.0}"#, get_save_migrations("midi_editor")).unwrap();
+    let migrated: Value = serde_json::from_str(&migrated).unwrap();
+    assert_eq!(migrated, json!({ "version": 1, "bpm": 120.0 }));
+}
@@ -0,0 +1,60 @@
+extern crate engine;
+
+use engine::views::mixer::mixer_state::*;
+
+fn effective_gains(state: &MixerState) -> Vec<f32> {
+    state
+        .tracks()
+        .iter()
+        .map(|track| state.get_effective_gain(track))
+        .collect()
+}
+
+#[test]
+fn mixer_mute_and_solo() {
+    let mut state = MixerState::default();
+    let track_0 = state.tracks()[0].id;
+    let track_1 = state.add_track();
+    let track_2 = state.add_track();
+    assert!(state.set_track_gain(track_0, 0.5));
+    assert_eq!(effective_gains(&state), vec![0.5, 1., 1.]);
+
+    state.set_track_muted(track_1, true);
+    assert_eq!(effective_gains(&state), vec![0.5, 0., 1.]);
+
+    // Soloing a track silences all un-soloed tracks
+    state.set_track_soloed(track_2, true);
+    assert_eq!(effective_gains(&state), vec![0., 0., 1.]);
+
+    // Muting takes precedence over soloing
+    state.set_track_soloed(track_1, true);
+    assert_eq!(effective_gains(&state), vec![0., 0., 1.]);
+    state.set_track_muted(track_1, false);
+    assert_eq!(effective_gains(&state), vec![0., 1., 1.]);
+
+    state.set_track_soloed(track_1, false);
+    state.set_track_soloed(track_2, false);
+    assert_eq!(effective_gains(&state), vec![0.5, 1., 1.]);
+}
+
+#[test]
+fn mixer_track_params() {
+    let mut state = MixerState::default();
+    let id = state.add_track();
+    assert!(state.set_track_pan(id, -3.));
+    assert!(state.set_track_gain(id, 10.));
+    assert!(state.set_track_source(id, Some("some-vc-id".into())));
+    state.set_master_gain(-1.);
+
+    let track = state.remove_track(id).unwrap();
+    assert_eq!(track.pan, -1.);
+    assert_eq!(track.gain, 2.);
+    assert_eq!(track.source_vc_id, Some("some-vc-id".into()));
+    assert_eq!(state.master_gain, 0.);
+
+    assert!(state.remove_track(id).is_none());
+    assert!(state.get_track(id).is_none());
+    assert!(!state.set_track_muted(id, true));
+    // IDs aren't re-used after tracks are removed
+    assert_ne!(state.add_track(), id);
+}
@@ -0,0 +1,83 @@
+extern crate engine;
+
+use engine::views::synth_designer::mod_matrix::*;
+
+fn lfo_route(destination: &str, depth: f32) -> ModRouteDefinition {
+    ModRouteDefinition {
+        source: ModSource::Lfo {
+            waveform: LfoWaveform::Sine,
+            frequency: 2.,
+        },
+        destination: destination.into(),
+        depth,
+    }
+}
+
+#[test]
+fn mod_matrix_add_remove_routes() {
+    let mut matrix = ModMatrix::default();
+    let id_1 = matrix.add_route(lfo_route("synth_0_filter_frequency", 400.));
+    let id_2 = matrix.add_route(ModRouteDefinition {
+        source: ModSource::MidiCc { cc: 74 },
+        destination: "synth_0_detune".into(),
+        depth: 100.,
+    });
+    assert_ne!(id_1, id_2);
+    assert_eq!(matrix.routes().len(), 2);
+
+    assert!(matrix.set_route_depth(id_2, -50.));
+    assert_eq!(matrix.routes()[1].definition.depth, -50.);
+
+    let removed = matrix.remove_route(id_1).unwrap();
+    assert_eq!(removed.definition, lfo_route("synth_0_filter_frequency", 400.));
+    assert!(matrix.remove_route(id_1).is_none());
+    assert!(!matrix.set_route_depth(id_1, 1.));
+    assert_eq!(matrix.routes().len(), 1);
+    assert_eq!(matrix.routes()[0].id, id_2);
+
+    // IDs aren't re-used after routes are removed
+    let id_3 = matrix.add_route(lfo_route("synth_0_filter_q", 1.));
+    assert!(id_3 != id_1 && id_3 != id_2);
+}
+
+#[test]
+fn mod_matrix_route_serialization() {
+    let mut matrix = ModMatrix::default();
+    matrix.add_route(ModRouteDefinition {
+        source: ModSource::Velocity,
+        destination: "synth_1_filter_frequency".into(),
+        depth: 1200.,
+    });
+
+    let serialized = matrix.serialize_routes();
+    assert_eq!(
+        serialized,
+        "[{\"id\":0,\"source\":{\"type\":\"velocity\"},\"destination\":\
+         \"synth_1_filter_frequency\",\"depth\":1200.0}]"
+    );
+
+    let definition: ModRouteDefinition = serde_json::from_str(
+        "{\"source\":{\"type\":\"lfo\",\"waveform\":\"sine\",\"frequency\":2},\
+         \"destination\":\"synth_0_filter_frequency\",\"depth\":400}",
+    )
+    .unwrap();
+    assert_eq!(definition, lfo_route("synth_0_filter_frequency", 400.));
+}
+
+#[test]
+fn noise_and_sample_and_hold_sources() {
+    let definition: ModRouteDefinition = serde_json::from_str(
+        "{\"source\":{\"type\":\"sample_and_hold\",\"frequency\":8},\
+         \"destination\":\"synth_0_filter_frequency\",\"depth\":2000}",
+    )
+    .unwrap();
+    assert_eq!(definition.source, ModSource::SampleAndHold { frequency: 8. });
+
+    let source = ModSource::Noise {
+        color: NoiseColor::Pink,
+    };
+    assert_eq!(
+        serde_json::to_string(&source).unwrap(),
+        "{\"type\":\"noise\",\"color\":\"pink\"}"
+    );
+}
@@ -0,0 +1,135 @@
+extern crate common;
+extern crate engine;
+
+use common::{
+    expression::{ExpressionDimension, ExpressionPoint, NoteExpression},
+    pitch_bend::PitchBendPoint,
+};
+use engine::views::midi_editor::{
+    midi_input::MIDIInputEvent,
+    mpe::{MPEConf, MPEInput, RecordedExpression},
+};
+
+fn point(beat_offset: f32, value: f32) -> ExpressionPoint { ExpressionPoint { beat_offset, value } }
+
+#[test]
+fn member_channels_are_demultiplexed_into_note_expression() {
+    let conf = MPEConf {
+        enabled: true,
+        pitch_bend_range: 48.,
+    };
+    let mut input = MPEInput::default();
+
+    // Expression on a channel without a note is dropped
+    assert_eq!(input.parse(&conf, &[0xD1, 64]), None);
+    assert_eq!(
+        input.parse(&conf, &[0x91, 60, 100]),
+        Some(MIDIInputEvent::NoteOn {
+            note_id: 60,
+            velocity: 100
+        })
+    );
+    input.parse(&conf, &[0x92, 64, 100]);
+
+    let expression = |note_id: usize, dimension: ExpressionDimension, value: f32| {
+        Some(MIDIInputEvent::NoteExpression {
+            note_id,
+            dimension,
+            value,
+        })
+    };
+    assert_eq!(
+        input.parse(&conf, &[0xD1, 127]),
+        expression(60, ExpressionDimension::Pressure, 1.)
+    );
+    assert_eq!(
+        input.parse(&conf, &[0xB2, 74, 0]),
+        expression(64, ExpressionDimension::Timbre, 0.)
+    );
+    // Pitch bends are scaled to the configured range
+    assert_eq!(
+        input.parse(&conf, &[0xE2, 0, 96]),
+        expression(64, ExpressionDimension::PitchBend, 24.)
+    );
+    // Other controllers on member channels are passed through
+    assert_eq!(
+        input.parse(&conf, &[0xB1, 1, 10]),
+        Some(MIDIInputEvent::ControlChange {
+            control_index: 1,
+            value: 10
+        })
+    );
+
+    input.parse(&conf, &[0x81, 60, 0]);
+    assert_eq!(input.parse(&conf, &[0xD1, 127]), None);
+    assert!(input.parse(&conf, &[0xD2, 127]).is_some());
+}
+
+#[test]
+fn master_channel_and_disabled_mpe_ignore_channels() {
+    let mut input = MPEInput::default();
+    let conf = MPEConf::default();
+    assert!(!conf.enabled);
+    input.parse(&conf, &[0x91, 60, 100]);
+    assert_eq!(input.parse(&conf, &[0xD1, 127]), None);
+    assert_eq!(input.parse(&conf, &[0xE1, 0, 96]), None);
+
+    let conf = MPEConf {
+        enabled: true,
+        ..conf
+    };
+    input.parse(&conf, &[0x90, 60, 100]);
+    assert_eq!(input.parse(&conf, &[0xD0, 127]), None);
+    assert_eq!(
+        input.parse(&conf, &[0xB0, 74, 127]),
+        Some(MIDIInputEvent::ControlChange {
+            control_index: 74,
+            value: 127
+        })
+    );
+}
+
+#[test]
+fn recorded_expression_follows_quantized_notes() {
+    let mut recorded = RecordedExpression::default();
+    recorded.record(0., ExpressionDimension::Pressure, 0.2);
+    // Values that come right after the last point replace it
+    recorded.record(0.001, ExpressionDimension::Pressure, 0.3);
+    recorded.record(0.5, ExpressionDimension::Pressure, 0.6);
+    recorded.record(0.25, ExpressionDimension::PitchBend, 1.);
+    recorded.record(3., ExpressionDimension::Timbre, 1.);
+
+    // The note started playing a quarter beat after its quantized start and is 2 beats long
+    let (pitch_bend, expression) = recorded.into_note_curves(0.25, 2.);
+    assert_eq!(
+        pitch_bend,
+        vec![PitchBendPoint {
+            beat_offset: 0.5,
+            semitones: 1.
+        }]
+    );
+    assert_eq!(expression.pressure, vec![point(0.25, 0.3), point(0.75, 0.6)]);
+    assert!(expression.timbre.is_empty());
+
+    // Values from before a quantized start that comes later are moved to it
+    let mut recorded = RecordedExpression::default();
+    recorded.record(0., ExpressionDimension::Timbre, 0.5);
+    let (_, expression) = recorded.into_note_curves(-0.25, 1.);
+    assert_eq!(expression.timbre, vec![point(0., 0.5)]);
+}
+
+#[test]
+fn expression_is_split_with_notes() {
+    let mut expression = NoteExpression {
+        pressure: vec![point(0., 0.2), point(1., 0.4), point(3., 0.8)],
+        timbre: Vec::new(),
+    };
+    let second_half = expression.split_off(2.);
+    assert_eq!(expression.pressure, vec![point(0., 0.2), point(1., 0.4)]);
+    // The value held at the split carries over to the start of the second half
+    assert_eq!(second_half.pressure, vec![point(0., 0.4), point(1., 0.8)]);
+    assert!(second_half.timbre.is_empty());
+
+    expression.normalize(0.5);
+    assert_eq!(expression.pressure, vec![point(0., 0.2)]);
+}
@@ -0,0 +1,142 @@
+extern crate engine;
+extern crate serde_json;
+
+use engine::views::midi_editor::{
+    note_layout::{DrumSound, NoteLayout, NoteRange},
+    slices::SliceMapping,
+    MIDIEditorConf,
+};
+use serde_json::json;
+
+#[test]
+fn note_ranges_are_validated() {
+    let range = NoteRange::new(36, 24).unwrap();
+    assert_eq!(range.highest_note(), 59);
+    assert!(range.contains(36) && range.contains(59));
+    assert!(!range.contains(35) && !range.contains(60));
+
+    assert_eq!(NoteRange::new(36, 0), None);
+    assert!(NoteRange::new(0, 128).is_some());
+    assert_eq!(NoteRange::new(1, 128), None);
+}
+
+#[test]
+fn unfolded_layouts_show_the_whole_range_from_the_top() {
+    let layout = NoteLayout::unfolded(NoteRange::new(36, 24).unwrap());
+    assert_eq!(layout.line_count(), 24);
+    assert_eq!(layout.line_note(0), 59);
+    assert_eq!(layout.line_note(23), 36);
+    assert_eq!(layout.note_line(48), Some(11));
+    assert_eq!(layout.note_line(60), None);
+
+    // The default range is the one that all MIDI editors used to show
+    let default_layout = NoteLayout::default();
+    assert_eq!(default_layout.line_count(), 96);
+    assert_eq!(default_layout.line_note(0), 96);
+    assert_eq!(default_layout.note_line(1), Some(95));
+}
+
+#[test]
+fn folded_layouts_only_show_used_notes() {
+    let range = NoteRange::new(36, 24).unwrap();
+    let layout = NoteLayout::folded(range, vec![38, 42, 36, 42, 70]);
+    assert!(layout.is_folded());
+    // Notes outside of the range are dropped and the rest are shown from the top down
+    assert_eq!(layout.folded_notes, Some(vec![42, 38, 36]));
+    assert_eq!(layout.line_count(), 3);
+    assert_eq!(layout.line_note(1), 38);
+    assert_eq!(layout.note_line(36), Some(2));
+    assert_eq!(layout.note_line(40), None);
+
+    // Lines are moved to the ones that show the same note when the layout changes
+    let unfolded = NoteLayout::unfolded(range);
+    assert_eq!(layout.remap_line(&unfolded, 0), unfolded.note_line(42));
+    assert_eq!(unfolded.remap_line(&layout, 0), None);
+
+    // There always has to be at least one line
+    assert_eq!(NoteLayout::folded(range, Vec::new()), unfolded);
+}
+
+#[test]
+fn drum_maps_label_lines_with_sounds() {
+    let sound = |name: &str, note: usize| DrumSound {
+        name: name.into(),
+        note,
+    };
+    let range = NoteRange::new(36, 24).unwrap();
+    let layout =
+        NoteLayout::with_drum_map(range, vec![sound("kick", 36), sound("hat", 42)]).unwrap();
+    assert!(layout.is_drum_map());
+    assert_eq!(layout.line_count(), 2);
+    assert_eq!(layout.line_note(1), 42);
+    assert_eq!(layout.note_line(36), Some(0));
+    assert_eq!(layout.note_line(38), None);
+    assert_eq!(layout.line_labels(), vec!["kick", "hat"]);
+    // Pitches aren't labeled
+    assert!(NoteLayout::unfolded(range).line_labels().is_empty());
+
+    assert_eq!(NoteLayout::with_drum_map(range, Vec::new()), None);
+    let duplicate_notes = vec![sound("kick", 36), sound("other kick", 36)];
+    assert_eq!(NoteLayout::with_drum_map(range, duplicate_notes), None);
+    assert_eq!(
+        NoteLayout::with_drum_map(range, vec![sound("kick", 128)]),
+        None
+    );
+}
+
+#[test]
+fn slices_are_mapped_to_drum_map_lines() {
+    let mapping = SliceMapping {
+        base_note: 36,
+        slice_start_beats: vec![0., 1., 1.5, 3.],
+        end_beat: 4.,
+    };
+    let drum_map = mapping.drum_map().unwrap();
+    assert_eq!(drum_map.len(), 4);
+    assert_eq!(
+        drum_map[2],
+        DrumSound {
+            name: "Slice 3".into(),
+            note: 38,
+        }
+    );
+    assert!(NoteLayout::with_drum_map(NoteRange::default(), drum_map).is_some());
+
+    // Each slice plays until the next one starts
+    assert_eq!(
+        mapping.pattern(),
+        vec![(0, 0., 1.), (1, 1., 1.5), (2, 1.5, 3.), (3, 3., 4.)]
+    );
+
+    // Slices can't be played past the highest MIDI note
+    let too_high = SliceMapping {
+        base_note: 126,
+        ..mapping
+    };
+    assert!(too_high.drum_map().is_none());
+}
+
+#[test]
+fn note_layouts_are_saved() {
+    let conf: MIDIEditorConf = serde_json::from_value(json!({
+        "bpm": 120.0,
+        "loop_start_mark_measure": null,
+        "loop_end_mark_measure": null,
+        "note_layout": {
+            "range": { "lowestNote": 24, "lineCount": 48 },
+            "foldedNotes": [40, 36],
+        },
+    }))
+    .unwrap();
+    assert_eq!(conf.note_layout.range, NoteRange::new(24, 48).unwrap());
+    assert_eq!(conf.note_layout.line_count(), 2);
+
+    // Saves from before the range could be changed use the default one
+    let conf: MIDIEditorConf = serde_json::from_value(json!({
+        "bpm": 120.0,
+        "loop_start_mark_measure": null,
+        "loop_end_mark_measure": null,
+    }))
+    .unwrap();
+    assert_eq!(conf.note_layout, NoteLayout::default());
+}
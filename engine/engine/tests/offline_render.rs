@@ -0,0 +1,157 @@
+extern crate audio_block;
+extern crate common;
+extern crate engine;
+
+use audio_block::{Block, BlockProcessor, Instrument, BLOCK_SIZE};
+use common::tempo_map::TempoMap;
+use engine::{
+    helpers::grid::skip_list::NoteEvent,
+    offline_render::*,
+    views::midi_editor::note_layout::{NoteLayout, NoteRange},
+};
+
+fn event(line_ix: usize, is_start: bool, beat: f32) -> NoteEvent {
+    NoteEvent {
+        line_ix,
+        is_start,
+        beat,
+        velocity: 100,
+    }
+}
+
+#[test]
+fn bounce_events_are_clipped_to_range() {
+    let events = vec![
+        // Starts before the range; skipped entirely
+        event(10, true, 0.),
+        event(5, true, 1.),
+        event(10, false, 2.),
+        event(5, false, 3.),
+        // Held past the end of the range; released at the end
+        event(7, true, 4.),
+        // Starts after the range
+        event(8, true, 6.),
+        event(7, false, 8.),
+        event(8, false, 8.),
+    ];
+    // Line 0 plays note 20
+    let note_layout = NoteLayout::unfolded(NoteRange::new(1, 20).unwrap());
+    // 60 BPM at 10 samples/second makes each beat 10 frames long
+    let tempo_map = TempoMap::new(60.);
+    let schedule = collect_bounce_events(events.into_iter(), &note_layout, &tempo_map, 10., 1., 5.);
+
+    assert_eq!(schedule.frame_count, 40);
+    let summary: Vec<(usize, bool, usize)> = schedule
+        .events
+        .iter()
+        .map(|event| (event.frame, event.is_attack, event.note_id))
+        .collect();
+    assert_eq!(summary, vec![
+        (0, true, 15),
+        (20, false, 15),
+        (30, true, 13),
+        (40, false, 13)
+    ]);
+}
+
+#[test]
+fn wav_encoding() {
+    let left = [0., 1., -1.];
+    let right = [0.5, 2., -0.5];
+    let wav = encode_wav(&[&left, &right], 44_100, WavBitDepth::TwentyFour);
+
+    assert_eq!(&wav[..4], b"RIFF");
+    assert_eq!(&wav[8..16], b"WAVEfmt ");
+    assert_eq!(&wav[22..24], &2u16.to_le_bytes());
+    assert_eq!(&wav[24..28], &44_100u32.to_le_bytes());
+    assert_eq!(&wav[34..36], &24u16.to_le_bytes());
+    assert_eq!(&wav[36..40], b"data");
+    assert_eq!(&wav[40..44], &18u32.to_le_bytes());
+    assert_eq!(wav.len(), 44 + 18);
+
+    let samples: Vec<i32> = wav[44..]
+        .chunks(3)
+        .map(|bytes| (i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]])) >> 8)
+        .collect();
+    assert_eq!(samples, vec![0, 4_194_304, 8_388_607, 8_388_607, -8_388_607, -4_194_304]);
+
+    let wav = encode_wav(&[&left], 48_000, WavBitDepth::Sixteen);
+    assert_eq!(&wav[44..], &[0, 0, 0xff, 0x7f, 0x01, 0x80]);
+}
+
+/// Outputs 1 on every frame that any of its voices are playing
+#[derive(Default)]
+struct GateInstrument {
+    playing_voices: Vec<usize>,
+    attacked_voices: Vec<usize>,
+}
+
+impl BlockProcessor for GateInstrument {
+    fn process(&mut self, block: &mut Block) { self.render(block, 0, BLOCK_SIZE) }
+}
+
+impl Instrument for GateInstrument {
+    fn trigger_attack(&mut self, voice_ix: usize, _note_id: usize, _velocity: u8) {
+        self.playing_voices.push(voice_ix);
+        self.attacked_voices.push(voice_ix);
+    }
+
+    fn trigger_release(&mut self, voice_ix: usize, _note_id: usize) {
+        self.playing_voices.retain(|&playing_voice_ix| playing_voice_ix != voice_ix);
+    }
+
+    fn render(&mut self, block: &mut Block, start: usize, end: usize) {
+        let level = if self.playing_voices.is_empty() { 0. } else { 1. };
+        for i in start..end {
+            block.left[i] = level;
+            block.right[i] = level;
+        }
+    }
+}
+
+fn bounce_event(frame: usize, is_attack: bool, note_id: usize) -> BounceEvent {
+    BounceEvent {
+        frame,
+        is_attack,
+        note_id,
+        velocity: 100,
+    }
+}
+
+#[test]
+fn bounce_applies_events_at_exact_frames() {
+    let schedule = BounceSchedule {
+        sample_rate: 100.,
+        frame_count: 200,
+        events: vec![
+            bounce_event(10, true, 60),
+            bounce_event(20, true, 62),
+            bounce_event(30, false, 60),
+            bounce_event(150, false, 62),
+        ],
+    };
+    let mut instrument = GateInstrument::default();
+    let [left, right] = render_bounce(&mut instrument, &schedule);
+
+    // Rendering stops after the first silent block past the end of the schedule
+    assert_eq!(left.len(), BLOCK_SIZE * 3);
+    assert_eq!(left, right);
+    let sounding: Vec<usize> = (0..left.len()).filter(|&i| left[i] == 1.).collect();
+    assert_eq!(sounding, (10..150).collect::<Vec<_>>());
+
+    // Overlapping notes are allocated separate voices by the voice manager
+    assert_eq!(instrument.attacked_voices.len(), 2);
+    assert_ne!(instrument.attacked_voices[0], instrument.attacked_voices[1]);
+}
+
+#[test]
+fn bounce_tail_is_limited() {
+    // Never released, so the output never goes silent
+    let schedule = BounceSchedule {
+        sample_rate: 10.,
+        frame_count: 50,
+        events: vec![bounce_event(0, true, 60)],
+    };
+    let [left, _] = render_bounce(&mut GateInstrument::default(), &schedule);
+    assert_eq!(left.len(), 150);
+}
@@ -0,0 +1,81 @@
+extern crate engine;
+
+use std::f32::consts::PI;
+
+use engine::views::oscilloscope::scope::{Scope, ScopeConf, TriggerMode};
+
+const SAMPLE_RATE: f32 = 48_000.;
+
+/// Pushes a sine wave with a period that isn't a whole number of samples in blocks, returning the
+/// frame displayed after each block
+fn push_sine(scope: &mut Scope, block_count: usize) -> Vec<Vec<f32>> {
+    let freq = 441.3;
+    let block_len = 1024;
+    (0..block_count)
+        .map(|block_ix| {
+            let block: Vec<f32> = (0..block_len)
+                .map(|i| {
+                    let t = (block_ix * block_len + i) as f32 / SAMPLE_RATE;
+                    (2. * PI * freq * t).sin() * 0.8
+                })
+                .collect();
+            scope.push_samples(&block);
+            scope.frame().to_vec()
+        })
+        .collect()
+}
+
+#[test]
+fn triggered_frames_are_stable() {
+    let mut scope = Scope::new(ScopeConf::default(), SAMPLE_RATE);
+    let frames = push_sine(&mut scope, 8);
+    let first = &frames[1];
+    assert!(first[0].abs() < 0.01);
+    assert!(first[1] > first[0]);
+    for frame in &frames[2..] {
+        for (a, b) in first.iter().zip(frame.iter()) {
+            assert!((a - b).abs() < 0.01);
+        }
+    }
+
+    // Free-running frames drift since blocks don't line up with the period
+    scope.set_conf(ScopeConf {
+        trigger_mode: TriggerMode::Free,
+        ..ScopeConf::default()
+    });
+    let frames = push_sine(&mut scope, 2);
+    assert!((frames[0][0] - frames[1][0]).abs() > 0.01);
+}
+
+#[test]
+fn falling_trigger_and_amplitude_scale() {
+    let conf = ScopeConf {
+        trigger_mode: TriggerMode::Falling,
+        trigger_level: 0.4,
+        amplitude_scale: 2.,
+        ..ScopeConf::default()
+    };
+    let mut scope = Scope::new(conf, SAMPLE_RATE);
+    let frames = push_sine(&mut scope, 4);
+    let frame = frames.last().unwrap();
+    assert!((frame[0] - 0.8).abs() < 0.01);
+    assert!(frame[1] < frame[0]);
+}
+
+#[test]
+fn untriggered_signals_are_still_displayed() {
+    let conf = ScopeConf {
+        trigger_level: 0.9,
+        ..ScopeConf::default()
+    };
+    let mut scope = Scope::new(conf, SAMPLE_RATE);
+    // 20ms frames are 960 samples long, so it takes a few blocks before the scope gives up
+    for _ in 0..3 {
+        scope.push_samples(&[0.5; 1024]);
+        assert!(scope.frame().iter().all(|&sample| sample == 0.));
+    }
+    for _ in 0..3 {
+        scope.push_samples(&[0.5; 1024]);
+    }
+    assert!(scope.frame().iter().all(|&sample| sample == 0.5));
+}
@@ -0,0 +1,80 @@
+extern crate common;
+extern crate engine;
+
+use common::pitch_bend::{
+    normalize_curve, pitch_bend_at, sample_curve, semitones_to_midi_pitch_bend, PitchBendPoint,
+};
+use engine::{
+    helpers::grid::note_box::{NoteBox, NoteBoxBounds, NoteId},
+    views::midi_editor::pitch_bend::get_note_pitch_bend_events,
+};
+
+fn point(beat_offset: f32, semitones: f32) -> PitchBendPoint {
+    PitchBendPoint {
+        beat_offset,
+        semitones,
+    }
+}
+
+#[test]
+fn bend_is_interpolated_from_the_start_of_the_note() {
+    let curve = vec![point(1., 2.), point(2., -2.)];
+
+    assert_eq!(pitch_bend_at(&curve, 0.), 0.);
+    assert_eq!(pitch_bend_at(&curve, 0.5), 1.);
+    assert_eq!(pitch_bend_at(&curve, 1.5), 0.);
+    // The last point is held after it's reached
+    assert_eq!(pitch_bend_at(&curve, 4.), -2.);
+    assert_eq!(pitch_bend_at(&[], 1.), 0.);
+}
+
+#[test]
+fn curves_are_sorted_and_cut_off_at_the_end_of_the_note() {
+    let mut curve = vec![point(3., 1.), point(0.5, 2.), point(-1., 1.), point(1., 0.)];
+    normalize_curve(&mut curve, 2.);
+    assert_eq!(curve, vec![point(0.5, 2.), point(1., 0.)]);
+}
+
+#[test]
+fn semitones_are_converted_to_midi_pitch_bends() {
+    assert_eq!(semitones_to_midi_pitch_bend(0.), 8192);
+    assert_eq!(semitones_to_midi_pitch_bend(-2.), 0);
+    assert_eq!(semitones_to_midi_pitch_bend(1.), 12288);
+    // Bends past the MIDI range are clamped
+    assert_eq!(semitones_to_midi_pitch_bend(12.), 16383);
+    assert_eq!(semitones_to_midi_pitch_bend(-12.), 0);
+}
+
+#[test]
+fn sampled_curves_include_their_points() {
+    let curve = vec![point(0.75, 3.)];
+    assert_eq!(
+        sample_curve(&curve, 4., 0.5),
+        vec![(0., 0.), (0.5, 2.), (0.75, 3.)]
+    );
+    assert!(sample_curve(&[], 4., 0.5).is_empty());
+}
+
+#[test]
+fn note_bend_events_stop_at_the_end_beat() {
+    let note = NoteBox {
+        id: NoteId::next(),
+        bounds: NoteBoxBounds {
+            start_beat: 4.,
+            end_beat: 8.,
+        },
+        data: 0usize,
+        velocity: 100,
+        pitch_bend: vec![point(0., 0.), point(1., 1.), point(3., -1.)],
+        expression: Default::default(),
+    };
+
+    assert_eq!(
+        get_note_pitch_bend_events(&note, f64::INFINITY),
+        vec![(4., 0.), (5., 1.), (7., -1.)]
+    );
+    assert_eq!(
+        get_note_pitch_bend_events(&note, 6.),
+        vec![(4., 0.), (5., 1.)]
+    );
+}
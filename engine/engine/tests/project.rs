@@ -0,0 +1,73 @@
+extern crate engine;
+extern crate uuid;
+
+use std::collections::BTreeMap;
+
+use engine::view_context::{
+    manager::{MinimalViewContextDefinition, SerializedWorkspace, ViewContextDefinition},
+    project::{Project, ProjectFileError, PROJECT_FILE_MAGIC, PROJECT_FILE_VERSION},
+};
+use uuid::Uuid;
+
+fn mk_project() -> Project {
+    let mut external_data = BTreeMap::new();
+    external_data.insert("synthDesigner_1".into(), "{\"synths\":[]}".into());
+    external_data.insert("grid_2".into(), "AAAA".into());
+
+    Project {
+        workspace: SerializedWorkspace {
+            view_contexts: vec![ViewContextDefinition {
+                minimal_def: MinimalViewContextDefinition {
+                    name: "synth_designer".into(),
+                    uuid: Uuid::from_u128(1),
+                    title: Some("Lead".into()),
+                },
+                conf: "{}".into(),
+            }],
+            active_view_ix: 0,
+            patch_network_connections: Vec::new(),
+            foreign_connectables: Vec::new(),
+            keymap_overrides: BTreeMap::new(),
+        },
+        external_data,
+    }
+}
+
+#[test]
+fn project_round_trip() {
+    let project = mk_project();
+    let bytes = project.encode();
+    assert!(bytes.starts_with(PROJECT_FILE_MAGIC));
+
+    let decoded = Project::decode(&bytes).expect("Failed to decode project");
+    assert_eq!(decoded.external_data, project.external_data);
+    assert_eq!(decoded.workspace.view_contexts.len(), 1);
+    let definition = &decoded.workspace.view_contexts[0];
+    assert_eq!(definition.minimal_def.uuid, Uuid::from_u128(1));
+    assert_eq!(definition.minimal_def.title.as_deref(), Some("Lead"));
+    assert_eq!(definition.conf, "{}");
+}
+
+#[test]
+fn invalid_project_files() {
+    match Project::decode(b"{\"workspace\":{}}") {
+        Err(ProjectFileError::NotAProjectFile) => (),
+        _ => panic!("Expected a file without the magic bytes to be rejected"),
+    }
+
+    let mut bytes = mk_project().encode();
+    bytes[PROJECT_FILE_MAGIC.len()..PROJECT_FILE_MAGIC.len() + 4]
+        .copy_from_slice(&(PROJECT_FILE_VERSION + 1).to_le_bytes());
+    match Project::decode(&bytes) {
+        Err(ProjectFileError::UnsupportedVersion(version)) =>
+            assert_eq!(version, PROJECT_FILE_VERSION + 1),
+        _ => panic!("Expected a file from a newer version to be rejected"),
+    }
+
+    let mut bytes = mk_project().encode();
+    bytes.truncate(bytes.len() - 1);
+    match Project::decode(&bytes) {
+        Err(ProjectFileError::InvalidContents(_)) => (),
+        _ => panic!("Expected a truncated file to be rejected"),
+    }
+}
@@ -0,0 +1,96 @@
+extern crate engine;
+
+use engine::{
+    sample_import::ImportedSample,
+    views::sample_editor::{
+        edits::{FadeCurve, SampleEdit},
+        waveform::{Peak, WaveformPeaks, BASE_BLOCK_SIZE},
+    },
+};
+
+fn mk_sample(channel: Vec<f32>) -> ImportedSample {
+    ImportedSample {
+        channels: vec![channel],
+        sample_rate: 44_100,
+    }
+}
+
+#[test]
+fn waveform_peaks_match_raw_samples_at_all_zoom_levels() {
+    // A ramp with a single spike in it
+    let mut channel: Vec<f32> = (0..100_000).map(|i| i as f32 / 100_000.).collect();
+    channel[54_321] = -1.;
+    let peaks = WaveformPeaks::new(&[channel.clone()]);
+
+    for &pixel_count in &[10, 100, 1000, 100_000, 200_000] {
+        let computed = peaks.peaks(0, &channel, 0, channel.len(), pixel_count);
+        assert_eq!(computed.len(), pixel_count);
+        // The spike must show up no matter how far out the view is zoomed
+        assert!(computed.iter().any(|peak| peak.min == -1.));
+        let overall_max = computed.iter().fold(0.0f32, |acc, peak| acc.max(peak.max));
+        assert_eq!(overall_max, channel[channel.len() - 1]);
+    }
+
+    // Zoomed in closer than the finest level, pixels come from the raw samples
+    let pixel_count = BASE_BLOCK_SIZE / 2;
+    let zoomed = peaks.peaks(0, &channel, 1000, 1000 + pixel_count, pixel_count);
+    assert_eq!(
+        zoomed[3],
+        Peak {
+            min: channel[1003],
+            max: channel[1003],
+        }
+    );
+}
+
+#[test]
+fn sample_edits() {
+    let mut sample = mk_sample(vec![0., 0.25, -0.5, 0.25, 0.]);
+
+    assert!(SampleEdit::Normalize {
+        start: 0,
+        end: 5,
+        peak_db: 0.,
+    }
+    .apply(&mut sample));
+    assert_eq!(sample.channels[0], vec![0., 0.5, -1., 0.5, 0.]);
+
+    assert!(SampleEdit::Reverse { start: 0, end: 3 }.apply(&mut sample));
+    assert_eq!(sample.channels[0], vec![-1., 0.5, 0., 0.5, 0.]);
+
+    assert!(SampleEdit::FadeIn {
+        start: 0,
+        end: 3,
+        curve: FadeCurve::Linear,
+    }
+    .apply(&mut sample));
+    assert_eq!(sample.channels[0], vec![0., 0.25, 0., 0.5, 0.]);
+
+    assert!(SampleEdit::Trim { start: 1, end: 4 }.apply(&mut sample));
+    assert_eq!(sample.channels[0], vec![0.25, 0., 0.5]);
+    // Ranges past the end of the sample don't do anything
+    assert!(!SampleEdit::Reverse { start: 5, end: 8 }.apply(&mut sample));
+}
+
+#[test]
+fn trimming_moves_loop_points() {
+    let trim = SampleEdit::Trim { start: 25, end: 75 };
+    assert_eq!(trim.adjust_loop_point(0.5, 100), 0.5);
+    assert_eq!(trim.adjust_loop_point(0.3, 100), 0.1);
+    // Loop points in the removed audio end up at the edges
+    assert_eq!(trim.adjust_loop_point(0.1, 100), 0.);
+    assert_eq!(trim.adjust_loop_point(0.9, 100), 1.);
+
+    let reverse = SampleEdit::Reverse { start: 25, end: 75 };
+    assert_eq!(reverse.adjust_loop_point(0.3, 100), 0.3);
+
+    // Stretching the second half to twice its length makes the sample 150 frames long
+    let stretch = SampleEdit::TimeStretch {
+        start: 50,
+        end: 100,
+        stretch: 2.,
+    };
+    assert_eq!(stretch.adjust_loop_point(0.3, 100), 0.2);
+    assert_eq!(stretch.adjust_loop_point(0.75, 100), 0.5 + 1. / 6.);
+    assert_eq!(stretch.adjust_loop_point(1., 100), 1.);
+}
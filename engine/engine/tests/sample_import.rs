@@ -0,0 +1,85 @@
+extern crate engine;
+
+use engine::{
+    offline_render::{encode_wav, WavBitDepth},
+    sample_import::{decode_wav, resample, ImportedSample, SampleImportError, SamplePool},
+};
+
+#[test]
+fn wav_round_trip() {
+    let left = [0., 0.5, -0.5, 1., -1.];
+    let right = [0.25; 5];
+    let bytes = encode_wav(&[&left, &right], 22_050, WavBitDepth::TwentyFour);
+
+    let sample = decode_wav(&bytes).unwrap();
+    assert_eq!(sample.sample_rate, 22_050);
+    assert_eq!(sample.channel_count(), 2);
+    assert_eq!(sample.len_frames(), 5);
+    for (decoded, expected) in sample.channels[0].iter().zip(left.iter()) {
+        assert!((decoded - expected).abs() < 1e-6);
+    }
+    assert!((sample.channels[1][3] - 0.25).abs() < 1e-6);
+
+    assert_eq!(
+        decode_wav(b"not a wav file"),
+        Err(SampleImportError::NotAWavFile)
+    );
+    // Header only, with no chunks
+    assert_eq!(
+        decode_wav(&bytes[..12]),
+        Err(SampleImportError::MissingChunk("fmt "))
+    );
+}
+
+#[test]
+fn resampling_preserves_level_and_duration() {
+    let sample = vec![0.5; 4410];
+    let upsampled = resample(&sample, 44_100, 48_000);
+    assert_eq!(upsampled.len(), 4800);
+    // Away from the edges, a constant signal should come through unchanged
+    assert!(upsampled[100..4700].iter().all(|s| (s - 0.5).abs() < 1e-3));
+
+    // A tone above the new Nyquist frequency should be filtered out when downsampling
+    let tone: Vec<f32> = (0..4800)
+        .map(|i| (i as f32 * 0.9 * std::f32::consts::PI).sin())
+        .collect();
+    let downsampled = resample(&tone, 48_000, 24_000);
+    assert_eq!(downsampled.len(), 2400);
+    assert!(downsampled[100..2300].iter().all(|s| s.abs() < 0.01));
+}
+
+#[test]
+fn sample_pool_accounting() {
+    let mk_sample = |len_frames: usize| {
+        ImportedSample::from_planar(&vec![0.; len_frames * 2], 2, 44_100).unwrap()
+    };
+    // Room for 100 stereo frames
+    let mut pool = SamplePool::new(100 * 2 * 4);
+
+    let kick = pool.register("kick.wav", mk_sample(60)).unwrap();
+    assert_eq!(pool.bytes_used(), 60 * 2 * 4);
+    // Registering the same sample again shares the existing entry
+    assert_eq!(pool.register("kick.wav", mk_sample(60)), Ok(kick));
+    assert_eq!(pool.acquire_existing("kick.wav", 44_100), Some(kick));
+    assert_eq!(pool.acquire_existing("kick.wav", 48_000), None);
+    assert_eq!(pool.bytes_used(), 60 * 2 * 4);
+
+    assert_eq!(
+        pool.register("snare.wav", mk_sample(50)),
+        Err(SampleImportError::PoolFull {
+            needed_bytes: 50 * 2 * 4,
+            available_bytes: 40 * 2 * 4,
+        })
+    );
+
+    // The sample is only freed once all three references have been released
+    assert!(pool.release(kick));
+    assert!(pool.release(kick));
+    assert_eq!(pool.len(), 1);
+    assert!(pool.release(kick));
+    assert!(pool.is_empty());
+    assert_eq!(pool.bytes_used(), 0);
+    assert!(!pool.release(kick));
+
+    assert!(pool.register("snare.wav", mk_sample(50)).is_ok());
+}
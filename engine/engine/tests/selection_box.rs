@@ -1,8 +1,11 @@
 extern crate engine;
 
 use engine::helpers::grid::{
-    note_box::{NoteBox, NoteBoxBounds},
+    lasso::Lasso,
+    note_box::{NoteBox, NoteBoxBounds, NoteId},
     selection_box::*,
+    skip_list::NoteLines,
+    GridConf,
 };
 
 fn test_selection_box_diff(
@@ -279,6 +282,34 @@ fn selection_region_from_mouse_coords() {
     check_region(10, 10, 20, 0, 10, 0, 10, 10);
 }
 
+#[test]
+fn selection_regions_can_be_diffed_above_and_left_of_the_grid() {
+    // Dragged up and to the left from `(1.5, 0.5)`, past the first beat and line
+    let original_box = SelectionRegion::from_points(1.5, 0.5, -0.5, -1.);
+    let new_box = SelectionRegion::from_points(1.5, 0.5, -1.5, -1.25);
+
+    let (retained_region, region_1, region_2) = original_box.diff(1.5, 0.5, &new_box);
+    assert_eq!(retained_region, Some(original_box.clone()));
+    assert_eq!(region_1, ChangedRegion {
+        was_added: true,
+        region: SelectionRegion {
+            x: -1.5,
+            y: -1.25,
+            width: 1.,
+            height: 1.75,
+        },
+    });
+    assert_eq!(region_2, ChangedRegion {
+        was_added: true,
+        region: SelectionRegion {
+            x: -0.5,
+            y: -1.25,
+            width: 2.,
+            height: 0.25,
+        },
+    });
+}
+
 #[test]
 fn note_box_self_intersection_exclusive() {
     let note_box = NoteBox {
@@ -287,6 +318,160 @@ fn note_box_self_intersection_exclusive() {
             end_beat: 10.0,
         },
         data: 0,
+        velocity: 100,
+        pitch_bend: Vec::new(),
+        expression: Default::default(),
+        id: NoteId::next(),
     };
     assert!(note_box.bounds.intersects_exclusive(&note_box.bounds));
 }
+
+fn mk_grid_conf() -> GridConf {
+    GridConf {
+        row_count: 8,
+        gutter_height: 0,
+        beat_length_px: 10,
+        note_snap_beat_interval: 1.,
+        cursor_gutter_height: 5,
+        line_border_width: 1,
+        line_height: 9,
+        grid_width: 1000,
+        measure_width_px: 40,
+        zoom_x: 1.,
+        zoom_y: 1.,
+        time_signature_changes: Vec::new(),
+    }
+}
+
+/// Inserts a note into `notes`, returning its ID
+fn insert_note(
+    notes: &mut NoteLines<usize>,
+    line_ix: usize,
+    start_beat: f32,
+    end_beat: f32,
+) -> NoteId {
+    let id = NoteId::next();
+    notes.insert(line_ix, NoteBox {
+        bounds: NoteBoxBounds {
+            start_beat,
+            end_beat,
+        },
+        data: 0,
+        velocity: 100,
+        pitch_bend: Vec::new(),
+        expression: Default::default(),
+        id,
+    });
+    id
+}
+
+#[test]
+fn notes_in_region_are_hit_tested_by_their_rendered_rects() {
+    let conf = mk_grid_conf();
+    let mut notes: NoteLines<usize> = NoteLines::new(conf.row_count);
+    let long_note = insert_note(&mut notes, 1, 0., 10.);
+    let short_note = insert_note(&mut notes, 2, 4., 5.);
+    insert_note(&mut notes, 2, 6., 7.);
+    insert_note(&mut notes, 5, 4., 5.);
+
+    // The region is entirely inside of the long note, so none of its corners are
+    let region = SelectionRegion {
+        x: 4.2,
+        y: 1.2,
+        width: 0.5,
+        height: 1.,
+    };
+    let hit_ids = get_note_ids_in_region(&conf, &notes, &region);
+    assert_eq!(hit_ids.len(), 2);
+    assert!(hit_ids.contains(&long_note));
+    assert!(hit_ids.contains(&short_note));
+}
+
+#[test]
+fn notes_spanning_the_origin_stay_selected_when_the_box_flips() {
+    let conf = mk_grid_conf();
+    let mut notes: NoteLines<usize> = NoteLines::new(conf.row_count);
+    let left_note = insert_note(&mut notes, 0, 2.5, 3.);
+    let spanning_note = insert_note(&mut notes, 1, 4., 6.);
+    let right_note = insert_note(&mut notes, 0, 7., 8.);
+
+    // The box is dragged from the left of the origin to the right of it
+    let selection_box = SelectionBoxData::compute(5., 0., 9., 1.5, 2., 1.5);
+    assert_eq!(selection_box.retained_region, None);
+
+    let mut changes: Vec<(NoteId, bool)> = iter_selection_changes(&conf, &notes, &selection_box)
+        .map(|(note_data, is_selected)| (note_data.note_box.id, is_selected))
+        .collect();
+    changes.sort();
+    changes.dedup();
+    assert_eq!(changes, vec![
+        (left_note, false),
+        (spanning_note, true),
+        (right_note, true)
+    ]);
+}
+
+#[test]
+fn selection_box_modes_are_relative_to_the_previous_selection() {
+    // (in_box, was_selected) for every combination
+    let cases = [(false, false), (false, true), (true, false), (true, true)];
+    let is_selected = |mode: SelectionBoxMode| -> Vec<bool> {
+        cases
+            .iter()
+            .map(|&(in_box, was_selected)| mode.is_selected(in_box, was_selected))
+            .collect()
+    };
+
+    assert_eq!(is_selected(SelectionBoxMode::Add), vec![false, true, true, true]);
+    assert_eq!(is_selected(SelectionBoxMode::Subtract), vec![false, true, false, false]);
+}
+
+#[test]
+fn lasso_selects_notes_whose_centers_are_inside_of_it() {
+    let conf = mk_grid_conf();
+    let mut notes: NoteLines<usize> = NoteLines::new(conf.row_count);
+    // A diagonal run of notes with a neighbor right next to each of them
+    let run = [
+        insert_note(&mut notes, 0, 0., 1.),
+        insert_note(&mut notes, 1, 1., 2.),
+        insert_note(&mut notes, 2, 2., 3.),
+    ];
+    insert_note(&mut notes, 0, 1., 2.);
+    insert_note(&mut notes, 1, 2., 3.);
+    insert_note(&mut notes, 2, 0., 1.);
+
+    // A thin shape along the diagonal that clips the edges of the neighbors
+    let mut lasso = Lasso::new(0., 0.);
+    for &(x, y) in &[(0.6, 0.), (3., 2.4), (3., 3.), (2.4, 3.), (0., 0.6)] {
+        lasso.push_point(x, y);
+    }
+    assert!(!lasso.contains_point(3.1, 0.2));
+
+    let mut selected: Vec<NoteId> = iter_notes_in_region(&conf, &notes, &lasso.bounding_region())
+        .filter(|note_data| lasso.contains_note(&conf, note_data))
+        .map(|note_data| note_data.note_box.id)
+        .collect();
+    selected.sort();
+    assert_eq!(selected, run.to_vec());
+}
+
+#[test]
+fn notes_starting_in_a_beat_range_are_found_on_every_line() {
+    let mut notes: NoteLines<usize> = NoteLines::new(8);
+    insert_note(&mut notes, 0, 0., 2.);
+    let starting_at_range_start = insert_note(&mut notes, 0, 2., 3.);
+    let inside = insert_note(&mut notes, 5, 3.5, 8.);
+    let held_into_range = insert_note(&mut notes, 7, 1., 5.);
+    insert_note(&mut notes, 3, 6., 7.);
+
+    let mut found: Vec<NoteId> = notes
+        .iter_starting_between(2., 6.)
+        .map(|note_data| note_data.note_box.id)
+        .collect();
+    found.sort();
+    assert_eq!(found, vec![starting_at_range_start, inside]);
+    assert!(!found.contains(&held_into_range));
+
+    let after_cursor = notes.iter_starting_between(3., f32::INFINITY).count();
+    assert_eq!(after_cursor, 2);
+}
@@ -1,12 +1,14 @@
-#![feature(box_syntax, nll, test)]
-
+extern crate common;
 extern crate engine;
 extern crate rand;
 extern crate rand_pcg;
-extern crate test;
 
 use std::num::NonZeroU32;
 
+use common::{
+    expression::{ExpressionPoint, NoteExpression},
+    pitch_bend::pitch_bend_at,
+};
 use engine::{
     helpers::grid::{note_box::NoteBox, skip_list::*},
     views::midi_editor::prelude::*,
@@ -22,6 +24,10 @@ fn mklines(notes: &[(f32, f32)]) -> NoteLines<usize> {
                 end_beat,
             },
             data: 0,
+            velocity: 100,
+            pitch_bend: Vec::new(),
+            expression: Default::default(),
+            id: NoteId::next(),
         })
     };
 
@@ -89,6 +95,81 @@ fn slab_key_size() {
     assert_eq!(s2, s3);
 }
 
+#[test]
+fn slab_key_round_trip() {
+    for &key in &[0usize, 1, 1024] {
+        assert_eq!(SlabKey::<NoteSkipListNode<usize>>::from(key).key(), key);
+    }
+}
+
+/// Exercises every code path that builds link arrays with notes that own heap memory.  Nothing in
+/// here touches uninitialized memory, so it can be run under Miri to check for undefined behavior.
+#[test]
+fn skiplist_notes_owning_heap_data() {
+    engine::init_rng();
+    let mkbox = |start_beat: f32| NoteBox {
+        bounds: NoteBoxBounds {
+            start_beat,
+            end_beat: start_beat + 1.0,
+        },
+        data: 0usize,
+        velocity: 100,
+        pitch_bend: vec![PitchBendPoint {
+            beat_offset: 0.5,
+            semitones: 1.0,
+        }],
+        expression: Default::default(),
+        id: NoteId::next(),
+    };
+
+    let mut lines = NoteLines::new(2);
+    for &start_beat in &[4.0, 0.0, 2.0, 8.0, 6.0] {
+        assert!(lines.insert(0, mkbox(start_beat)).is_none());
+    }
+    assert!(lines.lines[1]
+        .insert_sorted_batch(vec![mkbox(0.0), mkbox(2.0)])
+        .is_empty());
+
+    assert_eq!(lines.get_bounds(0, 5.5).bounds(), Some((5.0, Some(6.0))));
+    assert!(lines.get_bounds(0, 2.5).bounds().is_none());
+    assert_eq!(lines.remove(0, 4.0).unwrap().pitch_bend.len(), 1);
+    assert_eq!(lines.len(), 6);
+
+    lines.lines[1].clear();
+    assert_eq!(lines.len(), 4);
+    // The rest of the notes are dropped along with the lines
+}
+
+#[test]
+fn skiplist_seeded_levels_are_reproducible() {
+    let build = |seed: u64| {
+        let mut lines = NoteLines::with_seed(2, seed);
+        for i in 0..64 {
+            let line_ix = i % 2;
+            let start_beat = i as f32;
+            lines.insert(line_ix, NoteBox {
+                bounds: NoteBoxBounds {
+                    start_beat,
+                    end_beat: start_beat + 1.0,
+                },
+                data: 0usize,
+                velocity: 100,
+                pitch_bend: Vec::new(),
+                expression: Default::default(),
+                id: NoteId::next(),
+            });
+        }
+        lines
+            .lines
+            .iter()
+            .map(|line| format!("{:?}", line))
+            .collect::<Vec<_>>()
+    };
+
+    // Lists don't touch the global PRNG, so the same seed always produces the same shortcuts
+    assert_eq!(build(7), build(7));
+}
+
 #[test]
 fn skiplist_construction_iteration() {
     engine::init_rng();
@@ -101,6 +182,10 @@ fn skiplist_construction_iteration() {
                 end_beat,
             },
             data: 0,
+            velocity: 100,
+            pitch_bend: Vec::new(),
+            expression: Default::default(),
+            id: NoteId::next(),
         })
         .collect();
     for note in &notes {
@@ -121,7 +206,7 @@ fn skiplist_bulk_insertion() {
     for i in 0..500 {
         notes.push(((i * 2) as f32, ((i * 2) + 1) as f32));
     }
-    notes.shuffle(rng());
+    with_rng(|rng| notes.shuffle(rng));
 
     for (start_beat, end_beat) in notes {
         skip_list.insert(NoteBox {
@@ -130,16 +215,14 @@ fn skiplist_bulk_insertion() {
                 end_beat,
             },
             data: 0,
+            velocity: 100,
+            pitch_bend: Vec::new(),
+            expression: Default::default(),
+            id: NoteId::next(),
         });
     }
 }
 
-#[bench]
-fn skiplist_level_generation(b: &mut test::Bencher) {
-    engine::init_rng();
-    b.iter(get_skip_list_level)
-}
-
 #[test]
 fn skiplist_node_debug() {
     engine::init_rng();
@@ -154,8 +237,13 @@ fn skiplist_node_debug() {
                     end_beat: 30.0,
                 },
                 data: 0,
+                velocity: 100,
+                pitch_bend: Vec::new(),
+                expression: Default::default(),
+                id: NoteId::next(),
             },
             links: blank_shortcuts(),
+            prev: None,
         })
         .into();
 
@@ -166,17 +254,21 @@ fn skiplist_node_debug() {
                 end_beat: 10.0,
             },
             data: 0,
+            velocity: 100,
+            pitch_bend: Vec::new(),
+            expression: Default::default(),
+            id: NoteId::next(),
         },
         links: [Some(next_node_ptr), Some(next_node_ptr), None, None, None],
+        prev: None,
     };
     let node_key: SlabKey<NoteSkipListNode<usize>> = line.nodes.insert(node).into();
     let node: &NoteSkipListNode<usize> = line.get_node(node_key);
-    // pretend that we're inside of a full `SkipList` and initialize the global debug pointers
-    skip_list::create_skip_list_dbg_ptrs();
-    init_node_dbg_ptrs(node_key);
+    // pretend that we're inside of a full `SkipList` and initialize the debug pointers
+    let mut debug_ptrs = init_node_dbg_ptrs(node_key);
 
     let expected = "|0, 10|--\n|0, 10|--\n|0, 10|--\n|0, 10|->\n|0, 10|->";
-    let actual = format!("{}", line.debug_node(node));
+    let actual = format!("{}", line.debug_node(node, &mut debug_ptrs));
     println!("\nEXPECTED:\n{}", expected);
     println!("\nACTUAL:\n{}", actual);
     assert_eq!(expected, &actual);
@@ -195,6 +287,10 @@ fn skiplist_debug_fmt() {
                 end_beat: *end,
             },
             data: 0,
+            velocity: 100,
+            pitch_bend: Vec::new(),
+            expression: Default::default(),
+            id: NoteId::next(),
         })
         .collect::<Vec<_>>()[0..4];
     let [note_1_2, note_4_5, note_3_4, note_2_3] = match notes {
@@ -206,7 +302,13 @@ fn skiplist_debug_fmt() {
                   val: NoteBox<usize>,
                   links: [Option<SlabKey<NoteSkipListNode<usize>>>; NOTE_SKIP_LIST_LEVELS]|
      -> SlabKey<NoteSkipListNode<usize>> {
-        line.nodes.insert(NoteSkipListNode { val, links }).into()
+        line.nodes
+            .insert(NoteSkipListNode {
+                val,
+                links,
+                prev: None,
+            })
+            .into()
     };
 
     let node_4_5 = mknode(&mut skip_list, note_4_5.clone(), [
@@ -233,11 +335,10 @@ fn skiplist_debug_fmt() {
         Some(node_4_5),
         None,
     ]);
-    skip_list::create_skip_list_dbg_ptrs();
-    init_node_dbg_ptrs(head);
+    let mut debug_ptrs = init_node_dbg_ptrs(head);
     println!(
         "head: \n{:?}",
-        skip_list.debug_node(&skip_list.get_node(head))
+        skip_list.debug_node(&skip_list.get_node(head), &mut debug_ptrs)
     );
 
     // state().nodes are pre-linked, so all we have to do is insert the head.
@@ -274,6 +375,10 @@ fn skiplist_region_iter() {
     for (i, (line_ix, (start_beat, end_beat))) in notes.iter().enumerate() {
         lines.insert(*line_ix, NoteBox {
             data: i,
+            velocity: 100,
+            pitch_bend: Vec::new(),
+            expression: Default::default(),
+            id: NoteId::next(),
             bounds: NoteBoxBounds {
                 start_beat: *start_beat,
                 end_beat: *end_beat,
@@ -311,3 +416,799 @@ fn skiplist_region_iter() {
 
     assert_eq!(expected_results, actual_results);
 }
+
+#[test]
+fn skiplist_removal() {
+    engine::init_rng();
+    let mut lines = mklines(&[(1.0, 2.0), (2.0, 3.0), (4.0, 6.0), (7.0, 7.0), (8.0, 9.0)]);
+    let starts = |lines: &NoteLines<usize>| {
+        lines.lines[0]
+            .iter()
+            .map(|note| note.bounds.start_beat)
+            .collect::<Vec<_>>()
+    };
+
+    // Notes that don't exist aren't removed
+    assert!(lines.remove(0, 1.5).is_none());
+    assert!(lines.remove(0, 0.5).is_none());
+    assert!(lines.remove(0, 100.0).is_none());
+    assert_eq!(starts(&lines), vec![1.0, 2.0, 4.0, 7.0, 8.0]);
+
+    // middle, zero-width, head, and tail
+    assert_eq!(lines.remove(0, 4.0).unwrap().bounds.end_beat, 6.0);
+    assert_eq!(lines.remove(0, 7.0).unwrap().bounds.end_beat, 7.0);
+    assert_eq!(starts(&lines), vec![1.0, 2.0, 8.0]);
+    assert_eq!(lines.remove(0, 1.0).unwrap().bounds.end_beat, 2.0);
+    assert_eq!(starts(&lines), vec![2.0, 8.0]);
+    assert_eq!(lines.remove(0, 8.0).unwrap().bounds.end_beat, 9.0);
+    assert_eq!(starts(&lines), vec![2.0]);
+    assert_eq!(lines.get_bounds(0, 5.0).bounds(), Some((3.0, None)));
+    assert!(lines.remove(0, 2.0).is_some());
+    assert!(lines.lines[0].head_key.is_none());
+    assert!(lines.remove(0, 2.0).is_none());
+}
+
+#[test]
+fn skiplist_bulk_removal() {
+    engine::init_rng();
+    let mut skip_list = NoteSkipList::default();
+
+    let mut notes = Vec::with_capacity(500);
+    for i in 0..500 {
+        notes.push(((i * 2) as f32, ((i * 2) + 1) as f32));
+    }
+    with_rng(|rng| notes.shuffle(rng));
+    for &(start_beat, end_beat) in &notes {
+        let insertion_error = skip_list.insert(NoteBox {
+            bounds: NoteBoxBounds {
+                start_beat,
+                end_beat,
+            },
+            data: 0,
+            velocity: 100,
+            pitch_bend: Vec::new(),
+            expression: Default::default(),
+            id: NoteId::next(),
+        });
+        assert!(insertion_error.is_none());
+    }
+
+    let (removed, retained) = notes.split_at(250);
+    for &(start_beat, _) in removed {
+        assert!(skip_list.remove(start_beat).is_some());
+    }
+
+    let mut expected = retained.to_owned();
+    expected.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    let actual = skip_list
+        .iter()
+        .map(|note| (note.bounds.start_beat, note.bounds.end_beat))
+        .collect::<Vec<_>>();
+    assert_eq!(expected, actual);
+    // the slab slots of all removed notes should have been freed
+    assert_eq!(skip_list.nodes.len(), retained.len());
+
+    // Shortcuts must still lead to the right nodes after removal
+    for &(start_beat, end_beat) in retained {
+        assert_eq!(
+            skip_list
+                .iter_range(start_beat + 0.5, start_beat + 0.5)
+                .map(|note| note.bounds.end_beat)
+                .collect::<Vec<_>>(),
+            vec![end_beat]
+        );
+    }
+
+    // So must the back links
+    let mut reversed = actual;
+    reversed.reverse();
+    let actual_reversed = skip_list
+        .iter_rev_from(f32::INFINITY)
+        .map(|note| (note.bounds.start_beat, note.bounds.end_beat))
+        .collect::<Vec<_>>();
+    assert_eq!(reversed, actual_reversed);
+}
+
+#[test]
+fn skiplist_range_iter() {
+    engine::init_rng();
+    let lines = mklines(&[(1.0, 2.0), (2.0, 3.0), (4.0, 6.0), (8.0, 9.0), (12.0, 13.0)]);
+    let range = |start_beat: f32, end_beat: f32| {
+        lines.lines[0]
+            .iter_range(start_beat, end_beat)
+            .map(|note| note.bounds.start_beat)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(range(0.0, 0.5), Vec::<f32>::new());
+    assert_eq!(range(2.5, 7.0), vec![2.0, 4.0]);
+    assert_eq!(range(3.5, 3.9), Vec::<f32>::new());
+    assert_eq!(range(0.0, 100.0), vec![1.0, 2.0, 4.0, 8.0, 12.0]);
+    assert_eq!(range(9.5, 100.0), vec![12.0]);
+    assert_eq!(range(20.0, 100.0), Vec::<f32>::new());
+}
+
+#[test]
+fn note_lines_range_iter() {
+    engine::init_rng();
+    let mut lines = NoteLines::new(3);
+    for (line_ix, (start_beat, end_beat)) in &[(0, (0.0, 1.0)), (0, (6.0, 7.0)), (2, (3.0, 8.0))] {
+        lines.insert(*line_ix, NoteBox {
+            data: 0,
+            velocity: 100,
+            pitch_bend: Vec::new(),
+            expression: Default::default(),
+            id: NoteId::next(),
+            bounds: NoteBoxBounds {
+                start_beat: *start_beat,
+                end_beat: *end_beat,
+            },
+        });
+    }
+    let range = |start_beat: f32, end_beat: f32| {
+        lines
+            .iter_range(start_beat, end_beat)
+            .map(|note_data| (note_data.line_ix, note_data.note_box.bounds.start_beat))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(range(0.5, 2.0), vec![(0, 0.0)]);
+    // Notes that start before the range but are still held in it are included
+    assert_eq!(range(5.0, 6.5), vec![(0, 6.0), (2, 3.0)]);
+    assert_eq!(range(9.0, 20.0), vec![]);
+}
+
+#[test]
+fn note_lines_move_notes() {
+    engine::init_rng();
+    let mut lines = NoteLines::new(3);
+    for (line_ix, (start_beat, end_beat)) in &[(0, (0.0, 1.0)), (0, (1.0, 2.0)), (1, (4.0, 5.0))] {
+        lines.insert(*line_ix, NoteBox {
+            data: 0,
+            velocity: 100,
+            pitch_bend: Vec::new(),
+            expression: Default::default(),
+            id: NoteId::next(),
+            bounds: NoteBoxBounds {
+                start_beat: *start_beat,
+                end_beat: *end_beat,
+            },
+        });
+    }
+    let note_bounds = |lines: &NoteLines<usize>, line_ix: usize| {
+        lines.lines[line_ix]
+            .iter()
+            .map(|note| (note.bounds.start_beat, note.bounds.end_beat))
+            .collect::<Vec<_>>()
+    };
+
+    // Notes in the moved set can move into each other's space
+    assert_eq!(
+        lines.move_notes(&[(0, 0.0, 0, 1.0), (0, 1.0, 0, 2.0)]),
+        Ok(())
+    );
+    assert_eq!(note_bounds(&lines, 0), vec![(1.0, 2.0), (2.0, 3.0)]);
+
+    // A collision with a note outside of the moved set rolls back all of the moves
+    assert_eq!(
+        lines.move_notes(&[(0, 1.0, 2, 1.0), (0, 2.0, 1, 4.5)]),
+        Err(MoveError::Blocked(1))
+    );
+    assert_eq!(note_bounds(&lines, 0), vec![(1.0, 2.0), (2.0, 3.0)]);
+    assert_eq!(note_bounds(&lines, 1), vec![(4.0, 5.0)]);
+    assert_eq!(note_bounds(&lines, 2), vec![]);
+
+    assert_eq!(lines.move_note(1, 4.0, 2, 0.5), Ok(()));
+    assert_eq!(note_bounds(&lines, 1), vec![]);
+    assert_eq!(note_bounds(&lines, 2), vec![(0.5, 1.5)]);
+
+    // Moving a note that doesn't exist fails without changing anything
+    assert_eq!(
+        lines.move_note(1, 4.0, 0, 8.0),
+        Err(MoveError::NoteNotFound(0))
+    );
+    assert_eq!(note_bounds(&lines, 0), vec![(1.0, 2.0), (2.0, 3.0)]);
+}
+
+#[test]
+fn note_lines_get_by_id() {
+    engine::init_rng();
+    let mut lines = mklines(&[(1.0, 2.0), (4.0, 6.0)]);
+    let id = lines.lines[0].iter().nth(1).unwrap().id;
+    assert_eq!(lines.get_by_id(id).unwrap().1.bounds.start_beat, 4.0);
+
+    // IDs stay the same as notes are moved around
+    assert_eq!(lines.move_note(0, 4.0, 0, 8.0), Ok(()));
+    let (line_ix, note) = lines.get_by_id(id).unwrap();
+    assert_eq!((line_ix, note.bounds.start_beat), (0, 8.0));
+    assert_eq!(lines.move_note_horizontal(0, 8.0, -1.0), 7.0);
+    assert_eq!(lines.get_by_id(id).unwrap().1.bounds.start_beat, 7.0);
+    lines.remap_lines(2, |line_ix| Some(line_ix + 1));
+    let (line_ix, note) = lines.get_by_id(id).unwrap();
+    assert_eq!((line_ix, note.bounds.start_beat), (1, 7.0));
+
+    lines.remove(1, 7.0);
+    assert!(lines.get_by_id(id).is_none());
+
+    // Notes changed through the lines directly are still found
+    let id = lines.lines[1].head().unwrap().val.id;
+    lines.lines[1].head_mut().unwrap().val.bounds.start_beat = 0.5;
+    assert_eq!(lines.get_by_id(id).unwrap().1.bounds.start_beat, 0.5);
+}
+
+#[test]
+fn note_lines_iter_all() {
+    engine::init_rng();
+    let mut lines = NoteLines::new(3);
+    assert!(lines.is_empty());
+    assert_eq!(lines.iter_all().count(), 0);
+
+    for (line_ix, start_beat) in &[(2, 0.0), (0, 3.0), (0, 1.0)] {
+        lines.insert(*line_ix, NoteBox {
+            data: 0,
+            velocity: 100,
+            pitch_bend: Vec::new(),
+            expression: Default::default(),
+            id: NoteId::next(),
+            bounds: NoteBoxBounds {
+                start_beat: *start_beat,
+                end_beat: *start_beat + 1.0,
+            },
+        });
+    }
+    let all_notes: Vec<_> = lines
+        .iter_all()
+        .map(|(line_ix, note)| (line_ix, note.bounds.start_beat))
+        .collect();
+    assert_eq!(all_notes, vec![(0, 1.0), (0, 3.0), (2, 0.0)]);
+    assert_eq!(lines.len(), 3);
+    assert!(!lines.is_empty());
+    assert_eq!(lines.lines[0].len(), 2);
+    assert!(lines.lines[1].is_empty());
+
+    lines.remove(2, 0.0);
+    assert_eq!(lines.len(), 2);
+    assert!(lines.lines[2].is_empty());
+}
+
+#[test]
+fn note_lines_find_note_at() {
+    engine::init_rng();
+    let lines = mklines(&[(1.0, 2.0), (2.0, 3.0), (5.0, 6.0), (8.0, 9.0), (12.0, 16.0)]);
+    let start_beat_at = |beat: f32| {
+        lines
+            .find_note_at(0, beat)
+            .map(|note| note.bounds.start_beat)
+    };
+
+    assert_eq!(start_beat_at(0.5), None);
+    assert_eq!(start_beat_at(1.5), Some(1.0));
+    // The earlier note wins when two notes touch
+    assert_eq!(start_beat_at(2.0), Some(1.0));
+    assert_eq!(start_beat_at(2.5), Some(2.0));
+    assert_eq!(start_beat_at(4.0), None);
+    assert_eq!(start_beat_at(13.0), Some(12.0));
+    assert_eq!(start_beat_at(20.0), None);
+
+    let id = lines.lines[0].iter().nth(2).unwrap().id;
+    assert_eq!(lines.find_note_at(0, 5.5).unwrap().id, id);
+}
+
+#[test]
+fn note_lines_set_velocity() {
+    engine::init_rng();
+    let mut lines = mklines(&[(1.0, 2.0), (5.0, 6.0)]);
+
+    assert!(lines.set_velocity(0, 5.0, 40));
+    assert_eq!(lines.find_note_at(0, 5.5).unwrap().velocity, 40);
+    assert_eq!(lines.find_note_at(0, 1.5).unwrap().velocity, 100);
+    // Notes are only found by their exact start
+    assert!(!lines.set_velocity(0, 5.5, 40));
+}
+
+#[test]
+fn skiplist_insert_sorted_batch() {
+    engine::init_rng();
+    let mkbox = |start_beat: f32, end_beat: f32| NoteBox {
+        data: 0,
+        velocity: 100,
+        pitch_bend: Vec::new(),
+        expression: Default::default(),
+        id: NoteId::next(),
+        bounds: NoteBoxBounds {
+            start_beat,
+            end_beat,
+        },
+    };
+    let bounds = |line: &NoteSkipList<usize>| {
+        line.iter()
+            .map(|note| (note.bounds.start_beat, note.bounds.end_beat))
+            .collect::<Vec<_>>()
+    };
+
+    let mut line = NoteSkipList::default();
+    let notes: Vec<_> = (0..500).map(|i| mkbox(i as f32, i as f32 + 1.0)).collect();
+    assert!(line.insert_sorted_batch(notes).is_empty());
+    assert_eq!(line.len(), 500);
+    assert_eq!(
+        bounds(&line),
+        (0..500)
+            .map(|i| (i as f32, i as f32 + 1.0))
+            .collect::<Vec<_>>()
+    );
+    // The shortcuts built by the batch insert are usable by searches
+    assert_eq!(
+        line.find_note_at(250.5).map(|note| note.bounds.start_beat),
+        Some(250.0)
+    );
+    assert!(line.remove(250.0).is_some());
+    assert!(line.insert(mkbox(250.25, 250.75)).is_none());
+    assert_eq!(line.iter_range(249.5, 251.5).count(), 3);
+
+    // Intersecting notes are returned rather than inserted
+    let mut line = NoteSkipList::default();
+    let rejected =
+        line.insert_sorted_batch(vec![mkbox(0.0, 2.0), mkbox(1.0, 3.0), mkbox(2.0, 3.0)]);
+    assert_eq!(
+        rejected
+            .iter()
+            .map(|note| note.bounds.start_beat)
+            .collect::<Vec<_>>(),
+        vec![1.0]
+    );
+    assert_eq!(bounds(&line), vec![(0.0, 2.0), (2.0, 3.0)]);
+
+    // Unsorted notes and non-empty lists fall back to inserting one at a time
+    assert!(line
+        .insert_sorted_batch(vec![mkbox(6.0, 7.0), mkbox(4.0, 5.0)])
+        .is_empty());
+    assert_eq!(
+        bounds(&line),
+        vec![(0.0, 2.0), (2.0, 3.0), (4.0, 5.0), (6.0, 7.0)]
+    );
+}
+
+#[test]
+fn note_lines_clear() {
+    engine::init_rng();
+    let mut lines = mklines(&[(1.0, 2.0), (4.0, 6.0), (8.0, 9.0)]);
+    lines.clear();
+    assert!(lines.is_empty());
+    assert!(lines.lines[0].nodes.is_empty());
+
+    // The list is still usable after being cleared
+    assert!(lines
+        .insert(0, NoteBox {
+            data: 0,
+            velocity: 100,
+            pitch_bend: Vec::new(),
+            expression: Default::default(),
+            id: NoteId::next(),
+            bounds: NoteBoxBounds {
+                start_beat: 4.0,
+                end_beat: 5.0,
+            },
+        })
+        .is_none());
+    assert_eq!(lines.iter_all().count(), 1);
+}
+
+#[test]
+fn note_lines_remap_lines() {
+    engine::init_rng();
+    let mut lines = mklines(&[(1.0, 2.0), (4.0, 6.0)]);
+    let removed = lines.remap_lines(3, |line_ix| Some(line_ix + 2));
+    assert!(removed.is_empty());
+    assert_eq!(lines.lines.len(), 3);
+    let notes: Vec<(usize, f32)> = lines
+        .iter_all()
+        .map(|(line_ix, note)| (line_ix, note.bounds.start_beat))
+        .collect();
+    assert_eq!(notes, vec![(2, 1.0), (2, 4.0)]);
+
+    let removed = lines.remap_lines(1, |_| None);
+    assert!(lines.is_empty());
+    assert_eq!(removed.len(), 3);
+    assert_eq!(removed[2].iter().count(), 2);
+}
+
+#[test]
+fn skiplist_resize_note() {
+    engine::init_rng();
+    let mut lines = mklines(&[(1.0, 2.0), (4.0, 6.0), (8.0, 9.0)]);
+    let line = &mut lines.lines[0];
+
+    // Resizing is clamped to the neighboring notes
+    assert_eq!(
+        line.resize_note(4.0, 1.5, 10.0),
+        Some(NoteBoxBounds {
+            start_beat: 2.0,
+            end_beat: 8.0,
+        })
+    );
+    assert_eq!(
+        line.resize_note(2.0, 3.0, 5.0),
+        Some(NoteBoxBounds {
+            start_beat: 3.0,
+            end_beat: 5.0,
+        })
+    );
+    // The head can be extended back to the start of the line
+    assert_eq!(
+        line.resize_note(1.0, -1.0, 2.0),
+        Some(NoteBoxBounds {
+            start_beat: 0.0,
+            end_beat: 2.0,
+        })
+    );
+    // Resizes that would leave the note without any width are ignored
+    assert_eq!(
+        line.resize_note(8.0, 9.5, 9.5),
+        Some(NoteBoxBounds {
+            start_beat: 8.0,
+            end_beat: 9.0,
+        })
+    );
+    assert_eq!(line.resize_note(4.0, 4.0, 5.0), None);
+
+    let bounds = line
+        .iter()
+        .map(|note| (note.bounds.start_beat, note.bounds.end_beat))
+        .collect::<Vec<_>>();
+    assert_eq!(bounds, vec![(0.0, 2.0), (3.0, 5.0), (8.0, 9.0)]);
+}
+
+#[derive(Debug, PartialEq)]
+struct Marker {
+    beat: f32,
+    name: &'static str,
+}
+
+impl SkipListEntry for Marker {
+    fn bounds(&self) -> NoteBoxBounds {
+        NoteBoxBounds {
+            start_beat: self.beat,
+            end_beat: self.beat,
+        }
+    }
+}
+
+#[test]
+fn skiplist_holds_point_entries() {
+    let mut list: SkipList<Marker> = SkipList::with_seed(0);
+    for &(beat, name) in &[
+        (4.0, "chorus"),
+        (0.0, "intro"),
+        (8.0, "outro"),
+        (2.0, "verse"),
+    ] {
+        assert_eq!(list.insert(Marker { beat, name }), None);
+    }
+    // Two markers can't sit on the same beat
+    let duplicate = Marker {
+        beat: 4.0,
+        name: "bridge",
+    };
+    assert_eq!(
+        list.insert(duplicate).map(|marker| marker.name),
+        Some("bridge")
+    );
+
+    let names = list.iter().map(|marker| marker.name).collect::<Vec<_>>();
+    assert_eq!(names, vec!["intro", "verse", "chorus", "outro"]);
+    let names = list
+        .iter_range(1.0, 6.0)
+        .map(|marker| marker.name)
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["verse", "chorus"]);
+
+    assert_eq!(list.remove(2.0).map(|marker| marker.name), Some("verse"));
+    assert_eq!(list.len(), 3);
+}
+
+#[test]
+fn skiplist_iter_backwards() {
+    let marker = |beat: f32| Marker { beat, name: "" };
+    let beats_before = |list: &SkipList<Marker>, beat: f32| {
+        list.iter_rev_from(beat)
+            .map(|marker| marker.beat)
+            .collect::<Vec<_>>()
+    };
+
+    let mut list: SkipList<Marker> = SkipList::with_seed(0);
+    for &beat in &[5.0, 3.0, 8.0, 1.0, 6.0, 0.0, 9.0] {
+        assert_eq!(list.insert(marker(beat)), None);
+    }
+    assert_eq!(
+        beats_before(&list, f32::INFINITY),
+        vec![9.0, 8.0, 6.0, 5.0, 3.0, 1.0, 0.0]
+    );
+    assert_eq!(beats_before(&list, 5.5), vec![5.0, 3.0, 1.0, 0.0]);
+    assert_eq!(beats_before(&list, 6.0), vec![6.0, 5.0, 3.0, 1.0, 0.0]);
+    assert!(beats_before(&list, -1.0).is_empty());
+
+    // Back links are kept up to date when the head and inner nodes are removed
+    list.remove(0.0);
+    list.remove(6.0);
+    list.remove(9.0);
+    assert_eq!(beats_before(&list, f32::INFINITY), vec![8.0, 5.0, 3.0, 1.0]);
+
+    let mut list: SkipList<Marker> = SkipList::with_seed(0);
+    list.insert_sorted_batch((0..10).map(|beat| marker(beat as f32)).collect());
+    let expected = (0..=4).rev().map(|beat| beat as f32).collect::<Vec<_>>();
+    assert_eq!(beats_before(&list, 4.5), expected);
+}
+
+#[test]
+fn skiplist_cursor() {
+    let marker = |beat: f32| Marker { beat, name: "" };
+    let beats = |list: &SkipList<Marker>| list.iter().map(|marker| marker.beat).collect::<Vec<_>>();
+    let mut list: SkipList<Marker> = SkipList::with_seed(0);
+    list.insert_sorted_batch(vec![marker(0.0), marker(2.0), marker(4.0), marker(6.0)]);
+
+    let mut cursor = list.cursor_at(3.0);
+    assert_eq!(cursor.current().map(|marker| marker.beat), Some(2.0));
+    assert_eq!(cursor.next().map(|marker| marker.beat), Some(4.0));
+    assert_eq!(cursor.prev().map(|marker| marker.beat), Some(2.0));
+
+    // Entries can only be inserted between the current entry and the next one
+    assert!(cursor.insert_after(marker(3.0)).is_none());
+    assert_eq!(
+        cursor.insert_after(marker(5.0)).map(|marker| marker.beat),
+        Some(5.0)
+    );
+    assert_eq!(
+        cursor.insert_after(marker(1.0)).map(|marker| marker.beat),
+        Some(1.0)
+    );
+    assert_eq!(cursor.next().map(|marker| marker.beat), Some(3.0));
+
+    // Removing moves the cursor on to the following entry
+    assert_eq!(cursor.remove_current().map(|marker| marker.beat), Some(3.0));
+    assert_eq!(cursor.current().map(|marker| marker.beat), Some(4.0));
+    assert_eq!(cursor.next().map(|marker| marker.beat), Some(6.0));
+    assert_eq!(cursor.remove_current().map(|marker| marker.beat), Some(6.0));
+    assert!(cursor.current().is_none());
+    assert!(cursor.remove_current().is_none());
+
+    // Moving past the end wraps around
+    assert_eq!(cursor.next().map(|marker| marker.beat), Some(0.0));
+    assert!(cursor.prev().is_none());
+    assert_eq!(cursor.prev().map(|marker| marker.beat), Some(4.0));
+    assert_eq!(beats(&list), vec![0.0, 2.0, 4.0]);
+
+    let mut list: SkipList<Marker> = SkipList::with_seed(0);
+    let mut cursor = list.cursor_front();
+    assert!(cursor.current().is_none());
+    assert!(cursor.insert_after(marker(1.0)).is_none());
+    assert_eq!(cursor.next().map(|marker| marker.beat), Some(1.0));
+}
+
+#[test]
+fn skiplist_split_and_join() {
+    engine::init_rng();
+    let mut lines = mklines(&[(0.0, 4.0), (6.0, 8.0)]);
+    let bounds = |lines: &NoteLines<usize>| {
+        lines.lines[0]
+            .iter()
+            .map(|note| (note.bounds.start_beat, note.bounds.end_beat))
+            .collect::<Vec<_>>()
+    };
+    let original_id = lines.lines[0].head().unwrap().val.id;
+    lines.lines[0].head_mut().unwrap().val.pitch_bend = vec![
+        PitchBendPoint {
+            beat_offset: 1.0,
+            semitones: 2.0,
+        },
+        PitchBendPoint {
+            beat_offset: 3.0,
+            semitones: -2.0,
+        },
+    ];
+
+    // Splits have to be strictly inside of a note
+    assert_eq!(lines.lines[0].split_at(4.0, 1), None);
+    assert_eq!(lines.lines[0].split_at(5.0, 1), None);
+    let new_id = lines.lines[0].split_at(2.0, 1).unwrap();
+    assert_eq!(bounds(&lines), vec![(0.0, 2.0), (2.0, 4.0), (6.0, 8.0)]);
+    let first_half = lines.find_note(0, 0.0).unwrap();
+    let second_half = lines.find_note(0, 2.0).unwrap();
+    assert_eq!(first_half.id, original_id);
+    assert_eq!((second_half.id, second_half.data), (new_id, 1));
+    // Both halves bend the same way that the original note did
+    for &(offset, semitones) in &[(0.5, 1.0), (1.0, 2.0), (2.0, 0.0)] {
+        assert_eq!(pitch_bend_at(&first_half.pitch_bend, offset), semitones);
+    }
+    for &(offset, semitones) in &[(0.0, 0.0), (0.5, -1.0), (1.0, -2.0), (2.0, -2.0)] {
+        assert_eq!(pitch_bend_at(&second_half.pitch_bend, offset), semitones);
+    }
+
+    // Only neighboring notes can be joined
+    assert!(lines.lines[0].join(0.0, 6.0).is_none());
+    assert!(lines.lines[0].join(0.0, 3.0).is_none());
+    let removed = lines.lines[0].join(6.0, 2.0).unwrap();
+    assert_eq!(removed.bounds.start_beat, 6.0);
+    assert_eq!(bounds(&lines), vec![(0.0, 2.0), (2.0, 8.0)]);
+    let removed = lines.lines[0].join(0.0, 2.0).unwrap();
+    assert_eq!(removed.id, new_id);
+    assert_eq!(bounds(&lines), vec![(0.0, 8.0)]);
+    let joined = lines.find_note(0, 0.0).unwrap();
+    assert_eq!(joined.id, original_id);
+    for &(offset, semitones) in &[(1.0, 2.0), (2.0, 0.0), (3.0, -2.0), (7.0, 0.0)] {
+        assert_eq!(pitch_bend_at(&joined.pitch_bend, offset), semitones);
+    }
+}
+
+#[test]
+fn skiplist_join_keeps_expression() {
+    engine::init_rng();
+    let point = |beat_offset: f32, value: f32| ExpressionPoint { beat_offset, value };
+    let mut lines = NoteLines::new(1);
+    let mut mknote = |start_beat: f32, end_beat: f32, expression: NoteExpression| {
+        lines.insert(0, NoteBox {
+            bounds: NoteBoxBounds {
+                start_beat,
+                end_beat,
+            },
+            data: 0usize,
+            velocity: 100,
+            pitch_bend: Vec::new(),
+            expression,
+            id: NoteId::next(),
+        })
+    };
+    mknote(0.0, 2.0, NoteExpression {
+        pressure: vec![point(0.0, 0.2), point(1.0, 0.4)],
+        timbre: vec![point(0.5, 1.0)],
+    });
+    mknote(3.0, 5.0, NoteExpression {
+        pressure: vec![point(0.0, 0.6)],
+        timbre: vec![point(1.0, 0.0)],
+    });
+
+    lines.lines[0].join(0.0, 3.0).unwrap();
+    let joined = lines.find_note(0, 0.0).unwrap();
+    assert_eq!(joined.bounds.end_beat, 5.0);
+    assert_eq!(joined.expression, NoteExpression {
+        pressure: vec![point(0.0, 0.2), point(1.0, 0.4), point(3.0, 0.6)],
+        timbre: vec![point(0.5, 1.0), point(4.0, 0.0)],
+    });
+}
+
+#[test]
+fn note_lines_insert_with_policy() {
+    engine::init_rng();
+    let mknote = |start_beat: f32, end_beat: f32| NoteBox {
+        bounds: NoteBoxBounds {
+            start_beat,
+            end_beat,
+        },
+        data: 0,
+        velocity: 100,
+        pitch_bend: Vec::new(),
+        expression: Default::default(),
+        id: NoteId::next(),
+    };
+    let bounds = |lines: &NoteLines<usize>| {
+        lines.lines[0]
+            .iter()
+            .map(|note| (note.bounds.start_beat, note.bounds.end_beat))
+            .collect::<Vec<_>>()
+    };
+    let existing_notes = &[(0.0, 2.0), (3.0, 5.0), (6.0, 7.0), (8.0, 9.0)];
+
+    let mut lines = mklines(existing_notes);
+    let insertion = lines.insert_with_policy(0, mknote(4.0, 8.0), OverlapPolicy::Reject);
+    assert!(insertion.rejected.is_some());
+    assert!(insertion.truncated.is_empty() && insertion.removed.is_empty());
+    assert_eq!(bounds(&lines), existing_notes.to_vec());
+    // Notes that only touch don't overlap
+    let insertion = lines.insert_with_policy(0, mknote(2.0, 3.0), OverlapPolicy::Reject);
+    assert!(insertion.rejected.is_none());
+
+    let mut lines = mklines(existing_notes);
+    let truncated_id = lines.find_note(0, 3.0).unwrap().id;
+    let insertion = lines.insert_with_policy(0, mknote(4.0, 8.0), OverlapPolicy::Truncate);
+    assert!(insertion.rejected.is_none());
+    assert_eq!(insertion.truncated, vec![(truncated_id, NoteBoxBounds {
+        start_beat: 3.0,
+        end_beat: 4.0,
+    })]);
+    let removed = insertion
+        .removed
+        .iter()
+        .map(|note| note.bounds.start_beat)
+        .collect::<Vec<_>>();
+    assert_eq!(removed, vec![6.0]);
+    assert_eq!(
+        bounds(&lines),
+        vec![(0.0, 2.0), (3.0, 4.0), (4.0, 8.0), (8.0, 9.0)]
+    );
+
+    let mut lines = mklines(existing_notes);
+    let insertion = lines.insert_with_policy(0, mknote(4.0, 8.5), OverlapPolicy::Replace);
+    assert!(insertion.rejected.is_none() && insertion.truncated.is_empty());
+    let removed = insertion
+        .removed
+        .iter()
+        .map(|note| note.bounds.start_beat)
+        .collect::<Vec<_>>();
+    assert_eq!(removed, vec![3.0, 6.0, 8.0]);
+    assert_eq!(bounds(&lines), vec![(0.0, 2.0), (4.0, 8.5)]);
+}
+
+#[test]
+fn note_lines_transpose_and_shift() {
+    engine::init_rng();
+    let mut lines = NoteLines::new(3);
+    for (line_ix, (start_beat, end_beat)) in &[(1, (0.0, 1.0)), (1, (2.0, 3.0)), (0, (2.0, 4.0))] {
+        lines.insert(*line_ix, NoteBox {
+            data: 0,
+            velocity: 100,
+            pitch_bend: Vec::new(),
+            expression: Default::default(),
+            id: NoteId::next(),
+            bounds: NoteBoxBounds {
+                start_beat: *start_beat,
+                end_beat: *end_beat,
+            },
+        });
+    }
+    let select = |lines: &NoteLines<usize>, line_ix: usize, start_beat: f32| {
+        SelectedNoteData::from_note_box(line_ix, lines.find_note(line_ix, start_beat).unwrap())
+    };
+    let note_bounds = |lines: &NoteLines<usize>, line_ix: usize| {
+        lines.lines[line_ix]
+            .iter()
+            .map(|note| (note.bounds.start_beat, note.bounds.end_beat))
+            .collect::<Vec<_>>()
+    };
+
+    // The note at beat 2 collides with the note above it, so neither note is moved
+    let selection = vec![select(&lines, 1, 0.0), select(&lines, 1, 2.0)];
+    assert_eq!(lines.transpose(&selection, 1), Err(MoveError::Blocked(1)));
+    assert_eq!(note_bounds(&lines, 0), vec![(2.0, 4.0)]);
+    assert_eq!(note_bounds(&lines, 1), vec![(0.0, 1.0), (2.0, 3.0)]);
+
+    // Notes can't be moved off of the grid
+    assert_eq!(lines.transpose(&selection, -2), Err(MoveError::OffGrid(0)));
+    assert_eq!(lines.transpose(&selection, -1), Ok(()));
+    assert_eq!(note_bounds(&lines, 1), vec![]);
+    assert_eq!(note_bounds(&lines, 2), vec![(0.0, 1.0), (2.0, 3.0)]);
+
+    let selection = vec![select(&lines, 2, 0.0), select(&lines, 2, 2.0)];
+    assert_eq!(lines.shift(&selection, -0.5), Err(MoveError::OffGrid(0)));
+    assert_eq!(lines.shift(&selection, 1.0), Ok(()));
+    assert_eq!(note_bounds(&lines, 2), vec![(1.0, 2.0), (3.0, 4.0)]);
+}
+
+#[test]
+fn note_lines_insert_group() {
+    engine::init_rng();
+    let mut lines = NoteLines::new(3);
+    let mknote = |start_beat: f32| NoteBox {
+        data: 0,
+        velocity: 100,
+        pitch_bend: Vec::new(),
+        expression: Default::default(),
+        id: NoteId::next(),
+        bounds: NoteBoxBounds {
+            start_beat,
+            end_beat: start_beat + 1.0,
+        },
+    };
+    lines.insert(2, mknote(1.5));
+
+    // The note on line 2 blocks the whole group
+    let rejected = lines
+        .insert_group(vec![(0, mknote(1.0)), (1, mknote(1.0)), (2, mknote(1.0))])
+        .unwrap();
+    assert_eq!(rejected.len(), 3);
+    assert_eq!(lines.len(), 1);
+
+    assert!(lines
+        .insert_group(vec![(0, mknote(3.0)), (1, mknote(3.0)), (2, mknote(3.0))])
+        .is_none());
+    assert_eq!(lines.len(), 4);
+    assert!(lines.find_note(1, 3.0).is_some());
+}
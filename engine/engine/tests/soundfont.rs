@@ -0,0 +1,222 @@
+extern crate engine;
+
+use engine::{
+    sample_import::get_sample_pool,
+    soundfont::{load_preset, LoopMode, SoundFont, SoundFontError},
+};
+
+fn chunk(id: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut bytes = id.to_vec();
+    bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        bytes.push(0);
+    }
+    bytes
+}
+
+fn list(list_type: &[u8], chunks: &[Vec<u8>]) -> Vec<u8> {
+    let mut data = list_type.to_vec();
+    data.extend(chunks.concat());
+    chunk(b"LIST", &data)
+}
+
+fn name(name: &str) -> Vec<u8> {
+    let mut bytes = name.as_bytes().to_vec();
+    bytes.resize(20, 0);
+    bytes
+}
+
+fn u16s(vals: &[u16]) -> Vec<u8> { vals.iter().flat_map(|val| val.to_le_bytes()).collect() }
+
+fn u32s(vals: &[u32]) -> Vec<u8> { vals.iter().flat_map(|val| val.to_le_bytes()).collect() }
+
+fn range(lo: u8, hi: u8) -> u16 { lo as u16 | (hi as u16) << 8 }
+
+/// Generator records, with each zone's generators followed by the terminal record
+fn gens(gens: &[(u16, i16)]) -> Vec<u8> {
+    gens.iter()
+        .chain(std::iter::once(&(0, 0)))
+        .flat_map(|&(generator, amount)| u16s(&[generator, amount as u16]))
+        .collect()
+}
+
+fn bags(gen_ixs: &[u16]) -> Vec<u8> {
+    gen_ixs
+        .iter()
+        .flat_map(|&gen_ix| u16s(&[gen_ix, 0]))
+        .collect()
+}
+
+fn preset_header(preset_name: &str, program: u16, bank: u16, bag_ix: u16) -> Vec<u8> {
+    let mut record = name(preset_name);
+    record.extend(u16s(&[program, bank, bag_ix]));
+    record.extend(u32s(&[0, 0, 0]));
+    record
+}
+
+fn sample_header(
+    sample_name: &str,
+    start: u32,
+    end: u32,
+    (loop_start, loop_end): (u32, u32),
+    sample_rate: u32,
+    original_pitch: u8,
+    pitch_correction: i8,
+) -> Vec<u8> {
+    let mut record = name(sample_name);
+    record.extend(u32s(&[start, end, loop_start, loop_end, sample_rate]));
+    record.extend_from_slice(&[original_pitch, pitch_correction as u8]);
+    // Sample link and a sample type of mono
+    record.extend(u16s(&[0, 1]));
+    record
+}
+
+/// Builds a SoundFont with a piano and a drum kit preset that both play the same instrument, which
+/// has a looping sine and a noise sample split across the keyboard
+fn build_soundfont() -> Vec<u8> {
+    let samples: Vec<u16> = (0..200).map(|i| (i * 100) as u16).collect();
+
+    let phdr = [
+        preset_header("Piano", 0, 0, 0),
+        preset_header("Drums", 0, 128, 2),
+        preset_header("EOP", 0, 0, 3),
+    ]
+    .concat();
+    let pgen = gens(&[
+        // Piano's global zone
+        (48, 60),
+        (43, range(40, 127) as i16),
+        (51, 1),
+        // Only valid in instrument zones, so this is ignored
+        (0, 50),
+        (41, 0),
+        // Drums
+        (41, 0),
+    ]);
+    let inst = [name("Inst"), u16s(&[0]), name("EOI"), u16s(&[3])].concat();
+    let igen = gens(&[
+        // Global zone
+        (34, 0),
+        (38, -1200),
+        (43, range(0, 63) as i16),
+        (54, 1),
+        (53, 0),
+        (43, range(64, 127) as i16),
+        (44, range(0, 100) as i16),
+        (58, 70),
+        (0, 10),
+        (53, 1),
+    ]);
+    let shdr = [
+        sample_header("Sine", 0, 100, (10, 90), 22_050, 60, -5),
+        sample_header("Noise", 100, 200, (0, 0), 44_100, 255, 0),
+        sample_header("EOS", 0, 0, (0, 0), 0, 0, 0),
+    ]
+    .concat();
+
+    let sfbk = [
+        b"sfbk".to_vec(),
+        list(b"INFO", &[chunk(b"ifil", &u16s(&[2, 1]))]),
+        list(b"sdta", &[chunk(b"smpl", &u16s(&samples))]),
+        list(
+            b"pdta",
+            &[
+                chunk(b"phdr", &phdr),
+                chunk(b"pbag", &bags(&[0, 1, 5, 6])),
+                chunk(b"pmod", &[0; 10]),
+                chunk(b"pgen", &pgen),
+                chunk(b"inst", &inst),
+                chunk(b"ibag", &bags(&[0, 2, 5, 10])),
+                chunk(b"imod", &[0; 10]),
+                chunk(b"igen", &igen),
+                chunk(b"shdr", &shdr),
+            ],
+        ),
+    ]
+    .concat();
+    chunk(b"RIFF", &sfbk)
+}
+
+#[test]
+fn soundfont_parsing() {
+    let font = SoundFont::parse("test.sf2", &build_soundfont()).unwrap();
+
+    let presets: Vec<_> = font
+        .presets()
+        .into_iter()
+        .map(|preset| (preset.name, preset.bank, preset.program))
+        .collect();
+    assert_eq!(
+        presets,
+        vec![("Piano".to_owned(), 0, 0), ("Drums".to_owned(), 128, 0)]
+    );
+
+    assert_eq!(font.samples.len(), 2);
+    let noise = font.sample_data(1);
+    assert_eq!(noise.len(), 100);
+    assert!((noise[1] - 10_100. / 32768.).abs() < 1e-6);
+
+    assert_eq!(
+        SoundFont::parse("test.wav", b"RIFF\0\0\0\0WAVE").err(),
+        Some(SoundFontError::NotASoundFont)
+    );
+    assert_eq!(
+        font.preset_regions(0, 1),
+        Err(SoundFontError::PresetNotFound {
+            bank: 0,
+            program: 1
+        })
+    );
+}
+
+#[test]
+fn preset_zones_are_resolved_into_regions() {
+    let font = SoundFont::parse("test.sf2", &build_soundfont()).unwrap();
+    let regions = font.preset_regions(0, 0).unwrap();
+    assert_eq!(regions.len(), 2);
+
+    // The preset's key range is intersected with the instrument's, and its tuning and attenuation
+    // are added to the instrument's
+    let sine = &regions[0];
+    assert_eq!(
+        (sine.key_lo, sine.key_hi, sine.vel_lo, sine.vel_hi),
+        (40, 63, 0, 127)
+    );
+    assert_eq!(sine.sample_ix, 0);
+    assert_eq!((sine.start, sine.end), (0, 100));
+    assert_eq!(sine.loop_mode, LoopMode::Continuous);
+    assert_eq!((sine.loop_start, sine.loop_end), (10, 90));
+    assert_eq!(sine.root_key, 60);
+    assert_eq!(sine.tune_cents, 95.);
+    assert!((sine.gain - 10f32.powf(-60. / 200.)).abs() < 1e-6);
+    // Envelope times come from the instrument's global zone
+    assert!((sine.attack - 1.).abs() < 1e-6);
+    assert!((sine.release - 0.5).abs() < 1e-6);
+
+    // Positions are relative to the start of the sample, and the preset's start offset is ignored
+    let noise = &regions[1];
+    assert_eq!(
+        (noise.key_lo, noise.key_hi, noise.vel_lo, noise.vel_hi),
+        (64, 127, 0, 100)
+    );
+    assert_eq!(noise.sample_ix, 1);
+    assert_eq!((noise.start, noise.end), (10, 100));
+    assert_eq!(noise.loop_mode, LoopMode::NoLoop);
+    assert_eq!(noise.root_key, 70);
+
+    // Loading a preset indexes its regions into the samples that it loaded into the pool
+    let preset = load_preset(&font, 128, 0).unwrap();
+    assert_eq!(preset.samples.len(), 2);
+    assert_eq!(preset.regions[1].sample_ix, 1);
+    assert_eq!(preset.samples[0].sample_rate, 22_050);
+    let pool = get_sample_pool();
+    assert_eq!(
+        pool.get(preset.samples[1].pool_id).unwrap().len_frames(),
+        100
+    );
+    for sample in preset.samples {
+        assert!(pool.release(sample.pool_id));
+    }
+    assert!(pool.is_empty());
+}
@@ -0,0 +1,94 @@
+extern crate engine;
+
+use std::f32::consts::PI;
+
+use engine::{
+    dsp::fft::fft_in_place,
+    views::spectrum_analyzer::analyzer::{Analyzer, AnalyzerConf, MIN_DB},
+};
+
+#[test]
+fn fft_of_impulse_is_flat() {
+    let mut re = vec![0.; 64];
+    let mut im = vec![0.; 64];
+    re[0] = 1.;
+    fft_in_place(&mut re, &mut im);
+    for (re, im) in re.iter().zip(im.iter()) {
+        assert!((re - 1.).abs() < 1e-5);
+        assert!(im.abs() < 1e-5);
+    }
+}
+
+#[test]
+fn fft_of_cosine_peaks_at_its_bin() {
+    let len = 256;
+    let mut re: Vec<f32> = (0..len)
+        .map(|i| (2. * PI * 10. * i as f32 / len as f32).cos())
+        .collect();
+    let mut im = vec![0.; len];
+    fft_in_place(&mut re, &mut im);
+    for i in 0..len {
+        let magnitude = re[i].hypot(im[i]);
+        if i == 10 || i == len - 10 {
+            assert!((magnitude - len as f32 / 2.).abs() < 1e-2);
+        } else {
+            assert!(magnitude < 1e-2, "bin {} has magnitude {}", i, magnitude);
+        }
+    }
+}
+
+#[test]
+fn analyzer_bins_sine_by_frequency() {
+    let sample_rate = 48_000.;
+    let conf = AnalyzerConf {
+        fft_size: 4096,
+        smoothing: 0.,
+        bin_count: 64,
+        ..AnalyzerConf::default()
+    };
+    let mut analyzer = Analyzer::new(conf, sample_rate);
+    assert!(analyzer.bins().iter().all(|&db| db == MIN_DB));
+
+    let freq = 1000.;
+    let samples: Vec<f32> = (0..8192)
+        .map(|i| (2. * PI * freq * i as f32 / sample_rate).sin())
+        .collect();
+    analyzer.push_samples(&samples);
+
+    let bins = analyzer.bins();
+    let loudest_ix = (0..bins.len())
+        .max_by(|&a, &b| bins[a].partial_cmp(&bins[b]).unwrap())
+        .unwrap();
+    let frequencies = analyzer.bin_frequencies();
+    let bin_ratio = frequencies[1] / frequencies[0];
+    assert!(
+        frequencies[loudest_ix] / bin_ratio < freq && freq < frequencies[loudest_ix] * bin_ratio
+    );
+    // A full-scale sine reads close to 0 dB; the Hann window loses at most ~1.5 dB between bins
+    assert!(
+        bins[loudest_ix] > -2. && bins[loudest_ix] < 0.5,
+        "peak was {}",
+        bins[loudest_ix]
+    );
+    // Far away from the sine, there's nothing but leakage
+    assert!(bins[0] < -60.);
+    assert!(bins[bins.len() - 1] < -60.);
+}
+
+#[test]
+fn analyzer_conf_is_sanitized() {
+    let conf = AnalyzerConf {
+        fft_size: 3000,
+        smoothing: 4.,
+        bin_count: 0,
+        min_freq: 500.,
+        max_freq: 100.,
+        ..AnalyzerConf::default()
+    };
+    let analyzer = Analyzer::new(conf, 44_100.);
+    let conf = analyzer.conf();
+    assert_eq!(conf.fft_size, 4096);
+    assert_eq!(conf.smoothing, 0.99);
+    assert_eq!(conf.bin_count, 16);
+    assert_eq!(conf.max_freq, 1000.);
+}
@@ -0,0 +1,23 @@
+extern crate engine;
+
+use engine::storage::{get_storage, set_storage, MemoryStorage};
+
+#[test]
+fn state_is_saved_to_the_storage_that_was_set() {
+    set_storage(Box::new(MemoryStorage::default()));
+    let storage = get_storage();
+    assert_eq!(storage.get("vc_1"), None);
+
+    storage.set("vc_2", "b");
+    storage.set("vc_1", "a");
+    storage.set("vc_1", "c");
+    assert_eq!(get_storage().get("vc_1"), Some("c".to_owned()));
+    assert_eq!(
+        get_storage().list_keys(),
+        vec!["vc_1".to_owned(), "vc_2".to_owned()]
+    );
+
+    get_storage().delete("vc_2");
+    assert_eq!(get_storage().get("vc_2"), None);
+    assert_eq!(get_storage().list_keys(), vec!["vc_1".to_owned()]);
+}
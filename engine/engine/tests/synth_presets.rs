@@ -0,0 +1,97 @@
+extern crate engine;
+extern crate serde_json;
+
+use engine::views::synth_designer::{mod_matrix::*, presets::*};
+
+fn user_preset(name: &str, unison: usize) -> SynthPreset {
+    SynthPreset {
+        name: name.into(),
+        patch: SynthPatch {
+            modules: vec![SynthModulePatch {
+                unison,
+                ..SynthModulePatch::default()
+            }],
+            mod_routes: vec![ModRouteDefinition {
+                source: ModSource::ModWheel,
+                destination: "synth_0_detune".into(),
+                depth: 50.,
+            }],
+        },
+    }
+}
+
+#[test]
+fn synth_module_patches_match_the_js_format() {
+    let serialized = r#"{
+        "unison": 2,
+        "waveform": "sawtooth",
+        "detune": 5,
+        "filter": { "type": "lowpass", "frequency": 4400, "detune": 0, "Q": 0.001 },
+        "masterGain": 0,
+        "selectedEffectType": "reverb",
+        "gainEnvelope": {
+            "attack": { "pos": 0.04, "magnitude": 0.8 },
+            "decay": { "pos": 0.14, "magnitude": 0.35 },
+            "release": { "pos": 0.9, "magnitude": 0.35 }
+        },
+        "gainADSRLength": 1000,
+        "filterEnvelope": {
+            "attack": { "pos": 0.04, "magnitude": 0.8 },
+            "decay": { "pos": 0.14, "magnitude": 0.35 },
+            "release": { "pos": 0.9, "magnitude": 0.35 }
+        },
+        "filterADSRLength": 1200
+    }"#;
+    let patch: SynthModulePatch = serde_json::from_str(serialized).unwrap();
+    assert_eq!(patch, SynthModulePatch {
+        waveform: Waveform::Sawtooth,
+        unison: 2,
+        detune: 5.,
+        ..SynthModulePatch::default()
+    });
+
+    let reserialized = serde_json::to_value(&patch).unwrap();
+    assert_eq!(reserialized["filter"]["Q"], 0.001);
+    assert_eq!(reserialized["gainEnvelope"]["decay"]["pos"], 0.14);
+    assert_eq!(reserialized["filterADSRLength"], 1200.);
+    // Parameters that the filter type doesn't use are left out
+    assert!(reserialized["filter"].get("gain").is_none());
+}
+
+#[test]
+fn preset_bank() {
+    let mut bank = SynthPresetBank::default();
+    let factory_count = factory_presets().len();
+    assert_eq!(bank.list().len(), factory_count);
+    assert!(bank.list().iter().all(|listing| listing.is_factory));
+    assert_eq!(bank.get("Init").unwrap().patch.modules.len(), 1);
+
+    bank.save(user_preset("Lead", 2)).unwrap();
+    bank.save(user_preset("Keys", 1)).unwrap();
+    // Saving with an existing name replaces the preset
+    bank.save(user_preset("Lead", 3)).unwrap();
+    let listings = bank.list();
+    assert_eq!(listings.len(), factory_count + 2);
+    assert_eq!(listings[factory_count], SynthPresetListing {
+        name: "Lead".into(),
+        is_factory: false,
+    });
+    assert_eq!(bank.get("Lead").unwrap(), user_preset("Lead", 3));
+
+    assert_eq!(
+        bank.save(user_preset("Init", 1)),
+        Err(SavePresetError::FactoryPresetName)
+    );
+    assert_eq!(bank.save(user_preset(" ", 1)), Err(SavePresetError::EmptyName));
+
+    assert!(bank.delete("Lead"));
+    assert!(!bank.delete("Lead"));
+    assert!(!bank.delete("Init"));
+    assert!(bank.get("Lead").is_none());
+    assert_eq!(bank.list().len(), factory_count + 1);
+
+    // User presets are saved along with the synth designer
+    let serialized = serde_json::to_string(&bank).unwrap();
+    let deserialized: SynthPresetBank = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(deserialized.get("Keys").unwrap(), user_preset("Keys", 1));
+}
@@ -0,0 +1,52 @@
+extern crate common;
+
+use common::tempo_map::{TempoMap, TimeSignature};
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+}
+
+#[test]
+fn beat_seconds_conversion_follows_tempo_changes() {
+    // 4 beats at 120 BPM followed by 60 BPM
+    let mut tempo_map = TempoMap::new(120.);
+    tempo_map.set_tempo(4., 60.);
+
+    assert_close(tempo_map.beat_to_seconds(2.), 1.);
+    assert_close(tempo_map.beat_to_seconds(4.), 2.);
+    assert_close(tempo_map.beat_to_seconds(6.), 4.);
+    assert_close(tempo_map.seconds_to_beat(1.), 2.);
+    assert_close(tempo_map.seconds_to_beat(4.), 6.);
+    assert_close(tempo_map.beats_to_seconds_from(3., 2.), 1.5);
+    assert_close(tempo_map.seconds_to_beats_from(3., 1.5), 2.);
+
+    assert!(tempo_map.remove_tempo_change(4.));
+    assert!(!tempo_map.remove_tempo_change(0.));
+    assert_close(tempo_map.beat_to_seconds(6.), 3.);
+}
+
+#[test]
+fn measures_follow_time_signature_changes() {
+    // One measure of 4/4, then 6/8 from beat 4, then 3/4 from beat 8.5 which cuts the second 6/8
+    // measure short
+    let mut tempo_map = TempoMap::default();
+    tempo_map.set_time_signature(4., TimeSignature::new(6, 8).unwrap());
+    tempo_map.set_time_signature(8.5, TimeSignature::new(3, 4).unwrap());
+    assert!(TimeSignature::new(3, 5).is_none());
+
+    assert_close(tempo_map.next_measure_start(0.), 0.);
+    assert_close(tempo_map.next_measure_start(0.5), 4.);
+    assert_close(tempo_map.next_measure_start(5.), 7.);
+    assert_close(tempo_map.next_measure_start(7.5), 8.5);
+    assert_close(tempo_map.next_measure_start(9.), 11.5);
+
+    let measure_starts: Vec<f64> = tempo_map
+        .grid_lines(12.)
+        .into_iter()
+        .filter(|line| line.is_measure_start)
+        .map(|line| line.beat)
+        .collect();
+    assert_eq!(measure_starts, vec![0., 4., 7., 8.5, 11.5]);
+    // 4 beats of 4/4, 9 eighth notes of 6/8, and 4 beats of 3/4 before beat 12
+    assert_eq!(tempo_map.grid_lines(12.).len(), 4 + 9 + 4);
+}
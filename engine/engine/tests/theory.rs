@@ -0,0 +1,75 @@
+extern crate common;
+
+use common::theory::{Chord, ChordShape, Key, Scale};
+
+#[test]
+fn notes_are_checked_against_the_key() {
+    // D major: D E F# G A B C#
+    let key = Key {
+        tonic: 2,
+        scale: Scale::Major,
+    };
+    assert!(key.contains(62));
+    assert!(key.contains(66));
+    assert!(!key.contains(60));
+    assert_eq!(key.degree(62), Some(0));
+    assert_eq!(key.degree(61), Some(6));
+    assert_eq!(key.degree(65), None);
+
+    // The default key is chromatic and so contains every note
+    assert!((0..128).all(|note| Key::default().contains(note)));
+}
+
+#[test]
+fn notes_snap_to_the_nearest_note_in_the_key() {
+    let key = Key {
+        tonic: 9,
+        scale: Scale::MinorPentatonic,
+    };
+    // A C D E G
+    assert_eq!(key.nearest_in_key(57), 57);
+    assert_eq!(key.nearest_in_key(58), 57);
+    assert_eq!(key.nearest_in_key(59), 60);
+    // F is a semitone above E and two below G
+    assert_eq!(key.nearest_in_key(65), 64);
+    // C# is equally close to C and D, so the lower note wins
+    assert_eq!(key.nearest_in_key(61), 60);
+}
+
+#[test]
+fn chords_are_rooted_at_zero() {
+    for &chord in &[Chord::Major, Chord::Minor7, Chord::Sus4, Chord::Diminished7] {
+        assert_eq!(chord.intervals()[0], 0);
+    }
+    assert_eq!(Chord::Dominant7.intervals(), &[0, 4, 7, 10]);
+}
+
+#[test]
+fn chord_shapes_build_notes_from_the_root() {
+    let c_major = Key {
+        tonic: 0,
+        scale: Scale::Major,
+    };
+
+    // The diatonic triad on D in C major is D minor
+    let triad = ChordShape::Diatonic { note_count: 3 };
+    assert_eq!(triad.notes(&c_major, 62), vec![62, 65, 69]);
+    // Roots outside of the key are moved into it first, and chords wrap into the next octave
+    let seventh = ChordShape::Diatonic { note_count: 4 };
+    assert_eq!(seventh.notes(&c_major, 70), vec![69, 72, 76, 79]);
+    // Roots in the lowest octave below the tonic still work
+    let a_minor = Key {
+        tonic: 9,
+        scale: Scale::NaturalMinor,
+    };
+    assert_eq!(triad.notes(&a_minor, 5), vec![5, 9, 12]);
+
+    let fixed = ChordShape::Fixed {
+        chord: Chord::Dominant7,
+    };
+    assert_eq!(fixed.notes(&c_major, 60), vec![60, 64, 67, 70]);
+    let custom = ChordShape::Custom {
+        intervals: vec![-12, 0, 7],
+    };
+    assert_eq!(custom.notes(&c_major, 5), vec![5, 12]);
+}
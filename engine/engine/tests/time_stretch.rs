@@ -0,0 +1,57 @@
+extern crate engine;
+
+use std::f32::consts::PI;
+
+use engine::dsp::time_stretch::{pitch_shift, time_stretch};
+
+const SAMPLE_RATE: u32 = 44_100;
+
+fn sine(freq: f32, len: usize) -> Vec<f32> {
+    (0..len)
+        .map(|i| (2. * PI * freq * i as f32 / SAMPLE_RATE as f32).sin() * 0.5)
+        .collect()
+}
+
+/// Estimates the frequency of a sine wave from how often it crosses zero, skipping the edges
+fn estimate_freq(samples: &[f32]) -> f32 {
+    let middle = &samples[samples.len() / 4..samples.len() * 3 / 4];
+    let crossings = middle
+        .windows(2)
+        .filter(|pair| (pair[0] < 0.) != (pair[1] < 0.))
+        .count();
+    crossings as f32 / 2. / (middle.len() as f32 / SAMPLE_RATE as f32)
+}
+
+fn rms(samples: &[f32]) -> f32 {
+    (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+#[test]
+fn time_stretching_keeps_pitch_and_level() {
+    let input = sine(440., SAMPLE_RATE as usize);
+    for &stretch in &[0.5, 0.8, 1.25, 2.] {
+        let stretched = time_stretch(&[input.clone()], SAMPLE_RATE, stretch);
+        assert_eq!(
+            stretched[0].len(),
+            (input.len() as f64 * stretch).round() as usize
+        );
+        assert!((estimate_freq(&stretched[0]) - 440.).abs() < 5.);
+        assert!((rms(&stretched[0]) - rms(&input)).abs() < 0.03);
+    }
+}
+
+#[test]
+fn pitch_shifting_keeps_length() {
+    let input = sine(440., SAMPLE_RATE as usize);
+    let stereo = vec![input.clone(), input.clone()];
+
+    let up = pitch_shift(&stereo, SAMPLE_RATE, 12.);
+    assert_eq!(up.len(), 2);
+    assert_eq!(up[0].len(), input.len());
+    assert!((estimate_freq(&up[0]) - 880.).abs() < 10.);
+    // Both channels are processed identically
+    assert_eq!(up[0], up[1]);
+
+    let down = pitch_shift(&stereo, SAMPLE_RATE, -7.);
+    assert!((estimate_freq(&down[0]) - 440. * 2f32.powf(-7. / 12.)).abs() < 5.);
+}
@@ -0,0 +1,30 @@
+extern crate engine;
+
+use engine::helpers::grid::touch::{TouchAction, Touches};
+
+#[test]
+fn a_second_touch_cancels_the_first_and_starts_a_pinch() {
+    let mut touches = Touches::default();
+    assert_eq!(touches.start(1, 100, 50), TouchAction::MouseDown(100, 50));
+    assert_eq!(touches.move_to(1, 110, 50), TouchAction::MouseMove(110, 50));
+    assert_eq!(touches.start(2, 150, 50), TouchAction::CancelMouse(100, 50));
+
+    // Spreading the fingers apart zooms in and bringing them together zooms out.  Their midpoint
+    // moves right both times, which scrolls back towards the start of the grid.
+    assert_eq!(touches.move_to(2, 190, 50), TouchAction::Pinch {
+        zoom_factor: 2.,
+        scroll_px: -20,
+    });
+    assert_eq!(touches.move_to(1, 150, 50), TouchAction::Pinch {
+        zoom_factor: 0.5,
+        scroll_px: -20,
+    });
+
+    // Lifting the fingers doesn't draw anything, even when one of them is left down for a while
+    assert_eq!(touches.end(2, 190, 50), TouchAction::None);
+    assert_eq!(touches.move_to(1, 80, 50), TouchAction::None);
+    assert_eq!(touches.end(1, 80, 50), TouchAction::None);
+
+    assert_eq!(touches.start(3, 10, 10), TouchAction::MouseDown(10, 10));
+    assert_eq!(touches.end(3, 10, 10), TouchAction::MouseUp(10, 10));
+}
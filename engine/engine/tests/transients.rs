@@ -0,0 +1,40 @@
+extern crate engine;
+
+use std::f32::consts::PI;
+
+use engine::dsp::transients::{detect_transients, TransientDetectionConf};
+
+const SAMPLE_RATE: u32 = 44_100;
+
+/// Builds a loop of decaying tone bursts that start at each of `onsets`
+fn mk_loop(onsets: &[usize], len: usize) -> Vec<f32> {
+    let mut samples = vec![0.; len];
+    for (hit_ix, &onset) in onsets.iter().enumerate() {
+        let freq = 200. + 150. * hit_ix as f32;
+        for (i, sample) in samples[onset..].iter_mut().enumerate() {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            *sample += (2. * PI * freq * t).sin() * (-t * 30.).exp() * 0.8;
+        }
+    }
+    samples
+}
+
+#[test]
+fn detects_the_onsets_of_hits() {
+    let onsets = [0, 11_025, 22_050, 27_562, 33_075];
+    let samples = mk_loop(&onsets, 44_100);
+    let detected = detect_transients(&[samples], SAMPLE_RATE, &TransientDetectionConf::default());
+    assert_eq!(detected.len(), onsets.len());
+    for (&detected, &onset) in detected.iter().zip(onsets.iter()) {
+        assert!((detected as isize - onset as isize).abs() < 64);
+    }
+}
+
+#[test]
+fn steady_tones_have_no_transients_after_their_start() {
+    let samples: Vec<f32> = (0..44_100)
+        .map(|i| (2. * PI * 440. * i as f32 / SAMPLE_RATE as f32).sin() * 0.5)
+        .collect();
+    let detected = detect_transients(&[samples], SAMPLE_RATE, &TransientDetectionConf::default());
+    assert_eq!(detected, vec![0]);
+}
@@ -0,0 +1,129 @@
+extern crate common;
+
+use common::tuning::*;
+
+const MEANTONE_SCL: &str = "! meanquar.scl
+!
+1/4-comma meantone scale. Pietro Aaron's temperament (1523)
+ 12
+!
+ 76.04900
+ 193.15686
+ 310.26471
+ 5/4
+ 503.42157
+ 579.47057
+ 696.57843
+ 25/16
+ 889.73529
+ 1006.84314
+ 1082.89214
+ 2/1
+";
+
+fn assert_close(actual: Option<f64>, expected: f64) {
+    let actual = actual.expect("Expected the note to be mapped");
+    assert!(
+        (actual - expected).abs() < 0.001,
+        "Expected {} to be close to {}",
+        actual,
+        expected
+    );
+}
+
+#[test]
+fn twelve_tet_matches_the_default_tuning() {
+    let tuning = Tuning::twelve_tet();
+    for note in 0..MIDI_NOTE_COUNT as u8 {
+        let expected = 2f64.powf((note as f64 - 69.) / 12.) * 440.;
+        assert_close(tuning.frequency(note), expected);
+    }
+    assert_eq!(tuning.build_table().len(), MIDI_NOTE_COUNT);
+}
+
+#[test]
+fn scala_scales_are_parsed() {
+    let scale = ScalaScale::parse(MEANTONE_SCL).unwrap();
+    assert_eq!(
+        scale.description,
+        "1/4-comma meantone scale. Pietro Aaron's temperament (1523)"
+    );
+    assert_eq!(scale.degrees.len(), 12);
+    assert!((scale.degrees[3] - 386.3137).abs() < 0.001);
+    assert_eq!(scale.period(), 1200.);
+    // Degrees past the end of the scale are in the next period
+    assert!((scale.degree_cents(16) - (1200. + 386.3137)).abs() < 0.001);
+    assert_eq!(scale.degree_cents(-12), -1200.);
+
+    assert_eq!(
+        ScalaScale::parse("Empty\n0\n"),
+        Err(TuningParseError::EmptyScale)
+    );
+    assert_eq!(
+        ScalaScale::parse("Truncated\n3\n100.\n200.\n"),
+        Err(TuningParseError::UnexpectedEnd)
+    );
+    assert_eq!(
+        ScalaScale::parse("Bad ratio\n2\n100.\n3/0\n"),
+        Err(TuningParseError::InvalidValue {
+            line: 4,
+            value: "3/0".into()
+        })
+    );
+}
+
+#[test]
+fn keyboard_mappings() {
+    // A pentatonic mapping of the white keys, with the reference note on A
+    let kbm = "! Size of map
+12
+! First and last notes
+0
+127
+! Middle note
+60
+! Reference note and frequency
+69
+432.0
+! Octave degree
+12
+! Mapping
+0
+x
+2
+x
+4
+x
+x
+7
+x
+9
+";
+    let mapping = KeyboardMapping::parse(kbm).unwrap();
+    assert_eq!(mapping.mapping.len(), 12);
+    assert_eq!(mapping.mapping[0], Some(0));
+    assert_eq!(mapping.mapping[1], None);
+    // Entries missing from the end of the file are unmapped
+    assert_eq!(mapping.mapping[11], None);
+
+    let tuning = TuningConf {
+        scl: MEANTONE_SCL.into(),
+        kbm: Some(kbm.into()),
+    }
+    .parse()
+    .unwrap();
+    assert_close(tuning.frequency(69), 432.);
+    assert_eq!(tuning.frequency(61), None);
+    assert_eq!(tuning.build_table()[61], 0.);
+    // E is a pure major third above C in quarter-comma meantone
+    let c = tuning.frequency(60).unwrap();
+    assert_close(tuning.frequency(64), c * 5. / 4.);
+    assert_close(tuning.frequency(76), c * 5. / 2.);
+
+    let unmapped_reference = kbm.replace("\n69\n", "\n61\n");
+    let conf = TuningConf {
+        scl: MEANTONE_SCL.into(),
+        kbm: Some(unmapped_reference),
+    };
+    assert_eq!(conf.parse(), Err(TuningParseError::UnmappedReferenceNote));
+}
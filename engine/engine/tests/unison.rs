@@ -0,0 +1,84 @@
+extern crate engine;
+extern crate polysynth;
+extern crate serde_json;
+
+use engine::views::synth_designer::presets::SynthModulePatch;
+use polysynth::unison::*;
+
+#[test]
+fn unison_layout_spreads_copies_evenly() {
+    let conf = UnisonConf {
+        voices: 3,
+        detune_cents: 20.,
+        stereo_spread: 0.5,
+    };
+    assert_eq!(conf.layout(16), vec![
+        UnisonVoice {
+            detune_cents: -10.,
+            pan: -0.5,
+        },
+        UnisonVoice {
+            detune_cents: 0.,
+            pan: 0.,
+        },
+        UnisonVoice {
+            detune_cents: 10.,
+            pan: 0.5,
+        },
+    ]);
+
+    // A single copy is always centered
+    let conf = UnisonConf {
+        voices: 1,
+        ..conf
+    };
+    assert_eq!(conf.layout(16), vec![UnisonVoice {
+        detune_cents: 0.,
+        pan: 0.,
+    }]);
+}
+
+#[test]
+fn unison_is_limited_by_polyphony() {
+    let conf = UnisonConf {
+        voices: 32,
+        ..UnisonConf::default()
+    };
+    assert_eq!(conf.voice_count(1), MAX_UNISON_VOICES);
+    assert_eq!(conf.voice_count(16), MAX_UNISON_OSCILLATORS / 16);
+    assert_eq!(conf.layout(16).len(), MAX_UNISON_OSCILLATORS / 16);
+    // Every voice gets at least one oscillator no matter how many there are
+    assert_eq!(conf.voice_count(MAX_UNISON_OSCILLATORS * 2), 1);
+}
+
+#[test]
+fn unison_params_default_for_old_patches() {
+    let patch: SynthModulePatch = serde_json::from_value(serde_json::json!({
+        "unison": 4,
+        "waveform": "sawtooth",
+        "detune": 0,
+        "filter": { "type": "lowpass", "frequency": 4400, "detune": 0, "Q": 0.001 },
+        "masterGain": 0,
+        "selectedEffectType": "reverb",
+        "gainEnvelope": {
+            "attack": { "pos": 0.04, "magnitude": 0.8 },
+            "decay": { "pos": 0.14, "magnitude": 0.35 },
+            "release": { "pos": 0.9, "magnitude": 0.35 }
+        },
+        "gainADSRLength": 1000,
+        "filterEnvelope": {
+            "attack": { "pos": 0.04, "magnitude": 0.8 },
+            "decay": { "pos": 0.14, "magnitude": 0.35 },
+            "release": { "pos": 0.9, "magnitude": 0.35 }
+        },
+        "filterADSRLength": 1200
+    }))
+    .unwrap();
+    assert_eq!(patch.unison_detune, 0.);
+    assert_eq!(patch.unison_spread, 0.);
+    assert!(!patch.unison_phase_randomization);
+
+    let reserialized = serde_json::to_value(&patch).unwrap();
+    assert_eq!(reserialized["unisonSpread"], 0.);
+    assert_eq!(reserialized["unisonPhaseRandomization"], false);
+}
@@ -0,0 +1,38 @@
+extern crate common;
+extern crate engine;
+
+use common::{MAX_NOTE_VELOCITY, MIN_NOTE_VELOCITY};
+use engine::views::midi_editor::{
+    constants::{LANE_MARGIN_PX, VELOCITY_LANE_HEIGHT_PX},
+    velocity_lane::{ramp_velocity, y_to_velocity},
+};
+
+#[test]
+fn lane_heights_map_to_velocities() {
+    assert_eq!(y_to_velocity(LANE_MARGIN_PX), MAX_NOTE_VELOCITY);
+    assert_eq!(
+        y_to_velocity(LANE_MARGIN_PX + VELOCITY_LANE_HEIGHT_PX),
+        MIN_NOTE_VELOCITY
+    );
+    // Positions outside of the lane are clamped to it
+    assert_eq!(y_to_velocity(0), MAX_NOTE_VELOCITY);
+    assert_eq!(y_to_velocity(1000), MIN_NOTE_VELOCITY);
+    assert_eq!(
+        y_to_velocity(LANE_MARGIN_PX + VELOCITY_LANE_HEIGHT_PX / 2),
+        64
+    );
+}
+
+#[test]
+fn ramps_interpolate_between_their_ends() {
+    let (start, end) = ((100, 20), (200, 120));
+    assert_eq!(ramp_velocity(start, end, 100), 20);
+    assert_eq!(ramp_velocity(start, end, 150), 70);
+    assert_eq!(ramp_velocity(start, end, 200), 120);
+    // Ramps can be drawn from right to left
+    assert_eq!(ramp_velocity(end, start, 125), 45);
+    // Notes past the ends get the velocity of the closest end
+    assert_eq!(ramp_velocity(start, end, 50), 20);
+    assert_eq!(ramp_velocity(start, end, 300), 120);
+    assert_eq!(ramp_velocity(start, start, 300), 20);
+}
@@ -0,0 +1,271 @@
+extern crate engine;
+extern crate polysynth;
+extern crate serde_json;
+extern crate uuid;
+
+use engine::views::midi_editor::voice_manager::{self, AdsrConf, VoiceManagerConf};
+use polysynth::{
+    adsr::{AdsrParams, Envelope, EnvelopeStage},
+    glide::*,
+    voice_stealing::VoiceStealPolicy,
+    PolySynth, SynthCallbacks,
+};
+use uuid::Uuid;
+
+fn build_synth(
+    voice_count: usize,
+    glide: GlideConf,
+) -> PolySynth<
+    impl Fn(String, usize) -> usize,
+    impl Fn(usize, usize, usize, u8, Option<f32>),
+    impl Fn(usize, usize, usize, Option<f32>),
+    impl Fn(usize, usize, f32, f32),
+    impl Fn(usize, &[u8], &[usize], &[f32]),
+> {
+    let mut synth = PolySynth::with_voice_count(Uuid::nil(), false, voice_count, SynthCallbacks {
+        init_synth: |_, _| 0,
+        trigger_attack: |_, _, _, _, _| (),
+        trigger_release: |_, _, _, _| (),
+        trigger_attack_release: |_, _, _, _| (),
+        schedule_events: |_, _, _, _| (),
+    });
+    synth.glide = glide;
+    synth.sample_rate = 48_000.;
+    synth
+}
+
+#[test]
+fn mono_glides_from_last_note() {
+    let mut synth = build_synth(1, GlideConf {
+        time_ms: 100.,
+        poly_legato: false,
+    });
+    // Nothing to glide from yet
+    assert_eq!(synth.get_glide(), None);
+
+    synth.trigger_attack(60, 255, None);
+    let expected = Some(Glide {
+        from_note_id: 60,
+        duration_samples: 4800,
+    });
+    assert_eq!(synth.get_glide(), expected);
+
+    // Mono synths glide even after the last note was released
+    synth.trigger_release(60, None);
+    assert_eq!(synth.get_glide(), expected);
+    assert_eq!(expected.unwrap().duration_seconds(synth.sample_rate), 0.1);
+
+    synth.release_all();
+    assert_eq!(synth.get_glide(), None);
+}
+
+#[test]
+fn poly_glides_only_when_legato() {
+    let mut synth = build_synth(4, GlideConf {
+        time_ms: 50.,
+        poly_legato: true,
+    });
+    synth.trigger_attack(60, 255, None);
+    assert_eq!(synth.get_glide().map(|glide| glide.from_note_id), Some(60));
+    synth.trigger_release(60, None);
+    assert_eq!(synth.get_glide(), None);
+
+    let mut synth = build_synth(4, GlideConf {
+        time_ms: 50.,
+        poly_legato: false,
+    });
+    synth.trigger_attack(60, 255, None);
+    assert_eq!(synth.get_glide(), None);
+}
+
+#[test]
+fn zero_glide_time_disables_glide() {
+    let mut synth = build_synth(1, GlideConf::default());
+    synth.trigger_attack(60, 255, None);
+    assert_eq!(synth.get_glide(), None);
+}
+
+fn playing_notes(
+    synth: &PolySynth<
+        impl Fn(String, usize) -> usize,
+        impl Fn(usize, usize, usize, u8, Option<f32>),
+        impl Fn(usize, usize, usize, Option<f32>),
+        impl Fn(usize, usize, f32, f32),
+        impl Fn(usize, &[u8], &[usize], &[f32]),
+    >,
+) -> Vec<usize> {
+    [60, 62, 64]
+        .iter()
+        .copied()
+        .filter(|&note_id| synth.get_playing_voice_ix(note_id).is_some())
+        .collect()
+}
+
+fn play_with_policy(steal_policy: VoiceStealPolicy) -> Vec<usize> {
+    let mut synth = build_synth(2, GlideConf::default());
+    synth.steal_policy = steal_policy;
+    synth.trigger_attack(60, 100, None);
+    synth.trigger_attack(62, 20, None);
+    synth.trigger_attack(64, 80, None);
+    playing_notes(&synth)
+}
+
+#[test]
+fn voice_steal_policies() {
+    assert_eq!(play_with_policy(VoiceStealPolicy::StealOldest), vec![62, 64]);
+    assert_eq!(play_with_policy(VoiceStealPolicy::StealQuietest), vec![60, 64]);
+    assert_eq!(play_with_policy(VoiceStealPolicy::StealSamePitch), vec![62, 64]);
+    assert_eq!(play_with_policy(VoiceStealPolicy::DropNew), vec![60, 62]);
+}
+
+#[test]
+fn same_pitch_retriggers_its_voice() {
+    let mut synth = build_synth(4, GlideConf::default());
+    synth.trigger_attack(60, 100, None);
+    let voice_ix = synth.get_playing_voice_ix(60);
+    // Notes that are already playing are ignored by default
+    assert_eq!(synth.trigger_attack_cb(60, 100, |_, _, _, _| ()), None);
+
+    synth.steal_policy = VoiceStealPolicy::StealSamePitch;
+    let retriggered = synth.trigger_attack_cb(60, 50, |_, _, _, _| ());
+    assert_eq!(retriggered.map(|(_, voice_ix, ..)| voice_ix), voice_ix);
+    synth.trigger_release(60, None);
+    assert_eq!(playing_notes(&synth), Vec::<usize>::new());
+}
+
+#[test]
+fn voice_manager_conf_defaults_for_old_saves() {
+    let conf: VoiceManagerConf = serde_json::from_str(r#"{ "glideMs": 80 }"#).unwrap();
+    assert_eq!(conf, VoiceManagerConf {
+        glide_ms: 80.,
+        ..VoiceManagerConf::default()
+    });
+    assert_eq!(conf.steal_policy, voice_manager::VoiceStealPolicy::StealOldest);
+    assert_eq!(conf.voice_count(), 16);
+
+    let conf: VoiceManagerConf =
+        serde_json::from_str(r#"{ "polyphony": 64, "stealPolicy": "dropNew" }"#).unwrap();
+    assert_eq!(conf.steal_policy, voice_manager::VoiceStealPolicy::DropNew);
+    assert_eq!(conf.voice_count(), 16);
+    assert_eq!(
+        VoiceManagerConf {
+            mono: true,
+            ..conf
+        }
+        .voice_count(),
+        1
+    );
+}
+
+/// One sample per millisecond
+const ENVELOPE_SAMPLE_RATE: f32 = 1000.;
+
+const ADSR: AdsrParams = AdsrParams {
+    attack_ms: 4.,
+    decay_ms: 2.,
+    sustain: 0.5,
+    release_ms: 4.,
+};
+
+#[test]
+fn envelope_stages() {
+    let mut envelope = Envelope::default();
+    let mut out = [1.; 8];
+    envelope.render(&ADSR, ENVELOPE_SAMPLE_RATE, &mut out);
+    assert_eq!(out, [0.; 8]);
+
+    envelope.gate_on();
+    envelope.render(&ADSR, ENVELOPE_SAMPLE_RATE, &mut out);
+    assert_eq!(out, [0.25, 0.5, 0.75, 1., 0.75, 0.5, 0.5, 0.5]);
+    assert_eq!(envelope.stage, EnvelopeStage::Sustain);
+
+    envelope.gate_off();
+    envelope.render(&ADSR, ENVELOPE_SAMPLE_RATE, &mut out);
+    assert_eq!(out, [0.375, 0.25, 0.125, 0., 0., 0., 0., 0.]);
+    assert!(!envelope.is_sounding());
+}
+
+#[test]
+fn envelope_retriggers_from_current_level() {
+    let mut envelope = Envelope::default();
+    let mut out = [0.; 2];
+    envelope.gate_on();
+    envelope.render(&ADSR, ENVELOPE_SAMPLE_RATE, &mut out);
+    envelope.gate_off();
+    envelope.render(&ADSR, ENVELOPE_SAMPLE_RATE, &mut out);
+    assert_eq!(out, [0.375, 0.25]);
+
+    envelope.gate_on();
+    envelope.render(&ADSR, ENVELOPE_SAMPLE_RATE, &mut out);
+    assert_eq!(out, [0.5, 0.75]);
+}
+
+#[test]
+fn released_voices_ring_out() {
+    let mut synth = build_synth(3, GlideConf::default());
+    synth.adsr = ADSR;
+
+    synth.advance_envelopes(0.);
+    synth.trigger_attack(60, 255, None);
+    let ringing_voice_ix = synth.get_playing_voice_ix(60).unwrap();
+    synth.advance_envelopes(0.008);
+    synth.trigger_release(60, None);
+    assert_eq!(synth.get_playing_voice_ix(60), None);
+
+    // Releasing a note at the same time that it's attacked silences its voice right away
+    synth.trigger_attack(62, 255, None);
+    synth.trigger_attack(64, 255, None);
+    let silent_voice_ix = synth.get_playing_voice_ix(62).unwrap();
+    synth.trigger_release(62, None);
+    synth.advance_envelopes(0.009);
+
+    // The released voice that has gone silent is used rather than the one still ringing out
+    synth.trigger_attack(65, 255, None);
+    assert_eq!(synth.get_playing_voice_ix(65), Some(silent_voice_ix));
+    assert_eq!(
+        synth.render_envelope_segment(ringing_voice_ix),
+        Some(vec![0.375, 0.25, 0.125, 0.])
+    );
+
+    // Voices that don't exist don't have envelopes
+    assert_eq!(synth.render_envelope_segment(5), None);
+}
+
+#[test]
+fn released_voices_stop_sounding() {
+    let mut synth = build_synth(1, GlideConf::default());
+    synth.adsr = ADSR;
+
+    synth.advance_envelopes(0.);
+    synth.trigger_attack(60, 255, None);
+    assert_eq!(
+        synth.render_envelope_segment(0),
+        Some(vec![0., 0.25, 0.5, 0.75, 1., 0.75, 0.5])
+    );
+
+    synth.advance_envelopes(0.01);
+    synth.trigger_release(60, None);
+    assert_eq!(
+        synth.render_envelope_segment(0),
+        Some(vec![0.5, 0.375, 0.25, 0.125, 0.])
+    );
+
+    synth.advance_envelopes(0.012);
+    assert!(synth.voices[0].envelope.is_sounding());
+    synth.advance_envelopes(0.014);
+    assert!(!synth.voices[0].envelope.is_sounding());
+}
+
+#[test]
+fn adsr_conf_defaults_and_bounds() {
+    let conf: VoiceManagerConf = serde_json::from_str(r#"{ "adsr": { "sustain": 2 } }"#).unwrap();
+    assert_eq!(conf.adsr, AdsrConf {
+        sustain: 2.,
+        ..AdsrConf::default()
+    });
+    let params: AdsrParams = conf.adsr.into();
+    assert_eq!(params, AdsrParams {
+        sustain: 1.,
+        ..AdsrParams::default()
+    });
+}
@@ -0,0 +1,218 @@
+//! Multi-mode biquad filters using the formulas from the RBJ Audio EQ Cookbook.  Filters process
+//! blocks of samples and only recompute their coefficients when their parameters change, so the
+//! parameters can be set before every block without any cost while they're unchanged.
+//!
+//! A `Biquad` filters a single channel and is cheap enough to run one per voice.  `StereoFilter`
+//! pairs two of them into a `BlockProcessor` that processes stereo blocks written into a shared
+//! buffer by the `FilterNodeProcessor` AudioWorklet, which lets the filter be inserted anywhere in
+//! the patch network including on the master bus.
+
+// The exports are only called by the AudioWorklet with the pointer that it got from `init_filter`
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use std::f32::consts::PI;
+
+use audio_block::{Block, BlockProcessor, WorkletHandle};
+
+/// Cutoffs are kept at least this far away from 0 Hz and from the Nyquist frequency, where the
+/// filters become unstable
+const MIN_CUTOFF: f32 = 10.;
+const MAX_CUTOFF_NYQUIST_RATIO: f32 = 0.98;
+const MIN_Q: f32 = 0.01;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FilterMode {
+    Lowpass,
+    Highpass,
+    /// Constant 0dB peak gain at the cutoff, with the bandwidth set by the Q
+    Bandpass,
+    Notch,
+}
+
+impl FilterMode {
+    /// Mirrors the order of `FILTER_MODES` in `src/graphEditor/nodes/CustomAudio/Filter/Filter.ts`
+    pub fn from_u8(mode: u8) -> Option<Self> {
+        match mode {
+            0 => Some(FilterMode::Lowpass),
+            1 => Some(FilterMode::Highpass),
+            2 => Some(FilterMode::Bandpass),
+            3 => Some(FilterMode::Notch),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FilterParams {
+    pub mode: FilterMode,
+    /// Cutoff or center frequency in Hz
+    pub cutoff: f32,
+    /// Resonance of the lowpass and highpass modes and the inverse of the bandwidth of the
+    /// bandpass and notch modes
+    pub q: f32,
+}
+
+impl Default for FilterParams {
+    fn default() -> Self {
+        FilterParams {
+            mode: FilterMode::Lowpass,
+            cutoff: 1000.,
+            q: std::f32::consts::FRAC_1_SQRT_2,
+        }
+    }
+}
+
+/// Coefficients normalized so that `a0` is 1
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Coefficients {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+}
+
+impl Coefficients {
+    fn new(params: &FilterParams, sample_rate: f32) -> Self {
+        let max_cutoff = sample_rate / 2. * MAX_CUTOFF_NYQUIST_RATIO;
+        let cutoff = params.cutoff.clamp(MIN_CUTOFF, max_cutoff);
+        let q = params.q.max(MIN_Q);
+
+        let w0 = 2. * PI * cutoff / sample_rate;
+        let (sin_w0, cos_w0) = w0.sin_cos();
+        let alpha = sin_w0 / (2. * q);
+
+        let (b0, b1, b2) = match params.mode {
+            FilterMode::Lowpass => ((1. - cos_w0) / 2., 1. - cos_w0, (1. - cos_w0) / 2.),
+            FilterMode::Highpass => ((1. + cos_w0) / 2., -(1. + cos_w0), (1. + cos_w0) / 2.),
+            FilterMode::Bandpass => (alpha, 0., -alpha),
+            FilterMode::Notch => (1., -2. * cos_w0, 1.),
+        };
+        let a0 = 1. + alpha;
+
+        Coefficients {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: -2. * cos_w0 / a0,
+            a2: (1. - alpha) / a0,
+        }
+    }
+}
+
+/// A single channel biquad filter in transposed direct form II
+#[derive(Clone, Debug)]
+pub struct Biquad {
+    sample_rate: f32,
+    params: FilterParams,
+    coefficients: Coefficients,
+    z1: f32,
+    z2: f32,
+}
+
+impl Biquad {
+    pub fn new(sample_rate: f32, params: FilterParams) -> Self {
+        Biquad {
+            sample_rate,
+            params,
+            coefficients: Coefficients::new(&params, sample_rate),
+            z1: 0.,
+            z2: 0.,
+        }
+    }
+
+    pub fn params(&self) -> FilterParams { self.params }
+
+    /// Updates the params, recomputing the coefficients only if they've changed.  The filter's
+    /// state is kept so that params can be changed while it's running.
+    pub fn set_params(&mut self, params: FilterParams) {
+        if params == self.params {
+            return;
+        }
+
+        self.params = params;
+        self.coefficients = Coefficients::new(&params, self.sample_rate);
+    }
+
+    /// Clears the filter's state, as if it had only ever been fed silence
+    pub fn reset(&mut self) {
+        self.z1 = 0.;
+        self.z2 = 0.;
+    }
+
+    #[inline(always)]
+    pub fn process_sample(&mut self, input: f32) -> f32 {
+        let Coefficients { b0, b1, b2, a1, a2 } = self.coefficients;
+        let output = b0 * input + self.z1;
+        self.z1 = b1 * input - a1 * output + self.z2;
+        self.z2 = b2 * input - a2 * output;
+        output
+    }
+
+    /// Filters the samples in place
+    pub fn process(&mut self, samples: &mut [f32]) {
+        for sample in samples {
+            *sample = self.process_sample(*sample);
+        }
+    }
+}
+
+/// Two biquads sharing the same params, one for each channel
+pub struct StereoFilter {
+    channels: [Biquad; 2],
+}
+
+impl StereoFilter {
+    pub fn new(sample_rate: f32) -> Self {
+        let filter = Biquad::new(sample_rate, FilterParams::default());
+        StereoFilter {
+            channels: [filter.clone(), filter],
+        }
+    }
+
+    pub fn set_params(&mut self, params: FilterParams) {
+        for channel in &mut self.channels {
+            channel.set_params(params);
+        }
+    }
+}
+
+impl BlockProcessor for StereoFilter {
+    fn process(&mut self, block: &mut Block) {
+        let (left, right) = block.channels_mut();
+        self.channels[0].process(left);
+        self.channels[1].process(right);
+    }
+}
+
+#[no_mangle]
+pub fn init_filter(sample_rate: f32) -> *mut WorkletHandle<StereoFilter> {
+    Box::into_raw(Box::new(WorkletHandle::new(StereoFilter::new(sample_rate))))
+}
+
+/// Returns a pointer to a block of `BLOCK_SIZE * 2` samples that stores the left channel followed
+/// by the right channel.  Input samples are written here and replaced with the output samples by
+/// `process_filter`.
+#[no_mangle]
+pub fn get_io_buffer_ptr(filter: *mut WorkletHandle<StereoFilter>) -> *mut f32 {
+    unsafe { (*filter).io_buffer_ptr() }
+}
+
+/// `mode` is the index of the mode in `FilterMode`; unknown modes are treated as lowpass.
+#[no_mangle]
+pub fn set_filter_params(filter: *mut WorkletHandle<StereoFilter>, mode: u8, cutoff: f32, q: f32) {
+    let params = FilterParams {
+        mode: FilterMode::from_u8(mode).unwrap_or(FilterMode::Lowpass),
+        cutoff,
+        q,
+    };
+    unsafe { (*filter).set_params(params) }
+}
+
+#[no_mangle]
+pub fn process_filter(filter: *mut WorkletHandle<StereoFilter>) { unsafe { (*filter).process() } }
+
+#[no_mangle]
+pub fn drop_filter(filter: *mut WorkletHandle<StereoFilter>) {
+    drop(unsafe { Box::from_raw(filter) })
+}
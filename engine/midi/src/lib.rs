@@ -12,41 +12,144 @@ use js_sys::{Function, Promise, Uint8Array};
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::{future_to_promise, JsFuture};
 
-use common::RawNoteData;
-use rimd::{AbsoluteEvent, Event, MidiMessage, SMFWriter, Status, TrackEvent, SMF};
+use common::{
+    pitch_bend::{self, semitones_to_midi_pitch_bend},
+    tempo_map::TempoMap,
+    RawNoteData, MAX_NOTE_VELOCITY, MIN_NOTE_VELOCITY,
+};
+use rimd::{
+    AbsoluteEvent, Event, MetaEvent, MidiMessage, SMFFormat, SMFWriter, Status, TrackEvent, SMF,
+};
 
 pub mod streaming;
 
 const NO_PLAYING_NOTE: u64 = u64::MAX;
 
+/// The highest valid MIDI note number; notes on lines above this can't be represented
+const MAX_MIDI_NOTE_ID: usize = 127;
+
+/// How often the pitch bend curves of notes are sampled when they're exported.  MIDI pitch bends
+/// are discrete, so curves are approximated by a series of steps.
+const PITCH_BEND_SAMPLE_INTERVAL_BEATS: f32 = 1. / 32.;
+
+// Events at the same tick are ordered by these ranks.  Note offs come before note ons so that
+// back-to-back notes on the same line don't cut each other off, and a note's pitch bend is reset
+// after it ends but before the bend of a note starting at the same time is applied.
+const RANK_NOTE_OFF: u8 = 0;
+const RANK_PITCH_BEND_RESET: u8 = 1;
+const RANK_PITCH_BEND: u8 = 2;
+const RANK_NOTE_ON: u8 = 3;
+
+fn build_pitch_bend_message(semitones: f32) -> MidiMessage {
+    let value = semitones_to_midi_pitch_bend(semitones);
+    MidiMessage::pitch_bend((value & 0x7F) as u8, (value >> 7) as u8, 0)
+}
+
+/// Builds the tempo and time signature meta events for a tempo map, sorted by time
+fn build_tempo_map_events(tempo_map: &TempoMap, ticks_per_beat: f64) -> Vec<(u64, AbsoluteEvent)> {
+    let to_ticks = |beat: f64| (beat * ticks_per_beat).round() as u64;
+
+    let mut events: Vec<(u64, AbsoluteEvent)> = Vec::new();
+    for change in tempo_map.tempo_changes() {
+        let micros_per_beat = (60_000_000. / change.bpm).round() as u32;
+        let ticks = to_ticks(change.beat);
+        let meta = MetaEvent::tempo_setting(micros_per_beat);
+        events.push((ticks, AbsoluteEvent::new_meta(ticks, meta)));
+    }
+    for change in tempo_map.time_signature_changes() {
+        let ticks = to_ticks(change.beat);
+        let time_signature = change.time_signature;
+        // The denominator is stored as a power of two; 24 MIDI clocks per click and 8 32nd notes
+        // per quarter note are the standard values.
+        let meta = MetaEvent::time_signature(
+            time_signature.numerator,
+            time_signature.denominator.trailing_zeros() as u8,
+            24,
+            8,
+        );
+        events.push((ticks, AbsoluteEvent::new_meta(ticks, meta)));
+    }
+    events.sort_by_key(|&(ticks, _)| ticks);
+    events
+}
+
+/// Converts the serialized `RawNoteData` for a grid into a format-0 Standard MIDI File containing
+/// a single track with a note on and note off event for each note, plus pitch bend events for
+/// notes that have pitch bend curves.  If a serialized `TempoMap` is provided, its tempo and time
+/// signature changes are written as well.
 #[wasm_bindgen]
-pub fn write_to_midi(name: String, note_data: &[u8]) -> Vec<u8> {
+pub fn write_to_midi(name: String, note_data: &[u8], tempo_map_json: Option<String>) -> Vec<u8> {
     let ticks_per_beat = 256.;
     common::maybe_init();
 
-    let notes: Vec<RawNoteData> =
-        bincode::deserialize(note_data).expect("Error deserializing note data");
+    let tempo_map: TempoMap = match tempo_map_json.map(|json| serde_json::from_str(&json)) {
+        Some(Ok(tempo_map)) => tempo_map,
+        Some(Err(err)) => {
+            error!("Error deserializing tempo map: {:?}; using the default", err);
+            TempoMap::default()
+        },
+        None => TempoMap::default(),
+    };
 
-    let mut builder = rimd::SMFBuilder::new();
-    let mut midi_events = Vec::with_capacity(notes.len() * 2);
+    let notes =
+        common::deserialize_raw_note_data(note_data).expect("Error deserializing note data");
+
+    // (ticks, rank, message)
+    let mut raw_events: Vec<(u64, u8, MidiMessage)> = Vec::with_capacity(notes.len() * 2);
     for note in notes {
-        let start_ticks = (note.start_beat * ticks_per_beat) as u64;
-        let end_ticks = start_ticks + (note.width * ticks_per_beat) as u64;
-
-        midi_events.push(AbsoluteEvent::new_midi(
-            start_ticks,
-            MidiMessage::note_on(note.line_ix as u8, 255, 0),
-        ));
-        midi_events.push(AbsoluteEvent::new_midi(
-            end_ticks,
-            MidiMessage::note_off(note.line_ix as u8, 255, 0),
-        ))
+        if note.line_ix > MAX_MIDI_NOTE_ID {
+            warn!(
+                "Skipping note on line {} since it's outside of the valid MIDI note range",
+                note.line_ix
+            );
+            continue;
+        }
+
+        let start_ticks = (note.start_beat * ticks_per_beat).round() as u64;
+        let end_ticks = ((note.start_beat + note.width) * ticks_per_beat).round() as u64;
+        // A note on with a velocity of zero is interpreted as a note off
+        let velocity = note.velocity.clamp(MIN_NOTE_VELOCITY, MAX_NOTE_VELOCITY);
+        let note_id = note.line_ix as u8;
+        raw_events.push((start_ticks, RANK_NOTE_ON, MidiMessage::note_on(note_id, velocity, 0)));
+        raw_events.push((end_ticks, RANK_NOTE_OFF, MidiMessage::note_off(note_id, 0, 0)));
+
+        // Pitch bend applies to the whole channel, so bends of overlapping notes will interfere
+        // with each other.
+        if note.pitch_bend.is_empty() {
+            continue;
+        }
+        let samples = pitch_bend::sample_curve(
+            &note.pitch_bend,
+            note.width,
+            PITCH_BEND_SAMPLE_INTERVAL_BEATS,
+        );
+        for (beat_offset, semitones) in samples {
+            if beat_offset >= note.width {
+                break;
+            }
+            let ticks = ((note.start_beat + beat_offset) * ticks_per_beat).round() as u64;
+            raw_events.push((ticks, RANK_PITCH_BEND, build_pitch_bend_message(semitones)));
+        }
+        raw_events.push((end_ticks, RANK_PITCH_BEND_RESET, build_pitch_bend_message(0.)));
     }
-    midi_events.sort_unstable_by_key(|evt| evt.get_time());
+    raw_events.sort_by_key(|&(ticks, rank, _)| (ticks, rank));
+
+    // Meta events come first so that they're in effect for notes starting at the same time
+    let mut events = build_tempo_map_events(&tempo_map, ticks_per_beat);
+    events.extend(
+        raw_events
+            .into_iter()
+            .map(|(ticks, _, msg)| (ticks, AbsoluteEvent::new_midi(ticks, msg))),
+    );
+    events.sort_by_key(|&(ticks, _)| ticks);
+    let midi_events = events.into_iter().map(|(_, event)| event).collect::<Vec<_>>();
+
+    let mut builder = rimd::SMFBuilder::new();
     builder.add_static_track(midi_events.iter());
     builder.set_name(0, name);
 
     let mut midi_file = builder.result();
+    midi_file.format = SMFFormat::Single;
     midi_file.division = ticks_per_beat as i16;
 
     let mut output: Vec<u8> = Vec::new();
@@ -92,7 +195,13 @@ impl From<&SMF> for MIDIFileInfo {
 }
 
 /// Parses a MIDI file and returns the serialize byte representation of the `RawNote`s loaded from
-/// it.
+/// it.  Note ids are used directly as line indices and tick times are converted to beats using the
+/// file's `division` (ticks per quarter note), so tempo changes don't affect the loaded notes.
+///
+/// Since each line of the grid can only hold one note at a time, overlapping notes are handled by
+/// truncation: if a note is started while the same note id is already playing, the playing note
+/// is ended at that point and a new one is started.  Notes that end up with zero length are
+/// dropped.
 ///
 /// `info_cb` is a function that should be called with the object representing stats about the
 /// loaded MIDI file.  It should return a `Promise` which will then be awaited by this function.
@@ -181,10 +290,12 @@ pub fn load_midi_to_raw_note_bytes(file_bytes: &[u8], info_cb: Function) -> Opti
         let mut cur_vtime = 0;
         let mut notes: Vec<RawNoteData> = Vec::new();
         let mut on_notes: [u64; 255] = [NO_PLAYING_NOTE; 255];
+        let mut on_note_velocities: [u8; 255] = [0; 255];
 
         struct NoteParseContext<'a> {
             cur_vtime: u64,
             on_notes: &'a mut [u64; 255],
+            on_note_velocities: &'a mut [u8; 255],
             notes: &'a mut Vec<RawNoteData>,
             data: &'a [u8],
         }
@@ -192,6 +303,7 @@ pub fn load_midi_to_raw_note_bytes(file_bytes: &[u8], info_cb: Function) -> Opti
         let handle_note_off = |NoteParseContext {
                                    cur_vtime,
                                    on_notes,
+                                   on_note_velocities,
                                    notes,
                                    data,
                                }: &mut NoteParseContext| {
@@ -210,16 +322,23 @@ pub fn load_midi_to_raw_note_bytes(file_bytes: &[u8], info_cb: Function) -> Opti
             }
 
             let note_start_ticks = on_notes[note_id as usize];
+            on_notes[note_id as usize] = NO_PLAYING_NOTE;
+            if *cur_vtime == note_start_ticks {
+                trace!("Dropping zero-length note with id {}", note_id);
+                return;
+            }
+
             let note_duration_beats = (*cur_vtime - note_start_ticks) as f32 / ticks_per_beat;
             let note_start_beats = note_start_ticks as f32 / ticks_per_beat;
             let note_data = RawNoteData {
                 line_ix: note_id as usize,
                 start_beat: note_start_beats,
                 width: note_duration_beats,
+                velocity: on_note_velocities[note_id as usize],
+                pitch_bend: Vec::new(),
+                expression: Default::default(),
             };
             notes.push(note_data);
-
-            on_notes[note_id as usize] = NO_PLAYING_NOTE;
         };
 
         let handle_note_on = |context: &mut NoteParseContext| {
@@ -233,17 +352,20 @@ pub fn load_midi_to_raw_note_bytes(file_bytes: &[u8], info_cb: Function) -> Opti
             if velocity == 0 {
                 info!("Velocity is zero; handling as note off event.");
                 handle_note_off(context);
+                return;
             }
 
             if context.on_notes[note_id as usize] != NO_PLAYING_NOTE {
-                warn!(
-                    "Tried to start note id {} but it's already playing",
+                info!(
+                    "Tried to start note id {} but it's already playing; truncating the playing \
+                     note",
                     note_id
                 );
-                return;
+                handle_note_off(context);
             }
 
             context.on_notes[note_id as usize] = context.cur_vtime;
+            context.on_note_velocities[note_id as usize] = velocity.min(MAX_NOTE_VELOCITY);
         };
 
         for TrackEvent { vtime, event } in &track.events {
@@ -255,6 +377,7 @@ pub fn load_midi_to_raw_note_bytes(file_bytes: &[u8], info_cb: Function) -> Opti
                     let mut context = NoteParseContext {
                         cur_vtime,
                         on_notes: &mut on_notes,
+                        on_note_velocities: &mut on_note_velocities,
                         notes: &mut notes,
                         data: &midi_evt.data,
                     };
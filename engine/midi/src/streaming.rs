@@ -13,6 +13,8 @@ pub struct MsgHandlerContext {
     pub release_note: Function,
     pub pitch_bend: Option<Function>,
     pub mod_wheel: Option<Function>,
+    /// Called with `(control_index, value)` for all control change events, including mod wheel
+    pub generic_control: Option<Function>,
     pub voice_manager: PolySynth<
         Box<dyn Fn(String, usize) -> usize>,
         Box<dyn Fn(usize, usize, usize, u8, Option<f32>)>,
@@ -28,6 +30,7 @@ pub fn create_msg_handler_context(
     release_note: Function,
     pitch_bend: Option<Function>,
     mod_wheel: Option<Function>,
+    generic_control: Option<Function>,
 ) -> usize {
     common::maybe_init();
 
@@ -36,6 +39,7 @@ pub fn create_msg_handler_context(
         release_note,
         pitch_bend,
         mod_wheel,
+        generic_control,
         // Insert temporary pointers for now that we will swap out once we have psueo-static
         // pointers to the boxed `Function`s
         voice_manager: PolySynth::new(uuid_v4(), true, SynthCallbacks {
@@ -166,15 +170,27 @@ pub fn handle_midi_evt(evt_bytes: Vec<u8>, ctx_ptr: *mut MsgHandlerContext) {
                 Ok(())
             },
         },
-        // Mod Wheel
-        Status::ControlChange if evt.data[1] == 1 => {
-            if let Some(mod_wheel_handler) = &ctx.mod_wheel {
-                let value = evt.data[2];
-                mod_wheel_handler
+        Status::ControlChange => {
+            let (control_index, value) = (evt.data[1], evt.data[2]);
+            // Mod Wheel
+            let mod_wheel_res = match &ctx.mod_wheel {
+                Some(mod_wheel_handler) if control_index == 1 => mod_wheel_handler
                     .call1(&JsValue::NULL, &JsValue::from(value))
-                    .map(|_| ())
-            } else {
-                Ok(())
+                    .map(|_| ()),
+                _ => Ok(()),
+            };
+
+            match &ctx.generic_control {
+                Some(generic_control_handler) => mod_wheel_res.and_then(|_| {
+                    generic_control_handler
+                        .call2(
+                            &JsValue::NULL,
+                            &JsValue::from(control_index),
+                            &JsValue::from(value),
+                        )
+                        .map(|_| ())
+                }),
+                None => mod_wheel_res,
             }
         },
         status => {
@@ -0,0 +1,153 @@
+//! Amplitude envelopes for the voices of the voice manager.  Each voice owns an `Envelope` that is
+//! opened when it's attacked and closed when it's released, and the release keeps ringing out
+//! after the note is released until it reaches silence.
+//!
+//! Envelopes are run at `ENVELOPE_CONTROL_RATE` rather than at audio rate.  Each time that a voice
+//! is attacked or released, the segment of its envelope up to the next stage that holds its level
+//! is rendered and handed to the synth playing the voice, which applies it to the voice's gain.
+
+/// Stages are always at least one sample long so that zero-length stages don't divide by zero
+const MIN_STAGE_SAMPLES: f32 = 1.;
+/// The rate in Hz that envelopes are advanced and rendered at.  Synths interpolate between the
+/// rendered values, so this only needs to be fine enough to keep short attacks from clicking.
+pub const ENVELOPE_CONTROL_RATE: f32 = 1000.;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AdsrParams {
+    /// How long it takes to rise from silence to full volume after a note is attacked
+    pub attack_ms: f32,
+    /// How long it takes to fall from full volume to the sustain level after the attack
+    pub decay_ms: f32,
+    /// The level from 0 to 1 that is held for as long as the note is held after the decay
+    pub sustain: f32,
+    /// How long it takes to fall from the sustain level to silence after the note is released
+    pub release_ms: f32,
+}
+
+impl Default for AdsrParams {
+    fn default() -> Self {
+        AdsrParams {
+            attack_ms: 5.,
+            decay_ms: 100.,
+            sustain: 0.8,
+            release_ms: 200.,
+        }
+    }
+}
+
+fn ms_to_samples(ms: f32, sample_rate: f32) -> f32 {
+    (ms / 1000. * sample_rate).max(MIN_STAGE_SAMPLES)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EnvelopeStage {
+    Idle,
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Envelope {
+    pub stage: EnvelopeStage,
+    /// The envelope's current output level from 0 to 1
+    pub level: f32,
+    /// The level that the release started from, which sets the slope of the release
+    release_start_level: f32,
+}
+
+impl Default for Envelope {
+    fn default() -> Self {
+        Envelope {
+            stage: EnvelopeStage::Idle,
+            level: 0.,
+            release_start_level: 0.,
+        }
+    }
+}
+
+impl Envelope {
+    /// Starts the attack from the current level, so retriggering a voice that's still sounding
+    /// doesn't click.
+    pub fn gate_on(&mut self) { self.stage = EnvelopeStage::Attack; }
+
+    /// Starts the release from the current level
+    pub fn gate_off(&mut self) {
+        if self.stage == EnvelopeStage::Idle {
+            return;
+        }
+
+        self.stage = EnvelopeStage::Release;
+        self.release_start_level = self.level;
+    }
+
+    /// Returns `true` until the release has finished ringing out
+    pub fn is_sounding(&self) -> bool { self.stage != EnvelopeStage::Idle }
+
+    /// Returns `true` if the envelope is in a stage that holds its level until it's gated
+    pub fn is_steady(&self) -> bool {
+        self.stage == EnvelopeStage::Idle || self.stage == EnvelopeStage::Sustain
+    }
+
+    pub fn next_sample(&mut self, params: &AdsrParams, sample_rate: f32) -> f32 {
+        let sustain = params.sustain.clamp(0., 1.);
+        match self.stage {
+            EnvelopeStage::Idle => (),
+            EnvelopeStage::Attack => {
+                self.level += 1. / ms_to_samples(params.attack_ms, sample_rate);
+                if self.level >= 1. {
+                    self.level = 1.;
+                    self.stage = EnvelopeStage::Decay;
+                }
+            },
+            EnvelopeStage::Decay => {
+                self.level -= (1. - sustain) / ms_to_samples(params.decay_ms, sample_rate);
+                if self.level <= sustain {
+                    self.level = sustain;
+                    self.stage = EnvelopeStage::Sustain;
+                }
+            },
+            EnvelopeStage::Sustain => self.level = sustain,
+            EnvelopeStage::Release => {
+                self.level -=
+                    self.release_start_level / ms_to_samples(params.release_ms, sample_rate);
+                if self.level <= 0. {
+                    self.level = 0.;
+                    self.stage = EnvelopeStage::Idle;
+                }
+            },
+        }
+        self.level
+    }
+
+    /// Fills `out` with the envelope's next `out.len()` samples
+    pub fn render(&mut self, params: &AdsrParams, sample_rate: f32, out: &mut [f32]) {
+        for sample in out {
+            *sample = self.next_sample(params, sample_rate);
+        }
+    }
+
+    /// Advances the envelope by up to `samples` samples, stopping early once it reaches a stage
+    /// that holds its level
+    pub fn advance(&mut self, params: &AdsrParams, sample_rate: f32, samples: usize) {
+        for _ in 0..samples {
+            self.next_sample(params, sample_rate);
+            if self.is_steady() {
+                break;
+            }
+        }
+    }
+
+    /// Renders the envelope from its current level until it reaches a stage that holds its level
+    /// without advancing it.  That's the attack and decay after it's gated on and the release
+    /// after it's gated off.
+    pub fn render_segment(&self, params: &AdsrParams, sample_rate: f32) -> Vec<f32> {
+        let mut envelope = *self;
+        let mut segment = vec![envelope.level];
+        while !envelope.is_steady() {
+            segment.push(envelope.next_sample(params, sample_rate));
+        }
+        segment
+    }
+}
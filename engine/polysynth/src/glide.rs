@@ -0,0 +1,43 @@
+//! Glide (also known as portamento) slides the pitch of a newly played note from the pitch of the
+//! note played before it rather than jumping straight to it.
+
+/// The sample rate that glides are timed with until the voice manager is told the real one
+pub const DEFAULT_SAMPLE_RATE: f32 = 44_100.;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GlideConf {
+    /// How long it takes to slide to the pitch of a new note.  Glide is disabled if this is zero.
+    pub time_ms: f32,
+    /// If set, polyphonic synths glide when a note is played while the note played before it is
+    /// still held.  Monophonic synths always glide.
+    pub poly_legato: bool,
+}
+
+impl Default for GlideConf {
+    fn default() -> Self {
+        GlideConf {
+            time_ms: 0.,
+            poly_legato: false,
+        }
+    }
+}
+
+impl GlideConf {
+    /// Returns the length of glides in samples at `sample_rate`
+    pub fn duration_samples(&self, sample_rate: f32) -> usize {
+        (self.time_ms.max(0.) / 1000. * sample_rate).round() as usize
+    }
+}
+
+/// A slide from the pitch of one note to the pitch of a newly played note
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Glide {
+    pub from_note_id: usize,
+    pub duration_samples: usize,
+}
+
+impl Glide {
+    pub fn duration_seconds(&self, sample_rate: f32) -> f32 {
+        self.duration_samples as f32 / sample_rate
+    }
+}
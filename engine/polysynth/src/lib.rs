@@ -1,8 +1,6 @@
 //! Synth state management.  Handles keeping track of what each voice of each polyphonic synth
 //! is playing and passing the correct commands through to the WebAudio synths.
 
-#![feature(nll, box_syntax)]
-
 #[cfg(feature = "wasm-bindgen")]
 #[macro_use]
 extern crate wasm_bindgen;
@@ -14,12 +12,21 @@ extern crate js_sys;
 #[macro_use]
 extern crate log;
 
-use std::{mem, ptr};
-
 #[cfg(feature = "wasm-bindgen")]
 use js_sys::Array;
 use uuid::Uuid;
 
+pub mod adsr;
+pub mod glide;
+pub mod unison;
+pub mod voice_stealing;
+
+use crate::{
+    adsr::{AdsrParams, Envelope, ENVELOPE_CONTROL_RATE},
+    glide::{Glide, GlideConf, DEFAULT_SAMPLE_RATE},
+    voice_stealing::VoiceStealPolicy,
+};
+
 #[derive(Clone)]
 pub struct SynthCallbacks<
     // uuid: String, voice_count: usize
@@ -40,7 +47,8 @@ pub struct SynthCallbacks<
     pub schedule_events: SE,
 }
 
-pub const POLY_SYNTH_VOICE_COUNT: usize = 16; // TODO: Make this a configurable param
+/// The number of voices that synths are created with if no other polyphony is specified
+pub const POLY_SYNTH_VOICE_COUNT: usize = 16;
 
 #[derive(PartialEq, Clone, Copy, Debug)]
 pub enum VoicePlayingStatus {
@@ -51,16 +59,25 @@ pub enum VoicePlayingStatus {
 #[derive(Clone, Copy, Debug)]
 pub struct Voice {
     pub playing: VoicePlayingStatus,
+    /// The velocity of the note that the voice was last attacked with
+    pub velocity: u8,
     /// Index mapping this voice to its position in the array of voices on the JavaScript/WebAudio
     /// side of things.
     pub src_ix: usize,
+    /// The voice's amplitude envelope, which keeps ringing out after the voice is released
+    pub envelope: Envelope,
+    /// The time in seconds that the envelope has been advanced up to
+    pub envelope_time: f64,
 }
 
 impl Voice {
     pub fn new(src_ix: usize) -> Self {
         Voice {
             playing: VoicePlayingStatus::Tacent,
+            velocity: 0,
             src_ix,
+            envelope: Envelope::default(),
+            envelope_time: 0.,
         }
     }
 
@@ -87,8 +104,18 @@ pub struct PolySynth<
     /// Index of the first voice slot that is idle.  If no slots are idle,
     /// points to the voice that has been playing the longest.
     pub first_idle_voice_ix: usize,
-    /// Maps each voice's index to what frequency it's currently playing
-    pub voices: [Voice; POLY_SYNTH_VOICE_COUNT],
+    /// Maps each voice's index to what frequency it's currently playing.  Its length is the
+    /// polyphony of the synth.
+    pub voices: Vec<Voice>,
+    /// Determines which voice plays a new note when all voices are already playing
+    pub steal_policy: VoiceStealPolicy,
+    pub glide: GlideConf,
+    /// The amplitude envelope that the voices are played with
+    pub adsr: AdsrParams,
+    /// The sample rate of the audio context that the synth is played in, used to time glides
+    pub sample_rate: f32,
+    /// The note that was attacked most recently, which new notes glide from
+    pub last_note_id: Option<usize>,
     /// The functions that will be called to carry out synth actions
     pub synth_cbs: SynthCallbacks<I, TA, TR, TAR, SE>,
 }
@@ -111,11 +138,11 @@ impl<
         let (search_range_1, search_range_2) = if self.voices[self.first_idle_voice_ix].is_playing()
         {
             // all voices active; have to search the whole range
-            (0..POLY_SYNTH_VOICE_COUNT, 0..0)
+            (0..self.voices.len(), 0..0)
         } else if self.first_active_voice_ix > self.first_idle_voice_ix {
             // range is split; idle range is in the middle
             (
-                self.first_active_voice_ix..POLY_SYNTH_VOICE_COUNT,
+                self.first_active_voice_ix..self.voices.len(),
                 0..self.first_idle_voice_ix,
             )
         } else {
@@ -128,14 +155,48 @@ impl<
             .map(|(ix, _voice)| ix)
     }
 
-    pub fn new(uuid: Uuid, link: bool, synth_cbs: SynthCallbacks<I, TA, TR, TAR, SE>) -> Self {
-        let mut voices: [Voice; POLY_SYNTH_VOICE_COUNT] = unsafe { mem::uninitialized() };
-        let voices_ptr = &mut voices as *mut _ as *mut Voice;
-        for i in 0..POLY_SYNTH_VOICE_COUNT {
-            unsafe { ptr::write(voices_ptr.add(i), Voice::new(i)) };
+    /// Returns the index of the voice that was attacked with the lowest velocity, preferring the
+    /// oldest voice in case of ties.  Only valid to call if all voices are playing.
+    fn find_ix_of_quietest_voice(&self) -> usize {
+        let voice_count = self.voices.len();
+        (0..voice_count)
+            .map(|i| (self.first_active_voice_ix + i) % voice_count)
+            .min_by_key(|&ix| self.voices[ix].velocity)
+            .unwrap_or(self.first_active_voice_ix)
+    }
+
+    /// If the idle voice that would play the next note is still ringing out its release, swaps an
+    /// idle voice that has gone silent into its slot so that releases aren't cut off.
+    fn swap_in_silent_voice(&mut self) {
+        if !self.voices[self.first_idle_voice_ix].envelope.is_sounding() {
+            return;
+        }
+
+        let silent_voice_ix = self
+            .voices
+            .iter()
+            .position(|voice| !voice.is_playing() && !voice.envelope.is_sounding());
+        if let Some(silent_voice_ix) = silent_voice_ix {
+            self.voices.swap(silent_voice_ix, self.first_idle_voice_ix);
         }
+    }
+
+    pub fn new(uuid: Uuid, link: bool, synth_cbs: SynthCallbacks<I, TA, TR, TAR, SE>) -> Self {
+        Self::with_voice_count(uuid, link, POLY_SYNTH_VOICE_COUNT, synth_cbs)
+    }
+
+    /// Creates a synth that can play up to `voice_count` notes at once.  Once all voices are
+    /// playing, new notes steal the voice that has been playing the longest.
+    pub fn with_voice_count(
+        uuid: Uuid,
+        link: bool,
+        voice_count: usize,
+        synth_cbs: SynthCallbacks<I, TA, TR, TAR, SE>,
+    ) -> Self {
+        let voice_count = voice_count.max(1);
+        let voices = (0..voice_count).map(Voice::new).collect();
         let id = if link && cfg!(target_arch = "wasm32") {
-            (synth_cbs.init_synth)(uuid.to_string(), POLY_SYNTH_VOICE_COUNT)
+            (synth_cbs.init_synth)(uuid.to_string(), voice_count)
         } else {
             0
         };
@@ -145,13 +206,27 @@ impl<
             first_active_voice_ix: 0,
             first_idle_voice_ix: 0,
             voices,
+            steal_policy: VoiceStealPolicy::default(),
+            glide: GlideConf::default(),
+            adsr: AdsrParams::default(),
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            last_note_id: None,
             synth_cbs,
         }
     }
 
+    /// Changes the number of voices that the synth can play at once.  All playing voices are
+    /// released first.
+    pub fn set_voice_count(&mut self, voice_count: usize) {
+        self.release_all();
+        self.voices = (0..voice_count.max(1)).map(Voice::new).collect();
+        self.first_active_voice_ix = 0;
+        self.first_idle_voice_ix = 0;
+    }
+
     /// Starts playing a given frequency on one of the voices of the synthesizer.  If all of the
-    /// voices are occupied, one of the other voices will be stopped and used to play this
-    /// frequency.
+    /// voices are occupied, the steal policy determines which of the other voices will be stopped
+    /// and used to play this frequency, if any.
     pub fn trigger_attack_cb<F: FnMut(usize, usize, usize, u8)>(
         &mut self,
         note_id: usize,
@@ -160,17 +235,44 @@ impl<
     ) -> Option<(usize, usize, usize, u8)> {
         // Ignore this event if we already have a note playing with the provided `note_id` on any
         // voice.  This is necessary in order to prevent "ghost" notes that can't be
-        // released.
-        if self.find_ix_of_voice_playing(note_id).is_some() {
-            return None;
+        // released.  Retriggering the voice that's playing it re-uses that voice, so it's safe.
+        if let Some(voice_ix) = self.find_ix_of_voice_playing(note_id) {
+            if self.steal_policy != VoiceStealPolicy::StealSamePitch {
+                return None;
+            }
+
+            self.voices[voice_ix].velocity = velocity;
+            self.voices[voice_ix].envelope.gate_on();
+            self.last_note_id = Some(note_id);
+            let retriggered_voice_ix = self.voices[voice_ix].src_ix;
+            cb(self.id, retriggered_voice_ix, note_id, velocity);
+            return Some((self.id, retriggered_voice_ix, note_id, velocity));
+        }
+
+        if self.voices[self.first_idle_voice_ix].is_playing() {
+            match self.steal_policy {
+                VoiceStealPolicy::DropNew => return None,
+                VoiceStealPolicy::StealQuietest => {
+                    // Swap the quietest voice into the slot of the oldest one, which is the one
+                    // that gets overwritten
+                    let quietest_voice_ix = self.find_ix_of_quietest_voice();
+                    self.voices.swap(quietest_voice_ix, self.first_idle_voice_ix);
+                },
+                VoiceStealPolicy::StealOldest | VoiceStealPolicy::StealSamePitch => (),
+            }
+        } else {
+            self.swap_in_silent_voice();
         }
 
         self.voices[self.first_idle_voice_ix].playing = VoicePlayingStatus::Playing(note_id);
+        self.voices[self.first_idle_voice_ix].velocity = velocity;
+        self.voices[self.first_idle_voice_ix].envelope.gate_on();
+        self.last_note_id = Some(note_id);
         let played_voice_ix = self.voices[self.first_idle_voice_ix].src_ix;
         cb(self.id, played_voice_ix, note_id, velocity);
 
         // bump the first idle index since we're adding a new active voice
-        if self.first_idle_voice_ix == (POLY_SYNTH_VOICE_COUNT - 1) {
+        if self.first_idle_voice_ix == (self.voices.len() - 1) {
             self.first_idle_voice_ix = 0;
         } else {
             self.first_idle_voice_ix += 1;
@@ -185,6 +287,25 @@ impl<
         Some((self.id, played_voice_ix, note_id, velocity))
     }
 
+    /// Returns the glide that a note attacked now should be played with, if any.  Monophonic synths
+    /// glide from the last note that was played.  Polyphonic synths only glide if legato glide is
+    /// enabled and the last note that was played is still held.
+    pub fn get_glide(&self) -> Option<Glide> {
+        if self.glide.time_ms <= 0. {
+            return None;
+        }
+        let from_note_id = self.last_note_id?;
+        let is_legato = self.find_ix_of_voice_playing(from_note_id).is_some();
+        if self.voices.len() > 1 && !(self.glide.poly_legato && is_legato) {
+            return None;
+        }
+
+        Some(Glide {
+            from_note_id,
+            duration_samples: self.glide.duration_samples(self.sample_rate),
+        })
+    }
+
     pub fn trigger_attack(&mut self, note_id: usize, velocity: u8, offset: Option<f32>) {
         if let Some((synth_id, voice_ix, note_id, velocity)) =
             self.trigger_attack_cb(note_id, velocity, |_, _, _, _| ())
@@ -218,10 +339,11 @@ impl<
         let released_voice_ix = self.voices[target_voice_ix].src_ix;
         cb(self.id, released_voice_ix, note_id);
         self.voices[target_voice_ix].playing = VoicePlayingStatus::Tacent;
+        self.voices[target_voice_ix].envelope.gate_off();
         let old_first_active_voice_ix = self.first_active_voice_ix;
 
         // Bump the first active pointer forward since we're getting rid of an active voice
-        if self.first_active_voice_ix != POLY_SYNTH_VOICE_COUNT - 1 {
+        if self.first_active_voice_ix != self.voices.len() - 1 {
             self.first_active_voice_ix += 1;
         } else {
             self.first_active_voice_ix = 0;
@@ -246,12 +368,48 @@ impl<
         }
     }
 
+    /// Returns the index of the WebAudio voice that is currently playing `note_id`, if any.
+    pub fn get_playing_voice_ix(&self, note_id: usize) -> Option<usize> {
+        self.find_ix_of_voice_playing(note_id)
+            .map(|voice_ix| self.voices[voice_ix].src_ix)
+    }
+
+    /// Advances the envelopes of all voices up to `time` in seconds.  This should be called with
+    /// the time of each event before it's applied so that retriggered voices start from their
+    /// current level and voices that have finished ringing out can be reused.
+    pub fn advance_envelopes(&mut self, time: f64) {
+        let adsr = self.adsr;
+        for voice in &mut self.voices {
+            let samples = ((time - voice.envelope_time) * ENVELOPE_CONTROL_RATE as f64).round();
+            if samples < 1. {
+                continue;
+            }
+
+            voice
+                .envelope
+                .advance(&adsr, ENVELOPE_CONTROL_RATE, samples as usize);
+            voice.envelope_time += samples / ENVELOPE_CONTROL_RATE as f64;
+        }
+    }
+
+    /// Renders the amplitude envelope of the WebAudio voice with index `src_ix` at
+    /// `ENVELOPE_CONTROL_RATE` from its current level until it settles.  Returns `None` if the
+    /// voice doesn't exist.
+    pub fn render_envelope_segment(&self, src_ix: usize) -> Option<Vec<f32>> {
+        self.voices
+            .iter()
+            .find(|voice| voice.src_ix == src_ix)
+            .map(|voice| voice.envelope.render_segment(&self.adsr, ENVELOPE_CONTROL_RATE))
+    }
+
+    /// Releases all playing notes.  Notes played after this don't glide from notes played before.
     pub fn release_all(&mut self) {
-        for i in 0..POLY_SYNTH_VOICE_COUNT {
+        for i in 0..self.voices.len() {
             if let VoicePlayingStatus::Playing(note_id) = self.voices[i].playing {
                 self.trigger_release(note_id, None);
             }
         }
+        self.last_note_id = None;
     }
 }
 
@@ -261,11 +419,20 @@ pub fn stop_playback() {
 
 #[cfg(feature = "wasm-bindgen")]
 pub mod exports {
+    use std::mem;
+
     use wasm_bindgen::prelude::*;
 
     use crate::*;
 
     pub struct PolySynthContext {
+        /// Called with `(voice_ix, from_note_id, note_id, duration_seconds, offset)` after a note
+        /// that glides from another note is attacked
+        pub glide_note: Option<js_sys::Function>,
+        /// Called with `(voice_ix, envelope, duration_seconds, offset)` after a voice is attacked
+        /// or released, where `envelope` is a `Float32Array` of the gain that the voice should
+        /// ramp through over `duration_seconds`
+        pub apply_envelope: Option<js_sys::Function>,
         pub synth: PolySynth<
             Box<dyn Fn(String, usize) -> usize>,
             Box<dyn Fn(usize, usize, usize, u8, Option<f32>)>,
@@ -279,15 +446,19 @@ pub mod exports {
     pub fn create_polysynth_context(
         play_note: js_sys::Function,
         release_note: js_sys::Function,
+        glide_note: Option<js_sys::Function>,
+        apply_envelope: Option<js_sys::Function>,
     ) -> *mut PolySynthContext {
         let context = PolySynthContext {
+            glide_note,
+            apply_envelope,
             synth: PolySynth::new(common::uuid_v4(), true, SynthCallbacks {
-                init_synth: box |_, _| 0usize,
-                trigger_release:
-                    box move |_synth_ix: usize,
-                              voice_ix: usize,
-                              note_id: usize,
-                              offset: Option<f32>| match release_note.call3(
+                init_synth: Box::new(|_, _| 0usize),
+                trigger_release: Box::new(
+                    move |_synth_ix: usize,
+                          voice_ix: usize,
+                          note_id: usize,
+                          offset: Option<f32>| match release_note.call3(
                         &JsValue::NULL,
                         &JsValue::from(voice_ix as u32),
                         &JsValue::from(note_id as u32),
@@ -296,12 +467,13 @@ pub mod exports {
                         Ok(_) => (),
                         Err(err) => error!("Error playing note: {:?}", err),
                     },
-                trigger_attack: box move |_synth_ix: usize,
-                                          voice_ix: usize,
-                                          note_id: usize,
-                                          velocity: u8,
-                                          offset: Option<f32>| {
-                    match play_note.apply(
+                ),
+                trigger_attack: Box::new(
+                    move |_synth_ix: usize,
+                          voice_ix: usize,
+                          note_id: usize,
+                          velocity: u8,
+                          offset: Option<f32>| match play_note.apply(
                         &JsValue::NULL,
                         &Array::of4(
                             &JsValue::from(voice_ix as u32),
@@ -312,14 +484,51 @@ pub mod exports {
                     ) {
                         Ok(_) => (),
                         Err(err) => error!("Error playing note: {:?}", err),
-                    }
-                },
-                trigger_attack_release: box move |_, _, _, _| unimplemented!(),
-                schedule_events: box move |_, _, _, _| unimplemented!(),
+                    },
+                ),
+                trigger_attack_release: Box::new(move |_, _, _, _| unimplemented!()),
+                schedule_events: Box::new(move |_, _, _, _| unimplemented!()),
             }),
         };
 
-        Box::into_raw(box context)
+        Box::into_raw(Box::new(context))
+    }
+
+    /// Returns the time in seconds of an event that's played `offset` seconds from now
+    fn event_time(offset: Option<f32>) -> f64 {
+        js_sys::Date::now() / 1000. + offset.unwrap_or(0.) as f64
+    }
+
+    /// Sends the envelope segment that the voice `voice_ix` has just started to the synth so that
+    /// it's applied to the voice's gain
+    fn apply_envelope(ctx: &PolySynthContext, voice_ix: usize, offset: Option<f32>) {
+        let apply_envelope = match &ctx.apply_envelope {
+            Some(apply_envelope) => apply_envelope,
+            None => return,
+        };
+        let segment = match ctx.synth.render_envelope_segment(voice_ix) {
+            Some(segment) if segment.len() > 1 => segment,
+            _ => return,
+        };
+
+        let duration_seconds = (segment.len() - 1) as f32 / ENVELOPE_CONTROL_RATE;
+        let args = Array::of4(
+            &JsValue::from(voice_ix as u32),
+            &js_sys::Float32Array::from(segment.as_slice()),
+            &JsValue::from(duration_seconds),
+            &JsValue::from(offset),
+        );
+        if let Err(err) = apply_envelope.apply(&JsValue::NULL, &args) {
+            error!("Error applying envelope: {:?}", err);
+        }
+    }
+
+    fn release_note(ctx: &mut PolySynthContext, note_id: usize, offset: Option<f32>) {
+        let released = ctx.synth.trigger_release_cb(note_id, |_, _, _| ());
+        if let Some((synth_ix, voice_ix)) = released {
+            (ctx.synth.synth_cbs.trigger_release)(synth_ix, voice_ix, note_id, offset);
+            apply_envelope(ctx, voice_ix, offset);
+        }
     }
 
     #[wasm_bindgen]
@@ -337,21 +546,122 @@ pub mod exports {
         offset: Option<f32>,
     ) {
         let mut ctx = unsafe { Box::from_raw(ctx) };
-        ctx.synth
-            .trigger_attack(note_id, velocity.unwrap_or(255), offset);
+        ctx.synth.advance_envelopes(event_time(offset));
+        let glide = ctx.synth.get_glide();
+        let attacked = ctx
+            .synth
+            .trigger_attack_cb(note_id, velocity.unwrap_or(255), |_, _, _, _| ());
+        if let Some((synth_ix, voice_ix, note_id, velocity)) = attacked {
+            (ctx.synth.synth_cbs.trigger_attack)(synth_ix, voice_ix, note_id, velocity, offset);
+            apply_envelope(&ctx, voice_ix, offset);
+
+            if let (Some(glide), Some(glide_note)) = (glide, &ctx.glide_note) {
+                let args = Array::of5(
+                    &JsValue::from(voice_ix as u32),
+                    &JsValue::from(glide.from_note_id as u32),
+                    &JsValue::from(note_id as u32),
+                    &JsValue::from(glide.duration_seconds(ctx.synth.sample_rate)),
+                    &JsValue::from(offset),
+                );
+                if let Err(err) = glide_note.apply(&JsValue::NULL, &args) {
+                    error!("Error gliding note: {:?}", err);
+                }
+            }
+        }
         mem::forget(ctx);
     }
 
     #[wasm_bindgen]
     pub fn handle_note_up(ctx: *mut PolySynthContext, note_id: usize, offset: Option<f32>) {
         let mut ctx = unsafe { Box::from_raw(ctx) };
-        ctx.synth.trigger_release(note_id, offset);
+        ctx.synth.advance_envelopes(event_time(offset));
+        release_note(&mut ctx, note_id, offset);
+        mem::forget(ctx);
+    }
+
+    /// Sets the number of notes that the synth can play at once, releasing all playing notes.
+    #[wasm_bindgen]
+    pub fn set_voice_count(ctx: *mut PolySynthContext, voice_count: usize) {
+        let mut ctx = unsafe { Box::from_raw(ctx) };
+        ctx.synth.set_voice_count(voice_count);
         mem::forget(ctx);
     }
 
+    /// Sets which voice plays a new note when all voices are already playing.  `policy` is the
+    /// discriminant of a `VoiceStealPolicy`.
+    #[wasm_bindgen]
+    pub fn set_voice_steal_policy(ctx: *mut PolySynthContext, policy: u8) {
+        let mut ctx = unsafe { Box::from_raw(ctx) };
+        match voice_stealing::VoiceStealPolicy::from_u8(policy) {
+            Some(policy) => ctx.synth.steal_policy = policy,
+            None => error!("Invalid voice steal policy: {}", policy),
+        }
+        mem::forget(ctx);
+    }
+
+    /// Sets how notes glide from the pitch of the note played before them.  `sample_rate` is the
+    /// sample rate of the audio context that the synth is played in.
+    #[wasm_bindgen]
+    pub fn set_glide(
+        ctx: *mut PolySynthContext,
+        time_ms: f32,
+        poly_legato: bool,
+        sample_rate: f32,
+    ) {
+        let mut ctx = unsafe { Box::from_raw(ctx) };
+        ctx.synth.glide = glide::GlideConf {
+            time_ms,
+            poly_legato,
+        };
+        ctx.synth.sample_rate = sample_rate;
+        mem::forget(ctx);
+    }
+
+    /// Sets the amplitude envelope that voices are played with.  `sustain` is a level from 0 to 1.
+    #[wasm_bindgen]
+    pub fn set_adsr(
+        ctx: *mut PolySynthContext,
+        attack_ms: f32,
+        decay_ms: f32,
+        sustain: f32,
+        release_ms: f32,
+    ) {
+        let mut ctx = unsafe { Box::from_raw(ctx) };
+        ctx.synth.adsr = adsr::AdsrParams {
+            attack_ms,
+            decay_ms,
+            sustain,
+            release_ms,
+        };
+        mem::forget(ctx);
+    }
+
+    /// Returns the index of the voice playing `note_id` so that per-note events like pitch bends
+    /// can be routed to it.
+    #[wasm_bindgen]
+    pub fn get_playing_voice_ix(ctx: *mut PolySynthContext, note_id: usize) -> Option<usize> {
+        let ctx = unsafe { Box::from_raw(ctx) };
+        let voice_ix = ctx.synth.get_playing_voice_ix(note_id);
+        mem::forget(ctx);
+        voice_ix
+    }
+
     #[wasm_bindgen]
     pub fn release_all(ctx: *mut PolySynthContext) {
         let mut ctx = unsafe { Box::from_raw(ctx) };
+        ctx.synth.advance_envelopes(event_time(None));
+        let playing_note_ids: Vec<usize> = ctx
+            .synth
+            .voices
+            .iter()
+            .filter_map(|voice| match voice.playing {
+                VoicePlayingStatus::Playing(note_id) => Some(note_id),
+                VoicePlayingStatus::Tacent => None,
+            })
+            .collect();
+        for note_id in playing_note_ids {
+            release_note(&mut ctx, note_id, None);
+        }
         ctx.synth.release_all();
         mem::forget(ctx);
     }
@@ -0,0 +1,76 @@
+//! Unison plays several copies of each voice at once, detuned from each other and spread across
+//! the stereo field to thicken the sound.  Each copy costs a full oscillator, so the number of
+//! copies is limited based on the polyphony of the synth to keep CPU usage in check.
+
+/// The most copies that a single voice can be played with
+pub const MAX_UNISON_VOICES: usize = 16;
+/// The most oscillators that a synth can run across all of its voices.  Unison is limited so that
+/// a synth with every voice playing stays within this budget.
+pub const MAX_UNISON_OSCILLATORS: usize = 128;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UnisonConf {
+    /// The number of copies of each voice that are played
+    pub voices: usize,
+    /// The distance in cents between the two most detuned copies
+    pub detune_cents: f32,
+    /// How far the copies are spread across the stereo field, from 0 (mono) to 1 (hard left and
+    /// right for the outermost copies)
+    pub stereo_spread: f32,
+}
+
+impl Default for UnisonConf {
+    fn default() -> Self {
+        UnisonConf {
+            voices: 1,
+            detune_cents: 0.,
+            stereo_spread: 0.,
+        }
+    }
+}
+
+/// The settings of a single copy of a unison voice
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UnisonVoice {
+    pub detune_cents: f32,
+    /// From -1 (left) to 1 (right)
+    pub pan: f32,
+}
+
+/// Returns the most copies that each voice of a synth with `polyphony` voices can be played with
+pub fn max_unison_voices(polyphony: usize) -> usize {
+    (MAX_UNISON_OSCILLATORS / polyphony.max(1)).clamp(1, MAX_UNISON_VOICES)
+}
+
+impl UnisonConf {
+    /// Returns the number of copies that will actually be played for each voice of a synth with
+    /// `polyphony` voices
+    pub fn voice_count(&self, polyphony: usize) -> usize {
+        self.voices.clamp(1, max_unison_voices(polyphony))
+    }
+
+    /// Returns the settings of each copy of a voice.  Copies are spaced evenly from the lowest to
+    /// the highest detune, and their pans follow their detunes so that the copies furthest from
+    /// the center pitch are also furthest from the center of the stereo field.
+    pub fn layout(&self, polyphony: usize) -> Vec<UnisonVoice> {
+        let voice_count = self.voice_count(polyphony);
+        if voice_count == 1 {
+            return vec![UnisonVoice {
+                detune_cents: 0.,
+                pan: 0.,
+            }];
+        }
+
+        let stereo_spread = self.stereo_spread.clamp(0., 1.);
+        (0..voice_count)
+            .map(|i| {
+                // From -1 for the lowest copy to 1 for the highest
+                let position = (i as f32 / (voice_count - 1) as f32) * 2. - 1.;
+                UnisonVoice {
+                    detune_cents: position * self.detune_cents / 2.,
+                    pan: position * stereo_spread,
+                }
+            })
+            .collect()
+    }
+}
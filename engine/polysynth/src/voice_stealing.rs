@@ -0,0 +1,30 @@
+//! Policies for picking which voice plays a new note when all of the voices of a synth are
+//! already playing.  Pads usually want to steal the quietest voice so that the loss is hard to
+//! hear, while drums often want repeated hits of the same note to retrigger its voice.
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum VoiceStealPolicy {
+    /// Steals the voice that has been playing the longest
+    #[default]
+    StealOldest = 0,
+    /// Steals the voice that was attacked with the lowest velocity, picking the oldest of them if
+    /// several are tied
+    StealQuietest = 1,
+    /// Retriggers the voice that is already playing a note when that note is played again rather
+    /// than ignoring it.  Otherwise, steals the oldest voice.
+    StealSamePitch = 2,
+    /// Ignores new notes until a voice is released
+    DropNew = 3,
+}
+
+impl VoiceStealPolicy {
+    pub fn from_u8(val: u8) -> Option<Self> {
+        match val {
+            0 => Some(VoiceStealPolicy::StealOldest),
+            1 => Some(VoiceStealPolicy::StealQuietest),
+            2 => Some(VoiceStealPolicy::StealSamePitch),
+            3 => Some(VoiceStealPolicy::DropNew),
+            _ => None,
+        }
+    }
+}
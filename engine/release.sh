@@ -2,4 +2,8 @@ cd engine && cargo build --target wasm32-unknown-unknown --release && \
   cd ../midi && cargo build --target wasm32-unknown-unknown --release && \
   cd ../polysynth && cargo build --target wasm32-unknown-unknown --release --features wasm-bindgen-exports && \
   cd ../spectrum_viz && cargo build --target wasm32-unknown-unknown --release && \
-  cd ../wavetable && cargo build --target wasm32-unknown-unknown --release
+  cd ../wavetable && cargo build --target wasm32-unknown-unknown --release && \
+  cd ../filter && cargo build --target wasm32-unknown-unknown --release && \
+  cd ../reverb && cargo build --target wasm32-unknown-unknown --release && \
+  cd ../reverb && cargo build --target wasm32-unknown-unknown --release && \
+  cd ../sampler && cargo build --target wasm32-unknown-unknown --release
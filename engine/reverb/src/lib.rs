@@ -0,0 +1,190 @@
+//! Freeverb-style algorithmic reverb, processing stereo blocks of samples written into a shared
+//! buffer by the `ReverbNodeProcessor` AudioWorklet.
+
+#![feature(box_syntax)]
+
+use audio_block::{Block, BlockProcessor, WorkletHandle};
+
+const COMB_COUNT: usize = 8;
+const ALLPASS_COUNT: usize = 4;
+/// Delay line lengths in samples at 44.1kHz from the original Freeverb implementation
+const COMB_TUNINGS: [usize; COMB_COUNT] = [1116, 1188, 1277, 1356, 1422, 1491, 1557, 1617];
+const ALLPASS_TUNINGS: [usize; ALLPASS_COUNT] = [556, 441, 341, 225];
+/// Extra samples added to the delay lines of the right channel to decorrelate it from the left
+const STEREO_SPREAD: usize = 23;
+const TUNINGS_SAMPLE_RATE: f32 = 44_100.;
+
+const FIXED_GAIN: f32 = 0.015;
+const SCALE_ROOM: f32 = 0.28;
+const OFFSET_ROOM: f32 = 0.7;
+const SCALE_DAMPING: f32 = 0.4;
+const SCALE_WET: f32 = 3.;
+const ALLPASS_FEEDBACK: f32 = 0.5;
+/// Time in seconds that it takes for the wet mix to mostly settle after it changes or the reverb
+/// is bypassed, used to avoid clicks
+const MIX_SMOOTHING_SECONDS: f32 = 0.01;
+
+struct Comb {
+    buffer: Vec<f32>,
+    ix: usize,
+    filter_store: f32,
+}
+
+impl Comb {
+    fn new(len: usize) -> Self {
+        Comb {
+            buffer: vec![0.; len],
+            ix: 0,
+            filter_store: 0.,
+        }
+    }
+
+    #[inline(always)]
+    fn process(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let output = self.buffer[self.ix];
+        self.filter_store = output * (1. - damping) + self.filter_store * damping;
+        self.buffer[self.ix] = input + self.filter_store * feedback;
+        self.ix = (self.ix + 1) % self.buffer.len();
+        output
+    }
+}
+
+struct AllPass {
+    buffer: Vec<f32>,
+    ix: usize,
+}
+
+impl AllPass {
+    fn new(len: usize) -> Self {
+        AllPass {
+            buffer: vec![0.; len],
+            ix: 0,
+        }
+    }
+
+    #[inline(always)]
+    fn process(&mut self, input: f32) -> f32 {
+        let buffered = self.buffer[self.ix];
+        self.buffer[self.ix] = input + buffered * ALLPASS_FEEDBACK;
+        self.ix = (self.ix + 1) % self.buffer.len();
+        buffered - input
+    }
+}
+
+struct Channel {
+    combs: Vec<Comb>,
+    allpasses: Vec<AllPass>,
+}
+
+impl Channel {
+    fn new(sample_rate: f32, spread: usize) -> Self {
+        let scale =
+            |len: usize| (((len + spread) as f32 * sample_rate) / TUNINGS_SAMPLE_RATE) as usize;
+
+        Channel {
+            combs: COMB_TUNINGS.iter().map(|&len| Comb::new(scale(len).max(1))).collect(),
+            allpasses: ALLPASS_TUNINGS
+                .iter()
+                .map(|&len| AllPass::new(scale(len).max(1)))
+                .collect(),
+        }
+    }
+
+    #[inline(always)]
+    fn process(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let combed: f32 = self
+            .combs
+            .iter_mut()
+            .map(|comb| comb.process(input, feedback, damping))
+            .sum();
+        self.allpasses
+            .iter_mut()
+            .fold(combed, |acc, allpass| allpass.process(acc))
+    }
+}
+
+pub struct Reverb {
+    channels: [Channel; 2],
+    room_size: f32,
+    damping: f32,
+    wet: f32,
+    bypassed: bool,
+    /// The wet mix actually being applied, which glides towards `wet` (or 0 if bypassed)
+    cur_wet: f32,
+    smoothing_coefficient: f32,
+}
+
+impl Reverb {
+    pub fn new(sample_rate: f32) -> Self {
+        Reverb {
+            channels: [Channel::new(sample_rate, 0), Channel::new(sample_rate, STEREO_SPREAD)],
+            room_size: 0.5,
+            damping: 0.5,
+            wet: 0.3,
+            bypassed: false,
+            cur_wet: 0.,
+            smoothing_coefficient: (-1. / (MIX_SMOOTHING_SECONDS * sample_rate)).exp(),
+        }
+    }
+
+    /// `room_size`, `damping`, and `wet` are all in the range [0, 1].
+    pub fn set_params(&mut self, room_size: f32, damping: f32, wet: f32, bypassed: bool) {
+        self.room_size = room_size.clamp(0., 1.);
+        self.damping = damping.clamp(0., 1.);
+        self.wet = wet.clamp(0., 1.);
+        self.bypassed = bypassed;
+    }
+}
+
+impl BlockProcessor for Reverb {
+    /// Processes the block in place.  The reverb keeps running while bypassed so that its tail
+    /// fades out rather than being cut off.
+    fn process(&mut self, block: &mut Block) {
+        let feedback = self.room_size * SCALE_ROOM + OFFSET_ROOM;
+        let damping = self.damping * SCALE_DAMPING;
+        let target_wet = if self.bypassed { 0. } else { self.wet };
+        let (left, right) = block.channels_mut();
+
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            self.cur_wet = target_wet + (self.cur_wet - target_wet) * self.smoothing_coefficient;
+            let input = (*l + *r) * FIXED_GAIN;
+            let wet_l = self.channels[0].process(input, feedback, damping);
+            let wet_r = self.channels[1].process(input, feedback, damping);
+
+            let dry_level = 1. - self.cur_wet;
+            let wet_level = self.cur_wet * SCALE_WET;
+            *l = *l * dry_level + wet_l * wet_level;
+            *r = *r * dry_level + wet_r * wet_level;
+        }
+    }
+}
+
+#[no_mangle]
+pub fn init_reverb(sample_rate: f32) -> *mut WorkletHandle<Reverb> {
+    Box::into_raw(box WorkletHandle::new(Reverb::new(sample_rate)))
+}
+
+/// Returns a pointer to a block of `BLOCK_SIZE * 2` samples that stores the left channel followed
+/// by the right channel.  Input samples are written here and replaced with the output samples by
+/// `process_reverb`.
+#[no_mangle]
+pub fn get_io_buffer_ptr(reverb: *mut WorkletHandle<Reverb>) -> *mut f32 {
+    unsafe { (*reverb).io_buffer_ptr() }
+}
+
+#[no_mangle]
+pub fn set_reverb_params(
+    reverb: *mut WorkletHandle<Reverb>,
+    room_size: f32,
+    damping: f32,
+    wet: f32,
+    bypassed: bool,
+) {
+    unsafe { (*reverb).set_params(room_size, damping, wet, bypassed) }
+}
+
+#[no_mangle]
+pub fn process_reverb(reverb: *mut WorkletHandle<Reverb>) { unsafe { (*reverb).process() } }
+
+#[no_mangle]
+pub fn drop_reverb(reverb: *mut WorkletHandle<Reverb>) { drop(unsafe { Box::from_raw(reverb) }) }
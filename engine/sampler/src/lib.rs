@@ -0,0 +1,460 @@
+//! Polyphonic sampler that plays back decoded PCM samples at different pitches, rendering blocks of
+//! stereo samples into a shared buffer read by the `SamplerNodeProcessor` AudioWorklet.  Voices are
+//! addressed by the same voice indices that the polysynth voice manager hands out, so anything
+//! that can drive a synth can drive the sampler as well.
+//!
+//! Sliced samples have a range of notes starting at the slice base note that each play a single
+//! slice at its original pitch, which is how beat-sliced loops are played back from the MIDI
+//! editor.  Notes outside of that range play the whole sample as usual.
+
+// The exports are only called by the AudioWorklets with the pointers that they got from
+// `init_sampler` and `init_soundfont_player`
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use audio_block::{Block, BlockProcessor, Instrument, WorkletHandle, BLOCK_SIZE};
+
+pub mod soundfont;
+
+/// Voice indices past this are ignored
+const MAX_VOICES: usize = 64;
+/// Velocities are treated as the MIDI maximum of 127 being full volume
+pub(crate) const MAX_VELOCITY: f32 = 127.;
+/// Time in seconds that released voices take to fade out in loop mode
+const DEFAULT_RELEASE_SECONDS: f32 = 0.05;
+
+/// Mirrors `midiToFrequency` in `src/util.ts`.  Note IDs are the ones produced by the MIDI editor
+/// from its line indices, with 69 being A4.
+pub fn midi_to_frequency(note: f32) -> f32 { 2f32.powf((note - 69.) / 12.) * 440. }
+
+/// 4-point, 3rd-order Hermite interpolation between `y0` and `y1`
+#[inline(always)]
+pub(crate) fn hermite(x: f32, y_m1: f32, y0: f32, y1: f32, y2: f32) -> f32 {
+    let c1 = 0.5 * (y1 - y_m1);
+    let c2 = y_m1 - 2.5 * y0 + 2. * y1 - 0.5 * y2;
+    let c3 = 0.5 * (y2 - y_m1) + 1.5 * (y0 - y1);
+    ((c3 * x + c2) * x + c1) * x + y0
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlaybackMode {
+    /// The whole sample is played every time a note is triggered, ignoring releases
+    OneShot,
+    /// The region between the loop points is looped until the note is released, at which point it
+    /// fades out
+    Loop { start: usize, end: usize },
+}
+
+/// A decoded sample with its channels stored one after another
+pub struct Sample {
+    pub data: Vec<f32>,
+    pub channel_count: usize,
+    pub length: usize,
+    pub sample_rate: f32,
+}
+
+impl Sample {
+    fn channel(&self, channel_ix: usize) -> &[f32] {
+        let channel_ix = channel_ix.min(self.channel_count - 1);
+        &self.data[channel_ix * self.length..(channel_ix + 1) * self.length]
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum VoiceState {
+    Idle,
+    Playing,
+    /// Fading out; the voice goes idle once its gain reaches 0
+    Releasing,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Voice {
+    state: VoiceState,
+    note_id: usize,
+    /// Fractional position in the sample, in frames
+    position: f64,
+    /// Number of frames of the sample to advance for each output frame
+    rate: f64,
+    gain: f32,
+    /// Multiplier for `gain` that fades from 1 to 0 once the voice is released
+    envelope: f32,
+    /// Frame at which the voice stops if it's playing a slice.  Slices are never looped.
+    slice_end: Option<usize>,
+}
+
+impl Default for Voice {
+    fn default() -> Self {
+        Voice {
+            state: VoiceState::Idle,
+            note_id: 0,
+            position: 0.,
+            rate: 1.,
+            gain: 0.,
+            envelope: 0.,
+            slice_end: None,
+        }
+    }
+}
+
+pub struct Sampler {
+    output_sample_rate: f32,
+    sample: Option<Sample>,
+    /// Buffer that JS writes decoded samples into before they're committed with `commit_sample`
+    staged_sample: Vec<f32>,
+    /// Start frame of each slice in ascending order, written by JS before `commit_slices`
+    slices: Vec<usize>,
+    /// The note that plays the first slice
+    slice_base_note: usize,
+    voices: Vec<Voice>,
+    mode: PlaybackMode,
+    /// The note at which the sample is played back at its original pitch
+    root_note: f32,
+    /// The amount that the envelope of a releasing voice is reduced by each frame
+    release_step: f32,
+}
+
+impl Sampler {
+    pub fn new(output_sample_rate: f32) -> Self {
+        let mut sampler = Sampler {
+            output_sample_rate,
+            sample: None,
+            staged_sample: Vec::new(),
+            slices: Vec::new(),
+            slice_base_note: 36,
+            voices: Vec::new(),
+            mode: PlaybackMode::OneShot,
+            root_note: 60.,
+            release_step: 0.,
+        };
+        sampler.set_release_time(DEFAULT_RELEASE_SECONDS);
+        sampler
+    }
+
+    /// Replaces the sample with the one that was written into the staged sample buffer, stopping
+    /// all playing voices.  The loop points are clamped to the new sample's length.
+    pub fn commit_sample(&mut self, channel_count: usize, sample_rate: f32) {
+        let data = std::mem::take(&mut self.staged_sample);
+        self.set_sample(data, channel_count, sample_rate);
+    }
+
+    /// Replaces the sample with `data`, which holds all of the first channel's samples followed by
+    /// the second channel's.  Stops all playing voices.
+    pub fn set_sample(&mut self, data: Vec<f32>, channel_count: usize, sample_rate: f32) {
+        let channel_count = channel_count.max(1);
+        let length = data.len() / channel_count;
+        for voice in &mut self.voices {
+            voice.state = VoiceState::Idle;
+        }
+
+        self.sample = if length == 0 {
+            None
+        } else {
+            Some(Sample {
+                data,
+                channel_count,
+                length,
+                sample_rate,
+            })
+        };
+        self.set_mode(self.mode);
+    }
+
+    /// Loop points are in frames of the sample.  Loops shorter than a single frame are extended.
+    pub fn set_mode(&mut self, mode: PlaybackMode) {
+        self.mode = match (mode, &self.sample) {
+            (PlaybackMode::Loop { start, end }, Some(sample)) => {
+                let start = start.min(sample.length - 1);
+                PlaybackMode::Loop {
+                    start,
+                    end: end.min(sample.length).max(start + 1),
+                }
+            },
+            _ => mode,
+        };
+    }
+
+    pub fn set_root_note(&mut self, root_note: f32) { self.root_note = root_note; }
+
+    /// Sorts the slices written by JS and drops any past the end of the sample
+    pub fn commit_slices(&mut self, slice_base_note: usize) {
+        self.slice_base_note = slice_base_note;
+        self.slices.sort_unstable();
+        self.slices.dedup();
+        if let Some(sample) = &self.sample {
+            let length = sample.length;
+            self.slices.retain(|&start| start < length);
+        }
+    }
+
+    /// Returns the `(start, end)` frames of the slice that `note_id` plays, if any
+    fn get_slice(&self, note_id: usize) -> Option<(usize, usize)> {
+        let slice_ix = note_id.checked_sub(self.slice_base_note)?;
+        let start = *self.slices.get(slice_ix)?;
+        let end = match self.slices.get(slice_ix + 1) {
+            Some(&end) => end,
+            None => self.sample.as_ref()?.length,
+        };
+        Some((start, end))
+    }
+
+    pub fn set_release_time(&mut self, seconds: f32) {
+        self.release_step = 1. / (seconds.max(0.001) * self.output_sample_rate);
+    }
+
+    fn get_voice_mut(&mut self, voice_ix: usize) -> Option<&mut Voice> {
+        if voice_ix >= MAX_VOICES {
+            return None;
+        }
+        if self.voices.len() <= voice_ix {
+            self.voices.resize(voice_ix + 1, Voice::default());
+        }
+        Some(&mut self.voices[voice_ix])
+    }
+
+    /// Starts playing the sample on the provided voice, pitched so that `root_note` plays it back
+    /// at its original speed.  Notes that play slices play them at their original speed.
+    pub fn trigger_attack(&mut self, voice_ix: usize, note_id: usize, velocity: u8) {
+        let sample_rate = match &self.sample {
+            Some(sample) => sample.sample_rate,
+            None => return,
+        };
+        let slice = self.get_slice(note_id);
+        let pitch_ratio = match slice {
+            Some(_) => 1.,
+            None => midi_to_frequency(note_id as f32) / midi_to_frequency(self.root_note),
+        };
+        let rate = pitch_ratio as f64 * (sample_rate / self.output_sample_rate) as f64;
+
+        if let Some(voice) = self.get_voice_mut(voice_ix) {
+            *voice = Voice {
+                state: VoiceState::Playing,
+                note_id,
+                position: slice.map(|(start, _)| start as f64).unwrap_or(0.),
+                rate,
+                gain: (velocity as f32 / MAX_VELOCITY).min(1.),
+                envelope: 1.,
+                slice_end: slice.map(|(_, end)| end),
+            };
+        }
+    }
+
+    /// Releases the voice if it's still playing the provided note.  Releases are ignored in
+    /// one-shot mode.
+    pub fn trigger_release(&mut self, voice_ix: usize, note_id: usize) {
+        if self.mode == PlaybackMode::OneShot {
+            return;
+        }
+
+        if let Some(voice) = self.voices.get_mut(voice_ix) {
+            if voice.state == VoiceState::Playing && voice.note_id == note_id {
+                voice.state = VoiceState::Releasing;
+            }
+        }
+    }
+
+    pub fn release_all(&mut self) {
+        for voice in &mut self.voices {
+            if voice.state != VoiceState::Idle {
+                voice.state = VoiceState::Releasing;
+            }
+        }
+    }
+
+    /// Reads the sample at a fractional frame, wrapping around the loop points if looping
+    fn read_frame(channel: &[f32], position: f64, loop_points: Option<(usize, usize)>) -> f32 {
+        let base_ix = position.floor() as isize;
+        let get = |ix: isize| -> f32 {
+            let ix = match loop_points {
+                Some((start, end)) if ix >= end as isize =>
+                    start as isize + (ix - end as isize) % (end - start) as isize,
+                _ => ix,
+            };
+            if ix < 0 || ix as usize >= channel.len() {
+                0.
+            } else {
+                channel[ix as usize]
+            }
+        };
+
+        hermite(
+            position.fract() as f32,
+            get(base_ix - 1),
+            get(base_ix),
+            get(base_ix + 1),
+            get(base_ix + 2),
+        )
+    }
+
+    /// Renders frames `[start, end)` of the block.  Rendering a block in multiple parts allows
+    /// events to be applied at the exact frame that they're scheduled for.
+    pub fn render(&mut self, block: &mut Block, start: usize, end: usize) {
+        let end = end.min(BLOCK_SIZE);
+        block.clear_range(start, end);
+        let (left, right) = block.channels_mut();
+
+        let sample = match &self.sample {
+            Some(sample) => sample,
+            None => return,
+        };
+        let loop_points = match self.mode {
+            PlaybackMode::Loop { start, end } => Some((start, end)),
+            PlaybackMode::OneShot => None,
+        };
+        let (sample_l, sample_r) = (sample.channel(0), sample.channel(1));
+
+        for voice in self.voices.iter_mut() {
+            let loop_points = if voice.slice_end.is_some() {
+                None
+            } else {
+                loop_points
+            };
+            let end_frame = voice.slice_end.unwrap_or(sample.length) as f64;
+            for i in start..end {
+                if voice.state == VoiceState::Idle {
+                    break;
+                }
+
+                if voice.state == VoiceState::Releasing {
+                    voice.envelope -= self.release_step;
+                    if voice.envelope <= 0. {
+                        voice.state = VoiceState::Idle;
+                        break;
+                    }
+                }
+
+                let gain = voice.gain * voice.envelope;
+                left[i] += Self::read_frame(sample_l, voice.position, loop_points) * gain;
+                right[i] += Self::read_frame(sample_r, voice.position, loop_points) * gain;
+
+                voice.position += voice.rate;
+                match loop_points {
+                    Some((loop_start, loop_end)) if voice.position >= loop_end as f64 =>
+                        voice.position -= (loop_end - loop_start) as f64,
+                    None if voice.position >= end_frame => voice.state = VoiceState::Idle,
+                    _ => (),
+                }
+            }
+        }
+    }
+}
+
+impl BlockProcessor for Sampler {
+    fn process(&mut self, block: &mut Block) { self.render(block, 0, BLOCK_SIZE) }
+}
+
+impl Instrument for Sampler {
+    fn trigger_attack(&mut self, voice_ix: usize, note_id: usize, velocity: u8) {
+        Sampler::trigger_attack(self, voice_ix, note_id, velocity)
+    }
+
+    fn trigger_release(&mut self, voice_ix: usize, note_id: usize) {
+        Sampler::trigger_release(self, voice_ix, note_id)
+    }
+
+    fn render(&mut self, block: &mut Block, start: usize, end: usize) {
+        Sampler::render(self, block, start, end)
+    }
+}
+
+#[no_mangle]
+pub fn init_sampler(sample_rate: f32) -> *mut WorkletHandle<Sampler> {
+    Box::into_raw(Box::new(WorkletHandle::new(Sampler::new(sample_rate))))
+}
+
+/// Returns a pointer to a block of `BLOCK_SIZE * 2` samples that stores the left channel followed
+/// by the right channel.
+#[no_mangle]
+pub fn get_io_buffer_ptr(sampler: *mut WorkletHandle<Sampler>) -> *mut f32 {
+    unsafe { (*sampler).io_buffer_ptr() }
+}
+
+/// Returns a pointer to a buffer of `len` samples that the decoded sample should be written into,
+/// with its channels one after another.  The sample isn't used until `commit_sample` is called.
+#[no_mangle]
+pub fn get_staged_sample_ptr(sampler: *mut WorkletHandle<Sampler>, len: usize) -> *mut f32 {
+    let sampler = unsafe { &mut *sampler };
+    sampler.staged_sample = vec![0.; len];
+    sampler.staged_sample.as_mut_ptr()
+}
+
+#[no_mangle]
+pub fn commit_sampler_sample(
+    sampler: *mut WorkletHandle<Sampler>,
+    channel_count: usize,
+    sample_rate: f32,
+) {
+    unsafe { (*sampler).commit_sample(channel_count, sample_rate) }
+}
+
+/// Returns a pointer to a buffer of `count` slice start frames that should be written before
+/// calling `commit_sampler_slices`.  A count of 0 turns slicing off.
+#[no_mangle]
+pub fn get_staged_slices_ptr(sampler: *mut WorkletHandle<Sampler>, count: usize) -> *mut usize {
+    let sampler = unsafe { &mut *sampler };
+    sampler.slices = vec![0; count];
+    sampler.slices.as_mut_ptr()
+}
+
+#[no_mangle]
+pub fn commit_sampler_slices(sampler: *mut WorkletHandle<Sampler>, slice_base_note: usize) {
+    unsafe { (*sampler).commit_slices(slice_base_note) }
+}
+
+/// Loop points are ignored if `looping` is false, in which case the sampler is in one-shot mode.
+#[no_mangle]
+pub fn set_sampler_params(
+    sampler: *mut WorkletHandle<Sampler>,
+    root_note: f32,
+    looping: bool,
+    loop_start: usize,
+    loop_end: usize,
+    release_seconds: f32,
+) {
+    let sampler = unsafe { &mut *sampler };
+    sampler.set_root_note(root_note);
+    sampler.set_mode(if looping {
+        PlaybackMode::Loop {
+            start: loop_start,
+            end: loop_end,
+        }
+    } else {
+        PlaybackMode::OneShot
+    });
+    sampler.set_release_time(release_seconds);
+}
+
+#[no_mangle]
+pub fn trigger_sampler_attack(
+    sampler: *mut WorkletHandle<Sampler>,
+    voice_ix: usize,
+    note_id: usize,
+    velocity: u8,
+) {
+    unsafe { (*sampler).trigger_attack(voice_ix, note_id, velocity) }
+}
+
+#[no_mangle]
+pub fn trigger_sampler_release(
+    sampler: *mut WorkletHandle<Sampler>,
+    voice_ix: usize,
+    note_id: usize,
+) {
+    unsafe { (*sampler).trigger_release(voice_ix, note_id) }
+}
+
+#[no_mangle]
+pub fn release_all_sampler_voices(sampler: *mut WorkletHandle<Sampler>) {
+    unsafe { (*sampler).release_all() }
+}
+
+/// Renders frames `[start, end)` of the IO buffer
+#[no_mangle]
+pub fn render_sampler(sampler: *mut WorkletHandle<Sampler>, start: usize, end: usize) {
+    let sampler = unsafe { &mut *sampler };
+    sampler.processor.render(&mut sampler.io_buffer, start, end)
+}
+
+#[no_mangle]
+pub fn drop_sampler(sampler: *mut WorkletHandle<Sampler>) {
+    drop(unsafe { Box::from_raw(sampler) })
+}
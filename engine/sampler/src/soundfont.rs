@@ -0,0 +1,582 @@
+//! Plays the presets of SoundFonts, rendering into a shared buffer read by the
+//! `SoundFontNodeProcessor` AudioWorklet.  The engine resolves a preset into a flat list of regions
+//! that each play one sample over a range of keys and velocities; JS writes the regions and their
+//! samples in here, and each note plays every region that it falls into.
+//!
+//! Voices are addressed by the same voice indices as the sampler, but since a preset can layer
+//! several regions on a single note, each voice index can have multiple layers playing at once.
+
+use audio_block::{Block, BlockProcessor, Instrument, WorkletHandle, BLOCK_SIZE};
+
+use crate::{hermite, MAX_VELOCITY};
+
+/// Maximum number of layers playing at once.  The oldest layer is stopped to make room past this.
+const MAX_LAYERS: usize = 128;
+/// Number of `f32`s in each region record written by JS, in the order of the `region_param`
+/// constants
+pub const REGION_PARAM_COUNT: usize = 22;
+/// Level below which released layers are stopped, which is 100dB below full volume
+const SILENCE_LEVEL: f32 = 0.000_01;
+/// Time in seconds that layers take to fade out when cut off by another note in their exclusive
+/// class
+const EXCLUSIVE_RELEASE_SECONDS: f32 = 0.005;
+
+/// Offsets of the params within each region record
+mod region_param {
+    pub const KEY_LO: usize = 0;
+    pub const KEY_HI: usize = 1;
+    pub const VEL_LO: usize = 2;
+    pub const VEL_HI: usize = 3;
+    pub const SAMPLE_IX: usize = 4;
+    pub const START: usize = 5;
+    pub const END: usize = 6;
+    pub const LOOP_MODE: usize = 7;
+    pub const LOOP_START: usize = 8;
+    pub const LOOP_END: usize = 9;
+    pub const ROOT_KEY: usize = 10;
+    pub const TUNE_CENTS: usize = 11;
+    pub const SCALE_TUNING: usize = 12;
+    pub const GAIN: usize = 13;
+    pub const PAN: usize = 14;
+    pub const DELAY: usize = 15;
+    pub const ATTACK: usize = 16;
+    pub const HOLD: usize = 17;
+    pub const DECAY: usize = 18;
+    pub const SUSTAIN_LEVEL: usize = 19;
+    pub const RELEASE: usize = 20;
+    pub const EXCLUSIVE_CLASS: usize = 21;
+}
+
+/// Matches the `sampleModes` generator of the SoundFont spec
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LoopMode {
+    NoLoop,
+    Continuous,
+    UntilRelease,
+}
+
+impl LoopMode {
+    fn from_param(param: f32) -> Self {
+        match param as u8 {
+            1 => LoopMode::Continuous,
+            3 => LoopMode::UntilRelease,
+            _ => LoopMode::NoLoop,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Region {
+    pub key_lo: usize,
+    pub key_hi: usize,
+    pub vel_lo: u8,
+    pub vel_hi: u8,
+    pub sample_ix: usize,
+    /// Frames of the sample that the region plays, with the end being exclusive
+    pub start: usize,
+    pub end: usize,
+    pub loop_mode: LoopMode,
+    pub loop_start: usize,
+    pub loop_end: usize,
+    pub root_key: f32,
+    pub tune_cents: f32,
+    /// Cents of pitch change per key
+    pub scale_tuning: f32,
+    pub gain: f32,
+    /// In `[-1, 1]`
+    pub pan: f32,
+    /// Volume envelope stage lengths in seconds
+    pub delay: f32,
+    pub attack: f32,
+    pub hold: f32,
+    pub decay: f32,
+    pub sustain_level: f32,
+    pub release: f32,
+    pub exclusive_class: u32,
+}
+
+impl Region {
+    pub fn from_record(record: &[f32]) -> Self {
+        use region_param::*;
+
+        let start = record[START] as usize;
+        let end = (record[END] as usize).max(start);
+        Region {
+            key_lo: record[KEY_LO] as usize,
+            key_hi: record[KEY_HI] as usize,
+            vel_lo: record[VEL_LO] as u8,
+            vel_hi: record[VEL_HI] as u8,
+            sample_ix: record[SAMPLE_IX] as usize,
+            start,
+            end,
+            loop_mode: LoopMode::from_param(record[LOOP_MODE]),
+            loop_start: record[LOOP_START] as usize,
+            loop_end: record[LOOP_END] as usize,
+            root_key: record[ROOT_KEY],
+            tune_cents: record[TUNE_CENTS],
+            scale_tuning: record[SCALE_TUNING],
+            gain: record[GAIN],
+            pan: record[PAN].clamp(-1., 1.),
+            delay: record[DELAY],
+            attack: record[ATTACK],
+            hold: record[HOLD],
+            decay: record[DECAY],
+            sustain_level: record[SUSTAIN_LEVEL].clamp(0., 1.),
+            release: record[RELEASE],
+            exclusive_class: record[EXCLUSIVE_CLASS] as u32,
+        }
+    }
+
+    fn matches(&self, note_id: usize, velocity: u8) -> bool {
+        (self.key_lo..=self.key_hi).contains(&note_id)
+            && (self.vel_lo..=self.vel_hi).contains(&velocity)
+    }
+
+    /// Returns the loop points if the region loops and they're valid
+    fn loop_points(&self) -> Option<(usize, usize)> {
+        if self.loop_mode == LoopMode::NoLoop
+            || self.loop_end <= self.loop_start
+            || self.loop_end > self.end
+        {
+            return None;
+        }
+        Some((self.loop_start, self.loop_end))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum EnvelopeStage {
+    Delay,
+    Attack,
+    Hold,
+    Decay,
+    Sustain,
+    Release,
+}
+
+/// DAHDSR volume envelope.  The attack is linear, while the decay and release are exponential and
+/// fall by 100dB over their length as the SoundFont spec describes.
+#[derive(Clone, Debug)]
+struct Envelope {
+    stage: EnvelopeStage,
+    level: f32,
+    /// Frames left in the delay or hold stage
+    frames_left: usize,
+    attack_step: f32,
+    hold_frames: usize,
+    decay_factor: f32,
+    sustain_level: f32,
+    release_factor: f32,
+}
+
+/// Returns the amount to multiply a level by each frame so that it falls by 100dB over `seconds`
+fn exponential_fall_factor(seconds: f32, sample_rate: f32) -> f32 {
+    SILENCE_LEVEL.powf(1. / (seconds.max(0.001) * sample_rate))
+}
+
+impl Envelope {
+    fn new(region: &Region, sample_rate: f32) -> Self {
+        Envelope {
+            stage: EnvelopeStage::Delay,
+            level: 0.,
+            frames_left: (region.delay * sample_rate) as usize,
+            attack_step: 1. / (region.attack * sample_rate).max(1.),
+            hold_frames: (region.hold * sample_rate) as usize,
+            decay_factor: exponential_fall_factor(region.decay, sample_rate),
+            sustain_level: region.sustain_level,
+            release_factor: exponential_fall_factor(region.release, sample_rate),
+        }
+    }
+
+    fn release(&mut self, release_factor: Option<f32>) {
+        self.stage = EnvelopeStage::Release;
+        if let Some(release_factor) = release_factor {
+            self.release_factor = release_factor;
+        }
+    }
+
+    /// Advances the envelope by a frame and returns its level, or `None` once it's done
+    fn tick(&mut self) -> Option<f32> {
+        match self.stage {
+            EnvelopeStage::Delay =>
+                if self.frames_left == 0 {
+                    self.stage = EnvelopeStage::Attack;
+                } else {
+                    self.frames_left -= 1;
+                },
+            EnvelopeStage::Attack => {
+                self.level += self.attack_step;
+                if self.level >= 1. {
+                    self.level = 1.;
+                    self.stage = EnvelopeStage::Hold;
+                    self.frames_left = self.hold_frames;
+                }
+            },
+            EnvelopeStage::Hold =>
+                if self.frames_left == 0 {
+                    self.stage = EnvelopeStage::Decay;
+                } else {
+                    self.frames_left -= 1;
+                },
+            EnvelopeStage::Decay => {
+                self.level *= self.decay_factor;
+                if self.level <= self.sustain_level {
+                    self.level = self.sustain_level;
+                    self.stage = EnvelopeStage::Sustain;
+                }
+            },
+            EnvelopeStage::Sustain => (),
+            EnvelopeStage::Release => self.level *= self.release_factor,
+        }
+
+        // Decaying or sustaining at silence is the same as having finished
+        let rising = self.stage == EnvelopeStage::Delay || self.stage == EnvelopeStage::Attack;
+        if !rising && self.level < SILENCE_LEVEL {
+            None
+        } else {
+            Some(self.level)
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Layer {
+    voice_ix: usize,
+    note_id: usize,
+    region_ix: usize,
+    /// Fractional position in the sample, in frames
+    position: f64,
+    /// Number of frames of the sample to advance for each output frame
+    rate: f64,
+    gain_l: f32,
+    gain_r: f32,
+    envelope: Envelope,
+    released: bool,
+}
+
+#[derive(Default)]
+pub struct SoundFontPlayer {
+    output_sample_rate: f32,
+    /// Mono samples along with their sample rates
+    samples: Vec<(Vec<f32>, f32)>,
+    /// Buffer that JS writes samples into before they're committed with `commit_sample`
+    staged_sample: Vec<f32>,
+    regions: Vec<Region>,
+    /// Buffer of region records that JS writes before they're committed with `commit_regions`
+    staged_regions: Vec<f32>,
+    layers: Vec<Layer>,
+}
+
+impl SoundFontPlayer {
+    pub fn new(output_sample_rate: f32) -> Self {
+        SoundFontPlayer {
+            output_sample_rate,
+            ..Default::default()
+        }
+    }
+
+    /// Stores the sample that was written into the staged sample buffer at `sample_ix`
+    pub fn commit_sample(&mut self, sample_ix: usize, sample_rate: f32) {
+        let data = std::mem::take(&mut self.staged_sample);
+        if self.samples.len() <= sample_ix {
+            self.samples
+                .resize(sample_ix + 1, (Vec::new(), sample_rate));
+        }
+        self.samples[sample_ix] = (data, sample_rate);
+    }
+
+    /// Replaces the regions with the ones that were written into the staged regions buffer,
+    /// stopping all playing layers.  Samples that aren't used by any of the new regions are freed.
+    pub fn commit_regions(&mut self) {
+        self.layers.clear();
+        self.regions = self
+            .staged_regions
+            .chunks_exact(REGION_PARAM_COUNT)
+            .map(Region::from_record)
+            .collect();
+        self.staged_regions.clear();
+
+        let sample_count = self
+            .regions
+            .iter()
+            .map(|region| region.sample_ix + 1)
+            .max()
+            .unwrap_or(0);
+        self.samples.truncate(sample_count);
+    }
+
+    /// Starts playing every region that the note and velocity fall into on the provided voice
+    pub fn trigger_attack(&mut self, voice_ix: usize, note_id: usize, velocity: u8) {
+        let velocity = velocity.min(MAX_VELOCITY as u8);
+        // Velocity follows a squared curve, roughly matching the SoundFont spec's default velocity
+        // to attenuation modulator
+        let velocity_gain = (velocity as f32 / MAX_VELOCITY).powi(2);
+        let exclusive_release_factor =
+            exponential_fall_factor(EXCLUSIVE_RELEASE_SECONDS, self.output_sample_rate);
+
+        let (regions, samples) = (&self.regions, &self.samples);
+        let matching_region_ixs: Vec<usize> = (0..regions.len())
+            .filter(|&region_ix| {
+                let region = &regions[region_ix];
+                let has_sample = samples
+                    .get(region.sample_ix)
+                    .map(|(data, _)| !data.is_empty())
+                    .unwrap_or(false);
+                region.matches(note_id, velocity) && has_sample
+            })
+            .collect();
+
+        // Layers of this note can share an exclusive class, such as the two sides of a stereo
+        // sample, so only the layers that were already playing are cut off
+        for &region_ix in &matching_region_ixs {
+            let exclusive_class = regions[region_ix].exclusive_class;
+            if exclusive_class == 0 {
+                continue;
+            }
+            for layer in &mut self.layers {
+                if regions[layer.region_ix].exclusive_class == exclusive_class {
+                    layer.envelope.release(Some(exclusive_release_factor));
+                }
+            }
+        }
+
+        for region_ix in matching_region_ixs {
+            let region = &self.regions[region_ix];
+            let sample_rate = self.samples[region.sample_ix].1;
+            let cents =
+                (note_id as f32 - region.root_key) * region.scale_tuning + region.tune_cents;
+            let rate =
+                2f64.powf(cents as f64 / 1200.) * (sample_rate / self.output_sample_rate) as f64;
+            // Equal-power panning
+            let pan_angle = (region.pan + 1.) * std::f32::consts::FRAC_PI_4;
+            let gain = region.gain * velocity_gain;
+            let layer = Layer {
+                voice_ix,
+                note_id,
+                region_ix,
+                position: region.start as f64,
+                rate,
+                gain_l: gain * pan_angle.cos(),
+                gain_r: gain * pan_angle.sin(),
+                envelope: Envelope::new(region, self.output_sample_rate),
+                released: false,
+            };
+
+            if self.layers.len() >= MAX_LAYERS {
+                self.layers.remove(0);
+            }
+            self.layers.push(layer);
+        }
+    }
+
+    /// Releases all layers on the voice that are still playing the provided note
+    pub fn trigger_release(&mut self, voice_ix: usize, note_id: usize) {
+        for layer in &mut self.layers {
+            if layer.voice_ix == voice_ix && layer.note_id == note_id && !layer.released {
+                layer.released = true;
+                layer.envelope.release(None);
+            }
+        }
+    }
+
+    pub fn release_all(&mut self) {
+        for layer in &mut self.layers {
+            layer.released = true;
+            layer.envelope.release(None);
+        }
+    }
+
+    /// Reads the sample at a fractional frame, wrapping around the loop points if looping
+    fn read_frame(
+        data: &[f32],
+        position: f64,
+        end: usize,
+        loop_points: Option<(usize, usize)>,
+    ) -> f32 {
+        let base_ix = position.floor() as isize;
+        let get = |ix: isize| -> f32 {
+            let ix = match loop_points {
+                Some((start, end)) if ix >= end as isize =>
+                    start as isize + (ix - end as isize) % (end - start) as isize,
+                _ => ix,
+            };
+            if ix < 0 || ix as usize >= end.min(data.len()) {
+                0.
+            } else {
+                data[ix as usize]
+            }
+        };
+
+        hermite(
+            position.fract() as f32,
+            get(base_ix - 1),
+            get(base_ix),
+            get(base_ix + 1),
+            get(base_ix + 2),
+        )
+    }
+
+    /// Renders frames `[start, end)` of the block
+    pub fn render(&mut self, block: &mut Block, start: usize, end: usize) {
+        let end = end.min(BLOCK_SIZE);
+        block.clear_range(start, end);
+        let (left, right) = block.channels_mut();
+
+        let mut layer_ix = 0;
+        while layer_ix < self.layers.len() {
+            let layer = &mut self.layers[layer_ix];
+            let region = &self.regions[layer.region_ix];
+            let data = &self.samples[region.sample_ix].0;
+            if Self::render_layer(
+                layer,
+                region,
+                data,
+                &mut left[start..end],
+                &mut right[start..end],
+            ) {
+                layer_ix += 1;
+            } else {
+                self.layers.remove(layer_ix);
+            }
+        }
+    }
+
+    /// Adds the layer into the output buffers, returning `false` if it finished playing
+    fn render_layer(
+        layer: &mut Layer,
+        region: &Region,
+        data: &[f32],
+        left: &mut [f32],
+        right: &mut [f32],
+    ) -> bool {
+        for (out_l, out_r) in left.iter_mut().zip(right.iter_mut()) {
+            // Layers that loop until release play out the rest of the sample once released
+            let loop_points = match region.loop_mode {
+                LoopMode::UntilRelease if layer.released => None,
+                _ => region.loop_points(),
+            };
+            let level = match layer.envelope.tick() {
+                Some(level) => level,
+                None => return false,
+            };
+
+            let frame = Self::read_frame(data, layer.position, region.end, loop_points) * level;
+            *out_l += frame * layer.gain_l;
+            *out_r += frame * layer.gain_r;
+
+            layer.position += layer.rate;
+            match loop_points {
+                Some((loop_start, loop_end)) if layer.position >= loop_end as f64 =>
+                    layer.position -= (loop_end - loop_start) as f64,
+                None if layer.position >= region.end as f64 => return false,
+                _ => (),
+            }
+        }
+        true
+    }
+}
+
+impl BlockProcessor for SoundFontPlayer {
+    fn process(&mut self, block: &mut Block) { self.render(block, 0, BLOCK_SIZE) }
+}
+
+impl Instrument for SoundFontPlayer {
+    fn trigger_attack(&mut self, voice_ix: usize, note_id: usize, velocity: u8) {
+        SoundFontPlayer::trigger_attack(self, voice_ix, note_id, velocity)
+    }
+
+    fn trigger_release(&mut self, voice_ix: usize, note_id: usize) {
+        SoundFontPlayer::trigger_release(self, voice_ix, note_id)
+    }
+
+    fn render(&mut self, block: &mut Block, start: usize, end: usize) {
+        SoundFontPlayer::render(self, block, start, end)
+    }
+}
+
+#[no_mangle]
+pub fn init_soundfont_player(sample_rate: f32) -> *mut WorkletHandle<SoundFontPlayer> {
+    Box::into_raw(Box::new(WorkletHandle::new(SoundFontPlayer::new(sample_rate))))
+}
+
+/// Returns a pointer to a block of `BLOCK_SIZE * 2` samples that stores the left channel followed
+/// by the right channel.
+#[no_mangle]
+pub fn soundfont_get_io_buffer_ptr(player: *mut WorkletHandle<SoundFontPlayer>) -> *mut f32 {
+    unsafe { (*player).io_buffer_ptr() }
+}
+
+/// Returns a pointer to a buffer of `len` samples that a mono sample should be written into.  The
+/// sample isn't used until `soundfont_commit_sample` is called.
+#[no_mangle]
+pub fn soundfont_get_staged_sample_ptr(
+    player: *mut WorkletHandle<SoundFontPlayer>,
+    len: usize,
+) -> *mut f32 {
+    let player = unsafe { &mut *player };
+    player.staged_sample = vec![0.; len];
+    player.staged_sample.as_mut_ptr()
+}
+
+#[no_mangle]
+pub fn soundfont_commit_sample(
+    player: *mut WorkletHandle<SoundFontPlayer>,
+    sample_ix: usize,
+    sample_rate: f32,
+) {
+    unsafe { (*player).commit_sample(sample_ix, sample_rate) }
+}
+
+/// Returns a pointer to a buffer of `count` region records of `REGION_PARAM_COUNT` `f32`s each
+/// that should be written before calling `soundfont_commit_regions`.  All samples that the regions
+/// use should be committed first.
+#[no_mangle]
+pub fn soundfont_get_staged_regions_ptr(
+    player: *mut WorkletHandle<SoundFontPlayer>,
+    count: usize,
+) -> *mut f32 {
+    let player = unsafe { &mut *player };
+    player.staged_regions = vec![0.; count * REGION_PARAM_COUNT];
+    player.staged_regions.as_mut_ptr()
+}
+
+#[no_mangle]
+pub fn soundfont_commit_regions(player: *mut WorkletHandle<SoundFontPlayer>) {
+    unsafe { (*player).commit_regions() }
+}
+
+#[no_mangle]
+pub fn soundfont_trigger_attack(
+    player: *mut WorkletHandle<SoundFontPlayer>,
+    voice_ix: usize,
+    note_id: usize,
+    velocity: u8,
+) {
+    unsafe { (*player).trigger_attack(voice_ix, note_id, velocity) }
+}
+
+#[no_mangle]
+pub fn soundfont_trigger_release(
+    player: *mut WorkletHandle<SoundFontPlayer>,
+    voice_ix: usize,
+    note_id: usize,
+) {
+    unsafe { (*player).trigger_release(voice_ix, note_id) }
+}
+
+#[no_mangle]
+pub fn soundfont_release_all(player: *mut WorkletHandle<SoundFontPlayer>) {
+    unsafe { (*player).release_all() }
+}
+
+/// Renders frames `[start, end)` of the IO buffer
+#[no_mangle]
+pub fn render_soundfont(player: *mut WorkletHandle<SoundFontPlayer>, start: usize, end: usize) {
+    let player = unsafe { &mut *player };
+    player.processor.render(&mut player.io_buffer, start, end)
+}
+
+#[no_mangle]
+pub fn drop_soundfont_player(player: *mut WorkletHandle<SoundFontPlayer>) {
+    drop(unsafe { Box::from_raw(player) })
+}
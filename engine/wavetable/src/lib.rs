@@ -2,6 +2,8 @@
 
 use std::mem::{self, transmute};
 
+use audio_block::{Block, BlockProcessor, BLOCK_SIZE};
+
 pub struct WaveTableSettings {
     /// Number of `f32` samples in a single waveform
     pub waveform_length: usize,
@@ -25,54 +27,142 @@ impl WaveTableSettings {
     }
 }
 
+/// Mip levels are not created for waveforms that would be shorter than this many samples
+const MIN_MIP_WAVEFORM_LENGTH: usize = 16;
+
 pub struct WaveTable {
     pub settings: WaveTableSettings,
     pub samples: Vec<f32>,
+    /// Band-limited copies of `samples`.  The waveforms in each level are half as long as the ones
+    /// in the level before it and are low-pass filtered so that they can be played back at twice
+    /// the frequency without aliasing.  The first entry corresponds to mip level 1; `samples` is
+    /// level 0.
+    pub mip_levels: Vec<Vec<f32>>,
 }
 
 fn mix(mix_factor: f32, low: f32, high: f32) -> f32 {
     ((1.0 - mix_factor) * low) + (mix_factor * high)
 }
 
+/// Samples a single-cycle waveform at a fractional index, wrapping around its end
+fn sample_circular(waveform: &[f32], sample_ix: f32) -> f32 {
+    let sample_ix = sample_ix.rem_euclid(waveform.len() as f32);
+    let low_ix = sample_ix.floor() as usize % waveform.len();
+    let hi_ix = (low_ix + 1) % waveform.len();
+    mix(sample_ix.fract(), waveform[low_ix], waveform[hi_ix])
+}
+
+/// Applies a 5-tap binomial low-pass filter to a single-cycle waveform centered at a fractional
+/// index.
+fn sample_lowpass(waveform: &[f32], sample_ix: f32, tap_spacing: f32) -> f32 {
+    const TAPS: [f32; 5] = [1. / 16., 4. / 16., 6. / 16., 4. / 16., 1. / 16.];
+
+    TAPS.iter()
+        .enumerate()
+        .map(|(i, tap)| {
+            let offset = (i as f32 - 2.) * tap_spacing;
+            tap * sample_circular(waveform, sample_ix + offset)
+        })
+        .sum()
+}
+
 impl WaveTable {
     pub fn new(settings: WaveTableSettings) -> Self {
         let wavetable_data_size = settings.get_wavetable_size();
         WaveTable {
             settings,
             samples: vec![-1.0; wavetable_data_size],
+            mip_levels: Vec::new(),
+        }
+    }
+
+    fn get_mip_level_waveform_length(&self, mip_level: usize) -> usize {
+        self.settings.waveform_length >> mip_level
+    }
+
+    /// Builds the band-limited mip levels from `samples`.  This must be called again any time that
+    /// `samples` is changed.
+    pub fn build_mip_levels(&mut self) {
+        self.mip_levels.clear();
+        let waveform_count = self.settings.dimension_count * self.settings.waveforms_per_dimension;
+
+        let mut src_waveform_length = self.settings.waveform_length;
+        while src_waveform_length / 2 >= MIN_MIP_WAVEFORM_LENGTH {
+            let waveform_length = src_waveform_length / 2;
+            let stride = src_waveform_length as f32 / waveform_length as f32;
+            let src_samples: &[f32] = self.mip_levels.last().unwrap_or(&self.samples);
+
+            let mut level = Vec::with_capacity(waveform_count * waveform_length);
+            for src_waveform in src_samples.chunks_exact(src_waveform_length) {
+                for sample_ix in 0..waveform_length {
+                    level.push(sample_lowpass(
+                        src_waveform,
+                        sample_ix as f32 * stride,
+                        stride / 2.,
+                    ));
+                }
+            }
+
+            self.mip_levels.push(level);
+            src_waveform_length = waveform_length;
         }
     }
 
-    fn sample_waveform(&self, dimension_ix: usize, waveform_ix: usize, sample_ix: f32) -> f32 {
-        let waveform_offset_samples = (dimension_ix * self.settings.get_samples_per_dimension())
-            + (waveform_ix * self.settings.waveform_length);
+    /// Returns the mip level that should be sampled from to avoid aliasing when advancing
+    /// `sample_ix_offset` samples through the full-length waveforms for every output sample.
+    pub fn get_mip_level(&self, sample_ix_offset: f32) -> usize {
+        if sample_ix_offset <= 1. {
+            return 0;
+        }
 
-        let sample_mix = sample_ix.fract();
-        let (sample_low_ix, sample_hi_ix) = (sample_ix.floor() as usize, sample_ix.ceil() as usize);
-        let (low_sample, high_sample) = (
-            self.samples[waveform_offset_samples + sample_low_ix],
-            self.samples[waveform_offset_samples + sample_hi_ix],
-        );
+        (sample_ix_offset.log2().ceil() as usize).min(self.mip_levels.len())
+    }
 
-        mix(sample_mix, low_sample, high_sample)
+    fn sample_waveform(
+        &self,
+        mip_level: usize,
+        dimension_ix: usize,
+        waveform_ix: usize,
+        sample_ix: f32,
+    ) -> f32 {
+        let (samples, waveform_length) = match mip_level {
+            0 => (&self.samples, self.settings.waveform_length),
+            _ => (
+                &self.mip_levels[mip_level - 1],
+                self.get_mip_level_waveform_length(mip_level),
+            ),
+        };
+        let waveform_ix = (dimension_ix * self.settings.waveforms_per_dimension) + waveform_ix;
+        let waveform_offset_samples = waveform_ix * waveform_length;
+        let waveform = &samples[waveform_offset_samples..waveform_offset_samples + waveform_length];
+
+        // `sample_ix` is relative to the full-length waveform, so scale it to this mip level
+        let sample_ix = sample_ix * (waveform_length as f32 / self.settings.waveform_length as f32);
+        sample_circular(waveform, sample_ix)
     }
 
-    fn sample_dimension(&self, dimension_ix: usize, waveform_ix: f32, sample_ix: f32) -> f32 {
+    fn sample_dimension(
+        &self,
+        mip_level: usize,
+        dimension_ix: usize,
+        waveform_ix: f32,
+        sample_ix: f32,
+    ) -> f32 {
         let waveform_mix = waveform_ix.fract();
         let (waveform_low_ix, waveform_hi_ix) =
             (waveform_ix.floor() as usize, waveform_ix.ceil() as usize);
 
-        let low_sample = self.sample_waveform(dimension_ix, waveform_low_ix, sample_ix);
-        let high_sample = self.sample_waveform(dimension_ix, waveform_hi_ix, sample_ix);
+        let low_sample = self.sample_waveform(mip_level, dimension_ix, waveform_low_ix, sample_ix);
+        let high_sample = self.sample_waveform(mip_level, dimension_ix, waveform_hi_ix, sample_ix);
 
         mix(waveform_mix, low_sample, high_sample)
     }
 
-    pub fn get_sample(&self, sample_ix: f32, mixes: &[f32]) -> f32 {
+    pub fn get_sample(&self, sample_ix: f32, mip_level: usize, mixes: &[f32]) -> f32 {
         debug_assert!(sample_ix < (self.settings.waveform_length - 1) as f32);
 
         let waveform_ix = mixes[0] * ((self.settings.waveforms_per_dimension - 1) as f32);
-        let base_sample = self.sample_dimension(0, waveform_ix, sample_ix);
+        let base_sample = self.sample_dimension(mip_level, 0, waveform_ix, sample_ix);
 
         // For each higher dimension, mix the base sample from the lowest dimension with the output
         // of the next dimension until a final sample is produced
@@ -80,7 +170,8 @@ impl WaveTable {
         for dimension_ix in 1..self.settings.dimension_count {
             let waveform_ix =
                 mixes[dimension_ix * 2] * ((self.settings.waveforms_per_dimension - 1) as f32);
-            let sample_for_dimension = self.sample_dimension(dimension_ix, waveform_ix, sample_ix);
+            let sample_for_dimension =
+                self.sample_dimension(mip_level, dimension_ix, waveform_ix, sample_ix);
             sample = mix(mixes[dimension_ix * 2 + 1], sample, sample_for_dimension);
         }
 
@@ -123,17 +214,50 @@ impl WaveTableHandle {
     }
 
     pub fn get_sample(&mut self, frequency: f32) -> f32 {
+        let sample_ix_offset = self.get_sample_ix_offset(frequency);
+        let mip_level = self.table.get_mip_level(sample_ix_offset);
         let sample = self
             .table
-            .get_sample(self.sample_ix, &self.mixes_for_sample);
+            .get_sample(self.sample_ix, mip_level, &self.mixes_for_sample);
 
-        self.sample_ix += self.get_sample_ix_offset(frequency);
+        self.sample_ix += sample_ix_offset;
         if self.sample_ix >= (self.table.settings.waveform_length - 1) as f32 {
             self.sample_ix %= (self.table.settings.waveform_length - 1) as f32;
         }
 
         sample
     }
+
+    /// Renders `sample_count` samples into `sample_buffer` using the mixes and frequencies that
+    /// were written for each sample
+    pub fn render(&mut self, sample_count: usize) {
+        while self.sample_buffer.len() < sample_count {
+            self.sample_buffer.push(0.0);
+        }
+
+        for sample_ix in 0..sample_count {
+            for dimension_ix in 0..self.table.settings.dimension_count {
+                self.mixes_for_sample[dimension_ix * 2] =
+                    self.mixes[(dimension_ix * 2 * sample_count) + sample_ix];
+                self.mixes_for_sample[dimension_ix * 2 + 1] =
+                    self.mixes[(dimension_ix * 2 * sample_count) + sample_count + sample_ix];
+            }
+
+            let frequency = self.frequencies_buffer[sample_ix];
+            self.sample_buffer[sample_ix] = self.get_sample(frequency);
+        }
+    }
+}
+
+impl BlockProcessor for WaveTableHandle {
+    /// Renders a block of mono output into both channels.  The mixes and frequencies buffers are
+    /// laid out for `BLOCK_SIZE` samples, which is how JS writes them.
+    fn process(&mut self, block: &mut Block) {
+        self.render(BLOCK_SIZE);
+        let output = &self.sample_buffer[..BLOCK_SIZE];
+        block.left.copy_from_slice(output);
+        block.right.copy_from_slice(output);
+    }
 }
 
 #[no_mangle]
@@ -158,6 +282,11 @@ pub fn get_data_table_ptr(handle_ptr: *mut WaveTable) -> *mut f32 {
     unsafe { (*handle_ptr).samples.as_mut_ptr() }
 }
 
+/// Builds the band-limited versions of the waveforms in the table.  This should be called after
+/// the table's samples have been written.
+#[no_mangle]
+pub fn build_mip_levels(table: *mut WaveTable) { unsafe { (*table).build_mip_levels() } }
+
 #[no_mangle]
 pub fn drop_wavetable(table: *mut WaveTable) { drop(unsafe { Box::from_raw(table) }) }
 
@@ -204,21 +333,7 @@ pub fn get_frequencies_ptr(handle_ptr: *mut WaveTableHandle, sample_count: usize
 pub fn get_samples(handle_ptr: *mut WaveTableHandle, sample_count: usize) -> *const f32 {
     let mut handle = unsafe { Box::from_raw(handle_ptr) };
 
-    while handle.sample_buffer.len() < sample_count {
-        handle.sample_buffer.push(0.0);
-    }
-
-    for sample_ix in 0..sample_count {
-        for dimension_ix in 0..handle.table.settings.dimension_count {
-            handle.mixes_for_sample[dimension_ix * 2] =
-                handle.mixes[(dimension_ix * 2 * sample_count) + sample_ix];
-            handle.mixes_for_sample[dimension_ix * 2 + 1] =
-                handle.mixes[(dimension_ix * 2 * sample_count) + sample_count + sample_ix];
-        }
-
-        let frequency = handle.frequencies_buffer[sample_ix];
-        handle.sample_buffer[sample_ix] = handle.get_sample(frequency);
-    }
+    handle.render(sample_count);
 
     let sample_buf_ptr = handle.sample_buffer.as_ptr();
 
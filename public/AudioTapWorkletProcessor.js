@@ -0,0 +1,65 @@
+/**
+ * Number of frames that are buffered before being sent to the main thread to be analyzed
+ */
+const CHUNK_SIZE = 128 * 8;
+
+/**
+ * Copies the audio connected to it out of the audio thread so that it can be analyzed by the
+ * engine.  Multi-channel input is mixed down to mono.
+ */
+class AudioTapWorkletProcessor extends AudioWorkletProcessor {
+  constructor() {
+    super();
+
+    this.isActive = false;
+    this.resetChunk();
+
+    this.port.onmessage = ({ data }) => {
+      switch (data.type) {
+        case 'start': {
+          this.isActive = true;
+          break;
+        }
+        case 'stop': {
+          this.isActive = false;
+          this.resetChunk();
+          break;
+        }
+        default: {
+          console.error(`Unhandled message type in audio tap worklet: ${data.type}`);
+        }
+      }
+    };
+  }
+
+  resetChunk() {
+    this.chunk = new Float32Array(CHUNK_SIZE);
+    this.chunkOffset = 0;
+  }
+
+  process(inputs) {
+    const input = inputs[0];
+    if (!this.isActive || !input || input.length === 0) {
+      return true;
+    }
+
+    const frameCount = Math.min(input[0].length, CHUNK_SIZE - this.chunkOffset);
+    for (let i = 0; i < frameCount; i++) {
+      let sample = 0;
+      for (let channelIx = 0; channelIx < input.length; channelIx++) {
+        sample += input[channelIx][i];
+      }
+      this.chunk[this.chunkOffset + i] = sample / input.length;
+    }
+    this.chunkOffset += frameCount;
+
+    if (this.chunkOffset === CHUNK_SIZE) {
+      this.port.postMessage(this.chunk, [this.chunk.buffer]);
+      this.resetChunk();
+    }
+
+    return true;
+  }
+}
+
+registerProcessor('audio-tap-worklet-processor', AudioTapWorkletProcessor);
@@ -0,0 +1,80 @@
+const FRAME_SIZE = 128;
+const BYTES_PER_F32 = 32 / 8;
+
+class FilterNodeProcessor extends AudioWorkletProcessor {
+  static get parameterDescriptors() {
+    return [
+      // Index into `FILTER_MODES` in `Filter.ts`
+      { name: 'mode', defaultValue: 0, minValue: 0, maxValue: 3, automationRate: 'k-rate' },
+      {
+        name: 'frequency',
+        defaultValue: 1000,
+        minValue: 10,
+        maxValue: 22050,
+        automationRate: 'k-rate',
+      },
+      { name: 'Q', defaultValue: 0.707, minValue: 0.01, maxValue: 40, automationRate: 'k-rate' },
+    ];
+  }
+
+  async initWasmInstance(data) {
+    const compiledModule = await WebAssembly.compile(data.arrayBuffer);
+    this.wasmInstance = await WebAssembly.instantiate(compiledModule, { env: {} });
+
+    const filterPtr = this.wasmInstance.exports.init_filter(sampleRate);
+    const ioBufferPtr = this.wasmInstance.exports.get_io_buffer_ptr(filterPtr);
+    if (ioBufferPtr % 4 !== 0) {
+      throw new Error("Filter IO buffer pointer isn't 4-byte aligned");
+    }
+    this.ioBufferArrayOffset = ioBufferPtr / BYTES_PER_F32;
+    // Create the view after allocating the buffer since allocating can grow the Wasm memory and
+    // detach existing views of it
+    this.float32WasmMemory = new Float32Array(this.wasmInstance.exports.memory.buffer);
+    this.filterPtr = filterPtr;
+  }
+
+  constructor() {
+    super();
+
+    this.port.onmessage = event => this.initWasmInstance(event.data);
+  }
+
+  process(inputs, outputs, params) {
+    const input = inputs[0];
+    const output = outputs[0];
+    if (!this.filterPtr || !output) {
+      return true;
+    }
+
+    // Write the input into the IO buffer with the left channel followed by the right.  Mono input
+    // is copied to both channels, and missing input is treated as silence.
+    for (let channelIx = 0; channelIx < 2; channelIx++) {
+      const inputChannel =
+        input && input.length > 0 ? input[Math.min(channelIx, input.length - 1)] : null;
+      const offset = this.ioBufferArrayOffset + channelIx * FRAME_SIZE;
+      if (inputChannel) {
+        this.float32WasmMemory.set(inputChannel, offset);
+      } else {
+        this.float32WasmMemory.fill(0, offset, offset + FRAME_SIZE);
+      }
+    }
+
+    // The filter only recomputes its coefficients if these have changed
+    this.wasmInstance.exports.set_filter_params(
+      this.filterPtr,
+      Math.round(params.mode[0]),
+      params.frequency[0],
+      params.Q[0]
+    );
+    this.wasmInstance.exports.process_filter(this.filterPtr);
+
+    for (let channelIx = 0; channelIx < output.length; channelIx++) {
+      const offset = this.ioBufferArrayOffset + Math.min(channelIx, 1) * FRAME_SIZE;
+      output[channelIx].set(this.float32WasmMemory.subarray(offset, offset + FRAME_SIZE));
+    }
+
+    return true;
+  }
+}
+
+registerProcessor('filter-node-processor', FilterNodeProcessor);
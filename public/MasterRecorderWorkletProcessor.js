@@ -0,0 +1,70 @@
+/**
+ * Number of frames that are buffered before being sent to the main thread to be recorded
+ */
+const CHUNK_SIZE = 128 * 32;
+
+class MasterRecorderWorkletProcessor extends AudioWorkletProcessor {
+  constructor() {
+    super();
+
+    this.isRecording = false;
+    this.resetChunk();
+
+    this.port.onmessage = ({ data }) => {
+      switch (data.type) {
+        case 'start': {
+          this.isRecording = true;
+          break;
+        }
+        case 'stop': {
+          this.isRecording = false;
+          this.flush();
+          break;
+        }
+        default: {
+          console.error(`Unhandled message type in master recorder worklet: ${data.type}`);
+        }
+      }
+    };
+  }
+
+  resetChunk() {
+    this.left = new Float32Array(CHUNK_SIZE);
+    this.right = new Float32Array(CHUNK_SIZE);
+    this.chunkOffset = 0;
+  }
+
+  flush() {
+    if (this.chunkOffset === 0) {
+      return;
+    }
+
+    const left = this.left.subarray(0, this.chunkOffset);
+    const right = this.right.subarray(0, this.chunkOffset);
+    this.port.postMessage({ left, right }, [this.left.buffer, this.right.buffer]);
+    this.resetChunk();
+  }
+
+  process(inputs) {
+    const input = inputs[0];
+    if (!this.isRecording || !input || input.length === 0) {
+      return true;
+    }
+
+    // Mono input is recorded to both channels
+    const left = input[0];
+    const right = input[1] || input[0];
+    const frameCount = Math.min(left.length, CHUNK_SIZE - this.chunkOffset);
+    this.left.set(left.subarray(0, frameCount), this.chunkOffset);
+    this.right.set(right.subarray(0, frameCount), this.chunkOffset);
+    this.chunkOffset += frameCount;
+
+    if (this.chunkOffset === CHUNK_SIZE) {
+      this.flush();
+    }
+
+    return true;
+  }
+}
+
+registerProcessor('master-recorder-worklet-processor', MasterRecorderWorkletProcessor);
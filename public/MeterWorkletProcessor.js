@@ -0,0 +1,75 @@
+/**
+ * Number of blocks that are summarized before the summaries are sent to the main thread
+ */
+const BLOCKS_PER_MESSAGE = 8;
+
+/**
+ * Summarizes each block of audio connected to it by its peak and mean square, which is all that
+ * the meters in the engine need.  All channels are metered together.
+ */
+class MeterWorkletProcessor extends AudioWorkletProcessor {
+  constructor() {
+    super();
+
+    this.isActive = false;
+    this.resetLevels();
+
+    this.port.onmessage = ({ data }) => {
+      switch (data.type) {
+        case 'start': {
+          this.isActive = true;
+          break;
+        }
+        case 'stop': {
+          this.isActive = false;
+          this.resetLevels();
+          break;
+        }
+        default: {
+          console.error(`Unhandled message type in meter worklet: ${data.type}`);
+        }
+      }
+    };
+  }
+
+  resetLevels() {
+    // Peak and mean square of each block
+    this.levels = new Float32Array(BLOCKS_PER_MESSAGE * 2);
+    this.blockIx = 0;
+  }
+
+  process(inputs) {
+    const input = inputs[0];
+    if (!this.isActive) {
+      return true;
+    }
+
+    let peak = 0;
+    let sumOfSquares = 0;
+    let frameCount = 128;
+    if (input && input.length > 0) {
+      frameCount = input[0].length;
+      for (let channelIx = 0; channelIx < input.length; channelIx++) {
+        const channel = input[channelIx];
+        for (let i = 0; i < channel.length; i++) {
+          peak = Math.max(peak, Math.abs(channel[i]));
+          sumOfSquares += channel[i] * channel[i];
+        }
+      }
+      sumOfSquares /= input.length;
+    }
+
+    this.levels[this.blockIx * 2] = peak;
+    this.levels[this.blockIx * 2 + 1] = sumOfSquares / frameCount;
+    this.blockIx += 1;
+
+    if (this.blockIx === BLOCKS_PER_MESSAGE) {
+      this.port.postMessage({ frameCount, levels: this.levels }, [this.levels.buffer]);
+      this.resetLevels();
+    }
+
+    return true;
+  }
+}
+
+registerProcessor('meter-worklet-processor', MeterWorkletProcessor);
@@ -0,0 +1,75 @@
+const FRAME_SIZE = 128;
+const BYTES_PER_F32 = 32 / 8;
+
+class ReverbNodeProcessor extends AudioWorkletProcessor {
+  static get parameterDescriptors() {
+    return [
+      { name: 'roomSize', defaultValue: 0.5, minValue: 0, maxValue: 1, automationRate: 'k-rate' },
+      { name: 'damping', defaultValue: 0.5, minValue: 0, maxValue: 1, automationRate: 'k-rate' },
+      { name: 'wet', defaultValue: 0.3, minValue: 0, maxValue: 1, automationRate: 'k-rate' },
+      // Treated as bypassed if > 0.5.  The reverb fades its output in and out when this changes.
+      { name: 'bypass', defaultValue: 0, minValue: 0, maxValue: 1, automationRate: 'k-rate' },
+    ];
+  }
+
+  async initWasmInstance(data) {
+    const compiledModule = await WebAssembly.compile(data.arrayBuffer);
+    this.wasmInstance = await WebAssembly.instantiate(compiledModule, { env: {} });
+
+    const reverbPtr = this.wasmInstance.exports.init_reverb(sampleRate);
+    const ioBufferPtr = this.wasmInstance.exports.get_io_buffer_ptr(reverbPtr);
+    if (ioBufferPtr % 4 !== 0) {
+      throw new Error("Reverb IO buffer pointer isn't 4-byte aligned");
+    }
+    this.ioBufferArrayOffset = ioBufferPtr / BYTES_PER_F32;
+    // Create the view after allocating the buffer since allocating can grow the Wasm memory and
+    // detach existing views of it
+    this.float32WasmMemory = new Float32Array(this.wasmInstance.exports.memory.buffer);
+    this.reverbPtr = reverbPtr;
+  }
+
+  constructor() {
+    super();
+
+    this.port.onmessage = event => this.initWasmInstance(event.data);
+  }
+
+  process(inputs, outputs, params) {
+    const input = inputs[0];
+    const output = outputs[0];
+    if (!this.reverbPtr || !output) {
+      return true;
+    }
+
+    // Write the input into the IO buffer with the left channel followed by the right.  Mono input
+    // is copied to both channels, and missing input is treated as silence.
+    for (let channelIx = 0; channelIx < 2; channelIx++) {
+      const inputChannel =
+        input && input.length > 0 ? input[Math.min(channelIx, input.length - 1)] : null;
+      const offset = this.ioBufferArrayOffset + channelIx * FRAME_SIZE;
+      if (inputChannel) {
+        this.float32WasmMemory.set(inputChannel, offset);
+      } else {
+        this.float32WasmMemory.fill(0, offset, offset + FRAME_SIZE);
+      }
+    }
+
+    this.wasmInstance.exports.set_reverb_params(
+      this.reverbPtr,
+      params.roomSize[0],
+      params.damping[0],
+      params.wet[0],
+      params.bypass[0] > 0.5
+    );
+    this.wasmInstance.exports.process_reverb(this.reverbPtr);
+
+    for (let channelIx = 0; channelIx < output.length; channelIx++) {
+      const offset = this.ioBufferArrayOffset + Math.min(channelIx, 1) * FRAME_SIZE;
+      output[channelIx].set(this.float32WasmMemory.subarray(offset, offset + FRAME_SIZE));
+    }
+
+    return true;
+  }
+}
+
+registerProcessor('reverb-node-processor', ReverbNodeProcessor);
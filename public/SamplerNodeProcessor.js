@@ -0,0 +1,173 @@
+const FRAME_SIZE = 128;
+const BYTES_PER_F32 = 32 / 8;
+
+class SamplerNodeProcessor extends AudioWorkletProcessor {
+  async initWasmInstance(arrayBuffer) {
+    const compiledModule = await WebAssembly.compile(arrayBuffer);
+    this.wasmInstance = await WebAssembly.instantiate(compiledModule, { env: {} });
+
+    const samplerPtr = this.wasmInstance.exports.init_sampler(sampleRate);
+    const ioBufferPtr = this.wasmInstance.exports.get_io_buffer_ptr(samplerPtr);
+    if (ioBufferPtr % 4 !== 0) {
+      throw new Error("Sampler IO buffer pointer isn't 4-byte aligned");
+    }
+    this.ioBufferArrayOffset = ioBufferPtr / BYTES_PER_F32;
+    this.samplerPtr = samplerPtr;
+
+    // The sample and params may have been sent before the Wasm instance finished loading
+    if (this.pendingSample) {
+      this.setSample(this.pendingSample);
+      this.pendingSample = null;
+    }
+    if (this.pendingParams) {
+      this.setParams(this.pendingParams);
+      this.pendingParams = null;
+    }
+  }
+
+  setSample({ channels, sampleRate: sampleSampleRate }) {
+    if (!this.samplerPtr) {
+      this.pendingSample = { channels, sampleRate: sampleSampleRate };
+      return;
+    }
+
+    const length = channels[0] ? channels[0].length : 0;
+    const samplePtr = this.wasmInstance.exports.get_staged_sample_ptr(
+      this.samplerPtr,
+      length * channels.length
+    );
+    // Allocating the sample can grow the Wasm memory, so a fresh view has to be created
+    const memory = new Float32Array(this.wasmInstance.exports.memory.buffer);
+    channels.forEach((channel, i) => memory.set(channel, samplePtr / BYTES_PER_F32 + i * length));
+    this.wasmInstance.exports.commit_sampler_sample(
+      this.samplerPtr,
+      channels.length,
+      sampleSampleRate
+    );
+  }
+
+  setParams(params) {
+    if (!this.samplerPtr) {
+      this.pendingParams = params;
+      return;
+    }
+
+    this.wasmInstance.exports.set_sampler_params(
+      this.samplerPtr,
+      params.rootNote,
+      params.looping,
+      params.loopStart,
+      params.loopEnd,
+      params.releaseSeconds
+    );
+
+    // Slice start frames are `usize`s, which are 32 bits in Wasm
+    const slices = params.slices || [];
+    const slicesPtr = this.wasmInstance.exports.get_staged_slices_ptr(
+      this.samplerPtr,
+      slices.length
+    );
+    new Uint32Array(this.wasmInstance.exports.memory.buffer).set(slices, slicesPtr / 4);
+    this.wasmInstance.exports.commit_sampler_slices(this.samplerPtr, params.sliceBaseNote);
+  }
+
+  applyEvent(event) {
+    switch (event.type) {
+      case 'attack': {
+        this.wasmInstance.exports.trigger_sampler_attack(
+          this.samplerPtr,
+          event.voiceIx,
+          event.noteId,
+          event.velocity
+        );
+        break;
+      }
+      case 'release': {
+        this.wasmInstance.exports.trigger_sampler_release(
+          this.samplerPtr,
+          event.voiceIx,
+          event.noteId
+        );
+        break;
+      }
+      default: {
+        console.error(`Unhandled sampler event type: ${event.type}`);
+      }
+    }
+  }
+
+  constructor() {
+    super();
+
+    /**
+     * Attacks and releases sorted by the time at which they should be applied
+     */
+    this.pendingEvents = [];
+
+    this.port.onmessage = ({ data }) => {
+      switch (data.type) {
+        case 'init': {
+          this.initWasmInstance(data.arrayBuffer);
+          break;
+        }
+        case 'setSample': {
+          this.setSample(data);
+          break;
+        }
+        case 'setParams': {
+          this.setParams(data.params);
+          break;
+        }
+        case 'attack':
+        case 'release': {
+          this.pendingEvents.push(data);
+          this.pendingEvents.sort((a, b) => a.time - b.time);
+          break;
+        }
+        case 'releaseAll': {
+          this.pendingEvents = [];
+          if (this.samplerPtr) {
+            this.wasmInstance.exports.release_all_sampler_voices(this.samplerPtr);
+          }
+          break;
+        }
+        default: {
+          console.error(`Unhandled message type in sampler worklet: ${data.type}`);
+        }
+      }
+    };
+  }
+
+  process(_inputs, outputs) {
+    const output = outputs[0];
+    if (!this.samplerPtr || !output) {
+      return true;
+    }
+
+    // Render up to each event that falls within this frame and then apply it so that events are
+    // applied at the exact frame that they're scheduled for
+    const frameEndTime = currentTime + FRAME_SIZE / sampleRate;
+    let renderedFrames = 0;
+    while (this.pendingEvents.length > 0 && this.pendingEvents[0].time < frameEndTime) {
+      const event = this.pendingEvents.shift();
+      const eventFrame = Math.min(
+        Math.max(Math.round((event.time - currentTime) * sampleRate), renderedFrames),
+        FRAME_SIZE
+      );
+      this.wasmInstance.exports.render_sampler(this.samplerPtr, renderedFrames, eventFrame);
+      renderedFrames = eventFrame;
+      this.applyEvent(event);
+    }
+    this.wasmInstance.exports.render_sampler(this.samplerPtr, renderedFrames, FRAME_SIZE);
+
+    const memory = new Float32Array(this.wasmInstance.exports.memory.buffer);
+    for (let channelIx = 0; channelIx < output.length; channelIx++) {
+      const offset = this.ioBufferArrayOffset + Math.min(channelIx, 1) * FRAME_SIZE;
+      output[channelIx].set(memory.subarray(offset, offset + FRAME_SIZE));
+    }
+
+    return true;
+  }
+}
+
+registerProcessor('sampler-node-processor', SamplerNodeProcessor);
@@ -0,0 +1,182 @@
+const FRAME_SIZE = 128;
+const BYTES_PER_F32 = 32 / 8;
+
+/**
+ * The fields of each region in the order of the `region_param` offsets in the sampler crate's
+ * `soundfont` module
+ */
+const REGION_PARAMS = [
+  'keyLo',
+  'keyHi',
+  'velLo',
+  'velHi',
+  'sampleIx',
+  'start',
+  'end',
+  'loopMode',
+  'loopStart',
+  'loopEnd',
+  'rootKey',
+  'tuneCents',
+  'scaleTuning',
+  'gain',
+  'pan',
+  'delay',
+  'attack',
+  'hold',
+  'decay',
+  'sustainLevel',
+  'release',
+  'exclusiveClass',
+];
+
+/**
+ * Maps loop modes to the values of the SoundFont `sampleModes` generator
+ */
+const LOOP_MODES = { no_loop: 0, continuous: 1, until_release: 3 };
+
+class SoundFontNodeProcessor extends AudioWorkletProcessor {
+  async initWasmInstance(arrayBuffer) {
+    const compiledModule = await WebAssembly.compile(arrayBuffer);
+    this.wasmInstance = await WebAssembly.instantiate(compiledModule, { env: {} });
+
+    const playerPtr = this.wasmInstance.exports.init_soundfont_player(sampleRate);
+    const ioBufferPtr = this.wasmInstance.exports.soundfont_get_io_buffer_ptr(playerPtr);
+    if (ioBufferPtr % 4 !== 0) {
+      throw new Error("SoundFont player IO buffer pointer isn't 4-byte aligned");
+    }
+    this.ioBufferArrayOffset = ioBufferPtr / BYTES_PER_F32;
+    this.playerPtr = playerPtr;
+
+    // The preset may have been sent before the Wasm instance finished loading
+    if (this.pendingPreset) {
+      this.setPreset(this.pendingPreset);
+      this.pendingPreset = null;
+    }
+  }
+
+  setPreset({ regions, samples }) {
+    if (!this.playerPtr) {
+      this.pendingPreset = { regions, samples };
+      return;
+    }
+
+    const exports = this.wasmInstance.exports;
+    samples.forEach(({ data, sampleRate: sampleSampleRate }, sampleIx) => {
+      const samplePtr = exports.soundfont_get_staged_sample_ptr(this.playerPtr, data.length);
+      // Allocating the sample can grow the Wasm memory, so a fresh view has to be created
+      new Float32Array(exports.memory.buffer).set(data, samplePtr / BYTES_PER_F32);
+      exports.soundfont_commit_sample(this.playerPtr, sampleIx, sampleSampleRate);
+    });
+
+    const regionsPtr = exports.soundfont_get_staged_regions_ptr(this.playerPtr, regions.length);
+    const records = new Float32Array(
+      exports.memory.buffer,
+      regionsPtr,
+      regions.length * REGION_PARAMS.length
+    );
+    regions.forEach((region, regionIx) =>
+      REGION_PARAMS.forEach((param, paramIx) => {
+        const val = param === 'loopMode' ? LOOP_MODES[region.loopMode] : region[param];
+        records[regionIx * REGION_PARAMS.length + paramIx] = val || 0;
+      })
+    );
+    exports.soundfont_commit_regions(this.playerPtr);
+  }
+
+  applyEvent(event) {
+    switch (event.type) {
+      case 'attack': {
+        this.wasmInstance.exports.soundfont_trigger_attack(
+          this.playerPtr,
+          event.voiceIx,
+          event.noteId,
+          event.velocity
+        );
+        break;
+      }
+      case 'release': {
+        this.wasmInstance.exports.soundfont_trigger_release(
+          this.playerPtr,
+          event.voiceIx,
+          event.noteId
+        );
+        break;
+      }
+      default: {
+        console.error(`Unhandled SoundFont event type: ${event.type}`);
+      }
+    }
+  }
+
+  constructor() {
+    super();
+
+    /**
+     * Attacks and releases sorted by the time at which they should be applied
+     */
+    this.pendingEvents = [];
+
+    this.port.onmessage = ({ data }) => {
+      switch (data.type) {
+        case 'init': {
+          this.initWasmInstance(data.arrayBuffer);
+          break;
+        }
+        case 'setPreset': {
+          this.setPreset(data);
+          break;
+        }
+        case 'attack':
+        case 'release': {
+          this.pendingEvents.push(data);
+          this.pendingEvents.sort((a, b) => a.time - b.time);
+          break;
+        }
+        case 'releaseAll': {
+          this.pendingEvents = [];
+          if (this.playerPtr) {
+            this.wasmInstance.exports.soundfont_release_all(this.playerPtr);
+          }
+          break;
+        }
+        default: {
+          console.error(`Unhandled message type in SoundFont worklet: ${data.type}`);
+        }
+      }
+    };
+  }
+
+  process(_inputs, outputs) {
+    const output = outputs[0];
+    if (!this.playerPtr || !output) {
+      return true;
+    }
+
+    // Render up to each event that falls within this frame and then apply it so that events are
+    // applied at the exact frame that they're scheduled for
+    const frameEndTime = currentTime + FRAME_SIZE / sampleRate;
+    let renderedFrames = 0;
+    while (this.pendingEvents.length > 0 && this.pendingEvents[0].time < frameEndTime) {
+      const event = this.pendingEvents.shift();
+      const eventFrame = Math.min(
+        Math.max(Math.round((event.time - currentTime) * sampleRate), renderedFrames),
+        FRAME_SIZE
+      );
+      this.wasmInstance.exports.render_soundfont(this.playerPtr, renderedFrames, eventFrame);
+      renderedFrames = eventFrame;
+      this.applyEvent(event);
+    }
+    this.wasmInstance.exports.render_soundfont(this.playerPtr, renderedFrames, FRAME_SIZE);
+
+    const memory = new Float32Array(this.wasmInstance.exports.memory.buffer);
+    for (let channelIx = 0; channelIx < output.length; channelIx++) {
+      const offset = this.ioBufferArrayOffset + Math.min(channelIx, 1) * FRAME_SIZE;
+      output[channelIx].set(memory.subarray(offset, offset + FRAME_SIZE));
+    }
+
+    return true;
+  }
+}
+
+registerProcessor('soundfont-node-processor', SoundFontNodeProcessor);
@@ -74,6 +74,10 @@ class WaveTableNodeProcessor extends AudioWorkletProcessor {
 
     // Write the table's data into the Wasm heap
     this.float32WasmMemory.set(data.tableSamples, wavetableDataArrayOffset);
+    // Build band-limited versions of the waveforms to avoid aliasing at high frequencies
+    this.wasmInstance.exports.build_mip_levels(this.waveTablePtr);
+    // Building them allocates, which can grow the Wasm memory and detach our view of it
+    this.float32WasmMemory = new Float32Array(this.wasmInstance.exports.memory.buffer);
 
     this.waveTableHandlePtr = this.wasmInstance.exports.init_wavetable_handle(this.waveTablePtr);
 
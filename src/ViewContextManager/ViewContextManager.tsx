@@ -33,6 +33,12 @@ const viewContexts: { children: string; name: string; displayName: string }[] =
   { children: 'K', name: 'midi_keyboard', displayName: 'MIDI Keyboard' },
   { children: 'Q', name: 'sequencer', displayName: 'Sequencer' },
   { children: 'L', name: 'sample_library', displayName: 'Sample Library' },
+  { children: 'X', name: 'mixer', displayName: 'Mixer' },
+  { children: 'R', name: 'drum_sequencer', displayName: 'Drum Sequencer' },
+  { children: 'P', name: 'clip_launcher', displayName: 'Clip Launcher' },
+  { children: 'A', name: 'spectrum_analyzer', displayName: 'Spectrum Analyzer' },
+  { children: 'O', name: 'oscilloscope', displayName: 'Oscilloscope' },
+  { children: 'E', name: 'sample_editor', displayName: 'Sample Editor' },
 ];
 
 interface ViewContextIconProps extends React.HtmlHTMLAttributes<HTMLDivElement> {
@@ -158,7 +164,9 @@ const VCTabCloseIcon: React.FC<{
   </div>
 );
 
-const ViewContextTab = ({ engine, name, uuid, title, active }: ViewContextTabProps) => {
+const VC_TAB_DRAG_MIME_TYPE = 'application/x-vc-tab-ix';
+
+const ViewContextTab = ({ engine, name, uuid, title, active, i }: ViewContextTabProps) => {
   const [isRenaming, setIsRenaming] = useState(false);
   const [renamingTitle, setRenamingTitle] = useState(title || '');
 
@@ -179,6 +187,19 @@ const ViewContextTab = ({ engine, name, uuid, title, active }: ViewContextTabPro
         }
       }}
       onDoubleClick={() => setIsRenaming(true)}
+      draggable={!isRenaming}
+      onDragStart={e => e.dataTransfer.setData(VC_TAB_DRAG_MIME_TYPE, i.toString())}
+      onDragOver={e => {
+        if (e.dataTransfer.types.includes(VC_TAB_DRAG_MIME_TYPE)) {
+          e.preventDefault();
+        }
+      }}
+      onDrop={e => {
+        const fromIx = +e.dataTransfer.getData(VC_TAB_DRAG_MIME_TYPE);
+        if (fromIx !== i) {
+          engine.move_view_context(fromIx, i);
+        }
+      }}
     >
       {isRenaming ? (
         <ViewContextTabRenamer
@@ -0,0 +1,112 @@
+import { getEngine } from 'src';
+import { SampleDescriptor } from 'src/sampleLibrary';
+import { cacheSample } from 'src/sampleLibrary/sampleCache';
+import { getTransportState, subscribeToTransport } from 'src/transport';
+
+/**
+ * Records the master output into a buffer in the engine.  When recording is armed, blocks of audio
+ * are captured from the global volume node whenever the transport is playing.
+ */
+
+const ctx = new AudioContext();
+
+let recorderPtr: number | null = null;
+let workletHandle: AudioWorkletNode | null = null;
+let isArmed = false;
+let isCapturing = false;
+let unsubscribeFromTransport: (() => void) | null = null;
+
+const getRecorderPtr = (): number => {
+  if (recorderPtr === null) {
+    recorderPtr = getEngine()!.create_audio_recorder(ctx.sampleRate);
+  }
+  return recorderPtr;
+};
+
+const setIsCapturing = (newIsCapturing: boolean) => {
+  if (!workletHandle || newIsCapturing === isCapturing) {
+    return;
+  }
+
+  isCapturing = newIsCapturing;
+  workletHandle.port.postMessage({ type: newIsCapturing ? 'start' : 'stop' });
+};
+
+const initWorklet = async (): Promise<AudioWorkletNode> => {
+  if (workletHandle) {
+    return workletHandle;
+  }
+
+  await ctx.audioWorklet.addModule('/MasterRecorderWorkletProcessor.js');
+  workletHandle = new AudioWorkletNode(ctx, 'master-recorder-worklet-processor');
+  workletHandle.port.onmessage = ({ data }: MessageEvent) => {
+    const hasSpace = getEngine()!.audio_recorder_append_block(
+      getRecorderPtr(),
+      data.left,
+      data.right
+    );
+    if (!hasSpace) {
+      console.warn('Recording reached its maximum length; disarming');
+      disarmRecording();
+    }
+  };
+
+  ((ctx as any).globalVolume as GainNode).connect(workletHandle);
+  // The worklet only outputs silence, but it needs to be connected in order to be processed
+  workletHandle.connect(ctx.destination);
+  return workletHandle;
+};
+
+export const isRecordingArmed = () => isArmed;
+
+/**
+ * Starts capturing the master output whenever the transport is playing.  Audio is appended to the
+ * existing recording, if any.
+ */
+export const armRecording = async () => {
+  if (isArmed) {
+    return;
+  }
+
+  isArmed = true;
+  await initWorklet();
+  setIsCapturing(getTransportState().isPlaying);
+  unsubscribeFromTransport = subscribeToTransport(state => setIsCapturing(state.isPlaying));
+};
+
+export const disarmRecording = () => {
+  isArmed = false;
+  setIsCapturing(false);
+  if (unsubscribeFromTransport) {
+    unsubscribeFromTransport();
+    unsubscribeFromTransport = null;
+  }
+};
+
+export const getRecordingLengthSeconds = (): number =>
+  recorderPtr === null ? 0 : getEngine()!.audio_recorder_get_length_seconds(recorderPtr);
+
+export const clearRecording = () => {
+  if (recorderPtr !== null) {
+    getEngine()!.audio_recorder_clear(recorderPtr);
+  }
+};
+
+export const exportRecordingAsWav = (bitDepth: 16 | 24 = 16): Uint8Array =>
+  getEngine()!.audio_recorder_encode_wav(getRecorderPtr(), bitDepth);
+
+/**
+ * Stores the current recording in the sample library so that it can be loaded into samplers.
+ */
+export const saveRecordingAsSample = async (): Promise<SampleDescriptor> => {
+  if (getRecordingLengthSeconds() === 0) {
+    throw new Error('Nothing has been recorded');
+  }
+
+  const descriptor: SampleDescriptor = {
+    isLocal: true,
+    name: `recordings/recording-${new Date().toISOString()}.wav`,
+  };
+  await cacheSample(descriptor, exportRecordingAsWav(24).buffer);
+  return descriptor;
+};
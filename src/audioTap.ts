@@ -0,0 +1,83 @@
+/**
+ * Taps audio out of the graph so that it can be analyzed in the engine by view contexts such as
+ * the spectrum analyzer.  Audio can be taken from the master output or from whatever is connected
+ * to the tap's input.
+ */
+
+export type TapLocation = 'master' | 'input';
+
+export class AudioTap {
+  private ctx: AudioContext;
+  private location: TapLocation;
+  private isActive = false;
+  private isDestroyed = false;
+  private worklet: AudioWorkletNode | null = null;
+  /**
+   * Exposed as an input to the patch network.  Audio connected to it is only tapped if the tap
+   * location is `input`.
+   */
+  public input: GainNode;
+  private tapNode: GainNode;
+
+  constructor(ctx: AudioContext, location: TapLocation, onBlock: (samples: Float32Array) => void) {
+    this.ctx = ctx;
+    this.location = location;
+    this.input = new GainNode(ctx);
+    this.tapNode = new GainNode(ctx);
+    this.setLocation(location);
+
+    ctx.audioWorklet.addModule('/AudioTapWorkletProcessor.js').then(() => {
+      if (this.isDestroyed) {
+        return;
+      }
+
+      const worklet = new AudioWorkletNode(ctx, 'audio-tap-worklet-processor');
+      worklet.port.onmessage = ({ data }: MessageEvent) => onBlock(data);
+      this.tapNode.connect(worklet);
+      // The worklet only outputs silence, but it needs to be connected in order to be processed
+      worklet.connect(ctx.destination);
+      this.worklet = worklet;
+      this.setIsActive(this.isActive);
+    });
+  }
+
+  private getMasterNode = (): GainNode => (this.ctx as any).globalVolume;
+
+  public setLocation(location: TapLocation) {
+    this.input.disconnect();
+    if (this.location === 'master') {
+      try {
+        this.getMasterNode().disconnect(this.tapNode);
+      } catch (_err) {
+        // Wasn't connected
+      }
+    }
+
+    this.location = location;
+    (location === 'master' ? this.getMasterNode() : this.input).connect(this.tapNode);
+  }
+
+  /**
+   * Audio is only sent to the engine while the tap is active to avoid wasting work while the view
+   * that it belongs to is hidden
+   */
+  public setIsActive(isActive: boolean) {
+    this.isActive = isActive;
+    if (this.worklet) {
+      this.worklet.port.postMessage({ type: isActive ? 'start' : 'stop' });
+    }
+  }
+
+  public destroy() {
+    this.setIsActive(false);
+    this.isDestroyed = true;
+    this.input.disconnect();
+    if (this.location === 'master') {
+      this.getMasterNode().disconnect(this.tapNode);
+    }
+    if (this.worklet) {
+      this.tapNode.disconnect(this.worklet);
+      this.worklet.disconnect();
+    }
+  }
+}
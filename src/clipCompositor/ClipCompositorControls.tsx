@@ -0,0 +1,120 @@
+import React, { useState } from 'react';
+import ControlPanel from 'react-control-panel';
+
+import { useSelector, ReduxStore } from 'src/redux';
+
+interface ClipCompositorInfo {
+  midiEditorVcId: string | null;
+  activeClipId: string | null;
+}
+
+interface ClipsInfo {
+  clips: { id: string; name: string; lengthBeats: number }[];
+  playArrangement: boolean;
+}
+
+const ClipCompositorControls: React.FC<{
+  engine: typeof import('src/engine');
+  vcId: string;
+}> = ({ engine, vcId }) => {
+  const viewContexts = useSelector(
+    (state: ReduxStore) => state.viewContextManager.activeViewContexts
+  );
+  const midiEditors = viewContexts.filter(({ name }) => name === 'midi_editor');
+  const midiEditorLabels = midiEditors.map(
+    ({ uuid, title }) => `${title || 'MIDI Editor'} (${uuid.slice(0, 8)})`
+  );
+
+  const getInfo = (): ClipCompositorInfo =>
+    JSON.parse(
+      new TextDecoder().decode(
+        engine.handle_vc_message(vcId, 'get_clip_compositor_info', new Uint8Array())
+      )
+    );
+  const [info, setInfo] = useState<ClipCompositorInfo>(getInfo);
+  const sendMessage = (key: string, val: Uint8Array = new Uint8Array()) => {
+    engine.handle_vc_message(vcId, key, val);
+    setInfo(getInfo());
+  };
+
+  const getClips = (): ClipsInfo | null => {
+    if (!info.midiEditorVcId || !midiEditors.some(({ uuid }) => uuid === info.midiEditorVcId)) {
+      return null;
+    }
+    const res = engine.handle_vc_message(info.midiEditorVcId, 'get_clips', new Uint8Array());
+    return res ? JSON.parse(new TextDecoder().decode(res)) : null;
+  };
+  const clipsInfo = getClips();
+  const clips = clipsInfo ? clipsInfo.clips : [];
+  const clipLabels = clips.map(({ name }, i) => `${i + 1}: ${name}`);
+
+  const onChange = (key: string, val: any) => {
+    switch (key) {
+      case 'midi editor': {
+        const midiEditorIx = midiEditorLabels.indexOf(val);
+        if (midiEditorIx !== -1) {
+          sendMessage('set_midi_editor', new TextEncoder().encode(midiEditors[midiEditorIx].uuid));
+        }
+        break;
+      }
+      case 'clip': {
+        const clipIx = clipLabels.indexOf(val);
+        if (clipIx !== -1) {
+          sendMessage('set_active_clip', new TextEncoder().encode(clips[clipIx].id));
+        }
+        break;
+      }
+      case 'play arrangement': {
+        if (info.midiEditorVcId) {
+          engine.handle_vc_message(
+            info.midiEditorVcId,
+            'set_play_arrangement',
+            new Uint8Array([val ? 1 : 0])
+          );
+        }
+        break;
+      }
+      default: {
+        console.error(`Unhandled state key in clip compositor controls: ${key}`);
+      }
+    }
+  };
+
+  const activeMIDIEditorIx = midiEditors.findIndex(({ uuid }) => uuid === info.midiEditorVcId);
+  const activeClipIx = clips.findIndex(({ id }) => id === info.activeClipId);
+
+  return (
+    <ControlPanel
+      onChange={onChange}
+      width={400}
+      position='top-right'
+      draggable
+      settings={[
+        {
+          type: 'select',
+          label: 'midi editor',
+          options: ['', ...midiEditorLabels],
+          initial: activeMIDIEditorIx === -1 ? '' : midiEditorLabels[activeMIDIEditorIx],
+        },
+        {
+          type: 'select',
+          label: 'clip',
+          options: ['', ...clipLabels],
+          initial: activeClipIx === -1 ? '' : clipLabels[activeClipIx],
+        },
+        {
+          type: 'button',
+          label: 'duplicate selection',
+          action: () => sendMessage('duplicate_selection'),
+        },
+        {
+          type: 'checkbox',
+          label: 'play arrangement',
+          initial: clipsInfo ? clipsInfo.playArrangement : false,
+        },
+      ]}
+    />
+  );
+};
+
+export default ClipCompositorControls;
@@ -0,0 +1,42 @@
+/**
+ * UI for the clip compositor, which arranges the clips of a MIDI editor on a timeline.  The grid
+ * itself is rendered by the engine; this only renders the controls for choosing what's placed on
+ * it.
+ */
+
+import { mkContainerRenderHelper, mkContainerCleanupHelper } from 'src/reactUtils';
+import { store } from 'src/redux';
+import { getEngine } from 'src';
+import ClipCompositorControls from './ClipCompositorControls';
+
+const buildClipCompositorUIDomId = (vcId: string) => `clip-compositor-controls_${vcId}`;
+
+export const init_clip_compositor_ui = (vcId: string) => {
+  const domId = buildClipCompositorUIDomId(vcId);
+  const elem = document.createElement('div');
+  elem.id = domId;
+  document.getElementById('root')!.append(elem);
+
+  mkContainerRenderHelper({
+    Comp: ClipCompositorControls,
+    store,
+    getProps: () => ({ engine: getEngine()!, vcId }),
+  })(domId);
+};
+
+export const cleanup_clip_compositor_ui = (vcId: string) => {
+  const domId = buildClipCompositorUIDomId(vcId);
+  mkContainerCleanupHelper()(domId);
+  const elem = document.getElementById(domId);
+  if (elem) {
+    elem.remove();
+  }
+};
+
+export const hide_clip_compositor_ui = (vcId: string) => {
+  document.getElementById(buildClipCompositorUIDomId(vcId))!.style.display = 'none';
+};
+
+export const unhide_clip_compositor_ui = (vcId: string) => {
+  document.getElementById(buildClipCompositorUIDomId(vcId))!.style.display = 'block';
+};
@@ -0,0 +1,182 @@
+import React, { useEffect, useState } from 'react';
+
+import { useSelector, ReduxStore } from 'src/redux';
+import {
+  ClipLauncherState,
+  ClipLauncherTrack,
+  TrackPlaybackState,
+  MIDIEditorClip,
+  addTrack,
+  removeTrack,
+  addScene,
+  removeScene,
+  setTrackMIDIEditor,
+  setCell,
+  launchCell,
+  stopTrack,
+  launchScene,
+  stopAll,
+  getPlaybackState,
+  getMIDIEditorClips,
+} from './messages';
+import './ClipLauncher.scss';
+
+/**
+ * How often the playback state of the tracks is polled so that playing and queued cells can be
+ * highlighted
+ */
+const PLAYBACK_STATE_POLL_INTERVAL_MS = 100;
+
+const emptyPlaybackState: TrackPlaybackState = {
+  playingClipId: null,
+  queuedClipId: null,
+  stopQueued: false,
+};
+
+const Cell: React.FC<{
+  vcId: string;
+  trackIx: number;
+  sceneIx: number;
+  clipId: string | null;
+  clips: MIDIEditorClip[];
+  playback: TrackPlaybackState;
+}> = ({ vcId, trackIx, sceneIx, clipId, clips, playback }) => {
+  const classNames = ['clip-launcher-cell'];
+  if (clipId && playback.playingClipId === clipId) {
+    classNames.push(playback.stopQueued ? 'stopping' : 'playing');
+  }
+  if (clipId && playback.queuedClipId === clipId) {
+    classNames.push('queued');
+  }
+
+  return (
+    <div className={classNames.join(' ')}>
+      <button disabled={!clipId} onClick={() => launchCell(vcId, trackIx, sceneIx)}>
+        ▶
+      </button>
+      <select
+        value={clipId || ''}
+        onChange={evt => setCell(vcId, trackIx, sceneIx, evt.target.value || null)}
+      >
+        <option value=''>Empty</option>
+        {clips.map(({ id, name }) => (
+          <option key={id} value={id}>
+            {name}
+          </option>
+        ))}
+      </select>
+    </div>
+  );
+};
+
+const Track: React.FC<{
+  vcId: string;
+  trackIx: number;
+  track: ClipLauncherTrack;
+  playback: TrackPlaybackState;
+}> = ({ vcId, trackIx, track, playback }) => {
+  const viewContexts = useSelector(
+    (state: ReduxStore) => state.viewContextManager.activeViewContexts
+  );
+  const midiEditors = viewContexts.filter(({ name }) => name === 'midi_editor');
+  const clips =
+    track.midiEditorVcId && midiEditors.some(({ uuid }) => uuid === track.midiEditorVcId)
+      ? getMIDIEditorClips(track.midiEditorVcId)
+      : [];
+
+  return (
+    <div className='clip-launcher-track'>
+      <select
+        value={track.midiEditorVcId || ''}
+        onChange={evt => setTrackMIDIEditor(vcId, trackIx, evt.target.value || null)}
+      >
+        <option value=''>No MIDI Editor</option>
+        {midiEditors.map(({ uuid, title }) => (
+          <option key={uuid} value={uuid}>
+            {title || `MIDI Editor (${uuid.slice(0, 8)})`}
+          </option>
+        ))}
+      </select>
+      {track.cells.map((clipId, sceneIx) => (
+        <Cell
+          key={sceneIx}
+          vcId={vcId}
+          trackIx={trackIx}
+          sceneIx={sceneIx}
+          clipId={clipId}
+          clips={clips}
+          playback={playback}
+        />
+      ))}
+      <button
+        className={playback.stopQueued ? 'stopping' : undefined}
+        onClick={() => stopTrack(vcId, trackIx)}
+      >
+        ■ Stop
+      </button>
+      <button onClick={() => removeTrack(vcId, trackIx)}>Remove</button>
+    </div>
+  );
+};
+
+const ClipLauncherUI: React.FC<{
+  vcId: string;
+  initialState: ClipLauncherState;
+  subscribe: (onStateChange: ((state: ClipLauncherState) => void) | null) => void;
+  subscribePlayback: (
+    onPlaybackChange: ((playbackStates: TrackPlaybackState[]) => void) | null
+  ) => void;
+}> = ({ vcId, initialState, subscribe, subscribePlayback }) => {
+  const [state, setState] = useState(initialState);
+  const [playbackStates, setPlaybackStates] = useState<TrackPlaybackState[]>([]);
+
+  useEffect(() => {
+    subscribe(setState);
+    return () => subscribe(null);
+  }, [subscribe]);
+
+  useEffect(() => {
+    subscribePlayback(setPlaybackStates);
+    return () => subscribePlayback(null);
+  }, [subscribePlayback]);
+
+  useEffect(() => {
+    const intervalHandle = setInterval(
+      () => setPlaybackStates(getPlaybackState(vcId) || []),
+      PLAYBACK_STATE_POLL_INTERVAL_MS
+    );
+    return () => clearInterval(intervalHandle);
+  }, [vcId]);
+
+  const sceneIndices = [...Array(state.sceneCount).keys()];
+
+  return (
+    <div className='clip-launcher'>
+      <div className='clip-launcher-track clip-launcher-scenes'>
+        <div className='clip-launcher-track-header'>Scenes</div>
+        {sceneIndices.map(sceneIx => (
+          <div key={sceneIx} className='clip-launcher-cell'>
+            <button onClick={() => launchScene(vcId, sceneIx)}>▶ {sceneIx + 1}</button>
+            <button onClick={() => removeScene(vcId, sceneIx)}>✕</button>
+          </div>
+        ))}
+        <button onClick={() => stopAll(vcId)}>■ Stop All</button>
+        <button onClick={() => addScene(vcId)}>Add Scene</button>
+      </div>
+      {state.tracks.map((track, trackIx) => (
+        <Track
+          key={trackIx}
+          vcId={vcId}
+          trackIx={trackIx}
+          track={track}
+          playback={playbackStates[trackIx] || emptyPlaybackState}
+        />
+      ))}
+      <div className='clip-launcher-track'>
+        <button onClick={() => addTrack(vcId)}>Add Track</button>
+      </div>
+    </div>
+  );
+};
+
+export default ClipLauncherUI;
@@ -0,0 +1,112 @@
+/**
+ * View context for a clip launcher, which launches the clips of MIDI editors from a grid of cells.
+ * The state of the launcher lives in the engine, which sends it over every time that it changes.
+ */
+
+import { mkContainerRenderHelper, mkContainerCleanupHelper } from 'src/reactUtils';
+import { store } from 'src/redux';
+import { tryParseJson } from 'src/util';
+import { ClipLauncherState, TrackPlaybackState } from './messages';
+import ClipLauncherUI from './ClipLauncherUI';
+
+/**
+ * Called by the UI to subscribe to state updates, keyed by VC ID
+ */
+const stateListeners: Map<string, ((state: ClipLauncherState) => void) | null> = new Map();
+/**
+ * Called by the UI to subscribe to playback state updates, which the engine sends when the
+ * transport of one of the launcher's MIDI editors starts or stops
+ */
+const playbackListeners: Map<
+  string,
+  ((playbackStates: TrackPlaybackState[]) => void) | null
+> = new Map();
+
+const getVcId = (stateKey: string) => stateKey.split('_')[1]!;
+
+const getClipLauncherDOMElementId = (vcId: string) => `clip-launcher-${vcId}`;
+
+export const init_clip_launcher = (stateKey: string, stateJson: string) => {
+  const vcId = getVcId(stateKey);
+  const initialState = tryParseJson<ClipLauncherState>(
+    stateJson,
+    { tracks: [], sceneCount: 0 },
+    `Failed to parse state for clip launcher with stateKey ${stateKey}`
+  );
+  stateListeners.set(vcId, null);
+  playbackListeners.set(vcId, null);
+
+  const domId = getClipLauncherDOMElementId(vcId);
+  const elem = document.createElement('div');
+  elem.id = domId;
+  elem.setAttribute(
+    'style',
+    'z-index: 2; width: 100vw; height: 100vh; position: absolute; top: 0; left: 0; display: none;'
+  );
+  document.getElementById('content')!.appendChild(elem);
+
+  mkContainerRenderHelper({
+    Comp: ClipLauncherUI,
+    store,
+    getProps: () => ({
+      vcId,
+      initialState,
+      subscribe: (onStateChange: ((state: ClipLauncherState) => void) | null) =>
+        stateListeners.set(vcId, onStateChange),
+      subscribePlayback: (
+        onPlaybackChange: ((playbackStates: TrackPlaybackState[]) => void) | null
+      ) => playbackListeners.set(vcId, onPlaybackChange),
+    }),
+  })(domId);
+};
+
+export const set_clip_launcher_state = (stateKey: string, stateJson: string) => {
+  const vcId = getVcId(stateKey);
+  const onStateChange = stateListeners.get(vcId);
+  if (onStateChange) {
+    onStateChange(JSON.parse(stateJson));
+  }
+};
+
+export const set_clip_launcher_playback_state = (stateKey: string, playbackStateJson: string) => {
+  const vcId = getVcId(stateKey);
+  const onPlaybackChange = playbackListeners.get(vcId);
+  if (onPlaybackChange) {
+    onPlaybackChange(JSON.parse(playbackStateJson));
+  }
+};
+
+export const cleanup_clip_launcher = (stateKey: string) => {
+  const vcId = getVcId(stateKey);
+  stateListeners.delete(vcId);
+  playbackListeners.delete(vcId);
+
+  const domId = getClipLauncherDOMElementId(vcId);
+  mkContainerCleanupHelper()(domId);
+  const elem = document.getElementById(domId);
+  if (elem) {
+    elem.remove();
+  }
+};
+
+export const hide_clip_launcher = (stateKey: string) => {
+  const vcId = getVcId(stateKey);
+  const elem = document.getElementById(getClipLauncherDOMElementId(vcId));
+  if (!elem) {
+    console.error(`Unable to find DOM element for clip launcher with vcId ${vcId}; can't hide.`);
+    return;
+  }
+
+  elem.style.display = 'none';
+};
+
+export const unhide_clip_launcher = (stateKey: string) => {
+  const vcId = getVcId(stateKey);
+  const elem = document.getElementById(getClipLauncherDOMElementId(vcId));
+  if (!elem) {
+    console.error(`Unable to find DOM element for clip launcher with vcId ${vcId}; can't unhide.`);
+    return;
+  }
+
+  elem.style.display = 'block';
+};
@@ -0,0 +1,96 @@
+import { getEngine } from 'src';
+
+export interface ClipLauncherTrack {
+  midiEditorVcId: string | null;
+  cells: (string | null)[];
+}
+
+export interface ClipLauncherState {
+  tracks: ClipLauncherTrack[];
+  sceneCount: number;
+}
+
+export interface TrackPlaybackState {
+  playingClipId: string | null;
+  queuedClipId: string | null;
+  stopQueued: boolean;
+}
+
+export interface MIDIEditorClip {
+  id: string;
+  name: string;
+  lengthBeats: number;
+}
+
+/**
+ * Sends a message to the engine to be handled by the clip launcher with the provided `vcId`,
+ * returning the parsed response.
+ */
+const sendClipLauncherMessage = <T>(vcId: string, key: string, val: Uint8Array): T | null => {
+  const engine = getEngine();
+  if (!engine) {
+    console.error('Tried to update clip launcher before the engine was initialized');
+    return null;
+  }
+
+  const res = engine.handle_vc_message(vcId, key, val);
+  return res ? JSON.parse(new TextDecoder().decode(res)) : null;
+};
+
+const encodeJson = (val: any) => new TextEncoder().encode(JSON.stringify(val));
+
+const encodeIndex = (ix: number) => new Uint8Array(new Uint32Array([ix]).buffer);
+
+export const addTrack = (vcId: string) =>
+  sendClipLauncherMessage<ClipLauncherState>(vcId, 'add_track', new Uint8Array());
+
+export const removeTrack = (vcId: string, trackIx: number) =>
+  sendClipLauncherMessage<ClipLauncherState>(vcId, 'remove_track', encodeIndex(trackIx));
+
+export const addScene = (vcId: string) =>
+  sendClipLauncherMessage<ClipLauncherState>(vcId, 'add_scene', new Uint8Array());
+
+export const removeScene = (vcId: string, sceneIx: number) =>
+  sendClipLauncherMessage<ClipLauncherState>(vcId, 'remove_scene', encodeIndex(sceneIx));
+
+export const setTrackMIDIEditor = (vcId: string, trackIx: number, midiEditorVcId: string | null) =>
+  sendClipLauncherMessage<ClipLauncherState>(
+    vcId,
+    'set_track_midi_editor',
+    encodeJson({ trackIx, vcId: midiEditorVcId })
+  );
+
+export const setCell = (vcId: string, trackIx: number, sceneIx: number, clipId: string | null) =>
+  sendClipLauncherMessage<ClipLauncherState>(
+    vcId,
+    'set_cell',
+    encodeJson({ trackIx, sceneIx, clipId })
+  );
+
+export const launchCell = (vcId: string, trackIx: number, sceneIx: number) =>
+  sendClipLauncherMessage(vcId, 'launch_cell', encodeJson({ trackIx, sceneIx }));
+
+export const stopTrack = (vcId: string, trackIx: number) =>
+  sendClipLauncherMessage(vcId, 'stop_track', encodeIndex(trackIx));
+
+export const launchScene = (vcId: string, sceneIx: number) =>
+  sendClipLauncherMessage(vcId, 'launch_scene', encodeIndex(sceneIx));
+
+export const stopAll = (vcId: string) =>
+  sendClipLauncherMessage(vcId, 'stop_all', new Uint8Array());
+
+export const getPlaybackState = (vcId: string) =>
+  sendClipLauncherMessage<TrackPlaybackState[]>(vcId, 'get_playback_state', new Uint8Array());
+
+/**
+ * Returns the clips of the MIDI editor with the provided `vcId`, or an empty list if it doesn't
+ * exist.
+ */
+export const getMIDIEditorClips = (midiEditorVcId: string): MIDIEditorClip[] => {
+  const res = sendClipLauncherMessage<{ clips: MIDIEditorClip[] }>(
+    midiEditorVcId,
+    'get_clips',
+    new Uint8Array()
+  );
+  return res ? res.clips : [];
+};
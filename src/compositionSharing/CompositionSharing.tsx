@@ -9,6 +9,7 @@ import { BACKEND_BASE_URL } from '../conf';
 import { ReduxStore } from '../redux';
 import './CompositionSharing.scss';
 import { loadComposition } from '../persistance';
+import { getAllStoredEntries } from '../storage';
 
 interface CompositionDefinition {
   id: number;
@@ -150,7 +151,7 @@ const ShareCompositionInner: React.FC<{} & InjectedFormProps<{
         onSubmit={handleSubmit(async vals => {
           const res = await fetch(`${BACKEND_BASE_URL}/compositions`, {
             method: 'POST',
-            body: JSON.stringify({ ...vals, user: 0, content: { ...localStorage } }),
+            body: JSON.stringify({ ...vals, user: 0, content: getAllStoredEntries() }),
             headers: {
               'Content-Type': 'application/json',
             },
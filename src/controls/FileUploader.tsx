@@ -7,7 +7,7 @@ export interface Value<T = ArrayBuffer> {
   fileContent: T;
 }
 
-const parseUploadedFile = (evt: React.ChangeEvent<HTMLInputElement>): Promise<Value> =>
+export const parseUploadedFile = (evt: React.ChangeEvent<HTMLInputElement>): Promise<Value> =>
   new Promise((resolve, reject) => {
     const file = evt.target.files![0]!;
 
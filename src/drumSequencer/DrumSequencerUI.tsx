@@ -0,0 +1,327 @@
+import React, { useEffect, useMemo, useState } from 'react';
+
+import { renderModalWithControls } from 'src/controls/Modal';
+import { SampleDescriptor } from 'src/sampleLibrary';
+import SampleSelectDialog from 'src/sampleLibrary/SampleLibraryUI/SelectSample';
+import { getTransportState, setTransportState, subscribeToTransport } from 'src/transport';
+import {
+  DrumSequencerState,
+  DrumRow,
+  RowSource,
+  Pattern,
+  MAX_VELOCITY,
+  resolveChainStep,
+} from './drumSequencerAudio';
+import { AllSynthDrums, SynthDrum } from './synthDrums';
+import {
+  setStep,
+  setStepCount,
+  addRow,
+  removeRow,
+  setRowSource,
+  addPattern,
+  duplicatePattern,
+  removePattern,
+  setChain,
+} from './messages';
+import './DrumSequencer.scss';
+
+const DEFAULT_STEP = { velocity: 100, probability: 100 };
+
+interface StepValue {
+  velocity: number;
+  probability: number;
+}
+
+interface SelectedStep {
+  rowId: number;
+  stepIx: number;
+}
+
+const buildStepLookup = (pattern: Pattern | undefined) =>
+  (pattern ? pattern.steps : []).reduce(
+    (acc, [rowId, stepIx, velocity, probability]) =>
+      acc.set(`${rowId}-${stepIx}`, { velocity, probability }),
+    new Map<string, StepValue>()
+  );
+
+const selectSample = (): Promise<SampleDescriptor> => renderModalWithControls(SampleSelectDialog);
+
+const RowSourceSelect: React.FC<{ row: DrumRow }> = ({ row }) => {
+  const value = row.source.type === 'synth_drum' ? row.source.drum : 'sample';
+
+  return (
+    <div className='drum-sequencer-row-source'>
+      <select
+        value={value}
+        onChange={evt => {
+          const newSource: RowSource =
+            evt.target.value === 'sample'
+              ? { type: 'sample', sample: null }
+              : { type: 'synth_drum', drum: evt.target.value as SynthDrum };
+          setRowSource(row.id, newSource);
+        }}
+      >
+        {AllSynthDrums.map(drum => (
+          <option key={drum} value={drum}>
+            {drum}
+          </option>
+        ))}
+        <option value='sample'>sample</option>
+      </select>
+      {row.source.type === 'sample' ? (
+        <button
+          onClick={async () => {
+            try {
+              const sample = await selectSample();
+              setRowSource(row.id, { type: 'sample', sample });
+            } catch (_err) {
+              // The sample selection dialog was canceled
+            }
+          }}
+        >
+          {row.source.sample ? row.source.sample.name : 'Pick Sample'}
+        </button>
+      ) : null}
+    </div>
+  );
+};
+
+const StepCell: React.FC<{
+  step: StepValue | undefined;
+  isSelected: boolean;
+  isPlaying: boolean;
+  isBeatStart: boolean;
+  onClick: (evt: React.MouseEvent) => void;
+}> = ({ step, isSelected, isPlaying, isBeatStart, onClick }) => {
+  const classNames = ['drum-sequencer-step'];
+  if (step) {
+    classNames.push('active');
+  }
+  if (step && step.probability < 100) {
+    classNames.push('probabilistic');
+  }
+  if (isSelected) {
+    classNames.push('selected');
+  }
+  if (isPlaying) {
+    classNames.push('playing');
+  }
+  if (isBeatStart) {
+    classNames.push('beat-start');
+  }
+
+  return (
+    <div
+      className={classNames.join(' ')}
+      style={step ? { opacity: 0.3 + (0.7 * step.velocity) / MAX_VELOCITY } : undefined}
+      onClick={onClick}
+    />
+  );
+};
+
+const StepEditor: React.FC<{
+  patternId: number;
+  selectedStep: SelectedStep;
+  step: StepValue;
+}> = ({ patternId, selectedStep: { rowId, stepIx }, step }) => (
+  <div className='drum-sequencer-step-editor'>
+    <label>
+      Velocity
+      <input
+        type='range'
+        min={1}
+        max={MAX_VELOCITY}
+        value={step.velocity}
+        onChange={evt =>
+          setStep(patternId, rowId, stepIx, { ...step, velocity: +evt.target.value })
+        }
+      />
+      {step.velocity}
+    </label>
+    <label>
+      Probability
+      <input
+        type='range'
+        min={0}
+        max={100}
+        value={step.probability}
+        onChange={evt =>
+          setStep(patternId, rowId, stepIx, { ...step, probability: +evt.target.value })
+        }
+      />
+      {step.probability}%
+    </label>
+  </div>
+);
+
+const ChainEditor: React.FC<{ state: DrumSequencerState; playingChainIx: number | null }> = ({
+  state,
+  playingChainIx,
+}) => {
+  const patternNames = new Map(state.patterns.map(({ id, name }) => [id, name]));
+
+  return (
+    <div className='drum-sequencer-chain'>
+      Chain:
+      {state.chain.map((patternId, i) => (
+        <button
+          key={i}
+          className={i === playingChainIx ? 'playing' : undefined}
+          title='Click to remove from the chain'
+          onClick={() => setChain(state.chain.filter((_, j) => j !== i))}
+        >
+          {patternNames.get(patternId)}
+        </button>
+      ))}
+      <select value='' onChange={evt => setChain([...state.chain, +evt.target.value])}>
+        <option value=''>Append...</option>
+        {state.patterns.map(({ id, name }) => (
+          <option key={id} value={id}>
+            {name}
+          </option>
+        ))}
+      </select>
+    </div>
+  );
+};
+
+const TransportControls: React.FC<{ ctx: AudioContext }> = ({ ctx }) => {
+  const [transport, setTransport] = useState(getTransportState());
+  useEffect(() => subscribeToTransport(setTransport), []);
+
+  return (
+    <div className='drum-sequencer-transport'>
+      <button
+        onClick={() =>
+          setTransportState({
+            ...transport,
+            isPlaying: !transport.isPlaying,
+            beatZeroTime: ctx.currentTime,
+          })
+        }
+      >
+        {transport.isPlaying ? 'Stop' : 'Play'}
+      </button>
+      {transport.bpm} BPM
+    </div>
+  );
+};
+
+const DrumSequencerUI: React.FC<{
+  ctx: AudioContext;
+  initialState: DrumSequencerState;
+  subscribe: (onStateChange: ((state: DrumSequencerState) => void) | null) => void;
+  getPlayingChainStep: () => number | null;
+  previewRow: (rowId: number) => void;
+}> = ({ ctx, initialState, subscribe, getPlayingChainStep, previewRow }) => {
+  const [state, setState] = useState(initialState);
+  const [editingPatternId, setEditingPatternId] = useState(initialState.chain[0]);
+  const [selectedStep, setSelectedStep] = useState<SelectedStep | null>(null);
+  const [playingChainStep, setPlayingChainStep] = useState<number | null>(null);
+
+  useEffect(() => {
+    subscribe(setState);
+    return () => subscribe(null);
+  }, [subscribe]);
+
+  useEffect(() => {
+    let rafHandle = requestAnimationFrame(function tick() {
+      setPlayingChainStep(getPlayingChainStep());
+      rafHandle = requestAnimationFrame(tick);
+    });
+    return () => cancelAnimationFrame(rafHandle);
+  }, [getPlayingChainStep]);
+
+  const pattern = state.patterns.find(({ id }) => id === editingPatternId) || state.patterns[0];
+  const stepLookup = useMemo(() => buildStepLookup(pattern), [pattern]);
+  const playing = playingChainStep === null ? null : resolveChainStep(state, playingChainStep);
+  const playingChainIx =
+    playingChainStep === null
+      ? null
+      : Math.floor(playingChainStep / state.step_count) % state.chain.length;
+  const selectedStepValue = selectedStep
+    ? stepLookup.get(`${selectedStep.rowId}-${selectedStep.stepIx}`)
+    : undefined;
+
+  return (
+    <div className='drum-sequencer'>
+      <div className='drum-sequencer-controls'>
+        <TransportControls ctx={ctx} />
+        <select value={state.step_count} onChange={evt => setStepCount(+evt.target.value)}>
+          <option value={16}>16 steps</option>
+          <option value={32}>32 steps</option>
+        </select>
+        <div className='drum-sequencer-patterns'>
+          {state.patterns.map(({ id, name }) => (
+            <button
+              key={id}
+              className={id === pattern.id ? 'active' : undefined}
+              onClick={() => setEditingPatternId(id)}
+            >
+              {name}
+            </button>
+          ))}
+          <button onClick={addPattern}>+</button>
+          <button onClick={() => duplicatePattern(pattern.id)}>Duplicate</button>
+          <button
+            disabled={state.patterns.length <= 1}
+            onClick={() => {
+              removePattern(pattern.id);
+              setSelectedStep(null);
+            }}
+          >
+            Delete
+          </button>
+        </div>
+      </div>
+      <ChainEditor state={state} playingChainIx={playingChainIx} />
+
+      <div className='drum-sequencer-grid'>
+        {state.rows.map(row => (
+          <div key={row.id} className='drum-sequencer-row'>
+            <div className='drum-sequencer-row-name' onClick={() => previewRow(row.id)}>
+              {row.name}
+            </div>
+            <RowSourceSelect row={row} />
+            {Array.from({ length: state.step_count }, (_, stepIx) => {
+              const step = stepLookup.get(`${row.id}-${stepIx}`);
+              const isSelected =
+                !!selectedStep && selectedStep.rowId === row.id && selectedStep.stepIx === stepIx;
+
+              return (
+                <StepCell
+                  key={stepIx}
+                  step={step}
+                  isSelected={isSelected}
+                  isPlaying={
+                    !!playing && playing.patternId === pattern.id && playing.stepIx === stepIx
+                  }
+                  isBeatStart={stepIx % 4 === 0}
+                  onClick={evt => {
+                    // Shift-click selects a step for editing without toggling it
+                    if (evt.shiftKey && step) {
+                      setSelectedStep({ rowId: row.id, stepIx });
+                      return;
+                    }
+
+                    setStep(pattern.id, row.id, stepIx, step ? null : DEFAULT_STEP);
+                    setSelectedStep(step ? null : { rowId: row.id, stepIx });
+                  }}
+                />
+              );
+            })}
+            <button onClick={() => removeRow(row.id)}>×</button>
+          </div>
+        ))}
+        <button onClick={() => addRow({ type: 'sample', sample: null })}>Add Row</button>
+      </div>
+
+      {selectedStep && selectedStepValue ? (
+        <StepEditor patternId={pattern.id} selectedStep={selectedStep} step={selectedStepValue} />
+      ) : null}
+    </div>
+  );
+};
+
+export default DrumSequencerUI;
@@ -0,0 +1,232 @@
+import { Map } from 'immutable';
+
+import { AudioConnectables, ConnectableInput, ConnectableOutput } from 'src/patchNetwork';
+import { SampleDescriptor, getSample, hashSampleDescriptor } from 'src/sampleLibrary';
+import { getTransportState, subscribeToTransport, TransportState } from 'src/transport';
+import { SynthDrum, playSynthDrum } from './synthDrums';
+
+export type RowSource =
+  | { type: 'sample'; sample: SampleDescriptor | null }
+  | { type: 'synth_drum'; drum: SynthDrum };
+
+export interface DrumRow {
+  id: number;
+  name: string;
+  source: RowSource;
+}
+
+/**
+ * `[rowId, stepIx, velocity, probability]`.  Only active steps are included.
+ */
+export type CompactStep = [number, number, number, number];
+
+export interface Pattern {
+  id: number;
+  name: string;
+  steps: CompactStep[];
+}
+
+/**
+ * Mirrors the state serialized by the engine's `DrumSequencerState`
+ */
+export interface DrumSequencerState {
+  step_count: number;
+  rows: DrumRow[];
+  patterns: Pattern[];
+  chain: number[];
+}
+
+export const MAX_VELOCITY = 127;
+
+/**
+ * Each step is a 16th note
+ */
+const STEPS_PER_BEAT = 4;
+const SCHEDULE_INTERVAL_MS = 50;
+const LOOKAHEAD_SECONDS = 0.2;
+
+const getStepDuration = (bpm: number) => 60 / bpm / STEPS_PER_BEAT;
+
+/**
+ * Returns the pattern and step index that is played at the provided step of the chain.  Mirrors
+ * `DrumSequencerState::resolve_chain_step` in the engine.
+ */
+export const resolveChainStep = (
+  state: DrumSequencerState,
+  chainStep: number
+): { patternId: number; stepIx: number } => ({
+  patternId: state.chain[Math.floor(chainStep / state.step_count) % state.chain.length],
+  stepIx: chainStep % state.step_count,
+});
+
+/**
+ * Schedules the patterns of a drum sequencer against the global transport, playing samples and
+ * synthesized drums into a single output.
+ */
+export class DrumSequencerAudio {
+  private ctx: AudioContext;
+  private vcId: string;
+  private output: GainNode;
+  /**
+   * Active steps of each pattern grouped by step index
+   */
+  private stepsByPatternId: { [patternId: number]: CompactStep[][] } = {};
+  private sampleBuffers: { [rowId: number]: { hash: string; buffer: AudioBuffer | null } } = {};
+  private schedulerHandle: number | null = null;
+  private nextChainStep = 0;
+  private lastScheduledTime = -Infinity;
+  private unsubscribeFromTransport: () => void;
+  public state: DrumSequencerState;
+
+  constructor(ctx: AudioContext, vcId: string, state: DrumSequencerState) {
+    this.ctx = ctx;
+    this.vcId = vcId;
+    this.output = new GainNode(ctx);
+    this.state = state;
+    this.setState(state);
+
+    this.unsubscribeFromTransport = subscribeToTransport(this.handleTransportChange);
+    this.handleTransportChange(getTransportState());
+  }
+
+  public setState(state: DrumSequencerState) {
+    this.state = state;
+    this.stepsByPatternId = state.patterns.reduce((acc, { id, steps }) => {
+      const byStep: CompactStep[][] = Array.from({ length: state.step_count }, () => []);
+      steps.forEach(step => byStep[step[1]]?.push(step));
+      return { ...acc, [id]: byStep };
+    }, {});
+
+    state.rows.forEach(({ id, source }) => {
+      if (source.type !== 'sample' || !source.sample) {
+        delete this.sampleBuffers[id];
+        return;
+      }
+
+      const hash = hashSampleDescriptor(source.sample);
+      if (this.sampleBuffers[id]?.hash === hash) {
+        return;
+      }
+
+      this.sampleBuffers[id] = { hash, buffer: null };
+      getSample(source.sample)
+        .then(buffer => {
+          // The sample may have been changed again while it was loading
+          if (this.sampleBuffers[id]?.hash === hash) {
+            this.sampleBuffers[id].buffer = buffer;
+          }
+        })
+        .catch(err => console.warn(`Unable to load sample for drum sequencer row ${id}: `, err));
+    });
+  }
+
+  private handleTransportChange = ({ isPlaying, bpm, beatZeroTime }: TransportState) => {
+    this.stopScheduler();
+    if (!isPlaying) {
+      this.lastScheduledTime = -Infinity;
+      return;
+    }
+
+    const stepDuration = getStepDuration(bpm);
+    this.nextChainStep = Math.max(
+      Math.ceil((this.ctx.currentTime - beatZeroTime) / stepDuration),
+      0
+    );
+    const schedule = () => {
+      const endOfWindow = this.ctx.currentTime + LOOKAHEAD_SECONDS;
+      for (
+        let time = beatZeroTime + this.nextChainStep * stepDuration;
+        time < endOfWindow;
+        time = beatZeroTime + this.nextChainStep * stepDuration
+      ) {
+        // Steps can already have been scheduled if the tempo changed during the lookahead window
+        if (time > this.lastScheduledTime) {
+          this.scheduleChainStep(this.nextChainStep, time);
+          this.lastScheduledTime = time;
+        }
+        this.nextChainStep += 1;
+      }
+    };
+
+    schedule();
+    this.schedulerHandle = setInterval(schedule, SCHEDULE_INTERVAL_MS);
+  };
+
+  private stopScheduler() {
+    if (this.schedulerHandle !== null) {
+      clearInterval(this.schedulerHandle);
+      this.schedulerHandle = null;
+    }
+  }
+
+  private scheduleChainStep(chainStep: number, time: number) {
+    const { patternId, stepIx } = resolveChainStep(this.state, chainStep);
+    const steps = this.stepsByPatternId[patternId]?.[stepIx] || [];
+    steps.forEach(([rowId, , velocity, probability]) => {
+      if (Math.random() * 100 >= probability) {
+        return;
+      }
+
+      const row = this.state.rows.find(({ id }) => id === rowId);
+      if (row) {
+        this.playRow(row, time, velocity / MAX_VELOCITY);
+      }
+    });
+  }
+
+  private playRow(row: DrumRow, time: number, gain: number) {
+    if (row.source.type === 'synth_drum') {
+      playSynthDrum(this.ctx, row.source.drum, this.output, time, gain);
+      return;
+    }
+
+    const buffer = this.sampleBuffers[row.id]?.buffer;
+    if (!buffer) {
+      return;
+    }
+    const source = new AudioBufferSourceNode(this.ctx, { buffer });
+    const gainNode = new GainNode(this.ctx, { gain });
+    source.connect(gainNode);
+    gainNode.connect(this.output);
+    source.start(time);
+  }
+
+  /**
+   * Plays a single hit of the row right now at full velocity
+   */
+  public previewRow(rowId: number) {
+    const row = this.state.rows.find(({ id }) => id === rowId);
+    if (row) {
+      this.playRow(row, this.ctx.currentTime, 1);
+    }
+  }
+
+  /**
+   * Returns the step of the chain that is currently being played, or `null` if the transport
+   * isn't playing
+   */
+  public getPlayingChainStep(): number | null {
+    const { isPlaying, bpm, beatZeroTime } = getTransportState();
+    if (!isPlaying || this.ctx.currentTime < beatZeroTime) {
+      return null;
+    }
+
+    return Math.floor((this.ctx.currentTime - beatZeroTime) / getStepDuration(bpm));
+  }
+
+  public buildConnectables(): AudioConnectables {
+    return {
+      vcId: this.vcId,
+      inputs: Map<string, ConnectableInput>(),
+      outputs: Map<string, ConnectableOutput>().set('output', {
+        node: this.output,
+        type: 'customAudio',
+      }),
+    };
+  }
+
+  public destroy() {
+    this.stopScheduler();
+    this.unsubscribeFromTransport();
+  }
+}
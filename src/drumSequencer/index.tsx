@@ -0,0 +1,131 @@
+/**
+ * View context for a step sequencer for drums.  The patterns live in the engine, which sends them
+ * over every time that they change; this schedules them against the global transport and renders
+ * the UI.
+ */
+
+import { AudioConnectables, create_empty_audio_connectables } from 'src/patchNetwork';
+import { mkContainerRenderHelper, mkContainerCleanupHelper } from 'src/reactUtils';
+import { store } from 'src/redux';
+import { tryParseJson } from 'src/util';
+import { DrumSequencerAudio, DrumSequencerState } from './drumSequencerAudio';
+import DrumSequencerUI from './DrumSequencerUI';
+
+const ctx = new AudioContext();
+
+interface DrumSequencerInstance {
+  audio: DrumSequencerAudio;
+  /**
+   * Set by the UI to subscribe to state updates
+   */
+  onStateChange: ((state: DrumSequencerState) => void) | null;
+}
+
+const drumSequencers: Map<string, DrumSequencerInstance> = new Map();
+
+const getVcId = (stateKey: string) => stateKey.split('_')[1]!;
+
+const getDrumSequencerDOMElementId = (vcId: string) => `drum-sequencer-${vcId}`;
+
+export const init_drum_sequencer = (stateKey: string, stateJson: string) => {
+  const vcId = getVcId(stateKey);
+  const initialState = tryParseJson<DrumSequencerState>(
+    stateJson,
+    { step_count: 16, rows: [], patterns: [{ id: 0, name: 'Pattern 1', steps: [] }], chain: [0] },
+    `Failed to parse state for drum sequencer with stateKey ${stateKey}`
+  );
+  const instance: DrumSequencerInstance = {
+    audio: new DrumSequencerAudio(ctx, vcId, initialState),
+    onStateChange: null,
+  };
+  drumSequencers.set(vcId, instance);
+
+  const domId = getDrumSequencerDOMElementId(vcId);
+  const elem = document.createElement('div');
+  elem.id = domId;
+  elem.setAttribute(
+    'style',
+    'z-index: 2; width: 100vw; height: 100vh; position: absolute; top: 0; left: 0; display: none;'
+  );
+  document.getElementById('content')!.appendChild(elem);
+
+  mkContainerRenderHelper({
+    Comp: DrumSequencerUI,
+    store,
+    getProps: () => ({
+      ctx,
+      initialState,
+      subscribe: (onStateChange: DrumSequencerInstance['onStateChange']) => {
+        instance.onStateChange = onStateChange;
+      },
+      getPlayingChainStep: () => instance.audio.getPlayingChainStep(),
+      previewRow: (rowId: number) => instance.audio.previewRow(rowId),
+    }),
+  })(domId);
+};
+
+export const set_drum_sequencer_state = (stateKey: string, stateJson: string) => {
+  const vcId = getVcId(stateKey);
+  const instance = drumSequencers.get(vcId);
+  if (!instance) {
+    console.error(
+      `Tried to set state of drum sequencer with vcId ${vcId} but it wasn't initialized`
+    );
+    return;
+  }
+
+  const newState: DrumSequencerState = JSON.parse(stateJson);
+  instance.audio.setState(newState);
+  if (instance.onStateChange) {
+    instance.onStateChange(newState);
+  }
+};
+
+export const cleanup_drum_sequencer = (stateKey: string) => {
+  const vcId = getVcId(stateKey);
+  const instance = drumSequencers.get(vcId);
+  if (instance) {
+    instance.audio.destroy();
+    drumSequencers.delete(vcId);
+  }
+
+  const domId = getDrumSequencerDOMElementId(vcId);
+  mkContainerCleanupHelper()(domId);
+  const elem = document.getElementById(domId);
+  if (elem) {
+    elem.remove();
+  }
+};
+
+export const hide_drum_sequencer = (stateKey: string) => {
+  const vcId = getVcId(stateKey);
+  const elem = document.getElementById(getDrumSequencerDOMElementId(vcId));
+  if (!elem) {
+    console.error(`Unable to find DOM element for drum sequencer with vcId ${vcId}; can't hide.`);
+    return;
+  }
+
+  elem.style.display = 'none';
+};
+
+export const unhide_drum_sequencer = (stateKey: string) => {
+  const vcId = getVcId(stateKey);
+  const elem = document.getElementById(getDrumSequencerDOMElementId(vcId));
+  if (!elem) {
+    console.error(`Unable to find DOM element for drum sequencer with vcId ${vcId}; can't unhide.`);
+    return;
+  }
+
+  elem.style.display = 'block';
+};
+
+export const get_drum_sequencer_audio_connectables = (stateKey: string): AudioConnectables => {
+  const vcId = getVcId(stateKey);
+  const instance = drumSequencers.get(vcId);
+  if (!instance) {
+    console.warn(`No drum sequencer found for VC with VC ID "${vcId}"`);
+    return create_empty_audio_connectables(vcId);
+  }
+
+  return instance.audio.buildConnectables();
+};
@@ -0,0 +1,54 @@
+import { getEngine } from 'src';
+import { DrumSequencerState, RowSource } from './drumSequencerAudio';
+
+/**
+ * Sends a message to the engine to be handled by the active drum sequencer, returning the updated
+ * state.
+ */
+const sendDrumSequencerMessage = (key: string, val: Uint8Array): DrumSequencerState | null => {
+  const engine = getEngine();
+  if (!engine) {
+    console.error('Tried to update drum sequencer before the engine was initialized');
+    return null;
+  }
+
+  const res = engine.handle_message(key, val);
+  return res ? JSON.parse(new TextDecoder().decode(res)) : null;
+};
+
+const encodeJson = (val: any) => new TextEncoder().encode(JSON.stringify(val));
+
+const encodeU32 = (val: number) => new Uint8Array(new Uint32Array([val]).buffer);
+
+/**
+ * Sets the velocity and probability of a step, or clears it if `value` is `null`
+ */
+export const setStep = (
+  patternId: number,
+  rowId: number,
+  step: number,
+  value: { velocity: number; probability: number } | null
+) => sendDrumSequencerMessage('set_step', encodeJson({ patternId, rowId, step, value }));
+
+export const setStepCount = (stepCount: number) =>
+  sendDrumSequencerMessage('set_step_count', encodeU32(stepCount));
+
+export const addRow = (source: RowSource) =>
+  sendDrumSequencerMessage('add_row', encodeJson(source));
+
+export const removeRow = (rowId: number) =>
+  sendDrumSequencerMessage('remove_row', encodeU32(rowId));
+
+export const setRowSource = (rowId: number, source: RowSource) =>
+  sendDrumSequencerMessage('set_row_source', encodeJson({ rowId, source }));
+
+export const addPattern = () => sendDrumSequencerMessage('add_pattern', new Uint8Array());
+
+export const duplicatePattern = (patternId: number) =>
+  sendDrumSequencerMessage('duplicate_pattern', encodeU32(patternId));
+
+export const removePattern = (patternId: number) =>
+  sendDrumSequencerMessage('remove_pattern', encodeU32(patternId));
+
+export const setChain = (chain: number[]) =>
+  sendDrumSequencerMessage('set_chain', encodeJson(chain));
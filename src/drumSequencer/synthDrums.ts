@@ -0,0 +1,113 @@
+/**
+ * Simple synthesized drum sounds built out of oscillators and filtered noise, so that the drum
+ * sequencer is usable without loading any samples.
+ */
+
+export type SynthDrum = 'kick' | 'snare' | 'closed_hat' | 'open_hat' | 'clap';
+
+export const AllSynthDrums: SynthDrum[] = ['kick', 'snare', 'closed_hat', 'open_hat', 'clap'];
+
+let noiseBuffer: AudioBuffer | null = null;
+
+const getNoiseBuffer = (ctx: BaseAudioContext): AudioBuffer => {
+  if (noiseBuffer) {
+    return noiseBuffer;
+  }
+
+  noiseBuffer = ctx.createBuffer(1, ctx.sampleRate, ctx.sampleRate);
+  const data = noiseBuffer.getChannelData(0);
+  for (let i = 0; i < data.length; i++) {
+    data[i] = Math.random() * 2 - 1;
+  }
+  return noiseBuffer;
+};
+
+/**
+ * Creates a gain node that decays exponentially from `peak` to silence over `decay` seconds
+ * starting at `time`
+ */
+const buildEnvelope = (
+  ctx: BaseAudioContext,
+  dst: AudioNode,
+  time: number,
+  peak: number,
+  decay: number
+): GainNode => {
+  const env = new GainNode(ctx, { gain: 0 });
+  env.gain.setValueAtTime(peak, time);
+  env.gain.exponentialRampToValueAtTime(0.0001, time + decay);
+  env.connect(dst);
+  return env;
+};
+
+const playNoise = (
+  ctx: BaseAudioContext,
+  dst: AudioNode,
+  time: number,
+  duration: number,
+  filter: BiquadFilterOptions
+) => {
+  const source = new AudioBufferSourceNode(ctx, { buffer: getNoiseBuffer(ctx) });
+  const biquad = new BiquadFilterNode(ctx, filter);
+  source.connect(biquad);
+  biquad.connect(dst);
+  source.start(time);
+  source.stop(time + duration);
+};
+
+const SynthDrumPlayers: {
+  [K in SynthDrum]: (ctx: BaseAudioContext, dst: AudioNode, time: number, gain: number) => void;
+} = {
+  kick: (ctx, dst, time, gain) => {
+    const osc = new OscillatorNode(ctx, { frequency: 150 });
+    osc.frequency.setValueAtTime(150, time);
+    osc.frequency.exponentialRampToValueAtTime(45, time + 0.12);
+    osc.connect(buildEnvelope(ctx, dst, time, gain, 0.45));
+    osc.start(time);
+    osc.stop(time + 0.5);
+  },
+  snare: (ctx, dst, time, gain) => {
+    const osc = new OscillatorNode(ctx, { type: 'triangle', frequency: 185 });
+    osc.connect(buildEnvelope(ctx, dst, time, gain * 0.5, 0.1));
+    osc.start(time);
+    osc.stop(time + 0.15);
+    playNoise(ctx, buildEnvelope(ctx, dst, time, gain, 0.2), time, 0.25, {
+      type: 'highpass',
+      frequency: 1200,
+    });
+  },
+  closed_hat: (ctx, dst, time, gain) =>
+    playNoise(ctx, buildEnvelope(ctx, dst, time, gain * 0.6, 0.05), time, 0.08, {
+      type: 'highpass',
+      frequency: 7000,
+    }),
+  open_hat: (ctx, dst, time, gain) =>
+    playNoise(ctx, buildEnvelope(ctx, dst, time, gain * 0.6, 0.35), time, 0.4, {
+      type: 'highpass',
+      frequency: 7000,
+    }),
+  clap: (ctx, dst, time, gain) => {
+    // A few short bursts in quick succession followed by a longer tail
+    [0, 0.01, 0.02].forEach(offset =>
+      playNoise(ctx, buildEnvelope(ctx, dst, time + offset, gain, 0.01), time + offset, 0.02, {
+        type: 'bandpass',
+        frequency: 1500,
+      })
+    );
+    playNoise(ctx, buildEnvelope(ctx, dst, time + 0.03, gain, 0.15), time + 0.03, 0.2, {
+      type: 'bandpass',
+      frequency: 1500,
+    });
+  },
+};
+
+/**
+ * Plays the drum into `dst` at `time`.  `gain` is in the range [0, 1].
+ */
+export const playSynthDrum = (
+  ctx: BaseAudioContext,
+  drum: SynthDrum,
+  dst: AudioNode,
+  time: number,
+  gain: number
+) => SynthDrumPlayers[drum](ctx, dst, time, gain);
@@ -0,0 +1,59 @@
+/**
+ * Encodes typed messages for `handle_binary_message`, which are used in place of `handle_message`
+ * for messages that are sent many times per second such as parameter changes.  The format is
+ * defined by `Message` in the engine: a little-endian `u32` index of the message's variant
+ * followed by each of its fields.  The variant indices must match the order that they're defined
+ * in there.
+ */
+
+import { getEngine } from 'src';
+
+type Field = { type: 'u32'; value: number } | { type: 'f32'; value: number } | boolean;
+
+const encodeMessage = (variantIx: number, fields: Field[]): Uint8Array => {
+  const len = fields.reduce((acc, field) => acc + (typeof field === 'boolean' ? 1 : 4), 4);
+  const view = new DataView(new ArrayBuffer(len));
+  view.setUint32(0, variantIx, true);
+
+  let offset = 4;
+  fields.forEach(field => {
+    if (typeof field === 'boolean') {
+      view.setUint8(offset, field ? 1 : 0);
+      offset += 1;
+    } else if (field.type === 'u32') {
+      view.setUint32(offset, field.value, true);
+      offset += 4;
+    } else {
+      view.setFloat32(offset, field.value, true);
+      offset += 4;
+    }
+  });
+
+  return new Uint8Array(view.buffer);
+};
+
+const u32 = (value: number): Field => ({ type: 'u32', value });
+const f32 = (value: number): Field => ({ type: 'f32', value });
+
+export const Messages = {
+  setTrackGain: (trackId: number, gain: number) => encodeMessage(0, [u32(trackId), f32(gain)]),
+  setTrackPan: (trackId: number, pan: number) => encodeMessage(1, [u32(trackId), f32(pan)]),
+  setTrackMuted: (trackId: number, muted: boolean) => encodeMessage(2, [u32(trackId), muted]),
+  setTrackSoloed: (trackId: number, soloed: boolean) => encodeMessage(3, [u32(trackId), soloed]),
+  setMasterGain: (gain: number) => encodeMessage(4, [f32(gain)]),
+  setModRouteDepth: (routeId: number, depth: number) =>
+    encodeMessage(5, [u32(routeId), f32(depth)]),
+};
+
+/**
+ * Sends an encoded message to the active view context
+ */
+export const sendBinaryMessage = (message: Uint8Array): Uint8Array | undefined => {
+  const engine = getEngine();
+  if (!engine) {
+    console.error('Tried to send a message before the engine was initialized');
+    return;
+  }
+
+  return engine.handle_binary_message(message);
+};
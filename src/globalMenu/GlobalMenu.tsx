@@ -1,10 +1,24 @@
 import React, { useState } from 'react';
 import { connect } from 'react-redux';
 import * as R from 'ramda';
+import downloadjs from 'downloadjs';
 
-import { serializeAndDownloadComposition, loadComposition } from 'src/persistance';
-import { parseUploadedFileAsText } from 'src/controls/FileUploader';
+import {
+  serializeAndDownloadComposition,
+  loadComposition,
+  exportProject,
+  importProject,
+} from 'src/persistance';
+import { parseUploadedFile, parseUploadedFileAsText } from 'src/controls/FileUploader';
 import { ReduxStore } from 'src/redux';
+import {
+  armRecording,
+  disarmRecording,
+  isRecordingArmed,
+  getRecordingLengthSeconds,
+  exportRecordingAsWav,
+  clearRecording,
+} from 'src/audioRecorder';
 import './GlobalMenu.scss';
 
 const GlobalMenuItem: React.FC<{ onClick: () => void }> = ({ children, onClick }) => (
@@ -50,6 +64,68 @@ const GlobalMenuInner: React.FC<
         Load from File
       </>
     </GlobalMenuItem>
+    <GlobalMenuItem
+      onClick={async () => {
+        await exportProject(engine);
+        closeMenu();
+      }}
+    >
+      Export Project
+    </GlobalMenuItem>
+    <GlobalMenuItem
+      onClick={() =>
+        document.getElementById('import-project-uploader')!.dispatchEvent(new MouseEvent('click'))
+      }
+    >
+      <>
+        <input
+          type='file'
+          id='import-project-uploader'
+          style={{ display: 'none' }}
+          onChange={async evt => {
+            const { fileContent } = await parseUploadedFile(evt);
+            const res = await importProject(fileContent, engine);
+            if (res.isLeft()) {
+              alert(res.value);
+            }
+            closeMenu();
+          }}
+        />
+        Import Project
+      </>
+    </GlobalMenuItem>
+    <GlobalMenuItem
+      onClick={() => {
+        if (isRecordingArmed()) {
+          disarmRecording();
+        } else {
+          armRecording();
+        }
+        closeMenu();
+      }}
+    >
+      {isRecordingArmed() ? 'Disarm Recording' : 'Arm Recording'}
+    </GlobalMenuItem>
+    {getRecordingLengthSeconds() > 0 ? (
+      <>
+        <GlobalMenuItem
+          onClick={() => {
+            downloadjs(new Blob([exportRecordingAsWav(24)]), 'recording.wav', 'audio/wav');
+            closeMenu();
+          }}
+        >
+          Export Recording ({getRecordingLengthSeconds().toFixed(1)}s)
+        </GlobalMenuItem>
+        <GlobalMenuItem
+          onClick={() => {
+            clearRecording();
+            closeMenu();
+          }}
+        >
+          Clear Recording
+        </GlobalMenuItem>
+      </>
+    ) : null}
   </div>
 );
 
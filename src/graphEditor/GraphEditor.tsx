@@ -17,6 +17,7 @@ import { tryParseJson } from 'src/util';
 import { LGAudioConnectables } from 'src/graphEditor/nodes/AudioConnectablesNode';
 import { getEngine } from 'src';
 import FlatButton from 'src/misc/FlatButton';
+import { setNodePositions } from 'src/graphEditor/audioGraph';
 
 (window as any).LGraph = LiteGraph.LGraph;
 
@@ -25,19 +26,51 @@ import FlatButton from 'src/misc/FlatButton';
  */
 const instaceMap: { [stateKey: string]: any } = {};
 
+/**
+ * Node positions are sent to the engine as nodes are moved and persisted as part of the graph
+ * editor's view context, so all that needs to be done here is to drop the instance.
+ */
 export const saveStateForInstance = (stateKey: string) => {
-  const instance = instaceMap[stateKey];
-  if (!instance) {
+  if (!instaceMap[stateKey]) {
     console.error(`No entry in \`instanceCbs\` for instance with stateKey "${stateKey}"`);
     return;
   }
 
-  const state = instance.serialize();
-  localStorage.setItem(stateKey, JSON.stringify(state));
-
   delete instaceMap[stateKey];
 };
 
+export type NodePositions = { [id: string]: [number, number] };
+
+const getNodePositions = (graph: any): NodePositions =>
+  (graph._nodes as { id: string | number; pos: [number, number] }[]).reduce(
+    (acc, { id, pos }) => ({ ...acc, [id.toString()]: [pos[0], pos[1]] }),
+    {} as NodePositions
+  );
+
+/**
+ * Graph editors created before node positions were stored in the engine saved them along with the
+ * rest of the serialized LiteGraph state in `localStorage`.
+ */
+const getLegacyNodePositions = (stateKey: string): NodePositions => {
+  if (!localStorage[stateKey]) {
+    return {};
+  }
+
+  const state = tryParseJson<{ nodes: { id: string | number; pos: [number, number] }[] }, null>(
+    localStorage[stateKey],
+    null,
+    'Error parsing serialized LiteGraph state'
+  );
+  if (!state) {
+    return {};
+  }
+
+  return state.nodes.reduce(
+    (acc, { id, pos }) => ({ ...acc, [id.toString()]: pos }),
+    {} as NodePositions
+  );
+};
+
 const mapStateToProps = (state: ReduxStore) => ({
   patchNetwork: state.viewContextManager.patchNetwork,
   activeViewContexts: state.viewContextManager.activeViewContexts,
@@ -71,8 +104,11 @@ const handleNodeSelectAction = ({
   }
 };
 
-const GraphEditor: React.FC<{ stateKey: string } & ReturnType<typeof mapStateToProps>> = ({
+const GraphEditor: React.FC<
+  { stateKey: string; initialNodePositions: NodePositions } & ReturnType<typeof mapStateToProps>
+> = ({
   stateKey,
+  initialNodePositions,
   patchNetwork,
   activeViewContexts,
   isLoaded,
@@ -115,6 +151,8 @@ const GraphEditor: React.FC<{ stateKey: string } & ReturnType<typeof mapStateToP
         });
         curSelectedNode.current = node;
       };
+      canvas.onNodeMoved = node =>
+        setNodePositions({ [node.id.toString()]: [node.pos[0], node.pos[1]] });
 
       graph.start();
 
@@ -140,16 +178,8 @@ const GraphEditor: React.FC<{ stateKey: string } & ReturnType<typeof mapStateToP
     if (!lGraphInstance || !isLoaded) {
       return;
     }
-    const state = tryParseJson<{ nodes: { id: string | number; pos: [number, number] }[] }, null>(
-      localStorage[stateKey],
-      null,
-      'Error parsing serialized LiteGraph state'
-    );
-    if (!state) {
-      return;
-    }
-
-    state.nodes.forEach(({ id, pos }) => {
+    const nodePositions = { ...getLegacyNodePositions(stateKey), ...initialNodePositions };
+    Object.entries(nodePositions).forEach(([id, pos]) => {
       const node = lGraphInstance._nodes_by_id[id];
       if (!node) {
         return;
@@ -158,10 +188,18 @@ const GraphEditor: React.FC<{ stateKey: string } & ReturnType<typeof mapStateToP
       node.pos = pos;
     });
     lGraphInstance.setDirtyCanvas(true, true);
-  }, [stateKey, lGraphInstance, isLoaded]);
+  }, [stateKey, initialNodePositions, lGraphInstance, isLoaded]);
 
   const uiControls = useMemo(
-    () => (lGraphInstance ? { arrange: () => lGraphInstance.arrange() } : {}),
+    () =>
+      lGraphInstance
+        ? {
+            arrange: () => {
+              lGraphInstance.arrange();
+              setNodePositions(getNodePositions(lGraphInstance));
+            },
+          }
+        : {},
     [lGraphInstance]
   );
 
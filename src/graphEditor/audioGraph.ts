@@ -0,0 +1,80 @@
+/**
+ * Mirrors the patch network into the engine's audio graph so that the graph editor can validate new
+ * connections (matching port types, no cycles) before they're made.  All of these messages are
+ * handled by the graph editor view context, so they should only be sent while it's active.
+ */
+
+import { getEngine } from 'src';
+import {
+  AudioConnectables,
+  ConnectableDescriptor,
+  ConnectableType,
+  PatchNetwork,
+} from 'src/patchNetwork';
+
+type PortType = 'audio' | 'cv' | 'midi';
+type NodeKind = 'instrument' | 'effect' | 'mixer' | 'output';
+
+const PORT_TYPES: { [K in ConnectableType]: PortType } = {
+  customAudio: 'audio',
+  number: 'cv',
+  midi: 'midi',
+};
+
+const getNodeKind = (connectables: AudioConnectables): NodeKind => {
+  if (connectables.outputs.size === 0) {
+    return 'output';
+  } else if (![...connectables.inputs.values()].some(({ type }) => type === 'customAudio')) {
+    return 'instrument';
+  } else if (connectables.node && connectables.node.nodeType.toLowerCase().includes('mixer')) {
+    return 'mixer';
+  }
+  return 'effect';
+};
+
+const sendMessage = (key: string, val: any): Uint8Array | undefined => {
+  const engine = getEngine();
+  if (!engine) {
+    console.error(`Tried to send "${key}" message to the graph editor before engine initialized`);
+    return;
+  }
+
+  return engine.handle_message(key, new TextEncoder().encode(JSON.stringify(val)));
+};
+
+export const syncAudioGraph = (patchNetwork: PatchNetwork) =>
+  sendMessage('sync_audio_graph', {
+    nodes: [...patchNetwork.connectables.entries()].map(([id, connectables]) => ({
+      id,
+      kind: getNodeKind(connectables),
+      name: connectables.node ? connectables.node.name : id,
+      inputs: [...connectables.inputs.entries()].map(([name, { type }]) => ({
+        name,
+        type: PORT_TYPES[type],
+      })),
+      outputs: [...connectables.outputs.entries()].map(([name, { type }]) => ({
+        name,
+        type: PORT_TYPES[type],
+      })),
+    })),
+    connections: patchNetwork.connections,
+  });
+
+/**
+ * Returns a description of why the connection is invalid, or `null` if it can be made.
+ */
+export const validateConnection = (
+  patchNetwork: PatchNetwork,
+  from: ConnectableDescriptor,
+  to: ConnectableDescriptor
+): string | null => {
+  syncAudioGraph(patchNetwork);
+  const res = sendMessage('connect', [from, to]);
+  if (!res || res.length === 0) {
+    return null;
+  }
+  return new TextDecoder().decode(res);
+};
+
+export const setNodePositions = (positions: { [id: string]: [number, number] }) =>
+  sendMessage('set_node_positions', positions);
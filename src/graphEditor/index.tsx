@@ -3,11 +3,12 @@ import ReactDOM from 'react-dom';
 import { Provider } from 'react-redux';
 
 import { store } from 'src/redux';
-import GraphEditor, { saveStateForInstance } from './GraphEditor';
+import { tryParseJson } from 'src/util';
+import GraphEditor, { saveStateForInstance, NodePositions } from './GraphEditor';
 
 const ROOT_NODE_ID = 'graph-editor-react-root' as const;
 
-export const init_graph_editor = (stateKey: string) => {
+export const init_graph_editor = (stateKey: string, serializedNodePositions: string) => {
   // Create the base dom node for the faust editor
   const graphEditorBaseNode = document.createElement('div');
   graphEditorBaseNode.id = ROOT_NODE_ID;
@@ -20,7 +21,14 @@ export const init_graph_editor = (stateKey: string) => {
   document.getElementById('content')!.appendChild(graphEditorBaseNode);
   ReactDOM.render(
     <Provider store={store}>
-      <GraphEditor stateKey={stateKey} />
+      <GraphEditor
+        stateKey={stateKey}
+        initialNodePositions={tryParseJson<NodePositions>(
+          serializedNodePositions,
+          {},
+          'Error parsing graph editor node positions'
+        )}
+      />
     </Provider>,
     graphEditorBaseNode
   );
@@ -7,11 +7,14 @@ import * as R from 'ramda';
 
 import { AudioConnectables, ConnectableDescriptor } from 'src/patchNetwork';
 import {
+  LiteGraphNode,
   LiteGraphNodeInput,
+  LiteGraphNodeOutput,
   LiteGraphLink,
   LiteGraph as LiteGraphType,
 } from 'src/graphEditor/LiteGraphTypes';
-import { dispatch, actionCreators } from 'src/redux';
+import { dispatch, actionCreators, getState } from 'src/redux';
+import { validateConnection } from 'src/graphEditor/audioGraph';
 
 export function LGAudioConnectables(this: any) {
   // Default Properties
@@ -65,6 +68,38 @@ LGAudioConnectables.prototype.onPropertyChanged = function(name: string, value:
   connectable.node.value = value;
 };
 
+/**
+ * Called by LiteGraph before a connection is made to one of this node's inputs.  New connections
+ * are checked against the engine's audio graph and rejected if they're invalid.
+ */
+LGAudioConnectables.prototype.onConnectInput = function(
+  this: LiteGraphNode,
+  inputSlot: number,
+  _type: string,
+  _output: LiteGraphNodeOutput,
+  srcNode: LiteGraphNode,
+  srcSlot: number
+): boolean {
+  const from: ConnectableDescriptor = {
+    vcId: srcNode.id.toString(),
+    name: srcNode.outputs[srcSlot].name,
+  };
+  const to: ConnectableDescriptor = { vcId: this.id.toString(), name: this.inputs[inputSlot].name };
+
+  // Connections that already exist in the patch network are being re-created from it
+  const { patchNetwork } = getState().viewContextManager;
+  if (patchNetwork.connections.some(conn => R.equals(conn, [from, to]))) {
+    return true;
+  }
+
+  const error = validateConnection(patchNetwork, from, to);
+  if (error) {
+    console.warn(`Rejected connection: ${error}`, from, to);
+    return false;
+  }
+  return true;
+};
+
 LGAudioConnectables.prototype.onConnectionsChange = function(
   this: { graph: LiteGraphType },
   _connection: 1 | 2,
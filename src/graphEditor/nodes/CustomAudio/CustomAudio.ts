@@ -30,6 +30,16 @@ import { mkContainerRenderHelper, mkContainerCleanupHelper } from 'src/reactUtil
 import { getState } from 'src/redux';
 import { ScaleAndShiftNode } from 'src/graphEditor/nodes/CustomAudio/ScaleAndShift';
 import WaveTable from 'src/graphEditor/nodes/CustomAudio/WaveTable/WaveTable';
+import { FMSynth } from 'src/graphEditor/nodes/CustomAudio/FMSynth';
+import { Filter } from 'src/graphEditor/nodes/CustomAudio/Filter';
+import { Delay } from 'src/graphEditor/nodes/CustomAudio/Delay';
+import { Distortion } from 'src/graphEditor/nodes/CustomAudio/Distortion';
+import { Reverb } from 'src/graphEditor/nodes/CustomAudio/Reverb';
+import { Sampler } from 'src/graphEditor/nodes/CustomAudio/Sampler';
+import { SoundFont } from 'src/graphEditor/nodes/CustomAudio/SoundFont';
+import { EffectsChain } from 'src/graphEditor/nodes/CustomAudio/EffectsChain';
+import { NoiseNode, SampleAndHoldNode } from 'src/graphEditor/nodes/CustomAudio/Noise';
+import { ParametricEQ } from 'src/graphEditor/nodes/CustomAudio/ParametricEQ';
 
 const ctx = new AudioContext();
 
@@ -423,6 +433,39 @@ export const audioNodeGetters: {
   'customAudio/wavetable': {
     nodeGetter: (vcId, params) => new WaveTable(ctx, vcId, params),
   },
+  'customAudio/fmSynth': {
+    nodeGetter: (vcId, params) => new FMSynth(ctx, vcId, params),
+  },
+  'customAudio/filter': {
+    nodeGetter: (vcId, params) => new Filter(ctx, vcId, params),
+  },
+  'customAudio/delay': {
+    nodeGetter: (vcId, params) => new Delay(ctx, vcId, params),
+  },
+  'customAudio/reverb': {
+    nodeGetter: (vcId, params) => new Reverb(ctx, vcId, params),
+  },
+  'customAudio/sampler': {
+    nodeGetter: (vcId, params) => new Sampler(ctx, vcId, params),
+  },
+  'customAudio/soundFont': {
+    nodeGetter: (vcId, params) => new SoundFont(ctx, vcId, params),
+  },
+  'customAudio/effectsChain': {
+    nodeGetter: (vcId, params) => new EffectsChain(ctx, vcId, params),
+  },
+  'customAudio/noise': {
+    nodeGetter: (vcId, params) => new NoiseNode(ctx, vcId, params),
+  },
+  'customAudio/sampleAndHold': {
+    nodeGetter: (vcId, params) => new SampleAndHoldNode(ctx, vcId, params),
+  },
+  'customAudio/parametricEQ': {
+    nodeGetter: (vcId, params) => new ParametricEQ(ctx, vcId, params),
+  },
+  'customAudio/distortion': {
+    nodeGetter: (vcId, params) => new Distortion(ctx, vcId, params),
+  },
 };
 
 const registerCustomAudioNode = (
@@ -518,6 +561,7 @@ const registerCustomAudioNode = (
   //
   // This way, the state is persisted in the node and so we hold no source of truth in the LG node.
   CustomAudioNode.prototype.onPropertyChanged = LGAudioConnectables.prototype.onPropertyChanged;
+  CustomAudioNode.prototype.onConnectInput = LGAudioConnectables.prototype.onConnectInput;
   CustomAudioNode.prototype.onConnectionsChange = LGAudioConnectables.prototype.onConnectionsChange;
   CustomAudioNode.prototype.setConnectables = LGAudioConnectables.prototype.setConnectables;
 
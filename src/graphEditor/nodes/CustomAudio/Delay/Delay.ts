@@ -0,0 +1,223 @@
+import { Map } from 'immutable';
+import * as R from 'ramda';
+
+import { ForeignNode } from 'src/graphEditor/nodes/CustomAudio';
+import { AudioConnectables, ConnectableInput, ConnectableOutput } from 'src/patchNetwork';
+import { OverridableAudioParam } from 'src/graphEditor/nodes/util';
+import { mkContainerRenderHelper, mkContainerCleanupHelper } from 'src/reactUtils';
+import { getTransportState, subscribeToTransport } from 'src/transport';
+import DelaySmallView, { DELAY_SYNC_BEATS } from './DelayUI';
+
+export interface DelayParams {
+  /**
+   * The delay time used when `sync` is `free`
+   */
+  delayMs: number;
+  /**
+   * One of the keys of `DELAY_SYNC_BEATS`
+   */
+  sync: string;
+  feedback: number;
+  wet: number;
+  /**
+   * If `true`, echoes alternate between the left and right channels
+   */
+  pingPong: boolean;
+}
+
+const MAX_DELAY_SECONDS = 5;
+/**
+ * Time constant used when changing the delay time so that it glides rather than clicking
+ */
+const DELAY_TIME_CHANGE_TIME_CONSTANT = 0.02;
+
+const DEFAULT_DELAY_PARAMS: DelayParams = {
+  delayMs: 250,
+  sync: 'free',
+  feedback: 0.4,
+  wet: 0.3,
+  pingPong: false,
+};
+
+/**
+ * A feedback delay with a delay time that is either set freely or synced to the transport's BPM.
+ *
+ * The `wet` param crossfades between the dry and delayed signals; it is fed into the gain of the
+ * wet signal directly and, inverted, into the gain of the dry signal.
+ */
+export class Delay implements ForeignNode {
+  private ctx: AudioContext;
+  private vcId: string;
+  private params: DelayParams;
+  private inputNode: GainNode;
+  private outputNode: GainNode;
+  private dryGain: GainNode;
+  private wetGain: GainNode;
+  private delayL: DelayNode;
+  private delayR: DelayNode;
+  private feedbackGain: GainNode;
+  private merger: ChannelMergerNode;
+
+  private feedbackOverrideCSN: ConstantSourceNode;
+  private wetOverrideCSN: ConstantSourceNode;
+
+  public nodeType = 'customAudio/delay';
+  public name = 'Delay';
+
+  /**
+   * See the docs for `enhanceAudioNode`.
+   */
+  public paramOverrides: {
+    [name: string]: { param: OverridableAudioParam; override: ConstantSourceNode };
+  };
+
+  constructor(ctx: AudioContext, vcId: string, params?: { [key: string]: any } | null) {
+    this.ctx = ctx;
+    this.vcId = vcId;
+    this.params = { ...DEFAULT_DELAY_PARAMS, ...this.deserialize(params || {}) };
+
+    this.inputNode = new GainNode(ctx);
+    this.outputNode = new GainNode(ctx);
+    this.dryGain = new GainNode(ctx, { gain: 1 });
+    this.wetGain = new GainNode(ctx, { gain: 0 });
+    this.delayL = new DelayNode(ctx, { maxDelayTime: MAX_DELAY_SECONDS });
+    this.delayR = new DelayNode(ctx, { maxDelayTime: MAX_DELAY_SECONDS });
+    this.feedbackGain = new GainNode(ctx, { gain: 0 });
+    this.merger = new ChannelMergerNode(ctx, { numberOfInputs: 2 });
+
+    this.inputNode.connect(this.dryGain);
+    this.dryGain.connect(this.outputNode);
+    this.inputNode.connect(this.delayL);
+    this.wetGain.connect(this.outputNode);
+    this.feedbackGain.connect(this.delayL);
+    this.wireDelays();
+
+    this.feedbackOverrideCSN = new ConstantSourceNode(ctx, { offset: this.params.feedback });
+    this.feedbackOverrideCSN.start();
+    this.wetOverrideCSN = new ConstantSourceNode(ctx, { offset: this.params.wet });
+    this.wetOverrideCSN.start();
+
+    // Drives the wet gain directly and the dry gain inverted so that they crossfade
+    const wetNode = new ConstantSourceNode(ctx, { offset: 0 });
+    wetNode.start();
+    wetNode.connect(this.wetGain.gain);
+    const dryInverter = new GainNode(ctx, { gain: -1 });
+    wetNode.connect(dryInverter);
+    dryInverter.connect(this.dryGain.gain);
+
+    this.paramOverrides = {
+      feedback: {
+        param: new OverridableAudioParam(ctx, this.feedbackGain.gain, this.feedbackOverrideCSN),
+        override: this.feedbackOverrideCSN,
+      },
+      wet: {
+        param: new OverridableAudioParam(ctx, wetNode.offset, this.wetOverrideCSN),
+        override: this.wetOverrideCSN,
+      },
+    };
+
+    this.updateDelayTime();
+    subscribeToTransport(() => this.updateDelayTime());
+
+    this.renderSmallView = mkContainerRenderHelper({
+      Comp: DelaySmallView,
+      getProps: () => ({
+        initialParams: this.params,
+        onChange: (params: DelayParams) => this.setParams(params),
+      }),
+    });
+
+    this.cleanupSmallView = mkContainerCleanupHelper();
+  }
+
+  /**
+   * Connects the delay lines for either a regular or ping-pong delay.  The feedback gain always
+   * feeds back into the left delay line.
+   */
+  private wireDelays() {
+    this.delayL.disconnect();
+    this.delayR.disconnect();
+    this.merger.disconnect();
+
+    if (this.params.pingPong) {
+      this.delayL.connect(this.delayR);
+      this.delayR.connect(this.feedbackGain);
+      this.delayL.connect(this.merger, 0, 0);
+      this.delayR.connect(this.merger, 0, 1);
+      this.merger.connect(this.wetGain);
+    } else {
+      this.delayL.connect(this.feedbackGain);
+      this.delayL.connect(this.wetGain);
+    }
+  }
+
+  private updateDelayTime() {
+    const beats = DELAY_SYNC_BEATS[this.params.sync];
+    const delaySeconds = R.isNil(beats)
+      ? this.params.delayMs / 1000
+      : (beats * 60) / getTransportState().bpm;
+    const clampedDelaySeconds = R.clamp(0, MAX_DELAY_SECONDS, delaySeconds);
+
+    [this.delayL, this.delayR].forEach(delay =>
+      delay.delayTime.setTargetAtTime(
+        clampedDelaySeconds,
+        this.ctx.currentTime,
+        DELAY_TIME_CHANGE_TIME_CONSTANT
+      )
+    );
+  }
+
+  public setParams(params: DelayParams) {
+    const pingPongChanged = params.pingPong !== this.params.pingPong;
+    this.params = params;
+
+    this.feedbackOverrideCSN.offset.value = params.feedback;
+    this.wetOverrideCSN.offset.value = params.wet;
+    if (pingPongChanged) {
+      this.wireDelays();
+    }
+    this.updateDelayTime();
+  }
+
+  private deserialize(params: { [key: string]: any }): Partial<DelayParams> {
+    const deserialized: Partial<DelayParams> = {};
+    if (typeof params.delayMs === 'number') {
+      deserialized.delayMs = params.delayMs;
+    }
+    if (params.sync in DELAY_SYNC_BEATS) {
+      deserialized.sync = params.sync;
+    }
+    if (typeof params.feedback === 'number') {
+      deserialized.feedback = params.feedback;
+    }
+    if (typeof params.wet === 'number') {
+      deserialized.wet = params.wet;
+    }
+    if (typeof params.pingPong === 'boolean') {
+      deserialized.pingPong = params.pingPong;
+    }
+    return deserialized;
+  }
+
+  public serialize(): { [key: string]: any } {
+    return this.params;
+  }
+
+  public buildConnectables(): AudioConnectables & { node: ForeignNode } {
+    return {
+      vcId: this.vcId,
+      inputs: Map<string, ConnectableInput>()
+        .set('input', { node: this.inputNode, type: 'customAudio' })
+        .set('feedback', { node: this.paramOverrides.feedback.param, type: 'number' })
+        .set('wet', { node: this.paramOverrides.wet.param, type: 'number' }),
+      outputs: Map<string, ConnectableOutput>().set('output', {
+        node: this.outputNode,
+        type: 'customAudio',
+      }),
+      node: this,
+    };
+  }
+
+  public renderSmallView: ForeignNode['renderSmallView'];
+  public cleanupSmallView: ForeignNode['cleanupSmallView'];
+}
@@ -0,0 +1,64 @@
+import React, { useState } from 'react';
+import ControlPanel from 'react-control-panel';
+
+import { DelayParams } from 'src/graphEditor/nodes/CustomAudio/Delay/Delay';
+
+/**
+ * Maps the names of the beat-synced delay times to their length in beats.  The `free` setting uses
+ * the `delay ms` setting instead.
+ */
+export const DELAY_SYNC_BEATS: { [sync: string]: number | null } = {
+  free: null,
+  '1/16': 1 / 4,
+  '1/8 triplet': 1 / 3,
+  '1/8': 1 / 2,
+  'dotted 1/8': 3 / 4,
+  '1/4': 1,
+  'dotted 1/4': 3 / 2,
+  '1/2': 2,
+  '1 bar': 4,
+};
+
+const SETTINGS = [
+  { type: 'range', label: 'delay ms', min: 1, max: 5000, scale: 'log', steps: 500 },
+  { type: 'select', label: 'sync', options: Object.keys(DELAY_SYNC_BEATS) },
+  { type: 'range', label: 'feedback', min: 0, max: 0.95 },
+  { type: 'range', label: 'wet', min: 0, max: 1 },
+  { type: 'checkbox', label: 'ping pong' },
+];
+
+const KEYS: { [label: string]: keyof DelayParams } = {
+  'delay ms': 'delayMs',
+  sync: 'sync',
+  feedback: 'feedback',
+  wet: 'wet',
+  'ping pong': 'pingPong',
+};
+
+const DelaySmallView: React.FC<{
+  initialParams: DelayParams;
+  onChange: (params: DelayParams) => void;
+}> = ({ initialParams, onChange }) => {
+  const [params, setParams] = useState(initialParams);
+
+  return (
+    <ControlPanel
+      style={{ width: 500 }}
+      settings={SETTINGS}
+      state={{
+        'delay ms': params.delayMs,
+        sync: params.sync,
+        feedback: params.feedback,
+        wet: params.wet,
+        'ping pong': params.pingPong,
+      }}
+      onChange={(key: string, val: any) => {
+        const newParams = { ...params, [KEYS[key]]: val };
+        setParams(newParams);
+        onChange(newParams);
+      }}
+    />
+  );
+};
+
+export default DelaySmallView;
@@ -0,0 +1,72 @@
+import { Map } from 'immutable';
+
+import { ForeignNode } from 'src/graphEditor/nodes/CustomAudio';
+import { AudioConnectables, ConnectableInput, ConnectableOutput } from 'src/patchNetwork';
+import { OverridableAudioParam } from 'src/graphEditor/nodes/util';
+import { mkContainerRenderHelper, mkContainerCleanupHelper } from 'src/reactUtils';
+import {
+  DistortionChain,
+  DistortionParams,
+  DEFAULT_DISTORTION_PARAMS,
+  deserializeDistortionParams,
+} from './distortionChain';
+import DistortionSmallView from './DistortionUI';
+
+/**
+ * Waveshaping distortion for use on buses or anywhere else in the graph.  The synth designer has a
+ * per-voice version of the same effect.
+ */
+export class Distortion implements ForeignNode {
+  private vcId: string;
+  private chain: DistortionChain;
+
+  public nodeType = 'customAudio/distortion';
+  public name = 'Distortion';
+
+  /**
+   * See the docs for `enhanceAudioNode`.
+   */
+  public paramOverrides: {
+    [name: string]: { param: OverridableAudioParam; override: ConstantSourceNode };
+  } = {};
+
+  constructor(ctx: AudioContext, vcId: string, params?: { [key: string]: any } | null) {
+    this.vcId = vcId;
+    this.chain = new DistortionChain(ctx, {
+      ...DEFAULT_DISTORTION_PARAMS,
+      ...deserializeDistortionParams(params || {}),
+    });
+
+    this.renderSmallView = mkContainerRenderHelper({
+      Comp: DistortionSmallView,
+      getProps: () => ({
+        initialParams: this.chain.params,
+        onChange: (params: DistortionParams) => this.chain.setParams(params),
+      }),
+    });
+
+    this.cleanupSmallView = mkContainerCleanupHelper();
+  }
+
+  public serialize(): { [key: string]: any } {
+    return this.chain.params;
+  }
+
+  public buildConnectables(): AudioConnectables & { node: ForeignNode } {
+    return {
+      vcId: this.vcId,
+      inputs: Map<string, ConnectableInput>().set('input', {
+        node: this.chain.shaper,
+        type: 'customAudio',
+      }),
+      outputs: Map<string, ConnectableOutput>().set('output', {
+        node: this.chain.toneFilter,
+        type: 'customAudio',
+      }),
+      node: this,
+    };
+  }
+
+  public renderSmallView: ForeignNode['renderSmallView'];
+  public cleanupSmallView: ForeignNode['cleanupSmallView'];
+}
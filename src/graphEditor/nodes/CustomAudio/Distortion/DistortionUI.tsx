@@ -0,0 +1,65 @@
+import React, { useState } from 'react';
+import ControlPanel from 'react-control-panel';
+
+import {
+  ALL_OVERSAMPLE_TYPES,
+  DistortionParams,
+  DISTORTION_CURVES,
+  MAX_DISTORTION_DRIVE,
+  MAX_DISTORTION_TONE,
+  MIN_DISTORTION_DRIVE,
+  MIN_DISTORTION_TONE,
+} from 'src/graphEditor/nodes/CustomAudio/Distortion/distortionChain';
+
+const SETTINGS = [
+  { type: 'select', label: 'curve', options: Object.keys(DISTORTION_CURVES) },
+  {
+    type: 'range',
+    label: 'drive',
+    min: MIN_DISTORTION_DRIVE,
+    max: MAX_DISTORTION_DRIVE,
+    scale: 'log',
+    steps: 200,
+  },
+  {
+    type: 'range',
+    label: 'tone',
+    min: MIN_DISTORTION_TONE,
+    max: MAX_DISTORTION_TONE,
+    scale: 'log',
+    steps: 500,
+  },
+  { type: 'select', label: 'oversample', options: ALL_OVERSAMPLE_TYPES },
+];
+
+const DistortionSmallView: React.FC<{
+  initialParams: DistortionParams;
+  onChange: (params: DistortionParams) => void;
+}> = ({ initialParams, onChange }) => {
+  const [params, setParams] = useState(initialParams);
+
+  return (
+    <ControlPanel
+      style={{ width: 500 }}
+      settings={SETTINGS}
+      state={{
+        curve: Object.keys(DISTORTION_CURVES).find(
+          label => DISTORTION_CURVES[label] === params.curve
+        ),
+        drive: params.drive,
+        tone: params.tone,
+        oversample: params.oversample,
+      }}
+      onChange={(key: string, val: any) => {
+        const newParams =
+          key === 'curve'
+            ? { ...params, curve: DISTORTION_CURVES[val] }
+            : { ...params, [key]: val };
+        setParams(newParams);
+        onChange(newParams);
+      }}
+    />
+  );
+};
+
+export default DistortionSmallView;
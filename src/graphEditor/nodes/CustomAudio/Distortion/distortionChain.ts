@@ -0,0 +1,143 @@
+/**
+ * Waveshaping distortion shared between the distortion graph editor node, which is used as a bus
+ * effect, and the per-voice distortion effect of the synth designer.
+ */
+
+import * as R from 'ramda';
+
+export type DistortionCurveType = 'softClip' | 'hardClip' | 'foldback' | 'asymmetric';
+
+/**
+ * Maps the names of the transfer curves shown in the UI to their types
+ */
+export const DISTORTION_CURVES: { [label: string]: DistortionCurveType } = {
+  'soft clip': 'softClip',
+  'hard clip': 'hardClip',
+  foldback: 'foldback',
+  asymmetric: 'asymmetric',
+};
+
+export const ALL_DISTORTION_CURVES: DistortionCurveType[] = Object.values(DISTORTION_CURVES);
+
+export const ALL_OVERSAMPLE_TYPES: OverSampleType[] = ['none', '2x', '4x'];
+
+export interface DistortionParams {
+  curve: DistortionCurveType;
+  /**
+   * How much the input is amplified before it is shaped; higher values distort more
+   */
+  drive: number;
+  /**
+   * Cutoff frequency of the lowpass filter after the shaper that tames the harsh upper harmonics
+   */
+  tone: number;
+  /**
+   * Oversampling reduces the aliasing caused by the harmonics that shaping adds above Nyquist
+   */
+  oversample: OverSampleType;
+}
+
+export const MIN_DISTORTION_DRIVE = 1;
+export const MAX_DISTORTION_DRIVE = 50;
+export const MIN_DISTORTION_TONE = 200;
+export const MAX_DISTORTION_TONE = 20000;
+
+export const DEFAULT_DISTORTION_PARAMS: DistortionParams = {
+  curve: 'softClip',
+  drive: 4,
+  tone: 8000,
+  oversample: '2x',
+};
+
+/**
+ * The number of points in each transfer curve.  Inputs between points are linearly interpolated.
+ */
+const CURVE_POINT_COUNT = 4096;
+/**
+ * Time constant used when changing the tone so that it sweeps smoothly
+ */
+const TONE_TIME_CONSTANT = 0.01;
+
+/**
+ * Transfer functions for each curve type.  They all map 0 to 0 and inputs beyond ±1 are where they
+ * start to differ from each other.
+ */
+const TransferFunctions: { [K in DistortionCurveType]: (x: number) => number } = {
+  softClip: x => Math.tanh(x),
+  hardClip: x => R.clamp(-1, 1, x),
+  // Reflects the signal back towards 0 each time it crosses ±1 rather than clipping it
+  foldback: x => {
+    const t = (x + 1) / 4;
+    return 4 * Math.abs(t - Math.floor(t + 0.5)) - 1;
+  },
+  // Clips the negative half of the signal more softly than the positive half, which adds even
+  // harmonics
+  asymmetric: x => (x >= 0 ? Math.tanh(x) : Math.tanh(x / 2)),
+};
+
+/**
+ * Builds the curve for a `WaveShaperNode`, which maps inputs from -1 to 1 onto the curve.  Drive
+ * is applied by stretching the transfer function's input range.
+ */
+export const buildDistortionCurve = (curve: DistortionCurveType, drive: number): Float32Array => {
+  const transferFunction = TransferFunctions[curve];
+  return new Float32Array(CURVE_POINT_COUNT).map((_, i) =>
+    transferFunction(((i / (CURVE_POINT_COUNT - 1)) * 2 - 1) * drive)
+  );
+};
+
+/**
+ * A waveshaper followed by a lowpass tone filter.  Audio goes in `shaper` and comes out of
+ * `toneFilter`.
+ */
+export class DistortionChain {
+  private ctx: AudioContext;
+  public params: DistortionParams;
+  public shaper: WaveShaperNode;
+  public toneFilter: BiquadFilterNode;
+
+  constructor(ctx: AudioContext, params: DistortionParams) {
+    this.ctx = ctx;
+    this.params = params;
+    this.shaper = new WaveShaperNode(ctx, {
+      curve: buildDistortionCurve(params.curve, params.drive),
+      oversample: params.oversample,
+    });
+    this.toneFilter = new BiquadFilterNode(ctx, { type: 'lowpass', frequency: params.tone });
+    this.shaper.connect(this.toneFilter);
+  }
+
+  public setParams(newParams: Partial<DistortionParams>) {
+    const oldParams = this.params;
+    this.params = { ...oldParams, ...newParams };
+    const { curve, drive, tone, oversample } = this.params;
+
+    if (curve !== oldParams.curve || drive !== oldParams.drive) {
+      this.shaper.curve = buildDistortionCurve(curve, drive);
+    }
+    this.shaper.oversample = oversample;
+    this.toneFilter.frequency.setTargetAtTime(tone, this.ctx.currentTime, TONE_TIME_CONSTANT);
+  }
+}
+
+/**
+ * Returns the valid distortion params found in `params`
+ */
+export const deserializeDistortionParams = (params: {
+  [key: string]: any;
+}): Partial<DistortionParams> => {
+  const deserialized: Partial<DistortionParams> = {};
+  if (ALL_DISTORTION_CURVES.includes(params.curve)) {
+    deserialized.curve = params.curve;
+  }
+  if (typeof params.drive === 'number') {
+    deserialized.drive = R.clamp(MIN_DISTORTION_DRIVE, MAX_DISTORTION_DRIVE, params.drive);
+  }
+  if (typeof params.tone === 'number') {
+    deserialized.tone = R.clamp(MIN_DISTORTION_TONE, MAX_DISTORTION_TONE, params.tone);
+  }
+  if (ALL_OVERSAMPLE_TYPES.includes(params.oversample)) {
+    deserialized.oversample = params.oversample;
+  }
+  return deserialized;
+};
@@ -0,0 +1,245 @@
+import { Map } from 'immutable';
+import * as R from 'ramda';
+
+import { ForeignNode, audioNodeGetters } from 'src/graphEditor/nodes/CustomAudio/CustomAudio';
+import { AudioConnectables, ConnectableInput, ConnectableOutput } from 'src/patchNetwork';
+import { OverridableAudioParam } from 'src/graphEditor/nodes/util';
+import { mkContainerRenderHelper, mkContainerCleanupHelper } from 'src/reactUtils';
+import EffectsChainSmallView from './EffectsChainUI';
+
+/**
+ * Node types that can be inserted into an effects chain.  They must all have a `customAudio` input
+ * named `input` and a `customAudio` output named `output`.
+ */
+export const CHAIN_EFFECT_TYPES: { [nodeType: string]: string } = {
+  'customAudio/delay': 'Delay',
+  'customAudio/reverb': 'Reverb',
+  'customAudio/biquadFilter': 'Biquad Filter',
+  'customAudio/parametricEQ': 'Parametric EQ',
+  'customAudio/distortion': 'Distortion',
+  'customAudio/gain': 'Gain',
+};
+
+/**
+ * Time constant used when bypassing or un-bypassing an effect so that it fades rather than clicks
+ */
+const BYPASS_TIME_CONSTANT = 0.01;
+
+let nextSlotId = 0;
+
+export interface EffectSlot {
+  /**
+   * Unique identifier for the slot, used to key its UI
+   */
+  id: number;
+  nodeType: string;
+  effect: ForeignNode;
+  isBypassed: boolean;
+  slotInput: GainNode;
+  slotOutput: GainNode;
+  /**
+   * Gain for the output of the effect; 0 when bypassed
+   */
+  effectGain: GainNode;
+  /**
+   * Gain for the signal passing around the effect; 1 when bypassed
+   */
+  bypassGain: GainNode;
+}
+
+interface SerializedEffectSlot {
+  nodeType: string;
+  params: { [key: string]: any } | null;
+  isBypassed: boolean;
+}
+
+/**
+ * Processes its input through an ordered list of effects in series.  Effects can be inserted,
+ * removed, reordered, and bypassed while audio is running.
+ */
+export class EffectsChain implements ForeignNode {
+  private ctx: AudioContext;
+  private vcId: string;
+  private inputNode: GainNode;
+  private outputNode: GainNode;
+  public slots: EffectSlot[] = [];
+  /**
+   * Called after the chain is modified so that the UI can re-render
+   */
+  private onChange: (() => void) | null = null;
+
+  public nodeType = 'customAudio/effectsChain';
+  public name = 'Effects Chain';
+
+  /**
+   * See the docs for `enhanceAudioNode`.
+   */
+  public paramOverrides: {
+    [name: string]: { param: OverridableAudioParam; override: ConstantSourceNode };
+  } = {};
+
+  constructor(ctx: AudioContext, vcId: string, params?: { [key: string]: any } | null) {
+    this.ctx = ctx;
+    this.vcId = vcId;
+    this.inputNode = new GainNode(ctx);
+    this.outputNode = new GainNode(ctx);
+
+    if (params && Array.isArray(params.effects)) {
+      params.effects.forEach(({ nodeType, params, isBypassed }: SerializedEffectSlot) => {
+        if (!CHAIN_EFFECT_TYPES[nodeType]) {
+          console.warn(`Skipping unsupported effect type in effects chain: ${nodeType}`);
+          return;
+        }
+        const slot = this.buildSlot(nodeType, params);
+        this.slots.push(slot);
+        this.applyBypass(slot, !!isBypassed);
+      });
+    }
+    this.wire();
+
+    this.renderSmallView = mkContainerRenderHelper({
+      Comp: EffectsChainSmallView,
+      getProps: () => ({
+        chain: this,
+        registerOnChange: (onChange: (() => void) | null) => {
+          this.onChange = onChange;
+        },
+      }),
+    });
+
+    this.cleanupSmallView = mkContainerCleanupHelper();
+  }
+
+  private buildSlot(nodeType: string, params?: { [key: string]: any } | null): EffectSlot {
+    const effect = audioNodeGetters[nodeType].nodeGetter(this.vcId, params);
+    const connectables = effect.buildConnectables();
+    const effectInput = connectables.inputs.get('input')!.node as AudioNode;
+    const effectOutput = connectables.outputs.get('output')!.node as AudioNode;
+
+    const slotInput = new GainNode(this.ctx);
+    const slotOutput = new GainNode(this.ctx);
+    const effectGain = new GainNode(this.ctx, { gain: 1 });
+    const bypassGain = new GainNode(this.ctx, { gain: 0 });
+
+    slotInput.connect(effectInput);
+    effectOutput.connect(effectGain);
+    effectGain.connect(slotOutput);
+    slotInput.connect(bypassGain);
+    bypassGain.connect(slotOutput);
+
+    const id = nextSlotId++;
+    return {
+      id,
+      nodeType,
+      effect,
+      isBypassed: false,
+      slotInput,
+      slotOutput,
+      effectGain,
+      bypassGain,
+    };
+  }
+
+  /**
+   * Connects the chain's input through all of the slots in order and into the chain's output
+   */
+  private wire() {
+    this.inputNode.disconnect();
+    this.slots.forEach(slot => slot.slotOutput.disconnect());
+
+    const lastNode = this.slots.reduce((prevNode: AudioNode, slot) => {
+      prevNode.connect(slot.slotInput);
+      return slot.slotOutput;
+    }, this.inputNode);
+    lastNode.connect(this.outputNode);
+
+    if (this.onChange) {
+      this.onChange();
+    }
+  }
+
+  private applyBypass(slot: EffectSlot, isBypassed: boolean) {
+    slot.isBypassed = isBypassed;
+    const now = this.ctx.currentTime;
+    slot.effectGain.gain.setTargetAtTime(isBypassed ? 0 : 1, now, BYPASS_TIME_CONSTANT);
+    slot.bypassGain.gain.setTargetAtTime(isBypassed ? 1 : 0, now, BYPASS_TIME_CONSTANT);
+  }
+
+  /**
+   * Inserts a new effect of type `nodeType` at `index`, or at the end of the chain if no index is
+   * provided.
+   */
+  public insertEffect(nodeType: string, index: number = this.slots.length) {
+    if (!CHAIN_EFFECT_TYPES[nodeType]) {
+      console.error(`Tried to insert unsupported effect type into effects chain: ${nodeType}`);
+      return;
+    }
+
+    const slot = this.buildSlot(nodeType);
+    this.slots = R.insert(R.clamp(0, this.slots.length, index), slot, this.slots);
+    this.wire();
+  }
+
+  public removeEffect(index: number) {
+    const slot = this.slots[index];
+    if (!slot) {
+      console.error(`Tried to remove effect at index ${index} but only ${this.slots.length} exist`);
+      return;
+    }
+
+    this.slots = R.remove(index, 1, this.slots);
+    this.wire();
+    slot.slotInput.disconnect();
+  }
+
+  public moveEffect(fromIndex: number, toIndex: number) {
+    if (!this.slots[fromIndex] || !this.slots[toIndex]) {
+      console.error(`Tried to move effect from ${fromIndex} to ${toIndex} but one doesn't exist`);
+      return;
+    }
+
+    this.slots = R.move(fromIndex, toIndex, this.slots);
+    this.wire();
+  }
+
+  public setEffectBypassed(index: number, isBypassed: boolean) {
+    const slot = this.slots[index];
+    if (!slot) {
+      console.error(`Tried to bypass effect at index ${index} but only ${this.slots.length} exist`);
+      return;
+    }
+
+    this.applyBypass(slot, isBypassed);
+    if (this.onChange) {
+      this.onChange();
+    }
+  }
+
+  public serialize(): { [key: string]: any } {
+    return {
+      effects: this.slots.map(({ nodeType, effect, isBypassed }): SerializedEffectSlot => ({
+        nodeType,
+        params: effect.serialize(),
+        isBypassed,
+      })),
+    };
+  }
+
+  public buildConnectables(): AudioConnectables & { node: ForeignNode } {
+    return {
+      vcId: this.vcId,
+      inputs: Map<string, ConnectableInput>().set('input', {
+        node: this.inputNode,
+        type: 'customAudio',
+      }),
+      outputs: Map<string, ConnectableOutput>().set('output', {
+        node: this.outputNode,
+        type: 'customAudio',
+      }),
+      node: this,
+    };
+  }
+
+  public renderSmallView: ForeignNode['renderSmallView'];
+  public cleanupSmallView: ForeignNode['cleanupSmallView'];
+}
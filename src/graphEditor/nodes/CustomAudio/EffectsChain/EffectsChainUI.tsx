@@ -0,0 +1,86 @@
+import React, { useEffect, useReducer, useState } from 'react';
+
+import {
+  CHAIN_EFFECT_TYPES,
+  EffectsChain,
+  EffectSlot,
+} from 'src/graphEditor/nodes/CustomAudio/EffectsChain/EffectsChain';
+
+/**
+ * Renders the small view of the effect in a slot into a container of its own
+ */
+const EffectSlotSmallView: React.FC<{ slot: EffectSlot }> = ({ slot }) => {
+  const domId = `effects-chain-slot-${slot.id}`;
+
+  useEffect(() => {
+    const { effect } = slot;
+    if (effect.renderSmallView) {
+      effect.renderSmallView(domId);
+    }
+
+    return () => {
+      if (effect.renderSmallView && effect.cleanupSmallView) {
+        effect.cleanupSmallView(domId);
+      }
+    };
+  }, [slot, domId]);
+
+  return <div id={domId} />;
+};
+
+const EffectsChainSmallView: React.FC<{
+  chain: EffectsChain;
+  registerOnChange: (onChange: (() => void) | null) => void;
+}> = ({ chain, registerOnChange }) => {
+  const [, forceUpdate] = useReducer((x: number) => x + 1, 0);
+  const [selectedNodeType, setSelectedNodeType] = useState(Object.keys(CHAIN_EFFECT_TYPES)[0]);
+
+  useEffect(() => {
+    registerOnChange(forceUpdate);
+    return () => registerOnChange(null);
+  }, [registerOnChange]);
+
+  return (
+    <div className='effects-chain'>
+      <div>
+        <select value={selectedNodeType} onChange={evt => setSelectedNodeType(evt.target.value)}>
+          {Object.entries(CHAIN_EFFECT_TYPES).map(([nodeType, name]) => (
+            <option key={nodeType} value={nodeType}>
+              {name}
+            </option>
+          ))}
+        </select>
+        <button onClick={() => chain.insertEffect(selectedNodeType)}>add effect</button>
+      </div>
+
+      {chain.slots.map((slot, i) => (
+        <div key={slot.id} className='effects-chain-slot'>
+          <div>
+            <b>{CHAIN_EFFECT_TYPES[slot.nodeType]}</b>
+            <label>
+              <input
+                type='checkbox'
+                checked={slot.isBypassed}
+                onChange={evt => chain.setEffectBypassed(i, evt.target.checked)}
+              />
+              bypass
+            </label>
+            <button disabled={i === 0} onClick={() => chain.moveEffect(i, i - 1)}>
+              ↑
+            </button>
+            <button
+              disabled={i === chain.slots.length - 1}
+              onClick={() => chain.moveEffect(i, i + 1)}
+            >
+              ↓
+            </button>
+            <button onClick={() => chain.removeEffect(i)}>remove</button>
+          </div>
+          <EffectSlotSmallView slot={slot} />
+        </div>
+      ))}
+    </div>
+  );
+};
+
+export default EffectsChainSmallView;
@@ -0,0 +1,274 @@
+import { Map } from 'immutable';
+import * as R from 'ramda';
+
+import { ForeignNode } from 'src/graphEditor/nodes/CustomAudio';
+import { AudioConnectables, ConnectableInput, ConnectableOutput } from 'src/patchNetwork';
+import { MIDINode, buildMIDINode, MIDIInputCbs } from 'src/patchNetwork/midiNode';
+import { OverridableAudioParam } from 'src/graphEditor/nodes/util';
+import { ADSRModule } from 'src/synthDesigner/ADSRModule';
+import { mkContainerRenderHelper, mkContainerCleanupHelper } from 'src/reactUtils';
+import { midiToFrequency, velocityToGainLevel } from 'src/util';
+import FMSynthSmallView from './FMSynthUI';
+import {
+  ALGORITHMS,
+  DEFAULT_FM_SYNTH_PARAMS,
+  FMAlgorithm,
+  FMSynthParams,
+  OPERATOR_COUNT,
+} from './params';
+
+const VOICE_COUNT = 16;
+/**
+ * The level of a modulating operator is multiplied by this and by the operator's frequency to get
+ * the peak frequency deviation that it applies to the operators it modulates.
+ */
+const MAX_MODULATION_INDEX = 8;
+/**
+ * How far channel-wide pitch bends can bend the pitch in either direction
+ */
+const PITCH_BEND_RANGE_SEMITONES = 2;
+/**
+ * The value of the most significant byte of a MIDI pitch bend message that means no bend
+ */
+const PITCH_BEND_CENTER = 64;
+
+interface Operator {
+  oscillator: OscillatorNode;
+  gain: GainNode;
+  envelope: ADSRModule;
+}
+
+class FMVoice {
+  private operators: Operator[];
+  /**
+   * Applies the amplitude envelope sent by the voice manager playing the voice, if any
+   */
+  private amp: GainNode;
+  /**
+   * The bend applied to only this voice, which is reset when the voice is gated
+   */
+  private voiceBendSemitones = 0;
+  /**
+   * The bend applied to all voices of the synth, which is kept across notes
+   */
+  private channelBendSemitones = 0;
+
+  constructor(ctx: AudioContext, output: AudioNode) {
+    this.amp = new GainNode(ctx);
+    this.amp.connect(output);
+    this.operators = R.range(0, OPERATOR_COUNT).map(() => {
+      const oscillator = new OscillatorNode(ctx);
+      const gain = new GainNode(ctx);
+      gain.gain.value = 0;
+      const envelope = new ADSRModule(ctx, {});
+      envelope.start();
+      envelope.connect(gain.gain);
+
+      oscillator.connect(gain);
+      oscillator.start();
+      return { oscillator, gain, envelope };
+    });
+  }
+
+  public route(algorithm: FMAlgorithm) {
+    this.operators.forEach(({ gain }) => gain.disconnect());
+    algorithm.modulations.forEach(([src, dst]) =>
+      this.operators[src].gain.connect(this.operators[dst].oscillator.frequency)
+    );
+    algorithm.carriers.forEach(ix => this.operators[ix].gain.connect(this.amp));
+  }
+
+  public gate(
+    frequency: number,
+    { algorithmIx, operators }: FMSynthParams,
+    ctx: AudioContext,
+    velocity?: number,
+    offset?: number
+  ) {
+    const { carriers } = ALGORITHMS[algorithmIx];
+    const time = ctx.currentTime + (offset || 0);
+    this.voiceBendSemitones = 0;
+
+    this.operators.forEach(({ oscillator, envelope }, i) => {
+      const { ratio, level, envelope: envelopeValues, envelopeLengthMs } = operators[i];
+      const operatorFrequency = frequency * ratio;
+      oscillator.frequency.setValueAtTime(operatorFrequency, time);
+      // Clear any per-voice pitch bend left over from the last note that the voice played
+      oscillator.detune.setValueAtTime(this.channelBendSemitones * 100, time);
+
+      const isCarrier = carriers.includes(i);
+      // Carriers are scaled so that they don't clip when summed; modulators are scaled by their
+      // frequency so that the timbre stays consistent across the keyboard.
+      const maxValue = isCarrier
+        ? level / carriers.length
+        : level * MAX_MODULATION_INDEX * operatorFrequency;
+      envelope.setMaxValue(maxValue);
+      envelope.setEnvelope(envelopeValues);
+      envelope.setLengthMs(envelopeLengthMs);
+      envelope.gate(offset, isCarrier ? velocityToGainLevel(velocity) : 1);
+    });
+  }
+
+  /**
+   * Ramps the detune of all operators to the sum of the voice and channel bends, keeping the ratios
+   * between them the same.
+   */
+  private applyBend(ctx: AudioContext, offset?: number) {
+    const time = ctx.currentTime + Math.max(offset || 0, 0);
+    const cents = (this.voiceBendSemitones + this.channelBendSemitones) * 100;
+    this.operators.forEach(({ oscillator }) =>
+      oscillator.detune.linearRampToValueAtTime(cents, time)
+    );
+  }
+
+  /**
+   * Bends the pitch of this voice to `semitones` away from the gated frequency on top of the
+   * channel bend
+   */
+  public bend(semitones: number, ctx: AudioContext, offset?: number) {
+    this.voiceBendSemitones = semitones;
+    this.applyBend(ctx, offset);
+  }
+
+  /**
+   * Sets the bend shared by all voices, which applies on top of the voice's own bend
+   */
+  public bendChannel(semitones: number, ctx: AudioContext, offset?: number) {
+    this.channelBendSemitones = semitones;
+    this.applyBend(ctx, offset);
+  }
+
+  public ungate(offset?: number) {
+    this.operators.forEach(({ envelope }) => envelope.ungate(offset));
+  }
+
+  /**
+   * Ramps the gain of this voice through `envelope` over `duration` seconds, starting from wherever
+   * the previous envelope was interrupted
+   */
+  public applyEnvelope(
+    envelope: Float32Array,
+    duration: number,
+    ctx: AudioContext,
+    offset?: number
+  ) {
+    const time = ctx.currentTime + Math.max(offset || 0, 0);
+    const { gain } = this.amp;
+    if (gain.cancelAndHoldAtTime) {
+      gain.cancelAndHoldAtTime(time);
+    } else {
+      // Value curves can't overlap, so the one that's playing has to be cleared as well
+      gain.cancelScheduledValues(0);
+    }
+    gain.setValueCurveAtTime(envelope, time, duration);
+  }
+}
+
+/**
+ * A polyphonic 4-operator FM synthesizer driven by MIDI input.  Voices are selected by the voice
+ * index of the MIDI events it receives, so it plugs into the same voice management as the synth
+ * designer.
+ */
+export class FMSynth implements ForeignNode {
+  private ctx: AudioContext;
+  private vcId: string;
+  private midiNode: MIDINode;
+  private voices: FMVoice[];
+  private outputNode: GainNode;
+  private params: FMSynthParams = DEFAULT_FM_SYNTH_PARAMS;
+
+  public nodeType = 'customAudio/fmSynth';
+  public name = 'FM Synthesizer';
+
+  /**
+   * See the docs for `enhanceAudioNode`.
+   */
+  public paramOverrides: {
+    [name: string]: { param: OverridableAudioParam; override: ConstantSourceNode };
+  } = {};
+
+  private getMIDIInputCbs = (): MIDIInputCbs => ({
+    onAttack: (note, voiceIx, velocity, offset) =>
+      this.voices[voiceIx % VOICE_COUNT].gate(
+        midiToFrequency(note),
+        this.params,
+        this.ctx,
+        velocity,
+        offset
+      ),
+    onRelease: (_note, voiceIx, _velocity, offset) =>
+      this.voices[voiceIx % VOICE_COUNT].ungate(offset),
+    onPitchBend: (bendAmount, offset) => {
+      const semitones =
+        ((bendAmount - PITCH_BEND_CENTER) / PITCH_BEND_CENTER) * PITCH_BEND_RANGE_SEMITONES;
+      this.voices.forEach(voice => voice.bendChannel(semitones, this.ctx, offset));
+    },
+    onVoicePitchBend: (_note, voiceIx, semitones, offset) =>
+      this.voices[voiceIx % VOICE_COUNT].bend(semitones, this.ctx, offset),
+    onVoiceEnvelope: (voiceIx, envelope, duration, offset) =>
+      this.voices[voiceIx % VOICE_COUNT].applyEnvelope(envelope, duration, this.ctx, offset),
+    onClearAll: () => this.voices.forEach(voice => voice.ungate()),
+  });
+
+  constructor(ctx: AudioContext, vcId: string, params?: { [key: string]: any } | null) {
+    this.ctx = ctx;
+    this.vcId = vcId;
+    this.outputNode = new GainNode(ctx);
+    this.voices = R.range(0, VOICE_COUNT).map(() => new FMVoice(ctx, this.outputNode));
+    this.midiNode = buildMIDINode(this.getMIDIInputCbs);
+
+    if (params) {
+      this.deserialize(params);
+    }
+    this.voices.forEach(voice => voice.route(ALGORITHMS[this.params.algorithmIx]));
+
+    this.renderSmallView = mkContainerRenderHelper({
+      Comp: FMSynthSmallView,
+      getProps: () => ({
+        initialParams: this.params,
+        onChange: (params: FMSynthParams) => this.setParams(params),
+      }),
+    });
+
+    this.cleanupSmallView = mkContainerCleanupHelper();
+  }
+
+  public setParams(params: FMSynthParams) {
+    if (params.algorithmIx !== this.params.algorithmIx) {
+      this.voices.forEach(voice => voice.route(ALGORITHMS[params.algorithmIx]));
+    }
+    this.params = params;
+  }
+
+  private deserialize(params: { [key: string]: any }) {
+    if (
+      R.isNil(ALGORITHMS[params.algorithmIx]) ||
+      !Array.isArray(params.operators) ||
+      params.operators.length !== OPERATOR_COUNT
+    ) {
+      console.error('Invalid params provided to FM synth; using defaults: ', params);
+      return;
+    }
+
+    this.params = params as FMSynthParams;
+  }
+
+  public serialize(): { [key: string]: any } {
+    return this.params;
+  }
+
+  public buildConnectables(): AudioConnectables & { node: ForeignNode } {
+    return {
+      vcId: this.vcId,
+      inputs: Map<string, ConnectableInput>().set('midi', { node: this.midiNode, type: 'midi' }),
+      outputs: Map<string, ConnectableOutput>().set('output', {
+        node: this.outputNode,
+        type: 'customAudio',
+      }),
+      node: this,
+    };
+  }
+
+  public renderSmallView: ForeignNode['renderSmallView'];
+  public cleanupSmallView: ForeignNode['cleanupSmallView'];
+}
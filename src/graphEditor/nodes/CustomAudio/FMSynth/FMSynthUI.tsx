@@ -0,0 +1,94 @@
+import React, { useState, useMemo } from 'react';
+import ControlPanel from 'react-control-panel';
+import * as R from 'ramda';
+
+import { ControlPanelADSR } from 'src/controls/adsr';
+import {
+  ALGORITHMS,
+  FMSynthParams,
+  OPERATOR_COUNT,
+  OperatorParams,
+  PRESETS,
+} from 'src/graphEditor/nodes/CustomAudio/FMSynth/params';
+
+const ALGORITHM_NAMES = ALGORITHMS.map(R.prop('name'));
+
+const operatorSettings = R.range(0, OPERATOR_COUNT).flatMap(i => [
+  { type: 'range', label: `op ${i + 1} ratio`, min: 0.125, max: 16, scale: 'log', steps: 200 },
+  { type: 'range', label: `op ${i + 1} level`, min: 0, max: 1 },
+  { type: 'range', label: `op ${i + 1} length ms`, min: 50, max: 8000, scale: 'log', steps: 200 },
+  { type: 'custom', label: `op ${i + 1} envelope`, Comp: ControlPanelADSR },
+]);
+
+const SETTINGS = [
+  { type: 'select', label: 'preset', options: ['', ...Object.keys(PRESETS)] },
+  { type: 'select', label: 'algorithm', options: ALGORITHM_NAMES },
+  ...operatorSettings,
+];
+
+const OPERATOR_KEYS: { [key: string]: keyof OperatorParams } = {
+  ratio: 'ratio',
+  level: 'level',
+  'length ms': 'envelopeLengthMs',
+  envelope: 'envelope',
+};
+
+const FMSynthSmallView: React.FC<{
+  initialParams: FMSynthParams;
+  onChange: (params: FMSynthParams) => void;
+}> = ({ initialParams, onChange }) => {
+  const [params, setParams] = useState(initialParams);
+  const updateParams = (newParams: FMSynthParams) => {
+    setParams(newParams);
+    onChange(newParams);
+  };
+
+  const state = useMemo(
+    () =>
+      params.operators.reduce(
+        (acc, op, i) => ({
+          ...acc,
+          [`op ${i + 1} ratio`]: op.ratio,
+          [`op ${i + 1} level`]: op.level,
+          [`op ${i + 1} length ms`]: op.envelopeLengthMs,
+          [`op ${i + 1} envelope`]: op.envelope,
+        }),
+        { preset: '', algorithm: ALGORITHM_NAMES[params.algorithmIx] } as { [key: string]: any }
+      ),
+    [params]
+  );
+
+  return (
+    <ControlPanel
+      style={{ width: 500 }}
+      settings={SETTINGS}
+      state={state}
+      onChange={(key: string, val: any) => {
+        if (key === 'preset') {
+          if (PRESETS[val]) {
+            updateParams(PRESETS[val]);
+          }
+          return;
+        } else if (key === 'algorithm') {
+          updateParams({ ...params, algorithmIx: ALGORITHM_NAMES.indexOf(val) });
+          return;
+        }
+
+        const match = key.match(/^op (\d) (.+)$/);
+        if (!match || !OPERATOR_KEYS[match[2]]) {
+          console.warn('Unhandled key in FM synth control panel: ', key);
+          return;
+        }
+        const operatorIx = +match[1] - 1;
+        const operators = R.adjust(
+          operatorIx,
+          op => ({ ...op, [OPERATOR_KEYS[match[2]]]: val }),
+          params.operators
+        );
+        updateParams({ ...params, operators });
+      }}
+    />
+  );
+};
+
+export default FMSynthSmallView;
@@ -0,0 +1,2 @@
+export * from './FMSynth';
+export * from './params';
@@ -0,0 +1,96 @@
+import { ADSRValues, defaultAdsrEnvelope } from 'src/controls/adsr';
+
+export const OPERATOR_COUNT = 4;
+
+export interface OperatorParams {
+  /**
+   * Multiplier applied to the frequency of the played note to get this operator's frequency
+   */
+  ratio: number;
+  /**
+   * Output level in the range [0, 1].  For carriers this is volume; for modulators this is the
+   * modulation depth.
+   */
+  level: number;
+  envelope: ADSRValues;
+  envelopeLengthMs: number;
+}
+
+export interface FMSynthParams {
+  algorithmIx: number;
+  operators: OperatorParams[];
+}
+
+/**
+ * Describes how the operators of the synth are routed.  Each entry of `modulations` is a pair of
+ * operator indices `[src, dst]` where the output of `src` modulates the frequency of `dst`.  The
+ * outputs of the operators in `carriers` are summed to produce the output of the synth.
+ */
+export interface FMAlgorithm {
+  name: string;
+  modulations: [number, number][];
+  carriers: number[];
+}
+
+export const ALGORITHMS: FMAlgorithm[] = [
+  { name: '4 → 3 → 2 → 1', modulations: [[3, 2], [2, 1], [1, 0]], carriers: [0] },
+  { name: '(3 + 4) → 2 → 1', modulations: [[3, 1], [2, 1], [1, 0]], carriers: [0] },
+  { name: '(2 + 3 + 4) → 1', modulations: [[1, 0], [2, 0], [3, 0]], carriers: [0] },
+  { name: '2 → 1, 4 → 3', modulations: [[1, 0], [3, 2]], carriers: [0, 2] },
+  { name: '4 → (1 + 2 + 3)', modulations: [[3, 0], [3, 1], [3, 2]], carriers: [0, 1, 2] },
+  { name: '1 + 2 + 3 + 4', modulations: [], carriers: [0, 1, 2, 3] },
+];
+
+const mkOperator = (
+  ratio: number,
+  level: number,
+  envelope: ADSRValues = defaultAdsrEnvelope,
+  envelopeLengthMs = 1000
+): OperatorParams => ({ ratio, level, envelope, envelopeLengthMs });
+
+const percussiveEnvelope: ADSRValues = {
+  attack: { pos: 0.01, magnitude: 1 },
+  decay: { pos: 0.3, magnitude: 0.2 },
+  release: { pos: 0.8, magnitude: 0.2 },
+};
+
+export const PRESETS: { [name: string]: FMSynthParams } = {
+  'electric piano': {
+    algorithmIx: 3,
+    operators: [
+      mkOperator(1, 0.8, percussiveEnvelope, 2000),
+      mkOperator(1, 0.25, percussiveEnvelope, 1200),
+      mkOperator(1, 0.5, percussiveEnvelope, 2000),
+      mkOperator(14, 0.08, percussiveEnvelope, 400),
+    ],
+  },
+  bell: {
+    algorithmIx: 3,
+    operators: [
+      mkOperator(1, 0.7, percussiveEnvelope, 4000),
+      mkOperator(3.5, 0.4, percussiveEnvelope, 3000),
+      mkOperator(2, 0.4, percussiveEnvelope, 4000),
+      mkOperator(7.1, 0.3, percussiveEnvelope, 2000),
+    ],
+  },
+  bass: {
+    algorithmIx: 0,
+    operators: [
+      mkOperator(0.5, 0.9, percussiveEnvelope, 800),
+      mkOperator(0.5, 0.35, percussiveEnvelope, 500),
+      mkOperator(1, 0.1),
+      mkOperator(1, 0),
+    ],
+  },
+  brass: {
+    algorithmIx: 1,
+    operators: [
+      mkOperator(1, 0.8),
+      mkOperator(1, 0.3),
+      mkOperator(1, 0.1),
+      mkOperator(2, 0.05),
+    ],
+  },
+};
+
+export const DEFAULT_FM_SYNTH_PARAMS: FMSynthParams = PRESETS['electric piano'];
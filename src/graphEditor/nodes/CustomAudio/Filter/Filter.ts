@@ -0,0 +1,164 @@
+import { Map } from 'immutable';
+
+import { ForeignNode } from 'src/graphEditor/nodes/CustomAudio';
+import {
+  AudioConnectables,
+  ConnectableInput,
+  ConnectableOutput,
+  updateConnectables,
+} from 'src/patchNetwork';
+import { OverridableAudioParam } from 'src/graphEditor/nodes/util';
+import DummyNode from 'src/graphEditor/nodes/DummyNode';
+import { mkContainerRenderHelper, mkContainerCleanupHelper } from 'src/reactUtils';
+import FilterSmallView from './FilterUI';
+
+/**
+ * Mirrors `FilterMode::from_u8` in the filter crate
+ */
+export const FILTER_MODES = ['lowpass', 'highpass', 'bandpass', 'notch'] as const;
+
+export type FilterMode = typeof FILTER_MODES[number];
+
+export interface FilterParams {
+  mode: FilterMode;
+  frequency: number;
+  Q: number;
+}
+
+const DEFAULT_FILTER_PARAMS: FilterParams = {
+  mode: 'lowpass',
+  frequency: 1000,
+  Q: 0.707,
+};
+
+const OVERRIDABLE_PARAM_NAMES: ('frequency' | 'Q')[] = ['frequency', 'Q'];
+
+/**
+ * A stereo lowpass, highpass, bandpass, or notch biquad filter.  The DSP is implemented in Wasm
+ * and run inside of an `AudioWorkletProcessor`, and the filter's coefficients are only recomputed
+ * when its params change.
+ */
+export class Filter implements ForeignNode {
+  private ctx: AudioContext;
+  private vcId: string;
+  private params: FilterParams;
+  private workletHandle: AudioWorkletNode | undefined;
+  /**
+   * The input and output are created immediately so that connections can be made to them before
+   * the worklet finishes loading.
+   */
+  private inputNode: GainNode;
+  private outputNode: GainNode;
+
+  public nodeType = 'customAudio/filter';
+  public name = 'Filter';
+
+  /**
+   * See the docs for `enhanceAudioNode`.
+   */
+  public paramOverrides: {
+    [name: string]: { param: OverridableAudioParam; override: ConstantSourceNode };
+  } = {};
+
+  constructor(ctx: AudioContext, vcId: string, params?: { [key: string]: any } | null) {
+    this.ctx = ctx;
+    this.vcId = vcId;
+    this.params = { ...DEFAULT_FILTER_PARAMS, ...this.deserialize(params || {}) };
+    this.inputNode = new GainNode(ctx);
+    this.outputNode = new GainNode(ctx);
+
+    this.initWorklet().then(workletHandle => {
+      this.paramOverrides = this.buildParamOverrides(workletHandle);
+      this.setParams(this.params);
+
+      this.inputNode.connect(workletHandle);
+      workletHandle.connect(this.outputNode);
+
+      updateConnectables(this.vcId, this.buildConnectables());
+    });
+
+    this.renderSmallView = mkContainerRenderHelper({
+      Comp: FilterSmallView,
+      getProps: () => ({
+        initialParams: this.params,
+        onChange: (params: FilterParams) => this.setParams(params),
+      }),
+    });
+
+    this.cleanupSmallView = mkContainerCleanupHelper();
+  }
+
+  private async initWorklet() {
+    await this.ctx.audioWorklet.addModule('/FilterNodeProcessor.js');
+    this.workletHandle = new AudioWorkletNode(this.ctx, 'filter-node-processor', {
+      outputChannelCount: [2],
+    });
+
+    const moduleBytes = await fetch('./filter.wasm').then(res => res.arrayBuffer());
+    this.workletHandle.port.postMessage({ arrayBuffer: moduleBytes });
+
+    return this.workletHandle;
+  }
+
+  private buildParamOverrides(workletHandle: AudioWorkletNode): ForeignNode['paramOverrides'] {
+    return OVERRIDABLE_PARAM_NAMES.reduce((acc, name) => {
+      // Work around incomplete TypeScript typings
+      const param = (workletHandle.parameters as Map<string, AudioParam>).get(name)!;
+      const override = new OverridableAudioParam(this.ctx, param);
+      return { ...acc, [name]: { param: override, override: override.manualControl } };
+    }, {} as ForeignNode['paramOverrides']);
+  }
+
+  public setParams(params: FilterParams) {
+    this.params = params;
+    if (!this.workletHandle) {
+      return;
+    }
+
+    OVERRIDABLE_PARAM_NAMES.forEach(name => {
+      this.paramOverrides[name].override.offset.value = params[name];
+    });
+    // Work around incomplete TypeScript typings
+    const modeParam = (this.workletHandle.parameters as Map<string, AudioParam>).get('mode')!;
+    modeParam.value = FILTER_MODES.indexOf(params.mode);
+  }
+
+  private deserialize(params: { [key: string]: any }): Partial<FilterParams> {
+    const deserialized: Partial<FilterParams> = {};
+    OVERRIDABLE_PARAM_NAMES.forEach(name => {
+      if (typeof params[name] === 'number') {
+        deserialized[name] = params[name];
+      }
+    });
+    if (FILTER_MODES.includes(params.mode)) {
+      deserialized.mode = params.mode;
+    }
+    return deserialized;
+  }
+
+  public serialize(): { [key: string]: any } {
+    return this.params;
+  }
+
+  public buildConnectables(): AudioConnectables & { node: ForeignNode } {
+    return {
+      vcId: this.vcId,
+      inputs: OVERRIDABLE_PARAM_NAMES.reduce(
+        (acc, name) =>
+          acc.set(name, {
+            node: this.paramOverrides[name] ? this.paramOverrides[name].param : new DummyNode(),
+            type: 'number',
+          }),
+        Map<string, ConnectableInput>().set('input', { node: this.inputNode, type: 'customAudio' })
+      ),
+      outputs: Map<string, ConnectableOutput>().set('output', {
+        node: this.outputNode,
+        type: 'customAudio',
+      }),
+      node: this,
+    };
+  }
+
+  public renderSmallView: ForeignNode['renderSmallView'];
+  public cleanupSmallView: ForeignNode['cleanupSmallView'];
+}
@@ -0,0 +1,32 @@
+import React, { useState } from 'react';
+import ControlPanel from 'react-control-panel';
+
+import { FilterParams, FILTER_MODES } from 'src/graphEditor/nodes/CustomAudio/Filter/Filter';
+
+const SETTINGS = [
+  { type: 'select', label: 'mode', options: FILTER_MODES },
+  { type: 'range', label: 'frequency', min: 10, max: 20000, scale: 'log' },
+  { type: 'range', label: 'Q', min: 0.01, max: 40, scale: 'log' },
+];
+
+const FilterSmallView: React.FC<{
+  initialParams: FilterParams;
+  onChange: (params: FilterParams) => void;
+}> = ({ initialParams, onChange }) => {
+  const [params, setParams] = useState(initialParams);
+
+  return (
+    <ControlPanel
+      style={{ width: 500 }}
+      settings={SETTINGS}
+      state={params}
+      onChange={(key: keyof FilterParams, val: any) => {
+        const newParams = { ...params, [key]: val };
+        setParams(newParams);
+        onChange(newParams);
+      }}
+    />
+  );
+};
+
+export default FilterSmallView;
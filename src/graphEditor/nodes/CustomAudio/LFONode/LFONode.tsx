@@ -4,21 +4,47 @@ import * as R from 'ramda';
 import { ForeignNode } from 'src/graphEditor/nodes/CustomAudio';
 import { AudioConnectables, ConnectableInput, ConnectableOutput } from 'src/patchNetwork';
 import { OverridableAudioParam } from 'src/graphEditor/nodes/util';
-import LFOSmallView, { ALL_WAVEFORMS } from './LFONodeUI';
+import LFOSmallView, { ALL_WAVEFORMS, SYNC_BEATS_PER_CYCLE } from './LFONodeUI';
 import { mkContainerRenderHelper, mkContainerCleanupHelper } from 'src/reactUtils';
+import { getTransportState, subscribeToTransport, TransportState } from 'src/transport';
+import {
+  getRandomStepBuffer,
+  RANDOM_STEP_COUNT,
+} from 'src/graphEditor/nodes/CustomAudio/Noise/noiseBuffers';
+
+/**
+ * `random` is a sample & hold waveform that jumps to a new random value once per cycle.
+ */
+export type LFOWaveform = OscillatorType | 'random';
 
 export interface LFOParams {
   frequency: number;
   gain: number;
   offset: number;
-  waveform: OscillatorType;
+  waveform: LFOWaveform;
+  /**
+   * One of the keys of `SYNC_BEATS_PER_CYCLE`
+   */
+  sync: string;
 }
 
 export class LFONode implements ForeignNode {
+  private ctx: AudioContext;
   private vcId: string;
   public gainNode: GainNode;
   private offsetNode: ConstantSourceNode;
-  public oscillatorNode: OscillatorNode;
+  /**
+   * Outputs the current frequency of the LFO.  It is connected to the frequency param of whatever
+   * source is currently active so that the source can be swapped out without re-wiring the inputs.
+   */
+  private frequencyNode: ConstantSourceNode;
+  private source: OscillatorNode | AudioBufferSourceNode;
+  private waveform: LFOWaveform = 'sine';
+  private sync = 'free';
+  /**
+   * The frequency used when the LFO isn't synced to the transport
+   */
+  private freeFrequency = 1;
   public nodeType = 'customAudio/LFO';
   public name = 'LFO';
 
@@ -34,19 +60,19 @@ export class LFONode implements ForeignNode {
   };
 
   constructor(ctx: AudioContext, vcId: string, params?: { [key: string]: any } | null) {
+    this.ctx = ctx;
     this.vcId = vcId;
     this.gainNode = new GainNode(ctx);
     this.gainNode.gain.value = 0;
     this.offsetNode = new ConstantSourceNode(ctx);
     this.offsetNode.offset.value = 0;
     this.offsetNode.start();
-    this.oscillatorNode = new OscillatorNode(ctx);
+    this.frequencyNode = new ConstantSourceNode(ctx);
+    this.frequencyNode.offset.value = 0;
+    this.frequencyNode.start();
 
-    // Oscillator -> Gain -> Offset -> Output
-    this.oscillatorNode.connect(this.gainNode);
+    // Source -> Gain -> Offset -> Output
     this.gainNode.connect(this.offsetNode.offset);
-    this.oscillatorNode.start();
-    this.oscillatorNode.frequency.value = 0;
 
     this.frequencyOverrideCSN = new ConstantSourceNode(ctx);
     this.frequencyOverrideCSN.start();
@@ -58,15 +84,17 @@ export class LFONode implements ForeignNode {
     if (params) {
       this.deserialize(params);
     } else {
-      this.frequencyOverrideCSN.offset.value = 1;
       this.offsetOverrideCSN.offset.value = 0;
     }
+    this.source = this.buildSource(ctx.currentTime);
+    this.applyRate(getTransportState());
+    subscribeToTransport(transportState => this.applyRate(transportState));
 
     this.paramOverrides = {
       frequency: {
         param: new OverridableAudioParam(
           ctx,
-          this.oscillatorNode.frequency,
+          this.frequencyNode.offset,
           this.frequencyOverrideCSN
         ),
         override: this.frequencyOverrideCSN,
@@ -84,17 +112,26 @@ export class LFONode implements ForeignNode {
     this.renderSmallView = mkContainerRenderHelper({
       Comp: LFOSmallView,
       getProps: () => ({
-        onChange: ({ frequency, gain, offset, waveform }: LFOParams) => {
-          this.frequencyOverrideCSN.offset.value = frequency;
+        onChange: ({ frequency, gain, offset, waveform, sync }: LFOParams) => {
+          this.freeFrequency = frequency;
           this.amplitudeOverrideCSN.offset.value = gain;
           this.offsetOverrideCSN.offset.value = offset;
-          this.oscillatorNode.type = waveform;
+          const rateChanged = sync !== this.sync;
+          this.sync = sync;
+          if (waveform !== this.waveform) {
+            this.setWaveform(waveform);
+          } else if (rateChanged) {
+            this.applyRate(getTransportState());
+          } else if (R.isNil(SYNC_BEATS_PER_CYCLE[this.sync])) {
+            this.frequencyOverrideCSN.offset.value = frequency;
+          }
         },
         initialState: {
-          frequency: this.frequencyOverrideCSN.offset.value,
+          frequency: this.freeFrequency,
           gain: this.amplitudeOverrideCSN.offset.value,
           offset: this.offsetOverrideCSN.offset.value,
-          waveform: this.oscillatorNode.type,
+          waveform: this.waveform,
+          sync: this.sync,
         },
       }),
     });
@@ -102,27 +139,98 @@ export class LFONode implements ForeignNode {
     this.cleanupSmallView = mkContainerCleanupHelper();
   }
 
+  /**
+   * Creates a new source for the current waveform, wires it up, and starts it at `startTime` so
+   * that its phase is zero at that time.
+   */
+  private buildSource(startTime: number): OscillatorNode | AudioBufferSourceNode {
+    let source: OscillatorNode | AudioBufferSourceNode;
+    if (this.waveform === 'random') {
+      source = new AudioBufferSourceNode(this.ctx, {
+        buffer: getRandomStepBuffer(this.ctx),
+        loop: true,
+      });
+      source.playbackRate.value = 0;
+      const rateScaler = new GainNode(this.ctx, { gain: 1 / RANDOM_STEP_COUNT });
+      this.frequencyNode.connect(rateScaler);
+      rateScaler.connect(source.playbackRate);
+      source.addEventListener('ended', () => rateScaler.disconnect());
+    } else {
+      source = new OscillatorNode(this.ctx, { type: this.waveform });
+      source.frequency.value = 0;
+      const frequencyParam = source.frequency;
+      this.frequencyNode.connect(frequencyParam);
+      source.addEventListener('ended', () => this.frequencyNode.disconnect(frequencyParam));
+    }
+
+    source.connect(this.gainNode);
+    source.start(startTime);
+    return source;
+  }
+
+  /**
+   * Replaces the current source with a new one that starts at `startTime`
+   */
+  private restartSource(startTime: number) {
+    const oldSource = this.source;
+    oldSource.addEventListener('ended', () => oldSource.disconnect());
+    oldSource.stop(startTime);
+    this.source = this.buildSource(startTime);
+  }
+
+  private setWaveform(waveform: LFOWaveform) {
+    this.waveform = waveform;
+    this.restartSource(this.ctx.currentTime);
+    this.applyRate(getTransportState());
+  }
+
+  /**
+   * Sets the frequency of the LFO based on the sync setting.  If synced and the transport is
+   * playing, the source is restarted at the start of the next cycle as measured from beat 0 so that
+   * its phase lines up with the beat.
+   */
+  private applyRate({ isPlaying, bpm, beatZeroTime }: TransportState) {
+    const beatsPerCycle = SYNC_BEATS_PER_CYCLE[this.sync];
+    if (R.isNil(beatsPerCycle)) {
+      this.frequencyOverrideCSN.offset.value = this.freeFrequency;
+      return;
+    }
+
+    const cycleDuration = (beatsPerCycle * 60) / bpm;
+    this.frequencyOverrideCSN.offset.value = 1 / cycleDuration;
+    if (!isPlaying) {
+      return;
+    }
+
+    const cyclesElapsed = Math.ceil((this.ctx.currentTime - beatZeroTime) / cycleDuration);
+    this.restartSource(beatZeroTime + cyclesElapsed * cycleDuration);
+  }
+
   public deserialize(params: { [key: string]: any }) {
     if (!R.isNil(params.gain)) {
       this.amplitudeOverrideCSN.offset.value = params.gain;
     }
     if (!R.isNil(params.frequency)) {
-      this.frequencyOverrideCSN.offset.value = params.frequency;
+      this.freeFrequency = params.frequency;
     }
     if (!R.isNil(params.offset)) {
       this.offsetOverrideCSN.offset.value = params.offset;
     }
     if (ALL_WAVEFORMS.includes(params.waveform)) {
-      this.oscillatorNode.type = params.waveform;
+      this.waveform = params.waveform;
+    }
+    if (params.sync in SYNC_BEATS_PER_CYCLE) {
+      this.sync = params.sync;
     }
   }
 
   public serialize(): { [key: string]: any } {
     return {
       gain: this.amplitudeOverrideCSN.offset.value,
-      frequency: this.frequencyOverrideCSN.offset.value,
+      frequency: this.freeFrequency,
       offset: this.offsetOverrideCSN.offset.value,
-      waveform: this.oscillatorNode.type,
+      waveform: this.waveform,
+      sync: this.sync,
     };
   }
 
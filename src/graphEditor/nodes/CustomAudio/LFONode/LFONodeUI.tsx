@@ -2,13 +2,27 @@ import React from 'react';
 import ControlPanel, { Range, Select } from 'react-control-panel';
 import * as R from 'ramda';
 
-import { LFOParams } from 'src/graphEditor/nodes/CustomAudio/LFONode/LFONode';
+import { LFOParams, LFOWaveform } from 'src/graphEditor/nodes/CustomAudio/LFONode/LFONode';
 
-export const ALL_WAVEFORMS: OscillatorType[] = ['sine', 'triangle', 'square', 'sawtooth'];
+export const ALL_WAVEFORMS: LFOWaveform[] = ['sine', 'triangle', 'square', 'sawtooth', 'random'];
+
+/**
+ * Maps the names of the beat-synced rates to the number of beats that one LFO cycle lasts.  The
+ * `free` rate uses the `frequency` setting instead.
+ */
+export const SYNC_BEATS_PER_CYCLE: { [sync: string]: number | null } = {
+  free: null,
+  '4 beats': 4,
+  '2 beats': 2,
+  '1 beat': 1,
+  '1/2 beat': 1 / 2,
+  '1/4 beat': 1 / 4,
+  '1/8 beat': 1 / 8,
+};
 
 const LFOSmallView: React.FC<{
   onChange: (params: LFOParams) => void;
-  initialState: { frequency: number; gain: number; offset: number; waveform: OscillatorType };
+  initialState: LFOParams;
 }> = ({ onChange, initialState }) => (
   <ControlPanel
     style={{ width: 500 }}
@@ -21,11 +35,13 @@ const LFOSmallView: React.FC<{
         gain,
         offset,
         waveform,
+        sync,
       }: {
         frequency: number | undefined;
         gain: number | undefined;
         offset: number | undefined;
-        waveform: OscillatorType;
+        waveform: LFOWaveform;
+        sync: string;
       }
     ) =>
       onChange({
@@ -33,6 +49,7 @@ const LFOSmallView: React.FC<{
         gain: R.isNil(gain) ? initialState.gain : gain,
         offset: R.isNil(offset) ? initialState.offset : offset,
         waveform,
+        sync,
       })
     }
   >
@@ -40,6 +57,7 @@ const LFOSmallView: React.FC<{
     <Range label='gain' min={-1} max={50000} steps={5000} />
     <Range label='offset' min={-50000} max={50000} step={1} />
     <Select label='waveform' options={ALL_WAVEFORMS} />
+    <Select label='sync' options={Object.keys(SYNC_BEATS_PER_CYCLE)} />
   </ControlPanel>
 );
 
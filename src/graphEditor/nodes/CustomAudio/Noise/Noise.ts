@@ -0,0 +1,117 @@
+import { Map } from 'immutable';
+import * as R from 'ramda';
+
+import { ForeignNode } from 'src/graphEditor/nodes/CustomAudio';
+import { AudioConnectables, ConnectableInput, ConnectableOutput } from 'src/patchNetwork';
+import { OverridableAudioParam } from 'src/graphEditor/nodes/util';
+import { mkContainerRenderHelper, mkContainerCleanupHelper } from 'src/reactUtils';
+import {
+  ALL_NOISE_COLORS,
+  buildNoiseSource,
+  NoiseColor,
+} from 'src/graphEditor/nodes/CustomAudio/Noise/noiseBuffers';
+import { NoiseSmallView } from 'src/graphEditor/nodes/CustomAudio/Noise/NoiseUI';
+
+export interface NoiseParams {
+  color: NoiseColor;
+  gain: number;
+}
+
+/**
+ * Generates white or pink noise at audio rate, mostly useful for percussion synthesis
+ */
+export class NoiseNode implements ForeignNode {
+  private ctx: AudioContext;
+  private vcId: string;
+  private source: AudioBufferSourceNode;
+  private gainNode: GainNode;
+  private gainOverrideCSN: ConstantSourceNode;
+  private color: NoiseColor = 'white';
+  public nodeType = 'customAudio/noise';
+  public name = 'Noise';
+
+  /**
+   * See the docs for `enhanceAudioNode`.
+   */
+  public paramOverrides: {
+    [name: string]: { param: OverridableAudioParam; override: ConstantSourceNode };
+  };
+
+  constructor(ctx: AudioContext, vcId: string, params?: { [key: string]: any } | null) {
+    this.ctx = ctx;
+    this.vcId = vcId;
+    this.gainNode = new GainNode(ctx);
+    this.gainNode.gain.value = 0;
+    this.gainOverrideCSN = new ConstantSourceNode(ctx);
+    this.gainOverrideCSN.offset.value = 0.5;
+    this.gainOverrideCSN.start();
+
+    if (params) {
+      this.deserialize(params);
+    }
+    this.source = buildNoiseSource(ctx, this.color);
+    this.source.connect(this.gainNode);
+
+    this.paramOverrides = {
+      gain: {
+        param: new OverridableAudioParam(ctx, this.gainNode.gain, this.gainOverrideCSN),
+        override: this.gainOverrideCSN,
+      },
+    };
+
+    this.renderSmallView = mkContainerRenderHelper({
+      Comp: NoiseSmallView,
+      getProps: () => ({
+        onChange: ({ color, gain }: NoiseParams) => {
+          this.gainOverrideCSN.offset.value = gain;
+          if (color !== this.color) {
+            this.setColor(color);
+          }
+        },
+        initialState: { color: this.color, gain: this.gainOverrideCSN.offset.value },
+      }),
+    });
+
+    this.cleanupSmallView = mkContainerCleanupHelper();
+  }
+
+  private setColor(color: NoiseColor) {
+    this.color = color;
+    const oldSource = this.source;
+    oldSource.stop();
+    oldSource.disconnect();
+    this.source = buildNoiseSource(this.ctx, color);
+    this.source.connect(this.gainNode);
+  }
+
+  public deserialize(params: { [key: string]: any }) {
+    if (ALL_NOISE_COLORS.includes(params.color)) {
+      this.color = params.color;
+    }
+    if (!R.isNil(params.gain)) {
+      this.gainOverrideCSN.offset.value = params.gain;
+    }
+  }
+
+  public serialize(): { [key: string]: any } {
+    return { color: this.color, gain: this.gainOverrideCSN.offset.value };
+  }
+
+  public buildConnectables(): AudioConnectables & { node: ForeignNode } {
+    return {
+      vcId: this.vcId,
+      inputs: Map<string, ConnectableInput>().set('gain', {
+        node: this.paramOverrides.gain.param,
+        type: 'number',
+      }),
+      outputs: Map<string, ConnectableOutput>().set('output', {
+        node: this.gainNode,
+        type: 'customAudio',
+      }),
+      node: this,
+    };
+  }
+
+  public renderSmallView: ForeignNode['renderSmallView'];
+  public cleanupSmallView: ForeignNode['cleanupSmallView'];
+}
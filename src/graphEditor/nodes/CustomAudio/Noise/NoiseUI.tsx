@@ -0,0 +1,58 @@
+import React from 'react';
+import ControlPanel, { Range, Select } from 'react-control-panel';
+import * as R from 'ramda';
+
+import { ALL_NOISE_COLORS, NoiseColor } from 'src/graphEditor/nodes/CustomAudio/Noise/noiseBuffers';
+import { NoiseParams } from 'src/graphEditor/nodes/CustomAudio/Noise/Noise';
+import { SampleAndHoldParams } from 'src/graphEditor/nodes/CustomAudio/Noise/SampleAndHold';
+
+export const NoiseSmallView: React.FC<{
+  onChange: (params: NoiseParams) => void;
+  initialState: NoiseParams;
+}> = ({ onChange, initialState }) => (
+  <ControlPanel
+    style={{ width: 500 }}
+    initialState={initialState}
+    onChange={(
+      _key: string,
+      _val: number,
+      { color, gain }: { color: NoiseColor; gain: number | undefined }
+    ) => onChange({ color, gain: R.isNil(gain) ? initialState.gain : gain })}
+  >
+    <Select label='color' options={ALL_NOISE_COLORS} />
+    <Range label='gain' min={0} max={1} />
+  </ControlPanel>
+);
+
+export const SampleAndHoldSmallView: React.FC<{
+  onChange: (params: SampleAndHoldParams) => void;
+  initialState: SampleAndHoldParams;
+}> = ({ onChange, initialState }) => (
+  <ControlPanel
+    style={{ width: 500 }}
+    initialState={initialState}
+    onChange={(
+      _key: string,
+      _val: number,
+      {
+        frequency,
+        gain,
+        offset,
+      }: {
+        frequency: number | undefined;
+        gain: number | undefined;
+        offset: number | undefined;
+      }
+    ) =>
+      onChange({
+        frequency: R.isNil(frequency) ? initialState.frequency : frequency,
+        gain: R.isNil(gain) ? initialState.gain : gain,
+        offset: R.isNil(offset) ? initialState.offset : offset,
+      })
+    }
+  >
+    <Range label='frequency' min={0.01} max={1000} scale='log' steps={1000} />
+    <Range label='gain' min={-1} max={50000} steps={5000} />
+    <Range label='offset' min={-50000} max={50000} step={1} />
+  </ControlPanel>
+);
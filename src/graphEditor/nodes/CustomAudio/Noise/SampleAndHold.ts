@@ -0,0 +1,147 @@
+import { Map } from 'immutable';
+import * as R from 'ramda';
+
+import { ForeignNode } from 'src/graphEditor/nodes/CustomAudio';
+import { AudioConnectables, ConnectableInput, ConnectableOutput } from 'src/patchNetwork';
+import { OverridableAudioParam } from 'src/graphEditor/nodes/util';
+import { mkContainerRenderHelper, mkContainerCleanupHelper } from 'src/reactUtils';
+import {
+  buildSampleAndHoldSource,
+  RANDOM_STEP_COUNT,
+} from 'src/graphEditor/nodes/CustomAudio/Noise/noiseBuffers';
+import { SampleAndHoldSmallView } from 'src/graphEditor/nodes/CustomAudio/Noise/NoiseUI';
+
+export interface SampleAndHoldParams {
+  frequency: number;
+  gain: number;
+  offset: number;
+}
+
+/**
+ * A modulation source that picks a new random value `frequency` times per second and holds it
+ * until the next one, for classic stepped filter sweeps.
+ */
+export class SampleAndHoldNode implements ForeignNode {
+  private vcId: string;
+  private gainNode: GainNode;
+  private offsetNode: ConstantSourceNode;
+  private frequencyNode: ConstantSourceNode;
+  public nodeType = 'customAudio/sampleAndHold';
+  public name = 'Sample & Hold';
+
+  private frequencyOverrideCSN: ConstantSourceNode;
+  private amplitudeOverrideCSN: ConstantSourceNode;
+  private offsetOverrideCSN: ConstantSourceNode;
+
+  /**
+   * See the docs for `enhanceAudioNode`.
+   */
+  public paramOverrides: {
+    [name: string]: { param: OverridableAudioParam; override: ConstantSourceNode };
+  };
+
+  constructor(ctx: AudioContext, vcId: string, params?: { [key: string]: any } | null) {
+    this.vcId = vcId;
+    this.gainNode = new GainNode(ctx);
+    this.gainNode.gain.value = 0;
+    this.offsetNode = new ConstantSourceNode(ctx);
+    this.offsetNode.offset.value = 0;
+    this.offsetNode.start();
+    this.frequencyNode = new ConstantSourceNode(ctx);
+    this.frequencyNode.offset.value = 0;
+    this.frequencyNode.start();
+
+    // Source -> Gain -> Offset -> Output, with the source's playback rate driven by the frequency
+    const source = buildSampleAndHoldSource(ctx, 0);
+    const rateScaler = new GainNode(ctx, { gain: 1 / RANDOM_STEP_COUNT });
+    this.frequencyNode.connect(rateScaler);
+    rateScaler.connect(source.playbackRate);
+    source.connect(this.gainNode);
+    this.gainNode.connect(this.offsetNode.offset);
+
+    this.frequencyOverrideCSN = new ConstantSourceNode(ctx);
+    this.frequencyOverrideCSN.offset.value = 4;
+    this.frequencyOverrideCSN.start();
+    this.amplitudeOverrideCSN = new ConstantSourceNode(ctx);
+    this.amplitudeOverrideCSN.offset.value = 1;
+    this.amplitudeOverrideCSN.start();
+    this.offsetOverrideCSN = new ConstantSourceNode(ctx);
+    this.offsetOverrideCSN.offset.value = 0;
+    this.offsetOverrideCSN.start();
+
+    if (params) {
+      this.deserialize(params);
+    }
+
+    this.paramOverrides = {
+      frequency: {
+        param: new OverridableAudioParam(
+          ctx,
+          this.frequencyNode.offset,
+          this.frequencyOverrideCSN
+        ),
+        override: this.frequencyOverrideCSN,
+      },
+      amplitude: {
+        param: new OverridableAudioParam(ctx, this.gainNode.gain, this.amplitudeOverrideCSN),
+        override: this.amplitudeOverrideCSN,
+      },
+      offset: {
+        param: new OverridableAudioParam(ctx, this.offsetNode.offset, this.offsetOverrideCSN),
+        override: this.offsetOverrideCSN,
+      },
+    };
+
+    this.renderSmallView = mkContainerRenderHelper({
+      Comp: SampleAndHoldSmallView,
+      getProps: () => ({
+        onChange: ({ frequency, gain, offset }: SampleAndHoldParams) => {
+          this.frequencyOverrideCSN.offset.value = frequency;
+          this.amplitudeOverrideCSN.offset.value = gain;
+          this.offsetOverrideCSN.offset.value = offset;
+        },
+        initialState: this.serialize() as SampleAndHoldParams,
+      }),
+    });
+
+    this.cleanupSmallView = mkContainerCleanupHelper();
+  }
+
+  public deserialize(params: { [key: string]: any }) {
+    if (!R.isNil(params.frequency)) {
+      this.frequencyOverrideCSN.offset.value = params.frequency;
+    }
+    if (!R.isNil(params.gain)) {
+      this.amplitudeOverrideCSN.offset.value = params.gain;
+    }
+    if (!R.isNil(params.offset)) {
+      this.offsetOverrideCSN.offset.value = params.offset;
+    }
+  }
+
+  public serialize(): { [key: string]: any } {
+    return {
+      frequency: this.frequencyOverrideCSN.offset.value,
+      gain: this.amplitudeOverrideCSN.offset.value,
+      offset: this.offsetOverrideCSN.offset.value,
+    };
+  }
+
+  public buildConnectables(): AudioConnectables & { node: ForeignNode } {
+    return {
+      vcId: this.vcId,
+      inputs: Map<string, ConnectableInput>()
+        .set('frequency', { node: this.paramOverrides.frequency.param, type: 'number' })
+        .set('amplitude', { node: this.paramOverrides.amplitude.param, type: 'number' })
+        .set('offset', { node: this.paramOverrides.offset.param, type: 'number' }),
+      outputs: Map<string, ConnectableOutput>().set('signal', {
+        node: this.offsetNode,
+        type: 'number',
+      }),
+      node: this,
+    };
+  }
+
+  public renderSmallView: ForeignNode['renderSmallView'];
+  public cleanupSmallView: ForeignNode['cleanupSmallView'];
+}
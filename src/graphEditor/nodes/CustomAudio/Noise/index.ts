@@ -0,0 +1,2 @@
+export * from './Noise';
+export * from './SampleAndHold';
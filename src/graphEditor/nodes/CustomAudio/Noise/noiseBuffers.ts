@@ -0,0 +1,109 @@
+/**
+ * Noise and random steps are played from looping buffers of pre-generated samples rather than
+ * being generated live.  The buffers are shared between all of the nodes that play them.
+ */
+
+export type NoiseColor = 'white' | 'pink';
+
+export const ALL_NOISE_COLORS: NoiseColor[] = ['white', 'pink'];
+
+/**
+ * Long enough that the loop isn't audible
+ */
+const NOISE_BUFFER_SECONDS = 4;
+
+/**
+ * The number of random steps in the buffer backing sample & hold sources before it repeats
+ */
+export const RANDOM_STEP_COUNT = 64;
+
+const NoiseBuffers: Map<NoiseColor, AudioBuffer> = new Map();
+let RandomStepBuffer: AudioBuffer | null = null;
+
+/**
+ * Fills `samples` with pink noise using Paul Kellet's refined filter, which is accurate to within
+ * ±0.05dB above 9.2Hz.
+ */
+const fillPinkNoise = (samples: Float32Array) => {
+  let [b0, b1, b2, b3, b4, b5, b6] = [0, 0, 0, 0, 0, 0, 0];
+  for (let i = 0; i < samples.length; i++) {
+    const white = Math.random() * 2 - 1;
+    b0 = 0.99886 * b0 + white * 0.0555179;
+    b1 = 0.99332 * b1 + white * 0.0750759;
+    b2 = 0.969 * b2 + white * 0.153852;
+    b3 = 0.8665 * b3 + white * 0.3104856;
+    b4 = 0.55 * b4 + white * 0.5329522;
+    b5 = -0.7616 * b5 - white * 0.016898;
+    samples[i] = b0 + b1 + b2 + b3 + b4 + b5 + b6 + white * 0.5362;
+    b6 = white * 0.115926;
+  }
+};
+
+export const getNoiseBuffer = (ctx: BaseAudioContext, color: NoiseColor): AudioBuffer => {
+  const existing = NoiseBuffers.get(color);
+  if (existing) {
+    return existing;
+  }
+
+  const { sampleRate } = ctx;
+  const buffer = new AudioBuffer({ length: sampleRate * NOISE_BUFFER_SECONDS, sampleRate });
+  const samples = buffer.getChannelData(0);
+  if (color === 'pink') {
+    fillPinkNoise(samples);
+  } else {
+    for (let i = 0; i < samples.length; i++) {
+      samples[i] = Math.random() * 2 - 1;
+    }
+  }
+
+  // Normalize to the range [-1, 1]
+  const peak = samples.reduce((acc, sample) => Math.max(acc, Math.abs(sample)), 0);
+  if (peak > 0) {
+    samples.forEach((sample, i) => {
+      samples[i] = sample / peak;
+    });
+  }
+
+  NoiseBuffers.set(color, buffer);
+  return buffer;
+};
+
+/**
+ * Returns a buffer of `RANDOM_STEP_COUNT` steps of random values in the range [-1, 1].  It's one
+ * second long so that a playback rate of `1 / RANDOM_STEP_COUNT` steps once per second.
+ */
+export const getRandomStepBuffer = (ctx: BaseAudioContext): AudioBuffer => {
+  if (RandomStepBuffer) {
+    return RandomStepBuffer;
+  }
+
+  const { sampleRate } = ctx;
+  const buffer = new AudioBuffer({ length: sampleRate, sampleRate });
+  const samples = buffer.getChannelData(0);
+  const samplesPerStep = Math.ceil(samples.length / RANDOM_STEP_COUNT);
+  for (let i = 0; i < RANDOM_STEP_COUNT; i++) {
+    samples.fill(Math.random() * 2 - 1, i * samplesPerStep, (i + 1) * samplesPerStep);
+  }
+  RandomStepBuffer = buffer;
+  return buffer;
+};
+
+/**
+ * Builds and starts a looping source of noise.  It starts from a random point in the buffer so that
+ * multiple sources of the same color aren't correlated.
+ */
+export const buildNoiseSource = (ctx: BaseAudioContext, color: NoiseColor) => {
+  const source = new AudioBufferSourceNode(ctx, { buffer: getNoiseBuffer(ctx, color), loop: true });
+  source.start(ctx.currentTime, Math.random() * NOISE_BUFFER_SECONDS);
+  return source;
+};
+
+/**
+ * Builds and starts a source that picks a new random value `frequency` times per second
+ */
+export const buildSampleAndHoldSource = (ctx: BaseAudioContext, frequency: number) => {
+  const source = new AudioBufferSourceNode(ctx, { buffer: getRandomStepBuffer(ctx), loop: true });
+  source.playbackRate.value = frequency / RANDOM_STEP_COUNT;
+  source.start();
+  return source;
+};
@@ -0,0 +1,258 @@
+import { Map } from 'immutable';
+import * as R from 'ramda';
+
+import { ForeignNode } from 'src/graphEditor/nodes/CustomAudio';
+import { AudioConnectables, ConnectableInput, ConnectableOutput } from 'src/patchNetwork';
+import { OverridableAudioParam } from 'src/graphEditor/nodes/util';
+import { mkContainerRenderHelper, mkContainerCleanupHelper } from 'src/reactUtils';
+import ParametricEQSmallView from './ParametricEQUI';
+
+export type EQBandType = 'lowshelf' | 'peaking' | 'highshelf';
+
+export interface EQBand {
+  type: EQBandType;
+  frequency: number;
+  /**
+   * Boost or cut in dB
+   */
+  gain: number;
+  /**
+   * Only used by peaking bands; shelves have a fixed slope
+   */
+  q: number;
+}
+
+export interface ParametricEQParams {
+  /**
+   * A low shelf, followed by any number of peaking bands, followed by a high shelf
+   */
+  bands: EQBand[];
+}
+
+export const MIN_EQ_FREQUENCY = 20;
+export const MAX_EQ_FREQUENCY = 20000;
+export const MAX_EQ_GAIN_DB = 24;
+export const MAX_PEAKING_BAND_COUNT = 8;
+/**
+ * Time constant used when changing band params so that sweeps don't produce zipper noise
+ */
+const BAND_PARAM_TIME_CONSTANT = 0.01;
+
+export const buildDefaultPeakingBand = (frequency = 1000): EQBand => ({
+  type: 'peaking',
+  frequency,
+  gain: 0,
+  q: 1,
+});
+
+const DEFAULT_EQ_PARAMS: ParametricEQParams = {
+  bands: [
+    { type: 'lowshelf', frequency: 100, gain: 0, q: 1 },
+    buildDefaultPeakingBand(300),
+    buildDefaultPeakingBand(1000),
+    buildDefaultPeakingBand(3000),
+    { type: 'highshelf', frequency: 8000, gain: 0, q: 1 },
+  ],
+};
+
+const sanitizeBand = (band: any, type: EQBandType): EQBand | null => {
+  if (!band || typeof band !== 'object') {
+    return null;
+  }
+  const { frequency, gain, q } = band;
+  if (typeof frequency !== 'number' || typeof gain !== 'number' || typeof q !== 'number') {
+    return null;
+  }
+
+  return {
+    type,
+    frequency: R.clamp(MIN_EQ_FREQUENCY, MAX_EQ_FREQUENCY, frequency),
+    gain: R.clamp(-MAX_EQ_GAIN_DB, MAX_EQ_GAIN_DB, gain),
+    q,
+  };
+};
+
+/**
+ * A multi-band parametric EQ made up of a low shelf, any number of peaking bands, and a high shelf.
+ * Each band is a biquad filter and the bands are run in series.
+ */
+export class ParametricEQ implements ForeignNode {
+  private ctx: AudioContext;
+  private vcId: string;
+  private params: ParametricEQParams;
+  private inputNode: GainNode;
+  private outputNode: GainNode;
+  private filters: BiquadFilterNode[] = [];
+  /**
+   * Called after the bands are changed so that the UI can re-plot the frequency response
+   */
+  private onChange: (() => void) | null = null;
+
+  public nodeType = 'customAudio/parametricEQ';
+  public name = 'Parametric EQ';
+
+  /**
+   * See the docs for `enhanceAudioNode`.
+   */
+  public paramOverrides: {
+    [name: string]: { param: OverridableAudioParam; override: ConstantSourceNode };
+  } = {};
+
+  constructor(ctx: AudioContext, vcId: string, params?: { [key: string]: any } | null) {
+    this.ctx = ctx;
+    this.vcId = vcId;
+    this.params = { ...DEFAULT_EQ_PARAMS, ...this.deserialize(params || {}) };
+    this.inputNode = new GainNode(ctx);
+    this.outputNode = new GainNode(ctx);
+    this.buildFilters();
+
+    this.renderSmallView = mkContainerRenderHelper({
+      Comp: ParametricEQSmallView,
+      getProps: () => ({
+        eq: this,
+        registerOnChange: (onChange: (() => void) | null) => {
+          this.onChange = onChange;
+        },
+      }),
+    });
+
+    this.cleanupSmallView = mkContainerCleanupHelper();
+  }
+
+  /**
+   * Creates a biquad filter for each band and connects them in series between the input and output
+   */
+  private buildFilters() {
+    this.inputNode.disconnect();
+    this.filters.forEach(filter => filter.disconnect());
+
+    this.filters = this.params.bands.map(
+      ({ type, frequency, gain, q }) =>
+        new BiquadFilterNode(this.ctx, { type, frequency, gain, Q: q })
+    );
+    const lastNode = this.filters.reduce((prevNode: AudioNode, filter) => {
+      prevNode.connect(filter);
+      return filter;
+    }, this.inputNode);
+    lastNode.connect(this.outputNode);
+
+    if (this.onChange) {
+      this.onChange();
+    }
+  }
+
+  public getBands(): EQBand[] {
+    return this.params.bands;
+  }
+
+  public setBand(bandIx: number, band: Partial<Omit<EQBand, 'type'>>) {
+    const oldBand = this.params.bands[bandIx];
+    if (!oldBand) {
+      console.error(`Tried to set EQ band ${bandIx} but only ${this.params.bands.length} exist`);
+      return;
+    }
+
+    const newBand = sanitizeBand({ ...oldBand, ...band }, oldBand.type)!;
+    this.params = { bands: R.update(bandIx, newBand, this.params.bands) };
+
+    const filter = this.filters[bandIx];
+    const now = this.ctx.currentTime;
+    filter.frequency.setTargetAtTime(newBand.frequency, now, BAND_PARAM_TIME_CONSTANT);
+    filter.gain.setTargetAtTime(newBand.gain, now, BAND_PARAM_TIME_CONSTANT);
+    filter.Q.setTargetAtTime(newBand.q, now, BAND_PARAM_TIME_CONSTANT);
+
+    if (this.onChange) {
+      this.onChange();
+    }
+  }
+
+  public getPeakingBandCount(): number {
+    return this.params.bands.filter(band => band.type === 'peaking').length;
+  }
+
+  /**
+   * Adds a new peaking band just before the high shelf
+   */
+  public addPeakingBand() {
+    if (this.getPeakingBandCount() >= MAX_PEAKING_BAND_COUNT) {
+      return;
+    }
+
+    const { bands } = this.params;
+    this.params = { bands: R.insert(bands.length - 1, buildDefaultPeakingBand(), bands) };
+    this.buildFilters();
+  }
+
+  public removeBand(bandIx: number) {
+    if (this.params.bands[bandIx]?.type !== 'peaking') {
+      console.error(`Tried to remove EQ band ${bandIx}, but only peaking bands can be removed`);
+      return;
+    }
+
+    this.params = { bands: R.remove(bandIx, 1, this.params.bands) };
+    this.buildFilters();
+  }
+
+  /**
+   * Returns the gain in dB that the EQ applies at each of `frequencies`.  The responses of the
+   * bands are multiplied together since they run in series, so their gains in dB are summed.
+   *
+   * The response is computed with filters that are set to the new band params immediately rather
+   * than with the ones processing audio, since those glide to new values.
+   */
+  public getFrequencyResponse(frequencies: Float32Array): Float32Array {
+    const response = new Float32Array(frequencies.length);
+    const magnitudes = new Float32Array(frequencies.length);
+    const phases = new Float32Array(frequencies.length);
+
+    this.params.bands.forEach(({ type, frequency, gain, q }) => {
+      const filter = new BiquadFilterNode(this.ctx, { type, frequency, gain, Q: q });
+      filter.getFrequencyResponse(frequencies, magnitudes, phases);
+      magnitudes.forEach((magnitude, i) => {
+        response[i] += 20 * Math.log10(magnitude);
+      });
+    });
+    return response;
+  }
+
+  private deserialize(params: { [key: string]: any }): Partial<ParametricEQParams> {
+    if (!Array.isArray(params.bands) || params.bands.length < 2) {
+      return {};
+    }
+
+    const lowShelf = sanitizeBand(R.head(params.bands), 'lowshelf');
+    const highShelf = sanitizeBand(R.last(params.bands), 'highshelf');
+    const peakingBands = params.bands
+      .slice(1, -1)
+      .map((band: any) => sanitizeBand(band, 'peaking'))
+      .slice(0, MAX_PEAKING_BAND_COUNT);
+    if (!lowShelf || !highShelf || peakingBands.some(R.isNil)) {
+      console.warn('Invalid parametric EQ bands found in params; using defaults');
+      return {};
+    }
+
+    return { bands: [lowShelf, ...peakingBands, highShelf] };
+  }
+
+  public serialize(): { [key: string]: any } {
+    return this.params;
+  }
+
+  public buildConnectables(): AudioConnectables & { node: ForeignNode } {
+    return {
+      vcId: this.vcId,
+      inputs: Map<string, ConnectableInput>().set('input', {
+        node: this.inputNode,
+        type: 'customAudio',
+      }),
+      outputs: Map<string, ConnectableOutput>().set('output', {
+        node: this.outputNode,
+        type: 'customAudio',
+      }),
+      node: this,
+    };
+  }
+
+  public renderSmallView: ForeignNode['renderSmallView'];
+  public cleanupSmallView: ForeignNode['cleanupSmallView'];
+}
@@ -0,0 +1,163 @@
+import React, { useEffect, useMemo, useReducer, useState } from 'react';
+import ControlPanel from 'react-control-panel';
+
+import {
+  EQBand,
+  ParametricEQ,
+  MAX_EQ_FREQUENCY,
+  MAX_EQ_GAIN_DB,
+  MAX_PEAKING_BAND_COUNT,
+  MIN_EQ_FREQUENCY,
+} from 'src/graphEditor/nodes/CustomAudio/ParametricEQ/ParametricEQ';
+
+const PLOT_WIDTH = 500;
+const PLOT_HEIGHT = 160;
+/**
+ * The range in dB shown above and below 0 on the frequency response plot
+ */
+const PLOT_RANGE_DB = MAX_EQ_GAIN_DB;
+const GRID_FREQUENCIES = [100, 1000, 10000];
+
+const BAND_LABELS: { [type in EQBand['type']]: string } = {
+  lowshelf: 'low shelf',
+  peaking: 'peak',
+  highshelf: 'high shelf',
+};
+
+const frequencyToX = (frequency: number) =>
+  (Math.log(frequency / MIN_EQ_FREQUENCY) / Math.log(MAX_EQ_FREQUENCY / MIN_EQ_FREQUENCY)) *
+  PLOT_WIDTH;
+
+const dbToY = (db: number) => ((PLOT_RANGE_DB - db) / (PLOT_RANGE_DB * 2)) * PLOT_HEIGHT;
+
+/**
+ * The frequency plotted at each horizontal pixel of the frequency response plot
+ */
+const buildPlotFrequencies = () =>
+  new Float32Array(PLOT_WIDTH).map(
+    (_, x) => MIN_EQ_FREQUENCY * Math.pow(MAX_EQ_FREQUENCY / MIN_EQ_FREQUENCY, x / PLOT_WIDTH)
+  );
+
+const FrequencyResponsePlot: React.FC<{ eq: ParametricEQ; bands: EQBand[] }> = ({ eq, bands }) => {
+  const [canvasRef, setCanvasRef] = useState<HTMLCanvasElement | null>(null);
+  const frequencies = useMemo(buildPlotFrequencies, []);
+
+  useEffect(() => {
+    if (!canvasRef) {
+      return;
+    }
+    const ctx2d = canvasRef.getContext('2d')!;
+    ctx2d.clearRect(0, 0, PLOT_WIDTH, PLOT_HEIGHT);
+
+    ctx2d.strokeStyle = '#333';
+    ctx2d.beginPath();
+    GRID_FREQUENCIES.forEach(frequency => {
+      ctx2d.moveTo(frequencyToX(frequency), 0);
+      ctx2d.lineTo(frequencyToX(frequency), PLOT_HEIGHT);
+    });
+    ctx2d.moveTo(0, dbToY(0));
+    ctx2d.lineTo(PLOT_WIDTH, dbToY(0));
+    ctx2d.stroke();
+
+    const response = eq.getFrequencyResponse(frequencies);
+    ctx2d.strokeStyle = '#0f0';
+    ctx2d.beginPath();
+    response.forEach((db, x) => ctx2d.lineTo(x, dbToY(db)));
+    ctx2d.stroke();
+
+    ctx2d.fillStyle = '#ff0';
+    bands.forEach(band =>
+      ctx2d.fillRect(frequencyToX(band.frequency) - 2, dbToY(band.gain) - 2, 4, 4)
+    );
+  }, [canvasRef, eq, bands, frequencies]);
+
+  return (
+    <canvas
+      ref={setCanvasRef}
+      width={PLOT_WIDTH}
+      height={PLOT_HEIGHT}
+      style={{ backgroundColor: '#000' }}
+    />
+  );
+};
+
+const buildBandSettings = (band: EQBand) => [
+  {
+    type: 'range',
+    label: 'frequency',
+    min: MIN_EQ_FREQUENCY,
+    max: MAX_EQ_FREQUENCY,
+    scale: 'log',
+    steps: 500,
+  },
+  { type: 'range', label: 'gain', min: -MAX_EQ_GAIN_DB, max: MAX_EQ_GAIN_DB, step: 0.1 },
+  // Shelves have a fixed slope, so Q only applies to peaking bands
+  ...(band.type === 'peaking'
+    ? [{ type: 'range', label: 'Q', min: 0.1, max: 18, scale: 'log', steps: 200 }]
+    : []),
+];
+
+const ParametricEQSmallView: React.FC<{
+  eq: ParametricEQ;
+  registerOnChange: (onChange: (() => void) | null) => void;
+}> = ({ eq, registerOnChange }) => {
+  const [, forceUpdate] = useReducer((x: number) => x + 1, 0);
+
+  useEffect(() => {
+    registerOnChange(forceUpdate);
+    return () => registerOnChange(null);
+  }, [registerOnChange]);
+
+  const bands = eq.getBands();
+
+  return (
+    <div className='parametric-eq'>
+      <FrequencyResponsePlot eq={eq} bands={bands} />
+      <div>
+        <button
+          disabled={eq.getPeakingBandCount() >= MAX_PEAKING_BAND_COUNT}
+          onClick={() => eq.addPeakingBand()}
+        >
+          add band
+        </button>
+      </div>
+
+      {bands.map((band, i) => (
+        <div key={`${i}-${bands.length}`} className='parametric-eq-band'>
+          <div>
+            <b>{BAND_LABELS[band.type]}</b>
+            {band.type === 'peaking' ? (
+              <button onClick={() => eq.removeBand(i)}>remove</button>
+            ) : null}
+          </div>
+          <ControlPanel
+            style={{ width: 500 }}
+            settings={buildBandSettings(band)}
+            state={{ frequency: band.frequency, gain: band.gain, Q: band.q }}
+            onChange={(key: string, val: number) => {
+              switch (key) {
+                case 'frequency': {
+                  eq.setBand(i, { frequency: val });
+                  break;
+                }
+                case 'gain': {
+                  eq.setBand(i, { gain: val });
+                  break;
+                }
+                case 'Q': {
+                  eq.setBand(i, { q: val });
+                  break;
+                }
+                default: {
+                  console.error(`Unhandled parametric EQ setting: ${key}`);
+                }
+              }
+            }}
+          />
+        </div>
+      ))}
+    </div>
+  );
+};
+
+export default ParametricEQSmallView;
@@ -0,0 +1,163 @@
+import { Map } from 'immutable';
+
+import { ForeignNode } from 'src/graphEditor/nodes/CustomAudio';
+import { AudioConnectables, ConnectableInput, ConnectableOutput } from 'src/patchNetwork';
+import { OverridableAudioParam } from 'src/graphEditor/nodes/util';
+import { mkContainerRenderHelper, mkContainerCleanupHelper } from 'src/reactUtils';
+import ReverbSmallView from './ReverbUI';
+
+export interface ReverbParams {
+  roomSize: number;
+  damping: number;
+  wet: number;
+  bypass: boolean;
+}
+
+const DEFAULT_REVERB_PARAMS: ReverbParams = {
+  roomSize: 0.5,
+  damping: 0.5,
+  wet: 0.3,
+  bypass: false,
+};
+
+const OVERRIDABLE_PARAM_NAMES: ('roomSize' | 'damping' | 'wet')[] = ['roomSize', 'damping', 'wet'];
+
+/**
+ * A Freeverb-style stereo reverb.  The DSP is implemented in Wasm and run inside of an
+ * `AudioWorkletProcessor`.  Bypassing it fades out the wet signal rather than disconnecting
+ * anything so that it doesn't click.
+ */
+export class Reverb implements ForeignNode {
+  private ctx: AudioContext;
+  private vcId: string;
+  private params: ReverbParams;
+  private workletHandle: AudioWorkletNode | undefined;
+  /**
+   * The input, output, and params are created immediately so that connections can be made to them
+   * before the worklet finishes loading.  The param nodes are connected to the worklet's params
+   * once it's loaded.
+   */
+  private inputNode: GainNode;
+  private outputNode: GainNode;
+  private paramNodes: { [name: string]: ConstantSourceNode } = {};
+
+  public nodeType = 'customAudio/reverb';
+  public name = 'Reverb';
+
+  /**
+   * See the docs for `enhanceAudioNode`.
+   */
+  public paramOverrides: {
+    [name: string]: { param: OverridableAudioParam; override: ConstantSourceNode };
+  };
+
+  constructor(ctx: AudioContext, vcId: string, params?: { [key: string]: any } | null) {
+    this.ctx = ctx;
+    this.vcId = vcId;
+    this.params = { ...DEFAULT_REVERB_PARAMS, ...this.deserialize(params || {}) };
+    this.inputNode = new GainNode(ctx);
+    this.outputNode = new GainNode(ctx);
+    this.paramOverrides = this.buildParamOverrides();
+    this.setParams(this.params);
+
+    this.initWorklet().then(workletHandle => {
+      OVERRIDABLE_PARAM_NAMES.forEach(name => {
+        // Work around incomplete TypeScript typings
+        const param = (workletHandle.parameters as Map<string, AudioParam>).get(name)!;
+        param.value = 0;
+        this.paramNodes[name].connect(param);
+      });
+      this.setBypassed(this.params.bypass);
+
+      this.inputNode.connect(workletHandle);
+      workletHandle.connect(this.outputNode);
+    });
+
+    this.renderSmallView = mkContainerRenderHelper({
+      Comp: ReverbSmallView,
+      getProps: () => ({
+        initialParams: this.params,
+        onChange: (params: ReverbParams) => this.setParams(params),
+      }),
+    });
+
+    this.cleanupSmallView = mkContainerCleanupHelper();
+  }
+
+  private async initWorklet() {
+    await this.ctx.audioWorklet.addModule('/ReverbNodeProcessor.js');
+    this.workletHandle = new AudioWorkletNode(this.ctx, 'reverb-node-processor', {
+      outputChannelCount: [2],
+    });
+
+    const moduleBytes = await fetch('./reverb.wasm').then(res => res.arrayBuffer());
+    this.workletHandle.port.postMessage({ arrayBuffer: moduleBytes });
+
+    return this.workletHandle;
+  }
+
+  private buildParamOverrides(): ForeignNode['paramOverrides'] {
+    return OVERRIDABLE_PARAM_NAMES.reduce((acc, name) => {
+      const paramNode = new ConstantSourceNode(this.ctx, { offset: 0 });
+      paramNode.start();
+      this.paramNodes[name] = paramNode;
+
+      const override = new OverridableAudioParam(this.ctx, paramNode.offset);
+      return { ...acc, [name]: { param: override, override: override.manualControl } };
+    }, {} as ForeignNode['paramOverrides']);
+  }
+
+  private setBypassed(bypass: boolean) {
+    if (!this.workletHandle) {
+      return;
+    }
+
+    // Work around incomplete TypeScript typings
+    const bypassParam = (this.workletHandle.parameters as Map<string, AudioParam>).get('bypass')!;
+    bypassParam.value = bypass ? 1 : 0;
+  }
+
+  public setParams(params: ReverbParams) {
+    this.params = params;
+    OVERRIDABLE_PARAM_NAMES.forEach(name => {
+      this.paramOverrides[name].override.offset.value = params[name];
+    });
+    this.setBypassed(params.bypass);
+  }
+
+  private deserialize(params: { [key: string]: any }): Partial<ReverbParams> {
+    const deserialized: Partial<ReverbParams> = {};
+    OVERRIDABLE_PARAM_NAMES.forEach(name => {
+      if (typeof params[name] === 'number') {
+        deserialized[name] = params[name];
+      }
+    });
+    if (typeof params.bypass === 'boolean') {
+      deserialized.bypass = params.bypass;
+    }
+    return deserialized;
+  }
+
+  public serialize(): { [key: string]: any } {
+    return this.params;
+  }
+
+  public buildConnectables(): AudioConnectables & { node: ForeignNode } {
+    return {
+      vcId: this.vcId,
+      inputs: OVERRIDABLE_PARAM_NAMES.reduce(
+        (acc, name) =>
+          acc.set(name, { node: this.paramOverrides[name].param, type: 'number' }),
+        Map<string, ConnectableInput>().set('input', { node: this.inputNode, type: 'customAudio' })
+      ),
+      outputs: Map<string, ConnectableOutput>().set('output', {
+        node: this.outputNode,
+        type: 'customAudio',
+      }),
+      node: this,
+    };
+  }
+
+  public renderSmallView: ForeignNode['renderSmallView'];
+  public cleanupSmallView: ForeignNode['cleanupSmallView'];
+}
@@ -0,0 +1,45 @@
+import React, { useState } from 'react';
+import ControlPanel from 'react-control-panel';
+
+import { ReverbParams } from 'src/graphEditor/nodes/CustomAudio/Reverb/Reverb';
+
+const SETTINGS = [
+  { type: 'range', label: 'room size', min: 0, max: 1 },
+  { type: 'range', label: 'damping', min: 0, max: 1 },
+  { type: 'range', label: 'wet', min: 0, max: 1 },
+  { type: 'checkbox', label: 'bypass' },
+];
+
+const KEYS: { [label: string]: keyof ReverbParams } = {
+  'room size': 'roomSize',
+  damping: 'damping',
+  wet: 'wet',
+  bypass: 'bypass',
+};
+
+const ReverbSmallView: React.FC<{
+  initialParams: ReverbParams;
+  onChange: (params: ReverbParams) => void;
+}> = ({ initialParams, onChange }) => {
+  const [params, setParams] = useState(initialParams);
+
+  return (
+    <ControlPanel
+      style={{ width: 500 }}
+      settings={SETTINGS}
+      state={{
+        'room size': params.roomSize,
+        damping: params.damping,
+        wet: params.wet,
+        bypass: params.bypass,
+      }}
+      onChange={(key: string, val: any) => {
+        const newParams = { ...params, [KEYS[key]]: val };
+        setParams(newParams);
+        onChange(newParams);
+      }}
+    />
+  );
+};
+
+export default ReverbSmallView;
@@ -0,0 +1,235 @@
+import { Map } from 'immutable';
+
+import { ForeignNode } from 'src/graphEditor/nodes/CustomAudio';
+import { AudioConnectables, ConnectableInput, ConnectableOutput } from 'src/patchNetwork';
+import { MIDINode, buildMIDINode, MIDIInputCbs } from 'src/patchNetwork/midiNode';
+import { mkContainerRenderHelper, mkContainerCleanupHelper } from 'src/reactUtils';
+import { SampleDescriptor } from 'src/sampleLibrary';
+import {
+  ImportedSample,
+  importSample,
+  releaseImportedSample,
+} from 'src/sampleLibrary/sampleImport';
+import SamplerSmallView from './SamplerUI';
+
+export interface SamplerParams {
+  sample: SampleDescriptor | null;
+  /**
+   * The MIDI note at which the sample is played back at its original pitch
+   */
+  rootNote: number;
+  /**
+   * If `false`, the sampler is in one-shot mode and every note plays the whole sample, ignoring
+   * releases.
+   */
+  looping: boolean;
+  /**
+   * Loop points as a fraction of the length of the sample in the range [0, 1]
+   */
+  loopStart: number;
+  loopEnd: number;
+  releaseSeconds: number;
+  /**
+   * Start of each slice as a fraction of the length of the sample, set when the sample has been
+   * beat-sliced in the sample editor.  Notes starting at `sliceBaseNote` each play one slice at its
+   * original pitch.
+   */
+  slices: number[];
+  sliceBaseNote: number;
+}
+
+const DEFAULT_SAMPLER_PARAMS: SamplerParams = {
+  sample: null,
+  rootNote: 60,
+  looping: false,
+  loopStart: 0,
+  loopEnd: 1,
+  releaseSeconds: 0.05,
+  slices: [],
+  sliceBaseNote: 36,
+};
+
+/**
+ * Plays back a sample from the sample library at the pitch of the notes that it receives over MIDI.
+ * Playback is implemented in Wasm and run inside of an `AudioWorkletProcessor`.  MIDI events are
+ * routed to the sampler's voices by their voice index, so it can be driven by the MIDI editor in
+ * the same way as synths.
+ */
+export class Sampler implements ForeignNode {
+  private ctx: AudioContext;
+  private vcId: string;
+  private params: SamplerParams;
+  private midiNode: MIDINode;
+  private workletHandle: AudioWorkletNode | undefined;
+  /**
+   * Created immediately so that connections can be made to it before the worklet finishes loading
+   */
+  private outputNode: GainNode;
+  /**
+   * Length of the currently loaded sample in frames, used to convert the loop points
+   */
+  private sampleLength = 0;
+  /**
+   * Name of the sample that was most recently loaded or is currently being loaded
+   */
+  private requestedSampleName: string | null = null;
+  /**
+   * ID of the currently loaded sample in the engine's sample pool, which is released when it's
+   * replaced
+   */
+  private importedSampleId: number | null = null;
+
+  public nodeType = 'customAudio/sampler';
+  public name = 'Sampler';
+
+  /**
+   * See the docs for `enhanceAudioNode`.
+   */
+  public paramOverrides: ForeignNode['paramOverrides'] = {};
+
+  constructor(ctx: AudioContext, vcId: string, params?: { [key: string]: any } | null) {
+    this.ctx = ctx;
+    this.vcId = vcId;
+    this.params = { ...DEFAULT_SAMPLER_PARAMS, ...(params || {}) };
+    this.outputNode = new GainNode(ctx);
+    this.midiNode = buildMIDINode(this.getMIDIInputCbs);
+
+    this.initWorklet().then(workletHandle => {
+      workletHandle.connect(this.outputNode);
+      this.requestedSampleName = null;
+      this.setParams(this.params);
+    });
+
+    this.renderSmallView = mkContainerRenderHelper({
+      Comp: SamplerSmallView,
+      getProps: () => ({
+        initialParams: this.params,
+        onChange: (params: SamplerParams) => this.setParams(params),
+      }),
+    });
+
+    this.cleanupSmallView = mkContainerCleanupHelper();
+  }
+
+  private async initWorklet() {
+    await this.ctx.audioWorklet.addModule('/SamplerNodeProcessor.js');
+    this.workletHandle = new AudioWorkletNode(this.ctx, 'sampler-node-processor', {
+      numberOfInputs: 0,
+      outputChannelCount: [2],
+    });
+
+    const moduleBytes = await fetch('./sampler.wasm').then(res => res.arrayBuffer());
+    this.workletHandle.port.postMessage({ type: 'init', arrayBuffer: moduleBytes });
+
+    return this.workletHandle;
+  }
+
+  private getMIDIInputCbs = (): MIDIInputCbs => ({
+    onAttack: (note, voiceIx, velocity, offset) =>
+      this.workletHandle?.port.postMessage({
+        type: 'attack',
+        voiceIx,
+        noteId: note,
+        velocity: Math.min(velocity, 255),
+        time: this.ctx.currentTime + (offset || 0),
+      }),
+    onRelease: (note, voiceIx, _velocity, offset) =>
+      this.workletHandle?.port.postMessage({
+        type: 'release',
+        voiceIx,
+        noteId: note,
+        time: this.ctx.currentTime + (offset || 0),
+      }),
+    onPitchBend: () => {
+      // Not implemented
+    },
+    onClearAll: () => this.workletHandle?.port.postMessage({ type: 'releaseAll' }),
+  });
+
+  private async loadSample(descriptor: SampleDescriptor) {
+    let sample: ImportedSample;
+    try {
+      // Samples are converted to the context's sample rate when they're imported so that the
+      // sampler doesn't have to resample them while playing
+      sample = await importSample(descriptor, this.ctx.sampleRate);
+    } catch (err) {
+      console.error(`Unable to load sample "${descriptor.name}" for sampler: `, err);
+      return;
+    }
+    // The sample may have been changed while it was loading
+    if (!this.workletHandle || this.params.sample?.name !== descriptor.name) {
+      releaseImportedSample(sample.id);
+      return;
+    }
+
+    if (this.importedSampleId !== null) {
+      releaseImportedSample(this.importedSampleId);
+    }
+    this.importedSampleId = sample.id;
+
+    // The channels are views into a single buffer, so each is copied into its own to be transferred
+    const channels = sample.channels.map(channel => channel.slice());
+    this.workletHandle.port.postMessage(
+      { type: 'setSample', channels, sampleRate: sample.sampleRate },
+      channels.map(channel => channel.buffer)
+    );
+    this.sampleLength = channels[0]?.length ?? 0;
+    this.sendParams();
+  }
+
+  private sendParams() {
+    if (!this.workletHandle) {
+      return;
+    }
+
+    const {
+      rootNote,
+      looping,
+      loopStart,
+      loopEnd,
+      releaseSeconds,
+      slices,
+      sliceBaseNote,
+    } = this.params;
+    this.workletHandle.port.postMessage({
+      type: 'setParams',
+      params: {
+        rootNote,
+        looping,
+        loopStart: Math.floor(Math.min(loopStart, loopEnd) * this.sampleLength),
+        loopEnd: Math.ceil(Math.max(loopStart, loopEnd) * this.sampleLength),
+        releaseSeconds,
+        slices: slices.map(slice => Math.round(slice * this.sampleLength)),
+        sliceBaseNote,
+      },
+    });
+  }
+
+  public setParams(params: SamplerParams) {
+    this.params = params;
+    if (this.workletHandle && params.sample && params.sample.name !== this.requestedSampleName) {
+      this.requestedSampleName = params.sample.name;
+      this.loadSample(params.sample);
+    }
+    this.sendParams();
+  }
+
+  public serialize(): { [key: string]: any } {
+    return this.params;
+  }
+
+  public buildConnectables(): AudioConnectables & { node: ForeignNode } {
+    return {
+      vcId: this.vcId,
+      inputs: Map<string, ConnectableInput>().set('midi', { node: this.midiNode, type: 'midi' }),
+      outputs: Map<string, ConnectableOutput>().set('output', {
+        node: this.outputNode,
+        type: 'customAudio',
+      }),
+      node: this,
+    };
+  }
+
+  public renderSmallView: ForeignNode['renderSmallView'];
+  public cleanupSmallView: ForeignNode['cleanupSmallView'];
+}
@@ -0,0 +1,85 @@
+import React, { useState } from 'react';
+import ControlPanel from 'react-control-panel';
+
+import { renderModalWithControls } from 'src/controls/Modal';
+import { SampleDescriptor } from 'src/sampleLibrary';
+import SampleSelectDialog from 'src/sampleLibrary/SampleLibraryUI/SelectSample';
+import { SamplerParams } from 'src/graphEditor/nodes/CustomAudio/Sampler/Sampler';
+import { saveRecordingAsSample } from 'src/audioRecorder';
+
+const SETTINGS = [
+  { type: 'range', label: 'root note', min: 0, max: 127, step: 1 },
+  { type: 'checkbox', label: 'loop' },
+  { type: 'range', label: 'loop start', min: 0, max: 1 },
+  { type: 'range', label: 'loop end', min: 0, max: 1 },
+  { type: 'range', label: 'release', min: 0.001, max: 5, scale: 'log' },
+  { type: 'range', label: 'slice base note', min: 0, max: 127, step: 1 },
+];
+
+const KEYS: { [label: string]: keyof SamplerParams } = {
+  'root note': 'rootNote',
+  loop: 'looping',
+  'loop start': 'loopStart',
+  'loop end': 'loopEnd',
+  release: 'releaseSeconds',
+  'slice base note': 'sliceBaseNote',
+};
+
+const selectSample = (): Promise<SampleDescriptor> => renderModalWithControls(SampleSelectDialog);
+
+const SamplerSmallView: React.FC<{
+  initialParams: SamplerParams;
+  onChange: (params: SamplerParams) => void;
+}> = ({ initialParams, onChange }) => {
+  const [params, setParams] = useState(initialParams);
+  const updateParams = (newParams: SamplerParams) => {
+    setParams(newParams);
+    onChange(newParams);
+  };
+
+  return (
+    <div>
+      <div>
+        Sample: {params.sample ? params.sample.name : 'None'}
+        <button
+          onClick={async () => {
+            try {
+              // Slices only apply to the sample that they were detected in
+              updateParams({ ...params, sample: await selectSample(), slices: [] });
+            } catch (_err) {
+              // The sample selection dialog was canceled
+            }
+          }}
+        >
+          Pick Sample
+        </button>
+        <button
+          onClick={async () => {
+            try {
+              updateParams({ ...params, sample: await saveRecordingAsSample(), slices: [] });
+            } catch (err) {
+              console.error('Unable to load recording into sampler: ', err);
+            }
+          }}
+        >
+          Use Recording
+        </button>
+      </div>
+      <ControlPanel
+        style={{ width: 500 }}
+        settings={SETTINGS}
+        state={{
+          'root note': params.rootNote,
+          loop: params.looping,
+          'loop start': params.loopStart,
+          'loop end': params.loopEnd,
+          release: params.releaseSeconds,
+          'slice base note': params.sliceBaseNote,
+        }}
+        onChange={(key: string, val: any) => updateParams({ ...params, [KEYS[key]]: val })}
+      />
+    </div>
+  );
+};
+
+export default SamplerSmallView;
@@ -0,0 +1,238 @@
+import { Map } from 'immutable';
+
+import { getEngine } from 'src';
+import { ForeignNode } from 'src/graphEditor/nodes/CustomAudio';
+import { AudioConnectables, ConnectableInput, ConnectableOutput } from 'src/patchNetwork';
+import { MIDINode, buildMIDINode, MIDIInputCbs } from 'src/patchNetwork/midiNode';
+import { mkContainerRenderHelper, mkContainerCleanupHelper } from 'src/reactUtils';
+import { SampleDescriptor, getSampleData, hashSampleDescriptor } from 'src/sampleLibrary';
+import { releaseImportedSample } from 'src/sampleLibrary/sampleImport';
+import SoundFontSmallView from './SoundFontUI';
+
+export interface SoundFontParams {
+  /**
+   * The .sf2 file, which is picked from the sample library like any other sample
+   */
+  soundFont: SampleDescriptor | null;
+  bank: number;
+  program: number;
+}
+
+const DEFAULT_SOUNDFONT_PARAMS: SoundFontParams = {
+  soundFont: null,
+  bank: 0,
+  program: 0,
+};
+
+export interface SoundFontPreset {
+  name: string;
+  bank: number;
+  program: number;
+}
+
+/**
+ * Mirrors `LoadedPreset` in the engine's `soundfont` module
+ */
+interface LoadedPreset {
+  regions: { [key: string]: number | string }[];
+  samples: { poolId: number; sampleRate: number }[];
+}
+
+/**
+ * Plays presets from SoundFont (.sf2) files.  The engine parses the file and resolves the chosen
+ * preset into regions that each map a range of keys and velocities onto a sample; those regions and
+ * samples are handed to a player implemented in Wasm and run inside of an `AudioWorkletProcessor`.
+ * It's driven over MIDI in the same way as the sampler.
+ */
+export class SoundFont implements ForeignNode {
+  private ctx: AudioContext;
+  private vcId: string;
+  private params: SoundFontParams;
+  private midiNode: MIDINode;
+  private workletHandle: AudioWorkletNode | undefined;
+  /**
+   * Created immediately so that connections can be made to it before the worklet finishes loading
+   */
+  private outputNode: GainNode;
+  /**
+   * ID of the loaded SoundFont in the engine's SoundFont registry, which is released when it's
+   * replaced
+   */
+  private soundFontId: number | null = null;
+  /**
+   * Name of the SoundFont that was most recently loaded or is currently being loaded along with
+   * the load itself, which resolves to the SoundFont's presets
+   */
+  private requestedSoundFontName: string | null = null;
+  private soundFontLoad: Promise<SoundFontPreset[]> = Promise.resolve([]);
+  /**
+   * IDs of the samples of the current preset in the engine's sample pool
+   */
+  private presetSampleIds: number[] = [];
+
+  public nodeType = 'customAudio/soundFont';
+  public name = 'SoundFont';
+
+  /**
+   * See the docs for `enhanceAudioNode`.
+   */
+  public paramOverrides: ForeignNode['paramOverrides'] = {};
+
+  constructor(ctx: AudioContext, vcId: string, params?: { [key: string]: any } | null) {
+    this.ctx = ctx;
+    this.vcId = vcId;
+    this.params = { ...DEFAULT_SOUNDFONT_PARAMS, ...(params || {}) };
+    this.outputNode = new GainNode(ctx);
+    this.midiNode = buildMIDINode(this.getMIDIInputCbs);
+
+    this.initWorklet().then(workletHandle => {
+      workletHandle.connect(this.outputNode);
+      this.setParams(this.params);
+    });
+
+    this.renderSmallView = mkContainerRenderHelper({
+      Comp: SoundFontSmallView,
+      getProps: () => ({
+        initialParams: this.params,
+        getPresets: () => this.soundFontLoad,
+        onChange: (params: SoundFontParams) => this.setParams(params),
+      }),
+    });
+
+    this.cleanupSmallView = mkContainerCleanupHelper();
+  }
+
+  private async initWorklet() {
+    await this.ctx.audioWorklet.addModule('/SoundFontNodeProcessor.js');
+    this.workletHandle = new AudioWorkletNode(this.ctx, 'soundfont-node-processor', {
+      numberOfInputs: 0,
+      outputChannelCount: [2],
+    });
+
+    // The player lives in the same Wasm module as the sampler
+    const moduleBytes = await fetch('./sampler.wasm').then(res => res.arrayBuffer());
+    this.workletHandle.port.postMessage({ type: 'init', arrayBuffer: moduleBytes });
+
+    return this.workletHandle;
+  }
+
+  private getMIDIInputCbs = (): MIDIInputCbs => ({
+    onAttack: (note, voiceIx, velocity, offset) =>
+      this.workletHandle?.port.postMessage({
+        type: 'attack',
+        voiceIx,
+        noteId: note,
+        velocity: Math.min(velocity, 127),
+        time: this.ctx.currentTime + (offset || 0),
+      }),
+    onRelease: (note, voiceIx, _velocity, offset) =>
+      this.workletHandle?.port.postMessage({
+        type: 'release',
+        voiceIx,
+        noteId: note,
+        time: this.ctx.currentTime + (offset || 0),
+      }),
+    onPitchBend: () => {
+      // Not implemented
+    },
+    onClearAll: () => this.workletHandle?.port.postMessage({ type: 'releaseAll' }),
+  });
+
+  /**
+   * Loads the SoundFont into the engine's registry, re-using the already loaded copy if another
+   * player has loaded it before, and returns its presets
+   */
+  private async loadSoundFont(descriptor: SampleDescriptor): Promise<SoundFontPreset[]> {
+    const engine = getEngine()!;
+    const name = hashSampleDescriptor(descriptor);
+    let id: number | undefined = engine.acquire_soundfont(name);
+    if (id === undefined) {
+      const bytes = new Uint8Array(await getSampleData(descriptor));
+      id = engine.load_soundfont(name, bytes);
+    }
+    if (id === undefined) {
+      throw new Error(`Failed to load SoundFont "${descriptor.name}"; see the console for details`);
+    }
+    // The SoundFont may have been changed while it was loading
+    if (this.params.soundFont?.name !== descriptor.name) {
+      engine.release_soundfont(id);
+      return [];
+    }
+
+    if (this.soundFontId !== null) {
+      engine.release_soundfont(this.soundFontId);
+    }
+    this.soundFontId = id;
+    this.loadPreset();
+    return JSON.parse(engine.get_soundfont_presets(id));
+  }
+
+  private loadPreset() {
+    if (!this.workletHandle || this.soundFontId === null) {
+      return;
+    }
+
+    const engine = getEngine()!;
+    const { bank, program } = this.params;
+    const presetJson: string | undefined = engine.load_soundfont_preset(
+      this.soundFontId,
+      bank,
+      program
+    );
+    if (presetJson === undefined) {
+      console.error(`Unable to load preset ${bank}:${program}; see the console for details`);
+      return;
+    }
+    const preset: LoadedPreset = JSON.parse(presetJson);
+
+    const samples = preset.samples.map(({ poolId, sampleRate }) => ({
+      data: engine.get_imported_sample_planar(poolId) as Float32Array,
+      sampleRate,
+    }));
+    this.workletHandle.port.postMessage(
+      { type: 'setPreset', regions: preset.regions, samples },
+      samples.map(sample => sample.data.buffer)
+    );
+
+    this.presetSampleIds.forEach(releaseImportedSample);
+    this.presetSampleIds = preset.samples.map(sample => sample.poolId);
+  }
+
+  public setParams(params: SoundFontParams) {
+    const presetChanged =
+      params.bank !== this.params.bank || params.program !== this.params.program;
+    this.params = params;
+    if (!this.workletHandle || !params.soundFont) {
+      return;
+    }
+
+    if (params.soundFont.name !== this.requestedSoundFontName) {
+      this.requestedSoundFontName = params.soundFont.name;
+      this.soundFontLoad = this.loadSoundFont(params.soundFont).catch(err => {
+        console.error(`Unable to load SoundFont "${params.soundFont?.name}": `, err);
+        return [];
+      });
+    } else if (presetChanged) {
+      this.loadPreset();
+    }
+  }
+
+  public serialize(): { [key: string]: any } {
+    return this.params;
+  }
+
+  public buildConnectables(): AudioConnectables & { node: ForeignNode } {
+    return {
+      vcId: this.vcId,
+      inputs: Map<string, ConnectableInput>().set('midi', { node: this.midiNode, type: 'midi' }),
+      outputs: Map<string, ConnectableOutput>().set('output', {
+        node: this.outputNode,
+        type: 'customAudio',
+      }),
+      node: this,
+    };
+  }
+
+  public renderSmallView: ForeignNode['renderSmallView'];
+  public cleanupSmallView: ForeignNode['cleanupSmallView'];
+}
@@ -0,0 +1,81 @@
+import React, { useState, useEffect } from 'react';
+
+import { renderModalWithControls } from 'src/controls/Modal';
+import { SampleDescriptor } from 'src/sampleLibrary';
+import SampleSelectDialog from 'src/sampleLibrary/SampleLibraryUI/SelectSample';
+import {
+  SoundFontParams,
+  SoundFontPreset,
+} from 'src/graphEditor/nodes/CustomAudio/SoundFont/SoundFont';
+
+const selectSoundFont = (): Promise<SampleDescriptor> =>
+  renderModalWithControls(SampleSelectDialog);
+
+const getPresetKey = ({ bank, program }: { bank: number; program: number }) =>
+  `${bank}:${program}`;
+
+const SoundFontSmallView: React.FC<{
+  initialParams: SoundFontParams;
+  getPresets: () => Promise<SoundFontPreset[]>;
+  onChange: (params: SoundFontParams) => void;
+}> = ({ initialParams, getPresets, onChange }) => {
+  const [params, setParams] = useState(initialParams);
+  const [presets, setPresets] = useState<SoundFontPreset[]>([]);
+  const updateParams = (newParams: SoundFontParams) => {
+    setParams(newParams);
+    onChange(newParams);
+  };
+
+  const soundFontName = params.soundFont?.name;
+  useEffect(() => {
+    let canceled = false;
+    getPresets().then(presets => {
+      if (!canceled) {
+        setPresets(presets);
+      }
+    });
+    return () => {
+      canceled = true;
+    };
+  }, [soundFontName, getPresets]);
+
+  return (
+    <div>
+      <div>
+        SoundFont: {soundFontName || 'None'}
+        <button
+          onClick={async () => {
+            try {
+              // Bank 0 program 0 is the first preset of most SoundFonts
+              updateParams({ soundFont: await selectSoundFont(), bank: 0, program: 0 });
+            } catch (_err) {
+              // The sample selection dialog was canceled
+            }
+          }}
+        >
+          Pick SoundFont
+        </button>
+      </div>
+      <div>
+        Preset:{' '}
+        <select
+          value={getPresetKey(params)}
+          onChange={evt => {
+            const preset = presets.find(preset => getPresetKey(preset) === evt.target.value);
+            if (preset) {
+              updateParams({ ...params, bank: preset.bank, program: preset.program });
+            }
+          }}
+        >
+          {presets.map(preset => (
+            <option key={getPresetKey(preset)} value={getPresetKey(preset)}>
+              {getPresetKey(preset)} {preset.name}
+            </option>
+          ))}
+        </select>
+      </div>
+    </div>
+  );
+};
+
+export default SoundFontSmallView;
@@ -51,6 +51,11 @@ export class MIDIInputNode {
     throw new Error("Tried to get input callbacks for `MIDIInput` but it doesn't accept inputs");
   });
 
+  /**
+   * Outputs that accept raw MIDI messages get those instead of the parsed events
+   */
+  private getParsedOutputCbs = () => this.midiNode.outputCbs.filter(cbs => !cbs.onRawMessage);
+
   private async initMIDI() {
     let access: PromiseResolveType<ReturnType<typeof navigator.requestMIDIAccess>>;
     let midiModule: typeof import('src/midi');
@@ -100,21 +105,31 @@ export class MIDIInputNode {
     // to be called appropriately.
     const ctxPtr = midiModule.create_msg_handler_context(
       (voiceIx: number, note: number, velocity: number) =>
-        this.midiNode.outputCbs.forEach(({ onAttack }) => onAttack(note, voiceIx, velocity)),
+        this.getParsedOutputCbs().forEach(({ onAttack }) => onAttack(note, voiceIx, velocity)),
       (voiceIx: number, note: number, velocity: number) =>
-        this.midiNode.outputCbs.forEach(({ onRelease }) => onRelease(note, voiceIx, velocity)),
+        this.getParsedOutputCbs().forEach(({ onRelease }) => onRelease(note, voiceIx, velocity)),
       (_lsb: number, msb: number) => {
         this.pitchBendNode.offset.value = msb;
-        this.midiNode.outputCbs.forEach(({ onPitchBend }) => onPitchBend(msb));
+        this.getParsedOutputCbs().forEach(({ onPitchBend }) => onPitchBend(msb));
       },
       (modWheelValue: number) => {
         this.modWheelNode.offset.value = modWheelValue;
-      }
+      },
+      (controlIndex: number, value: number) =>
+        this.getParsedOutputCbs().forEach(
+          ({ onGenericControl }) => onGenericControl && onGenericControl(controlIndex, value)
+        )
     );
     this.wasmMidiCtxPtr = ctxPtr;
 
-    const midiMsgHandlerCb = (evt: Event & { data: Uint8Array }) =>
+    const midiMsgHandlerCb = (evt: Event & { data: Uint8Array }) => {
+      if (evt.data.length === 2 || evt.data.length === 3) {
+        this.midiNode.outputCbs.forEach(
+          ({ onRawMessage }) => onRawMessage && onRawMessage(evt.data)
+        );
+      }
       midiModule.handle_midi_evt(evt.data, ctxPtr);
+    };
     input.addEventListener('midimessage', midiMsgHandlerCb);
 
     this.midiInput = input;
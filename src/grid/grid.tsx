@@ -20,6 +20,7 @@ export const init_grid = (vcId: string) => {
 
   const gridElement = document.createElement('div');
   gridElement.id = buildGridDOMID(vcId);
+  gridElement.className = 'grid';
   gridElement.setAttribute('width', '100vh');
   const canvasesWrapperElement = document.createElement('div');
   gridElement.append(canvasesWrapperElement);
@@ -38,6 +39,11 @@ export const init_grid = (vcId: string) => {
   foregroundCanvas.id = 'foreground-svg';
   canvasesWrapperElement.append(backgroundCanvas);
   canvasesWrapperElement.append(foregroundCanvas);
+  // Stays at the left edge of the grid while it's scrolled horizontally.  Double-clicking it
+  // selects every note on the line next to it.
+  const rowHeaderElement = document.createElement('div');
+  rowHeaderElement.className = 'grid-row-header';
+  gridElement.append(rowHeaderElement);
 
   const contentElement = document.getElementById('content');
   if (!contentElement) {
@@ -49,12 +55,39 @@ export const init_grid = (vcId: string) => {
 
   const scrollOffset = () => Math.max(gridElement.scrollTop - 2, 0);
 
+  // Pointer events are used rather than mouse events so that the pressure and tilt of pens can be
+  // passed along.  Touches are handled separately below.
+  const getPenArgs = (evt: PointerEvent): [number | undefined, number, number] =>
+    evt.pointerType === 'pen' ? [evt.pressure, evt.tiltX, evt.tiltY] : [undefined, 0, 0];
+
   let mouseDown = false;
-  foregroundCanvas.addEventListener('mousedown', evt => {
+  // Holds the last x position of the mouse while panning the grid with the middle mouse button
+  let panningLastX: number | null = null;
+  foregroundCanvas.addEventListener('pointerdown', evt => {
+    if (evt.pointerType === 'touch') {
+      return;
+    }
+    if (evt.button === 1) {
+      evt.preventDefault();
+      panningLastX = evt.pageX;
+      return;
+    }
+
     mouseDown = true;
-    engine.handle_mouse_down(evt.pageX, evt.pageY - CONTENT_OFFSET_TOP + scrollOffset());
+    engine.handle_mouse_down(
+      evt.pageX,
+      evt.pageY - CONTENT_OFFSET_TOP + scrollOffset(),
+      ...getPenArgs(evt)
+    );
   });
-  foregroundCanvas.addEventListener('mouseup', evt => {
+  foregroundCanvas.addEventListener('pointerup', evt => {
+    if (evt.pointerType === 'touch') {
+      return;
+    }
+    if (evt.button === 1) {
+      panningLastX = null;
+      return;
+    }
     if (!mouseDown) {
       return;
     }
@@ -62,12 +95,65 @@ export const init_grid = (vcId: string) => {
 
     engine.handle_mouse_up(evt.pageX, evt.pageY - CONTENT_OFFSET_TOP + scrollOffset());
   });
-  foregroundCanvas.addEventListener('mousemove', evt =>
-    engine.handle_mouse_move(evt.pageX, evt.pageY - CONTENT_OFFSET_TOP + scrollOffset())
+  foregroundCanvas.addEventListener('pointermove', evt => {
+    if (evt.pointerType === 'touch') {
+      return;
+    }
+    if (panningLastX !== null) {
+      const diff = new Float64Array([panningLastX - evt.pageX]);
+      panningLastX = evt.pageX;
+      engine.handle_message('scroll_by_px', new Uint8Array(diff.buffer));
+      return;
+    }
+
+    engine.handle_mouse_move(
+      evt.pageX,
+      evt.pageY - CONTENT_OFFSET_TOP + scrollOffset(),
+      ...getPenArgs(evt)
+    );
+  });
+  foregroundCanvas.addEventListener(
+    'wheel',
+    evt => {
+      // Alt + wheel adjusts the velocity of selected notes
+      if (evt.altKey) {
+        evt.preventDefault();
+        const velocityDiff = new Int8Array([evt.deltaY < 0 ? 8 : -8]);
+        engine.handle_message('adjust_velocity', new Uint8Array(velocityDiff.buffer));
+        return;
+      }
+
+      // Control + wheel is used for zooming the grid rather than the page
+      if (evt.ctrlKey) {
+        evt.preventDefault();
+      }
+      // Browsers report shift + wheel as horizontal scrolling
+      engine.handle_mouse_wheel(evt.deltaY || evt.deltaX);
+    },
+    { passive: false }
   );
-  foregroundCanvas.addEventListener('wheel', evt => engine.handle_mouse_wheel(evt.deltaX));
   foregroundCanvas.addEventListener('contextmenu', evt => evt.preventDefault());
 
+  // Touches are handled separately from the mouse since several of them can be active at once.
+  // Preventing the default action of `pointerdown` stops the browser from emulating the mouse.
+  const mkTouchHandler = (handle: (pointerId: number, x: number, y: number) => void) => (
+    evt: PointerEvent
+  ) => {
+    if (evt.pointerType !== 'touch') {
+      return;
+    }
+    evt.preventDefault();
+    handle(evt.pointerId, evt.pageX, evt.pageY - CONTENT_OFFSET_TOP + scrollOffset());
+  };
+  foregroundCanvas.addEventListener('pointerdown', mkTouchHandler(engine.handle_touch_start));
+  foregroundCanvas.addEventListener('pointermove', mkTouchHandler(engine.handle_touch_move));
+  foregroundCanvas.addEventListener('pointerup', mkTouchHandler(engine.handle_touch_end));
+  foregroundCanvas.addEventListener('pointercancel', mkTouchHandler(engine.handle_touch_end));
+  rowHeaderElement.addEventListener('dblclick', evt => {
+    const y = new Float64Array([evt.pageY - CONTENT_OFFSET_TOP + scrollOffset()]);
+    engine.handle_message('select_line_at', new Uint8Array(y.buffer));
+  });
+
   document.body.addEventListener('mouseleave', evt => {
     if (mouseDown) {
       engine.handle_mouse_up(evt.pageX, evt.pageY - CONTENT_OFFSET_TOP + scrollOffset());
@@ -76,11 +162,18 @@ export const init_grid = (vcId: string) => {
 
   document.addEventListener('keydown', evt => {
     engine.handle_key_down(evt.key, evt.ctrlKey, evt.shiftKey);
-    // Prevent spacebar from scrolling down the page
+    // Prevent spacebar from scrolling down the page and alt from focusing the browser's menu
     if (
-      ['Space', 'ArrowLeft', 'ArrowRight', 'ArrowUp', 'ArrowDown', 'Backspace'].includes(
-        evt.code
-      ) &&
+      [
+        'Space',
+        'ArrowLeft',
+        'ArrowRight',
+        'ArrowUp',
+        'ArrowDown',
+        'Backspace',
+        'AltLeft',
+        'AltRight',
+      ].includes(evt.code) &&
       !(evt.target instanceof HTMLInputElement || evt.target instanceof HTMLTextAreaElement)
     ) {
       evt.preventDefault();
@@ -101,6 +194,69 @@ export const unhide_grid = (vcId: string) => {
   document.getElementById(buildGridDOMID(vcId))!.style.display = 'block';
 };
 
+/**
+ * Shifts the rendered grid to the left by `x` pixels, used for horizontally scrolling the grid.
+ * `x` can be fractional.
+ */
+export const set_grid_scroll_offset = (vcId: string, x: number) => {
+  const gridElement = document.getElementById(buildGridDOMID(vcId));
+  const canvasesWrapper = gridElement?.querySelector<HTMLDivElement>('#canvases-wrapper');
+  if (!canvasesWrapper) {
+    return;
+  }
+
+  canvasesWrapper.style.transform = `translateX(${-x}px)`;
+};
+
+/**
+ * Sizes the rendered grid to fit all of its rows, scrolling it vertically if it's taller than the
+ * page.
+ */
+export const set_grid_height = (vcId: string, height: number) => {
+  const gridElement = document.getElementById(buildGridDOMID(vcId));
+  if (!gridElement) {
+    return;
+  }
+
+  gridElement
+    .querySelectorAll<SVGSVGElement>('svg.notes')
+    .forEach(svg => svg.setAttribute('height', height.toString()));
+  const canvasesWrapper = gridElement.querySelector<HTMLDivElement>('#canvases-wrapper')!;
+  canvasesWrapper.style.height = `${height}px`;
+  gridElement.querySelector<HTMLDivElement>('.grid-row-header')!.style.height = `${height}px`;
+};
+
+/**
+ * Labels the lines of the grid in its row header.  `top` is the y position of the first line and
+ * `lineHeight` is the distance between the tops of consecutive lines.  An empty list of labels
+ * removes them.
+ */
+export const set_grid_line_labels = (
+  vcId: string,
+  labelsJson: string,
+  top: number,
+  lineHeight: number
+) => {
+  const rowHeader = document
+    .getElementById(buildGridDOMID(vcId))
+    ?.querySelector<HTMLDivElement>('.grid-row-header');
+  if (!rowHeader) {
+    return;
+  }
+
+  rowHeader.innerHTML = '';
+  const labels: string[] = JSON.parse(labelsJson);
+  labels.forEach((label, lineIx) => {
+    const labelElement = document.createElement('div');
+    labelElement.className = 'grid-line-label';
+    labelElement.textContent = label;
+    labelElement.style.top = `${top + lineIx * lineHeight}px`;
+    labelElement.style.height = `${lineHeight}px`;
+    labelElement.style.lineHeight = `${lineHeight}px`;
+    rowHeader.append(labelElement);
+  });
+};
+
 export const get_active_attr = (key: string): string | null => ACTIVE_SHAPE.getAttribute(key);
 
 /**
@@ -184,6 +340,11 @@ export const render_line = renderHelper(
   })
 );
 
+export const render_polygon = renderHelper((points: string, className: string) => ({
+  name: 'polygon',
+  attrs: { points, class: className },
+}));
+
 export const delete_element = (id: number): void => {
   const elem = getElem(id);
   elem.parentNode!.removeChild(elem);
@@ -9,6 +9,11 @@ import { commitForeignConnectables } from 'src/redux/modules/viewContextManager'
 import { tryParseJson } from 'src/util';
 import { ConnectableDescriptor } from 'src/patchNetwork';
 import BrowserNotSupported from 'src/misc/BrowserNotSupported';
+import KeymapHelp from 'src/misc/KeymapHelp';
+import { renderModalWithControls } from 'src/controls/Modal';
+import { loadIndexedDBStorage } from 'src/storage';
+
+const AUTOSAVE_CHECK_INTERVAL_MS = 1000;
 
 let engineHandle: typeof import('./engine');
 
@@ -30,6 +35,32 @@ const createViewContextManager = (engine: typeof import('./engine')) => {
   );
 };
 
+let keymapHelpOpen = false;
+
+/**
+ * Opens the list of keyboard shortcuts for the active view context when "?" is pressed
+ */
+const registerKeymapHelpHandler = () =>
+  document.addEventListener('keydown', evt => {
+    if (
+      evt.key !== '?' ||
+      keymapHelpOpen ||
+      evt.target instanceof HTMLInputElement ||
+      evt.target instanceof HTMLTextAreaElement
+    ) {
+      return;
+    }
+
+    keymapHelpOpen = true;
+    renderModalWithControls(KeymapHelp)
+      .catch(() => {
+        // Closing the help rejects the promise since it has nothing to return
+      })
+      .finally(() => {
+        keymapHelpOpen = false;
+      });
+  });
+
 const createBrowserNotSupportedMessage = () => {
   const body = document.getElementsByTagName('body')[0];
   while (body.children.length > 0) {
@@ -98,8 +129,11 @@ export const set_active_vc_ix = (newActiveVxIx: number) =>
 if (typeof AudioWorkletNode === 'undefined') {
   createBrowserNotSupportedMessage();
 } else {
-  wasm.then(engine => {
+  wasm.then(async engine => {
     engineHandle = engine;
+    if (await loadIndexedDBStorage()) {
+      engine.use_indexeddb_storage();
+    }
     engine.init();
 
     window.addEventListener('beforeunload', () => {
@@ -114,5 +148,25 @@ if (typeof AudioWorkletNode === 'undefined') {
     });
 
     createViewContextManager(engine);
+    registerKeymapHelpHandler();
+
+    // Let VCs know the size of the area that they're rendered into so that they can lay themselves
+    // out to fit it, and when the page is hidden so that they can pause rendering
+    const reportContentSize = () => {
+      const contentElement = document.getElementById('content');
+      if (!contentElement) {
+        return;
+      }
+      const { width, top } = contentElement.getBoundingClientRect();
+      engine.handle_resize(Math.round(width), Math.max(Math.round(window.innerHeight - top), 0));
+    };
+    reportContentSize();
+    window.addEventListener('resize', reportContentSize);
+    document.addEventListener('visibilitychange', () =>
+      engine.handle_visibility_change(document.visibilityState === 'visible')
+    );
+
+    // The engine throttles saves itself, so this can run more often than they actually happen
+    setInterval(() => engine.autosave(performance.now()), AUTOSAVE_CHECK_INTERVAL_MS);
   });
 }
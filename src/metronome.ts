@@ -0,0 +1,69 @@
+import { getEngine } from 'src';
+
+/**
+ * Plays metronome clicks scheduled by the engine.  Clicks are routed directly to the destination
+ * rather than through the global volume so that they aren't captured when recording the master
+ * output.
+ */
+
+const ctx = new AudioContext();
+
+let clickBuffers: { normal: AudioBuffer; accent: AudioBuffer } | null = null;
+const scheduledClicksByVcId: Map<string, Set<AudioBufferSourceNode>> = new Map();
+
+const buildClickBuffer = (isAccent: boolean): AudioBuffer => {
+  const samples: Float32Array = getEngine()!.synthesize_metronome_click(ctx.sampleRate, isAccent);
+  const buffer = ctx.createBuffer(1, samples.length, ctx.sampleRate);
+  buffer.copyToChannel(samples, 0);
+  return buffer;
+};
+
+const getClickBuffers = () => {
+  if (!clickBuffers) {
+    clickBuffers = { normal: buildClickBuffer(false), accent: buildClickBuffer(true) };
+  }
+  return clickBuffers;
+};
+
+export const scheduleMetronomeClicks = (
+  vcId: string,
+  timings: Float64Array,
+  isAccentFlags: Uint8Array,
+  volume: number
+) => {
+  const buffers = getClickBuffers();
+  const gain = new GainNode(ctx, { gain: volume });
+  gain.connect(ctx.destination);
+
+  let scheduledClicks = scheduledClicksByVcId.get(vcId);
+  if (!scheduledClicks) {
+    scheduledClicks = new Set();
+    scheduledClicksByVcId.set(vcId, scheduledClicks);
+  }
+
+  timings.forEach((time, i) => {
+    const source = new AudioBufferSourceNode(ctx, {
+      buffer: isAccentFlags[i] ? buffers.accent : buffers.normal,
+    });
+    source.connect(gain);
+    source.onended = () => scheduledClicks!.delete(source);
+    scheduledClicks!.add(source);
+    source.start(Math.max(time, ctx.currentTime));
+  });
+};
+
+/**
+ * Stops all clicks that have been scheduled for the provided view context and haven't played yet
+ */
+export const cancelMetronomeClicks = (vcId: string) => {
+  const scheduledClicks = scheduledClicksByVcId.get(vcId);
+  if (!scheduledClicks) {
+    return;
+  }
+
+  scheduledClicks.forEach(source => {
+    source.onended = null;
+    source.stop();
+  });
+  scheduledClicksByVcId.delete(vcId);
+};
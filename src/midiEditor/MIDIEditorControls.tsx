@@ -1,5 +1,5 @@
 import * as R from 'ramda';
-import React, { useMemo, useState } from 'react';
+import React, { useEffect, useMemo, useRef, useState } from 'react';
 import ControlPanel from 'react-control-panel';
 import downloadjs from 'downloadjs';
 import { Option } from 'funfix-core';
@@ -7,14 +7,503 @@ import { Option } from 'funfix-core';
 import FileUploader, { Value as FileUploaderValue } from '../controls/FileUploader';
 import { MidiFileInfo, getMidiImportSettings } from '../controls/MidiImportDialog';
 import { MIDIEditorStateMap } from 'src/midiEditor';
+import { bounceSamplerToWav, getConnectedSampler } from 'src/midiEditor/bounce';
+import { getMIDIOutputPortNames } from 'src/midiEditor/midiOutput';
+import { getAutomatableParams } from 'src/midiEditor/automation';
+import { VoiceManagerConf, VoiceStealPolicy } from 'src/patchNetwork/voiceManagerWrapper';
+import { SamplerParams } from 'src/graphEditor/nodes/CustomAudio/Sampler';
+import { AllSynthDrums, SynthDrum } from 'src/drumSequencer/synthDrums';
 
 const ctx = new AudioContext();
 
+/**
+ * Length in beats of each of the snap divisions that can be selected.  Holding alt while editing
+ * temporarily disables snapping as well.
+ */
+const SnapIntervals: { [division: string]: number } = {
+  '1/1': 4,
+  '1/2': 2,
+  '1/4': 1,
+  '1/8': 1 / 2,
+  '1/16': 1 / 4,
+  '1/32': 1 / 8,
+  '1/4 triplet': 2 / 3,
+  '1/8 triplet': 1 / 3,
+  '1/16 triplet': 1 / 6,
+  off: 0,
+};
+
+interface MetronomeConf {
+  enabled: boolean;
+  volume: number;
+  countInBars: number;
+}
+
+interface MIDIOutputConf {
+  enabled: boolean;
+  portName: string | null;
+  channel: number;
+}
+
+const NoMIDIOutputPort = 'none';
+
+interface InstrumentAssignment {
+  kind: 'subtractive_synth' | 'fm_synth' | 'sampler' | 'sound_font';
+  id: string;
+}
+
+interface AvailableInstrument extends InstrumentAssignment {
+  title: string | null;
+}
+
+const InstrumentKindLabels: { [kind in InstrumentAssignment['kind']]: string } = {
+  subtractive_synth: 'synth designer',
+  fm_synth: 'fm synth',
+  sampler: 'sampler',
+  sound_font: 'soundfont',
+};
+
+const NoInstrument = 'none';
+
+const getInstrumentLabel = ({ kind, id, title }: AvailableInstrument) =>
+  `${title || InstrumentKindLabels[kind]} (${id.slice(0, 8)})`;
+
+const PitchClassNames = ['C', 'C#', 'D', 'Eb', 'E', 'F', 'F#', 'G', 'Ab', 'A', 'Bb', 'B'];
+
+/**
+ * Maps the labels of the scales that can be selected to the names the engine uses for them
+ */
+const Scales: { [label: string]: string } = {
+  chromatic: 'chromatic',
+  major: 'major',
+  'natural minor': 'naturalMinor',
+  'harmonic minor': 'harmonicMinor',
+  'melodic minor': 'melodicMinor',
+  dorian: 'dorian',
+  phrygian: 'phrygian',
+  lydian: 'lydian',
+  mixolydian: 'mixolydian',
+  locrian: 'locrian',
+  'major pentatonic': 'majorPentatonic',
+  'minor pentatonic': 'minorPentatonic',
+  blues: 'blues',
+};
+
+/**
+ * The chord shapes that can be inserted with the chord tool, which is toggled with the "h" key.
+ * Diatonic chords are built from the notes of the selected key.
+ */
+const ChordShapes: { [label: string]: object } = {
+  'diatonic triad': { type: 'diatonic', noteCount: 3 },
+  'diatonic seventh': { type: 'diatonic', noteCount: 4 },
+  major: { type: 'fixed', chord: 'major' },
+  minor: { type: 'fixed', chord: 'minor' },
+  diminished: { type: 'fixed', chord: 'diminished' },
+  augmented: { type: 'fixed', chord: 'augmented' },
+  sus2: { type: 'fixed', chord: 'sus2' },
+  sus4: { type: 'fixed', chord: 'sus4' },
+  'major 7': { type: 'fixed', chord: 'major7' },
+  'minor 7': { type: 'fixed', chord: 'minor7' },
+  'dominant 7': { type: 'fixed', chord: 'dominant7' },
+  'half-diminished 7': { type: 'fixed', chord: 'halfDiminished7' },
+  'diminished 7': { type: 'fixed', chord: 'diminished7' },
+};
+const CustomChordShape = 'custom';
+
+interface ScaleConf {
+  key: { tonic: number; scale: string };
+  snapToScale: boolean;
+}
+
+interface NoteRange {
+  lowestNote: number;
+  lineCount: number;
+}
+
+interface DrumSound {
+  name: string;
+  note: number;
+}
+
+interface NoteLayout {
+  range: NoteRange;
+  /**
+   * The notes shown by each line from the top down if the grid is folded to the used notes
+   */
+  foldedNotes: number[] | null;
+  /**
+   * The sound of each line from the top down if the grid is in drum map mode
+   */
+  drumMap: DrumSound[] | null;
+}
+
+/**
+ * The General MIDI percussion notes of the drum sequencer's synthesized drums
+ */
+const SynthDrumNotes: { [drum in SynthDrum]: number } = {
+  kick: 36,
+  snare: 38,
+  clap: 39,
+  closed_hat: 42,
+  open_hat: 46,
+};
+
+const buildSynthDrumMap = (): DrumSound[] =>
+  AllSynthDrums.map(drum => ({ name: drum.replace('_', ' '), note: SynthDrumNotes[drum] }));
+
+/**
+ * The connected sampler plays a single sample, so its drum map has one line at its root note
+ */
+const buildSamplerDrumMap = (vcId: string): DrumSound[] | null => {
+  const sampler = getConnectedSampler(vcId);
+  const params = sampler ? (sampler.serialize() as SamplerParams) : null;
+  if (!params?.sample) {
+    return null;
+  }
+  return [{ name: params.sample.name, note: params.rootNote }];
+};
+
+const DrumMaps = {
+  off: 'off',
+  synthDrums: 'synth drums',
+  sampler: 'connected sampler',
+};
+
+const ArpeggiatorPatterns: { [label: string]: string } = {
+  up: 'up',
+  down: 'down',
+  'up-down': 'upDown',
+  random: 'random',
+};
+
+const VoiceStealPolicies: { [label: string]: VoiceStealPolicy } = {
+  'steal oldest': 'stealOldest',
+  'steal quietest': 'stealQuietest',
+  'steal same pitch': 'stealSamePitch',
+  'drop new': 'dropNew',
+};
+
+/**
+ * Arpeggiator step lengths are the same divisions as the snap intervals
+ */
+const ArpeggiatorRates = R.omit(['off'], SnapIntervals);
+
+interface ArpeggiatorConf {
+  live: boolean;
+  pattern: string;
+  rate: number;
+  octaves: number;
+  gate: number;
+}
+
+const SwingDivisions: { [division: string]: number } = {
+  '1/8': 1 / 2,
+  '1/16': 1 / 4,
+};
+
+interface GrooveConf {
+  swing: number;
+  division: number;
+}
+
+interface MPEConf {
+  enabled: boolean;
+  pitchBendRange: number;
+}
+
+interface HumanizeConf {
+  maxStartOffset: number;
+  maxLengthOffset: number;
+  maxVelocityOffset: number;
+}
+
+interface AutomationLane {
+  target: { vcId: string; paramName: string };
+  controlIndex: number | null;
+}
+
+interface AutomationLanesInfo {
+  lanes: AutomationLane[];
+  activeLaneIx: number | null;
+  learningLaneIx: number | null;
+}
+
+interface ClipInstance {
+  clipId: string;
+  startBeat: number;
+  lengthBeats: number;
+}
+
+interface ClipsInfo {
+  clips: { id: string; name: string; lengthBeats: number }[];
+  activeClipIx: number;
+  arrangement: ClipInstance[];
+  playArrangement: boolean;
+}
+
+const buildClipLabel = (ix: number, name: string) => `${ix + 1}: ${name}`;
+
+const buildAutomationLaneLabel = (ix: number, { target, controlIndex }: AutomationLane) =>
+  `${ix + 1}: ${target.paramName} (${target.vcId.slice(0, 8)})` +
+  (R.isNil(controlIndex) ? '' : ` [CC ${controlIndex}]`);
+
+interface PitchBendPoint {
+  beatOffset: number;
+  semitones: number;
+}
+
+const VibratoPointsPerBeat = 8;
+
+/**
+ * Builds the pitch bend curves that can be applied to the selected notes, bending them by up to
+ * `amount` semitones.  Curves are cut off at the end of each note.
+ */
+const PitchBendPresets: { [name: string]: (amount: number) => PitchBendPoint[] } = {
+  none: () => [],
+  'glide up': amount => [{ beatOffset: 1, semitones: amount }],
+  'glide down': amount => [{ beatOffset: 1, semitones: -amount }],
+  vibrato: amount =>
+    R.range(1, 16 * VibratoPointsPerBeat).map(i => ({
+      beatOffset: i / VibratoPointsPerBeat,
+      semitones: [0, amount, 0, -amount][i % 4],
+    })),
+};
+
+const TimeSignatures = ['4/4', '3/4', '2/4', '5/4', '6/8', '7/8', '12/8'];
+
+/**
+ * Sends a message that edits the MIDI editor's tempo map.  All such messages are prefixed with the
+ * beat at which the change happens and the current time so that playback can be rescheduled.
+ */
+const sendTempoMapMessage = (
+  engine: typeof import('../engine'),
+  key: string,
+  beat: number,
+  extraBytes: number[] = []
+) => {
+  const buf = new Uint8Array(16 + extraBytes.length);
+  buf.set(new Uint8Array(new Float64Array([beat, ctx.currentTime]).buffer));
+  buf.set(extraBytes, 16);
+  engine.handle_message(key, buf);
+};
+
 const MIDIEditorControls: React.FC<{
   engine: typeof import('../engine');
   vcId: string;
 }> = ({ engine, vcId }) => {
   const [isRecordingMIDI, setIsRecordingMIDI] = useState(false);
+  const bounceSettings = useRef({ startBeat: 0, endBeat: 16, bitDepth: 16 as 16 | 24 });
+  const tempoSettings = useRef({ beat: 0, bpm: 120, timeSignature: '4/4' });
+  const metronomeConf = useRef<MetronomeConf | null>(null);
+  if (!metronomeConf.current) {
+    const confBytes = engine.handle_message('get_metronome_conf', new Uint8Array());
+    metronomeConf.current = JSON.parse(new TextDecoder().decode(confBytes));
+  }
+  const setMetronomeConf = (newConf: Partial<MetronomeConf>) => {
+    metronomeConf.current = { ...metronomeConf.current!, ...newConf };
+    const confBytes = new TextEncoder().encode(JSON.stringify(metronomeConf.current));
+    engine.handle_message('set_metronome_conf', confBytes);
+  };
+
+  const midiOutputConf = useRef<MIDIOutputConf | null>(null);
+  if (!midiOutputConf.current) {
+    const confBytes = engine.handle_message('get_midi_output_conf', new Uint8Array());
+    midiOutputConf.current = JSON.parse(new TextDecoder().decode(confBytes));
+  }
+  const setMIDIOutputConf = (newConf: Partial<MIDIOutputConf>) => {
+    midiOutputConf.current = { ...midiOutputConf.current!, ...newConf };
+    const confBytes = new TextEncoder().encode(JSON.stringify(midiOutputConf.current));
+    engine.handle_message('set_midi_output_conf', confBytes);
+  };
+
+  const availableInstruments = useMemo(
+    (): AvailableInstrument[] =>
+      JSON.parse(
+        new TextDecoder().decode(engine.handle_message('list_instruments', new Uint8Array()))
+      ),
+    [engine]
+  );
+  const initialInstrument = useMemo((): InstrumentAssignment | null => {
+    const instrumentBytes = engine.handle_message('get_instrument', new Uint8Array());
+    return JSON.parse(new TextDecoder().decode(instrumentBytes));
+  }, [engine]);
+  const setInstrument = (label: string) => {
+    const instrument = availableInstruments.find(
+      instrument => getInstrumentLabel(instrument) === label
+    );
+    const assignment: InstrumentAssignment | null = instrument
+      ? { kind: instrument.kind, id: instrument.id }
+      : null;
+    const assignmentBytes = new TextEncoder().encode(JSON.stringify(assignment));
+    engine.handle_message('set_instrument', assignmentBytes);
+  };
+
+  const scaleConf = useRef<ScaleConf | null>(null);
+  if (!scaleConf.current) {
+    const confBytes = engine.handle_message('get_scale_conf', new Uint8Array());
+    scaleConf.current = JSON.parse(new TextDecoder().decode(confBytes));
+  }
+  const setScaleConf = (newConf: Partial<ScaleConf>) => {
+    scaleConf.current = { ...scaleConf.current!, ...newConf };
+    const confBytes = new TextEncoder().encode(JSON.stringify(scaleConf.current));
+    engine.handle_message('set_scale_conf', confBytes);
+  };
+
+  const getNoteLayout = (): NoteLayout =>
+    JSON.parse(
+      new TextDecoder().decode(engine.handle_message('get_note_layout', new Uint8Array()))
+    );
+  const noteLayout = useRef<NoteLayout | null>(null);
+  if (!noteLayout.current) {
+    noteLayout.current = getNoteLayout();
+  }
+  // The engine refuses changes that would hide notes, in which case the current layout is kept
+  const setNoteRange = (newRange: Partial<NoteRange>) => {
+    const range = { ...noteLayout.current!.range, ...newRange };
+    const rangeBytes = new TextEncoder().encode(JSON.stringify(range));
+    const res = engine.handle_message('set_note_range', rangeBytes);
+    if (!res?.[0]) {
+      console.warn('Note range would hide existing notes or is invalid: ', range);
+    }
+    noteLayout.current = getNoteLayout();
+  };
+  const setFoldToUsedNotes = (fold: boolean) => {
+    engine.handle_message('set_fold_to_used_notes', new Uint8Array([fold ? 1 : 0]));
+    noteLayout.current = getNoteLayout();
+  };
+  const setDrumMap = (label: string) => {
+    let drumMap: DrumSound[] | null = null;
+    if (label === DrumMaps.synthDrums) {
+      drumMap = buildSynthDrumMap();
+    } else if (label === DrumMaps.sampler) {
+      drumMap = buildSamplerDrumMap(vcId);
+      if (!drumMap) {
+        console.warn('The MIDI editor must be connected to a sampler with a sample selected');
+        return;
+      }
+    }
+
+    const drumMapBytes = new TextEncoder().encode(JSON.stringify(drumMap));
+    const res = engine.handle_message('set_drum_map', drumMapBytes);
+    if (!res?.[0]) {
+      console.warn("Drum map doesn't include all existing notes or is invalid: ", drumMap);
+    }
+    noteLayout.current = getNoteLayout();
+  };
+  const initialDrumMap = useMemo((): string => {
+    const { drumMap } = noteLayout.current!;
+    if (!drumMap) {
+      return DrumMaps.off;
+    }
+    return R.equals(drumMap, buildSynthDrumMap()) ? DrumMaps.synthDrums : DrumMaps.sampler;
+  }, [engine]);
+
+  const arpeggiatorConf = useRef<ArpeggiatorConf | null>(null);
+  if (!arpeggiatorConf.current) {
+    const confBytes = engine.handle_message('get_arpeggiator_conf', new Uint8Array());
+    arpeggiatorConf.current = JSON.parse(new TextDecoder().decode(confBytes));
+  }
+  const setArpeggiatorConf = (newConf: Partial<ArpeggiatorConf>) => {
+    arpeggiatorConf.current = { ...arpeggiatorConf.current!, ...newConf };
+    const confBytes = new TextEncoder().encode(JSON.stringify(arpeggiatorConf.current));
+    engine.handle_message('set_arpeggiator_conf', confBytes);
+  };
+
+  const grooveConf = useRef<GrooveConf | null>(null);
+  if (!grooveConf.current) {
+    const confBytes = engine.handle_message('get_groove_conf', new Uint8Array());
+    grooveConf.current = JSON.parse(new TextDecoder().decode(confBytes));
+  }
+  const setGrooveConf = (newConf: Partial<GrooveConf>) => {
+    grooveConf.current = { ...grooveConf.current!, ...newConf };
+    const confBytes = new TextEncoder().encode(JSON.stringify(grooveConf.current));
+    engine.handle_message('set_groove_conf', confBytes);
+  };
+  const mpeConf = useRef<MPEConf | null>(null);
+  if (!mpeConf.current) {
+    const confBytes = engine.handle_message('get_mpe_conf', new Uint8Array());
+    mpeConf.current = JSON.parse(new TextDecoder().decode(confBytes));
+  }
+  const setMPEConf = (newConf: Partial<MPEConf>) => {
+    mpeConf.current = { ...mpeConf.current!, ...newConf };
+    const confBytes = new TextEncoder().encode(JSON.stringify(mpeConf.current));
+    engine.handle_message('set_mpe_conf', confBytes);
+  };
+  const voiceManagerConf = useRef<VoiceManagerConf | null>(null);
+  if (!voiceManagerConf.current) {
+    const confBytes = engine.handle_message('get_voice_manager_conf', new Uint8Array());
+    voiceManagerConf.current = JSON.parse(new TextDecoder().decode(confBytes));
+  }
+  const setVoiceManagerConf = (newConf: Partial<VoiceManagerConf>) => {
+    voiceManagerConf.current = { ...voiceManagerConf.current!, ...newConf };
+    const confBytes = new TextEncoder().encode(JSON.stringify(voiceManagerConf.current));
+    engine.handle_message('set_voice_manager_conf', confBytes);
+  };
+  const humanizeConf = useRef<HumanizeConf | null>(null);
+  if (!humanizeConf.current) {
+    const confBytes = engine.handle_message('get_humanize_conf', new Uint8Array());
+    humanizeConf.current = JSON.parse(new TextDecoder().decode(confBytes));
+  }
+  const setHumanizeConf = (newConf: Partial<HumanizeConf>) => {
+    humanizeConf.current = { ...humanizeConf.current!, ...newConf };
+    const confBytes = new TextEncoder().encode(JSON.stringify(humanizeConf.current));
+    engine.handle_message('set_humanize_conf', confBytes);
+  };
+
+  const chordSettings = useRef({ shape: 'diatonic triad', customIntervals: '0 4 7 11 14' });
+  const setChordShape = () => {
+    const { shape, customIntervals } = chordSettings.current;
+    const chordShape =
+      shape === CustomChordShape
+        ? {
+            type: 'custom',
+            intervals: customIntervals
+              .split(/[\s,]+/)
+              .filter(interval => interval !== '')
+              .map(interval => +interval),
+          }
+        : ChordShapes[shape];
+    const chordShapeBytes = new TextEncoder().encode(JSON.stringify(chordShape));
+    engine.handle_message('set_chord_shape', chordShapeBytes);
+  };
+
+  const [midiOutputPortNames, setMIDIOutputPortNames] = useState<string[]>([]);
+  useEffect(() => {
+    getMIDIOutputPortNames()
+      .then(setMIDIOutputPortNames)
+      .catch(err => console.error('Failed to list MIDI output ports: ', err));
+  }, []);
+
+  const pitchBendSettings = useRef({ preset: 'none', amount: 2 });
+
+  const automationSettings = useRef({ param: '', minValue: 0, maxValue: 1 });
+  const automatableParams = getAutomatableParams();
+  const automatableParamLabels = automatableParams.map(
+    ({ vcId, name }) => `${name} (${vcId.slice(0, 8)})`
+  );
+  const getAutomationLanes = (): AutomationLanesInfo =>
+    JSON.parse(
+      new TextDecoder().decode(engine.handle_message('get_automation_lanes', new Uint8Array()))
+    );
+  const [automationLaneLabels, setAutomationLaneLabels] = useState<string[]>(() =>
+    getAutomationLanes().lanes.map((lane, i) => buildAutomationLaneLabel(i, lane))
+  );
+  const sendAutomationMessage = (key: string, val: Uint8Array = new Uint8Array()) => {
+    engine.handle_message(key, val);
+    setAutomationLaneLabels(
+      getAutomationLanes().lanes.map((lane, i) => buildAutomationLaneLabel(i, lane))
+    );
+  };
+
+  const getClips = (): ClipsInfo =>
+    JSON.parse(new TextDecoder().decode(engine.handle_message('get_clips', new Uint8Array())));
+  const [clipLabels, setClipLabels] = useState<string[]>(() =>
+    getClips().clips.map(({ name }, i) => buildClipLabel(i, name))
+  );
+  const sendClipMessage = (key: string, val: Uint8Array = new Uint8Array()) => {
+    engine.handle_message(key, val);
+    setClipLabels(getClips().clips.map(({ name }, i) => buildClipLabel(i, name)));
+  };
 
   const onChange = useMemo<(key: string, val: any) => void>(
     () => async (key, val) => {
@@ -42,12 +531,235 @@ const MIDIEditorControls: React.FC<{
           engine.handle_message('set_bpm', new Uint8Array(buf.buffer));
           break;
         }
+        case 'change beat': {
+          tempoSettings.current.beat = val;
+          break;
+        }
+        case 'change bpm': {
+          tempoSettings.current.bpm = val;
+          break;
+        }
+        case 'time signature': {
+          tempoSettings.current.timeSignature = val;
+          break;
+        }
+        case 'metronome': {
+          setMetronomeConf({ enabled: val });
+          break;
+        }
+        case 'metronome volume': {
+          setMetronomeConf({ volume: val });
+          break;
+        }
+        case 'count-in bars': {
+          setMetronomeConf({ countInBars: val });
+          break;
+        }
+        case 'instrument': {
+          setInstrument(val);
+          break;
+        }
+        case 'midi output': {
+          setMIDIOutputConf({ enabled: val });
+          break;
+        }
+        case 'midi output port': {
+          setMIDIOutputConf({ portName: val === NoMIDIOutputPort ? null : val });
+          break;
+        }
+        case 'midi output channel': {
+          setMIDIOutputConf({ channel: val - 1 });
+          break;
+        }
+        case 'key': {
+          setScaleConf({ key: { ...scaleConf.current!.key, tonic: PitchClassNames.indexOf(val) } });
+          break;
+        }
+        case 'scale': {
+          setScaleConf({ key: { ...scaleConf.current!.key, scale: Scales[val] } });
+          break;
+        }
+        case 'snap to scale': {
+          setScaleConf({ snapToScale: val });
+          break;
+        }
+        case 'lowest note': {
+          setNoteRange({ lowestNote: val });
+          break;
+        }
+        case 'line count': {
+          setNoteRange({ lineCount: val });
+          break;
+        }
+        case 'fold to used notes': {
+          setFoldToUsedNotes(val);
+          break;
+        }
+        case 'drum map': {
+          setDrumMap(val);
+          break;
+        }
+        case 'chord': {
+          chordSettings.current.shape = val;
+          setChordShape();
+          break;
+        }
+        case 'custom chord intervals': {
+          chordSettings.current.customIntervals = val;
+          if (chordSettings.current.shape === CustomChordShape) {
+            setChordShape();
+          }
+          break;
+        }
+        case 'keyboard piano': {
+          engine.handle_message('set_keyboard_piano_enabled', new Uint8Array([val ? 1 : 0]));
+          break;
+        }
+        case 'live arpeggiator': {
+          setArpeggiatorConf({ live: val });
+          break;
+        }
+        case 'arpeggiator pattern': {
+          setArpeggiatorConf({ pattern: ArpeggiatorPatterns[val] });
+          break;
+        }
+        case 'arpeggiator rate': {
+          setArpeggiatorConf({ rate: ArpeggiatorRates[val] });
+          break;
+        }
+        case 'arpeggiator octaves': {
+          setArpeggiatorConf({ octaves: val });
+          break;
+        }
+        case 'arpeggiator gate': {
+          setArpeggiatorConf({ gate: val });
+          break;
+        }
+        case 'MPE input': {
+          setMPEConf({ enabled: val });
+          break;
+        }
+        case 'MPE pitch bend range': {
+          setMPEConf({ pitchBendRange: val });
+          break;
+        }
+        case 'mono': {
+          setVoiceManagerConf({ mono: val });
+          break;
+        }
+        case 'polyphony': {
+          setVoiceManagerConf({ polyphony: val });
+          break;
+        }
+        case 'voice stealing': {
+          setVoiceManagerConf({ stealPolicy: VoiceStealPolicies[val] });
+          break;
+        }
+        case 'glide time (ms)': {
+          setVoiceManagerConf({ glideMs: val });
+          break;
+        }
+        case 'legato glide': {
+          setVoiceManagerConf({ legatoGlide: val });
+          break;
+        }
+        case 'attack (ms)': {
+          setVoiceManagerConf({ adsr: { ...voiceManagerConf.current!.adsr, attackMs: val } });
+          break;
+        }
+        case 'decay (ms)': {
+          setVoiceManagerConf({ adsr: { ...voiceManagerConf.current!.adsr, decayMs: val } });
+          break;
+        }
+        case 'sustain': {
+          setVoiceManagerConf({ adsr: { ...voiceManagerConf.current!.adsr, sustain: val } });
+          break;
+        }
+        case 'release (ms)': {
+          setVoiceManagerConf({ adsr: { ...voiceManagerConf.current!.adsr, releaseMs: val } });
+          break;
+        }
+        case 'swing': {
+          setGrooveConf({ swing: val });
+          break;
+        }
+        case 'swing division': {
+          setGrooveConf({ division: SwingDivisions[val] });
+          break;
+        }
+        case 'humanize timing': {
+          setHumanizeConf({ maxStartOffset: val });
+          break;
+        }
+        case 'humanize length': {
+          setHumanizeConf({ maxLengthOffset: val });
+          break;
+        }
+        case 'humanize velocity': {
+          setHumanizeConf({ maxVelocityOffset: val });
+          break;
+        }
+        case 'automation param': {
+          automationSettings.current.param = val;
+          break;
+        }
+        case 'automation min': {
+          automationSettings.current.minValue = +val;
+          break;
+        }
+        case 'automation max': {
+          automationSettings.current.maxValue = +val;
+          break;
+        }
+        case 'automation lane': {
+          const laneIx = automationLaneLabels.indexOf(val);
+          if (laneIx !== -1) {
+            sendAutomationMessage('set_active_automation_lane', new Uint8Array([laneIx]));
+          }
+          break;
+        }
+        case 'clip': {
+          const clipIx = clipLabels.indexOf(val);
+          if (clipIx !== -1) {
+            sendClipMessage('set_active_clip', new Uint8Array([clipIx]));
+          }
+          break;
+        }
+        case 'play arrangement': {
+          sendClipMessage('set_play_arrangement', new Uint8Array([val ? 1 : 0]));
+          break;
+        }
+        case 'pitch bend': {
+          pitchBendSettings.current.preset = val;
+          break;
+        }
+        case 'pitch bend amount': {
+          pitchBendSettings.current.amount = val;
+          break;
+        }
+        case 'snap': {
+          const buf = new Float64Array([SnapIntervals[val]]);
+          engine.handle_message('set_snap_interval', new Uint8Array(buf.buffer));
+          break;
+        }
+        case 'bounce start beat': {
+          bounceSettings.current.startBeat = val;
+          break;
+        }
+        case 'bounce end beat': {
+          bounceSettings.current.endBeat = val;
+          break;
+        }
+        case 'bounce bit depth': {
+          bounceSettings.current.bitDepth = +val as 16 | 24;
+          break;
+        }
         default: {
           console.error(`Unhandled state key in MIDI editor controls: ${key}`);
         }
       }
     },
-    [engine]
+    [engine, automationLaneLabels, clipLabels]
   );
 
   return (
@@ -58,6 +770,280 @@ const MIDIEditorControls: React.FC<{
       draggable
       settings={[
         { type: 'range', label: 'bpm', min: 20, max: 400 },
+        { type: 'checkbox', label: 'metronome', initial: metronomeConf.current.enabled },
+        {
+          type: 'range',
+          label: 'metronome volume',
+          min: 0,
+          max: 1,
+          initial: metronomeConf.current.volume,
+        },
+        {
+          type: 'range',
+          label: 'count-in bars',
+          min: 0,
+          max: 4,
+          step: 1,
+          initial: metronomeConf.current.countInBars,
+        },
+        {
+          type: 'select',
+          label: 'instrument',
+          options: [NoInstrument, ...availableInstruments.map(getInstrumentLabel)],
+          initial: Option.of(initialInstrument)
+            .flatMap(({ id }) => Option.of(availableInstruments.find(inst => inst.id === id)))
+            .map(getInstrumentLabel)
+            .getOrElse(NoInstrument),
+        },
+        { type: 'checkbox', label: 'midi output', initial: midiOutputConf.current.enabled },
+        {
+          type: 'select',
+          label: 'midi output port',
+          options: [NoMIDIOutputPort, ...midiOutputPortNames],
+          initial: midiOutputConf.current.portName || NoMIDIOutputPort,
+        },
+        {
+          type: 'range',
+          label: 'midi output channel',
+          min: 1,
+          max: 16,
+          step: 1,
+          initial: midiOutputConf.current.channel + 1,
+        },
+        {
+          type: 'select',
+          label: 'snap',
+          options: R.keys(SnapIntervals),
+          initial: '1/8',
+        },
+        {
+          type: 'select',
+          label: 'key',
+          options: PitchClassNames,
+          initial: PitchClassNames[scaleConf.current.key.tonic],
+        },
+        {
+          type: 'select',
+          label: 'scale',
+          options: R.keys(Scales),
+          initial: R.keys(Scales).find(label => Scales[label] === scaleConf.current!.key.scale),
+        },
+        { type: 'checkbox', label: 'snap to scale', initial: scaleConf.current.snapToScale },
+        {
+          type: 'range',
+          label: 'lowest note',
+          min: 0,
+          max: 127,
+          step: 1,
+          initial: noteLayout.current.range.lowestNote,
+        },
+        {
+          type: 'range',
+          label: 'line count',
+          min: 1,
+          max: 128,
+          step: 1,
+          initial: noteLayout.current.range.lineCount,
+        },
+        {
+          type: 'checkbox',
+          label: 'fold to used notes',
+          initial: !!noteLayout.current.foldedNotes,
+        },
+        {
+          type: 'select',
+          label: 'drum map',
+          options: Object.values(DrumMaps),
+          initial: initialDrumMap,
+        },
+        {
+          type: 'select',
+          label: 'chord',
+          options: [...R.keys(ChordShapes), CustomChordShape],
+          initial: chordSettings.current.shape,
+        },
+        {
+          type: 'text',
+          label: 'custom chord intervals',
+          initial: chordSettings.current.customIntervals,
+        },
+        {
+          type: 'button',
+          label: 'undo note edit',
+          action: () => engine.handle_message('undo_note_edit', new Uint8Array()),
+        },
+        {
+          type: 'button',
+          label: 'redo note edit',
+          action: () => engine.handle_message('redo_note_edit', new Uint8Array()),
+        },
+        { type: 'checkbox', label: 'keyboard piano', initial: false },
+        { type: 'checkbox', label: 'MPE input', initial: mpeConf.current.enabled },
+        {
+          type: 'range',
+          label: 'MPE pitch bend range',
+          min: 1,
+          max: 96,
+          step: 1,
+          initial: mpeConf.current.pitchBendRange,
+        },
+        { type: 'checkbox', label: 'mono', initial: voiceManagerConf.current.mono },
+        {
+          type: 'range',
+          label: 'polyphony',
+          min: 1,
+          max: 16,
+          step: 1,
+          initial: voiceManagerConf.current.polyphony,
+        },
+        {
+          type: 'select',
+          label: 'voice stealing',
+          options: R.keys(VoiceStealPolicies),
+          initial: R.keys(VoiceStealPolicies).find(
+            label => VoiceStealPolicies[label] === voiceManagerConf.current!.stealPolicy
+          ),
+        },
+        {
+          type: 'range',
+          label: 'glide time (ms)',
+          min: 0,
+          max: 2000,
+          step: 1,
+          initial: voiceManagerConf.current.glideMs,
+        },
+        { type: 'checkbox', label: 'legato glide', initial: voiceManagerConf.current.legatoGlide },
+        {
+          type: 'range',
+          label: 'attack (ms)',
+          min: 0,
+          max: 5000,
+          step: 1,
+          initial: voiceManagerConf.current.adsr.attackMs,
+        },
+        {
+          type: 'range',
+          label: 'decay (ms)',
+          min: 0,
+          max: 5000,
+          step: 1,
+          initial: voiceManagerConf.current.adsr.decayMs,
+        },
+        {
+          type: 'range',
+          label: 'sustain',
+          min: 0,
+          max: 1,
+          step: 0.01,
+          initial: voiceManagerConf.current.adsr.sustain,
+        },
+        {
+          type: 'range',
+          label: 'release (ms)',
+          min: 0,
+          max: 10000,
+          step: 1,
+          initial: voiceManagerConf.current.adsr.releaseMs,
+        },
+        { type: 'checkbox', label: 'live arpeggiator', initial: arpeggiatorConf.current.live },
+        {
+          type: 'select',
+          label: 'arpeggiator pattern',
+          options: R.keys(ArpeggiatorPatterns),
+          initial: R.keys(ArpeggiatorPatterns).find(
+            label => ArpeggiatorPatterns[label] === arpeggiatorConf.current!.pattern
+          ),
+        },
+        {
+          type: 'select',
+          label: 'arpeggiator rate',
+          options: R.keys(ArpeggiatorRates),
+          initial: R.keys(ArpeggiatorRates).find(
+            label => ArpeggiatorRates[label] === arpeggiatorConf.current!.rate
+          ),
+        },
+        {
+          type: 'range',
+          label: 'arpeggiator octaves',
+          min: 1,
+          max: 4,
+          step: 1,
+          initial: arpeggiatorConf.current.octaves,
+        },
+        {
+          type: 'range',
+          label: 'arpeggiator gate',
+          min: 0.05,
+          max: 1,
+          initial: arpeggiatorConf.current.gate,
+        },
+        {
+          type: 'button',
+          label: 'arpeggiate selection',
+          action: () => engine.handle_message('arpeggiate_selection', new Uint8Array()),
+        },
+        { type: 'range', label: 'swing', min: 0, max: 0.75, initial: grooveConf.current.swing },
+        {
+          type: 'select',
+          label: 'swing division',
+          options: R.keys(SwingDivisions),
+          initial:
+            R.keys(SwingDivisions).find(
+              label => SwingDivisions[label] === grooveConf.current!.division
+            ) || '1/8',
+        },
+        {
+          type: 'button',
+          label: 'apply groove',
+          action: () => {
+            engine.handle_message('apply_groove', new Uint8Array());
+            grooveConf.current = { ...grooveConf.current!, swing: 0 };
+          },
+        },
+        {
+          type: 'range',
+          label: 'humanize timing',
+          min: 0,
+          max: 0.25,
+          initial: humanizeConf.current.maxStartOffset,
+        },
+        {
+          type: 'range',
+          label: 'humanize length',
+          min: 0,
+          max: 0.25,
+          initial: humanizeConf.current.maxLengthOffset,
+        },
+        {
+          type: 'range',
+          label: 'humanize velocity',
+          min: 0,
+          max: 64,
+          step: 1,
+          initial: humanizeConf.current.maxVelocityOffset,
+        },
+        {
+          type: 'button',
+          label: 'humanize selection',
+          action: () => engine.handle_message('humanize_selection', new Uint8Array()),
+        },
+        {
+          type: 'button',
+          label: 'randomize selection',
+          action: () => engine.handle_message('randomize_selection', new Uint8Array()),
+        },
+        { type: 'select', label: 'pitch bend', options: R.keys(PitchBendPresets), initial: 'none' },
+        { type: 'range', label: 'pitch bend amount', min: 0, max: 12, step: 0.5, initial: 2 },
+        {
+          type: 'button',
+          label: 'apply pitch bend',
+          action: () => {
+            const { preset, amount } = pitchBendSettings.current;
+            const curve = PitchBendPresets[preset](amount);
+            const curveBytes = new TextEncoder().encode(JSON.stringify(curve));
+            engine.handle_message('set_pitch_bend', curveBytes);
+          },
+        },
         {
           type: 'button',
           label: 'toggle loop',
@@ -77,11 +1063,165 @@ const MIDIEditorControls: React.FC<{
               console.error('MIDI Wasm module returned undefined when handling exported MIDI');
               return;
             }
-            const midiFileBytes = midiModule.write_to_midi('midi_export', noteData);
+            const tempoMapBytes = engine.handle_message('get_tempo_map', new Uint8Array());
+            const tempoMapJson = tempoMapBytes
+              ? new TextDecoder().decode(tempoMapBytes)
+              : undefined;
+            const midiFileBytes = midiModule.write_to_midi('midi_export', noteData, tempoMapJson);
             downloadjs(new Blob([midiFileBytes]), 'composition.midi', 'application/x-midi');
           },
         },
         { type: 'custom', label: 'upload midi', renderContainer: false, Comp: FileUploader },
+        { type: 'range', label: 'change beat', min: 0, max: 512, step: 1, initial: 0 },
+        { type: 'range', label: 'change bpm', min: 20, max: 400, initial: 120 },
+        { type: 'select', label: 'time signature', options: TimeSignatures, initial: '4/4' },
+        {
+          type: 'button',
+          label: 'set tempo',
+          action: () => {
+            const { beat, bpm } = tempoSettings.current;
+            const buf = new Float64Array([beat, bpm, ctx.currentTime]);
+            engine.handle_message('set_tempo', new Uint8Array(buf.buffer));
+          },
+        },
+        {
+          type: 'button',
+          label: 'remove tempo change',
+          action: () =>
+            sendTempoMapMessage(engine, 'remove_tempo_change', tempoSettings.current.beat),
+        },
+        {
+          type: 'button',
+          label: 'set time signature',
+          action: () => {
+            const { beat, timeSignature } = tempoSettings.current;
+            const [numerator, denominator] = timeSignature.split('/').map(x => +x);
+            sendTempoMapMessage(engine, 'set_time_signature', beat, [numerator, denominator]);
+          },
+        },
+        {
+          type: 'button',
+          label: 'remove time signature',
+          action: () =>
+            sendTempoMapMessage(engine, 'remove_time_signature_change', tempoSettings.current.beat),
+        },
+        {
+          type: 'select',
+          label: 'automation param',
+          options: ['', ...automatableParamLabels],
+          initial: '',
+        },
+        { type: 'text', label: 'automation min', initial: '0' },
+        { type: 'text', label: 'automation max', initial: '1' },
+        {
+          type: 'button',
+          label: 'add automation lane',
+          action: () => {
+            const { param, minValue, maxValue } = automationSettings.current;
+            const paramIx = automatableParamLabels.indexOf(param);
+            if (paramIx === -1) {
+              return;
+            }
+
+            const { vcId: targetVcId, name } = automatableParams[paramIx];
+            const lane = { target: { vcId: targetVcId, paramName: name }, minValue, maxValue };
+            sendAutomationMessage(
+              'add_automation_lane',
+              new TextEncoder().encode(JSON.stringify(lane))
+            );
+          },
+        },
+        { type: 'select', label: 'automation lane', options: automationLaneLabels },
+        {
+          type: 'button',
+          label: 'remove automation lane',
+          action: () => {
+            const { activeLaneIx } = getAutomationLanes();
+            if (activeLaneIx !== null) {
+              sendAutomationMessage('remove_automation_lane', new Uint8Array([activeLaneIx]));
+            }
+          },
+        },
+        {
+          type: 'button',
+          label: 'learn automation CC',
+          action: () => {
+            const { activeLaneIx } = getAutomationLanes();
+            if (activeLaneIx !== null) {
+              // The next controller that's moved is bound to the lane
+              sendAutomationMessage('learn_automation_cc', new Uint8Array([activeLaneIx]));
+            }
+          },
+        },
+        {
+          type: 'button',
+          label: 'clear automation CC',
+          action: () => {
+            const { activeLaneIx } = getAutomationLanes();
+            if (activeLaneIx !== null) {
+              sendAutomationMessage('clear_automation_cc', new Uint8Array([activeLaneIx]));
+            }
+          },
+        },
+        {
+          type: 'button',
+          label: 'undo automation edit',
+          action: () => sendAutomationMessage('undo_automation'),
+        },
+        {
+          type: 'button',
+          label: 'redo automation edit',
+          action: () => sendAutomationMessage('redo_automation'),
+        },
+        { type: 'select', label: 'clip', options: clipLabels },
+        {
+          type: 'button',
+          label: 'add clip',
+          action: () =>
+            sendClipMessage(
+              'add_clip',
+              new TextEncoder().encode(`Clip ${getClips().clips.length + 1}`)
+            ),
+        },
+        {
+          type: 'button',
+          label: 'delete clip',
+          action: () => sendClipMessage('delete_clip', new Uint8Array([getClips().activeClipIx])),
+        },
+        {
+          type: 'button',
+          label: 'add clip to arrangement',
+          action: () => {
+            const { clips, activeClipIx, arrangement } = getClips();
+            const { id, lengthBeats } = clips[activeClipIx];
+            const startBeat = arrangement.reduce(
+              (acc, instance) => Math.max(acc, instance.startBeat + instance.lengthBeats),
+              0
+            );
+            const newArrangement = [...arrangement, { clipId: id, startBeat, lengthBeats }];
+            sendClipMessage(
+              'set_arrangement',
+              new TextEncoder().encode(JSON.stringify(newArrangement))
+            );
+          },
+        },
+        { type: 'checkbox', label: 'play arrangement', initial: getClips().playArrangement },
+        { type: 'range', label: 'bounce start beat', min: 0, max: 512, step: 1, initial: 0 },
+        { type: 'range', label: 'bounce end beat', min: 0, max: 512, step: 1, initial: 16 },
+        { type: 'select', label: 'bounce bit depth', options: ['16', '24'], initial: '16' },
+        {
+          type: 'button',
+          label: 'bounce sampler to wav',
+          action: async () => {
+            const { startBeat, endBeat, bitDepth } = bounceSettings.current;
+            try {
+              const wavBytes = await bounceSamplerToWav(engine, vcId, startBeat, endBeat, bitDepth);
+              downloadjs(new Blob([wavBytes]), 'bounce.wav', 'audio/wav');
+            } catch (err) {
+              console.error('Failed to bounce MIDI editor: ', err);
+            }
+          },
+        },
         {
           type: 'button',
           label: isRecordingMIDI ? 'stop recording' : 'start recording',
@@ -0,0 +1,93 @@
+import { getState } from 'src/redux';
+import { OverridableAudioParam } from 'src/graphEditor/nodes/util';
+
+/**
+ * Applies values sampled from the MIDI editor's automation lanes to parameters in the patch
+ * network.  Lanes are addressed by the ID of the VC that owns the parameter and the name of its
+ * input in the patch network.
+ */
+
+const ctx = new AudioContext();
+
+/**
+ * The params that each MIDI editor has scheduled automation for.  The first value scheduled for a
+ * param after it has been cancelled is set directly, and after that it is ramped to each new value.
+ */
+const automatedParamsByVcId: Map<string, Set<AudioParam>> = new Map();
+
+export interface AutomatableParam {
+  vcId: string;
+  name: string;
+}
+
+const getParam = (targetVcId: string, paramName: string): AudioParam | null => {
+  const connectables = getState().viewContextManager.patchNetwork.connectables.get(targetVcId);
+  const input = connectables ? connectables.inputs.get(paramName) : undefined;
+  if (!input) {
+    return null;
+  }
+
+  if (input.node instanceof AudioParam) {
+    return input.node;
+  } else if (input.node instanceof OverridableAudioParam) {
+    return input.node.manualControl.offset;
+  }
+  return null;
+};
+
+/**
+ * Returns all of the params in the patch network that can be automated
+ */
+export const getAutomatableParams = (): AutomatableParam[] => {
+  const params: AutomatableParam[] = [];
+  getState().viewContextManager.patchNetwork.connectables.forEach((connectables, vcId) =>
+    connectables.inputs.forEach((input, name) => {
+      if (input.type === 'number') {
+        params.push({ vcId, name });
+      }
+    })
+  );
+  return params;
+};
+
+export const scheduleAutomation = (
+  vcId: string,
+  targetVcId: string,
+  paramName: string,
+  timings: Float64Array,
+  values: Float32Array
+) => {
+  const param = getParam(targetVcId, paramName);
+  if (!param) {
+    console.warn(`No automatable param "${paramName}" found for VC ${targetVcId}`);
+    return;
+  }
+
+  let automatedParams = automatedParamsByVcId.get(vcId);
+  if (!automatedParams) {
+    automatedParams = new Set();
+    automatedParamsByVcId.set(vcId, automatedParams);
+  }
+
+  timings.forEach((time, i) => {
+    if (automatedParams!.has(param)) {
+      param.linearRampToValueAtTime(values[i], Math.max(time, ctx.currentTime));
+    } else {
+      param.setValueAtTime(values[i], Math.max(time, ctx.currentTime));
+      automatedParams!.add(param);
+    }
+  });
+};
+
+/**
+ * Cancels all automation that has been scheduled by the MIDI editor with the provided `vcId`
+ */
+export const cancelAutomation = (vcId: string) => {
+  const automatedParams = automatedParamsByVcId.get(vcId);
+  if (!automatedParams) {
+    return;
+  }
+
+  automatedParams.forEach(param => param.cancelScheduledValues(ctx.currentTime));
+  automatedParamsByVcId.delete(vcId);
+};
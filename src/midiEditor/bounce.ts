@@ -0,0 +1,65 @@
+import { getState } from 'src/redux';
+import { Sampler, SamplerParams } from 'src/graphEditor/nodes/CustomAudio/Sampler';
+import { getSample } from 'src/sampleLibrary';
+
+const BOUNCE_SAMPLE_RATE = 44100;
+
+/**
+ * Finds the first sampler that the MIDI editor's output is connected to.  Only instruments
+ * implemented in Rust can be rendered offline independently of the `AudioContext`, and the sampler
+ * is the only one of those that bouncing currently supports.
+ */
+export const getConnectedSampler = (vcId: string): Sampler | null => {
+  const { connections, connectables } = getState().viewContextManager.patchNetwork;
+  const sampler = connections
+    .filter(([from]) => from.vcId === vcId && from.name === 'midi_output')
+    .map(([, to]) => connectables.get(to.vcId)?.node)
+    .find(node => node instanceof Sampler);
+  return (sampler as Sampler | undefined) || null;
+};
+
+/**
+ * Renders the notes of a MIDI editor within `[startBeat, endBeat)` through the sampler connected
+ * to it in a tight loop rather than in realtime, returning a WAV file of the result.  The engine
+ * does the rendering; the sample is decoded here and handed over to it.
+ */
+export const bounceSamplerToWav = async (
+  engine: typeof import('src/engine'),
+  vcId: string,
+  startBeat: number,
+  endBeat: number,
+  bitDepth: 16 | 24
+): Promise<Uint8Array> => {
+  const sampler = getConnectedSampler(vcId);
+  if (!sampler) {
+    throw new Error('The MIDI editor must be connected to a sampler in order to bounce it');
+  }
+  const params = sampler.serialize() as SamplerParams;
+  if (!params.sample) {
+    throw new Error('The connected sampler has no sample selected');
+  }
+
+  const scheduleBytes = engine.handle_message(
+    'get_bounce_events',
+    new Uint8Array(new Float64Array([startBeat, endBeat, BOUNCE_SAMPLE_RATE]).buffer)
+  )!;
+
+  // The engine expects all of the first channel's samples followed by the second channel's
+  const sample = await getSample(params.sample);
+  const channelCount = Math.min(sample.numberOfChannels, 2);
+  const sampleData = new Float32Array(sample.length * channelCount);
+  for (let channelIx = 0; channelIx < channelCount; channelIx++) {
+    sampleData.set(sample.getChannelData(channelIx), channelIx * sample.length);
+  }
+
+  const conf = {
+    rootNote: params.rootNote,
+    looping: params.looping,
+    loopStart: params.loopStart,
+    loopEnd: params.loopEnd,
+    releaseSeconds: params.releaseSeconds,
+    channelCount,
+    sampleRate: sample.sampleRate,
+  };
+  return engine.bounce_sampler(scheduleBytes, JSON.stringify(conf), sampleData, bitDepth);
+};
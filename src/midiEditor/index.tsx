@@ -58,6 +58,18 @@ export const create_midi_editor_audio_connectables = (vcId: string): AudioConnec
   };
 };
 
+/**
+ * Sends a raw 2 or 3-byte MIDI message to the MIDI editor with the provided `vcId`, timestamped
+ * with the current audio context time.
+ */
+const sendMIDIInput = (vcId: string, [status, ...dataBytes]: number[] | Uint8Array) => {
+  const clampDataByte = (byte: number) => Math.max(Math.min(Math.round(byte), 127), 0);
+  const val = new Uint8Array(9 + dataBytes.length);
+  new Float64Array(val.buffer, 0, 1)[0] = ctx.currentTime;
+  val.set([status, ...dataBytes.map(clampDataByte)], 8);
+  getEngine()!.handle_vc_message(vcId, 'midi_input', val);
+};
+
 export const init_midi_editor_ui = (vcId: string) => {
   const container = document.createElement('div');
   container.id = buildMIDIEditorUIDomId(vcId);
@@ -75,26 +87,18 @@ export const init_midi_editor_ui = (vcId: string) => {
     voiceManager: mkVoiceManagerWrapper(midiNode),
   };
 
-  // And build one for accepting MIDI input when recording
+  // And build one for accepting MIDI input.  Input is passed through the engine which plays it
+  // through the MIDI editor's output and records it if MIDI recording is active.
   const inputMIDINode = buildMIDINode(() => ({
-    onAttack: (noteId: number, voiceIx: number, velocity: number, offset?: number | undefined) => {
-      midiEditorState.midiRecordingCtxPtr.forEach(ptr =>
-        getEngine()!.midi_editor_record_note_down(ptr, ctx.currentTime, noteId)
-      );
-
-      midiNode.outputCbs.forEach(outputCbs =>
-        outputCbs.onAttack(noteId, voiceIx, velocity, offset)
-      );
-    },
-    onRelease: (noteId: number, voiceIx: number, velocity: number, offset?: number | undefined) => {
-      midiEditorState.midiRecordingCtxPtr.forEach(ptr =>
-        getEngine()!.midi_editor_record_note_up(ptr, ctx.currentTime, noteId)
-      );
-
-      midiNode.outputCbs.forEach(outputCbs =>
-        outputCbs.onRelease(noteId, voiceIx, velocity, offset)
-      );
-    },
+    onAttack: (noteId: number, _voiceIx: number, velocity: number) =>
+      sendMIDIInput(vcId, [0x90, noteId, velocity]),
+    onRelease: (noteId: number, _voiceIx: number, velocity: number) =>
+      sendMIDIInput(vcId, [0x80, noteId, velocity]),
+    onGenericControl: (controlIndex: number, value: number) =>
+      sendMIDIInput(vcId, [0xb0, controlIndex, value]),
+    // Raw messages from hardware MIDI inputs keep their channels so that MPE input can be
+    // demultiplexed by the engine
+    onRawMessage: (data: Uint8Array) => sendMIDIInput(vcId, data),
     onClearAll: (...args) => {
       midiEditorState.midiRecordingCtxPtr.forEach(_ptr => {
         throw new UnimplementedError();
@@ -0,0 +1,77 @@
+import { MIDIAccess } from 'src/patchNetwork/midiNode';
+
+/**
+ * Forwards MIDI messages produced by the engine to Web MIDI output ports so that the MIDI editor
+ * can sequence external hardware.  The engine timestamps messages with audio context times, which
+ * are converted to the `performance.now()` timestamps that Web MIDI expects.
+ */
+
+const ctx = new AudioContext();
+
+let midiAccess: MIDIAccess | null = null;
+let midiAccessPromise: Promise<MIDIAccess> | null = null;
+
+const getMIDIAccess = (): Promise<MIDIAccess> => {
+  if (!midiAccessPromise) {
+    midiAccessPromise = navigator.requestMIDIAccess().then(access => {
+      midiAccess = access;
+      return access;
+    });
+  }
+  return midiAccessPromise;
+};
+
+export const getMIDIOutputPortNames = async (): Promise<string[]> => {
+  const access = await getMIDIAccess();
+  const names: string[] = [];
+  for (const [, output] of access.outputs) {
+    names.push(output.name || output.id);
+  }
+  return names;
+};
+
+const getOutputPort = (portName: string) => {
+  if (!midiAccess) {
+    // Start requesting access so that the port is available for future messages
+    getMIDIAccess().catch(err => console.error('Failed to get MIDI access: ', err));
+    return null;
+  }
+
+  for (const [, output] of midiAccess.outputs) {
+    if ((output.name || output.id) === portName) {
+      return output;
+    }
+  }
+  console.warn(`No MIDI output port named "${portName}" found`);
+  return null;
+};
+
+export const sendMIDIOutput = (
+  _vcId: string,
+  portName: string,
+  messages: Uint8Array,
+  timings: Float64Array
+) => {
+  const output = getOutputPort(portName);
+  if (!output) {
+    return;
+  }
+
+  const performanceNow = performance.now();
+  const audioCtxNow = ctx.currentTime;
+  timings.forEach((time, i) => {
+    const timestamp = performanceNow + Math.max(time - audioCtxNow, 0) * 1000;
+    output.send(messages.subarray(i * 3, i * 3 + 3), timestamp);
+  });
+};
+
+/**
+ * Drops all messages that have been sent to the output port but not yet played.  Not all browsers
+ * support this, in which case already-scheduled messages will still play.
+ */
+export const cancelMIDIOutput = (_vcId: string, portName: string) => {
+  const output = getOutputPort(portName);
+  if (output && (output as any).clear) {
+    (output as any).clear();
+  }
+};
@@ -2,6 +2,11 @@ import * as R from 'ramda';
 import { Option } from 'funfix-core';
 
 import { MIDIEditorStateMap } from './';
+import { setTransportState } from 'src/transport';
+import { scheduleMetronomeClicks, cancelMetronomeClicks } from 'src/metronome';
+import { sendMIDIOutput, cancelMIDIOutput } from 'src/midiEditor/midiOutput';
+import { scheduleAutomation, cancelAutomation } from 'src/midiEditor/automation';
+import { connect, disconnect } from 'src/patchNetwork';
 
 const ctx = new AudioContext();
 
@@ -22,13 +27,18 @@ const getVoiceManager = (vcId: string) =>
     .map(R.prop('voiceManager'))
     .orNull();
 
-export const midi_editor_trigger_attack = (vcId: string, noteId: number, offset?: number) => {
+export const midi_editor_trigger_attack = (
+  vcId: string,
+  noteId: number,
+  offset?: number,
+  velocity?: number
+) => {
   const voiceManager = getVoiceManager(vcId);
   if (!voiceManager) {
     return;
   }
 
-  voiceManager.onAttack(noteId, undefined, offset);
+  voiceManager.onAttack(noteId, velocity, offset);
 };
 
 export const midi_editor_trigger_release = (vcId: string, noteId: number, offset?: number) => {
@@ -49,23 +59,98 @@ export const midi_editor_trigger_attack_release = (
   midi_editor_trigger_release(vcId, noteId, duration);
 };
 
+/**
+ * Types of the events scheduled by `midi_editor_schedule_events`.  Pitch bends and expression
+ * directly follow the attack of the note that they belong to.
+ */
+enum ScheduledEventType {
+  Release = 0,
+  Attack = 1,
+  PitchBend = 2,
+  Pressure = 3,
+  Timbre = 4,
+}
+
+/**
+ * Mirrors the `ExpressionDimension` enum from the engine
+ */
+enum ExpressionDimension {
+  PitchBend = 0,
+  Pressure = 1,
+  Timbre = 2,
+}
+
 export const midi_editor_schedule_events = (
   vcId: string,
-  isAttackFlags: number[],
+  eventTypes: number[],
   noteIds: number[],
+  velocities: number[],
+  values: number[],
   timings: number[]
 ) => {
+  const voiceManager = getVoiceManager(vcId);
   const curTime = ctx.currentTime;
-  for (let i = 0; i < isAttackFlags.length; i++) {
+  for (let i = 0; i < eventTypes.length; i++) {
     const offset = timings[i] - curTime;
-    if (isAttackFlags[i]) {
-      midi_editor_trigger_attack(vcId, noteIds[i], offset);
-    } else {
-      midi_editor_trigger_release(vcId, noteIds[i], offset);
+    switch (eventTypes[i]) {
+      case ScheduledEventType.Attack: {
+        midi_editor_trigger_attack(vcId, noteIds[i], offset, velocities[i]);
+        break;
+      }
+      case ScheduledEventType.Release: {
+        midi_editor_trigger_release(vcId, noteIds[i], offset);
+        break;
+      }
+      case ScheduledEventType.PitchBend: {
+        if (voiceManager) {
+          voiceManager.onPitchBend(noteIds[i], values[i], offset);
+        }
+        break;
+      }
+      case ScheduledEventType.Pressure:
+      case ScheduledEventType.Timbre: {
+        const dimension = eventTypes[i] === ScheduledEventType.Pressure ? 'pressure' : 'timbre';
+        if (voiceManager) {
+          voiceManager.onExpression(noteIds[i], dimension, values[i], offset);
+        }
+        break;
+      }
+      default: {
+        console.error(`Unknown scheduled MIDI editor event type: ${eventTypes[i]}`);
+      }
     }
   }
 };
 
+/**
+ * Applies live expression from MPE input to the voice playing `noteId`
+ */
+export const midi_editor_set_note_expression = (
+  vcId: string,
+  noteId: number,
+  dimension: ExpressionDimension,
+  value: number
+) => {
+  const voiceManager = getVoiceManager(vcId);
+  if (!voiceManager) {
+    return;
+  }
+
+  switch (dimension) {
+    case ExpressionDimension.PitchBend:
+      voiceManager.onPitchBend(noteId, value);
+      break;
+    case ExpressionDimension.Pressure:
+      voiceManager.onExpression(noteId, 'pressure', value);
+      break;
+    case ExpressionDimension.Timbre:
+      voiceManager.onExpression(noteId, 'timbre', value);
+      break;
+    default:
+      console.error(`Unknown expression dimension: ${dimension}`);
+  }
+};
+
 export const midi_editor_cancel_all_events = (vcId: string, stopPlayingNotes: boolean) => {
   const state = getState(vcId);
   if (!state) {
@@ -76,6 +161,75 @@ export const midi_editor_cancel_all_events = (vcId: string, stopPlayingNotes: bo
   state.midiNode.outputCbs.forEach(output => output.onClearAll(stopPlayingNotes));
 };
 
+export const midi_editor_forward_control_change = (
+  vcId: string,
+  controlIndex: number,
+  value: number
+) =>
+  Option.of(getState(vcId)).forEach(({ midiNode }) =>
+    midiNode.outputCbs.forEach(
+      ({ onGenericControl }) => onGenericControl && onGenericControl(controlIndex, value)
+    )
+  );
+
+/**
+ * Applies the MIDI editor's voice allocation and glide settings to its voice manager
+ */
+export const midi_editor_set_voice_manager_conf = (
+  vcId: string,
+  confJson: string,
+  voiceCount: number
+) => {
+  const voiceManager = getVoiceManager(vcId);
+  if (!voiceManager) {
+    return;
+  }
+
+  voiceManager.setConf(JSON.parse(confJson), voiceCount);
+};
+
+export const midi_editor_schedule_metronome_clicks = scheduleMetronomeClicks;
+
+export const midi_editor_cancel_metronome_clicks = cancelMetronomeClicks;
+
+export const midi_editor_send_midi_output = sendMIDIOutput;
+
+export const midi_editor_cancel_midi_output = cancelMIDIOutput;
+
+export const connect_midi_editor_instrument = (
+  fromVcId: string,
+  fromName: string,
+  toVcId: string,
+  toName: string
+) => connect({ vcId: fromVcId, name: fromName }, { vcId: toVcId, name: toName });
+
+export const disconnect_midi_editor_instrument = (
+  fromVcId: string,
+  fromName: string,
+  toVcId: string,
+  toName: string
+) => disconnect({ vcId: fromVcId, name: fromName }, { vcId: toVcId, name: toName });
+
+export const midi_editor_schedule_automation = scheduleAutomation;
+
+export const midi_editor_cancel_automation = cancelAutomation;
+
+export const midi_editor_set_transport_state = (
+  _vcId: string,
+  isPlaying: boolean,
+  bpm: number,
+  beatZeroTime: number,
+  loopStartBeat: number | undefined,
+  loopEndBeat: number | undefined
+) =>
+  setTransportState({
+    isPlaying,
+    bpm,
+    beatZeroTime,
+    loopStartBeat: loopStartBeat === undefined ? null : loopStartBeat,
+    loopEndBeat: loopEndBeat === undefined ? null : loopEndBeat,
+  });
+
 let registeredAnimationFrameCount = 0;
 const RegisteredAnimationFrames: Map<number, number> = new Map();
 
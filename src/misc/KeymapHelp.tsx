@@ -0,0 +1,97 @@
+/**
+ * Lists the keyboard shortcuts of the active view context and lets them be rebound.  Clicking on
+ * the keys of an action waits for the next key to be pressed and binds it in their place.
+ */
+
+import React, { useEffect, useState } from 'react';
+
+import { getEngine } from 'src';
+import { ModalCompProps } from 'src/controls/Modal';
+import { tryParseJson } from 'src/util';
+import './KeymapHelp.scss';
+
+interface KeyBinding {
+  section: string;
+  action: string;
+  description: string;
+  keys: string[];
+  customized: boolean;
+}
+
+const formatKey = (key: string) => (key === ' ' ? 'Space' : key);
+
+const getKeymap = (): KeyBinding[] =>
+  tryParseJson<KeyBinding[]>(getEngine()!.get_keymap(), [], 'Failed to parse keymap from engine');
+
+const KeymapHelp: React.FC<ModalCompProps<void>> = ({ onCancel }) => {
+  const [bindings, setBindings] = useState(getKeymap);
+  const [capturing, setCapturing] = useState<KeyBinding | null>(null);
+
+  useEffect(() => {
+    const handleKeyDown = (evt: KeyboardEvent) => {
+      if (!capturing) {
+        if (evt.key === 'Escape') {
+          onCancel?.();
+        }
+        return;
+      }
+
+      // Keep the key from triggering whatever it's currently bound to
+      evt.preventDefault();
+      evt.stopImmediatePropagation();
+      if (evt.key !== 'Escape') {
+        getEngine()!.set_key_bindings(
+          capturing.section,
+          capturing.action,
+          JSON.stringify([evt.key])
+        );
+        setBindings(getKeymap());
+      }
+      setCapturing(null);
+    };
+
+    window.addEventListener('keydown', handleKeyDown, true);
+    return () => window.removeEventListener('keydown', handleKeyDown, true);
+  }, [capturing, onCancel]);
+
+  return (
+    <div className='keymap-help'>
+      <h2>Keyboard Shortcuts</h2>
+      <table>
+        <tbody>
+          {bindings.map(binding => (
+            <tr key={`${binding.section}.${binding.action}`}>
+              <td>{binding.description}</td>
+              <td>
+                <button
+                  className='keymap-help-keys'
+                  onClick={() => setCapturing(binding)}
+                  title='Click and then press a key to bind it to this action'
+                >
+                  {capturing === binding
+                    ? 'Press a key...'
+                    : binding.keys.map(formatKey).join(', ') || 'Unbound'}
+                </button>
+              </td>
+              <td>
+                {binding.customized ? (
+                  <button
+                    onClick={() => {
+                      getEngine()!.reset_key_bindings(binding.section, binding.action);
+                      setBindings(getKeymap());
+                    }}
+                  >
+                    Reset
+                  </button>
+                ) : null}
+              </td>
+            </tr>
+          ))}
+        </tbody>
+      </table>
+      <button onClick={onCancel}>Close</button>
+    </div>
+  );
+};
+
+export default KeymapHelp;
@@ -0,0 +1,148 @@
+import React, { useEffect, useState } from 'react';
+
+import { useSelector, ReduxStore } from 'src/redux';
+import { MixerState, MixerTrackState, MeterLevels, MeterReading } from './mixerAudio';
+import {
+  addTrack,
+  removeTrack,
+  setTrackGain,
+  setTrackPan,
+  setTrackMuted,
+  setTrackSoloed,
+  setTrackSource,
+  setMasterGain,
+  resetMeterClips,
+} from './messages';
+import './Mixer.scss';
+
+const SILENT_READING: MeterReading = { peak: 0, rms: 0, heldPeak: 0, clipped: false };
+
+const levelToPercent = (level: number) => `${Math.min(level, 1) * 100}%`;
+
+/**
+ * Shows the peak level with the RMS level in front of it, a line at the held peak, and a clip
+ * indicator that stays lit until it's clicked.
+ */
+const Meter: React.FC<{ vcId: string; reading: MeterReading }> = ({ vcId, reading }) => (
+  <div className='mixer-meter'>
+    <div className='mixer-meter-fill peak' style={{ height: levelToPercent(reading.peak) }} />
+    <div className='mixer-meter-fill' style={{ height: levelToPercent(reading.rms) }} />
+    <div className='mixer-meter-hold' style={{ bottom: levelToPercent(reading.heldPeak) }} />
+    <div
+      className={reading.clipped ? 'mixer-meter-clip clipping' : 'mixer-meter-clip'}
+      onClick={() => resetMeterClips(vcId)}
+    />
+  </div>
+);
+
+const GainSlider: React.FC<{ value: number; onChange: (gain: number) => void }> = ({
+  value,
+  onChange,
+}) => (
+  <input
+    type='range'
+    className='mixer-gain-slider'
+    min={0}
+    max={2}
+    step={0.01}
+    value={value}
+    onChange={evt => onChange(+evt.target.value)}
+  />
+);
+
+const TrackStrip: React.FC<{
+  vcId: string;
+  track: MixerTrackState;
+  reading: MeterReading;
+}> = ({ vcId, track, reading }) => {
+  const viewContexts = useSelector(
+    (state: ReduxStore) => state.viewContextManager.activeViewContexts
+  );
+
+  return (
+    <div className='mixer-track'>
+      <div className='mixer-track-name'>{track.name}</div>
+      <select
+        value={track.source_vc_id || ''}
+        onChange={evt => setTrackSource(track.id, evt.target.value || null)}
+      >
+        <option value=''>No Source</option>
+        {viewContexts
+          .filter(({ uuid }) => uuid !== vcId)
+          .map(({ uuid, name, title }) => (
+            <option key={uuid} value={uuid}>
+              {title || name}
+            </option>
+          ))}
+      </select>
+      <input
+        type='range'
+        min={-1}
+        max={1}
+        step={0.01}
+        value={track.pan}
+        onChange={evt => setTrackPan(track.id, +evt.target.value)}
+        onDoubleClick={() => setTrackPan(track.id, 0)}
+      />
+      <div className='mixer-track-buttons'>
+        <button
+          className={track.muted ? 'active' : undefined}
+          onClick={() => setTrackMuted(track.id, !track.muted)}
+        >
+          M
+        </button>
+        <button
+          className={track.soloed ? 'active' : undefined}
+          onClick={() => setTrackSoloed(track.id, !track.soloed)}
+        >
+          S
+        </button>
+      </div>
+      <div className='mixer-fader'>
+        <GainSlider value={track.gain} onChange={gain => setTrackGain(track.id, gain)} />
+        <Meter vcId={vcId} reading={reading} />
+      </div>
+      <button onClick={() => removeTrack(track.id)}>Remove</button>
+    </div>
+  );
+};
+
+const MixerUI: React.FC<{
+  vcId: string;
+  initialState: MixerState;
+  subscribe: (
+    onStateChange: ((state: MixerState) => void) | null,
+    onMeterLevels: ((levels: MeterLevels) => void) | null
+  ) => void;
+}> = ({ vcId, initialState, subscribe }) => {
+  const [state, setState] = useState(initialState);
+  const [levels, setLevels] = useState<MeterLevels>({ tracks: {}, master: SILENT_READING });
+
+  useEffect(() => {
+    subscribe(setState, setLevels);
+    return () => subscribe(null, null);
+  }, [subscribe]);
+
+  return (
+    <div className='mixer'>
+      {state.tracks.map(track => (
+        <TrackStrip
+          key={track.id}
+          vcId={vcId}
+          track={track}
+          reading={levels.tracks[track.id] || SILENT_READING}
+        />
+      ))}
+      <div className='mixer-track mixer-master'>
+        <div className='mixer-track-name'>Master</div>
+        <div className='mixer-fader'>
+          <GainSlider value={state.master_gain} onChange={setMasterGain} />
+          <Meter vcId={vcId} reading={levels.master} />
+        </div>
+        <button onClick={addTrack}>Add Track</button>
+      </div>
+    </div>
+  );
+};
+
+export default MixerUI;
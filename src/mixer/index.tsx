@@ -0,0 +1,255 @@
+/**
+ * View context for a mixer.  The state of the mixer lives in the engine, which sends it over every
+ * time that it changes; this builds the audio graph, meters it, and renders the UI.
+ */
+
+import {
+  AudioConnectables,
+  ConnectableDescriptor,
+  create_empty_audio_connectables,
+  PatchNetwork,
+  updateConnectables,
+} from 'src/patchNetwork';
+import { mkContainerRenderHelper, mkContainerCleanupHelper } from 'src/reactUtils';
+import { store, dispatch, actionCreators, getState } from 'src/redux';
+import { tryParseJson } from 'src/util';
+import { MixerAudio, MixerState, MeterLevels, getTrackInputName } from './mixerAudio';
+import { getMeterLevels, pushMeterBlocks } from './messages';
+import MixerUI from './MixerUI';
+
+const ctx = new AudioContext();
+
+interface MixerInstance {
+  audio: MixerAudio;
+  /**
+   * Called by the UI to subscribe to state and meter updates
+   */
+  onStateChange: ((state: MixerState) => void) | null;
+  onMeterLevels: ((levels: MeterLevels) => void) | null;
+}
+
+const mixers: Map<string, MixerInstance> = new Map();
+
+const getVcId = (stateKey: string) => stateKey.split('_')[1]!;
+
+const getMixerDOMElementId = (vcId: string) => `mixer-${vcId}`;
+
+/**
+ * Sources with audio outputs are routed directly.  MIDI sources such as MIDI editors are resolved
+ * to the audio outputs of the synths that they're connected to.
+ */
+const resolveSourceOutputs = (
+  patchNetwork: PatchNetwork,
+  sourceVcId: string,
+  visited: Set<string> = new Set()
+): ConnectableDescriptor[] => {
+  const connectables = patchNetwork.connectables.get(sourceVcId);
+  if (!connectables || visited.has(sourceVcId)) {
+    return [];
+  }
+  visited.add(sourceVcId);
+
+  const audioOutput = [...connectables.outputs.entries()].find(
+    ([, { type }]) => type === 'customAudio'
+  );
+  if (audioOutput) {
+    return [{ vcId: sourceVcId, name: audioOutput[0] }];
+  }
+
+  return patchNetwork.connections
+    .filter(
+      ([from]) =>
+        from.vcId === sourceVcId && connectables.outputs.get(from.name)?.type === 'midi'
+    )
+    .flatMap(([, to]) => resolveSourceOutputs(patchNetwork, to.vcId, visited));
+};
+
+/**
+ * Connects and disconnects the sources of tracks whose source VC changed
+ */
+const routeTrackSources = (vcId: string, oldState: MixerState, newState: MixerState) => {
+  const { patchNetwork } = getState().viewContextManager;
+
+  newState.tracks.forEach(({ id, source_vc_id }) => {
+    const oldTrack = oldState.tracks.find(track => track.id === id);
+    const oldSourceVcId = oldTrack ? oldTrack.source_vc_id : null;
+    if (oldSourceVcId === source_vc_id) {
+      return;
+    }
+
+    const to: ConnectableDescriptor = { vcId, name: getTrackInputName(id) };
+    if (oldSourceVcId) {
+      resolveSourceOutputs(patchNetwork, oldSourceVcId).forEach(from =>
+        dispatch(actionCreators.viewContextManager.DISCONNECT(from, to))
+      );
+    }
+    if (source_vc_id) {
+      resolveSourceOutputs(patchNetwork, source_vc_id).forEach(from =>
+        dispatch(actionCreators.viewContextManager.CONNECT(from, to))
+      );
+    }
+  });
+};
+
+export const init_mixer = (stateKey: string, stateJson: string): number => {
+  const vcId = getVcId(stateKey);
+  const initialState = tryParseJson<MixerState>(
+    stateJson,
+    { tracks: [], master_gain: 1 },
+    `Failed to parse state for mixer with stateKey ${stateKey}`
+  );
+  const instance: MixerInstance = {
+    audio: new MixerAudio(ctx, vcId, initialState, (meterId, frameCount, levels) =>
+      pushMeterBlocks(vcId, meterId, frameCount, levels)
+    ),
+    onStateChange: null,
+    onMeterLevels: null,
+  };
+  mixers.set(vcId, instance);
+
+  const domId = getMixerDOMElementId(vcId);
+  const elem = document.createElement('div');
+  elem.id = domId;
+  elem.setAttribute(
+    'style',
+    'z-index: 2; width: 100vw; height: 100vh; position: absolute; top: 0; left: 0; display: none;'
+  );
+  document.getElementById('content')!.appendChild(elem);
+
+  mkContainerRenderHelper({
+    Comp: MixerUI,
+    store,
+    getProps: () => ({
+      vcId,
+      initialState,
+      subscribe: (
+        onStateChange: MixerInstance['onStateChange'],
+        onMeterLevels: MixerInstance['onMeterLevels']
+      ) => {
+        instance.onStateChange = onStateChange;
+        instance.onMeterLevels = onMeterLevels;
+      },
+    }),
+  })(domId);
+
+  return ctx.sampleRate;
+};
+
+export const set_mixer_state = (stateKey: string, stateJson: string) => {
+  const vcId = getVcId(stateKey);
+  const instance = mixers.get(vcId);
+  if (!instance) {
+    console.error(`Tried to set state of mixer with vcId ${vcId} but it wasn't initialized`);
+    return;
+  }
+
+  const newState: MixerState = JSON.parse(stateJson);
+  const oldState = instance.audio.state;
+  const tracksChanged = instance.audio.setState(newState);
+  if (tracksChanged) {
+    updateConnectables(vcId, instance.audio.buildConnectables());
+  }
+  routeTrackSources(vcId, oldState, newState);
+
+  if (instance.onStateChange) {
+    instance.onStateChange(newState);
+  }
+};
+
+export const set_mixer_track_params = (
+  stateKey: string,
+  trackId: number,
+  gain: number,
+  pan: number,
+  effectiveGain: number
+) => {
+  const vcId = getVcId(stateKey);
+  const instance = mixers.get(vcId);
+  if (!instance) {
+    console.error(`Tried to set track params of mixer with vcId ${vcId} but it wasn't initialized`);
+    return;
+  }
+
+  instance.audio.setTrackParams(trackId, gain, pan, effectiveGain);
+  if (instance.onStateChange) {
+    instance.onStateChange(instance.audio.state);
+  }
+};
+
+export const set_mixer_master_gain = (stateKey: string, gain: number) => {
+  const vcId = getVcId(stateKey);
+  const instance = mixers.get(vcId);
+  if (!instance) {
+    console.error(`Tried to set master gain of mixer with vcId ${vcId} but it wasn't initialized`);
+    return;
+  }
+
+  instance.audio.setMasterGain(gain);
+  if (instance.onStateChange) {
+    instance.onStateChange(instance.audio.state);
+  }
+};
+
+export const cleanup_mixer = (stateKey: string) => {
+  const vcId = getVcId(stateKey);
+  const instance = mixers.get(vcId);
+  if (instance) {
+    instance.audio.destroy();
+    mixers.delete(vcId);
+  }
+
+  const domId = getMixerDOMElementId(vcId);
+  mkContainerCleanupHelper()(domId);
+  const elem = document.getElementById(domId);
+  if (elem) {
+    elem.remove();
+  }
+};
+
+export const hide_mixer = (stateKey: string) => {
+  const vcId = getVcId(stateKey);
+  const elem = document.getElementById(getMixerDOMElementId(vcId));
+  if (!elem) {
+    console.error(`Unable to find DOM element for mixer with vcId ${vcId}; can't hide.`);
+    return;
+  }
+
+  elem.style.display = 'none';
+  const instance = mixers.get(vcId);
+  if (instance) {
+    instance.audio.stopMetering();
+  }
+};
+
+export const unhide_mixer = (stateKey: string) => {
+  const vcId = getVcId(stateKey);
+  const elem = document.getElementById(getMixerDOMElementId(vcId));
+  if (!elem) {
+    console.error(`Unable to find DOM element for mixer with vcId ${vcId}; can't unhide.`);
+    return;
+  }
+
+  elem.style.display = 'block';
+  const instance = mixers.get(vcId);
+  if (instance) {
+    instance.audio.startMetering(
+      () => getMeterLevels(vcId, instance.audio.state.tracks.map(({ id }) => id)),
+      levels => {
+        if (instance.onMeterLevels) {
+          instance.onMeterLevels(levels);
+        }
+      }
+    );
+  }
+};
+
+export const get_mixer_audio_connectables = (stateKey: string): AudioConnectables => {
+  const vcId = getVcId(stateKey);
+  const instance = mixers.get(vcId);
+  if (!instance) {
+    console.warn(`No mixer found for VC with VC ID "${vcId}"`);
+    return create_empty_audio_connectables(vcId);
+  }
+
+  return instance.audio.buildConnectables();
+};
@@ -0,0 +1,101 @@
+import { getEngine } from 'src';
+import { Messages, sendBinaryMessage } from 'src/engineMessages';
+import { MeterLevels, MeterReading, MixerState } from './mixerAudio';
+
+/**
+ * Sends a message to the engine to be handled by the active mixer, returning the updated state.
+ */
+const sendMixerMessage = (key: string, val: Uint8Array): MixerState | null => {
+  const engine = getEngine();
+  if (!engine) {
+    console.error('Tried to update mixer before the engine was initialized');
+    return null;
+  }
+
+  const res = engine.handle_message(key, val);
+  return res ? JSON.parse(new TextDecoder().decode(res)) : null;
+};
+
+export const addTrack = () => sendMixerMessage('add_track', new Uint8Array());
+
+export const removeTrack = (trackId: number) =>
+  sendMixerMessage('remove_track', new Uint8Array(new Uint32Array([trackId]).buffer));
+
+// Parameter changes are sent as binary messages.  The mixer sends its changes through
+// `set_mixer_track_params`, `set_mixer_master_gain`, or `set_mixer_state` rather than returning
+// them.
+
+export const setTrackGain = (trackId: number, gain: number) =>
+  sendBinaryMessage(Messages.setTrackGain(trackId, gain));
+
+export const setTrackPan = (trackId: number, pan: number) =>
+  sendBinaryMessage(Messages.setTrackPan(trackId, pan));
+
+export const setTrackMuted = (trackId: number, muted: boolean) =>
+  sendBinaryMessage(Messages.setTrackMuted(trackId, muted));
+
+export const setTrackSoloed = (trackId: number, soloed: boolean) =>
+  sendBinaryMessage(Messages.setTrackSoloed(trackId, soloed));
+
+export const setTrackSource = (trackId: number, vcId: string | null) =>
+  sendMixerMessage(
+    'set_track_source',
+    new TextEncoder().encode(JSON.stringify({ id: trackId, vcId }))
+  );
+
+export const setMasterGain = (gain: number) => sendBinaryMessage(Messages.setMasterGain(gain));
+
+/**
+ * Meter messages are sent to the mixer with the provided `vcId` rather than to the active VC since
+ * they're sent from the audio thread, independently of the UI.
+ */
+const sendMeterMessage = (vcId: string, key: string, val: Uint8Array): Uint8Array | undefined => {
+  const engine = getEngine();
+  if (!engine) {
+    return undefined;
+  }
+
+  return engine.handle_vc_message(vcId, key, val);
+};
+
+/**
+ * Sends the peak and mean square of each of a batch of blocks that passed through a meter
+ */
+export const pushMeterBlocks = (
+  vcId: string,
+  meterId: number,
+  frameCount: number,
+  levels: Float32Array
+) => {
+  const message = new Uint8Array(8 + levels.byteLength);
+  message.set(new Uint8Array(new Uint32Array([meterId, frameCount]).buffer), 0);
+  message.set(new Uint8Array(levels.buffer, levels.byteOffset, levels.byteLength), 8);
+  sendMeterMessage(vcId, 'push_meter_blocks', message);
+};
+
+/**
+ * Fetches the levels of all meters at once.  `trackIds` must be in the same order as the tracks in
+ * the mixer's state.
+ */
+export const getMeterLevels = (vcId: string, trackIds: number[]): MeterLevels | null => {
+  const res = sendMeterMessage(vcId, 'get_meter_levels', new Uint8Array());
+  if (!res) {
+    return null;
+  }
+
+  // Each reading is four `f32`s: peak, RMS, held peak, and clipped
+  const values = new Float32Array(res.buffer, res.byteOffset, res.byteLength / 4);
+  const readReading = (ix: number): MeterReading => ({
+    peak: values[ix * 4],
+    rms: values[ix * 4 + 1],
+    heldPeak: values[ix * 4 + 2],
+    clipped: values[ix * 4 + 3] !== 0,
+  });
+  return {
+    tracks: trackIds.reduce((acc, id, ix) => ({ ...acc, [id]: readReading(ix) }), {}),
+    master: readReading(trackIds.length),
+  };
+};
+
+export const resetMeterClips = (vcId: string) =>
+  sendMeterMessage(vcId, 'reset_meter_clips', new Uint8Array());
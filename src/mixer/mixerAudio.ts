@@ -0,0 +1,262 @@
+import { Map } from 'immutable';
+
+import { AudioConnectables, ConnectableInput, ConnectableOutput } from 'src/patchNetwork';
+
+/**
+ * Mirrors the state serialized by the engine's `MixerState`
+ */
+export interface MixerTrackState {
+  id: number;
+  name: string;
+  gain: number;
+  pan: number;
+  muted: boolean;
+  soloed: boolean;
+  source_vc_id: string | null;
+  /**
+   * The gain that is actually applied to the track, taking mute and solo into account
+   */
+  effective_gain: number;
+}
+
+export interface MixerState {
+  tracks: MixerTrackState[];
+  master_gain: number;
+}
+
+/**
+ * Mirrors `MeterReading` in the engine.  Levels are linear amplitudes where 1 is full scale.
+ */
+export interface MeterReading {
+  peak: number;
+  rms: number;
+  heldPeak: number;
+  clipped: boolean;
+}
+
+export interface MeterLevels {
+  tracks: { [trackId: number]: MeterReading };
+  master: MeterReading;
+}
+
+/**
+ * Called with the peak and mean square of each block of audio that passes through a meter
+ */
+export type MeterBlocksHandler = (
+  meterId: number,
+  frameCount: number,
+  levels: Float32Array
+) => void;
+
+interface TrackNodes {
+  input: GainNode;
+  panner: StereoPannerNode;
+  gain: GainNode;
+}
+
+/**
+ * Time constant used when changing gains and pans so that they don't click
+ */
+const PARAM_CHANGE_TIME_CONSTANT = 0.01;
+/**
+ * Mirrors `MASTER_METER_ID` in the engine
+ */
+export const MASTER_METER_ID = 0xffffffff;
+
+export const getTrackInputName = (trackId: number) => `track_${trackId}`;
+
+/**
+ * Builds the audio graph for a mixer.  Each track runs from an input through a panner and gain into
+ * the master bus.  All tracks along with the master bus are metered by worklets that summarize
+ * each block of audio and pass the summaries to `onMeterBlocks`, to be sent to the engine.
+ */
+export class MixerAudio {
+  private ctx: AudioContext;
+  private vcId: string;
+  private tracks: { [trackId: number]: TrackNodes } = {};
+  private masterGain: GainNode;
+  private meters: { [meterId: number]: AudioWorkletNode } = {};
+  private meterWorkletLoaded: Promise<void>;
+  private onMeterBlocks: MeterBlocksHandler;
+  private isMetering = false;
+  private meteringRAFHandle: number | null = null;
+  public state: MixerState;
+
+  constructor(
+    ctx: AudioContext,
+    vcId: string,
+    state: MixerState,
+    onMeterBlocks: MeterBlocksHandler
+  ) {
+    this.ctx = ctx;
+    this.vcId = vcId;
+    this.onMeterBlocks = onMeterBlocks;
+    this.meterWorkletLoaded = ctx.audioWorklet.addModule('/MeterWorkletProcessor.js');
+    this.masterGain = new GainNode(ctx);
+    this.addMeter(MASTER_METER_ID, this.masterGain);
+    this.state = { tracks: [], master_gain: 1 };
+    this.setState(state);
+  }
+
+  /**
+   * Meters `node` once the meter worklet has loaded
+   */
+  private async addMeter(meterId: number, node: AudioNode) {
+    await this.meterWorkletLoaded;
+    if (meterId !== MASTER_METER_ID && !this.tracks[meterId]) {
+      // The track was removed while the worklet was loading
+      return;
+    }
+
+    const meter = new AudioWorkletNode(this.ctx, 'meter-worklet-processor');
+    meter.port.onmessage = ({ data }: MessageEvent) =>
+      this.onMeterBlocks(meterId, data.frameCount, data.levels);
+    node.connect(meter);
+    // The worklet only outputs silence, but it needs to be connected in order to be processed
+    meter.connect(this.ctx.destination);
+    if (this.isMetering) {
+      meter.port.postMessage({ type: 'start' });
+    }
+    this.meters[meterId] = meter;
+  }
+
+  private removeMeter(meterId: number) {
+    const meter = this.meters[meterId];
+    if (meter) {
+      meter.port.postMessage({ type: 'stop' });
+      meter.disconnect();
+      delete this.meters[meterId];
+    }
+  }
+
+  private buildTrack(trackId: number): TrackNodes {
+    const input = new GainNode(this.ctx);
+    const panner = new StereoPannerNode(this.ctx);
+    const gain = new GainNode(this.ctx);
+    input.connect(panner);
+    panner.connect(gain);
+    gain.connect(this.masterGain);
+    this.addMeter(trackId, gain);
+    return { input, panner, gain };
+  }
+
+  /**
+   * Updates the audio graph to match the provided state, returning `true` if tracks were added or
+   * removed and the connectables need to be updated as a result.
+   */
+  public setState(state: MixerState): boolean {
+    const trackIds = new Set(state.tracks.map(({ id }) => id));
+    let tracksChanged = false;
+
+    Object.keys(this.tracks)
+      .map(id => +id)
+      .filter(id => !trackIds.has(id))
+      .forEach(id => {
+        // Incoming connections are trimmed when the connectables are updated
+        this.tracks[id].gain.disconnect();
+        delete this.tracks[id];
+        this.removeMeter(id);
+        tracksChanged = true;
+      });
+
+    state.tracks.forEach(({ id, pan, effective_gain }) => {
+      if (!this.tracks[id]) {
+        this.tracks[id] = this.buildTrack(id);
+        tracksChanged = true;
+      }
+
+      const { panner, gain } = this.tracks[id];
+      panner.pan.setTargetAtTime(pan, this.ctx.currentTime, PARAM_CHANGE_TIME_CONSTANT);
+      gain.gain.setTargetAtTime(effective_gain, this.ctx.currentTime, PARAM_CHANGE_TIME_CONSTANT);
+    });
+    this.masterGain.gain.setTargetAtTime(
+      state.master_gain,
+      this.ctx.currentTime,
+      PARAM_CHANGE_TIME_CONSTANT
+    );
+
+    this.state = state;
+    return tracksChanged;
+  }
+
+  /**
+   * Updates the gain and pan of a single track without touching the rest of the audio graph
+   */
+  public setTrackParams(trackId: number, gain: number, pan: number, effectiveGain: number) {
+    if (!this.tracks[trackId]) {
+      console.error(`Tried to set params of mixer track ${trackId} but it doesn't exist`);
+      return;
+    }
+
+    const { panner, gain: gainNode } = this.tracks[trackId];
+    panner.pan.setTargetAtTime(pan, this.ctx.currentTime, PARAM_CHANGE_TIME_CONSTANT);
+    gainNode.gain.setTargetAtTime(effectiveGain, this.ctx.currentTime, PARAM_CHANGE_TIME_CONSTANT);
+    this.state = {
+      ...this.state,
+      tracks: this.state.tracks.map(track =>
+        track.id === trackId ? { ...track, gain, pan, effective_gain: effectiveGain } : track
+      ),
+    };
+  }
+
+  public setMasterGain(gain: number) {
+    this.masterGain.gain.setTargetAtTime(gain, this.ctx.currentTime, PARAM_CHANGE_TIME_CONSTANT);
+    this.state = { ...this.state, master_gain: gain };
+  }
+
+  private setMetersActive(isActive: boolean) {
+    this.isMetering = isActive;
+    Object.values(this.meters).forEach(meter =>
+      meter.port.postMessage({ type: isActive ? 'start' : 'stop' })
+    );
+  }
+
+  /**
+   * Starts sending audio levels to the engine and calls `getLevels` every animation frame,
+   * passing the result to `onLevels`, until `stopMetering` is called.
+   */
+  public startMetering(
+    getLevels: () => MeterLevels | null,
+    onLevels: (levels: MeterLevels) => void
+  ) {
+    this.stopMetering();
+    this.setMetersActive(true);
+
+    const tick = () => {
+      const levels = getLevels();
+      if (levels) {
+        onLevels(levels);
+      }
+      this.meteringRAFHandle = requestAnimationFrame(tick);
+    };
+    this.meteringRAFHandle = requestAnimationFrame(tick);
+  }
+
+  public stopMetering() {
+    this.setMetersActive(false);
+    if (this.meteringRAFHandle !== null) {
+      cancelAnimationFrame(this.meteringRAFHandle);
+      this.meteringRAFHandle = null;
+    }
+  }
+
+  public destroy() {
+    this.stopMetering();
+    Object.keys(this.meters).forEach(meterId => this.removeMeter(+meterId));
+  }
+
+  public buildConnectables(): AudioConnectables {
+    return {
+      vcId: this.vcId,
+      inputs: this.state.tracks.reduce(
+        (acc, { id }) =>
+          acc.set(getTrackInputName(id), { node: this.tracks[id].input, type: 'customAudio' }),
+        Map<string, ConnectableInput>()
+      ),
+      outputs: Map<string, ConnectableOutput>().set('output', {
+        node: this.masterGain,
+        type: 'customAudio',
+      }),
+    };
+  }
+}
@@ -0,0 +1,136 @@
+import React, { useEffect, useState } from 'react';
+import ControlPanel from 'react-control-panel';
+
+import { setConf, OscilloscopeConf } from './messages';
+
+const CANVAS_WIDTH = 1200;
+const CANVAS_HEIGHT = 500;
+const GRID_DIVISIONS_X = 10;
+const GRID_DIVISIONS_Y = 8;
+
+/**
+ * Mirrors the default `ScopeConf` in the engine
+ */
+const DEFAULT_CONF: OscilloscopeConf = {
+  time_span_ms: 20,
+  amplitude_scale: 1,
+  trigger_mode: 'rising',
+  trigger_level: 0,
+  tap: 'master',
+};
+
+const SETTINGS = [
+  { type: 'range', label: 'time span (ms)', min: 1, max: 1000, scale: 'log', steps: 300 },
+  { type: 'range', label: 'amplitude scale', min: 0.1, max: 20, scale: 'log', steps: 200 },
+  { type: 'select', label: 'trigger', options: ['free', 'rising', 'falling'] },
+  { type: 'range', label: 'trigger level', min: -1, max: 1, step: 0.01 },
+  { type: 'select', label: 'tap', options: ['master', 'input'] },
+];
+
+const CONF_KEYS: { [label: string]: keyof OscilloscopeConf } = {
+  'time span (ms)': 'time_span_ms',
+  'amplitude scale': 'amplitude_scale',
+  trigger: 'trigger_mode',
+  'trigger level': 'trigger_level',
+  tap: 'tap',
+};
+
+/**
+ * Maps samples from [-1, 1] onto the height of the canvas
+ */
+const sampleToY = (sample: number) => ((1 - sample) / 2) * CANVAS_HEIGHT;
+
+const drawGrid = (ctx2d: CanvasRenderingContext2D, conf: OscilloscopeConf) => {
+  ctx2d.strokeStyle = '#333';
+  ctx2d.beginPath();
+  for (let i = 1; i < GRID_DIVISIONS_X; i++) {
+    ctx2d.moveTo((i / GRID_DIVISIONS_X) * CANVAS_WIDTH, 0);
+    ctx2d.lineTo((i / GRID_DIVISIONS_X) * CANVAS_WIDTH, CANVAS_HEIGHT);
+  }
+  for (let i = 1; i < GRID_DIVISIONS_Y; i++) {
+    ctx2d.moveTo(0, (i / GRID_DIVISIONS_Y) * CANVAS_HEIGHT);
+    ctx2d.lineTo(CANVAS_WIDTH, (i / GRID_DIVISIONS_Y) * CANVAS_HEIGHT);
+  }
+  ctx2d.stroke();
+
+  if (conf.trigger_mode !== 'free') {
+    const y = sampleToY(conf.trigger_level * conf.amplitude_scale);
+    ctx2d.strokeStyle = '#860';
+    ctx2d.beginPath();
+    ctx2d.moveTo(0, y);
+    ctx2d.lineTo(CANVAS_WIDTH, y);
+    ctx2d.stroke();
+  }
+
+  ctx2d.fillStyle = '#888';
+  ctx2d.fillText(`${conf.time_span_ms / GRID_DIVISIONS_X} ms/div`, 4, CANVAS_HEIGHT - 4);
+};
+
+const drawFrame = (
+  ctx2d: CanvasRenderingContext2D,
+  conf: OscilloscopeConf,
+  frame: Float32Array
+) => {
+  ctx2d.clearRect(0, 0, CANVAS_WIDTH, CANVAS_HEIGHT);
+  drawGrid(ctx2d, conf);
+
+  ctx2d.strokeStyle = '#0f0';
+  ctx2d.beginPath();
+  frame.forEach((sample, i) =>
+    ctx2d.lineTo((i / (frame.length - 1)) * CANVAS_WIDTH, sampleToY(sample))
+  );
+  ctx2d.stroke();
+};
+
+const OscilloscopeUI: React.FC<{
+  vcId: string;
+  initialConf: OscilloscopeConf | null;
+  onConfChange: (conf: OscilloscopeConf) => void;
+  subscribe: (onFrame: ((frame: Float32Array) => void) | null) => void;
+}> = ({ vcId, initialConf, onConfChange, subscribe }) => {
+  const [conf, setLocalConf] = useState(initialConf || DEFAULT_CONF);
+  const [canvasRef, setCanvasRef] = useState<HTMLCanvasElement | null>(null);
+
+  useEffect(() => {
+    if (!canvasRef) {
+      return;
+    }
+
+    const ctx2d = canvasRef.getContext('2d')!;
+    subscribe(frame => drawFrame(ctx2d, conf, frame));
+    return () => subscribe(null);
+  }, [canvasRef, conf, subscribe]);
+
+  return (
+    <div className='oscilloscope'>
+      <canvas
+        ref={setCanvasRef}
+        width={CANVAS_WIDTH}
+        height={CANVAS_HEIGHT}
+        style={{ backgroundColor: '#000' }}
+      />
+      <ControlPanel
+        style={{ width: 500 }}
+        settings={SETTINGS}
+        state={{
+          'time span (ms)': conf.time_span_ms,
+          'amplitude scale': conf.amplitude_scale,
+          trigger: conf.trigger_mode,
+          'trigger level': conf.trigger_level,
+          tap: conf.tap,
+        }}
+        onChange={(key: string, val: any) => {
+          const sanitizedConf = setConf(vcId, { ...conf, [CONF_KEYS[key]]: val });
+          if (!sanitizedConf) {
+            return;
+          }
+
+          setLocalConf(sanitizedConf);
+          onConfChange(sanitizedConf);
+        }}
+      />
+    </div>
+  );
+};
+
+export default OscilloscopeUI;
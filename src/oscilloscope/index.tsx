@@ -0,0 +1,163 @@
+/**
+ * View context for an oscilloscope.  Audio is tapped here and sent to the engine, which buffers it
+ * and handles triggering; the current frame is fetched and drawn every animation frame while the
+ * view is visible.
+ */
+
+import { Map as ImmMap } from 'immutable';
+
+import { AudioTap } from 'src/audioTap';
+import {
+  AudioConnectables,
+  ConnectableInput,
+  ConnectableOutput,
+  create_empty_audio_connectables,
+} from 'src/patchNetwork';
+import { mkContainerRenderHelper, mkContainerCleanupHelper } from 'src/reactUtils';
+import { store } from 'src/redux';
+import { tryParseJson } from 'src/util';
+import { getFrame, pushSamples, OscilloscopeConf } from './messages';
+import OscilloscopeUI from './OscilloscopeUI';
+
+const ctx = new AudioContext();
+
+interface OscilloscopeInstance {
+  tap: AudioTap;
+  renderRAFHandle: number | null;
+  /**
+   * Set by the UI to be called with the current frame of the display every animation frame
+   */
+  onFrame: ((frame: Float32Array) => void) | null;
+}
+
+const oscilloscopes: Map<string, OscilloscopeInstance> = new Map();
+
+const getVcId = (stateKey: string) => stateKey.split('_')[1]!;
+
+const getOscilloscopeDOMElementId = (vcId: string) => `oscilloscope-${vcId}`;
+
+const stopRendering = (instance: OscilloscopeInstance) => {
+  if (instance.renderRAFHandle !== null) {
+    cancelAnimationFrame(instance.renderRAFHandle);
+    instance.renderRAFHandle = null;
+  }
+};
+
+export const init_oscilloscope = (stateKey: string, confJson: string): number => {
+  const vcId = getVcId(stateKey);
+  const initialConf = tryParseJson<OscilloscopeConf | null>(
+    confJson,
+    null,
+    `Failed to parse config for oscilloscope with stateKey ${stateKey}`
+  );
+  const instance: OscilloscopeInstance = {
+    tap: new AudioTap(ctx, initialConf?.tap || 'master', samples => pushSamples(vcId, samples)),
+    renderRAFHandle: null,
+    onFrame: null,
+  };
+  oscilloscopes.set(vcId, instance);
+
+  const domId = getOscilloscopeDOMElementId(vcId);
+  const elem = document.createElement('div');
+  elem.id = domId;
+  elem.setAttribute(
+    'style',
+    'z-index: 2; width: 100vw; height: 100vh; position: absolute; top: 0; left: 0; display: none;'
+  );
+  document.getElementById('content')!.appendChild(elem);
+
+  mkContainerRenderHelper({
+    Comp: OscilloscopeUI,
+    store,
+    getProps: () => ({
+      vcId,
+      initialConf,
+      onConfChange: (conf: OscilloscopeConf) => instance.tap.setLocation(conf.tap),
+      subscribe: (onFrame: OscilloscopeInstance['onFrame']) => {
+        instance.onFrame = onFrame;
+      },
+    }),
+  })(domId);
+
+  return ctx.sampleRate;
+};
+
+export const cleanup_oscilloscope = (stateKey: string) => {
+  const vcId = getVcId(stateKey);
+  const instance = oscilloscopes.get(vcId);
+  if (instance) {
+    stopRendering(instance);
+    instance.tap.destroy();
+    oscilloscopes.delete(vcId);
+  }
+
+  const domId = getOscilloscopeDOMElementId(vcId);
+  mkContainerCleanupHelper()(domId);
+  const elem = document.getElementById(domId);
+  if (elem) {
+    elem.remove();
+  }
+};
+
+export const hide_oscilloscope = (stateKey: string) => {
+  const vcId = getVcId(stateKey);
+  const elem = document.getElementById(getOscilloscopeDOMElementId(vcId));
+  if (!elem) {
+    console.error(
+      `Unable to find DOM element for oscilloscope with vcId ${vcId}; can't hide.`
+    );
+    return;
+  }
+
+  elem.style.display = 'none';
+  const instance = oscilloscopes.get(vcId);
+  if (instance) {
+    stopRendering(instance);
+    instance.tap.setIsActive(false);
+  }
+};
+
+export const unhide_oscilloscope = (stateKey: string) => {
+  const vcId = getVcId(stateKey);
+  const elem = document.getElementById(getOscilloscopeDOMElementId(vcId));
+  if (!elem) {
+    console.error(
+      `Unable to find DOM element for oscilloscope with vcId ${vcId}; can't unhide.`
+    );
+    return;
+  }
+
+  elem.style.display = 'block';
+  const instance = oscilloscopes.get(vcId);
+  if (!instance) {
+    return;
+  }
+
+  instance.tap.setIsActive(true);
+  stopRendering(instance);
+  const tick = () => {
+    if (instance.onFrame) {
+      instance.onFrame(getFrame(vcId));
+    }
+    instance.renderRAFHandle = requestAnimationFrame(tick);
+  };
+  instance.renderRAFHandle = requestAnimationFrame(tick);
+};
+
+export const get_oscilloscope_audio_connectables = (stateKey: string): AudioConnectables => {
+  const vcId = getVcId(stateKey);
+  const instance = oscilloscopes.get(vcId);
+  if (!instance) {
+    console.warn(`No oscilloscope found for VC with VC ID "${vcId}"`);
+    return create_empty_audio_connectables(vcId);
+  }
+
+  return {
+    vcId,
+    inputs: ImmMap<string, ConnectableInput>().set('input', {
+      node: instance.tap.input,
+      type: 'customAudio',
+    }),
+    outputs: ImmMap<string, ConnectableOutput>(),
+  };
+};
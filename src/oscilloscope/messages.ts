@@ -0,0 +1,54 @@
+import { getEngine } from 'src';
+import { TapLocation } from 'src/audioTap';
+
+export type TriggerMode = 'free' | 'rising' | 'falling';
+
+/**
+ * Mirrors `ScopeConf` in the engine
+ */
+export interface OscilloscopeConf {
+  time_span_ms: number;
+  amplitude_scale: number;
+  trigger_mode: TriggerMode;
+  trigger_level: number;
+  tap: TapLocation;
+}
+
+const sendOscilloscopeMessage = (vcId: string, key: string, val: Uint8Array) => {
+  const engine = getEngine();
+  if (!engine) {
+    console.error('Tried to message oscilloscope before the engine was initialized');
+    return undefined;
+  }
+
+  return engine.handle_vc_message(vcId, key, val);
+};
+
+export const pushSamples = (vcId: string, samples: Float32Array) =>
+  sendOscilloscopeMessage(
+    vcId,
+    'push_samples',
+    new Uint8Array(samples.buffer, samples.byteOffset, samples.byteLength)
+  );
+
+/**
+ * Returns the samples of the currently displayed frame, already scaled by the amplitude scale
+ */
+export const getFrame = (vcId: string): Float32Array => {
+  const res = sendOscilloscopeMessage(vcId, 'get_frame', new Uint8Array());
+  return res
+    ? new Float32Array(res.buffer, res.byteOffset, res.byteLength / 4)
+    : new Float32Array();
+};
+
+/**
+ * Returns the new config, which may have been adjusted by the engine to fit into valid ranges
+ */
+export const setConf = (vcId: string, conf: OscilloscopeConf): OscilloscopeConf | null => {
+  const res = sendOscilloscopeMessage(
+    vcId,
+    'set_conf',
+    new TextEncoder().encode(JSON.stringify(conf))
+  );
+  return res ? JSON.parse(new TextDecoder().decode(res)) : null;
+};
@@ -8,7 +8,53 @@ export interface MIDIInputCbs {
   onAttack: (note: number, voiceIx: number, velocity: number, offset?: number) => void;
   onRelease: (note: number, voiceIx: number, velocity: number, offset?: number) => void;
   onPitchBend: (bendAmount: number, offset?: number) => void;
+  /**
+   * Bends the pitch of a single voice by `semitones`, ramping to it from the voice's previous bend.
+   * Voices are reset to no bend when they're attacked.
+   */
+  onVoicePitchBend?: (note: number, voiceIx: number, semitones: number, offset?: number) => void;
+  /**
+   * Slides the pitch of a voice that just started playing `note` from the pitch of `fromNote`
+   * over `duration` seconds
+   */
+  onVoiceGlide?: (
+    fromNote: number,
+    note: number,
+    voiceIx: number,
+    duration: number,
+    offset?: number
+  ) => void;
+  /**
+   * Sets the MPE pressure or timbre of a single voice to `value`, which is in the range [0, 1]
+   */
+  onVoiceExpression?: (
+    note: number,
+    voiceIx: number,
+    dimension: 'pressure' | 'timbre',
+    value: number,
+    offset?: number
+  ) => void;
+  /**
+   * Ramps the gain of a single voice through `envelope` over `duration` seconds.  Voice managers
+   * send these each time that a voice is attacked or released.
+   */
+  onVoiceEnvelope?: (
+    voiceIx: number,
+    envelope: Float32Array,
+    duration: number,
+    offset?: number
+  ) => void;
   onClearAll: (stopPlayingNotes: boolean) => void;
+  /**
+   * Called for MIDI control change events such as the mod wheel (control index 1) with a value in
+   * the range [0, 127]
+   */
+  onGenericControl?: (controlIndex: number, value: number) => void;
+  /**
+   * If provided, hardware MIDI inputs pass raw 2 or 3-byte MIDI messages to this instead of calling
+   * the other callbacks.  This keeps the channels of the messages, which MPE input needs.
+   */
+  onRawMessage?: (data: Uint8Array) => void;
 }
 
 /**
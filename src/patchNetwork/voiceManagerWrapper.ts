@@ -1,5 +1,63 @@
+import * as R from 'ramda';
+
 import { MIDINode } from 'src/patchNetwork/midiNode';
 
+const audioCtx = new AudioContext();
+
+/**
+ * Mirrors the `VoiceStealPolicy` enum from the `polysynth` crate
+ */
+const VoiceStealPolicies = {
+  stealOldest: 0,
+  stealQuietest: 1,
+  stealSamePitch: 2,
+  dropNew: 3,
+} as const;
+
+export type VoiceStealPolicy = keyof typeof VoiceStealPolicies;
+
+/**
+ * Mirrors the `AdsrConf` struct from the engine, the amplitude envelope that voices are played with
+ */
+export interface AdsrConf {
+  attackMs: number;
+  decayMs: number;
+  /**
+   * The level from 0 to 1 that is held for as long as the note is held
+   */
+  sustain: number;
+  /**
+   * How long notes ring out for after they're released
+   */
+  releaseMs: number;
+}
+
+/**
+ * Mirrors the `VoiceManagerConf` struct from the engine
+ */
+export interface VoiceManagerConf {
+  mono: boolean;
+  /**
+   * The most notes that can play at once when not in mono mode
+   */
+  polyphony: number;
+  /**
+   * Determines which voice plays a new note when all voices are already playing
+   */
+  stealPolicy: VoiceStealPolicy;
+  /**
+   * How long it takes to slide to the pitch of a new note from the pitch of the note played before
+   * it.  Glide is disabled if this is zero.
+   */
+  glideMs: number;
+  /**
+   * If set, notes glide when played while the previous note is still held even when not in mono
+   * mode
+   */
+  legatoGlide: boolean;
+  adsr: AdsrConf;
+}
+
 /**
  * A wrapper around `MIDINode` that exposes voice management functionality
  */
@@ -7,22 +65,79 @@ import { MIDINode } from 'src/patchNetwork/midiNode';
 export interface VoiceManagerWrapper {
   onAttack: (noteId: number, velocity?: number, offset?: number) => void;
   onRelease: (noteId: number, offset?: number) => void;
+  /**
+   * Bends the pitch of the voice playing `noteId` by `semitones`, ramping to it from the voice's
+   * previous bend.  Has no effect if the note isn't playing.
+   */
+  onPitchBend: (noteId: number, semitones: number, offset?: number) => void;
+  /**
+   * Sets the MPE pressure or timbre of the voice playing `noteId`.  Has no effect if the note isn't
+   * playing.
+   */
+  onExpression: (
+    noteId: number,
+    dimension: 'pressure' | 'timbre',
+    value: number,
+    offset?: number
+  ) => void;
   reset: () => void;
+  /**
+   * Applies `conf` to the voice manager.  `voiceCount` is the number of voices that notes are
+   * played with, taking mono mode and the instrument's number of voices into account.
+   */
+  setConf: (conf: VoiceManagerConf, voiceCount: number) => void;
 }
 
 export const mkVoiceManagerWrapper = (midiNode: MIDINode): VoiceManagerWrapper => {
   const polysynthModule = import('src/polysynth');
 
   let ctx: number | null = null;
+  let voiceCount: number | null = null;
   polysynthModule.then(mod => {
     const playNote = (voiceIx: number, note: number, velocity: number, offset?: number) =>
       midiNode.outputCbs.forEach(output => output.onAttack(note, voiceIx, velocity, offset));
     const releaseNote = (voiceIx: number, note: number, offset?: number) =>
       midiNode.outputCbs.forEach(output => output.onRelease(note, voiceIx, 255, offset));
 
-    ctx = mod.create_polysynth_context(playNote, releaseNote);
+    const glideNote = (
+      voiceIx: number,
+      fromNote: number,
+      note: number,
+      duration: number,
+      offset?: number
+    ) =>
+      midiNode.outputCbs.forEach(
+        ({ onVoiceGlide }) =>
+          onVoiceGlide && onVoiceGlide(fromNote, note, voiceIx, duration, offset)
+      );
+
+    const applyEnvelope = (
+      voiceIx: number,
+      envelope: Float32Array,
+      duration: number,
+      offset?: number
+    ) =>
+      midiNode.outputCbs.forEach(
+        ({ onVoiceEnvelope }) =>
+          onVoiceEnvelope && onVoiceEnvelope(voiceIx, envelope, duration, offset)
+      );
+
+    ctx = mod.create_polysynth_context(playNote, releaseNote, glideNote, applyEnvelope);
   });
 
+  const withPlayingVoiceIx = (noteId: number, cb: (voiceIx: number) => void) =>
+    polysynthModule.then(mod => {
+      if (ctx === null) {
+        return;
+      }
+
+      const voiceIx = mod.get_playing_voice_ix(ctx, noteId);
+      if (R.isNil(voiceIx)) {
+        return;
+      }
+      cb(voiceIx);
+    });
+
   return {
     onAttack: (noteId: number, velocity?: number, offset?: number) =>
       polysynthModule.then(
@@ -30,6 +145,41 @@ export const mkVoiceManagerWrapper = (midiNode: MIDINode): VoiceManagerWrapper =
       ),
     onRelease: (noteId: number, offset?: number) =>
       polysynthModule.then(mod => ctx !== null && mod.handle_note_up(ctx, noteId, offset)),
+    onPitchBend: (noteId: number, semitones: number, offset?: number) =>
+      withPlayingVoiceIx(noteId, voiceIx =>
+        midiNode.outputCbs.forEach(
+          ({ onVoicePitchBend }) =>
+            onVoicePitchBend && onVoicePitchBend(noteId, voiceIx, semitones, offset)
+        )
+      ),
+    onExpression: (
+      noteId: number,
+      dimension: 'pressure' | 'timbre',
+      value: number,
+      offset?: number
+    ) =>
+      withPlayingVoiceIx(noteId, voiceIx =>
+        midiNode.outputCbs.forEach(
+          ({ onVoiceExpression }) =>
+            onVoiceExpression && onVoiceExpression(noteId, voiceIx, dimension, value, offset)
+        )
+      ),
     reset: () => polysynthModule.then(mod => ctx !== null && mod.release_all(ctx)),
+    setConf: (conf: VoiceManagerConf, newVoiceCount: number) =>
+      polysynthModule.then(mod => {
+        if (ctx === null) {
+          return;
+        }
+
+        // Changing the voice count releases all playing notes, so only do it when it changes
+        if (newVoiceCount !== voiceCount) {
+          voiceCount = newVoiceCount;
+          mod.set_voice_count(ctx, voiceCount);
+        }
+        mod.set_voice_steal_policy(ctx, VoiceStealPolicies[conf.stealPolicy]);
+        mod.set_glide(ctx, conf.glideMs, conf.legatoGlide, audioCtx.sampleRate);
+        const { attackMs, decayMs, sustain, releaseMs } = conf.adsr;
+        mod.set_adsr(ctx, attackMs, decayMs, sustain, releaseMs);
+      }),
   };
 };
@@ -1,8 +1,25 @@
 import download from 'downloadjs';
 import { Either } from 'funfix-core';
 
+import { getAllStoredEntries } from 'src/storage';
+
+// The compression streams aren't included in the DOM typings of the TypeScript version in use
+type GzipStreamConstructor = new (format: 'gzip') => TransformStream<Uint8Array, Uint8Array>;
+declare const CompressionStream: GzipStreamConstructor;
+declare const DecompressionStream: GzipStreamConstructor;
+
+const GZIP_MAGIC = [0x1f, 0x8b];
+
+const pipeThroughGzipStream = (
+  bytes: Uint8Array,
+  stream: TransformStream<Uint8Array, Uint8Array>
+): Promise<Uint8Array> =>
+  new Response(new Blob([bytes]).stream().pipeThrough(stream))
+    .arrayBuffer()
+    .then(buf => new Uint8Array(buf));
+
 export const serializeAndDownloadComposition = () => {
-  download(JSON.stringify(localStorage), 'composition.json', 'application/json');
+  download(JSON.stringify(getAllStoredEntries()), 'composition.json', 'application/json');
 };
 
 export const loadComposition = (
@@ -28,3 +45,37 @@ export const loadComposition = (
 
   return Either.right(void 0);
 };
+
+/**
+ * Downloads the whole project, including the data of all VCs, as a single file that can be loaded
+ * with `importProject` on another machine.  It's compressed with gzip if the browser supports it.
+ */
+export const exportProject = async (engine: typeof import('./engine'), compress = true) => {
+  const project = engine.export_project();
+  const fileContent =
+    compress && typeof CompressionStream !== 'undefined'
+      ? await pipeThroughGzipStream(project, new CompressionStream('gzip'))
+      : project;
+  download(new Blob([fileContent]), 'project.wsynth', 'application/octet-stream');
+};
+
+/**
+ * Replaces the current project with one from a file created by `exportProject`, decompressing it
+ * first if it was compressed.
+ */
+export const importProject = async (
+  fileContent: ArrayBuffer,
+  engine: typeof import('./engine')
+): Promise<Either<string, void>> => {
+  let project = new Uint8Array(fileContent);
+  if (GZIP_MAGIC.every((byte, i) => project[i] === byte)) {
+    if (typeof DecompressionStream === 'undefined') {
+      return Either.left("This browser can't decompress project files");
+    }
+    project = await pipeThroughGzipStream(project, new DecompressionStream('gzip'));
+  }
+
+  return engine.import_project(project)
+    ? Either.right(void 0)
+    : Either.left('The provided file is not a valid project');
+};
@@ -7,6 +7,8 @@ import { EffectNode } from 'src/synthDesigner/effects';
 import { ADSRValues, defaultAdsrEnvelope, ControlPanelADSR } from 'src/controls/adsr';
 import { ADSRModule } from 'src/synthDesigner/ADSRModule';
 import { SynthVoicePreset } from 'src/redux/modules/presets';
+import { velocityToGainLevel } from 'src/util';
+import { getEngine } from 'src';
 
 const disposeSynthModule = (synthModule: SynthModule) => {
   synthModule.voices.forEach(voice => voice.outerGainNode.disconnect());
@@ -69,8 +71,44 @@ export interface FilterCSNs {
   detune: ConstantSourceNode;
 }
 
+export interface UnisonNodes {
+  /**
+   * Delays the oscillator by a fraction of its period to randomize its phase
+   */
+  delay: DelayNode;
+  panner: StereoPannerNode;
+}
+
+/**
+ * Mirrors `UnisonVoice` from the voice manager's `unison` module
+ */
+export interface UnisonVoice {
+  /**
+   * In cents
+   */
+  detune: number;
+  pan: number;
+}
+
+export interface UnisonParams {
+  /**
+   * The distance in cents between the two most detuned oscillators of each voice
+   */
+  unisonDetune: number;
+  /**
+   * How far the oscillators of each voice are spread across the stereo field, from 0 to 1
+   */
+  unisonSpread: number;
+  unisonPhaseRandomization: boolean;
+}
+
 export interface Voice {
   oscillators: OscillatorNode[];
+  /**
+   * The nodes that each oscillator is routed through on its way to the filter, indexed the same as
+   * `oscillators`
+   */
+  unisonNodes: UnisonNodes[];
   frequencyCSN: ConstantSourceNode;
   effects: EffectModule[];
   // The node that is connected to whatever the synth module as a whole is connected to.  Its
@@ -81,9 +119,13 @@ export interface Voice {
   filterADSRModule: ADSRModule;
 }
 
-export interface SynthModule {
+export interface SynthModule extends UnisonParams {
   waveform: Waveform;
   detune: number;
+  /**
+   * The detune and pan of each oscillator of every voice
+   */
+  unisonLayout: UnisonVoice[];
   detuneCSN: ConstantSourceNode;
   voices: Voice[];
   filterParams: FilterParams;
@@ -196,8 +238,67 @@ function updateFilterNode<K extends keyof FilterParams>(
   }
 }
 
+/**
+ * Lays out the oscillators of each voice for unison.  The number of oscillators is limited based on
+ * the number of voices to keep CPU usage in check, so the layout can be shorter than `unison`.
+ */
+const getUnisonLayout = (unison: number, detune: number, spread: number): UnisonVoice[] => {
+  const engine = getEngine();
+  if (!engine) {
+    return [{ detune: 0, pan: 0 }];
+  }
+
+  const layout = Array.from(engine.get_unison_layout(unison, detune, spread, VOICE_COUNT));
+  return R.splitEvery(2, layout).map(([detune, pan]) => ({ detune, pan }));
+};
+
+/**
+ * Routes `osc` into `filterNode` through the nodes used to randomize its phase and place it in the
+ * stereo field
+ */
+const connectUnisonOscillator = (
+  osc: OscillatorNode,
+  filterNode: BiquadFilterNode,
+  pan: number
+): UnisonNodes => {
+  const delay = new DelayNode(ctx, { delayTime: 0 });
+  const panner = new StereoPannerNode(ctx, { pan });
+  osc.connect(delay);
+  delay.connect(panner);
+  panner.connect(filterNode);
+  return { delay, panner };
+};
+
+const disposeUnisonOscillator = (osc: OscillatorNode, { delay, panner }: UnisonNodes) => {
+  osc.stop();
+  osc.disconnect();
+  delay.disconnect();
+  panner.disconnect();
+};
+
+/**
+ * Re-computes the unison layout of `synth` from its current params and applies the pans of the
+ * layout to its oscillators.  Detunes are applied when notes are played.
+ */
+const applyUnisonLayout = (synth: SynthModule): SynthModule => {
+  const unisonLayout = getUnisonLayout(
+    synth.voices[0].oscillators.length,
+    synth.unisonDetune,
+    synth.unisonSpread
+  );
+  synth.voices.forEach(voice =>
+    voice.unisonNodes.forEach(({ panner }, i) =>
+      panner.pan.setValueAtTime(unisonLayout[i]?.pan ?? 0, ctx.currentTime)
+    )
+  );
+  return { ...synth, unisonLayout };
+};
+
 export const serializeSynthModule = (synth: SynthModule) => ({
   unison: synth.voices[0].oscillators.length,
+  unisonDetune: synth.unisonDetune,
+  unisonSpread: synth.unisonSpread,
+  unisonPhaseRandomization: synth.unisonPhaseRandomization,
   waveform: synth.waveform,
   detune: synth.detune,
   filter: synth.filterParams,
@@ -260,6 +361,10 @@ const buildDefaultSynthModule = (): SynthModule => {
   const inst: SynthModule = {
     waveform: Waveform.Sine,
     detune: 0,
+    unisonDetune: 0,
+    unisonSpread: 0,
+    unisonPhaseRandomization: false,
+    unisonLayout: [{ detune: 0, pan: 0 }],
     detuneCSN: new ConstantSourceNode(ctx),
     voices: R.range(0, VOICE_COUNT).map(() => {
       const outerGainNode = new GainNode(ctx);
@@ -279,7 +384,7 @@ const buildDefaultSynthModule = (): SynthModule => {
 
       const osc = new OscillatorNode(ctx);
       osc.start();
-      osc.connect(filterNode);
+      const unisonNodes = connectUnisonOscillator(osc, filterNode, 0);
 
       // Start the gain ADSR module and configure it to modulate the voice's gain node
       const gainADSRModule = new ADSRModule(ctx, { minValue: 0, maxValue: 1.8, lengthMs: 1000 });
@@ -292,6 +397,7 @@ const buildDefaultSynthModule = (): SynthModule => {
 
       return {
         oscillators: [osc],
+        unisonNodes: [unisonNodes],
         frequencyCSN: new ConstantSourceNode(ctx),
         effects: [],
         outerGainNode,
@@ -333,6 +439,9 @@ const buildDefaultSynthModule = (): SynthModule => {
 export const deserializeSynthModule = ({
   waveform,
   unison,
+  unisonDetune = 0,
+  unisonSpread = 0,
+  unisonPhaseRandomization = false,
   detune,
   filter: filterParams,
   masterGain,
@@ -352,13 +461,11 @@ export const deserializeSynthModule = ({
   gainADSRLength: number;
   filterEnvelope: ADSRValues;
   filterADSRLength: number;
-}): SynthModule => {
+} & Partial<UnisonParams>): SynthModule => {
   const base = buildDefaultSynthModule();
+  const unisonLayout = getUnisonLayout(unison, unisonDetune, unisonSpread);
   const voices = base.voices.map(voice => {
-    voice.oscillators.forEach(osc => {
-      osc.stop();
-      osc.disconnect();
-    });
+    voice.oscillators.forEach((osc, i) => disposeUnisonOscillator(osc, voice.unisonNodes[i]));
 
     voice.filterNode.connect(voice.outerGainNode);
     Object.entries(filterParams).forEach(([key, val]: [keyof typeof filterParams, any]) =>
@@ -373,18 +480,22 @@ export const deserializeSynthModule = ({
     voice.filterADSRModule.setLengthMs(filterADSRLength);
     voice.filterADSRModule.connect(voice.filterNode.frequency);
 
+    const oscillators = unisonLayout.map(() => {
+      const osc = new OscillatorNode(ctx);
+      osc.type = waveform;
+      osc.detune.setValueAtTime(0, ctx.currentTime);
+      voice.frequencyCSN.connect(osc.frequency);
+      base.detuneCSN.connect(osc.detune);
+      osc.start();
+      return osc;
+    });
+
     return {
       ...voice,
-      oscillators: R.range(0, unison).map(() => {
-        const osc = new OscillatorNode(ctx);
-        osc.type = waveform;
-        osc.detune.setValueAtTime(0, ctx.currentTime);
-        voice.frequencyCSN.connect(osc.frequency);
-        base.detuneCSN.connect(osc.detune);
-        osc.start();
-        osc.connect(voice.filterNode);
-        return osc;
-      }),
+      oscillators,
+      unisonNodes: oscillators.map((osc, i) =>
+        connectUnisonOscillator(osc, voice.filterNode, unisonLayout[i].pan)
+      ),
       effects: [], // TODO
     };
   });
@@ -395,6 +506,10 @@ export const deserializeSynthModule = ({
     ...base,
     waveform,
     detune,
+    unisonDetune,
+    unisonSpread,
+    unisonPhaseRandomization,
+    unisonLayout,
     voices,
     masterGain,
     selectedEffectType,
@@ -462,13 +577,28 @@ const setEffect = (
   return setSynth(synthIx, newSynth, state);
 };
 
-const mkSetFreqForOsc = (frequency: number, offset?: number) => (osc: OscillatorNode) =>
-  osc.frequency.setValueAtTime(
-    frequency,
-    Option.of(offset)
-      .map(offset => ctx.currentTime + offset)
-      .getOrElse(ctx.currentTime)
-  );
+/**
+ * Sets the oscillators of `voice` to play `frequency`, detuning each of them according to the
+ * unison layout of `synth` and randomizing their phases if enabled
+ */
+const setVoiceFrequency = (
+  synth: SynthModule,
+  voice: Voice,
+  frequency: number,
+  offset?: number
+) => {
+  const time = Option.of(offset)
+    .map(offset => ctx.currentTime + offset)
+    .getOrElse(ctx.currentTime);
+  voice.oscillators.forEach((osc, i) => {
+    const detune = synth.unisonLayout[i]?.detune ?? 0;
+    osc.frequency.setValueAtTime(frequency * Math.pow(2, detune / 1200), time);
+    // Clear any pitch bend left over from the last note that the voice played
+    osc.detune.setValueAtTime(0, time);
+    const phaseDelay = synth.unisonPhaseRandomization ? Math.random() / frequency : 0;
+    voice.unisonNodes[i].delay.delayTime.setValueAtTime(phaseDelay, time);
+  });
+};
 
 const actionGroups = {
   SET_STATE: buildActionGroup({
@@ -603,15 +733,22 @@ const actionGroups = {
     },
   }),
   GATE: buildActionGroup({
-    actionCreator: (frequency: number, voiceIx: number, synthIx?: number, offset?: number) => ({
+    actionCreator: (
+      frequency: number,
+      voiceIx: number,
+      synthIx?: number,
+      offset?: number,
+      velocity?: number
+    ) => ({
       type: 'GATE',
       frequency,
       voiceIx,
       synthIx,
       offset,
+      velocity,
     }),
-    subReducer: (state: SynthDesignerState, { frequency, voiceIx, synthIx, offset }) => {
-      const setFreqForOsc = mkSetFreqForOsc(frequency, offset);
+    subReducer: (state: SynthDesignerState, { frequency, voiceIx, synthIx, offset, velocity }) => {
+      const gainLevel = velocityToGainLevel(velocity);
 
       // TODO: Dedup
       if (R.isNil(synthIx)) {
@@ -619,20 +756,20 @@ const actionGroups = {
           const targetVoice = synth.voices[voiceIx];
 
           // Trigger gain and filter ADSRs
-          targetVoice.gainADSRModule.gate(offset);
+          targetVoice.gainADSRModule.gate(offset, gainLevel);
           targetVoice.filterADSRModule.gate(offset);
 
-          targetVoice.oscillators.forEach(osc => setFreqForOsc(osc));
+          setVoiceFrequency(synth, targetVoice, frequency, offset);
         });
       } else {
         const targetSynth = getSynth(synthIx, state.synths);
         const targetVoice = targetSynth.voices[voiceIx];
 
         // Trigger gain and filter ADSRs
-        targetVoice.gainADSRModule.gate(offset);
+        targetVoice.gainADSRModule.gate(offset, gainLevel);
         targetVoice.filterADSRModule.gate(offset);
 
-        targetVoice.oscillators.forEach(osc => setFreqForOsc(osc));
+        setVoiceFrequency(targetSynth, targetVoice, frequency, offset);
       }
 
       return state;
@@ -666,6 +803,50 @@ const actionGroups = {
       return state;
     },
   }),
+  BEND_VOICE_PITCH: buildActionGroup({
+    actionCreator: (voiceIx: number, semitones: number, offset?: number) => ({
+      type: 'BEND_VOICE_PITCH',
+      voiceIx,
+      semitones,
+      offset,
+    }),
+    subReducer: (state: SynthDesignerState, { voiceIx, semitones, offset }) => {
+      const time = ctx.currentTime + Math.max(offset || 0, 0);
+      state.synths.forEach(({ voices }) =>
+        voices[voiceIx].oscillators.forEach(osc =>
+          osc.detune.linearRampToValueAtTime(semitones * 100, time)
+        )
+      );
+
+      return state;
+    },
+  }),
+  GLIDE_VOICE: buildActionGroup({
+    actionCreator: (
+      voiceIx: number,
+      fromFrequency: number,
+      toFrequency: number,
+      duration: number,
+      offset?: number
+    ) => ({ type: 'GLIDE_VOICE', voiceIx, fromFrequency, toFrequency, duration, offset }),
+    subReducer: (
+      state: SynthDesignerState,
+      { voiceIx, fromFrequency, toFrequency, duration, offset }
+    ) => {
+      const start = ctx.currentTime + Math.max(offset || 0, 0);
+      state.synths.forEach(synth =>
+        synth.voices[voiceIx].oscillators.forEach((osc, i) => {
+          const ratio = Math.pow(2, (synth.unisonLayout[i]?.detune ?? 0) / 1200);
+          // Replaces the jump to the new note's frequency scheduled when the voice was gated
+          osc.frequency.cancelScheduledValues(start);
+          osc.frequency.setValueAtTime(fromFrequency * ratio, start);
+          osc.frequency.exponentialRampToValueAtTime(toFrequency * ratio, start + duration);
+        })
+      );
+
+      return state;
+    },
+  }),
   SET_UNISON: buildActionGroup({
     actionCreator: (synthIx: number, unison: number) => ({ type: 'SET_UNISON', synthIx, unison }),
     subReducer: (state: SynthDesignerState, { synthIx, unison }) => {
@@ -676,33 +857,44 @@ const actionGroups = {
         return state;
       }
 
+      const oscillatorCount = getUnisonLayout(
+        unison,
+        targetSynth.unisonDetune,
+        targetSynth.unisonSpread
+      ).length;
       const newVoices = targetSynth.voices.map(voice => {
-        while (voice.oscillators.length > unison) {
-          const osc = voice.oscillators.pop()!;
-          osc.stop();
-          osc.disconnect();
+        while (voice.oscillators.length > oscillatorCount) {
+          disposeUnisonOscillator(voice.oscillators.pop()!, voice.unisonNodes.pop()!);
         }
 
-        while (voice.oscillators.length < unison) {
+        while (voice.oscillators.length < oscillatorCount) {
           const osc = new OscillatorNode(ctx);
-          // TODO: Set detune and other params here once they are implemented and stored in state
           // TODO: Keep track of playing state for all synths and trigger oscillators if synth is playing
           osc.type = targetSynth.waveform;
+          voice.frequencyCSN.connect(osc.frequency);
+          targetSynth.detuneCSN.connect(osc.detune);
           voice.oscillators.push(osc);
+          voice.unisonNodes.push(connectUnisonOscillator(osc, voice.filterNode, 0));
           osc.start();
         }
 
-        return { ...voice, oscillators: [...voice.oscillators] };
+        return {
+          ...voice,
+          oscillators: [...voice.oscillators],
+          unisonNodes: [...voice.unisonNodes],
+        };
       });
 
-      return {
-        ...state,
-        synths: [
-          ...state.synths.slice(0, synthIx),
-          { ...targetSynth, voices: newVoices },
-          ...state.synths.slice(synthIx + 1),
-        ],
-      };
+      return setSynth(synthIx, applyUnisonLayout({ ...targetSynth, voices: newVoices }), state);
+    },
+  }),
+  SET_UNISON_PARAM: buildActionGroup({
+    actionCreator<K extends keyof UnisonParams>(synthIx: number, key: K, val: UnisonParams[K]) {
+      return { type: 'SET_UNISON_PARAM', synthIx, key, val };
+    },
+    subReducer: (state: SynthDesignerState, { synthIx, key, val }) => {
+      const targetSynth = getSynth(synthIx, state.synths);
+      return setSynth(synthIx, applyUnisonLayout({ ...targetSynth, [key]: val }), state);
     },
   }),
   SET_DETUNE: buildActionGroup({
@@ -928,7 +1120,11 @@ const actionGroups = {
             );
           }
 
-          voice.oscillators.forEach(osc => osc.frequency.cancelScheduledValues(0));
+          voice.oscillators.forEach(osc => {
+            osc.frequency.cancelScheduledValues(0);
+            osc.detune.cancelScheduledValues(0);
+            osc.detune.setValueAtTime(0, ctx.currentTime);
+          });
         })
       );
 
@@ -968,6 +1164,25 @@ const actionGroups = {
       return { ...state, synths: R.set(R.lensIndex(voiceIx), builtVoice, state.synths) };
     },
   }),
+  SET_SYNTH_MODULES: buildActionGroup({
+    actionCreator: (modules: ReturnType<typeof serializeSynthModule>[]) => ({
+      type: 'SET_SYNTH_MODULES',
+      modules,
+    }),
+    subReducer: (state: SynthDesignerState, { modules }) => {
+      state.synths.forEach(disposeSynthModule);
+
+      const synths = modules.map(deserializeSynthModule);
+      if (state.wavyJonesInstance) {
+        synths
+          .flatMap(R.prop('voices'))
+          .map(R.prop('outerGainNode'))
+          .forEach(outerGainNode => outerGainNode.connect(state.wavyJonesInstance!));
+      }
+
+      return { ...state, synths };
+    },
+  }),
   SET_SYNTH_DESIGNER_IS_HIDDEN: buildActionGroup({
     actionCreator: (isHidden: boolean) => ({ type: 'SET_SYNTH_DESIGNER_IS_HIDDEN', isHidden }),
     subReducer: (state: SynthDesignerState, { isHidden }) => ({ ...state, isHidden }),
@@ -0,0 +1,395 @@
+import React, { useEffect, useState } from 'react';
+
+import { renderModalWithControls } from 'src/controls/Modal';
+import { SampleDescriptor } from 'src/sampleLibrary';
+import SampleSelectDialog from 'src/sampleLibrary/SampleLibraryUI/SelectSample';
+import { getTransportState } from 'src/transport';
+import {
+  applyEdit,
+  detectSlices,
+  getPeaks,
+  revert,
+  setLoopPoints,
+  setSlices,
+  FadeCurve,
+  SampleEdit,
+  SampleEditorInfo,
+} from './messages';
+import {
+  listMIDIEditors,
+  listSamplers,
+  mapSlicesToMIDIEditor,
+  openSample,
+  openSamplerSample,
+  saveEditedSample,
+  syncSampler,
+} from './sampleEditor';
+
+const CANVAS_WIDTH = 1200;
+const CANVAS_HEIGHT = 400;
+/**
+ * The view can't be zoomed in closer than this many frames across the whole canvas
+ */
+const MIN_VIEW_FRAMES = 64;
+const ZOOM_FACTOR = 1.2;
+
+interface FrameRange {
+  start: number;
+  end: number;
+}
+
+const selectSample = (): Promise<SampleDescriptor> => renderModalWithControls(SampleSelectDialog);
+
+const drawWaveform = (
+  ctx2d: CanvasRenderingContext2D,
+  info: SampleEditorInfo,
+  peaks: Float32Array,
+  view: FrameRange,
+  selection: FrameRange | null
+) => {
+  ctx2d.clearRect(0, 0, CANVAS_WIDTH, CANVAS_HEIGHT);
+  const frameToX = (frame: number) =>
+    ((frame - view.start) / (view.end - view.start)) * CANVAS_WIDTH;
+
+  if (selection) {
+    ctx2d.fillStyle = '#334';
+    const x = frameToX(selection.start);
+    ctx2d.fillRect(x, 0, frameToX(selection.end) - x, CANVAS_HEIGHT);
+  }
+
+  // Each channel gets an equal share of the height
+  const channelHeight = CANVAS_HEIGHT / Math.max(info.channelCount, 1);
+  ctx2d.fillStyle = '#3cf';
+  for (let channelIx = 0; channelIx < info.channelCount; channelIx++) {
+    const center = channelHeight * (channelIx + 0.5);
+    for (let x = 0; x < CANVAS_WIDTH; x++) {
+      const peakIx = (channelIx * CANVAS_WIDTH + x) * 2;
+      const [min, max] = [peaks[peakIx], peaks[peakIx + 1]];
+      const top = center - (max * channelHeight) / 2;
+      // Always draw at least a pixel so that silence is visible
+      ctx2d.fillRect(x, top, 1, Math.max(((max - min) * channelHeight) / 2, 1));
+    }
+  }
+
+  ctx2d.strokeStyle = '#fc3';
+  ctx2d.beginPath();
+  info.state.slices.forEach(slice => {
+    const x = frameToX(slice * info.lenFrames);
+    ctx2d.moveTo(x, 0);
+    ctx2d.lineTo(x, CANVAS_HEIGHT);
+  });
+  ctx2d.stroke();
+
+  ctx2d.strokeStyle = '#0f0';
+  ctx2d.beginPath();
+  [info.state.loopStart, info.state.loopEnd].forEach(loopPoint => {
+    const x = frameToX(loopPoint * info.lenFrames);
+    ctx2d.moveTo(x, 0);
+    ctx2d.lineTo(x, CANVAS_HEIGHT);
+  });
+  ctx2d.stroke();
+};
+
+const SampleEditorUI: React.FC<{
+  vcId: string;
+  initialInfo: Promise<SampleEditorInfo | null>;
+}> = ({ vcId, initialInfo }) => {
+  const [info, setInfo] = useState<SampleEditorInfo | null>(null);
+  const [view, setView] = useState<FrameRange>({ start: 0, end: 0 });
+  const [selection, setSelection] = useState<FrameRange | null>(null);
+  const [dragStartFrame, setDragStartFrame] = useState<number | null>(null);
+  const [fadeCurve, setFadeCurve] = useState<FadeCurve>('linear');
+  const [stretch, setStretch] = useState(1);
+  const [semitones, setSemitones] = useState(0);
+  const [loopBeats, setLoopBeats] = useState(4);
+  const [sliceSensitivity, setSliceSensitivity] = useState(0.5);
+  const [midiEditors, setMIDIEditors] = useState<{ vcId: string; title: string }[]>([]);
+  const [samplerVcIds, setSamplerVcIds] = useState<string[]>([]);
+  const [canvasRef, setCanvasRef] = useState<HTMLCanvasElement | null>(null);
+
+  const updateInfo = (newInfo: SampleEditorInfo | null, resetView = false) => {
+    if (!newInfo) {
+      return;
+    }
+    setInfo(newInfo);
+    if (resetView) {
+      setView({ start: 0, end: newInfo.lenFrames });
+      setSelection(null);
+    }
+  };
+
+  useEffect(() => {
+    initialInfo.then(newInfo => updateInfo(newInfo, true));
+    // eslint-disable-next-line react-hooks/exhaustive-deps
+  }, [initialInfo]);
+
+  useEffect(() => {
+    if (!canvasRef || !info?.isLoaded) {
+      return;
+    }
+    const peaks = getPeaks(vcId, Math.round(view.start), Math.round(view.end), CANVAS_WIDTH);
+    drawWaveform(canvasRef.getContext('2d')!, info, peaks, view, selection);
+  }, [canvasRef, vcId, info, view, selection]);
+
+  const xToFrame = (evt: React.MouseEvent<HTMLCanvasElement>) => {
+    const rect = evt.currentTarget.getBoundingClientRect();
+    const pos = Math.min(Math.max((evt.clientX - rect.left) / rect.width, 0), 1);
+    return Math.round(view.start + pos * (view.end - view.start));
+  };
+
+  const handleWheel = (evt: React.WheelEvent<HTMLCanvasElement>) => {
+    if (!info) {
+      return;
+    }
+    const viewFrames = view.end - view.start;
+    let newView: FrameRange;
+    if (evt.shiftKey) {
+      // Scroll through the sample
+      const offset = (Math.sign(evt.deltaY) * viewFrames) / 10;
+      newView = { start: view.start + offset, end: view.end + offset };
+    } else {
+      // Zoom in or out around the cursor
+      const rect = evt.currentTarget.getBoundingClientRect();
+      const pos = (evt.clientX - rect.left) / rect.width;
+      const newViewFrames = Math.min(
+        Math.max(viewFrames * (evt.deltaY > 0 ? ZOOM_FACTOR : 1 / ZOOM_FACTOR), MIN_VIEW_FRAMES),
+        info.lenFrames
+      );
+      const start = view.start + pos * (viewFrames - newViewFrames);
+      newView = { start, end: start + newViewFrames };
+    }
+
+    // Keep the view within the sample
+    const shift = Math.max(-newView.start, 0) - Math.max(newView.end - info.lenFrames, 0);
+    setView({ start: newView.start + shift, end: newView.end + shift });
+  };
+
+  const getEditRange = (): FrameRange =>
+    selection && selection.end > selection.start
+      ? selection
+      : { start: 0, end: info ? info.lenFrames : 0 };
+
+  const edit = (mkEdit: (range: FrameRange) => SampleEdit) => {
+    const sampleEdit = mkEdit(getEditRange());
+    // Trimming and stretching change the length of the sample, so the view is reset to show all
+    // of it
+    updateInfo(
+      applyEdit(vcId, sampleEdit),
+      sampleEdit.type === 'trim' || sampleEdit.type === 'time_stretch'
+    );
+  };
+
+  /**
+   * Stretches the selection, or the whole sample if nothing is selected, so that it lasts
+   * `loopBeats` beats at the tempo of the project
+   */
+  const conformToTempo = () => {
+    if (!info || loopBeats <= 0) {
+      return;
+    }
+    const targetFrames = ((loopBeats * 60) / getTransportState().bpm) * info.sampleRate;
+    edit(range => ({
+      type: 'time_stretch',
+      ...range,
+      stretch: targetFrames / Math.max(range.end - range.start, 1),
+    }));
+  };
+
+  const setLoopToSelection = () => {
+    if (!info || !selection || info.lenFrames === 0) {
+      return;
+    }
+    const newInfo = setLoopPoints(
+      vcId,
+      selection.start / info.lenFrames,
+      selection.end / info.lenFrames
+    );
+    updateInfo(newInfo);
+    if (newInfo) {
+      syncSampler(newInfo);
+    }
+  };
+
+  /**
+   * Slices are sent to the sampler right away so that they can be played from the MIDI editor
+   */
+  const updateSlices = (newInfo: SampleEditorInfo | null) => {
+    updateInfo(newInfo);
+    if (newInfo) {
+      syncSampler(newInfo);
+    }
+  };
+
+  const runAsync = async (action: () => Promise<SampleEditorInfo | null>) => {
+    try {
+      updateInfo(await action(), true);
+    } catch (err) {
+      if (err) {
+        console.error('Sample editor action failed: ', err);
+      }
+    }
+  };
+
+  return (
+    <div className='sample-editor'>
+      <div>
+        <button onClick={() => runAsync(async () => openSample(vcId, await selectSample()))}>
+          Open Sample
+        </button>
+        <select
+          value=''
+          onFocus={() => setSamplerVcIds(listSamplers())}
+          onChange={evt => runAsync(() => openSamplerSample(vcId, evt.target.value))}
+        >
+          <option value='' disabled>
+            Open from sampler...
+          </option>
+          {samplerVcIds.map(samplerVcId => (
+            <option key={samplerVcId} value={samplerVcId}>
+              Sampler {samplerVcId}
+            </option>
+          ))}
+        </select>
+        {info?.state.sample ? (
+          <span>
+            {' '}
+            {info.state.sample.name} ({(info.lenFrames / info.sampleRate).toFixed(3)}s)
+            {info.isModified ? ' (modified)' : ''}
+          </span>
+        ) : null}
+      </div>
+
+      <canvas
+        ref={setCanvasRef}
+        width={CANVAS_WIDTH}
+        height={CANVAS_HEIGHT}
+        style={{ backgroundColor: '#111' }}
+        onWheel={handleWheel}
+        onMouseDown={evt => {
+          const frame = xToFrame(evt);
+          setDragStartFrame(frame);
+          setSelection({ start: frame, end: frame });
+        }}
+        onMouseMove={evt => {
+          if (dragStartFrame === null) {
+            return;
+          }
+          const frame = xToFrame(evt);
+          setSelection({
+            start: Math.min(dragStartFrame, frame),
+            end: Math.max(dragStartFrame, frame),
+          });
+        }}
+        onMouseUp={() => setDragStartFrame(null)}
+        onMouseLeave={() => setDragStartFrame(null)}
+      />
+
+      <div>
+        <button onClick={() => edit(range => ({ type: 'trim', ...range }))}>Trim</button>
+        <button onClick={() => edit(range => ({ type: 'normalize', ...range, peak_db: 0 }))}>
+          Normalize
+        </button>
+        <button onClick={() => edit(range => ({ type: 'reverse', ...range }))}>Reverse</button>
+        <button onClick={() => edit(range => ({ type: 'fade_in', ...range, curve: fadeCurve }))}>
+          Fade In
+        </button>
+        <button onClick={() => edit(range => ({ type: 'fade_out', ...range, curve: fadeCurve }))}>
+          Fade Out
+        </button>
+        <select value={fadeCurve} onChange={evt => setFadeCurve(evt.target.value as FadeCurve)}>
+          <option value='linear'>linear</option>
+          <option value='exponential'>exponential</option>
+          <option value='equal_power'>equal power</option>
+        </select>
+        <button onClick={setLoopToSelection}>Set Loop to Selection</button>
+      </div>
+
+      <div>
+        <input
+          type='number'
+          step={0.01}
+          min={0.01}
+          value={stretch}
+          onChange={evt => setStretch(+evt.target.value)}
+        />
+        <button
+          disabled={stretch <= 0}
+          onClick={() => edit(range => ({ type: 'time_stretch', ...range, stretch }))}
+        >
+          Time Stretch
+        </button>
+        <input
+          type='number'
+          step={1}
+          value={semitones}
+          onChange={evt => setSemitones(+evt.target.value)}
+        />
+        <button onClick={() => edit(range => ({ type: 'pitch_shift', ...range, semitones }))}>
+          Pitch Shift (semitones)
+        </button>
+        <input
+          type='number'
+          step={1}
+          min={1}
+          value={loopBeats}
+          onChange={evt => setLoopBeats(+evt.target.value)}
+        />
+        <button onClick={conformToTempo}>Conform to Tempo (beats)</button>
+      </div>
+
+      <div>
+        Slice sensitivity{' '}
+        <input
+          type='range'
+          min={0}
+          max={1}
+          step={0.01}
+          value={sliceSensitivity}
+          onChange={evt => setSliceSensitivity(+evt.target.value)}
+        />
+        <button
+          onClick={() =>
+            updateSlices(
+              detectSlices(vcId, { sensitivity: sliceSensitivity, minIntervalSeconds: 0.05 })
+            )
+          }
+        >
+          Detect Slices
+        </button>
+        <button onClick={() => updateSlices(setSlices(vcId, []))}>Clear Slices</button>
+        <select
+          value=''
+          disabled={!info || info.state.slices.length === 0}
+          onFocus={() => setMIDIEditors(listMIDIEditors())}
+          onChange={evt => {
+            try {
+              if (info) {
+                mapSlicesToMIDIEditor(info, evt.target.value);
+              }
+            } catch (err) {
+              console.error(err);
+            }
+          }}
+        >
+          <option value='' disabled>
+            Map slices to MIDI editor...
+          </option>
+          {midiEditors.map(({ vcId: midiEditorVcId, title }) => (
+            <option key={midiEditorVcId} value={midiEditorVcId}>
+              {title}
+            </option>
+          ))}
+        </select>
+        <button onClick={() => updateInfo(revert(vcId), true)}>Revert</button>
+        <button
+          disabled={!info?.isModified}
+          onClick={() => info && runAsync(() => saveEditedSample(vcId, info))}
+        >
+          Save
+        </button>
+      </div>
+    </div>
+  );
+};
+
+export default SampleEditorUI;
@@ -0,0 +1,93 @@
+/**
+ * View context for viewing and editing samples.  The sample data, waveform peaks, and edits all
+ * live in the engine; this handles loading samples into the sample pool, rendering the UI, and
+ * sending loop points over to samplers.
+ */
+
+import { mkContainerRenderHelper, mkContainerCleanupHelper } from 'src/reactUtils';
+import { tryParseJson } from 'src/util';
+import { getInfo, SampleEditorInfo, SampleEditorState } from './messages';
+import { openSample } from './sampleEditor';
+import SampleEditorUI from './SampleEditorUI';
+
+const getVcId = (stateKey: string) => stateKey.split('_')[1]!;
+
+const getSampleEditorDOMElementId = (vcId: string) => `sampleEditor-${vcId}`;
+
+/**
+ * Re-opens the sample that was open when the editor was last saved.  Any edits that weren't saved
+ * at the time are lost.
+ */
+const reopenSample = async (
+  vcId: string,
+  state: SampleEditorState | null
+): Promise<SampleEditorInfo | null> => {
+  if (!state?.sample) {
+    return getInfo(vcId);
+  }
+
+  try {
+    return await openSample(vcId, state.sample, true);
+  } catch (err) {
+    console.error(`Unable to re-open sample "${state.sample.name}" in sample editor: `, err);
+    return getInfo(vcId);
+  }
+};
+
+export const init_sample_editor = (stateKey: string, stateJson: string) => {
+  const vcId = getVcId(stateKey);
+  const initialState = tryParseJson<SampleEditorState | null>(
+    stateJson,
+    null,
+    `Failed to parse state for sample editor with stateKey ${stateKey}`
+  );
+
+  const domId = getSampleEditorDOMElementId(vcId);
+  const elem = document.createElement('div');
+  elem.id = domId;
+  elem.setAttribute(
+    'style',
+    'z-index: 2; width: 100vw; height: 100vh; position: absolute; top: 0; left: 0; display: none;'
+  );
+  document.getElementById('content')!.appendChild(elem);
+
+  // The engine isn't done initializing the VC until this returns, so the sample is opened after
+  const initialInfo = Promise.resolve().then(() => reopenSample(vcId, initialState));
+  mkContainerRenderHelper({
+    Comp: SampleEditorUI,
+    getProps: () => ({ vcId, initialInfo }),
+  })(domId);
+};
+
+export const cleanup_sample_editor = (stateKey: string) => {
+  const domId = getSampleEditorDOMElementId(getVcId(stateKey));
+  mkContainerCleanupHelper()(domId);
+  const elem = document.getElementById(domId);
+  if (elem) {
+    elem.remove();
+  }
+};
+
+export const hide_sample_editor = (stateKey: string) => {
+  const vcId = getVcId(stateKey);
+  const elem = document.getElementById(getSampleEditorDOMElementId(vcId));
+  if (!elem) {
+    console.error(`Unable to find DOM element for sample editor with vcId ${vcId}; can't hide.`);
+    return;
+  }
+
+  elem.style.display = 'none';
+};
+
+export const unhide_sample_editor = (stateKey: string) => {
+  const vcId = getVcId(stateKey);
+  const elem = document.getElementById(getSampleEditorDOMElementId(vcId));
+  if (!elem) {
+    console.error(
+      `Unable to find DOM element for sample editor with vcId ${vcId}; can't unhide.`
+    );
+    return;
+  }
+
+  elem.style.display = 'block';
+};
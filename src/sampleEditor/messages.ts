@@ -0,0 +1,132 @@
+import { getEngine } from 'src';
+import { SampleDescriptor } from 'src/sampleLibrary';
+
+/**
+ * Mirrors `SampleEditorState` in the engine
+ */
+export interface SampleEditorState {
+  sample: SampleDescriptor | null;
+  samplerVcId: string | null;
+  /**
+   * Loop points as a fraction of the length of the sample in the range [0, 1]
+   */
+  loopStart: number;
+  loopEnd: number;
+  /**
+   * Start of each slice as a fraction of the length of the sample, in ascending order
+   */
+  slices: number[];
+}
+
+export interface SampleEditorInfo {
+  state: SampleEditorState;
+  isLoaded: boolean;
+  lenFrames: number;
+  channelCount: number;
+  sampleRate: number;
+  /**
+   * Set once the sample has been edited and until it's saved or reverted
+   */
+  isModified: boolean;
+}
+
+/**
+ * Mirrors `TransientDetectionConf` in the engine
+ */
+export interface TransientDetectionConf {
+  /**
+   * In [0, 1]; higher values detect quieter and less sudden transients
+   */
+  sensitivity: number;
+  minIntervalSeconds: number;
+}
+
+export type FadeCurve = 'linear' | 'exponential' | 'equal_power';
+
+/**
+ * Mirrors `SampleEdit` in the engine.  All edits apply to the frames in `[start, end)`.
+ */
+export type SampleEdit =
+  | { type: 'trim'; start: number; end: number }
+  | { type: 'normalize'; start: number; end: number; peak_db: number }
+  | { type: 'reverse'; start: number; end: number }
+  | { type: 'fade_in'; start: number; end: number; curve: FadeCurve }
+  | { type: 'fade_out'; start: number; end: number; curve: FadeCurve }
+  | { type: 'time_stretch'; start: number; end: number; stretch: number }
+  | { type: 'pitch_shift'; start: number; end: number; semitones: number };
+
+const sendSampleEditorMessage = (vcId: string, key: string, val: Uint8Array) => {
+  const engine = getEngine();
+  if (!engine) {
+    console.error('Tried to message sample editor before the engine was initialized');
+    return undefined;
+  }
+
+  return engine.handle_vc_message(vcId, key, val);
+};
+
+const encodeJson = (val: any) => new TextEncoder().encode(JSON.stringify(val));
+
+const decodeInfo = (res: Uint8Array | undefined): SampleEditorInfo | null =>
+  res ? JSON.parse(new TextDecoder().decode(res)) : null;
+
+export const getInfo = (vcId: string) =>
+  decodeInfo(sendSampleEditorMessage(vcId, 'get_info', new Uint8Array()));
+
+/**
+ * Opens the sample with the provided ID in the sample pool.  The sample editor takes over the
+ * reference to it, so it must not be released by the caller.
+ */
+export const loadSample = (
+  vcId: string,
+  sample: SampleDescriptor,
+  poolId: number,
+  keepLoopPoints = false
+) =>
+  decodeInfo(
+    sendSampleEditorMessage(vcId, 'load_sample', encodeJson({ sample, poolId, keepLoopPoints }))
+  );
+
+export const setSampler = (vcId: string, samplerVcId: string | null) =>
+  decodeInfo(sendSampleEditorMessage(vcId, 'set_sampler', encodeJson(samplerVcId)));
+
+export const setLoopPoints = (vcId: string, start: number, end: number) =>
+  decodeInfo(sendSampleEditorMessage(vcId, 'set_loop_points', encodeJson({ start, end })));
+
+export const applyEdit = (vcId: string, edit: SampleEdit) =>
+  decodeInfo(sendSampleEditorMessage(vcId, 'apply_edit', encodeJson(edit)));
+
+export const revert = (vcId: string) =>
+  decodeInfo(sendSampleEditorMessage(vcId, 'revert', new Uint8Array()));
+
+/**
+ * Slices the sample at each of its transients, replacing the current slices
+ */
+export const detectSlices = (vcId: string, conf: TransientDetectionConf) =>
+  decodeInfo(sendSampleEditorMessage(vcId, 'detect_slices', encodeJson(conf)));
+
+export const setSlices = (vcId: string, slices: number[]) =>
+  decodeInfo(sendSampleEditorMessage(vcId, 'set_slices', encodeJson(slices)));
+
+/**
+ * Returns `pixelCount` `(min, max)` pairs for each channel of the frames in `[start, end)`, all of
+ * the first channel's followed by the second's.
+ */
+export const getPeaks = (
+  vcId: string,
+  start: number,
+  end: number,
+  pixelCount: number
+): Float32Array => {
+  const args = new Uint32Array([start, end, pixelCount]);
+  const res = sendSampleEditorMessage(vcId, 'get_peaks', new Uint8Array(args.buffer));
+  return res
+    ? new Float32Array(res.buffer, res.byteOffset, res.byteLength / 4)
+    : new Float32Array();
+};
+
+/**
+ * Returns the edited sample encoded as a 24-bit WAV file
+ */
+export const exportWav = (vcId: string): Uint8Array | undefined =>
+  sendSampleEditorMessage(vcId, 'export_wav', new Uint8Array());
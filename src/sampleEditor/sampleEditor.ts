@@ -0,0 +1,163 @@
+import { Sampler, SamplerParams } from 'src/graphEditor/nodes/CustomAudio/Sampler';
+import { getEngine } from 'src';
+import { getState } from 'src/redux';
+import { SampleDescriptor } from 'src/sampleLibrary';
+import { cacheSample } from 'src/sampleLibrary/sampleCache';
+import { importSample } from 'src/sampleLibrary/sampleImport';
+import { getTransportState } from 'src/transport';
+import { exportWav, loadSample, setLoopPoints, setSampler, SampleEditorInfo } from './messages';
+
+const ctx = new AudioContext();
+
+/**
+ * Returns the VC IDs of all samplers in the patch network
+ */
+export const listSamplers = (): string[] =>
+  getState()
+    .viewContextManager.patchNetwork.connectables.filter(
+      connectables => connectables?.node instanceof Sampler
+    )
+    .keySeq()
+    .toArray();
+
+const getSampler = (samplerVcId: string | null): Sampler | null => {
+  if (samplerVcId === null) {
+    return null;
+  }
+  const node = getState().viewContextManager.patchNetwork.connectables.get(samplerVcId)?.node;
+  return node instanceof Sampler ? node : null;
+};
+
+/**
+ * Imports the sample into the sample pool at the sample rate of the audio context and opens it in
+ * the sample editor
+ */
+export const openSample = async (
+  vcId: string,
+  descriptor: SampleDescriptor,
+  keepLoopPoints = false
+): Promise<SampleEditorInfo | null> => {
+  const { id } = await importSample(descriptor, ctx.sampleRate);
+  return loadSample(vcId, descriptor, id, keepLoopPoints);
+};
+
+/**
+ * Opens the sample that's loaded into a sampler along with its loop points.  Loop points edited
+ * after this are sent back to that sampler.
+ */
+export const openSamplerSample = async (
+  vcId: string,
+  samplerVcId: string
+): Promise<SampleEditorInfo | null> => {
+  const sampler = getSampler(samplerVcId);
+  if (!sampler) {
+    throw new Error(`No sampler found with VC ID ${samplerVcId}`);
+  }
+  const params = sampler.serialize() as SamplerParams;
+  if (!params.sample) {
+    throw new Error('The sampler has no sample loaded');
+  }
+
+  setSampler(vcId, samplerVcId);
+  await openSample(vcId, params.sample);
+  return setLoopPoints(vcId, params.loopStart, params.loopEnd);
+};
+
+/**
+ * Sends the loop points and slices to the sampler that the sample was opened from, if any.  While
+ * the sample has unsaved edits, they refer to the edited audio rather than the audio that the
+ * sampler is playing, so they're held back until it's saved.
+ */
+export const syncSampler = (info: SampleEditorInfo) => {
+  const sampler = getSampler(info.state.samplerVcId);
+  if (!sampler || info.isModified || !info.state.sample) {
+    return;
+  }
+
+  const params = sampler.serialize() as SamplerParams;
+  sampler.setParams({
+    ...params,
+    sample: info.state.sample,
+    loopStart: info.state.loopStart,
+    loopEnd: info.state.loopEnd,
+    slices: info.state.slices,
+  });
+};
+
+/**
+ * Returns the VC IDs and titles of all MIDI editors
+ */
+export const listMIDIEditors = (): { vcId: string; title: string }[] =>
+  getState()
+    .viewContextManager.activeViewContexts.filter(({ name }) => name === 'midi_editor')
+    .map(({ uuid, title }) => ({
+      vcId: uuid,
+      title: title || `MIDI Editor (${uuid.slice(0, 8)})`,
+    }));
+
+/**
+ * Maps each slice to a line of the MIDI editor and writes a pattern into it that plays the loop
+ * back at the project's tempo.  The MIDI editor is set to play on the sampler that the sample was
+ * opened from, which plays the slices once they've been sent to it.
+ */
+export const mapSlicesToMIDIEditor = (info: SampleEditorInfo, midiEditorVcId: string) => {
+  const engine = getEngine();
+  if (!engine || info.state.slices.length === 0 || info.sampleRate === 0) {
+    return;
+  }
+
+  const sampler = getSampler(info.state.samplerVcId);
+  const sliceBaseNote = sampler ? (sampler.serialize() as SamplerParams).sliceBaseNote : 36;
+  const beatsPerFrame = getTransportState().bpm / 60 / info.sampleRate;
+  const mapping = {
+    baseNote: sliceBaseNote,
+    sliceStartBeats: info.state.slices.map(slice => slice * info.lenFrames * beatsPerFrame),
+    endBeat: info.lenFrames * beatsPerFrame,
+  };
+  const res = engine.handle_vc_message(
+    midiEditorVcId,
+    'map_slices',
+    new TextEncoder().encode(JSON.stringify(mapping))
+  );
+  if (!res || res[0] !== 1) {
+    throw new Error(
+      "Unable to map slices to MIDI editor; its other clips may have notes that aren't slices"
+    );
+  }
+
+  if (sampler && info.state.samplerVcId) {
+    const assignment = { kind: 'sampler', id: info.state.samplerVcId };
+    engine.handle_vc_message(
+      midiEditorVcId,
+      'set_instrument',
+      new TextEncoder().encode(JSON.stringify(assignment))
+    );
+  }
+};
+
+/**
+ * Stores the edited sample in the sample library as a new sample, re-opens it in the editor, and
+ * loads it into the sampler that the original was opened from.
+ */
+export const saveEditedSample = async (
+  vcId: string,
+  info: SampleEditorInfo
+): Promise<SampleEditorInfo | null> => {
+  const wav = exportWav(vcId);
+  if (!wav || !info.state.sample) {
+    return null;
+  }
+
+  const baseName = info.state.sample.name.replace(/\.[^/.]+$/, '');
+  const descriptor: SampleDescriptor = {
+    isLocal: true,
+    name: `edited/${baseName}-${new Date().toISOString()}.wav`,
+  };
+  await cacheSample(descriptor, wav.buffer);
+
+  const newInfo = await openSample(vcId, descriptor, true);
+  if (newInfo) {
+    syncSampler(newInfo);
+  }
+  return newInfo;
+};
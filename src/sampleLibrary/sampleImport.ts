@@ -0,0 +1,102 @@
+/**
+ * Imports samples into the engine's sample pool, which converts them to the sample rate that
+ * they're played back at and keeps track of how much memory the loaded samples take up.  WAV files
+ * are decoded by the engine directly; other formats are decoded by the browser first and their PCM
+ * is handed over instead.
+ */
+
+import { getEngine } from 'src';
+import {
+  SampleDescriptor,
+  getSampleData,
+  hashSampleDescriptor,
+} from 'src/sampleLibrary/sampleLibrary';
+
+export interface ImportedSample {
+  /**
+   * ID of the sample in the engine's sample pool.  It must be passed to `releaseImportedSample`
+   * once the sample is no longer needed so that its memory can be freed.
+   */
+  id: number;
+  /**
+   * At most two channels, all the same length
+   */
+  channels: Float32Array[];
+  sampleRate: number;
+}
+
+const ctx = new AudioContext();
+
+const readImportedSample = (id: number, sampleRate: number): ImportedSample => {
+  const engine = getEngine()!;
+  const channelCount: number = engine.get_imported_sample_channel_count(id);
+  const planar: Float32Array = engine.get_imported_sample_planar(id);
+  const length = channelCount === 0 ? 0 : planar.length / channelCount;
+  const channels = [...Array(channelCount).keys()].map(channelIx =>
+    planar.subarray(channelIx * length, (channelIx + 1) * length)
+  );
+  return { id, channels, sampleRate };
+};
+
+/**
+ * Decodes the sample with the browser and hands its PCM over to the engine to be imported
+ */
+const importDecodedSample = async (
+  name: string,
+  sampleData: ArrayBuffer,
+  targetSampleRate: number
+): Promise<number | undefined> => {
+  const buffer = await ctx.decodeAudioData(sampleData.slice(0));
+  const channelCount = Math.min(buffer.numberOfChannels, 2);
+  const planar = new Float32Array(buffer.length * channelCount);
+  for (let channelIx = 0; channelIx < channelCount; channelIx++) {
+    planar.set(buffer.getChannelData(channelIx), channelIx * buffer.length);
+  }
+
+  return getEngine()!.import_pcm_sample(
+    name,
+    planar,
+    channelCount,
+    buffer.sampleRate,
+    targetSampleRate
+  );
+};
+
+/**
+ * Loads a sample into the engine's sample pool at `targetSampleRate`, re-using the already loaded
+ * copy if it's been imported before.  Throws if the sample couldn't be decoded or if there isn't
+ * enough room left in the pool for it.
+ */
+export const importSample = async (
+  descriptor: SampleDescriptor,
+  targetSampleRate: number
+): Promise<ImportedSample> => {
+  const engine = getEngine()!;
+  const name = hashSampleDescriptor(descriptor);
+  const existingId: number | undefined = engine.acquire_imported_sample(name, targetSampleRate);
+  if (existingId !== undefined) {
+    return readImportedSample(existingId, targetSampleRate);
+  }
+
+  const sampleData = await getSampleData(descriptor);
+  const bytes = new Uint8Array(sampleData);
+  let id: number | undefined = engine.is_wav_file(bytes)
+    ? engine.import_wav_sample(name, bytes, targetSampleRate)
+    : undefined;
+  // The browser supports more formats than the engine does, including WAV encodings like ADPCM
+  if (id === undefined) {
+    id = await importDecodedSample(name, sampleData, targetSampleRate);
+  }
+  if (id === undefined) {
+    throw new Error(`Failed to import sample "${descriptor.name}"; see the console for details`);
+  }
+
+  return readImportedSample(id, targetSampleRate);
+};
+
+export const releaseImportedSample = (id: number) => getEngine()!.release_imported_sample(id);
+
+/**
+ * Returns the number of bytes of memory taken up by all of the samples in the sample pool
+ */
+export const getSamplePoolBytesUsed = (): number => getEngine()!.get_sample_pool_bytes_used();
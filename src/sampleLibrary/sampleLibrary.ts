@@ -94,30 +94,34 @@ export const listSamples = async ({
   return [...allSamples.values()];
 };
 
-export const getSample = async (descriptor: SampleDescriptor): Promise<AudioBuffer> => {
-  // First we check the highest level of cache, the in-memory sample manager
-  const cachedSample = GLOBAL_SAMPLE_MANAGER.getSample(descriptor);
-  if (cachedSample) {
-    return cachedSample;
-  }
-
-  // Then we check the second level of cache, the on-disk IndexedDB cache to see if we have the desired sample available
+/**
+ * Returns the raw, un-decoded contents of the sample's file, loading it from its original source
+ * and adding it to the on-disk cache if it isn't cached already.
+ */
+export const getSampleData = async (descriptor: SampleDescriptor): Promise<ArrayBuffer> => {
   const diskCachedSample = await getCachedSample(descriptor);
   if (!R.isNil(diskCachedSample)) {
-    const buf = await ctx.decodeAudioData(diskCachedSample);
-    // Add it to the top level cache
-    GLOBAL_SAMPLE_MANAGER.setSample(descriptor, buf);
-    return buf;
+    return diskCachedSample;
   }
 
-  // We don't have the sample available, so we load it from its original source
   const sampleData = await (descriptor.isLocal
     ? loadLocalSample(descriptor)
     : loadRemoteSample(descriptor));
-  const buf = await ctx.decodeAudioData(sampleData.slice(0));
-
-  // Add it to both levels of cache
   cacheSample(descriptor, sampleData);
+  return sampleData;
+};
+
+export const getSample = async (descriptor: SampleDescriptor): Promise<AudioBuffer> => {
+  // First we check the highest level of cache, the in-memory sample manager
+  const cachedSample = GLOBAL_SAMPLE_MANAGER.getSample(descriptor);
+  if (cachedSample) {
+    return cachedSample;
+  }
+
+  // Then we load the file, either from the on-disk IndexedDB cache or from its original source
+  const sampleData = await getSampleData(descriptor);
+  // Decoding detaches the buffer, so a copy is decoded to leave the cached one intact
+  const buf = await ctx.decodeAudioData(sampleData.slice(0));
   GLOBAL_SAMPLE_MANAGER.setSample(descriptor, buf);
 
   return buf;
@@ -0,0 +1,142 @@
+import React, { useEffect, useState } from 'react';
+import ControlPanel from 'react-control-panel';
+
+import { setConf, SpectrumAnalyzerConf } from './messages';
+
+const CANVAS_WIDTH = 1200;
+const CANVAS_HEIGHT = 500;
+/**
+ * Bins are drawn from this level up to 0 dB
+ */
+const MIN_DISPLAYED_DB = -100;
+const GRID_FREQUENCIES = [50, 100, 200, 500, 1000, 2000, 5000, 10000];
+
+/**
+ * Mirrors the default `AnalyzerConf` in the engine
+ */
+const DEFAULT_CONF: SpectrumAnalyzerConf = {
+  fft_size: 4096,
+  smoothing: 0.8,
+  bin_count: 256,
+  min_freq: 20,
+  max_freq: 20000,
+  tap: 'master',
+};
+
+const SETTINGS = [
+  {
+    type: 'select',
+    label: 'fft size',
+    options: ['256', '512', '1024', '2048', '4096', '8192', '16384', '32768'],
+  },
+  { type: 'range', label: 'smoothing', min: 0, max: 0.99, step: 0.01 },
+  { type: 'range', label: 'bin count', min: 16, max: 1024, step: 1 },
+  { type: 'range', label: 'min freq', min: 1, max: 1000, scale: 'log', steps: 200 },
+  { type: 'range', label: 'max freq', min: 1000, max: 24000, scale: 'log', steps: 200 },
+  { type: 'select', label: 'tap', options: ['master', 'input'] },
+];
+
+const CONF_KEYS: { [label: string]: keyof SpectrumAnalyzerConf } = {
+  smoothing: 'smoothing',
+  'bin count': 'bin_count',
+  'min freq': 'min_freq',
+  'max freq': 'max_freq',
+  tap: 'tap',
+};
+
+const freqToX = (conf: SpectrumAnalyzerConf, freq: number) =>
+  (Math.log(freq / conf.min_freq) / Math.log(conf.max_freq / conf.min_freq)) * CANVAS_WIDTH;
+
+const dbToY = (db: number) =>
+  (Math.min(Math.max(db, MIN_DISPLAYED_DB), 0) / MIN_DISPLAYED_DB) * CANVAS_HEIGHT;
+
+const drawGrid = (ctx2d: CanvasRenderingContext2D, conf: SpectrumAnalyzerConf) => {
+  ctx2d.strokeStyle = '#333';
+  ctx2d.fillStyle = '#888';
+  ctx2d.beginPath();
+  GRID_FREQUENCIES.filter(freq => freq > conf.min_freq && freq < conf.max_freq).forEach(freq => {
+    const x = freqToX(conf, freq);
+    ctx2d.moveTo(x, 0);
+    ctx2d.lineTo(x, CANVAS_HEIGHT);
+    ctx2d.fillText(freq >= 1000 ? `${freq / 1000}k` : `${freq}`, x + 2, CANVAS_HEIGHT - 4);
+  });
+  for (let db = -20; db > MIN_DISPLAYED_DB; db -= 20) {
+    ctx2d.moveTo(0, dbToY(db));
+    ctx2d.lineTo(CANVAS_WIDTH, dbToY(db));
+    ctx2d.fillText(`${db} dB`, 2, dbToY(db) - 2);
+  }
+  ctx2d.stroke();
+};
+
+const drawBins = (
+  ctx2d: CanvasRenderingContext2D,
+  conf: SpectrumAnalyzerConf,
+  bins: Float32Array
+) => {
+  ctx2d.clearRect(0, 0, CANVAS_WIDTH, CANVAS_HEIGHT);
+  drawGrid(ctx2d, conf);
+
+  // Bins are evenly spaced on the log-frequency axis
+  const binWidth = CANVAS_WIDTH / bins.length;
+  ctx2d.fillStyle = '#0c8';
+  bins.forEach((db, binIx) => {
+    const y = dbToY(db);
+    ctx2d.fillRect(binIx * binWidth, y, Math.max(binWidth - 1, 1), CANVAS_HEIGHT - y);
+  });
+};
+
+const SpectrumAnalyzerUI: React.FC<{
+  vcId: string;
+  initialConf: SpectrumAnalyzerConf | null;
+  onConfChange: (conf: SpectrumAnalyzerConf) => void;
+  subscribe: (onBins: ((bins: Float32Array) => void) | null) => void;
+}> = ({ vcId, initialConf, onConfChange, subscribe }) => {
+  const [conf, setLocalConf] = useState(initialConf || DEFAULT_CONF);
+  const [canvasRef, setCanvasRef] = useState<HTMLCanvasElement | null>(null);
+
+  useEffect(() => {
+    if (!canvasRef) {
+      return;
+    }
+
+    const ctx2d = canvasRef.getContext('2d')!;
+    subscribe(bins => drawBins(ctx2d, conf, bins));
+    return () => subscribe(null);
+  }, [canvasRef, conf, subscribe]);
+
+  return (
+    <div className='spectrum-analyzer'>
+      <canvas
+        ref={setCanvasRef}
+        width={CANVAS_WIDTH}
+        height={CANVAS_HEIGHT}
+        style={{ backgroundColor: '#000' }}
+      />
+      <ControlPanel
+        style={{ width: 500 }}
+        settings={SETTINGS}
+        state={{
+          'fft size': `${conf.fft_size}`,
+          smoothing: conf.smoothing,
+          'bin count': conf.bin_count,
+          'min freq': conf.min_freq,
+          'max freq': conf.max_freq,
+          tap: conf.tap,
+        }}
+        onChange={(key: string, val: any) => {
+          const newConf =
+            key === 'fft size' ? { ...conf, fft_size: +val } : { ...conf, [CONF_KEYS[key]]: val };
+          const sanitizedConf = setConf(vcId, newConf);
+          if (!sanitizedConf) {
+            return;
+          }
+
+          setLocalConf(sanitizedConf);
+          onConfChange(sanitizedConf);
+        }}
+      />
+    </div>
+  );
+};
+
+export default SpectrumAnalyzerUI;
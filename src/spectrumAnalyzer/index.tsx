@@ -0,0 +1,162 @@
+/**
+ * View context for a spectrum analyzer.  Audio is tapped here and sent to the engine, which
+ * analyzes it; the resulting bins are fetched and rendered every frame while the view is visible.
+ */
+
+import { Map as ImmMap } from 'immutable';
+
+import { AudioTap } from 'src/audioTap';
+import {
+  AudioConnectables,
+  ConnectableInput,
+  ConnectableOutput,
+  create_empty_audio_connectables,
+} from 'src/patchNetwork';
+import { mkContainerRenderHelper, mkContainerCleanupHelper } from 'src/reactUtils';
+import { store } from 'src/redux';
+import { tryParseJson } from 'src/util';
+import { getBins, pushSamples, SpectrumAnalyzerConf } from './messages';
+import SpectrumAnalyzerUI from './SpectrumAnalyzerUI';
+
+const ctx = new AudioContext();
+
+interface SpectrumAnalyzerInstance {
+  tap: AudioTap;
+  renderRAFHandle: number | null;
+  /**
+   * Set by the UI to be called with the latest bins every animation frame
+   */
+  onBins: ((bins: Float32Array) => void) | null;
+}
+
+const spectrumAnalyzers: Map<string, SpectrumAnalyzerInstance> = new Map();
+
+const getVcId = (stateKey: string) => stateKey.split('_')[1]!;
+
+const getSpectrumAnalyzerDOMElementId = (vcId: string) => `spectrum-analyzer-${vcId}`;
+
+const stopRendering = (instance: SpectrumAnalyzerInstance) => {
+  if (instance.renderRAFHandle !== null) {
+    cancelAnimationFrame(instance.renderRAFHandle);
+    instance.renderRAFHandle = null;
+  }
+};
+
+export const init_spectrum_analyzer = (stateKey: string, confJson: string): number => {
+  const vcId = getVcId(stateKey);
+  const initialConf = tryParseJson<SpectrumAnalyzerConf | null>(
+    confJson,
+    null,
+    `Failed to parse config for spectrum analyzer with stateKey ${stateKey}`
+  );
+  const instance: SpectrumAnalyzerInstance = {
+    tap: new AudioTap(ctx, initialConf?.tap || 'master', samples => pushSamples(vcId, samples)),
+    renderRAFHandle: null,
+    onBins: null,
+  };
+  spectrumAnalyzers.set(vcId, instance);
+
+  const domId = getSpectrumAnalyzerDOMElementId(vcId);
+  const elem = document.createElement('div');
+  elem.id = domId;
+  elem.setAttribute(
+    'style',
+    'z-index: 2; width: 100vw; height: 100vh; position: absolute; top: 0; left: 0; display: none;'
+  );
+  document.getElementById('content')!.appendChild(elem);
+
+  mkContainerRenderHelper({
+    Comp: SpectrumAnalyzerUI,
+    store,
+    getProps: () => ({
+      vcId,
+      initialConf,
+      onConfChange: (conf: SpectrumAnalyzerConf) => instance.tap.setLocation(conf.tap),
+      subscribe: (onBins: SpectrumAnalyzerInstance['onBins']) => {
+        instance.onBins = onBins;
+      },
+    }),
+  })(domId);
+
+  return ctx.sampleRate;
+};
+
+export const cleanup_spectrum_analyzer = (stateKey: string) => {
+  const vcId = getVcId(stateKey);
+  const instance = spectrumAnalyzers.get(vcId);
+  if (instance) {
+    stopRendering(instance);
+    instance.tap.destroy();
+    spectrumAnalyzers.delete(vcId);
+  }
+
+  const domId = getSpectrumAnalyzerDOMElementId(vcId);
+  mkContainerCleanupHelper()(domId);
+  const elem = document.getElementById(domId);
+  if (elem) {
+    elem.remove();
+  }
+};
+
+export const hide_spectrum_analyzer = (stateKey: string) => {
+  const vcId = getVcId(stateKey);
+  const elem = document.getElementById(getSpectrumAnalyzerDOMElementId(vcId));
+  if (!elem) {
+    console.error(
+      `Unable to find DOM element for spectrum analyzer with vcId ${vcId}; can't hide.`
+    );
+    return;
+  }
+
+  elem.style.display = 'none';
+  const instance = spectrumAnalyzers.get(vcId);
+  if (instance) {
+    stopRendering(instance);
+    instance.tap.setIsActive(false);
+  }
+};
+
+export const unhide_spectrum_analyzer = (stateKey: string) => {
+  const vcId = getVcId(stateKey);
+  const elem = document.getElementById(getSpectrumAnalyzerDOMElementId(vcId));
+  if (!elem) {
+    console.error(
+      `Unable to find DOM element for spectrum analyzer with vcId ${vcId}; can't unhide.`
+    );
+    return;
+  }
+
+  elem.style.display = 'block';
+  const instance = spectrumAnalyzers.get(vcId);
+  if (!instance) {
+    return;
+  }
+
+  instance.tap.setIsActive(true);
+  stopRendering(instance);
+  const tick = () => {
+    if (instance.onBins) {
+      instance.onBins(getBins(vcId));
+    }
+    instance.renderRAFHandle = requestAnimationFrame(tick);
+  };
+  instance.renderRAFHandle = requestAnimationFrame(tick);
+};
+
+export const get_spectrum_analyzer_audio_connectables = (stateKey: string): AudioConnectables => {
+  const vcId = getVcId(stateKey);
+  const instance = spectrumAnalyzers.get(vcId);
+  if (!instance) {
+    console.warn(`No spectrum analyzer found for VC with VC ID "${vcId}"`);
+    return create_empty_audio_connectables(vcId);
+  }
+
+  return {
+    vcId,
+    inputs: ImmMap<string, ConnectableInput>().set('input', {
+      node: instance.tap.input,
+      type: 'customAudio',
+    }),
+    outputs: ImmMap<string, ConnectableOutput>(),
+  };
+};
@@ -0,0 +1,52 @@
+import { getEngine } from 'src';
+import { TapLocation } from 'src/audioTap';
+
+/**
+ * Mirrors `AnalyzerConf` in the engine
+ */
+export interface SpectrumAnalyzerConf {
+  fft_size: number;
+  smoothing: number;
+  bin_count: number;
+  min_freq: number;
+  max_freq: number;
+  tap: TapLocation;
+}
+
+const sendSpectrumAnalyzerMessage = (vcId: string, key: string, val: Uint8Array) => {
+  const engine = getEngine();
+  if (!engine) {
+    console.error('Tried to message spectrum analyzer before the engine was initialized');
+    return undefined;
+  }
+
+  return engine.handle_vc_message(vcId, key, val);
+};
+
+const decodeF32s = (res: Uint8Array | undefined): Float32Array =>
+  res ? new Float32Array(res.buffer, res.byteOffset, res.byteLength / 4) : new Float32Array();
+
+export const pushSamples = (vcId: string, samples: Float32Array) =>
+  sendSpectrumAnalyzerMessage(
+    vcId,
+    'push_samples',
+    new Uint8Array(samples.buffer, samples.byteOffset, samples.byteLength)
+  );
+
+/**
+ * Returns the level of each bin in dB, from lowest to highest frequency
+ */
+export const getBins = (vcId: string): Float32Array =>
+  decodeF32s(sendSpectrumAnalyzerMessage(vcId, 'get_bins', new Uint8Array()));
+
+/**
+ * Returns the new config, which may have been adjusted by the engine to fit into valid ranges
+ */
+export const setConf = (vcId: string, conf: SpectrumAnalyzerConf): SpectrumAnalyzerConf | null => {
+  const res = sendSpectrumAnalyzerMessage(
+    vcId,
+    'set_conf',
+    new TextEncoder().encode(JSON.stringify(conf))
+  );
+  return res ? JSON.parse(new TextDecoder().decode(res)) : null;
+};
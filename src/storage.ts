@@ -0,0 +1,90 @@
+/**
+ * Storage backends used by the engine for its saved state.  IndexedDB can hold much more data
+ * than `localStorage`, but it's asynchronous while the engine reads its state synchronously.  All
+ * entries are loaded into memory before the engine is initialized, and changes are written back
+ * to IndexedDB in the background.
+ */
+
+const DB_NAME = 'web-synth';
+const STORE_NAME = 'engineState';
+
+let db: IDBDatabase | null = null;
+const indexedDBCache: Map<string, string> = new Map();
+
+const openDB = (): Promise<IDBDatabase> =>
+  new Promise((resolve, reject) => {
+    const req = indexedDB.open(DB_NAME, 1);
+    req.onupgradeneeded = () => req.result.createObjectStore(STORE_NAME);
+    req.onsuccess = () => resolve(req.result);
+    req.onerror = () => reject(req.error);
+  });
+
+/**
+ * Opens the database and loads all of its entries into memory.  Resolves to `false` if IndexedDB
+ * isn't available, in which case `localStorage` should be used instead.
+ */
+export const loadIndexedDBStorage = async (): Promise<boolean> => {
+  if (typeof indexedDB === 'undefined') {
+    return false;
+  }
+
+  try {
+    db = await openDB();
+    const store = db.transaction(STORE_NAME, 'readonly').objectStore(STORE_NAME);
+    await new Promise<void>((resolve, reject) => {
+      const req = store.openCursor();
+      req.onsuccess = () => {
+        const cursor = req.result;
+        if (!cursor) {
+          resolve();
+          return;
+        }
+        indexedDBCache.set(cursor.key as string, cursor.value);
+        cursor.continue();
+      };
+      req.onerror = () => reject(req.error);
+    });
+    return true;
+  } catch (err) {
+    console.error('Failed to load IndexedDB storage; falling back to `localStorage`: ', err);
+    db = null;
+    return false;
+  }
+};
+
+const writeToDB = (write: (store: IDBObjectStore) => IDBRequest) => {
+  if (!db) {
+    return;
+  }
+
+  const req = write(db.transaction(STORE_NAME, 'readwrite').objectStore(STORE_NAME));
+  req.onerror = () => console.error('Failed to write to IndexedDB storage: ', req.error);
+};
+
+export const list_localstorage_keys = (): string => JSON.stringify(Object.keys(localStorage));
+
+export const get_indexeddb_key = (key: string): string | undefined => indexedDBCache.get(key);
+
+export const set_indexeddb_key = (key: string, val: string) => {
+  indexedDBCache.set(key, val);
+  writeToDB(store => store.put(val, key));
+};
+
+export const delete_indexeddb_key = (key: string) => {
+  indexedDBCache.delete(key);
+  writeToDB(store => store.delete(key));
+};
+
+export const list_indexeddb_keys = (): string => JSON.stringify([...indexedDBCache.keys()]);
+
+/**
+ * Returns every stored entry, including those in IndexedDB, for exporting or sharing the full
+ * application state.  Entries in `localStorage` take precedence just like they do in the engine.
+ */
+export const getAllStoredEntries = (): { [key: string]: string } => {
+  const entries: { [key: string]: string } = {};
+  indexedDBCache.forEach((val, key) => {
+    entries[key] = val;
+  });
+  return { ...entries, ...localStorage };
+};
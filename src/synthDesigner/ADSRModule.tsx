@@ -9,6 +9,11 @@ export class ADSRModule extends ConstantSourceNode {
   public maxValue: number;
   private lengthMs: number;
   private envelope: ADSRValues = defaultAdsrEnvelope;
+  /**
+   * Multiplier applied to the range of the envelope for the most recent gate, used to scale the
+   * output by note velocity
+   */
+  private gateLevel = 1;
 
   constructor(
     ctx: AudioContext,
@@ -44,21 +49,40 @@ export class ADSRModule extends ConstantSourceNode {
     this.envelope = newEnvelope;
   }
 
+  /**
+   * Cancels all ramps scheduled after `time` while holding the envelope at the level it has at that
+   * time, so that new ramps start from wherever the envelope currently is rather than jumping.
+   */
+  private holdAtTime(time: number) {
+    if ('cancelAndHoldAtTime' in this.offset) {
+      this.offset.cancelAndHoldAtTime(time);
+      return;
+    }
+
+    // Fall back to holding the current value for browsers that don't support `cancelAndHoldAtTime`
+    const curValue = this.offset.value;
+    this.offset.cancelScheduledValues(time);
+    this.offset.setValueAtTime(curValue, time);
+  }
+
   /**
    * Triggers the ADSR to implement the signal, triggering ramps to each of the levels defined by the envelope to the
-   * underlying `ConstantSourceNode` and effecting all connected `AudioParam`s
+   * underlying `ConstantSourceNode` and effecting all connected `AudioParam`s.  `level` scales the
+   * peak of the envelope and should be in the range [0, 1].
    */
-  public gate(offset?: number) {
+  public gate(offset?: number, level = 1) {
+    this.gateLevel = level;
+
     // start out off at the minimum
     if (R.isNil(offset)) {
-      this.offset.cancelScheduledValues(0);
+      this.holdAtTime(this.ctx.currentTime);
       this.offset.linearRampToValueAtTime(this.minValue, this.ctx.currentTime + 0.0001);
     } else {
       this.offset.setValueAtTime(this.minValue, this.ctx.currentTime + offset);
     }
 
     const realOffset = Option.of(offset).getOrElse(0);
-    const range = this.maxValue - this.minValue;
+    const range = (this.maxValue - this.minValue) * this.gateLevel;
     const { attack, decay } = this.envelope;
 
     // Ramp to the attack
@@ -75,15 +99,18 @@ export class ADSRModule extends ConstantSourceNode {
 
   /**
    * Triggers the start of the release.  This will override all other envelope ramp events that are currently queued
-   * and start ramping to zero immediately.
+   * and start ramping to zero from the envelope's current level, letting the note ring out for the
+   * duration of the release.
    */
   public ungate(offset?: number) {
-    const range = this.maxValue - this.minValue;
+    const range = (this.maxValue - this.minValue) * this.gateLevel;
     const { release, decay } = this.envelope;
 
     if (R.isNil(offset)) {
-      // Clear any queued ramp events
-      this.offset.cancelScheduledValues(0);
+      // Clear any queued ramp events, holding at the current level
+      this.holdAtTime(this.ctx.currentTime);
+    } else if ('cancelAndHoldAtTime' in this.offset) {
+      this.offset.cancelAndHoldAtTime(this.ctx.currentTime + offset);
     } else {
       this.offset.cancelScheduledValues(this.ctx.currentTime + offset);
       this.offset.linearRampToValueAtTime(
@@ -1,4 +1,4 @@
-import React, { useEffect, useRef } from 'react';
+import React, { useEffect, useRef, useState } from 'react';
 import { connect, Provider } from 'react-redux';
 import * as R from 'ramda';
 import ControlPanel from 'react-control-panel';
@@ -15,6 +15,19 @@ import {
 import { updateConnectables } from 'src/patchNetwork';
 import SynthModuleComp from './SynthModule';
 import EffectModuleComp from './effects/Effect';
+import {
+  listSynthPresets,
+  saveSynthPreset,
+  loadSynthPreset,
+  deleteSynthPreset,
+  SynthPresetListing,
+} from './patchPresets';
+import {
+  TuningConf,
+  getSynthDesignerTuning,
+  setSynthDesignerTuning,
+  describeTuning,
+} from './tuning';
 import './SynthDesigner.scss';
 import { ReduxStore, store } from 'src/redux';
 import { PropTypesOf } from 'ameo-utils';
@@ -91,6 +104,117 @@ const AddModuleControls: React.FC<Omit<
   </Provider>
 );
 
+/**
+ * Controls for saving and loading presets of the whole synth designer, which are managed by the
+ * engine.  Messages for them are handled by the active VC, so the list of presets is fetched
+ * whenever this synth designer is shown.
+ */
+const PresetControls: React.FC<{ isHidden: boolean }> = ({ isHidden }) => {
+  const [presets, setPresets] = useState<SynthPresetListing[]>([]);
+  const [selectedName, setSelectedName] = useState('');
+  useEffect(() => {
+    if (isHidden) {
+      return;
+    }
+
+    const listedPresets = listSynthPresets();
+    setPresets(listedPresets);
+    setSelectedName(name => name || listedPresets[0]?.name || '');
+  }, [isHidden]);
+  const selectedPreset = presets.find(({ name }) => name === selectedName);
+
+  return (
+    <div className='synth-presets'>
+      <select value={selectedName} onChange={evt => setSelectedName(evt.target.value)}>
+        {presets.map(({ name, is_factory }) => (
+          <option key={name} value={name}>
+            {is_factory ? `${name} (factory)` : name}
+          </option>
+        ))}
+      </select>
+      <button disabled={!selectedPreset} onClick={() => setPresets(loadSynthPreset(selectedName))}>
+        Load Preset
+      </button>
+      <button
+        onClick={() => {
+          const name = prompt('Preset name');
+          if (name) {
+            setPresets(saveSynthPreset(name));
+            setSelectedName(name);
+          }
+        }}
+      >
+        Save Preset
+      </button>
+      <button
+        disabled={!selectedPreset || selectedPreset.is_factory}
+        onClick={() => setPresets(deleteSynthPreset(selectedName))}
+      >
+        Delete Preset
+      </button>
+    </div>
+  );
+};
+
+/**
+ * Returns a change handler for file inputs that reads the selected file as text
+ */
+const readTextFile = (onLoad: (contents: string) => void) => (
+  evt: React.ChangeEvent<HTMLInputElement>
+) => {
+  const file = evt.target.files?.[0];
+  if (file) {
+    file.text().then(onLoad);
+  }
+  // Allow the same file to be selected again after editing it
+  evt.target.value = '';
+};
+
+const TuningControls: React.FC<{ isHidden: boolean }> = ({ isHidden }) => {
+  const [tuning, setTuning] = useState<TuningConf | null>(null);
+  const [error, setError] = useState<string | null>(null);
+  useEffect(() => {
+    if (!isHidden) {
+      setTuning(getSynthDesignerTuning());
+    }
+  }, [isHidden]);
+
+  const applyTuning = (newTuning: TuningConf | null) => {
+    const err = setSynthDesignerTuning(newTuning);
+    setError(err);
+    if (!err) {
+      setTuning(newTuning);
+    }
+  };
+
+  return (
+    <div className='synth-tuning'>
+      <span>Tuning: {describeTuning(tuning)}</span>
+      <label>
+        Load .scl
+        <input
+          type='file'
+          accept='.scl'
+          onChange={readTextFile(scl => applyTuning({ scl, kbm: tuning?.kbm ?? null }))}
+        />
+      </label>
+      <label>
+        Load .kbm
+        <input
+          type='file'
+          accept='.kbm'
+          disabled={!tuning}
+          onChange={readTextFile(kbm => applyTuning({ scl: tuning!.scl, kbm }))}
+        />
+      </label>
+      <button disabled={!tuning} onClick={() => applyTuning(null)}>
+        Reset to 12-TET
+      </button>
+      {error ? <span className='synth-tuning-error'>{error}</span> : null}
+    </div>
+  );
+};
+
 const mapStateToProps = ({ synthDesigner }: SynthDesignerReduxStore) => ({
   synthDesignerState: synthDesigner,
 });
@@ -122,6 +246,8 @@ const SynthDesigner: React.FC<{ stateKey: string } & ReturnType<typeof mapStateT
 
   return (
     <>
+      <PresetControls isHidden={synthDesignerState.isHidden} />
+      <TuningControls isHidden={synthDesignerState.isHidden} />
       <div className='synth-designer'>
         {synthDesignerState.synths.map((synth, i) => (
           <SynthModuleComp key={i} synth={synth} index={i} stateKey={stateKey}>
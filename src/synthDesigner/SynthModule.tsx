@@ -39,9 +39,26 @@ const SYNTH_SETTINGS = [
     label: 'unison',
     min: 1,
     initial: 1,
-    max: 32,
+    max: 16,
     step: 1,
   },
+  {
+    type: 'range',
+    label: 'unison detune',
+    min: 0,
+    initial: 0,
+    max: 100,
+    step: 0.5,
+  },
+  {
+    type: 'range',
+    label: 'stereo spread',
+    min: 0,
+    initial: 0,
+    max: 1,
+    step: 0.01,
+  },
+  { type: 'checkbox', label: 'random phase', initial: false },
   {
     type: 'range',
     label: 'detune',
@@ -105,6 +122,24 @@ const SynthModuleCompInner: React.FC<{
               dispatch(actionCreators.synthDesigner.SET_UNISON(index, val));
               break;
             }
+            case 'unison detune': {
+              dispatch(actionCreators.synthDesigner.SET_UNISON_PARAM(index, 'unisonDetune', val));
+              break;
+            }
+            case 'stereo spread': {
+              dispatch(actionCreators.synthDesigner.SET_UNISON_PARAM(index, 'unisonSpread', val));
+              break;
+            }
+            case 'random phase': {
+              dispatch(
+                actionCreators.synthDesigner.SET_UNISON_PARAM(
+                  index,
+                  'unisonPhaseRandomization',
+                  val
+                )
+              );
+              break;
+            }
             case 'volume': {
               dispatch(actionCreators.synthDesigner.SET_SYNTH_MASTER_GAIN(index, val));
               break;
@@ -127,11 +162,23 @@ const SynthModuleCompInner: React.FC<{
             waveform: synth.waveform,
             volume: synth.masterGain,
             unison,
+            'unison detune': synth.unisonDetune,
+            'stereo spread': synth.unisonSpread,
+            'random phase': synth.unisonPhaseRandomization,
             detune: synth.detune,
             adsr: synth.gainEnvelope,
           }),
           // eslint-disable-next-line react-hooks/exhaustive-deps
-          [synth.waveform, unison, synth.masterGain, synth.detune, synth.gainEnvelope]
+          [
+            synth.waveform,
+            unison,
+            synth.unisonDetune,
+            synth.unisonSpread,
+            synth.unisonPhaseRandomization,
+            synth.masterGain,
+            synth.detune,
+            synth.gainEnvelope,
+          ]
         )}
         style={{ width: 378 }}
       />
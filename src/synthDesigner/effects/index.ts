@@ -1,5 +1,16 @@
 import { UnimplementedError } from 'ameo-utils';
 import { EffectType, Effect } from 'src/redux/modules/synthDesigner';
+import {
+  ALL_DISTORTION_CURVES,
+  ALL_OVERSAMPLE_TYPES,
+  DEFAULT_DISTORTION_PARAMS,
+  DistortionChain,
+  DISTORTION_CURVES,
+  MAX_DISTORTION_DRIVE,
+  MAX_DISTORTION_TONE,
+  MIN_DISTORTION_DRIVE,
+  MIN_DISTORTION_TONE,
+} from 'src/graphEditor/nodes/CustomAudio/Distortion/distortionChain';
 
 export interface EffectNode extends AudioNode {
   setParam: (key: string, val: number) => void;
@@ -21,14 +32,92 @@ export class Bitcrusher extends AudioWorkletNode {
   public getDefaultParams = () => ({ bits: 4 });
 }
 
-export class Distortion extends WaveShaperNode {
+/**
+ * Effects are connected to as a single node, so the distortion node itself is the input of its
+ * chain while connecting or disconnecting it acts on the output of its chain.
+ *
+ * Params are numbers, so the curve and oversampling are set by their index in
+ * `ALL_DISTORTION_CURVES` and `ALL_OVERSAMPLE_TYPES`.
+ */
+export class Distortion extends GainNode {
+  private chain: DistortionChain;
+
+  constructor(audioContext: AudioContext) {
+    super(audioContext);
+    this.chain = new DistortionChain(audioContext, DEFAULT_DISTORTION_PARAMS);
+    super.connect(this.chain.shaper);
+  }
+
+  public connect(destination: any, output?: number, input?: number): any {
+    return this.chain.toneFilter.connect(destination, output, input);
+  }
+
+  public disconnect(...args: any[]) {
+    (this.chain.toneFilter as any).disconnect(...args);
+  }
+
   public setParam = (key: string, val: number) => {
-    throw new UnimplementedError();
+    switch (key) {
+      case 'curve': {
+        this.chain.setParams({ curve: ALL_DISTORTION_CURVES[+val] });
+        break;
+      }
+      case 'drive': {
+        this.chain.setParams({ drive: val });
+        break;
+      }
+      case 'tone': {
+        this.chain.setParams({ tone: val });
+        break;
+      }
+      case 'oversample': {
+        this.chain.setParams({ oversample: ALL_OVERSAMPLE_TYPES[+val] });
+        break;
+      }
+      default: {
+        console.error(`Unhandled distortion param: ${key}`);
+      }
+    }
   };
 
-  public getSettingDefs = () => [] as { [key: string]: any }[];
+  public getSettingDefs = () => [
+    {
+      type: 'select',
+      label: 'curve',
+      options: Object.keys(DISTORTION_CURVES).reduce(
+        (acc, label, i) => ({ ...acc, [label]: i }),
+        {}
+      ),
+    },
+    {
+      type: 'range',
+      label: 'drive',
+      min: MIN_DISTORTION_DRIVE,
+      max: MAX_DISTORTION_DRIVE,
+      scale: 'log',
+      steps: 200,
+    },
+    {
+      type: 'range',
+      label: 'tone',
+      min: MIN_DISTORTION_TONE,
+      max: MAX_DISTORTION_TONE,
+      scale: 'log',
+      steps: 500,
+    },
+    {
+      type: 'select',
+      label: 'oversample',
+      options: ALL_OVERSAMPLE_TYPES.reduce((acc, type, i) => ({ ...acc, [type]: i }), {}),
+    },
+  ];
 
-  public getDefaultParams = () => ({} as { [key: string]: any });
+  public getDefaultParams = () => ({
+    curve: ALL_DISTORTION_CURVES.indexOf(DEFAULT_DISTORTION_PARAMS.curve),
+    drive: DEFAULT_DISTORTION_PARAMS.drive,
+    tone: DEFAULT_DISTORTION_PARAMS.tone,
+    oversample: ALL_OVERSAMPLE_TYPES.indexOf(DEFAULT_DISTORTION_PARAMS.oversample),
+  });
 }
 
 export class Reverb extends ConvolverNode {
@@ -13,10 +13,16 @@ import {
   getInitialSynthDesignerState,
 } from 'src/redux/modules/synthDesigner';
 import SynthDesigner from './SynthDesigner';
-import { AudioConnectables, ConnectableInput, ConnectableOutput } from 'src/patchNetwork';
+import {
+  AudioConnectables,
+  ConnectableInput,
+  ConnectableOutput,
+  updateConnectables,
+} from 'src/patchNetwork';
 import synthDesignerModule from 'src/redux/modules/synthDesigner';
 import { buildMIDINode } from 'src/patchNetwork/midiNode';
 import { midiToFrequency } from 'src/util';
+import { getModMatrix, ModRoute } from './modMatrix';
 
 const buildSynthDesignerRedux = () => {
   const modules = {
@@ -173,21 +179,81 @@ function memoizeOne<F extends (...args: any[]) => any>(
   return memoized;
 }
 
+/**
+ * Frequencies of every MIDI note for synth designers that have a tuning other than 12-TET set
+ */
+const TuningTables: Map<string, Float32Array> = new Map();
+
 const memoizedGetMidiNode = memoizeOne((stateKey: string) => {
   const { dispatch, actionCreators } = getReduxInfra(stateKey);
 
+  const modMatrix = getModMatrix(stateKey);
+
   return buildMIDINode(() => ({
-    onAttack: (note: number, voiceIx: number, _velocity: number, offset?: number) =>
+    onAttack: (note: number, voiceIx: number, velocity: number, offset?: number) => {
+      const frequency = midiToFrequency(note, TuningTables.get(stateKey));
+      // Notes that the tuning leaves unmapped don't play
+      if (frequency === 0) {
+        return;
+      }
+
+      modMatrix.onAttack(velocity, offset);
       dispatch(
-        actionCreators.synthDesigner.GATE(midiToFrequency(note), voiceIx, undefined, offset)
-      ),
-    onRelease: (_note: number, voiceIx: number, _velocity: number, offset?: number) =>
-      dispatch(actionCreators.synthDesigner.UNGATE(voiceIx, undefined, offset)),
+        actionCreators.synthDesigner.GATE(
+          frequency,
+          voiceIx,
+          undefined,
+          offset,
+          velocity
+        )
+      );
+    },
+    onRelease: (_note: number, voiceIx: number, _velocity: number, offset?: number) => {
+      modMatrix.onRelease(offset);
+      dispatch(actionCreators.synthDesigner.UNGATE(voiceIx, undefined, offset));
+    },
     onPitchBend: () => {
       // No-op; TODO?
     },
-    onClearAll: (stopPlayingNotes: boolean) =>
-      dispatch(actionCreators.synthDesigner.CLEAR_ALL_SCHEDULED_MIDI_EVENTS(stopPlayingNotes)),
+    onVoicePitchBend: (_note: number, voiceIx: number, semitones: number, offset?: number) =>
+      dispatch(actionCreators.synthDesigner.BEND_VOICE_PITCH(voiceIx, semitones, offset)),
+    onVoiceGlide: (
+      fromNote: number,
+      note: number,
+      voiceIx: number,
+      duration: number,
+      offset?: number
+    ) => {
+      const tuningTable = TuningTables.get(stateKey);
+      const fromFrequency = midiToFrequency(fromNote, tuningTable);
+      const toFrequency = midiToFrequency(note, tuningTable);
+      if (fromFrequency === 0 || toFrequency === 0) {
+        return;
+      }
+
+      dispatch(
+        actionCreators.synthDesigner.GLIDE_VOICE(
+          voiceIx,
+          fromFrequency,
+          toFrequency,
+          duration,
+          offset
+        )
+      );
+    },
+    onVoiceExpression: (
+      _note: number,
+      _voiceIx: number,
+      dimension: 'pressure' | 'timbre',
+      value: number,
+      offset?: number
+    ) => modMatrix.onExpression(dimension, value, offset),
+    onClearAll: (stopPlayingNotes: boolean) => {
+      modMatrix.onClearAll();
+      dispatch(actionCreators.synthDesigner.CLEAR_ALL_SCHEDULED_MIDI_EVENTS(stopPlayingNotes));
+    },
+    onGenericControl: (controlIndex: number, value: number) =>
+      modMatrix.onGenericControl(controlIndex, value),
   }));
 });
 
@@ -225,3 +291,47 @@ export const get_synth_designer_audio_connectables = (stateKey: string): AudioCo
     }),
   };
 };
+
+/**
+ * Called by the engine every time the modulation routes of the synth designer change.  All
+ * `number` inputs of the synth designer's audio connectables are valid destinations.
+ */
+export const set_synth_designer_mod_routes = (stateKey: string, routesJson: string) => {
+  const routes: ModRoute[] = JSON.parse(routesJson);
+  const destinations = get_synth_designer_audio_connectables(stateKey)
+    .inputs.filter(({ type }) => type === 'number')
+    .map(({ node }) => node as AudioParam);
+
+  getModMatrix(stateKey).setRoutes(routes, destinations);
+};
+
+/**
+ * Called by the engine to snapshot the settings of all synth modules when saving a preset
+ */
+export const get_synth_designer_modules = (stateKey: string): string =>
+  JSON.stringify(getReduxInfra(stateKey).getState().synthDesigner.synths.map(serializeSynthModule));
+
+/**
+ * Called by the engine to replace all synth modules with the ones from a preset.  The engine sends
+ * the preset's mod routes afterwards.
+ */
+export const set_synth_designer_modules = (stateKey: string, modulesJson: string) => {
+  const { dispatch, actionCreators } = getReduxInfra(stateKey);
+  dispatch(actionCreators.synthDesigner.SET_SYNTH_MODULES(JSON.parse(modulesJson)));
+
+  const vcId = stateKey.split('_')[1]!;
+  updateConnectables(vcId, get_synth_designer_audio_connectables(stateKey));
+};
+
+/**
+ * Called by the engine when the synth designer's tuning changes with the frequency of every MIDI
+ * note.  The table is empty for 12-TET.
+ */
+export const set_synth_designer_tuning = (stateKey: string, frequencies: Float32Array) => {
+  if (frequencies.length === 0) {
+    TuningTables.delete(stateKey);
+  } else {
+    // The table is a view into the engine's memory, so it's copied to keep it around
+    TuningTables.set(stateKey, frequencies.slice());
+  }
+};
@@ -0,0 +1,236 @@
+import { getEngine } from 'src';
+import { Messages, sendBinaryMessage } from 'src/engineMessages';
+import { ADSRModule } from 'src/synthDesigner/ADSRModule';
+import {
+  buildNoiseSource,
+  buildSampleAndHoldSource,
+  NoiseColor,
+} from 'src/graphEditor/nodes/CustomAudio/Noise/noiseBuffers';
+
+/**
+ * Mirrors the `ModSource` enum from the engine's `mod_matrix` module
+ */
+export type ModSource =
+  | { type: 'lfo'; waveform: 'sine' | 'triangle' | 'square' | 'sawtooth'; frequency: number }
+  | { type: 'envelope'; attack_ms: number; decay_ms: number; sustain: number; release_ms: number }
+  | { type: 'noise'; color: NoiseColor }
+  | { type: 'sample_and_hold'; frequency: number }
+  | { type: 'velocity' }
+  | { type: 'pressure' }
+  | { type: 'timbre' }
+  | { type: 'mod_wheel' }
+  | { type: 'midi_cc'; cc: number };
+
+export interface ModRouteDefinition {
+  source: ModSource;
+  /**
+   * The name of a `number` input of the synth designer's audio connectables
+   */
+  destination: string;
+  depth: number;
+}
+
+export interface ModRoute extends ModRouteDefinition {
+  id: number;
+}
+
+interface BuiltRoute {
+  sourceNode: AudioNode;
+  /**
+   * Set if the source node was created for this route alone and should be stopped when the route
+   * is torn down
+   */
+  ownedSource: AudioScheduledSourceNode | null;
+  depthNode: GainNode;
+}
+
+const ctx = new AudioContext();
+
+/**
+ * Builds the audio graph for the modulation routes of a synth designer.  The routes themselves are
+ * owned by the engine, which calls `set_synth_designer_mod_routes` every time they change.
+ */
+export class ModMatrix {
+  private builtRoutes: BuiltRoute[] = [];
+  private envelopes: ADSRModule[] = [];
+  private velocityCSN: ConstantSourceNode;
+  /**
+   * MPE expression of the most recently expressed voice
+   */
+  private expressionCSNs: { pressure: ConstantSourceNode; timbre: ConstantSourceNode };
+  private controlCSNs: Map<number, ConstantSourceNode> = new Map();
+  private heldNoteCount = 0;
+
+  constructor() {
+    this.velocityCSN = new ConstantSourceNode(ctx, { offset: 0 });
+    this.velocityCSN.start();
+    this.expressionCSNs = {
+      pressure: new ConstantSourceNode(ctx, { offset: 0 }),
+      timbre: new ConstantSourceNode(ctx, { offset: 0 }),
+    };
+    this.expressionCSNs.pressure.start();
+    this.expressionCSNs.timbre.start();
+  }
+
+  private getControlCSN(controlIndex: number): ConstantSourceNode {
+    const existing = this.controlCSNs.get(controlIndex);
+    if (existing) {
+      return existing;
+    }
+
+    const csn = new ConstantSourceNode(ctx, { offset: 0 });
+    csn.start();
+    this.controlCSNs.set(controlIndex, csn);
+    return csn;
+  }
+
+  private buildSource(source: ModSource): Pick<BuiltRoute, 'sourceNode' | 'ownedSource'> {
+    switch (source.type) {
+      case 'lfo': {
+        const osc = new OscillatorNode(ctx, {
+          type: source.waveform,
+          frequency: source.frequency,
+        });
+        osc.start();
+        return { sourceNode: osc, ownedSource: osc };
+      }
+      case 'envelope': {
+        const { attack_ms, decay_ms, sustain, release_ms } = source;
+        const lengthMs = Math.max(attack_ms + decay_ms + release_ms, 1);
+        const envelope = new ADSRModule(ctx, { lengthMs });
+        envelope.setEnvelope({
+          attack: { pos: attack_ms / lengthMs, magnitude: 1 },
+          decay: { pos: (attack_ms + decay_ms) / lengthMs, magnitude: sustain },
+          // The release lasts for `(1 - release.pos) * lengthMs`
+          release: { pos: (attack_ms + decay_ms) / lengthMs, magnitude: sustain },
+        });
+        envelope.start();
+        this.envelopes.push(envelope);
+        return { sourceNode: envelope, ownedSource: envelope };
+      }
+      case 'noise': {
+        const noise = buildNoiseSource(ctx, source.color);
+        return { sourceNode: noise, ownedSource: noise };
+      }
+      case 'sample_and_hold': {
+        const sampleAndHold = buildSampleAndHoldSource(ctx, source.frequency);
+        return { sourceNode: sampleAndHold, ownedSource: sampleAndHold };
+      }
+      case 'velocity':
+        return { sourceNode: this.velocityCSN, ownedSource: null };
+      case 'pressure':
+      case 'timbre':
+        return { sourceNode: this.expressionCSNs[source.type], ownedSource: null };
+      case 'mod_wheel':
+        return { sourceNode: this.getControlCSN(1), ownedSource: null };
+      case 'midi_cc':
+        return { sourceNode: this.getControlCSN(source.cc), ownedSource: null };
+      default:
+        throw new Error(`Unhandled mod source type: ${(source as any).type}`);
+    }
+  }
+
+  private teardown() {
+    this.builtRoutes.forEach(({ sourceNode, ownedSource, depthNode }) => {
+      depthNode.disconnect();
+      if (ownedSource) {
+        ownedSource.stop();
+        ownedSource.disconnect();
+      } else {
+        sourceNode.disconnect(depthNode);
+      }
+    });
+    this.builtRoutes = [];
+    this.envelopes = [];
+  }
+
+  public setRoutes(routes: ModRoute[], destinations: Pick<Map<string, AudioParam>, 'get'>) {
+    this.teardown();
+
+    routes.forEach(({ source, destination, depth }) => {
+      const param = destinations.get(destination);
+      if (!param) {
+        console.warn(`Mod route has unknown destination "${destination}"; skipping`);
+        return;
+      }
+
+      const { sourceNode, ownedSource } = this.buildSource(source);
+      const depthNode = new GainNode(ctx, { gain: depth });
+      sourceNode.connect(depthNode);
+      depthNode.connect(param);
+      this.builtRoutes.push({ sourceNode, ownedSource, depthNode });
+    });
+  }
+
+  public onAttack(velocity: number, offset?: number) {
+    this.velocityCSN.offset.setValueAtTime(velocity / 127, ctx.currentTime + (offset || 0));
+    this.heldNoteCount += 1;
+    this.envelopes.forEach(envelope => envelope.gate(offset));
+  }
+
+  public onRelease(offset?: number) {
+    this.heldNoteCount = Math.max(this.heldNoteCount - 1, 0);
+    if (this.heldNoteCount === 0) {
+      this.envelopes.forEach(envelope => envelope.ungate(offset));
+    }
+  }
+
+  public onClearAll() {
+    this.heldNoteCount = 0;
+    this.envelopes.forEach(envelope => envelope.ungate());
+  }
+
+  public onExpression(dimension: 'pressure' | 'timbre', value: number, offset?: number) {
+    this.expressionCSNs[dimension].offset.setValueAtTime(value, ctx.currentTime + (offset || 0));
+  }
+
+  public onGenericControl(controlIndex: number, value: number) {
+    this.getControlCSN(controlIndex).offset.value = value / 127;
+  }
+}
+
+/**
+ * Global map of synth designer state key to mod matrix
+ */
+const MOD_MATRICES: Map<string, ModMatrix> = new Map();
+
+export const getModMatrix = (stateKey: string): ModMatrix => {
+  const existing = MOD_MATRICES.get(stateKey);
+  if (existing) {
+    return existing;
+  }
+
+  const modMatrix = new ModMatrix();
+  MOD_MATRICES.set(stateKey, modMatrix);
+  return modMatrix;
+};
+
+/**
+ * Sends a message to the engine to be handled by the active synth designer, returning the updated
+ * list of routes.
+ */
+const sendModMatrixMessage = (key: string, val: Uint8Array): ModRoute[] => {
+  const engine = getEngine();
+  if (!engine) {
+    console.error('Tried to update mod matrix before the engine was initialized');
+    return [];
+  }
+
+  const res = engine.handle_message(key, val);
+  return res ? JSON.parse(new TextDecoder().decode(res)) : [];
+};
+
+export const listModRoutes = () => sendModMatrixMessage('list_mod_routes', new Uint8Array());
+
+export const addModRoute = (definition: ModRouteDefinition) =>
+  sendModMatrixMessage('add_mod_route', new TextEncoder().encode(JSON.stringify(definition)));
+
+export const removeModRoute = (id: number) =>
+  sendModMatrixMessage('remove_mod_route', new Uint8Array(new Uint32Array([id]).buffer));
+
+/**
+ * Sent as a binary message since it's changed continuously while dragging.  The new routes are
+ * sent through `set_synth_designer_mod_routes` rather than being returned.
+ */
+export const setModRouteDepth = (id: number, depth: number) =>
+  sendBinaryMessage(Messages.setModRouteDepth(id, depth));
@@ -0,0 +1,32 @@
+import { getEngine } from 'src';
+
+/**
+ * Mirrors `SynthPresetListing` from the engine's `presets` module
+ */
+export interface SynthPresetListing {
+  name: string;
+  is_factory: boolean;
+}
+
+/**
+ * Sends a message to the engine to be handled by the active synth designer, returning the updated
+ * list of presets.  Presets include the settings of all synth modules along with the mod routes.
+ */
+const sendPresetMessage = (key: string, name: string): SynthPresetListing[] => {
+  const engine = getEngine();
+  if (!engine) {
+    console.error('Tried to manage synth presets before the engine was initialized');
+    return [];
+  }
+
+  const res = engine.handle_message(key, new TextEncoder().encode(name));
+  return res ? JSON.parse(new TextDecoder().decode(res)) : [];
+};
+
+export const listSynthPresets = () => sendPresetMessage('list_presets', '');
+
+export const saveSynthPreset = (name: string) => sendPresetMessage('save_preset', name);
+
+export const loadSynthPreset = (name: string) => sendPresetMessage('load_preset', name);
+
+export const deleteSynthPreset = (name: string) => sendPresetMessage('delete_preset', name);
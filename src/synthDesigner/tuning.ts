@@ -0,0 +1,45 @@
+import { getEngine } from 'src';
+
+/**
+ * Mirrors `TuningConf` from the engine's `tuning` module.  Holds the contents of the Scala scale
+ * (.scl) and keyboard mapping (.kbm) files that the tuning was loaded from.
+ */
+export interface TuningConf {
+  scl: string;
+  kbm: string | null;
+}
+
+/**
+ * Returns the tuning of the active synth designer, or `null` if it's in 12-TET
+ */
+export const getSynthDesignerTuning = (): TuningConf | null => {
+  const res = getEngine()?.handle_message('get_tuning', new Uint8Array());
+  return res ? JSON.parse(new TextDecoder().decode(res)) : null;
+};
+
+/**
+ * Sets the tuning of the active synth designer, switching it back to 12-TET if `tuning` is `null`.
+ * Returns an error message if the Scala files couldn't be parsed.
+ */
+export const setSynthDesignerTuning = (tuning: TuningConf | null): string | null => {
+  const engine = getEngine();
+  if (!engine) {
+    console.error('Tried to set synth designer tuning before the engine was initialized');
+    return null;
+  }
+
+  const res = engine.handle_message('set_tuning', new TextEncoder().encode(JSON.stringify(tuning)));
+  return res ? new TextDecoder().decode(res) : null;
+};
+
+/**
+ * Returns the description line of the tuning's scale file
+ */
+export const describeTuning = (tuning: TuningConf | null): string => {
+  if (!tuning) {
+    return '12-TET';
+  }
+
+  const description = tuning.scl.split('\n').find(line => !line.startsWith('!'));
+  return description?.trim() || 'Custom tuning';
+};
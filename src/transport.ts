@@ -0,0 +1,45 @@
+/**
+ * Global state of the transport that is currently playing back a composition.  Used to sync things
+ * like LFOs to the beat.
+ */
+export interface TransportState {
+  isPlaying: boolean;
+  bpm: number;
+  /**
+   * The `AudioContext` time at which beat 0 of the composition was (or would have been) played
+   */
+  beatZeroTime: number;
+  /**
+   * The beats between which playback loops, if a loop region is set.  If only the end is set, the
+   * loop starts at beat 0.
+   */
+  loopStartBeat: number | null;
+  loopEndBeat: number | null;
+}
+
+let transportState: TransportState = {
+  isPlaying: false,
+  bpm: 120,
+  beatZeroTime: 0,
+  loopStartBeat: null,
+  loopEndBeat: null,
+};
+const listeners: Set<(state: TransportState) => void> = new Set();
+
+export const getTransportState = (): TransportState => transportState;
+
+export const setTransportState = (newState: TransportState) => {
+  transportState = newState;
+  listeners.forEach(cb => cb(newState));
+};
+
+/**
+ * Registers a callback to be called every time the transport starts, stops, or changes tempo.
+ * Returns a function that unregisters it.
+ */
+export const subscribeToTransport = (cb: (state: TransportState) => void) => {
+  listeners.add(cb);
+  return () => {
+    listeners.delete(cb);
+  };
+};
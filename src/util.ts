@@ -13,7 +13,19 @@ export const roundTo = (num: number, decimals: number): number => {
   return Math.round(num * multiplicand) / multiplicand;
 };
 
-export const midiToFrequency = (midiNote: number) => Math.pow(2, (midiNote - 69) / 12) * 440;
+/**
+ * Returns the frequency of a MIDI note in 12-TET, or in the provided tuning table if one is given.
+ * Tuning tables are built by the engine from Scala files and contain 0 for notes that are unmapped.
+ */
+export const midiToFrequency = (midiNote: number, tuningTable?: Float32Array) =>
+  tuningTable ? tuningTable[midiNote] ?? 0 : Math.pow(2, (midiNote - 69) / 12) * 440;
+
+/**
+ * Maps a MIDI velocity to a multiplier for the gain envelope.  Velocities above the MIDI maximum of
+ * 127 as well as missing velocities are treated as full volume.
+ */
+export const velocityToGainLevel = (velocity?: number): number =>
+  velocity === undefined || velocity === null ? 1 : clamp(0, 1, velocity / 127);
 
 /**
  * Tries to parse the provided string out of JSON.